@@ -0,0 +1,87 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use wasmer::*;
+
+/// A module with the shape of a typical serverless handler: a few
+/// imports, a memory, a table, globals and a dozen exports.
+static MEDIUM_WAT: &str = r#"(module
+    (import "env" "log" (func $log (param i32 i32)))
+    (import "env" "now" (func $now (result i64)))
+    (import "env" "counter" (global $counter (mut i32)))
+    (memory (export "memory") 1)
+    (table (export "table") 4 funcref)
+    (global $heap (export "heap") (mut i32) (i32.const 1024))
+    (global (export "version") i32 (i32.const 1))
+    (data (i32.const 0) "hello from the handler")
+    (elem (i32.const 0) $add $sub $mul $handle)
+    (func $add (export "add") (param i32 i32) (result i32)
+        (i32.add (local.get 0) (local.get 1)))
+    (func $sub (export "sub") (param i32 i32) (result i32)
+        (i32.sub (local.get 0) (local.get 1)))
+    (func $mul (export "mul") (param i32 i32) (result i32)
+        (i32.mul (local.get 0) (local.get 1)))
+    (func (export "alloc") (param i32) (result i32)
+        (global.get $heap)
+        (global.set $heap (i32.add (global.get $heap) (local.get 0))))
+    (func (export "reset")
+        (global.set $heap (i32.const 1024)))
+    (func (export "elapsed") (param i64) (result i64)
+        (i64.sub (call $now) (local.get 0)))
+    (func (export "count") (result i32)
+        (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+        (global.get $counter))
+    (func $handle (export "handle") (param i32 i32) (result i32)
+        (call $log (i32.const 0) (i32.const 22))
+        (i32.add (local.get 0) (local.get 1)))
+)"#;
+
+pub fn run_instantiation(store: &Store, compiler_name: &str, c: &mut Criterion) {
+    let module = Module::new(store, MEDIUM_WAT).unwrap();
+    let import_object = imports! {
+        "env" => {
+            "log" => Function::new_native(store, |_ptr: i32, _len: i32| {}),
+            "now" => Function::new_native(store, || 0i64),
+            "counter" => Global::new_mut(store, Value::I32(0)),
+        },
+    };
+
+    c.bench_function(
+        &format!("instantiate medium module {}", compiler_name),
+        |b| {
+            // The instances are returned, to be dropped outside of the
+            // measurement.
+            b.iter_with_large_drop(|| Instance::new(&module, &import_object).unwrap())
+        },
+    );
+}
+
+fn run_instantiation_benchmarks(c: &mut Criterion) {
+    #[allow(unused_mut)]
+    let mut stores: Vec<(&str, Store)> = Vec::new();
+
+    #[cfg(feature = "llvm")]
+    stores.push((
+        "llvm",
+        Store::new(&Universal::new(wasmer_compiler_llvm::LLVM::new()).engine()),
+    ));
+
+    #[cfg(feature = "cranelift")]
+    stores.push((
+        "cranelift",
+        Store::new(&Universal::new(wasmer_compiler_cranelift::Cranelift::new()).engine()),
+    ));
+
+    #[cfg(feature = "singlepass")]
+    stores.push((
+        "singlepass",
+        Store::new(&Universal::new(wasmer_compiler_singlepass::Singlepass::new()).engine()),
+    ));
+
+    for (compiler_name, store) in stores {
+        run_instantiation(&store, compiler_name, c);
+    }
+}
+
+criterion_group!(benches, run_instantiation_benchmarks);
+
+criterion_main!(benches);
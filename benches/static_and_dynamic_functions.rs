@@ -146,7 +146,39 @@ pub fn run_basic_dynamic_function(store: &Store, compiler_name: &str, c: &mut Cr
     );
 }
 
-fn run_static_benchmarks(_c: &mut Criterion) {
+pub fn run_dynamic_host_function(store: &Store, compiler_name: &str, c: &mut Criterion) {
+    let module = Module::new(&store, BASIC_WAT).unwrap();
+    let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    let multiply_dynamic = Function::new(&store, &signature, |args| {
+        Ok(vec![Val::I32(args[0].unwrap_i32() * args[1].unwrap_i32())])
+    });
+    let multiply_raw = Function::new_raw(&store, &signature, |call| {
+        let result = call.arg(0).unwrap_i32() * call.arg(1).unwrap_i32();
+        call.ret(&[Val::I32(result)])
+    });
+
+    for (kind, multiply) in &[("dynamic", multiply_dynamic), ("raw", multiply_raw)] {
+        let import_object = imports! {
+            "env" => {
+                "multiply" => multiply.clone(),
+            },
+        };
+        let instance = Instance::new(&module, &import_object).unwrap();
+        let f: NativeFunc<(i32, i32), i32> = instance
+            .exports
+            .get_native_function("double_then_add")
+            .unwrap();
+        c.bench_function(&format!("{} host func {}", kind, compiler_name), |b| {
+            b.iter(|| {
+                let result = black_box(f.call(4, 6).unwrap());
+                assert_eq!(result, 20);
+            })
+        });
+    }
+}
+
+#[allow(unused_variables)]
+fn run_static_benchmarks(c: &mut Criterion) {
     #[cfg(feature = "llvm")]
     {
         let store = Store::new(&Universal::new(wasmer_compiler_llvm::LLVM::new()).engine());
@@ -168,11 +200,13 @@ fn run_static_benchmarks(_c: &mut Criterion) {
     }
 }
 
-fn run_dynamic_benchmarks(_c: &mut Criterion) {
+#[allow(unused_variables)]
+fn run_dynamic_benchmarks(c: &mut Criterion) {
     #[cfg(feature = "llvm")]
     {
         let store = Store::new(&Universal::new(wasmer_compiler_llvm::LLVM::new()).engine());
         run_basic_dynamic_function(&store, "llvm", c);
+        run_dynamic_host_function(&store, "llvm", c);
     }
 
     #[cfg(feature = "cranelift")]
@@ -180,6 +214,7 @@ fn run_dynamic_benchmarks(_c: &mut Criterion) {
         let store =
             Store::new(&Universal::new(wasmer_compiler_cranelift::Cranelift::new()).engine());
         run_basic_dynamic_function(&store, "cranelift", c);
+        run_dynamic_host_function(&store, "cranelift", c);
     }
 
     #[cfg(feature = "singlepass")]
@@ -187,6 +222,7 @@ fn run_dynamic_benchmarks(_c: &mut Criterion) {
         let store =
             Store::new(&Universal::new(wasmer_compiler_singlepass::Singlepass::new()).engine());
         run_basic_dynamic_function(&store, "singlepass", c);
+        run_dynamic_host_function(&store, "singlepass", c);
     }
 }
 
@@ -92,7 +92,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // appropriate string?
     //
     // To do that, we'll dereference our pointer and change the content
-    // of each `Cell`
+    // of each `WasmCell`
     let new_str = b"Hello, Wasmer!";
     let values = ptr.deref(memory, 0, new_str.len() as u32).unwrap();
     for i in 0..new_str.len() {
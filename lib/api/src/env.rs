@@ -1,4 +1,8 @@
-use crate::{ExportError, Instance};
+use crate::{ExportError, Instance, Memory, RuntimeError};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
 use thiserror::Error;
 
 /// An error while initializing the user supplied host env with the `WasmerEnv` trait.
@@ -57,9 +61,25 @@ impl From<ExportError> for HostEnvInitError {
 /// - `name = "string"`: specify the name of this item in the Wasm module. If this is not specified, it will default to the name of the field.
 /// - `optional = true`: specify whether this export is optional. Defaults to
 /// `false`. Being optional means that if the export can't be found, the
-/// [`LazyInit`] will be left uninitialized.
+/// [`LazyInit`] will be left uninitialized. `optional` alone is the same as
+/// `optional = true`.
 /// - `alias = "string"`: specify additional names to look for in the Wasm module.
 /// `alias` may be specified multiple times to search for multiple aliases.
+///
+/// The struct may be generic over lifetimes, types and constants. The derived
+/// impl then requires the struct to be `Clone`, `Send` and `Sync`, like the
+/// supertraits of `WasmerEnv`:
+///
+/// ```
+/// use wasmer::{WasmerEnv, LazyInit, Memory};
+///
+/// #[derive(WasmerEnv, Clone)]
+/// pub struct MyGenericEnv<T: Clone> {
+///     data: T,
+///     #[wasmer(export(optional))]
+///     memory: LazyInit<Memory>,
+/// }
+/// ```
 /// -------
 ///
 /// This trait may also be implemented manually:
@@ -222,3 +242,194 @@ impl<T> Default for LazyInit<T> {
 unsafe impl<T: Send> Send for LazyInit<T> {}
 // I thought we could opt out of sync..., look into this
 // unsafe impl<T> !Sync for InitWithInstance<T> {}
+
+/// An environment shared by host functions, which gives them mutable
+/// access to its data through [`FunctionEnv::borrow_mut`], without the
+/// data having to be `Sync` or to use interior mutability.
+///
+/// The clones of a `FunctionEnv` share the same data. Each one is
+/// initialized with the `memory` export of the instance its function is
+/// imported by, if there is one.
+///
+/// # Examples
+///
+/// ```
+/// # use wasmer::{Array, Function, FunctionEnv, RuntimeError, Store, WasmPtr};
+/// # let store = Store::default();
+/// struct Counter {
+///     count: i32,
+///     last_messages: Vec<String>,
+/// }
+///
+/// let env = FunctionEnv::new(Counter { count: 0, last_messages: vec![] });
+/// let log = Function::new_native_with_env(
+///     &store,
+///     env.clone(),
+///     |env: &FunctionEnv<Counter>, ptr: WasmPtr<u8, Array>, len: u32| {
+///         let mut env = env.borrow_mut()?;
+///         let message = env
+///             .memory()
+///             .and_then(|memory| ptr.get_utf8_string(memory, len))
+///             .unwrap_or_default();
+///         env.count += 1;
+///         env.last_messages.push(message);
+///         Ok::<_, RuntimeError>(())
+///     },
+/// );
+/// # let _ = log;
+/// ```
+pub struct FunctionEnv<T> {
+    shared: Arc<SharedEnv<T>>,
+    memory: LazyInit<Memory>,
+}
+
+struct SharedEnv<T> {
+    data: Mutex<T>,
+    /// The thread currently borrowing `data`, to detect reentrant borrows
+    /// which would deadlock.
+    borrower: Mutex<Option<ThreadId>>,
+}
+
+impl<T: Send> FunctionEnv<T> {
+    /// Creates a new environment holding `data`.
+    pub fn new(data: T) -> Self {
+        Self {
+            shared: Arc::new(SharedEnv {
+                data: Mutex::new(data),
+                borrower: Mutex::new(None),
+            }),
+            memory: LazyInit::new(),
+        }
+    }
+
+    /// Mutably borrows the data of the environment, waiting for other
+    /// threads to release it.
+    ///
+    /// Fails when the data is already borrowed by the current thread, for
+    /// example by a host function which called back into Wasm, which
+    /// called the host again.
+    pub fn borrow_mut(&self) -> Result<FunctionEnvMut<'_, T>, RuntimeError> {
+        let current = thread::current().id();
+        if *self.shared.borrower.lock().unwrap() == Some(current) {
+            return Err(RuntimeError::new(
+                "the environment of the host function is already borrowed",
+            ));
+        }
+        // A panic of a host function while borrowing doesn't leave the data
+        // unusable, like with `RefCell`
+        let data = match self.shared.data.lock() {
+            Ok(data) => data,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        *self.shared.borrower.lock().unwrap() = Some(current);
+        Ok(FunctionEnvMut {
+            data,
+            borrower: &self.shared.borrower,
+            memory: self.memory.get_ref(),
+        })
+    }
+
+    /// Returns the data of the environment, if this is the last clone of
+    /// it, or the environment back otherwise.
+    pub fn into_inner(self) -> Result<T, Self> {
+        let memory = self.memory;
+        match Arc::try_unwrap(self.shared) {
+            Ok(shared) => Ok(match shared.data.into_inner() {
+                Ok(data) => data,
+                Err(poisoned) => poisoned.into_inner(),
+            }),
+            Err(shared) => Err(Self { shared, memory }),
+        }
+    }
+}
+
+impl<T> Clone for FunctionEnv<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            memory: self.memory.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for FunctionEnv<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("FunctionEnv");
+        match self.shared.data.try_lock() {
+            Ok(data) => debug.field("data", &*data),
+            Err(_) => debug.field("data", &format_args!("<borrowed>")),
+        };
+        debug.field("memory", &self.memory).finish()
+    }
+}
+
+impl<T: Send> WasmerEnv for FunctionEnv<T> {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        if let Ok(memory) = instance
+            .exports
+            .get_with_generics_weak::<Memory, _, _>("memory")
+        {
+            self.memory.initialize(memory);
+        }
+        Ok(())
+    }
+}
+
+/// A mutable borrow of the data of a [`FunctionEnv`], which also gives
+/// access to the memory of the instance calling the host function.
+///
+/// The data is released when the `FunctionEnvMut` is dropped.
+pub struct FunctionEnvMut<'a, T> {
+    data: MutexGuard<'a, T>,
+    borrower: &'a Mutex<Option<ThreadId>>,
+    memory: Option<&'a Memory>,
+}
+
+impl<'a, T> FunctionEnvMut<'a, T> {
+    /// Gets the data of the environment.
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    /// Gets the data of the environment mutably.
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+
+    /// Gets the `memory` exported by the instance calling the host function,
+    /// or `None` if it doesn't export one.
+    pub fn memory(&self) -> Option<&'a Memory> {
+        self.memory
+    }
+}
+
+impl<T> Deref for FunctionEnvMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.data
+    }
+}
+
+impl<T> DerefMut for FunctionEnvMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.data
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for FunctionEnvMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FunctionEnvMut")
+            .field("data", &*self.data)
+            .field("memory", &self.memory)
+            .finish()
+    }
+}
+
+impl<T> Drop for FunctionEnvMut<'_, T> {
+    fn drop(&mut self) {
+        // Cleared before `data` is unlocked, so another thread can't set it
+        // first
+        *self.borrower.lock().unwrap() = None;
+    }
+}
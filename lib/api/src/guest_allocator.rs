@@ -0,0 +1,197 @@
+use crate::{ExportError, Exports, Memory, MemoryAccessError, NativeFunc, RuntimeError};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Passes strings and byte buffers to and from a module with its own
+/// allocator, following the common `(ptr, len)` convention.
+///
+/// The module exports a `malloc(size: i32) -> i32` function allocating
+/// `size` bytes in its memory, and a `free(ptr: i32)` function releasing
+/// them, like C and Rust modules can. To pass a buffer to a function of
+/// the module, the host allocates it with `malloc`, copies the bytes into
+/// it, calls the function with its pointer and its length, and frees it
+/// with `free`, which is what [`GuestAllocator::with_bytes`] and
+/// [`GuestAllocator::with_str`] do.
+///
+/// # Examples
+///
+/// ```
+/// # use wasmer::{imports, GuestAllocator, Instance, Module, Store};
+/// # fn main() -> anyhow::Result<()> {
+/// # let store = Store::default();
+/// let module = Module::new(&store, r#"(module
+///     (memory (export "memory") 1)
+///     (global $heap (mut i32) (i32.const 16))
+///     (func (export "malloc") (param $size i32) (result i32)
+///         (global.get $heap)
+///         (global.set $heap (i32.add (global.get $heap) (local.get $size))))
+///     (func (export "free") (param i32))
+///     (func (export "first_byte") (param $ptr i32) (param $len i32) (result i32)
+///         (i32.load8_u (local.get $ptr))))"#)?;
+/// let instance = Instance::new(&module, &imports! {})?;
+/// let allocator = GuestAllocator::new(&instance.exports)?;
+/// let first_byte = instance
+///     .exports
+///     .get_native_function::<(i32, i32), i32>("first_byte")?;
+///
+/// let byte = allocator.with_str("wasmer", |ptr, len| first_byte.call(ptr, len))?;
+/// assert_eq!(byte, 'w' as i32);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct GuestAllocator {
+    memory: Memory,
+    malloc: NativeFunc<i32, i32>,
+    free: NativeFunc<i32, ()>,
+}
+
+impl GuestAllocator {
+    /// Gets the `memory`, `malloc` and `free` exports of an instance.
+    pub fn new(exports: &Exports) -> Result<Self, ExportError> {
+        Self::with_names(exports, "memory", "malloc", "free")
+    }
+
+    /// Gets the memory and the allocation functions of an instance
+    /// exported with the names given, like `"allocate"` and `"deallocate"`.
+    pub fn with_names(
+        exports: &Exports,
+        memory: &str,
+        malloc: &str,
+        free: &str,
+    ) -> Result<Self, ExportError> {
+        Ok(Self {
+            memory: exports.get_memory(memory)?.clone(),
+            malloc: exports.get_native_function(malloc)?,
+            free: exports.get_native_function(free)?,
+        })
+    }
+
+    /// Returns the memory the buffers are allocated in.
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Allocates a buffer in the memory of the module, and copies `bytes`
+    /// into it.
+    ///
+    /// The buffer has to be freed with [`GuestAllocator::free`], unless
+    /// the module takes ownership of it.
+    pub fn alloc_bytes(&self, bytes: &[u8]) -> Result<GuestBuffer, RuntimeError> {
+        let len = i32::try_from(bytes.len())
+            .map_err(|_| RuntimeError::new("the buffer is too large for the module"))?;
+        // Empty buffers are allocated too, so that the module always gets a
+        // valid pointer
+        let ptr = self.malloc.call(len.max(1))?;
+        if ptr == 0 {
+            return Err(RuntimeError::new(format!(
+                "the module failed to allocate {} bytes",
+                len
+            )));
+        }
+        let buffer = GuestBuffer { ptr, len };
+        if let Err(error) = self.memory.write(ptr as u32 as u64, bytes) {
+            self.free(buffer)?;
+            return Err(error.into());
+        }
+        Ok(buffer)
+    }
+
+    /// Allocates a buffer in the memory of the module, and copies the
+    /// UTF-8 bytes of `s` into it.
+    ///
+    /// The buffer has to be freed with [`GuestAllocator::free`], unless
+    /// the module takes ownership of it.
+    pub fn alloc_str(&self, s: &str) -> Result<GuestBuffer, RuntimeError> {
+        self.alloc_bytes(s.as_bytes())
+    }
+
+    /// Frees a buffer with the `free` function of the module.
+    pub fn free(&self, buffer: GuestBuffer) -> Result<(), RuntimeError> {
+        self.free.call(buffer.ptr)
+    }
+
+    /// Copies `bytes` into a new buffer of the module, calls `f` with its
+    /// pointer and its length, and frees it once `f` returns, even when it
+    /// fails.
+    pub fn with_bytes<T>(
+        &self,
+        bytes: &[u8],
+        f: impl FnOnce(i32, i32) -> Result<T, RuntimeError>,
+    ) -> Result<T, RuntimeError> {
+        let buffer = self.alloc_bytes(bytes)?;
+        let result = f(buffer.ptr, buffer.len);
+        let freed = self.free(buffer);
+        let value = result?;
+        freed?;
+        Ok(value)
+    }
+
+    /// Copies `s` into a new buffer of the module, calls `f` with its
+    /// pointer and its length, and frees it once `f` returns, even when it
+    /// fails.
+    pub fn with_str<T>(
+        &self,
+        s: &str,
+        f: impl FnOnce(i32, i32) -> Result<T, RuntimeError>,
+    ) -> Result<T, RuntimeError> {
+        self.with_bytes(s.as_bytes(), f)
+    }
+
+    /// Copies the `len` bytes at `ptr` out of a buffer allocated by the
+    /// module, typically returned by one of its functions, and frees it.
+    pub fn take_bytes(&self, ptr: i32, len: i32) -> Result<Vec<u8>, RuntimeError> {
+        let offset = ptr as u32 as u64;
+        // The length comes from the module, so it's checked against the
+        // memory before allocating
+        if offset + len as u32 as u64 > self.memory.data_size() {
+            self.free(GuestBuffer { ptr, len })?;
+            return Err(MemoryAccessError::HeapOutOfBounds.into());
+        }
+        let mut bytes = vec![0; len as u32 as usize];
+        let read = self.memory.read(offset, &mut bytes);
+        self.free(GuestBuffer { ptr, len })?;
+        read?;
+        Ok(bytes)
+    }
+
+    /// Copies the UTF-8 string of `len` bytes at `ptr` out of a buffer
+    /// allocated by the module, typically returned by one of its
+    /// functions, and frees it.
+    pub fn take_string(&self, ptr: i32, len: i32) -> Result<String, RuntimeError> {
+        String::from_utf8(self.take_bytes(ptr, len)?)
+            .map_err(|_| RuntimeError::new("the module returned an invalid UTF-8 string"))
+    }
+}
+
+impl fmt::Debug for GuestAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GuestAllocator")
+            .field("memory", &self.memory)
+            .finish()
+    }
+}
+
+/// A buffer allocated in the memory of a module by a [`GuestAllocator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuestBuffer {
+    ptr: i32,
+    len: i32,
+}
+
+impl GuestBuffer {
+    /// The offset of the buffer in the memory of the module.
+    pub fn ptr(&self) -> i32 {
+        self.ptr
+    }
+
+    /// The length of the buffer, in bytes.
+    pub fn len(&self) -> i32 {
+        self.len
+    }
+
+    /// Whether the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
@@ -0,0 +1,64 @@
+use crate::js::externals::Memory;
+use std::fmt;
+use std::marker::PhantomData;
+use wasmer_types::ValueType;
+
+/// A mutable location in the memory of a Wasm module, as returned by
+/// [`WasmPtr::deref`].
+///
+/// The memory of the module is owned by the host with the `js` backend, so
+/// the value is copied out of it by [`WasmCell::get`], and into it by
+/// [`WasmCell::set`].
+///
+/// [`WasmPtr::deref`]: crate::WasmPtr::deref
+pub struct WasmCell<'a, T> {
+    memory: &'a Memory,
+    offset: u64,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, T> WasmCell<'a, T> {
+    /// Creates a new `WasmCell` for the value at `offset` in `memory`,
+    /// which is in bounds.
+    #[inline]
+    pub(crate) fn new(memory: &'a Memory, offset: u64) -> Self {
+        Self {
+            memory,
+            offset,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: ValueType> WasmCell<'a, T> {
+    /// Returns a copy of the value.
+    #[inline]
+    pub fn get(&self) -> T {
+        // The memory only grows, so the value stays in bounds
+        self.memory
+            .read_value(self.offset)
+            .expect("the `WasmCell` is out of bounds")
+    }
+
+    /// Sets the value.
+    #[inline]
+    pub fn set(&self, val: T) {
+        self.memory
+            .write_value(self.offset, val)
+            .expect("the `WasmCell` is out of bounds")
+    }
+}
+
+impl<T> Clone for WasmCell<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WasmCell<'_, T> {}
+
+impl<T: ValueType + fmt::Debug> fmt::Debug for WasmCell<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("WasmCell").field(&self.get()).finish()
+    }
+}
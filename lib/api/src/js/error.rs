@@ -0,0 +1,133 @@
+//! The errors of the `js` backend, mirroring the ones of `wasmer-compiler`
+//! and `wasmer-engine`, which aren't available there.
+
+use crate::js::trap::RuntimeError;
+use std::io;
+use thiserror::Error;
+use wasmer_types::ExternType;
+
+/// The WebAssembly.CompileError object indicates an error during
+/// WebAssembly decoding or validation.
+///
+/// This is based on the [Wasm Compile Error][compile-error] API.
+///
+/// [compile-error]: https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/WebAssembly/CompileError
+#[derive(Error, Debug)]
+pub enum CompileError {
+    /// A Wasm translation error occured.
+    #[error("WebAssembly translation error: {0}")]
+    Wasm(WasmError),
+
+    /// A compilation error occured.
+    #[error("Compilation error: {0}")]
+    Codegen(String),
+
+    /// The module did not pass validation.
+    #[error("Validation error: {0}")]
+    Validate(String),
+
+    /// The host doesn't support a Wasm feature
+    #[error("Feature {0} is not yet supported")]
+    UnsupportedFeature(String),
+}
+
+impl From<WasmError> for CompileError {
+    fn from(original: WasmError) -> Self {
+        Self::Wasm(original)
+    }
+}
+
+/// A WebAssembly translation error.
+///
+/// When a WebAssembly function can't be translated, one of these error codes will be returned
+/// to describe the failure.
+#[derive(Error, Debug)]
+pub enum WasmError {
+    /// The input WebAssembly code is invalid.
+    ///
+    /// This error code is used by a WebAssembly translator when it encounters invalid WebAssembly
+    /// code. This should never happen for validated WebAssembly code.
+    #[error("Invalid input WebAssembly code at offset {offset}: {message}")]
+    InvalidWebAssembly {
+        /// A string describing the validation error.
+        message: String,
+        /// The bytecode offset where the error occurred.
+        offset: usize,
+    },
+
+    /// A feature used by the WebAssembly code is not supported by the embedding environment.
+    ///
+    /// Embedding environments may have their own limitations and feature restrictions.
+    #[error("Unsupported feature: {0}")]
+    Unsupported(String),
+
+    /// A generic error.
+    #[error("{0}")]
+    Generic(String),
+}
+
+/// The Serialize error can occur when serializing a
+/// compiled Module into a binary.
+#[derive(Error, Debug)]
+pub enum SerializeError {
+    /// An IO error
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A generic serialization error
+    #[error("{0}")]
+    Generic(String),
+}
+
+/// The Deserialize error can occur when loading a
+/// compiled Module from a binary.
+#[derive(Error, Debug)]
+pub enum DeserializeError {
+    /// An IO error
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A generic deserialization error
+    #[error("{0}")]
+    Generic(String),
+    /// The binary was valid, but we got an error when
+    /// trying to allocate the required resources.
+    #[error(transparent)]
+    Compiler(CompileError),
+}
+
+/// An ImportError.
+///
+/// Note: this error is not standard to WebAssembly, but it's
+/// useful to determine the import issue on the API side.
+#[derive(Error, Debug)]
+pub enum ImportError {
+    /// Incompatible Import Type.
+    /// This error occurs when the import types mismatch.
+    #[error("incompatible import type. Expected {0:?} but received {1:?}")]
+    IncompatibleType(ExternType, ExternType),
+
+    /// Unknown Import.
+    /// This error occurs when an import was expected but not provided.
+    #[error("unknown import. Expected {0:?}")]
+    UnknownImport(ExternType),
+}
+
+/// The WebAssembly.LinkError object indicates an error during
+/// module instantiation (besides traps from the start function).
+///
+/// This is based on the [link error][link-error] API.
+///
+/// [link-error]: https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/WebAssembly/LinkError
+#[derive(Error, Debug)]
+pub enum LinkError {
+    /// An error occurred when checking the import types.
+    #[error("Error while importing {0:?}.{1:?}: {2}")]
+    Import(String, String, ImportError),
+
+    /// A trap ocurred during linking.
+    #[error("RuntimeError occurred during linking: {0}")]
+    Trap(#[source] RuntimeError),
+
+    /// The host rejected the imports, with its message.
+    #[error("Link error: {0}")]
+    Host(String),
+}
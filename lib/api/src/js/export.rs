@@ -0,0 +1,202 @@
+use crate::js::instance::Instance;
+use crate::js::wasm_bindgen_polyfill::Global;
+use crate::HostEnvInitError;
+use js_sys::Function;
+use js_sys::WebAssembly::{Memory, Table};
+use std::fmt;
+use std::sync::Arc;
+use wasm_bindgen::{JsCast, JsValue};
+use wasmer_types::{ExternType, FunctionType, GlobalType, MemoryType, TableType};
+
+// The JavaScript objects can't be shared between threads, but `wasm32` is
+// single threaded, and the types of the API are `Send` and `Sync` with the
+// `sys` backend, like the environments of the host functions holding them
+// must be.
+
+/// A memory of the host, with its type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VMMemory {
+    pub(crate) memory: Memory,
+    pub(crate) ty: MemoryType,
+}
+
+unsafe impl Send for VMMemory {}
+unsafe impl Sync for VMMemory {}
+
+impl VMMemory {
+    pub(crate) fn new(memory: Memory, ty: MemoryType) -> Self {
+        Self { memory, ty }
+    }
+}
+
+/// A global of the host, with its type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VMGlobal {
+    pub(crate) global: Global,
+    pub(crate) ty: GlobalType,
+}
+
+unsafe impl Send for VMGlobal {}
+unsafe impl Sync for VMGlobal {}
+
+impl VMGlobal {
+    pub(crate) fn new(global: Global, ty: GlobalType) -> Self {
+        Self { global, ty }
+    }
+}
+
+/// A table of the host, with its type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VMTable {
+    pub(crate) table: Table,
+    pub(crate) ty: TableType,
+}
+
+unsafe impl Send for VMTable {}
+unsafe impl Sync for VMTable {}
+
+impl VMTable {
+    pub(crate) fn new(table: Table, ty: TableType) -> Self {
+        Self { table, ty }
+    }
+}
+
+/// Initializes the environment of a host function with the instance
+/// importing it, see [`WasmerEnv::init_with_instance`].
+///
+/// [`WasmerEnv::init_with_instance`]: crate::WasmerEnv::init_with_instance
+pub(crate) type HostEnvInitializer = dyn Fn(&Instance) -> Result<(), HostEnvInitError>;
+
+/// A function of the host, with its type, and the initializer of its
+/// environment if it's a host function with one.
+#[derive(Clone)]
+pub struct VMFunction {
+    pub(crate) function: Function,
+    pub(crate) ty: FunctionType,
+    pub(crate) environment: Option<Arc<HostEnvInitializer>>,
+}
+
+unsafe impl Send for VMFunction {}
+unsafe impl Sync for VMFunction {}
+
+impl VMFunction {
+    pub(crate) fn new(
+        function: Function,
+        ty: FunctionType,
+        environment: Option<Arc<HostEnvInitializer>>,
+    ) -> Self {
+        Self {
+            function,
+            ty,
+            environment,
+        }
+    }
+
+    /// Initializes the environment of the function, if it has one.
+    pub(crate) fn init_envs(&self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        match &self.environment {
+            Some(init) => init(instance),
+            None => Ok(()),
+        }
+    }
+}
+
+impl PartialEq for VMFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.function == other.function
+    }
+}
+
+impl fmt::Debug for VMFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMFunction")
+            .field("function", &self.function)
+            .field("ty", &self.ty)
+            .finish()
+    }
+}
+
+/// The value of an export passed from one instance to another.
+#[derive(Debug, Clone)]
+pub enum Export {
+    /// A function export value.
+    Function(VMFunction),
+
+    /// A table export value.
+    Table(VMTable),
+
+    /// A memory export value.
+    Memory(VMMemory),
+
+    /// A global export value.
+    Global(VMGlobal),
+}
+
+impl Export {
+    /// Returns the JavaScript object of the export.
+    pub fn as_jsvalue(&self) -> &JsValue {
+        match self {
+            Self::Memory(js_wasm_memory) => js_wasm_memory.memory.as_ref(),
+            Self::Function(js_func) => js_func.function.as_ref(),
+            Self::Table(js_wasm_table) => js_wasm_table.table.as_ref(),
+            Self::Global(js_wasm_global) => js_wasm_global.global.as_ref(),
+        }
+    }
+
+    /// Wraps the JavaScript object of an export of the type `extern_type`,
+    /// or returns `None` if it isn't an object of that kind.
+    pub(crate) fn from_js_value(val: JsValue, extern_type: ExternType) -> Option<Self> {
+        match extern_type {
+            ExternType::Memory(memory_type) => val
+                .dyn_into::<Memory>()
+                .ok()
+                .map(|memory| Self::Memory(VMMemory::new(memory, memory_type))),
+            ExternType::Global(global_type) => val
+                .dyn_into::<Global>()
+                .ok()
+                .map(|global| Self::Global(VMGlobal::new(global, global_type))),
+            ExternType::Function(function_type) => val
+                .dyn_into::<Function>()
+                .ok()
+                .map(|function| Self::Function(VMFunction::new(function, function_type, None))),
+            ExternType::Table(table_type) => val
+                .dyn_into::<Table>()
+                .ok()
+                .map(|table| Self::Table(VMTable::new(table, table_type))),
+        }
+    }
+
+    /// Returns the type of the export.
+    pub(crate) fn ty(&self) -> ExternType {
+        match self {
+            Self::Memory(memory) => ExternType::Memory(memory.ty),
+            Self::Function(function) => ExternType::Function(function.ty.clone()),
+            Self::Table(table) => ExternType::Table(table.ty),
+            Self::Global(global) => ExternType::Global(global.ty),
+        }
+    }
+}
+
+impl From<VMFunction> for Export {
+    fn from(func: VMFunction) -> Self {
+        Self::Function(func)
+    }
+}
+
+impl From<VMTable> for Export {
+    fn from(table: VMTable) -> Self {
+        Self::Table(table)
+    }
+}
+
+impl From<VMMemory> for Export {
+    fn from(memory: VMMemory) -> Self {
+        Self::Memory(memory)
+    }
+}
+
+impl From<VMGlobal> for Export {
+    fn from(global: VMGlobal) -> Self {
+        Self::Global(global)
+    }
+}
@@ -1,14 +1,14 @@
-use crate::externals::{Extern, Function, Global, Memory, Table};
-use crate::import_object::LikeNamespace;
-use crate::native::NativeFunc;
-use crate::WasmTypeList;
+use crate::js::export::Export;
+use crate::js::externals::{Extern, Function, Global, Memory, Table};
+use crate::js::import_object::LikeNamespace;
+use crate::js::native::NativeFunc;
+use crate::{FunctionType, WasmTypeList};
 use indexmap::IndexMap;
-use loupe::MemoryUsage;
+use std::any::type_name;
 use std::fmt;
 use std::iter::{ExactSizeIterator, FromIterator};
 use std::sync::Arc;
 use thiserror::Error;
-use wasmer_engine::Export;
 
 /// The `ExportError` can happen when trying to get a specific
 /// export [`Extern`] from the [`Instance`] exports.
@@ -47,6 +47,27 @@ use wasmer_engine::Export;
 /// // This results with an error: `ExportError::Missing`.
 /// let export = instance.exports.get_function("unknown").unwrap();
 /// ```
+///
+/// ## Incompatible function signature
+///
+/// ```should_panic
+/// # use wasmer::{imports, wat2wasm, Function, Instance, Module, Store, Type, Value, ExportError};
+/// # let store = Store::default();
+/// # let wasm_bytes = wat2wasm(r#"
+/// # (module
+/// #   (func (export "sum") (param i32 i32) (result i32)
+/// #     local.get 0
+/// #     local.get 1
+/// #     i32.add))
+/// # "#.as_bytes()).unwrap();
+/// # let module = Module::new(&store, wasm_bytes).unwrap();
+/// # let import_object = imports! {};
+/// # let instance = Instance::new(&module, &import_object).unwrap();
+/// #
+/// // This results with an error: `ExportError::IncompatibleSignature`,
+/// // showing both `[I32, I32] -> [I32]` and `(i32,) -> i64`.
+/// let sum = instance.exports.get_native_function::<(i32,), i64>("sum").unwrap();
+/// ```
 #[derive(Error, Debug)]
 pub enum ExportError {
     /// An error than occurs when the exported type and the expected type
@@ -56,13 +77,46 @@ pub enum ExportError {
     /// This error arises when an export is missing
     #[error("Missing export {0}")]
     Missing(String),
+    /// An error that occurs when the signature of an exported function
+    /// doesn't match the one of the requested `NativeFunc`.
+    #[error(
+        "Incompatible signature for export `{name}`: it is `{wasm}` in Wasm, but `{rust}` (`{requested}`) was requested"
+    )]
+    IncompatibleSignature {
+        /// The name of the export.
+        name: String,
+        /// The signature of the exported function.
+        wasm: FunctionType,
+        /// The Wasm signature of the requested `NativeFunc`.
+        requested: FunctionType,
+        /// The Rust signature of the requested `NativeFunc`, like
+        /// `(i32,) -> i64`.
+        rust: String,
+    },
+}
+
+impl ExportError {
+    /// Creates an `IncompatibleSignature` error for the export `name`
+    /// requested as a `NativeFunc<Args, Rets>`.
+    pub(crate) fn incompatible_signature<Args, Rets>(name: &str, wasm: &FunctionType) -> Self
+    where
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        Self::IncompatibleSignature {
+            name: name.to_string(),
+            wasm: wasm.clone(),
+            requested: FunctionType::new(Args::wasm_types(), Rets::wasm_types()),
+            rust: format!("{} -> {}", type_name::<Args>(), type_name::<Rets>()),
+        }
+    }
 }
 
 /// Exports is a special kind of map that allows easily unwrapping
 /// the types of instances.
 ///
 /// TODO: add examples of using exports
-#[derive(Clone, Default, MemoryUsage)]
+#[derive(Clone, Default)]
 pub struct Exports {
     map: Arc<IndexMap<String, Extern>>,
 }
@@ -148,9 +202,10 @@ impl Exports {
         Args: WasmTypeList,
         Rets: WasmTypeList,
     {
-        self.get_function(name)?
+        let function = self.get_function(name)?;
+        function
             .native()
-            .map_err(|_| ExportError::IncompatibleType)
+            .map_err(|_| ExportError::incompatible_signature::<Args, Rets>(name, function.ty()))
     }
 
     /// Hack to get this working with nativefunc too
@@ -162,7 +217,23 @@ impl Exports {
     {
         match self.map.get(name) {
             None => Err(ExportError::Missing(name.to_string())),
-            Some(extern_) => T::get_self_from_extern_with_generics(extern_),
+            Some(extern_) => {
+                T::get_self_from_extern_with_generics(extern_).map_err(|err| match err {
+                    // The name of the export isn't known by `T`
+                    ExportError::IncompatibleSignature {
+                        wasm,
+                        requested,
+                        rust,
+                        ..
+                    } => ExportError::IncompatibleSignature {
+                        name: name.to_string(),
+                        wasm,
+                        requested,
+                        rust,
+                    },
+                    err => err,
+                })
+            }
         }
     }
 
@@ -192,6 +263,14 @@ impl Exports {
         self.map.contains_key(&name.into())
     }
 
+    /// Get an iterator over the exported functions, with their names.
+    ///
+    /// Their signatures are given by [`Function::ty`], which is useful to
+    /// find out which `NativeFunc` an export can be got as.
+    pub fn iter_functions(&self) -> impl Iterator<Item = (&String, &Function)> {
+        self.iter().functions()
+    }
+
     /// Get an iterator over the exports.
     pub fn iter(&self) -> ExportsIterator<impl Iterator<Item = (&String, &Extern)>> {
         ExportsIterator {
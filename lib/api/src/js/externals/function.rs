@@ -0,0 +1,908 @@
+use crate::js::export::{Export, HostEnvInitializer, VMFunction};
+use crate::js::exports::{ExportError, Exportable};
+use crate::js::externals::Extern;
+use crate::js::store::Store;
+use crate::js::types::{as_js, param_from_js, Val};
+use crate::FunctionType;
+use crate::NativeFunc;
+use crate::RuntimeError;
+use crate::WasmerEnv;
+pub use inner::{FromToNativeWasmType, HostFunction, WasmTypeList, WithEnv, WithoutEnv};
+
+use js_sys::{Array, Function as JsFunction};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// A WebAssembly `function` instance.
+///
+/// A function instance is the runtime representation of a function.
+/// It effectively is a closure of the original function (defined in either
+/// the host or the WebAssembly module) over the runtime `Instance` of its
+/// originating `Module`.
+///
+/// The module instance is used to resolve references to other definitions
+/// during execution of the function.
+///
+/// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#function-instances>
+///
+/// With the `js` backend, the host functions are wrapped in JavaScript
+/// functions, so unlike with the `sys` backend, closures are supported.
+#[derive(PartialEq)]
+pub struct Function {
+    pub(crate) store: Store,
+    pub(crate) exported: VMFunction,
+}
+
+/// The JavaScript functions can't be stored in raw memory, so a `funcref`
+/// is written as a pointer to the `Function`, which must outlive it.
+impl wasmer_types::WasmValueType for Function {
+    /// Write the value.
+    unsafe fn write_value_to(&self, p: *mut i128) {
+        std::ptr::write(p as *mut *const Self, self);
+    }
+
+    /// Read the value.
+    unsafe fn read_value_from(_store: &dyn std::any::Any, p: *const i128) -> Self {
+        (*std::ptr::read(p as *const *const Self)).clone()
+    }
+}
+
+impl WasmerEnv for WithoutEnv {}
+
+/// The type of the Rust closures wrapped in JavaScript functions: they
+/// take the arguments as an array, and return the result, or throw.
+type JsHostFunction = dyn FnMut(Array) -> Result<JsValue, JsValue>;
+
+thread_local! {
+    /// Wraps a `JsHostFunction` in a variadic JavaScript function, as the
+    /// WebAssembly code calls it with separate arguments.
+    static WRAPPER: JsFunction = JsFunction::new_with_args(
+        "f",
+        "return function() { return f(Array.prototype.slice.call(arguments)); }",
+    );
+}
+
+impl Function {
+    /// Creates a new host `Function` (dynamic) with the provided signature.
+    ///
+    /// If you know the signature of the host function at compile time,
+    /// consider using [`Function::new_native`] for less runtime overhead.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new(&store, &signature, |args| {
+    ///     let sum = args[0].unwrap_i32() + args[1].unwrap_i32();
+    ///     Ok(vec![Value::I32(sum)])
+    /// });
+    /// ```
+    pub fn new<FT, F>(store: &Store, ty: FT, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&[Val]) -> Result<Vec<Val>, RuntimeError> + 'static + Send + Sync,
+    {
+        Self::from_host(store, ty.into(), func, None)
+    }
+
+    /// Creates a new host `Function` (dynamic) with the provided signature and environment.
+    ///
+    /// If you know the signature of the host function at compile time,
+    /// consider using [`Function::new_native_with_env`] for less runtime
+    /// overhead.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value, WasmerEnv};
+    /// # let store = Store::default();
+    /// #
+    /// #[derive(WasmerEnv, Clone)]
+    /// struct Env {
+    ///   multiplier: i32,
+    /// };
+    /// let env = Env { multiplier: 2 };
+    ///
+    /// let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_with_env(&store, &signature, env, |env, args| {
+    ///     let result = env.multiplier * (args[0].unwrap_i32() + args[1].unwrap_i32());
+    ///     Ok(vec![Value::I32(result)])
+    /// });
+    /// ```
+    pub fn new_with_env<FT, F, Env>(store: &Store, ty: FT, env: Env, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&Env, &[Val]) -> Result<Vec<Val>, RuntimeError> + 'static + Send + Sync,
+        Env: Sized + WasmerEnv + 'static,
+    {
+        let env = Rc::new(RefCell::new(env));
+        let initializer = Self::env_initializer(&env);
+        Self::from_host(
+            store,
+            ty.into(),
+            move |args| func(&env.borrow(), args),
+            Some(initializer),
+        )
+    }
+
+    /// Creates a new host `Function` from a native function.
+    ///
+    /// The function signature is automatically retrieved using the
+    /// Rust typing system.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// # use wasmer::{Store, Function};
+    /// # let store = Store::default();
+    /// #
+    /// fn sum(a: i32, b: i32) -> i32 {
+    ///     a + b
+    /// }
+    ///
+    /// let f = Function::new_native(&store, sum);
+    /// ```
+    pub fn new_native<F, Args, Rets, Env>(store: &Store, func: F) -> Self
+    where
+        F: HostFunction<Args, Rets, WithoutEnv, Env>,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+        Env: Sized + 'static,
+    {
+        let ty = FunctionType::new(Args::wasm_types(), Rets::wasm_types());
+        let body = func.function_body();
+        Self::from_host(store, ty, move |args| body(None, args), None)
+    }
+
+    /// Creates a new host `Function` from a native function and a provided environment.
+    ///
+    /// The function signature is automatically retrieved using the
+    /// Rust typing system.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// # use wasmer::{Store, Function, WasmerEnv};
+    /// # let store = Store::default();
+    /// #
+    /// #[derive(WasmerEnv, Clone)]
+    /// struct Env {
+    ///     multiplier: i32,
+    /// };
+    /// let env = Env { multiplier: 2 };
+    ///
+    /// fn sum_and_multiply(env: &Env, a: i32, b: i32) -> i32 {
+    ///     (a + b) * env.multiplier
+    /// }
+    ///
+    /// let f = Function::new_native_with_env(&store, env, sum_and_multiply);
+    /// ```
+    pub fn new_native_with_env<F, Args, Rets, Env>(store: &Store, env: Env, func: F) -> Self
+    where
+        F: HostFunction<Args, Rets, WithEnv, Env>,
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+        Env: Sized + WasmerEnv + 'static,
+    {
+        let ty = FunctionType::new(Args::wasm_types(), Rets::wasm_types());
+        let body = func.function_body();
+        let env = Rc::new(RefCell::new(env));
+        let initializer = Self::env_initializer(&env);
+        Self::from_host(
+            store,
+            ty,
+            move |args| body(Some(&*env.borrow()), args),
+            Some(initializer),
+        )
+    }
+
+    /// Builds the initializer of a shared environment, called with the
+    /// instance importing the function.
+    fn env_initializer<Env>(env: &Rc<RefCell<Env>>) -> Arc<HostEnvInitializer>
+    where
+        Env: Sized + WasmerEnv + 'static,
+    {
+        let env = env.clone();
+        Arc::new(move |instance| env.borrow_mut().init_with_instance(instance))
+    }
+
+    /// Wraps a host function in a JavaScript function, which converts the
+    /// arguments and results, and checks the types of the latter.
+    fn from_host<F>(
+        store: &Store,
+        ty: FunctionType,
+        func: F,
+        environment: Option<Arc<HostEnvInitializer>>,
+    ) -> Self
+    where
+        F: Fn(&[Val]) -> Result<Vec<Val>, RuntimeError> + 'static,
+    {
+        let func_ty = ty.clone();
+        let wrapped = move |args: Array| -> Result<JsValue, JsValue> {
+            let params = func_ty
+                .params()
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| param_from_js(ty, &args.get(i as u32)))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(RuntimeError::into_js)?;
+            let returns = func(&params).map_err(RuntimeError::into_js)?;
+
+            // We need to dynamically check that the returns
+            // match the expected types, as well as expected length.
+            let return_types = returns.iter().map(|ret| ret.ty()).collect::<Vec<_>>();
+            if return_types != func_ty.results() {
+                return Err(RuntimeError::new(format!(
+                    "Dynamic function returned wrong signature. Expected {:?} but got {:?}",
+                    func_ty.results(),
+                    return_types
+                ))
+                .into_js());
+            }
+            match returns.len() {
+                0 => Ok(JsValue::UNDEFINED),
+                1 => as_js(&returns[0]),
+                _ => returns
+                    .iter()
+                    .map(as_js)
+                    .collect::<Result<Array, _>>()
+                    .map(Into::into),
+            }
+            .map_err(RuntimeError::into_js)
+        };
+
+        // The closure is owned by the JavaScript function from now on, and
+        // freed with it by the garbage collector.
+        let closure = Closure::wrap(Box::new(wrapped) as Box<JsHostFunction>).into_js_value();
+        let function = WRAPPER
+            .with(|wrapper| wrapper.call1(&JsValue::UNDEFINED, &closure))
+            .expect("the host function wrapper can't throw")
+            .unchecked_into::<JsFunction>();
+
+        Self {
+            store: store.clone(),
+            exported: VMFunction::new(function, ty, environment),
+        }
+    }
+
+    /// Returns the [`FunctionType`] of the `Function`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// # use wasmer::{Function, Store, Type};
+    /// # let store = Store::default();
+    /// #
+    /// fn sum(a: i32, b: i32) -> i32 {
+    ///     a + b
+    /// }
+    ///
+    /// let f = Function::new_native(&store, sum);
+    ///
+    /// assert_eq!(f.ty().params(), vec![Type::I32, Type::I32]);
+    /// assert_eq!(f.ty().results(), vec![Type::I32]);
+    /// ```
+    pub fn ty(&self) -> &FunctionType {
+        &self.exported.ty
+    }
+
+    /// Returns the [`Store`] where the `Function` belongs.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Returns the number of parameters that this function takes.
+    pub fn param_arity(&self) -> usize {
+        self.ty().params().len()
+    }
+
+    /// Returns the number of results this function produces.
+    pub fn result_arity(&self) -> usize {
+        self.ty().results().len()
+    }
+
+    /// Call the `Function` function.
+    ///
+    /// The function is called through the host, whether it's defined
+    /// inside a WebAssembly module or in the host.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// # use wasmer::{imports, wat2wasm, Function, Instance, Module, Store, Type, Value};
+    /// # let store = Store::default();
+    /// # let wasm_bytes = wat2wasm(r#"
+    /// # (module
+    /// #   (func (export "sum") (param $x i32) (param $y i32) (result i32)
+    /// #     local.get $x
+    /// #     local.get $y
+    /// #     i32.add
+    /// #   ))
+    /// # "#.as_bytes()).unwrap();
+    /// # let module = Module::new(&store, wasm_bytes).unwrap();
+    /// # let import_object = imports! {};
+    /// # let instance = Instance::new(&module, &import_object).unwrap();
+    /// #
+    /// let sum = instance.exports.get_function("sum").unwrap();
+    ///
+    /// assert_eq!(sum.call(&[Value::I32(1), Value::I32(2)]).unwrap().to_vec(), vec![Value::I32(3)]);
+    /// ```
+    pub fn call(&self, params: &[Val]) -> Result<Box<[Val]>, RuntimeError> {
+        let format_types_for_error_message = |items: &[Val]| {
+            items
+                .iter()
+                .map(|param| param.ty().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let signature = self.ty();
+        if signature.params().len() != params.len()
+            || params
+                .iter()
+                .zip(signature.params())
+                .any(|(param, ty)| param.ty() != *ty)
+        {
+            return Err(RuntimeError::new(format!(
+                "Parameters of type [{}] did not match signature {}",
+                format_types_for_error_message(params),
+                &signature
+            )));
+        }
+
+        let arguments = params.iter().map(as_js).collect::<Result<Array, _>>()?;
+        let result =
+            js_sys::Reflect::apply(&self.exported.function, &JsValue::UNDEFINED, &arguments)
+                .map_err(RuntimeError::from_js)?;
+
+        let result_types = signature.results();
+        Ok(match result_types.len() {
+            0 => Box::new([]) as Box<[Val]>,
+            1 => Box::new([param_from_js(&result_types[0], &result)?]) as Box<[Val]>,
+            _ => {
+                let results = result.unchecked_into::<Array>();
+                result_types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| param_from_js(ty, &results.get(i as u32)))
+                    .collect::<Result<_, _>>()?
+            }
+        })
+    }
+
+    pub(crate) fn from_vm_export(store: &Store, wasmer_export: VMFunction) -> Self {
+        Self {
+            store: store.clone(),
+            exported: wasmer_export,
+        }
+    }
+
+    /// Transform this WebAssembly function into a function with the
+    /// native ABI. See [`NativeFunc`] to learn more.
+    ///
+    /// # Errors
+    ///
+    /// If the `Args` or `Rets` generic parameters don't match the
+    /// function type, an error will be returned.
+    pub fn native<Args, Rets>(&self) -> Result<NativeFunc<Args, Rets>, RuntimeError>
+    where
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+    {
+        // type check
+        {
+            let expected = self.exported.ty.params();
+            let given = Args::wasm_types();
+
+            if expected != given {
+                return Err(RuntimeError::new(format!(
+                    "given types (`{:?}`) for the function arguments don't match the actual types (`{:?}`)",
+                    given,
+                    expected,
+                )));
+            }
+        }
+
+        {
+            let expected = self.exported.ty.results();
+            let given = Rets::wasm_types();
+
+            if expected != given {
+                return Err(RuntimeError::new(format!(
+                    "given types (`{:?}`) for the function results don't match the actual types (`{:?}`)",
+                    given,
+                    expected,
+                )));
+            }
+        }
+
+        Ok(NativeFunc::new(self.store.clone(), self.exported.clone()))
+    }
+}
+
+impl<'a> Exportable<'a> for Function {
+    fn to_export(&self) -> Export {
+        Export::Function(self.exported.clone())
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Function(func) => Ok(func),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+
+    fn into_weak_instance_ref(&mut self) {
+        // The instances are garbage collected by the host, which handles
+        // the cycles.
+    }
+}
+
+impl Clone for Function {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            exported: self.exported.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Function")
+            .field("ty", &self.ty())
+            .finish()
+    }
+}
+
+/// This private inner module contains the low-level implementation
+/// for `Function` and its siblings.
+mod inner {
+    use std::array::TryFromSliceError;
+    use std::convert::{Infallible, TryInto};
+    use std::error::Error;
+
+    use crate::js::types::{binary_to_val, val_to_binary, Val};
+    use crate::RuntimeError;
+    use wasmer_types::{NativeWasmType, Type};
+
+    /// A trait to convert a Rust value to a `WasmNativeType` value,
+    /// or to convert `WasmNativeType` value to a Rust value.
+    ///
+    /// This trait should ideally be split into two traits:
+    /// `FromNativeWasmType` and `ToNativeWasmType` but it creates a
+    /// non-negligible complexity in the `WasmTypeList`
+    /// implementation.
+    pub unsafe trait FromToNativeWasmType
+    where
+        Self: Sized,
+    {
+        /// Native Wasm type.
+        type Native: NativeWasmType;
+
+        /// Convert a value of kind `Self::Native` to `Self`.
+        ///
+        /// # Panics
+        ///
+        /// This method panics if `native` cannot fit in the `Self`
+        /// type`.
+        fn from_native(native: Self::Native) -> Self;
+
+        /// Convert self to `Self::Native`.
+        ///
+        /// # Panics
+        ///
+        /// This method panics if `self` cannot fit in the
+        /// `Self::Native` type.
+        fn to_native(self) -> Self::Native;
+    }
+
+    macro_rules! from_to_native_wasm_type {
+        ( $( $type:ty => $native_type:ty ),* ) => {
+            $(
+                #[allow(clippy::use_self)]
+                unsafe impl FromToNativeWasmType for $type {
+                    type Native = $native_type;
+
+                    #[inline]
+                    fn from_native(native: Self::Native) -> Self {
+                        native as Self
+                    }
+
+                    #[inline]
+                    fn to_native(self) -> Self::Native {
+                        self as Self::Native
+                    }
+                }
+            )*
+        };
+    }
+
+    macro_rules! from_to_native_wasm_type_same_size {
+        ( $( $type:ty => $native_type:ty ),* ) => {
+            $(
+                #[allow(clippy::use_self)]
+                unsafe impl FromToNativeWasmType for $type {
+                    type Native = $native_type;
+
+                    #[inline]
+                    fn from_native(native: Self::Native) -> Self {
+                        Self::from_ne_bytes(Self::Native::to_ne_bytes(native))
+                    }
+
+                    #[inline]
+                    fn to_native(self) -> Self::Native {
+                        Self::Native::from_ne_bytes(Self::to_ne_bytes(self))
+                    }
+                }
+            )*
+        };
+    }
+
+    from_to_native_wasm_type!(
+        i8 => i32,
+        u8 => i32,
+        i16 => i32,
+        u16 => i32
+    );
+
+    from_to_native_wasm_type_same_size!(
+        i32 => i32,
+        u32 => i32,
+        i64 => i64,
+        u64 => i64,
+        f32 => f32,
+        f64 => f64
+    );
+
+    /// The `WasmTypeList` trait represents a tuple (list) of Wasm
+    /// typed values. It is used to get low-level representation of
+    /// such a tuple.
+    pub trait WasmTypeList
+    where
+        Self: Sized,
+    {
+        /// The array type that can hold all the represented values.
+        ///
+        /// Note that all values are stored in their binary form.
+        type Array: AsMut<[i128]>;
+
+        /// Constructs `Self` based on an array of values.
+        fn from_array(array: Self::Array) -> Self;
+
+        /// Constructs `Self` based on a slice of values.
+        ///
+        /// `from_slice` returns a `Result` because it is possible
+        /// that the slice doesn't have the same size than
+        /// `Self::Array`, in which circumstance an error of kind
+        /// `TryFromSliceError` will be returned.
+        fn from_slice(slice: &[i128]) -> Result<Self, TryFromSliceError>;
+
+        /// Builds and returns an array of type `Array` from a tuple
+        /// (list) of values.
+        fn into_array(self) -> Self::Array;
+
+        /// Allocates and return an empty array of type `Array` that
+        /// will hold a tuple (list) of values, usually to hold the
+        /// returned values of a WebAssembly function call.
+        fn empty_array() -> Self::Array;
+
+        /// Get the Wasm types for the tuple (list) of currently
+        /// represented values.
+        fn wasm_types() -> &'static [Type];
+    }
+
+    /// The `IntoResult` trait turns a `WasmTypeList` into a
+    /// `Result<WasmTypeList, Self::Error>`.
+    ///
+    /// It is mostly used to turn result values of a Wasm function
+    /// call into a `Result`.
+    pub trait IntoResult<T>
+    where
+        T: WasmTypeList,
+    {
+        /// The error type for this trait.
+        type Error: Error + Sync + Send + 'static;
+
+        /// Transforms `Self` into a `Result`.
+        fn into_result(self) -> Result<T, Self::Error>;
+    }
+
+    impl<T> IntoResult<T> for T
+    where
+        T: WasmTypeList,
+    {
+        // `T` is not a `Result`, it's already a value, so no error
+        // can be built.
+        type Error = Infallible;
+
+        fn into_result(self) -> Result<Self, Infallible> {
+            Ok(self)
+        }
+    }
+
+    impl<T, E> IntoResult<T> for Result<T, E>
+    where
+        T: WasmTypeList,
+        E: Error + Sync + Send + 'static,
+    {
+        type Error = E;
+
+        fn into_result(self) -> Self {
+            self
+        }
+    }
+
+    /// The body of a host function: it's called with the environment, if
+    /// the function has one, and the arguments, and returns the results.
+    pub type FunctionBody<T> = Box<dyn Fn(Option<&T>, &[Val]) -> Result<Vec<Val>, RuntimeError>>;
+
+    /// The `HostFunction` trait represents the set of functions that
+    /// can be used as host function. To uphold this statement, it is
+    /// necessary for a function to be transformed into a
+    /// [`FunctionBody`] taking and returning dynamic values.
+    pub trait HostFunction<Args, Rets, Kind, T>
+    where
+        Args: WasmTypeList,
+        Rets: WasmTypeList,
+        Kind: HostFunctionKind,
+        T: Sized,
+        Self: Sized,
+    {
+        /// Get the body of the function.
+        fn function_body(self) -> FunctionBody<T>;
+    }
+
+    /// Empty trait to specify the kind of `HostFunction`: With or
+    /// without an environment.
+    ///
+    /// This trait is never aimed to be used by a user. It is used by
+    /// the trait system to automatically generate the appropriate
+    /// host functions.
+    #[doc(hidden)]
+    pub trait HostFunctionKind {}
+
+    /// An empty struct to help Rust typing to determine
+    /// when a `HostFunction` does have an environment.
+    pub struct WithEnv;
+
+    impl HostFunctionKind for WithEnv {}
+
+    /// An empty struct to help Rust typing to determine
+    /// when a `HostFunction` does not have an environment.
+    #[derive(Clone)]
+    pub struct WithoutEnv;
+
+    impl HostFunctionKind for WithoutEnv {}
+
+    /// Converts the dynamic arguments of a host function to `Args`.
+    fn args_from_vals<Args: WasmTypeList>(args: &[Val]) -> Result<Args, RuntimeError> {
+        let binaries = args
+            .iter()
+            .map(val_to_binary)
+            .collect::<Result<Vec<_>, _>>()?;
+        Args::from_slice(&binaries)
+            .map_err(|_| RuntimeError::new("the arguments don't match the function type"))
+    }
+
+    /// Converts the results of a host function to dynamic values.
+    fn vals_from_rets<Rets: WasmTypeList>(rets: Rets) -> Result<Vec<Val>, RuntimeError> {
+        let mut array = rets.into_array();
+        array
+            .as_mut()
+            .iter()
+            .zip(Rets::wasm_types())
+            .map(|(binary, ty)| binary_to_val(ty, *binary))
+            .collect()
+    }
+
+    macro_rules! impl_host_function {
+        ( $( $x:ident ),* ) => {
+            // Implement `WasmTypeList` for a specific tuple.
+            #[allow(unused_parens, dead_code)]
+            impl< $( $x ),* >
+                WasmTypeList
+            for
+                ( $( $x ),* )
+            where
+                $( $x: FromToNativeWasmType ),*
+            {
+                type Array = [i128; count_idents!( $( $x ),* )];
+
+                fn from_array(array: Self::Array) -> Self {
+                    // Unpack items of the array.
+                    #[allow(non_snake_case)]
+                    let [ $( $x ),* ] = array;
+
+                    // Build the tuple.
+                    (
+                        $(
+                            FromToNativeWasmType::from_native(NativeWasmType::from_binary($x))
+                        ),*
+                    )
+                }
+
+                fn from_slice(slice: &[i128]) -> Result<Self, TryFromSliceError> {
+                    Ok(Self::from_array(slice.try_into()?))
+                }
+
+                fn into_array(self) -> Self::Array {
+                    // Unpack items of the tuple.
+                    #[allow(non_snake_case)]
+                    let ( $( $x ),* ) = self;
+
+                    // Build the array.
+                    [
+                        $(
+                            FromToNativeWasmType::to_native($x).to_binary()
+                        ),*
+                    ]
+                }
+
+                fn empty_array() -> Self::Array {
+                    // Build an array initialized with `0`.
+                    [0; count_idents!( $( $x ),* )]
+                }
+
+                fn wasm_types() -> &'static [Type] {
+                    &[
+                        $(
+                            $x::Native::WASM_TYPE
+                        ),*
+                    ]
+                }
+            }
+
+            // Implement `HostFunction` for a function that has the same arity than the tuple.
+            // This specific function has no environment.
+            #[allow(unused_parens)]
+            impl< $( $x, )* Rets, RetsAsResult, Func >
+                HostFunction<( $( $x ),* ), Rets, WithoutEnv, ()>
+            for
+                Func
+            where
+                $( $x: FromToNativeWasmType, )*
+                Rets: WasmTypeList,
+                RetsAsResult: IntoResult<Rets>,
+                Func: Fn($( $x , )*) -> RetsAsResult + 'static + Send,
+            {
+                #[allow(non_snake_case)]
+                fn function_body(self) -> FunctionBody<()> {
+                    Box::new(move |_env, args| {
+                        let ( $( $x ),* ) = args_from_vals::<( $( $x ),* )>(args)?;
+                        let rets = self( $( $x ),* )
+                            .into_result()
+                            .map_err(|error| RuntimeError::user(Box::new(error)))?;
+                        vals_from_rets(rets)
+                    })
+                }
+            }
+
+            // Implement `HostFunction` for a function that has the same arity than the tuple.
+            // This specific function has an environment.
+            #[allow(unused_parens)]
+            impl< $( $x, )* Rets, RetsAsResult, Env, Func >
+                HostFunction<( $( $x ),* ), Rets, WithEnv, Env>
+            for
+                Func
+            where
+                $( $x: FromToNativeWasmType, )*
+                Rets: WasmTypeList,
+                RetsAsResult: IntoResult<Rets>,
+                Env: Sized,
+                Func: Fn(&Env, $( $x , )*) -> RetsAsResult + Send + 'static,
+            {
+                #[allow(non_snake_case)]
+                fn function_body(self) -> FunctionBody<Env> {
+                    Box::new(move |env, args| {
+                        let env = env.expect("a host function with an environment is called without it");
+                        let ( $( $x ),* ) = args_from_vals::<( $( $x ),* )>(args)?;
+                        let rets = self(env, $( $x ),* )
+                            .into_result()
+                            .map_err(|error| RuntimeError::user(Box::new(error)))?;
+                        vals_from_rets(rets)
+                    })
+                }
+            }
+        };
+    }
+
+    // Black-magic to count the number of identifiers at compile-time.
+    macro_rules! count_idents {
+        ( $($idents:ident),* ) => {
+            {
+                #[allow(dead_code, non_camel_case_types)]
+                enum Idents { $( $idents, )* __CountIdentsLast }
+                const COUNT: usize = Idents::__CountIdentsLast as usize;
+                COUNT
+            }
+        };
+    }
+
+    // Here we go! Let's generate all the `WasmTypeList`
+    // implementations and `HostFunction` implementations.
+    impl_host_function!();
+    impl_host_function!(A1);
+    impl_host_function!(A1, A2);
+    impl_host_function!(A1, A2, A3);
+    impl_host_function!(A1, A2, A3, A4);
+    impl_host_function!(A1, A2, A3, A4, A5);
+    impl_host_function!(A1, A2, A3, A4, A5, A6);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8, A9);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16);
+    impl_host_function!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17);
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23, A24
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23, A24, A25
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23, A24, A25, A26
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23, A24, A25, A26, A27
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23, A24, A25, A26, A27, A28
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23, A24, A25, A26, A27, A28, A29
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23, A24, A25, A26, A27, A28, A29, A30
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23, A24, A25, A26, A27, A28, A29, A30, A31
+    );
+    impl_host_function!(
+        A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20,
+        A21, A22, A23, A24, A25, A26, A27, A28, A29, A30, A31, A32
+    );
+}
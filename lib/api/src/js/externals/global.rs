@@ -0,0 +1,189 @@
+use crate::js::export::{Export, VMGlobal};
+use crate::js::exports::{ExportError, Exportable};
+use crate::js::externals::Extern;
+use crate::js::store::Store;
+use crate::js::types::{as_js, param_from_js, Val};
+use crate::js::wasm_bindgen_polyfill::Global as JsGlobal;
+use crate::GlobalType;
+use crate::Mutability;
+use crate::RuntimeError;
+use crate::ValType;
+use js_sys::{Object, Reflect};
+use std::fmt;
+use wasm_bindgen::JsValue;
+
+/// A WebAssembly `global` instance.
+///
+/// A global instance is the runtime representation of a global variable.
+/// It consists of an individual value and a flag indicating whether it is mutable.
+///
+/// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#global-instances>
+pub struct Global {
+    store: Store,
+    vm_global: VMGlobal,
+}
+
+impl Global {
+    /// Create a new `Global` with the initial value [`Val`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// # use wasmer::{Global, Mutability, Store, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let g = Global::new(&store, Value::I32(1));
+    ///
+    /// assert_eq!(g.get(), Value::I32(1));
+    /// assert_eq!(g.ty().mutability, Mutability::Const);
+    /// ```
+    pub fn new(store: &Store, val: Val) -> Self {
+        Self::from_value(store, val, Mutability::Const).unwrap()
+    }
+
+    /// Create a mutable `Global` with the initial value [`Val`].
+    pub fn new_mut(store: &Store, val: Val) -> Self {
+        Self::from_value(store, val, Mutability::Var).unwrap()
+    }
+
+    /// Create a `Global` with the initial value [`Val`] and the provided [`Mutability`].
+    fn from_value(store: &Store, val: Val, mutability: Mutability) -> Result<Self, RuntimeError> {
+        let ty = GlobalType {
+            mutability,
+            ty: val.ty(),
+        };
+        let value_type = match ty.ty {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+            ValType::F32 => "f32",
+            ValType::F64 => "f64",
+            other => {
+                return Err(RuntimeError::new(format!(
+                    "The `js` backend doesn't support {:?} globals yet",
+                    other
+                )))
+            }
+        };
+        let descriptor = Object::new();
+        Reflect::set(&descriptor, &"value".into(), &value_type.into())
+            .and_then(|_| {
+                Reflect::set(
+                    &descriptor,
+                    &"mutable".into(),
+                    &JsValue::from_bool(mutability.is_mutable()),
+                )
+            })
+            .map_err(RuntimeError::from_js)?;
+        let global = JsGlobal::new(&descriptor, &as_js(&val)?).map_err(|e| {
+            RuntimeError::new(format!(
+                "create global for {:?}: {}",
+                val,
+                RuntimeError::from_js(e).message()
+            ))
+        })?;
+
+        Ok(Self {
+            store: store.clone(),
+            vm_global: VMGlobal::new(global, ty),
+        })
+    }
+
+    /// Returns the [`GlobalType`] of the `Global`.
+    pub fn ty(&self) -> &GlobalType {
+        &self.vm_global.ty
+    }
+
+    /// Returns the [`Store`] where the `Global` belongs.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Retrieves the current value [`Val`] that the Global has.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the global holds a reference or a `v128`, which the `js`
+    /// backend can't read yet.
+    pub fn get(&self) -> Val {
+        self.value()
+            .unwrap_or_else(|error| panic!("{}", error.message()))
+    }
+
+    /// Retrieves the current value of the global, or the error of the values
+    /// the `js` backend can't read yet.
+    fn value(&self) -> Result<Val, RuntimeError> {
+        param_from_js(&self.vm_global.ty.ty, &self.vm_global.global.value())
+    }
+
+    /// Sets a custom value [`Val`] to the runtime Global.
+    ///
+    /// # Errors
+    ///
+    /// Trying to mutate a immutable global, or to set a value of an
+    /// incompatible type, will raise an error.
+    pub fn set(&self, val: Val) -> Result<(), RuntimeError> {
+        if self.vm_global.ty.mutability == Mutability::Const {
+            return Err(RuntimeError::new("Constant globals cannot be set"));
+        }
+        if val.ty() != self.vm_global.ty.ty {
+            return Err(RuntimeError::new(format!(
+                "Attempted to operate on a global of type {expected} as a global of type {found}",
+                expected = self.vm_global.ty.ty,
+                found = val.ty(),
+            )));
+        }
+        self.vm_global
+            .global
+            .set_value(&as_js(&val)?)
+            .map_err(RuntimeError::from_js)
+    }
+
+    pub(crate) fn from_vm_export(store: &Store, vm_global: VMGlobal) -> Self {
+        Self {
+            store: store.clone(),
+            vm_global,
+        }
+    }
+
+    /// Returns whether or not these two globals refer to the same data.
+    pub fn same(&self, other: &Self) -> bool {
+        self.vm_global.global == other.vm_global.global
+    }
+}
+
+impl Clone for Global {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            vm_global: self.vm_global.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for Global {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Global")
+            .field("ty", &self.ty())
+            .field("value", &self.value().ok())
+            .finish()
+    }
+}
+
+impl<'a> Exportable<'a> for Global {
+    fn to_export(&self) -> Export {
+        Export::Global(self.vm_global.clone())
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Global(global) => Ok(global),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+
+    fn into_weak_instance_ref(&mut self) {
+        // The instances are garbage collected by the host, which handles
+        // the cycles.
+    }
+}
@@ -0,0 +1,333 @@
+use crate::js::export::{Export, VMMemory};
+use crate::js::exports::{ExportError, Exportable};
+use crate::js::externals::Extern;
+use crate::js::store::Store;
+use crate::js::trap::RuntimeError;
+use crate::MemoryType;
+use js_sys::{Function as JsFunction, Object, Reflect, Uint8Array, WebAssembly};
+use std::cmp;
+use std::mem::{self, MaybeUninit};
+use std::slice;
+use thiserror::Error;
+use wasm_bindgen::{JsCast, JsValue};
+use wasmer_types::{Pages, ValueType, WASM_MAX_PAGES, WASM_PAGE_SIZE};
+
+/// An error while accessing the contents of a [`Memory`] with one of its
+/// helpers, like [`Memory::read`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MemoryAccessError {
+    /// The access goes past the end of the memory.
+    #[error("memory access out of bounds")]
+    HeapOutOfBounds,
+    /// The end address of the access doesn't fit in 64 bits.
+    #[error("address calculation overflow")]
+    Overflow,
+    /// The bytes read aren't valid UTF-8.
+    #[error("string is not valid utf-8")]
+    NonUtf8String,
+}
+
+impl From<MemoryAccessError> for RuntimeError {
+    fn from(err: MemoryAccessError) -> Self {
+        Self::new(err.to_string())
+    }
+}
+
+/// Error type describing things that can go wrong when operating on Wasm Memories.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MemoryError {
+    /// The operation would cause the size of the memory to exceed the maximum or would cause
+    /// an overflow leading to unindexable memory.
+    #[error("The memory could not grow: current size {} pages, requested increase: {} pages", current.0, attempted_delta.0)]
+    CouldNotGrow {
+        /// The current size in pages.
+        current: Pages,
+        /// The attempted amount to grow by in pages.
+        attempted_delta: Pages,
+    },
+    /// The operation would cause the size of the memory size exceed the maximum.
+    #[error("The memory is invalid because {}", reason)]
+    InvalidMemory {
+        /// The reason why the provided memory is invalid.
+        reason: String,
+    },
+    /// A user defined error value, used for error cases not listed above.
+    #[error("A user-defined error occurred: {0}")]
+    Generic(String),
+}
+
+/// A WebAssembly `memory` instance.
+///
+/// A memory instance is the runtime representation of a linear memory.
+/// It consists of a vector of bytes and an optional maximum size.
+///
+/// The length of the vector always is a multiple of the WebAssembly
+/// page size, which is defined to be the constant 65536 – abbreviated 64Ki.
+/// Like in a memory type, the maximum size in a memory instance is
+/// given in units of this page size.
+///
+/// A memory created by the host or in WebAssembly code will be accessible and
+/// mutable from both host and WebAssembly.
+///
+/// With the `js` backend, the memory is a `WebAssembly.Memory` of the host,
+/// outside of the memory of the embedder, so its contents are only
+/// accessed by copying them, with helpers like [`Memory::read`].
+///
+/// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#memory-instances>
+#[derive(Debug, Clone)]
+pub struct Memory {
+    store: Store,
+    vm_memory: VMMemory,
+}
+
+impl Memory {
+    /// Creates a new host `Memory` from the provided [`MemoryType`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// # use wasmer::{Memory, MemoryType, Pages, Store, Type, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// ```
+    pub fn new(store: &Store, ty: MemoryType) -> Result<Self, MemoryError> {
+        let descriptor = Object::new();
+        let set = |key: &str, value: JsValue| {
+            Reflect::set(&descriptor, &key.into(), &value)
+                .map_err(|_| MemoryError::Generic("can't build the memory descriptor".into()))
+        };
+        set("initial", ty.minimum.0.into())?;
+        if let Some(maximum) = ty.maximum {
+            set("maximum", maximum.0.into())?;
+        }
+        set("shared", ty.shared.into())?;
+
+        let memory = WebAssembly::Memory::new(&descriptor)
+            .map_err(|error| MemoryError::Generic(RuntimeError::from_js(error).message()))?;
+
+        Ok(Self {
+            store: store.clone(),
+            vm_memory: VMMemory::new(memory, ty),
+        })
+    }
+
+    /// Returns the [`MemoryType`] of the `Memory`.
+    pub fn ty(&self) -> MemoryType {
+        self.vm_memory.ty
+    }
+
+    /// Returns the [`Store`] where the `Memory` belongs.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Returns a view of the bytes of the memory.
+    ///
+    /// The view is detached when the memory grows, so it's built again for
+    /// each access.
+    fn bytes(&self) -> Uint8Array {
+        Uint8Array::new(&self.vm_memory.memory.buffer())
+    }
+
+    /// Returns the size (in bytes) of the `Memory`.
+    pub fn data_size(&self) -> u64 {
+        self.bytes().length() as u64
+    }
+
+    /// Returns the size (in [`Pages`]) of the `Memory`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// # use wasmer::{Memory, MemoryType, Pages, Store, Type, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    ///
+    /// assert_eq!(m.size(), Pages(1));
+    /// ```
+    pub fn size(&self) -> Pages {
+        Pages((self.data_size() / WASM_PAGE_SIZE as u64) as u32)
+    }
+
+    /// Grow memory by the specified amount of WebAssembly [`Pages`] and return
+    /// the previous memory size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if memory can't be grown by the specified amount
+    /// of pages.
+    pub fn grow<IntoPages>(&self, delta: IntoPages) -> Result<Pages, MemoryError>
+    where
+        IntoPages: Into<Pages>,
+    {
+        let delta = delta.into();
+        let current = self.size();
+        let maximum = self.vm_memory.ty.maximum.unwrap_or(Pages(WASM_MAX_PAGES));
+        let could_not_grow = MemoryError::CouldNotGrow {
+            current,
+            attempted_delta: delta,
+        };
+        match current.checked_add(delta) {
+            Some(new_size) if new_size <= maximum => {}
+            _ => return Err(could_not_grow),
+        }
+
+        // `WebAssembly.Memory.grow` throws when the host can't allocate
+        // the memory, which `js_sys` doesn't catch.
+        let js_memory: &JsValue = self.vm_memory.memory.as_ref();
+        let grow = Reflect::get(js_memory, &"grow".into())
+            .ok()
+            .and_then(|grow| grow.dyn_into::<JsFunction>().ok())
+            .ok_or_else(|| MemoryError::InvalidMemory {
+                reason: "it has no `grow` method".into(),
+            })?;
+        grow.call1(js_memory, &delta.0.into())
+            .map_err(|_| could_not_grow)?;
+        Ok(current)
+    }
+
+    /// Returns the bytes of the memory from `offset` to `offset + len`, if
+    /// they are in bounds.
+    fn checked_subarray(&self, offset: u64, len: usize) -> Result<Uint8Array, MemoryAccessError> {
+        let end = offset
+            .checked_add(len as u64)
+            .ok_or(MemoryAccessError::Overflow)?;
+        let bytes = self.bytes();
+        if end > bytes.length() as u64 {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        Ok(bytes.subarray(offset as u32, end as u32))
+    }
+
+    /// Copies the bytes at `offset` to `buf`, which is filled entirely.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write(0x100, b"hello").unwrap();
+    ///
+    /// let mut buf = [0; 5];
+    /// m.read(0x100, &mut buf).unwrap();
+    /// assert_eq!(&buf, b"hello");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes to read go past the end of the memory,
+    /// in which case `buf` is left unchanged.
+    pub fn read(&self, offset: u64, buf: &mut [u8]) -> Result<(), MemoryAccessError> {
+        self.checked_subarray(offset, buf.len())?.copy_to(buf);
+        Ok(())
+    }
+
+    /// Copies `data` to the memory at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes to write go past the end of the memory,
+    /// in which case the memory is left unchanged.
+    pub fn write(&self, offset: u64, data: &[u8]) -> Result<(), MemoryAccessError> {
+        self.checked_subarray(offset, data.len())?.copy_from(data);
+        Ok(())
+    }
+
+    /// Reads a value of a plain type at `offset`, which doesn't need to be
+    /// aligned.
+    pub fn read_value<T: ValueType>(&self, offset: u64) -> Result<T, MemoryAccessError> {
+        let mut value = MaybeUninit::<T>::zeroed();
+        unsafe {
+            let buf = slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, mem::size_of::<T>());
+            self.read(offset, buf)?;
+            Ok(value.assume_init())
+        }
+    }
+
+    /// Writes a value of a plain type at `offset`, which doesn't need to be
+    /// aligned.
+    pub fn write_value<T: ValueType>(
+        &self,
+        offset: u64,
+        value: T,
+    ) -> Result<(), MemoryAccessError> {
+        let data =
+            unsafe { slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>()) };
+        self.write(offset, data)
+    }
+
+    /// Reads the UTF-8 string of `len` bytes at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes go past the end of the memory, or
+    /// aren't valid UTF-8.
+    pub fn read_utf8(&self, offset: u64, len: usize) -> Result<String, MemoryAccessError> {
+        // Checked first, not to allocate for lengths going past the end
+        let bytes = self.checked_subarray(offset, len)?;
+        let mut buf = vec![0; len];
+        bytes.copy_to(&mut buf);
+        String::from_utf8(buf).map_err(|_| MemoryAccessError::NonUtf8String)
+    }
+
+    /// Reads the nul-terminated UTF-8 string at `offset`, without its
+    /// terminator.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no nul byte between `offset` and the end
+    /// of the memory, or if the string isn't valid UTF-8.
+    pub fn read_cstring(&self, offset: u64) -> Result<String, MemoryAccessError> {
+        let size = self.data_size();
+        let mut bytes = Vec::new();
+        let mut chunk = [0; 256];
+        let mut position = offset;
+        loop {
+            if position >= size {
+                return Err(MemoryAccessError::HeapOutOfBounds);
+            }
+            let len = cmp::min(chunk.len() as u64, size - position) as usize;
+            self.read(position, &mut chunk[..len])?;
+            if let Some(nul) = chunk[..len].iter().position(|&byte| byte == 0) {
+                bytes.extend_from_slice(&chunk[..nul]);
+                break;
+            }
+            bytes.extend_from_slice(&chunk[..len]);
+            position += len as u64;
+        }
+        String::from_utf8(bytes).map_err(|_| MemoryAccessError::NonUtf8String)
+    }
+
+    pub(crate) fn from_vm_export(store: &Store, vm_memory: VMMemory) -> Self {
+        Self {
+            store: store.clone(),
+            vm_memory,
+        }
+    }
+
+    /// Returns whether or not these two memories refer to the same data.
+    pub fn same(&self, other: &Self) -> bool {
+        self.vm_memory.memory == other.vm_memory.memory
+    }
+}
+
+impl<'a> Exportable<'a> for Memory {
+    fn to_export(&self) -> Export {
+        Export::Memory(self.vm_memory.clone())
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Memory(memory) => Ok(memory),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+
+    fn into_weak_instance_ref(&mut self) {
+        // The instances are garbage collected by the host, which handles
+        // the cycles.
+    }
+}
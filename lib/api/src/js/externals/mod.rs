@@ -8,21 +8,20 @@ pub use self::function::{
 };
 
 pub use self::global::Global;
-pub use self::memory::Memory;
+pub use self::memory::{Memory, MemoryAccessError, MemoryError};
 pub use self::table::Table;
 
-use crate::exports::{ExportError, Exportable};
-use crate::store::{Store, StoreObject};
+use crate::js::export::Export;
+use crate::js::exports::{ExportError, Exportable};
+use crate::js::store::{Store, StoreObject};
 use crate::ExternType;
-use loupe::MemoryUsage;
 use std::fmt;
-use wasmer_engine::Export;
 
 /// An `Extern` is the runtime representation of an entity that
 /// can be imported or exported.
 ///
 /// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#external-values>
-#[derive(Clone, MemoryUsage)]
+#[derive(Clone)]
 pub enum Extern {
     /// A external [`Function`].
     Function(Function),
@@ -45,7 +44,7 @@ impl Extern {
         }
     }
 
-    /// Create an `Extern` from an `wasmer_engine::Export`.
+    /// Create an `Extern` from an `Export`.
     pub fn from_vm_export(store: &Store, export: Export) -> Self {
         match export {
             Export::Function(f) => Self::Function(Function::from_vm_export(store, f)),
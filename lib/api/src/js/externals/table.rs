@@ -0,0 +1,160 @@
+use crate::js::export::{Export, VMTable};
+use crate::js::exports::{ExportError, Exportable};
+use crate::js::externals::Extern;
+use crate::js::store::Store;
+use crate::js::types::{as_js, param_from_js, Val};
+use crate::RuntimeError;
+use crate::TableType;
+use crate::ValType;
+use js_sys::{Function as JsFunction, Object, Reflect, WebAssembly};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// A WebAssembly `table` instance.
+///
+/// The `Table` struct is an array-like structure representing a WebAssembly Table,
+/// which stores function references.
+///
+/// A table created by the host or in WebAssembly code will be accessible and
+/// mutable from both host and WebAssembly.
+///
+/// With the `js` backend, the types of the functions in a table aren't
+/// known, so only null elements can be read from it yet.
+///
+/// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#table-instances>
+#[derive(Clone)]
+pub struct Table {
+    store: Store,
+    vm_table: VMTable,
+}
+
+/// Converts an element of a table to the JavaScript value of the host.
+fn table_item(val: &Val) -> Result<JsValue, RuntimeError> {
+    match val {
+        Val::FuncRef(_) => as_js(val),
+        _ => Err(RuntimeError::new("val is not func ref")),
+    }
+}
+
+impl Table {
+    /// Creates a new `Table` with the provided [`TableType`] definition.
+    ///
+    /// All the elements in the table will be set to the `init` value.
+    pub fn new(store: &Store, ty: TableType, init: Val) -> Result<Self, RuntimeError> {
+        if ty.ty != ValType::FuncRef {
+            return Err(RuntimeError::new(format!(
+                "The `js` backend doesn't support {:?} tables yet",
+                ty.ty
+            )));
+        }
+        let item = table_item(&init)?;
+        let descriptor = Object::new();
+        let set = |key: &str, value: JsValue| {
+            Reflect::set(&descriptor, &key.into(), &value).map_err(RuntimeError::from_js)
+        };
+        set("element", "anyfunc".into())?;
+        set("initial", ty.minimum.into())?;
+        if let Some(maximum) = ty.maximum {
+            set("maximum", maximum.into())?;
+        }
+        let table = WebAssembly::Table::new(&descriptor).map_err(RuntimeError::from_js)?;
+
+        for i in 0..table.length() {
+            table
+                .set(i, item.unchecked_ref::<JsFunction>())
+                .map_err(RuntimeError::from_js)?;
+        }
+
+        Ok(Self {
+            store: store.clone(),
+            vm_table: VMTable::new(table, ty),
+        })
+    }
+
+    /// Returns the [`TableType`] of the `Table`.
+    pub fn ty(&self) -> &TableType {
+        &self.vm_table.ty
+    }
+
+    /// Returns the [`Store`] where the `Table` belongs.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Retrieves an element of the table at the provided `index`.
+    ///
+    /// Returns `None` if the index is out of bounds, or if the element isn't
+    /// null, as the type of its function isn't known.
+    pub fn get(&self, index: u32) -> Option<Val> {
+        let item = self.vm_table.table.get(index).ok()?;
+        param_from_js(&ValType::FuncRef, &item.into()).ok()
+    }
+
+    /// Sets an element `val` in the Table at the provided `index`.
+    pub fn set(&self, index: u32, val: Val) -> Result<(), RuntimeError> {
+        let item = table_item(&val)?;
+        self.vm_table
+            .table
+            .set(index, item.unchecked_ref::<JsFunction>())
+            .map_err(RuntimeError::from_js)
+    }
+
+    /// Retrieves the size of the `Table` (in elements)
+    pub fn size(&self) -> u32 {
+        self.vm_table.table.length()
+    }
+
+    /// Grows the size of the `Table` by `delta`, initializating
+    /// the elements with the provided `init` value.
+    ///
+    /// It returns the previous size of the `Table` in case is able
+    /// to grow the Table successfully.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `delta` is out of bounds for the table.
+    pub fn grow(&self, delta: u32, init: Val) -> Result<u32, RuntimeError> {
+        let item = table_item(&init)?;
+        let previous = self
+            .vm_table
+            .table
+            .grow(delta)
+            .map_err(|_| RuntimeError::new(format!("failed to grow table by `{}`", delta)))?;
+        for i in previous..previous + delta {
+            self.vm_table
+                .table
+                .set(i, item.unchecked_ref::<JsFunction>())
+                .map_err(RuntimeError::from_js)?;
+        }
+        Ok(previous)
+    }
+
+    pub(crate) fn from_vm_export(store: &Store, vm_table: VMTable) -> Self {
+        Self {
+            store: store.clone(),
+            vm_table,
+        }
+    }
+
+    /// Returns whether or not these two tables refer to the same data.
+    pub fn same(&self, other: &Self) -> bool {
+        self.vm_table.table == other.vm_table.table
+    }
+}
+
+impl<'a> Exportable<'a> for Table {
+    fn to_export(&self) -> Export {
+        Export::Table(self.vm_table.clone())
+    }
+
+    fn get_self_from_extern(_extern: &'a Extern) -> Result<&'a Self, ExportError> {
+        match _extern {
+            Extern::Table(table) => Ok(table),
+            _ => Err(ExportError::IncompatibleType),
+        }
+    }
+
+    fn into_weak_instance_ref(&mut self) {
+        // The instances are garbage collected by the host, which handles
+        // the cycles.
+    }
+}
@@ -0,0 +1,342 @@
+//! The import module contains the implementation data structures and helper functions used to
+//! manipulate and access a wasm module's imports including memories, tables, globals, and
+//! functions.
+use crate::js::export::Export;
+use crate::js::resolver::NamedResolver;
+use crate::{Extern, Store};
+use indexmap::IndexMap;
+use std::borrow::{Borrow, BorrowMut};
+use std::collections::VecDeque;
+use std::collections::{hash_map::Entry, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// The `LikeNamespace` trait represents objects that act as a namespace for imports.
+/// For example, an `Instance` or `Namespace` could be
+/// considered namespaces that could provide imports to an instance.
+pub trait LikeNamespace {
+    /// Gets an export by name.
+    fn get_namespace_export(&self, name: &str) -> Option<Export>;
+    /// Gets all exports in the namespace.
+    fn get_namespace_exports(&self) -> Vec<(String, Export)>;
+}
+
+/// All of the import data used when instantiating.
+///
+/// It's suggested that you use the [`imports!`] macro
+/// instead of creating an `ImportObject` by hand.
+///
+/// [`imports!`]: macro.imports.html
+///
+/// # Usage:
+/// ```ignore
+/// use wasmer::{Exports, ImportObject, Function};
+///
+/// let mut import_object = ImportObject::new();
+/// let mut env = Exports::new();
+///
+/// env.insert("foo", Function::new_native(foo));
+/// import_object.register("env", env);
+///
+/// fn foo(n: i32) -> i32 {
+///     n
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct ImportObject {
+    map: Arc<Mutex<HashMap<String, Box<dyn LikeNamespace>>>>,
+}
+
+impl ImportObject {
+    /// Create a new `ImportObject`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Gets an export given a module and a name
+    ///
+    /// # Usage
+    /// ```ignore
+    /// # use wasmer_vm::{ImportObject, Instance, Namespace};
+    /// let mut import_object = ImportObject::new();
+    /// import_object.get_export("module", "name");
+    /// ```
+    pub fn get_export(&self, module: &str, name: &str) -> Option<Export> {
+        let guard = self.map.lock().unwrap();
+        let map_ref = guard.borrow();
+        if map_ref.contains_key(module) {
+            let namespace = map_ref[module].as_ref();
+            return namespace.get_namespace_export(name);
+        }
+        None
+    }
+
+    /// Returns true if the ImportObject contains namespace with the provided name.
+    pub fn contains_namespace(&self, name: &str) -> bool {
+        self.map.lock().unwrap().borrow().contains_key(name)
+    }
+
+    /// Returns true if the ImportObject contains an import with the provided
+    /// name in the provided namespace.
+    pub fn contains(&self, module: &str, name: &str) -> bool {
+        self.get_export(module, name).is_some()
+    }
+
+    /// Adds the imports of `other` to this `ImportObject`.
+    ///
+    /// The namespaces which are in both are merged, and the imports of
+    /// `other` replace the ones with the same name. This is useful to
+    /// combine the imports of several host libraries.
+    ///
+    /// # Usage:
+    /// ```
+    /// # use wasmer::{imports, Global, Store, Value};
+    /// # let store = Store::default();
+    /// let mut import_object = imports! {
+    ///     "env" => {
+    ///         "foo" => Global::new(&store, Value::I32(1)),
+    ///     },
+    /// };
+    /// import_object.extend(&imports! {
+    ///     "env" => {
+    ///         "bar" => Global::new(&store, Value::I32(2)),
+    ///     },
+    /// });
+    ///
+    /// assert!(import_object.contains("env", "foo"));
+    /// assert!(import_object.contains("env", "bar"));
+    /// ```
+    pub fn extend(&mut self, other: &ImportObject) {
+        // Collected first, as `other` may share its map with `self`
+        let mut namespaces: IndexMap<String, IndexMap<String, Export>> = IndexMap::new();
+        for ((module, name), export) in other.get_objects() {
+            namespaces.entry(module).or_default().insert(name, export);
+        }
+
+        let mut guard = self.map.lock().unwrap();
+        let map = guard.borrow_mut();
+        for (module, exports) in namespaces {
+            let base = map.remove(&module);
+            map.insert(module, Box::new(ExtendedNamespace { base, exports }));
+        }
+    }
+
+    /// Iterates over the imports, as `(namespace, name, extern)`, to inspect
+    /// them before instantiating a module for example.
+    ///
+    /// The namespaces are sorted by name. The `store` is the one the
+    /// `Extern`s are created in.
+    pub fn iter<'a>(
+        &self,
+        store: &'a Store,
+    ) -> impl Iterator<Item = (String, String, Extern)> + 'a {
+        self.get_objects()
+            .into_iter()
+            .map(move |((module, name), export)| {
+                (module, name, Extern::from_vm_export(store, export))
+            })
+    }
+
+    /// Register anything that implements `LikeNamespace` as a namespace.
+    ///
+    /// # Usage:
+    /// ```ignore
+    /// # use wasmer_vm::{ImportObject, Instance, Namespace};
+    /// let mut import_object = ImportObject::new();
+    ///
+    /// import_object.register("namespace0", instance);
+    /// import_object.register("namespace1", namespace);
+    /// // ...
+    /// ```
+    pub fn register<S, N>(&mut self, name: S, namespace: N) -> Option<Box<dyn LikeNamespace>>
+    where
+        S: Into<String>,
+        N: LikeNamespace + 'static,
+    {
+        let mut guard = self.map.lock().unwrap();
+        let map = guard.borrow_mut();
+
+        match map.entry(name.into()) {
+            Entry::Vacant(empty) => {
+                empty.insert(Box::new(namespace));
+                None
+            }
+            Entry::Occupied(mut occupied) => Some(occupied.insert(Box::new(namespace))),
+        }
+    }
+
+    fn get_objects(&self) -> VecDeque<((String, String), Export)> {
+        let mut out = VecDeque::new();
+        let guard = self.map.lock().unwrap();
+        let map = guard.borrow();
+        let mut namespaces = map.iter().collect::<Vec<_>>();
+        namespaces.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, ns) in namespaces {
+            for (id, exp) in ns.get_namespace_exports() {
+                out.push_back(((name.clone(), id), exp));
+            }
+        }
+        out
+    }
+}
+
+/// A namespace extended by [`ImportObject::extend`], whose new exports
+/// take precedence over the ones of the namespace it replaced.
+struct ExtendedNamespace {
+    base: Option<Box<dyn LikeNamespace>>,
+    exports: IndexMap<String, Export>,
+}
+
+impl LikeNamespace for ExtendedNamespace {
+    fn get_namespace_export(&self, name: &str) -> Option<Export> {
+        self.exports.get(name).cloned().or_else(|| {
+            self.base
+                .as_ref()
+                .and_then(|base| base.get_namespace_export(name))
+        })
+    }
+
+    fn get_namespace_exports(&self) -> Vec<(String, Export)> {
+        let mut out = match &self.base {
+            Some(base) => base.get_namespace_exports(),
+            None => vec![],
+        };
+        out.retain(|(name, _)| !self.exports.contains_key(name));
+        out.extend(
+            self.exports
+                .iter()
+                .map(|(name, export)| (name.clone(), export.clone())),
+        );
+        out
+    }
+}
+
+impl NamedResolver for ImportObject {
+    fn resolve_by_name(&self, module: &str, name: &str) -> Option<Export> {
+        self.get_export(module, name)
+    }
+}
+
+/// Iterator for an `ImportObject`'s exports.
+pub struct ImportObjectIterator {
+    elements: VecDeque<((String, String), Export)>,
+}
+
+impl Iterator for ImportObjectIterator {
+    type Item = ((String, String), Export);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.elements.pop_front()
+    }
+}
+
+impl IntoIterator for ImportObject {
+    type IntoIter = ImportObjectIterator;
+    type Item = ((String, String), Export);
+
+    fn into_iter(self) -> Self::IntoIter {
+        ImportObjectIterator {
+            elements: self.get_objects(),
+        }
+    }
+}
+
+impl fmt::Debug for ImportObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        enum SecretMap {
+            Empty,
+            Some(usize),
+        }
+
+        impl SecretMap {
+            fn new(len: usize) -> Self {
+                if len == 0 {
+                    Self::Empty
+                } else {
+                    Self::Some(len)
+                }
+            }
+        }
+
+        impl fmt::Debug for SecretMap {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    Self::Empty => write!(f, "(empty)"),
+                    Self::Some(len) => write!(f, "(... {} item(s) ...)", len),
+                }
+            }
+        }
+
+        f.debug_struct("ImportObject")
+            .field(
+                "map",
+                &SecretMap::new(self.map.lock().unwrap().borrow().len()),
+            )
+            .finish()
+    }
+}
+
+// The import! macro for ImportObject
+
+/// Generate an [`ImportObject`] easily with the `imports!` macro.
+///
+/// [`ImportObject`]: struct.ImportObject.html
+///
+/// # Usage
+///
+/// ```
+/// # use wasmer::{Function, Store};
+/// # let store = Store::default();
+/// use wasmer::imports;
+///
+/// let import_object = imports! {
+///     "env" => {
+///         "foo" => Function::new_native(&store, foo)
+///     },
+/// };
+///
+/// fn foo(n: i32) -> i32 {
+///     n
+/// }
+/// ```
+#[macro_export]
+macro_rules! imports {
+    ( $( $ns_name:expr => $ns:tt ),* $(,)? ) => {
+        {
+            let mut import_object = $crate::ImportObject::new();
+
+            $({
+                let namespace = $crate::import_namespace!($ns);
+
+                import_object.register($ns_name, namespace);
+            })*
+
+            import_object
+        }
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! namespace {
+    ($( $import_name:expr => $import_item:expr ),* $(,)? ) => {
+        $crate::import_namespace!( { $( $import_name => $import_item, )* } )
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! import_namespace {
+    ( { $( $import_name:expr => $import_item:expr ),* $(,)? } ) => {{
+        let mut namespace = $crate::Exports::new();
+
+        $(
+            namespace.insert($import_name, $import_item);
+        )*
+
+        namespace
+    }};
+
+    ( $namespace:ident ) => {
+        $namespace
+    };
+}
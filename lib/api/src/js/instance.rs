@@ -0,0 +1,205 @@
+use crate::js::error::{ImportError, LinkError};
+use crate::js::export::{Export, VMFunction};
+use crate::js::exports::Exports;
+use crate::js::externals::Extern;
+use crate::js::module::Module;
+use crate::js::resolver::Resolver;
+use crate::js::store::Store;
+use crate::js::trap::RuntimeError;
+use crate::HostEnvInitError;
+use js_sys::{Object, Reflect, WebAssembly};
+use std::fmt;
+use thiserror::Error;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// A WebAssembly Instance is a stateful, executable
+/// instance of a WebAssembly [`Module`].
+///
+/// Instance objects contain all the exported WebAssembly
+/// functions, memories, tables and globals that allow
+/// interacting with WebAssembly.
+///
+/// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#module-instances>
+#[derive(Clone)]
+pub struct Instance {
+    module: Module,
+    /// The exports for an instance.
+    pub exports: Exports,
+}
+
+/// An error while instantiating a module.
+///
+/// This is not a common WebAssembly error, however
+/// we need to differentiate from a `LinkError` (an error
+/// that happens while linking, on instantiation), a
+/// Trap that occurs when calling the WebAssembly module
+/// start function, and an error when initializing the user's
+/// host environments.
+#[derive(Error, Debug)]
+pub enum InstantiationError {
+    /// A linking ocurred during instantiation.
+    #[error(transparent)]
+    Link(LinkError),
+
+    /// A runtime error occured while invoking the start function
+    #[error(transparent)]
+    Start(RuntimeError),
+
+    /// Error occurred when initializing the host environment.
+    #[error(transparent)]
+    HostEnvInitialization(HostEnvInitError),
+}
+
+impl From<HostEnvInitError> for InstantiationError {
+    fn from(other: HostEnvInitError) -> Self {
+        Self::HostEnvInitialization(other)
+    }
+}
+
+impl Instance {
+    /// Creates a new `Instance` from a WebAssembly [`Module`] and a
+    /// set of imports resolved by the [`Resolver`].
+    ///
+    /// The resolver can be anything that implements the [`Resolver`] trait,
+    /// so you can plug custom resolution for the imports, if you wish not
+    /// to use [`ImportObject`].
+    ///
+    /// The [`ImportObject`] is the easiest way to provide imports to the instance.
+    ///
+    /// [`ImportObject`]: crate::ImportObject
+    ///
+    /// ```ignore
+    /// # use wasmer::{imports, Store, Module, Global, Value, Instance};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let module = Module::new(&store, "(module)")?;
+    /// let imports = imports!{
+    ///   "host" => {
+    ///     "var" => Global::new(&store, Value::I32(2))
+    ///   }
+    /// };
+    /// let instance = Instance::new(&module, &imports)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// The function can return [`InstantiationError`]s.
+    ///
+    /// Those are, as defined by the spec:
+    ///  * Link errors that happen when plugging the imports into the instance
+    ///  * Runtime errors that happen when running the module `start` function.
+    pub fn new(module: &Module, resolver: &dyn Resolver) -> Result<Self, InstantiationError> {
+        let store = module.store();
+        let (imports, functions) = Self::resolve_imports(module, resolver)?;
+
+        let instance =
+            WebAssembly::Instance::new(module.js_module(), &imports).map_err(|error| {
+                // The host throws a `LinkError` when it rejects the imports, and
+                // the error of the `start` function otherwise.
+                if error.is_instance_of::<WebAssembly::LinkError>() {
+                    InstantiationError::Link(LinkError::Host(
+                        RuntimeError::from_js(error).message(),
+                    ))
+                } else {
+                    InstantiationError::Start(RuntimeError::from_js(error))
+                }
+            })?;
+
+        let instance_exports = instance.exports();
+        let exports = module
+            .exports()
+            .map(|export_type| {
+                let name = export_type.name().to_string();
+                let js_export =
+                    Reflect::get(&instance_exports, &name.as_str().into()).expect("export");
+                let export = Export::from_js_value(js_export, export_type.ty().clone())
+                    .expect("the host to export an object of the declared type");
+                let extern_ = Extern::from_vm_export(store, export);
+                (name, extern_)
+            })
+            .collect::<Exports>();
+
+        let instance = Self {
+            module: module.clone(),
+            exports,
+        };
+
+        // The environments of the imported host functions are set up
+        // after instantiation but before returning the `Instance` to the
+        // user, like with the `sys` backend.
+        for function in functions {
+            function.init_envs(&instance)?;
+        }
+
+        Ok(instance)
+    }
+
+    /// Resolves the imports of `module`, and returns the JavaScript object
+    /// holding them along with the imported functions.
+    fn resolve_imports(
+        module: &Module,
+        resolver: &dyn Resolver,
+    ) -> Result<(Object, Vec<VMFunction>), InstantiationError> {
+        let imports = Object::new();
+        let mut functions = Vec::new();
+
+        for (index, import_type) in module.imports().enumerate() {
+            let link_error = |error| {
+                InstantiationError::Link(LinkError::Import(
+                    import_type.module().to_string(),
+                    import_type.name().to_string(),
+                    error,
+                ))
+            };
+            let export = resolver
+                .resolve(index as u32, import_type.module(), import_type.name())
+                .ok_or_else(|| link_error(ImportError::UnknownImport(import_type.ty().clone())))?;
+            let export_type = export.ty();
+            if !export_type.is_compatible_with(import_type.ty()) {
+                return Err(link_error(ImportError::IncompatibleType(
+                    import_type.ty().clone(),
+                    export_type,
+                )));
+            }
+
+            let namespace_name: JsValue = import_type.module().into();
+            let namespace = match Reflect::get(&imports, &namespace_name) {
+                Ok(namespace) if namespace.is_object() => namespace,
+                _ => {
+                    let namespace: JsValue = Object::new().into();
+                    Reflect::set(&imports, &namespace_name, &namespace)
+                        .expect("setting a property of a plain object");
+                    namespace
+                }
+            };
+            Reflect::set(&namespace, &import_type.name().into(), export.as_jsvalue())
+                .expect("setting a property of a plain object");
+
+            if let Export::Function(function) = export {
+                functions.push(function);
+            }
+        }
+
+        Ok((imports, functions))
+    }
+
+    /// Gets the [`Module`] associated with this instance.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Returns the [`Store`] where the `Instance` belongs.
+    pub fn store(&self) -> &Store {
+        self.module.store()
+    }
+}
+
+impl fmt::Debug for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Instance")
+            .field("exports", &self.exports)
+            .finish()
+    }
+}
@@ -0,0 +1,56 @@
+mod cell;
+mod error;
+mod export;
+mod exports;
+mod externals;
+mod import_object;
+mod instance;
+mod module;
+mod module_info;
+mod native;
+mod ptr;
+mod resolver;
+mod store;
+mod trap;
+mod types;
+mod wasm_bindgen_polyfill;
+
+#[doc(hidden)]
+pub mod internals {
+    //! We use the internals module for exporting types that are only
+    //! intended to use in internal crates such as the compatibility crate
+    //! `wasmer-vm`. Please don't use any of this types directly, as
+    //! they might change frequently or be removed in the future.
+
+    pub use crate::js::externals::{WithEnv, WithoutEnv};
+}
+
+pub use crate::js::cell::WasmCell;
+pub use crate::js::error::{
+    CompileError, DeserializeError, ImportError, LinkError, SerializeError, WasmError,
+};
+pub use crate::js::export::Export;
+pub use crate::js::exports::{ExportError, Exportable, Exports, ExportsIterator};
+pub use crate::js::externals::{
+    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, MemoryAccessError,
+    MemoryError, Table, WasmTypeList,
+};
+pub use crate::js::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
+pub use crate::js::instance::{Instance, InstantiationError};
+pub use crate::js::module::Module;
+pub use crate::js::native::NativeFunc;
+pub use crate::js::ptr::{Array, Item, WasmPtr};
+pub use crate::js::resolver::{
+    ChainableNamedResolver, NamedResolver, NamedResolverChain, NullResolver, Resolver,
+};
+pub use crate::js::store::{Store, StoreObject};
+pub use crate::js::trap::RuntimeError;
+pub use crate::js::types::{
+    ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
+    TableType, Val, ValType,
+};
+pub use crate::js::types::{Val as Value, ValType as Type};
+pub use wasmer_types::{
+    Atomic, Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, Pages, ValueType,
+    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+};
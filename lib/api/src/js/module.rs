@@ -0,0 +1,208 @@
+use crate::js::error::{CompileError, DeserializeError, SerializeError, WasmError};
+use crate::js::module_info::{self, ModuleInfo};
+use crate::js::store::Store;
+use crate::js::trap::RuntimeError;
+use crate::js::types::{ExportType, ImportType};
+use js_sys::{Uint8Array, WebAssembly};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// An error while compiling a module read from a file.
+#[derive(Error, Debug)]
+pub enum IoCompileError {
+    /// An IO error
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A compilation error
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+}
+
+/// A WebAssembly Module contains stateless WebAssembly
+/// code that has already been compiled and can be instantiated
+/// multiple times.
+///
+/// With the `js` backend, the module is compiled by the host, which
+/// doesn't provide the types of its imports and exports: they're parsed
+/// from its binary.
+///
+/// ## Cloning a module
+///
+/// Cloning a module is cheap: it does a shallow copy of the compiled
+/// contents rather than a deep copy.
+#[derive(Clone)]
+pub struct Module {
+    store: Store,
+    module: WebAssembly::Module,
+    info: Arc<ModuleInfo>,
+    binary: Arc<[u8]>,
+}
+
+// See the comment on the `Send` and `Sync` implementations of `VMMemory`
+unsafe impl Send for Module {}
+unsafe impl Sync for Module {}
+
+impl Module {
+    /// Creates a new WebAssembly Module given the configuration
+    /// in the store.
+    ///
+    /// If the provided bytes are not WebAssembly-like (start with `b"\0asm"`),
+    /// and the "wat" feature is enabled for this crate, this function will try to
+    /// to convert the bytes assuming they correspond to the WebAssembly text
+    /// format.
+    ///
+    /// ## Errors
+    ///
+    /// Creating a WebAssembly module from bytecode can result in a
+    /// [`CompileError`] if the host can't compile it.
+    #[allow(unreachable_code)]
+    pub fn new(store: &Store, bytes: impl AsRef<[u8]>) -> Result<Self, CompileError> {
+        #[cfg(feature = "wat")]
+        let bytes = wat::parse_bytes(bytes.as_ref()).map_err(|e| {
+            CompileError::Wasm(WasmError::Generic(format!(
+                "Error when converting wat: {}",
+                e
+            )))
+        })?;
+
+        Self::from_binary(store, bytes.as_ref())
+    }
+
+    /// Creates a new WebAssembly module from a file path.
+    ///
+    /// This needs a file system, which the host doesn't usually provide.
+    pub fn from_file(store: &Store, file: impl AsRef<Path>) -> Result<Self, IoCompileError> {
+        let file_ref = file.as_ref();
+        let wasm_bytes = std::fs::read(file_ref)?;
+        let mut module = Self::new(store, &wasm_bytes)?;
+        if let Some(filename) = file_ref.to_str() {
+            module.set_name(filename);
+        }
+        Ok(module)
+    }
+
+    /// Creates a new WebAssembly module from a binary.
+    ///
+    /// Opposed to [`Module::new`], this function is not compatible with
+    /// the WebAssembly text format (if the "wat" feature is enabled for
+    /// this crate).
+    pub fn from_binary(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
+        Self::validate(store, binary)?;
+        unsafe { Self::from_binary_unchecked(store, binary) }
+    }
+
+    /// Creates a new WebAssembly module skipping any kind of validation.
+    ///
+    /// # Safety
+    ///
+    /// The host validates the module anyway when it compiles it, so this
+    /// is the same as [`Module::from_binary`] with the `js` backend.
+    pub unsafe fn from_binary_unchecked(
+        store: &Store,
+        binary: &[u8],
+    ) -> Result<Self, CompileError> {
+        let info = module_info::parse(binary)?;
+        let js_bytes = Uint8Array::from(binary);
+        let module = WebAssembly::Module::new(&js_bytes.into())
+            .map_err(|e| CompileError::Validate(RuntimeError::from_js(e).message()))?;
+
+        Ok(Self {
+            store: store.clone(),
+            module,
+            info: Arc::new(info),
+            binary: binary.into(),
+        })
+    }
+
+    /// Validates a new WebAssembly Module given the configuration
+    /// in the Store.
+    ///
+    /// The module is validated by the host, with the features it enables.
+    pub fn validate(_store: &Store, binary: &[u8]) -> Result<(), CompileError> {
+        let js_bytes = Uint8Array::from(binary);
+        match WebAssembly::validate(&js_bytes.into()) {
+            Ok(true) => Ok(()),
+            _ => Err(CompileError::Validate("Invalid Wasm file".to_owned())),
+        }
+    }
+
+    /// Serializes a module into a binary representation that can later be
+    /// processed via [`Module::deserialize`].
+    ///
+    /// The host doesn't give access to the compiled code, so the
+    /// representation is the WebAssembly binary of the module itself.
+    pub fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        Ok(self.binary.to_vec())
+    }
+
+    /// Deserializes a serialized module binary into a `Module`, which
+    /// compiles it again with the `js` backend.
+    ///
+    /// # Safety
+    ///
+    /// The binary is validated by the host when it compiles it, unlike
+    /// with the `sys` backend.
+    pub unsafe fn deserialize(store: &Store, bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Self::from_binary_unchecked(store, bytes).map_err(DeserializeError::Compiler)
+    }
+
+    /// Returns the name of the current module.
+    ///
+    /// This name is normally set in the WebAssembly bytecode by some
+    /// compilers, but can be also overwritten using the [`Module::set_name`] method.
+    pub fn name(&self) -> Option<&str> {
+        self.info.name.as_deref()
+    }
+
+    /// Sets the name of the current module.
+    /// This is normally useful for stacktraces and debugging.
+    ///
+    /// It will return `true` if the module name was changed successfully,
+    /// and return `false` otherwise (in case the module is already
+    /// instantiated).
+    pub fn set_name(&mut self, name: &str) -> bool {
+        Arc::get_mut(&mut self.info)
+            .map(|info| {
+                info.name = Some(name.to_string());
+                true
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns an iterator over the imported types in the Module.
+    ///
+    /// The order of the imports is guaranteed to be the same as in the
+    /// WebAssembly bytecode.
+    pub fn imports<'a>(&'a self) -> impl ExactSizeIterator<Item = ImportType> + 'a {
+        self.info.imports.iter().cloned()
+    }
+
+    /// Returns an iterator over the exported types in the Module.
+    ///
+    /// The order of the exports is guaranteed to be the same as in the
+    /// WebAssembly bytecode.
+    pub fn exports<'a>(&'a self) -> impl ExactSizeIterator<Item = ExportType> + 'a {
+        self.info.exports.iter().cloned()
+    }
+
+    /// Returns the [`Store`] where the `Instance` belongs.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// Returns the module compiled by the host.
+    pub(crate) fn js_module(&self) -> &WebAssembly::Module {
+        &self.module
+    }
+}
+
+impl fmt::Debug for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Module")
+            .field("name", &self.name())
+            .finish()
+    }
+}
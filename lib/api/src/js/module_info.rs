@@ -0,0 +1,236 @@
+//! Parses the types of the imports and exports of a module, which the host
+//! doesn't provide.
+
+use crate::js::error::WasmError;
+use crate::js::types::{ExportType, ImportType};
+use wasmer_types::{ExternType, FunctionType, GlobalType, MemoryType, Pages, TableType, Type};
+use wasmparser::{
+    ExternalKind, ImportSectionEntryType, MemoryType as WPMemoryType, NameSectionReader, Parser,
+    Payload, TypeDef,
+};
+
+/// The types of the imports and exports of a module, and its name.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ModuleInfo {
+    /// The name of the module, from its name section.
+    pub name: Option<String>,
+    /// The imports, in the order of the module.
+    pub imports: Vec<ImportType>,
+    /// The exports, in the order of the module.
+    pub exports: Vec<ExportType>,
+}
+
+impl From<wasmparser::BinaryReaderError> for WasmError {
+    fn from(original: wasmparser::BinaryReaderError) -> Self {
+        Self::InvalidWebAssembly {
+            message: original.message().into(),
+            offset: original.offset(),
+        }
+    }
+}
+
+fn wptype_to_type(ty: wasmparser::Type) -> Result<Type, WasmError> {
+    match ty {
+        wasmparser::Type::I32 => Ok(Type::I32),
+        wasmparser::Type::I64 => Ok(Type::I64),
+        wasmparser::Type::F32 => Ok(Type::F32),
+        wasmparser::Type::F64 => Ok(Type::F64),
+        wasmparser::Type::V128 => Ok(Type::V128),
+        wasmparser::Type::ExternRef => Ok(Type::ExternRef),
+        wasmparser::Type::FuncRef => Ok(Type::FuncRef),
+        ty => Err(WasmError::Unsupported(format!(
+            "wptype_to_type: wasmparser type {:?}",
+            ty
+        ))),
+    }
+}
+
+fn memory_type(memory: WPMemoryType) -> Result<MemoryType, WasmError> {
+    match memory {
+        WPMemoryType::M32 { limits, shared } => Ok(MemoryType {
+            minimum: Pages(limits.initial),
+            maximum: limits.maximum.map(Pages),
+            shared,
+        }),
+        WPMemoryType::M64 { .. } => Err(WasmError::Unsupported(
+            "64bit memory not implemented yet".into(),
+        )),
+    }
+}
+
+fn table_type(table: wasmparser::TableType) -> Result<TableType, WasmError> {
+    Ok(TableType {
+        ty: wptype_to_type(table.element_type)?,
+        minimum: table.limits.initial,
+        maximum: table.limits.maximum,
+    })
+}
+
+fn global_type(global: wasmparser::GlobalType) -> Result<GlobalType, WasmError> {
+    Ok(GlobalType {
+        ty: wptype_to_type(global.content_type)?,
+        mutability: global.mutable.into(),
+    })
+}
+
+/// Parses the types of the imports and exports of the module `data`.
+pub(crate) fn parse(data: &[u8]) -> Result<ModuleInfo, WasmError> {
+    let mut info = ModuleInfo::default();
+    let mut signatures = Vec::new();
+    // The index spaces, imports first
+    let mut functions = Vec::new();
+    let mut tables = Vec::new();
+    let mut memories = Vec::new();
+    let mut globals = Vec::new();
+    let mut exports = Vec::new();
+
+    for payload in Parser::new(0).parse_all(data) {
+        match payload? {
+            Payload::TypeSection(types) => {
+                for entry in types {
+                    match entry? {
+                        TypeDef::Func(func) => {
+                            let params = func
+                                .params
+                                .iter()
+                                .map(|ty| wptype_to_type(*ty))
+                                .collect::<Result<Vec<_>, _>>()?;
+                            let returns = func
+                                .returns
+                                .iter()
+                                .map(|ty| wptype_to_type(*ty))
+                                .collect::<Result<Vec<_>, _>>()?;
+                            signatures.push(FunctionType::new(params, returns));
+                        }
+                        _ => {
+                            return Err(WasmError::Unsupported(
+                                "module linking not implemented yet".into(),
+                            ))
+                        }
+                    }
+                }
+            }
+
+            Payload::ImportSection(imports) => {
+                for entry in imports {
+                    let import = entry?;
+                    let ty = match import.ty {
+                        ImportSectionEntryType::Function(sig) => {
+                            let ty = signatures.get(sig as usize).cloned().ok_or_else(|| {
+                                WasmError::Generic(format!("unknown signature {}", sig))
+                            })?;
+                            functions.push(ty.clone());
+                            ExternType::Function(ty)
+                        }
+                        ImportSectionEntryType::Table(table) => {
+                            let ty = table_type(table)?;
+                            tables.push(ty);
+                            ExternType::Table(ty)
+                        }
+                        ImportSectionEntryType::Memory(memory) => {
+                            let ty = memory_type(memory)?;
+                            memories.push(ty);
+                            ExternType::Memory(ty)
+                        }
+                        ImportSectionEntryType::Global(global) => {
+                            let ty = global_type(global)?;
+                            globals.push(ty);
+                            ExternType::Global(ty)
+                        }
+                        ImportSectionEntryType::Module(_)
+                        | ImportSectionEntryType::Instance(_)
+                        | ImportSectionEntryType::Event(_) => {
+                            return Err(WasmError::Unsupported(
+                                "module linking not implemented yet".into(),
+                            ))
+                        }
+                    };
+                    info.imports.push(ImportType::new(
+                        import.module,
+                        import.field.unwrap_or_default(),
+                        ty,
+                    ));
+                }
+            }
+
+            Payload::FunctionSection(declarations) => {
+                for entry in declarations {
+                    let sig = entry?;
+                    let ty = signatures
+                        .get(sig as usize)
+                        .cloned()
+                        .ok_or_else(|| WasmError::Generic(format!("unknown signature {}", sig)))?;
+                    functions.push(ty);
+                }
+            }
+
+            Payload::TableSection(declarations) => {
+                for entry in declarations {
+                    tables.push(table_type(entry?)?);
+                }
+            }
+
+            Payload::MemorySection(declarations) => {
+                for entry in declarations {
+                    memories.push(memory_type(entry?)?);
+                }
+            }
+
+            Payload::GlobalSection(declarations) => {
+                for entry in declarations {
+                    globals.push(global_type(entry?.ty)?);
+                }
+            }
+
+            Payload::ExportSection(declarations) => {
+                for entry in declarations {
+                    let export = entry?;
+                    exports.push((export.field, export.kind, export.index as usize));
+                }
+            }
+
+            Payload::CustomSection {
+                name: "name",
+                data,
+                data_offset,
+                ..
+            } => {
+                let mut names = NameSectionReader::new(data, data_offset)?;
+                while let Ok(subsection) = names.read() {
+                    if let wasmparser::Name::Module(module) = subsection {
+                        if let Ok(name) = module.get_name() {
+                            info.name = Some(name.to_string());
+                        }
+                    }
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    // The exports are resolved at the end, as they can refer to anything
+    // declared in the module
+    for (name, kind, index) in exports {
+        let unknown = || WasmError::Generic(format!("unknown export {:?}", name));
+        let ty = match kind {
+            ExternalKind::Function => {
+                ExternType::Function(functions.get(index).cloned().ok_or_else(unknown)?)
+            }
+            ExternalKind::Table => ExternType::Table(*tables.get(index).ok_or_else(unknown)?),
+            ExternalKind::Memory => ExternType::Memory(*memories.get(index).ok_or_else(unknown)?),
+            ExternalKind::Global => ExternType::Global(*globals.get(index).ok_or_else(unknown)?),
+            ExternalKind::Type
+            | ExternalKind::Module
+            | ExternalKind::Instance
+            | ExternalKind::Event => {
+                return Err(WasmError::Unsupported(
+                    "module linking not implemented yet".into(),
+                ))
+            }
+        };
+        info.exports.push(ExportType::new(name, ty));
+    }
+
+    Ok(info)
+}
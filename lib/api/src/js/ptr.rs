@@ -0,0 +1,266 @@
+//! Types for a reusable pointer abstraction for accessing Wasm linear memory.
+//!
+//! This abstraction is safe: it ensures the memory is in bounds and that the pointer
+//! is aligned (avoiding undefined behavior).
+//!
+//! Therefore, you should use this abstraction whenever possible to avoid memory
+//! related bugs when implementing an ABI.
+
+use crate::js::{externals::Memory, FromToNativeWasmType, WasmCell};
+use std::{fmt, marker::PhantomData, mem};
+use wasmer_types::ValueType;
+
+/// The `Array` marker type. This type can be used like `WasmPtr<T, Array>`
+/// to get access to methods
+pub struct Array;
+/// The `Item` marker type. This is the default and does not usually need to be
+/// specified.
+pub struct Item;
+
+/// A zero-cost type that represents a pointer to something in Wasm linear
+/// memory.
+///
+/// This type can be used directly in the host function arguments:
+/// ```ignore
+/// # use wasmer::Memory;
+/// # use wasmer::WasmPtr;
+/// pub fn host_import(memory: Memory, ptr: WasmPtr<u32>) {
+///     let derefed_ptr = ptr.deref(&memory).expect("pointer in bounds");
+///     let inner_val: u32 = derefed_ptr.get();
+///     println!("Got {} from Wasm memory address 0x{:X}", inner_val, ptr.offset());
+///     // update the value being pointed to
+///     derefed_ptr.set(inner_val + 1);
+/// }
+/// ```
+///
+/// This type can also be used with primitive-filled structs, but be careful of
+/// guarantees required by `ValueType`. The derive macro checks them for
+/// `#[repr(C)]` structs, which may contain other `WasmPtr`s:
+/// ```ignore
+/// # use wasmer::{Array, Memory, ValueType, WasmPtr};
+/// #[derive(Copy, Clone, ValueType)]
+/// #[repr(C)]
+/// struct Buffer {
+///     data: WasmPtr<u8, Array>,
+///     len: u32,
+/// }
+///
+/// fn read_buffer(memory: &Memory, ptr: WasmPtr<Buffer>) -> Option<Vec<u8>> {
+///     let buffer = ptr.deref(memory)?.get();
+///     let bytes = buffer.data.slice(memory, buffer.len)?;
+///     Some(bytes.iter().map(|byte| byte.get()).collect())
+/// }
+/// ```
+///
+/// `ValueType` may also be implemented manually:
+/// ```ignore
+/// # use wasmer::Memory;
+/// # use wasmer::WasmPtr;
+/// # use wasmer::ValueType;
+///
+/// #[derive(Copy, Clone, Debug)]
+/// #[repr(C)]
+/// struct V3 {
+///     x: f32,
+///     y: f32,
+///     z: f32
+/// }
+/// // This is safe as the 12 bytes represented by this struct
+/// // are valid for all bit combinations.
+/// unsafe impl ValueType for V3 {
+/// }
+///
+/// fn update_vector_3(memory: Memory, ptr: WasmPtr<V3>) {
+///     let derefed_ptr = ptr.deref(&memory).expect("pointer in bounds");
+///     let mut inner_val: V3 = derefed_ptr.get();
+///     println!("Got {:?} from Wasm memory address 0x{:X}", inner_val, ptr.offset());
+///     // update the value being pointed to
+///     inner_val.x = 10.4;
+///     derefed_ptr.set(inner_val);
+/// }
+/// ```
+#[repr(transparent)]
+pub struct WasmPtr<T: Copy, Ty = Item> {
+    offset: u32,
+    _phantom: PhantomData<(T, Ty)>,
+}
+
+/// Methods relevant to all types of `WasmPtr`.
+impl<T: Copy, Ty> WasmPtr<T, Ty> {
+    /// Create a new `WasmPtr` at the given offset.
+    #[inline]
+    pub fn new(offset: u32) -> Self {
+        Self {
+            offset,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the offset into Wasm linear memory for this `WasmPtr`.
+    #[inline]
+    pub fn offset(self) -> u32 {
+        self.offset
+    }
+}
+
+#[inline(always)]
+fn align_pointer(ptr: usize, align: usize) -> usize {
+    // clears bits below aligment amount (assumes power of 2) to align pointer
+    debug_assert!(align.count_ones() == 1);
+    ptr & !(align - 1)
+}
+
+/// Methods for `WasmPtr`s to data that can be dereferenced, namely to types
+/// that implement [`ValueType`], meaning that they're valid for all possible
+/// bit patterns.
+impl<T: Copy + ValueType> WasmPtr<T, Item> {
+    /// Dereference the `WasmPtr` getting access to a [`WasmCell`] allowing
+    /// for reading and mutating of the inner value.
+    #[inline]
+    pub fn deref<'a>(self, memory: &'a Memory) -> Option<WasmCell<'a, T>> {
+        if (self.offset as usize) + mem::size_of::<T>() > memory.size().bytes().0
+            || mem::size_of::<T>() == 0
+        {
+            return None;
+        }
+        let offset = align_pointer(self.offset as usize, mem::align_of::<T>());
+        Some(WasmCell::new(memory, offset as u64))
+    }
+}
+
+/// Methods for `WasmPtr`s to arrays of data that can be dereferenced, namely to
+/// types that implement [`ValueType`], meaning that they're valid for all
+/// possible bit patterns.
+impl<T: Copy + ValueType> WasmPtr<T, Array> {
+    /// Dereference the `WasmPtr` getting access to a [`WasmCell`] for each
+    /// of the `length` items from `index`, allowing for reading and mutating
+    /// of the inner values.
+    #[inline]
+    pub fn deref<'a>(
+        self,
+        memory: &'a Memory,
+        index: u32,
+        length: u32,
+    ) -> Option<Vec<WasmCell<'a, T>>> {
+        // gets the size of the item in the array with padding added such that
+        // for any index, we will always result an aligned memory access
+        let item_size = mem::size_of::<T>();
+        let slice_full_len = index as usize + length as usize;
+        let memory_size = memory.size().bytes().0;
+
+        if (self.offset as usize) + (item_size * slice_full_len) > memory_size
+            || self.offset as usize >= memory_size
+            || mem::size_of::<T>() == 0
+        {
+            return None;
+        }
+
+        let offset = align_pointer(self.offset as usize, mem::align_of::<T>());
+        Some(
+            (index as usize..slice_full_len)
+                .map(|i| WasmCell::new(memory, (offset + i * item_size) as u64))
+                .collect(),
+        )
+    }
+
+    /// Dereference the `len` items starting at this `WasmPtr`, like
+    /// [`WasmPtr::deref`] from index 0.
+    #[inline]
+    pub fn slice<'a>(self, memory: &'a Memory, len: u32) -> Option<Vec<WasmCell<'a, T>>> {
+        self.deref(memory, 0, len)
+    }
+
+    /// Get a `WasmPtr` to the item at `index` in the array, or `None` if its
+    /// offset doesn't fit in 32 bits.
+    ///
+    /// It isn't checked against the bounds of a memory until it is
+    /// dereferenced.
+    #[inline]
+    pub fn index(self, index: u32) -> Option<WasmPtr<T, Item>> {
+        let offset = (mem::size_of::<T>() as u32)
+            .checked_mul(index)
+            .and_then(|relative| self.offset.checked_add(relative))?;
+        Some(WasmPtr::new(offset))
+    }
+
+    /// Get a UTF-8 `String` from the `WasmPtr` with the given length.
+    pub fn get_utf8_string(self, memory: &Memory, str_len: u32) -> Option<String> {
+        let memory_size = memory.size().bytes().0;
+        if self.offset as usize + str_len as usize > memory.size().bytes().0
+            || self.offset as usize >= memory_size
+        {
+            return None;
+        }
+
+        memory.read_utf8(self.offset as u64, str_len as usize).ok()
+    }
+
+    /// Get a UTF-8 `String` from the `WasmPtr`, where the string is nul-terminated.
+    ///
+    /// Note that this does not account for UTF-8 strings that _contain_ nul themselves,
+    /// [`WasmPtr::get_utf8_string`] has to be used for those.
+    pub fn get_utf8_string_with_nul(self, memory: &Memory) -> Option<String> {
+        memory.read_cstring(self.offset as u64).ok()
+    }
+
+    /// Get a UTF-8 `String` from the `WasmPtr`, where the string is
+    /// nul-terminated within its first `max_len` bytes.
+    ///
+    /// Unlike [`WasmPtr::get_utf8_string_with_nul`], a missing terminator
+    /// doesn't make it read the whole memory, which guards against
+    /// untrusted guests.
+    pub fn get_utf8_string_with_nul_max(self, memory: &Memory, max_len: u32) -> Option<String> {
+        let memory_size = memory.size().bytes().0;
+        if self.offset as usize >= memory_size {
+            return None;
+        }
+        let start = self.offset as usize;
+        let end = memory_size.min(start + max_len as usize);
+        let mut bytes = vec![0; end - start];
+        memory.read(start as u64, &mut bytes).ok()?;
+        let len = bytes.iter().position(|&byte| byte == 0)?;
+        bytes.truncate(len);
+        String::from_utf8(bytes).ok()
+    }
+}
+
+unsafe impl<T: Copy, Ty> FromToNativeWasmType for WasmPtr<T, Ty> {
+    type Native = i32;
+
+    fn to_native(self) -> Self::Native {
+        self.offset as i32
+    }
+    fn from_native(n: Self::Native) -> Self {
+        Self {
+            offset: n as u32,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+unsafe impl<T: Copy, Ty> ValueType for WasmPtr<T, Ty> {}
+
+impl<T: Copy, Ty> Clone for WasmPtr<T, Ty> {
+    fn clone(&self) -> Self {
+        Self {
+            offset: self.offset,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy, Ty> Copy for WasmPtr<T, Ty> {}
+
+impl<T: Copy, Ty> PartialEq for WasmPtr<T, Ty> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+
+impl<T: Copy, Ty> Eq for WasmPtr<T, Ty> {}
+
+impl<T: Copy, Ty> fmt::Debug for WasmPtr<T, Ty> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WasmPtr({:#x})", self.offset)
+    }
+}
@@ -0,0 +1,127 @@
+//! Define the `Resolver` trait, allowing custom resolution for external
+//! references.
+//!
+//! These are the same traits as in `wasmer-engine`, which isn't available
+//! with the `js` backend.
+
+use crate::js::export::Export;
+
+/// Import resolver connects imports with available exported values.
+pub trait Resolver {
+    /// Resolves an import a WebAssembly module to an export it's hooked up to.
+    ///
+    /// The `index` provided is the index of the import in the wasm module
+    /// that's being resolved. For example 1 means that it's the second import
+    /// listed in the wasm module.
+    ///
+    /// The `module` and `field` arguments provided are the module/field names
+    /// listed on the import itself.
+    fn resolve(&self, _index: u32, module: &str, field: &str) -> Option<Export>;
+}
+
+/// Import resolver connects imports with available exported values.
+///
+/// This is a specific subtrait for [`Resolver`] for those users who don't
+/// care about the `index`, but only about the `module` and `field` for
+/// the resolution.
+pub trait NamedResolver {
+    /// Resolves an import a WebAssembly module to an export it's hooked up to.
+    ///
+    /// It receives the `module` and `field` names and return the [`Export`] in
+    /// case it's found.
+    fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export>;
+}
+
+// All NamedResolvers should extend `Resolver`.
+impl<T: NamedResolver> Resolver for T {
+    /// By default this method will be calling [`NamedResolver::resolve_by_name`],
+    /// dismissing the provided `index`.
+    fn resolve(&self, _index: u32, module: &str, field: &str) -> Option<Export> {
+        self.resolve_by_name(module, field)
+    }
+}
+
+impl<T: NamedResolver> NamedResolver for &T {
+    fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
+        (**self).resolve_by_name(module, field)
+    }
+}
+
+impl NamedResolver for Box<dyn NamedResolver> {
+    fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
+        (**self).resolve_by_name(module, field)
+    }
+}
+
+impl NamedResolver for () {
+    /// Always returns `None`.
+    fn resolve_by_name(&self, _module: &str, _field: &str) -> Option<Export> {
+        None
+    }
+}
+
+/// `Resolver` implementation that always resolves to `None`. Equivalent to `()`.
+pub struct NullResolver {}
+
+impl Resolver for NullResolver {
+    fn resolve(&self, _idx: u32, _module: &str, _field: &str) -> Option<Export> {
+        None
+    }
+}
+
+/// A [`Resolver`] that links two resolvers together in a chain.
+pub struct NamedResolverChain<A: NamedResolver, B: NamedResolver> {
+    a: A,
+    b: B,
+}
+
+/// A trait for chaining resolvers together.
+pub trait ChainableNamedResolver: NamedResolver + Sized {
+    /// Chain a resolver in front of the current resolver.
+    ///
+    /// This will cause the second resolver to override the first.
+    fn chain_front<U>(self, other: U) -> NamedResolverChain<U, Self>
+    where
+        U: NamedResolver,
+    {
+        NamedResolverChain { a: other, b: self }
+    }
+
+    /// Chain a resolver behind the current resolver.
+    ///
+    /// This will cause the first resolver to override the second.
+    fn chain_back<U>(self, other: U) -> NamedResolverChain<Self, U>
+    where
+        U: NamedResolver,
+    {
+        NamedResolverChain { a: self, b: other }
+    }
+}
+
+// We give these chain methods to all types implementing NamedResolver
+impl<T: NamedResolver> ChainableNamedResolver for T {}
+
+impl<A, B> NamedResolver for NamedResolverChain<A, B>
+where
+    A: NamedResolver,
+    B: NamedResolver,
+{
+    fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
+        self.a
+            .resolve_by_name(module, field)
+            .or_else(|| self.b.resolve_by_name(module, field))
+    }
+}
+
+impl<A, B> Clone for NamedResolverChain<A, B>
+where
+    A: NamedResolver + Clone,
+    B: NamedResolver + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            a: self.a.clone(),
+            b: self.b.clone(),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+
+/// The store represents all global state that can be manipulated by
+/// WebAssembly programs. It consists of the runtime representation
+/// of all instances of functions, tables, memories, and globals that
+/// have been allocated during the lifetime of the abstract machine.
+///
+/// With the `js` backend, all that state is held by the host, and the
+/// modules are compiled by it, so the `Store` holds nothing: it's there
+/// for the embedding code to be the same with both backends.
+///
+/// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#store>
+#[derive(Clone)]
+pub struct Store {}
+
+impl Store {
+    /// Creates a new `Store`.
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Checks whether two stores are identical. All the stores share the
+    /// state of the host, so they're always identical.
+    pub fn same(_a: &Self, _b: &Self) -> bool {
+        true
+    }
+}
+
+impl PartialEq for Store {
+    fn eq(&self, other: &Self) -> bool {
+        Self::same(self, other)
+    }
+}
+
+impl Default for Store {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Store {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Store").finish()
+    }
+}
+
+/// A trait represinting any object that lives in the `Store`.
+pub trait StoreObject {
+    /// Return true if the object `Store` is the same as the provided `Store`.
+    fn comes_from_same_store(&self, _store: &Store) -> bool {
+        true
+    }
+}
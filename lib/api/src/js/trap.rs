@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// A struct representing an aborted instruction execution, with a message
+/// indicating the cause.
+#[derive(Clone)]
+pub struct RuntimeError {
+    inner: Arc<RuntimeErrorSource>,
+}
+
+/// The source of the `RuntimeError`.
+#[derive(Debug)]
+enum RuntimeErrorSource {
+    Generic(String),
+    User(Box<dyn Error + Send + Sync>),
+    /// An exception thrown by the host, like the traps of the Wasm code,
+    /// with its message
+    Js(String),
+}
+
+impl fmt::Display for RuntimeErrorSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Generic(s) => write!(f, "{}", s),
+            Self::User(s) => write!(f, "{}", s),
+            Self::Js(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+thread_local! {
+    /// The error of the host function being unwound, which the JavaScript
+    /// exception thrown for it can't carry. It's taken back by the first
+    /// call from Rust which catches the exception.
+    static PENDING: RefCell<Option<RuntimeError>> = RefCell::new(None);
+}
+
+impl RuntimeError {
+    /// Creates a new generic `RuntimeError` with the given `message`.
+    pub fn new<I: Into<String>>(message: I) -> Self {
+        Self {
+            inner: Arc::new(RuntimeErrorSource::Generic(message.into())),
+        }
+    }
+
+    /// Creates a `RuntimeError` from a custom user `Error`.
+    pub(crate) fn user(error: Box<dyn Error + Send + Sync>) -> Self {
+        match error.downcast::<Self>() {
+            // A `RuntimeError` returned by a host function is kept as is
+            Ok(runtime_error) => *runtime_error,
+            Err(error) => Self {
+                inner: Arc::new(RuntimeErrorSource::User(error)),
+            },
+        }
+    }
+
+    /// Raises a custom user Error
+    ///
+    /// The error is thrown to the host as a JavaScript exception, so the
+    /// Rust frames it goes through aren't unwound: their values aren't
+    /// dropped.
+    pub fn raise(error: Box<dyn Error + Send + Sync>) -> ! {
+        let js_error = Self::user(error).into_js();
+        wasm_bindgen::throw_val(js_error)
+    }
+
+    /// Turns the error into the exception to throw to the host.
+    pub(crate) fn into_js(self) -> JsValue {
+        let js_error = js_sys::Error::new(&self.message());
+        PENDING.with(|pending| *pending.borrow_mut() = Some(self));
+        js_error.into()
+    }
+
+    /// Gets the error from an exception caught from the host.
+    pub(crate) fn from_js(value: JsValue) -> Self {
+        if let Some(error) = PENDING.with(|pending| pending.borrow_mut().take()) {
+            return error;
+        }
+        let message = match value.dyn_ref::<js_sys::Error>() {
+            Some(error) => String::from(error.message()),
+            None => value.as_string().unwrap_or_else(|| format!("{:?}", value)),
+        };
+        Self {
+            inner: Arc::new(RuntimeErrorSource::Js(message)),
+        }
+    }
+
+    /// Returns a reference the `message` stored in `Trap`.
+    pub fn message(&self) -> String {
+        format!("{}", self.inner)
+    }
+
+    /// Attempts to downcast the `RuntimeError` to a concrete type.
+    pub fn downcast<T: Error + 'static>(self) -> Result<T, Self> {
+        match Arc::try_unwrap(self.inner) {
+            // We only try to downcast user errors
+            Ok(RuntimeErrorSource::User(err)) if err.is::<T>() => Ok(*err.downcast::<T>().unwrap()),
+            Ok(inner) => Err(Self {
+                inner: Arc::new(inner),
+            }),
+            Err(inner) => Err(Self { inner }),
+        }
+    }
+
+    /// Attempts to downcast the `RuntimeError` to a reference of a
+    /// concrete type, which works on a shared error unlike
+    /// [`RuntimeError::downcast`].
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        match self.inner.as_ref() {
+            // We only try to downcast user errors
+            RuntimeErrorSource::User(err) => err.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the `RuntimeError` is the same as T
+    pub fn is<T: Error + 'static>(&self) -> bool {
+        match self.inner.as_ref() {
+            RuntimeErrorSource::User(err) => err.is::<T>(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuntimeError")
+            .field("source", &self.inner)
+            .finish()
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RuntimeError: {}", self.message())
+    }
+}
+
+impl Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.inner.as_ref() {
+            RuntimeErrorSource::User(err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::js::externals::Function;
+use crate::js::store::StoreObject;
+use crate::js::RuntimeError;
+use wasm_bindgen::{JsCast, JsValue};
+use wasmer_types::Value;
+pub use wasmer_types::{
+    ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
+    TableType, Type as ValType,
+};
+
+/// WebAssembly computations manipulate values of basic value types:
+/// * Integers (32 or 64 bit width)
+/// * Floating-point (32 or 64 bit width)
+/// * Vectors (128 bits, with 32 or 64 bit lanes)
+///
+/// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#values>
+pub type Val = Value<Function>;
+
+impl StoreObject for Val {}
+
+impl From<Function> for Val {
+    fn from(val: Function) -> Self {
+        Self::FuncRef(Some(val))
+    }
+}
+
+/// The error of the values the `js` backend doesn't support yet.
+fn unsupported(ty: ValType) -> RuntimeError {
+    RuntimeError::new(format!(
+        "The `js` backend doesn't support {:?} values yet",
+        ty
+    ))
+}
+
+/// Converts a value to the JavaScript value passed to the host for it.
+///
+/// `i64`s are passed as `BigInt`s, like the JS-BigInt integration of the
+/// host does, and the `externref`s and `v128`s aren't supported yet.
+pub(crate) fn as_js(val: &Val) -> Result<JsValue, RuntimeError> {
+    Ok(match val {
+        Val::I32(i) => JsValue::from_f64(*i as f64),
+        Val::I64(i) => js_sys::BigInt::from(*i).into(),
+        Val::F32(f) => JsValue::from_f64(*f as f64),
+        Val::F64(f) => JsValue::from_f64(*f),
+        Val::FuncRef(Some(func)) => func.exported.function.clone().into(),
+        Val::FuncRef(None) => JsValue::NULL,
+        Val::ExternRef(_) | Val::V128(_) => return Err(unsupported(val.ty())),
+    })
+}
+
+/// Converts a JavaScript value received from the host to a value of the
+/// type `ty`.
+///
+/// Only null `funcref`s can be received, as the types of the functions
+/// aren't known.
+pub(crate) fn param_from_js(ty: &ValType, js_val: &JsValue) -> Result<Val, RuntimeError> {
+    let mismatch = || {
+        RuntimeError::new(format!(
+            "Expected a {:?} value, but the host passed {:?}",
+            ty, js_val
+        ))
+    };
+    let number = || js_val.as_f64().ok_or_else(mismatch);
+    Ok(match ty {
+        ValType::I32 => Val::I32(number()? as i32),
+        ValType::I64 => Val::I64(match js_val.dyn_ref::<js_sys::BigInt>() {
+            Some(bigint) => bigint
+                .to_string(10)
+                .ok()
+                .and_then(|s| String::from(s).parse().ok())
+                .ok_or_else(mismatch)?,
+            // The host may pass a `Number` rather than a `BigInt`
+            None => number()? as i64,
+        }),
+        ValType::F32 => Val::F32(number()? as f32),
+        ValType::F64 => Val::F64(number()?),
+        ValType::FuncRef if js_val.is_null() => Val::FuncRef(None),
+        ValType::FuncRef | ValType::ExternRef | ValType::V128 => return Err(unsupported(*ty)),
+    })
+}
+
+/// Converts a value to its binary representation, as used by the native
+/// arrays of [`WasmTypeList`](crate::WasmTypeList).
+pub(crate) fn val_to_binary(val: &Val) -> Result<i128, RuntimeError> {
+    Ok(match val {
+        Val::I32(i) => *i as i128,
+        Val::I64(i) => *i as i128,
+        Val::F32(f) => f.to_bits() as i128,
+        Val::F64(f) => f.to_bits() as i128,
+        Val::V128(v) => *v as i128,
+        Val::FuncRef(_) | Val::ExternRef(_) => return Err(unsupported(val.ty())),
+    })
+}
+
+/// Converts the binary representation of a value of the type `ty` back to
+/// the value.
+pub(crate) fn binary_to_val(ty: &ValType, binary: i128) -> Result<Val, RuntimeError> {
+    Ok(match ty {
+        ValType::I32 => Val::I32(binary as i32),
+        ValType::I64 => Val::I64(binary as i64),
+        ValType::F32 => Val::F32(f32::from_bits(binary as u32)),
+        ValType::F64 => Val::F64(f64::from_bits(binary as u64)),
+        ValType::V128 => Val::V128(binary as u128),
+        ValType::FuncRef | ValType::ExternRef => return Err(unsupported(*ty)),
+    })
+}
@@ -0,0 +1,32 @@
+//! Bindings to the parts of the `WebAssembly` JavaScript API which are
+//! missing from `js-sys`.
+
+use js_sys::Object;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    /// The `WebAssembly.Global()` constructor creates a new `Global` object
+    /// representing a global variable instance, accessible from both
+    /// JavaScript and importable/exportable across one or more
+    /// `WebAssembly.Module` instances.
+    ///
+    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/WebAssembly/Global)
+    #[wasm_bindgen(js_namespace = WebAssembly, extends = Object, typescript_type = "WebAssembly.Global")]
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub type Global;
+
+    /// Creates a new `Global` with the given descriptor, like
+    /// `{ value: "i32", mutable: true }`, and initial value.
+    #[wasm_bindgen(constructor, js_namespace = WebAssembly, catch)]
+    pub fn new(global_descriptor: &Object, value: &JsValue) -> Result<Global, JsValue>;
+
+    /// The value contained inside the global variable.
+    #[wasm_bindgen(method, getter, structural, js_namespace = WebAssembly)]
+    pub fn value(this: &Global) -> JsValue;
+
+    /// Sets the value contained inside the global variable, which throws
+    /// if it's immutable.
+    #[wasm_bindgen(method, setter = value, structural, js_namespace = WebAssembly, catch)]
+    pub fn set_value(this: &Global, value: &JsValue) -> Result<(), JsValue>;
+}
@@ -208,12 +208,38 @@
 //! module executable. For example, by generating native machine code
 //! for each Wasm function.
 //!
+//! ### Headless
+//!
+//! An engine without a compiler is headless: it can only load modules
+//! compiled ahead of time with [`Module::serialize`], but it's much
+//! smaller and starts faster. Enable the `universal` feature without any
+//! compiler, and create the store with `Universal::headless`:
+//!
+//! ```ignore
+//! use wasmer::{Module, Store, Universal};
+//!
+//! let store = Store::new(&Universal::headless().engine());
+//! let module = unsafe { Module::deserialize(&store, serialized)? };
+//! ```
+//!
+//! Compiling or validating a module with a headless engine fails with
+//! [`CompileError::Headless`]. With `default-universal` but no default
+//! compiler, `Store::default` is headless.
+//!
 //!
 //! ## Features
 //!
 //! This crate's features can be broken down into 2 kinds, features that
 //! enable new functionality and features that set defaults.
 //!
+//! The first thing to pick is the backend, with one of these mutually
+//! exclusive features:
+//! - `sys` - run the modules natively, compiled by one of Wasmer's
+//!   compilers and engines. This is the default.
+//! - `js` - run the modules with the `WebAssembly` API of the host, when
+//!   the crate is itself compiled to `wasm32-unknown-unknown` to run in a
+//!   browser or in Node.js. (See [JavaScript](#javascript))
+//!
 //! The features that enable new functionality are:
 //! - `universal` - enable the Universal engine. (See [wasmer-universal][])
 //! - `native` - enable the native engine. (See [wasmer-native][])
@@ -235,8 +261,25 @@
 //!
 //! --------
 //!
-//! By default the `wat`, `default-cranelift`, and `default-universal` features
-//! are enabled.
+//! By default the `sys`, `wat`, `default-cranelift`, and `default-universal`
+//! features are enabled.
+//!
+//! ## JavaScript
+//!
+//! With the `js` feature, and without the default features, `Module`,
+//! `Instance`, `Memory` and the other externs are backed by the objects of
+//! the `WebAssembly` JavaScript API, so the same embedding code runs
+//! natively and in the browser:
+//!
+//! ```toml
+//! [dependencies]
+//! wasmer = { version = "2.0", default-features = false, features = ["js", "wat"] }
+//! ```
+//!
+//! The engines and compilers aren't available there, as the host compiles
+//! the modules. Host functions receive and return their values through
+//! JavaScript, and `WasmPtr::deref` copies the values out of the memory
+//! of the module into a `WasmCell` rather than referencing it.
 //!
 //!
 //!
@@ -253,130 +296,56 @@
 //! [wasmer-llvm]: https://docs.rs/wasmer-compiler-llvm/*/wasmer_compiler_llvm/
 //! [wasmer-wasi]: https://docs.rs/wasmer-wasi/*/wasmer_wasi/
 
-mod env;
-mod exports;
-mod externals;
-mod import_object;
-mod instance;
-mod module;
-mod native;
-mod ptr;
-mod store;
-mod tunables;
-mod types;
-mod utils;
-
-/// Implement [`WasmerEnv`] for your type with `#[derive(WasmerEnv)]`.
-///
-/// See the [`WasmerEnv`] trait for more information.
-pub use wasmer_derive::WasmerEnv;
-
-#[doc(hidden)]
-pub mod internals {
-    //! We use the internals module for exporting types that are only
-    //! intended to use in internal crates such as the compatibility crate
-    //! `wasmer-vm`. Please don't use any of this types directly, as
-    //! they might change frequently or be removed in the future.
+#[cfg(all(not(feature = "sys"), not(feature = "js")))]
+compile_error!("At least the `sys` or the `js` feature must be enabled. Please, pick one.");
 
-    pub use crate::externals::{WithEnv, WithoutEnv};
-}
-
-pub use crate::env::{HostEnvInitError, LazyInit, WasmerEnv};
-pub use crate::exports::{ExportError, Exportable, Exports, ExportsIterator};
-pub use crate::externals::{
-    Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
-};
-pub use crate::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
-pub use crate::instance::{Instance, InstantiationError};
-pub use crate::module::Module;
-pub use crate::native::NativeFunc;
-pub use crate::ptr::{Array, Item, WasmPtr};
-pub use crate::store::{Store, StoreObject};
-pub use crate::tunables::BaseTunables;
-pub use crate::types::{
-    ExportType, ExternType, FunctionType, GlobalType, ImportType, MemoryType, Mutability,
-    TableType, Val, ValType,
-};
-pub use crate::types::{Val as Value, ValType as Type};
-pub use crate::utils::is_wasm;
-pub use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple, HOST};
-#[cfg(feature = "compiler")]
-pub use wasmer_compiler::{
-    wasmparser, CompilerConfig, FunctionMiddleware, MiddlewareError, MiddlewareReaderState,
-    ModuleMiddleware,
-};
-pub use wasmer_compiler::{
-    CompileError, CpuFeature, Features, ParseCpuFeatureError, Target, WasmError, WasmResult,
-};
-pub use wasmer_engine::{
-    ChainableNamedResolver, DeserializeError, Engine, Export, FrameInfo, LinkError, NamedResolver,
-    NamedResolverChain, Resolver, RuntimeError, SerializeError, Tunables,
-};
-#[cfg(feature = "experimental-reference-types-extern-ref")]
-pub use wasmer_types::ExternRef;
-pub use wasmer_types::{
-    Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
-    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
-};
+#[cfg(all(feature = "sys", feature = "js"))]
+compile_error!(
+    "Cannot have both `sys` and `js` features enabled at the same time. Please, pick one."
+);
 
-// TODO: should those be moved into wasmer::vm as well?
-pub use wasmer_vm::{raise_user_trap, MemoryError};
-pub mod vm {
-    //! The vm module re-exports wasmer-vm types.
+#[cfg(all(feature = "sys", target_arch = "wasm32"))]
+compile_error!("The `sys` feature must be enabled only for non-`wasm32` targets.");
 
-    pub use wasmer_vm::{
-        Memory, MemoryError, MemoryStyle, Table, TableStyle, VMExtern, VMMemoryDefinition,
-        VMTableDefinition,
-    };
-}
+#[cfg(all(feature = "js", not(target_arch = "wasm32")))]
+compile_error!("The `js` feature must be enabled only for the `wasm32` target.");
 
-#[cfg(feature = "wat")]
-pub use wat::parse_bytes as wat2wasm;
+mod env;
+mod guest_allocator;
 
-// The compilers are mutually exclusive
-#[cfg(any(
-    all(
-        feature = "default-llvm",
-        any(feature = "default-cranelift", feature = "default-singlepass")
-    ),
-    all(feature = "default-cranelift", feature = "default-singlepass")
-))]
-compile_error!(
-    r#"The `default-singlepass`, `default-cranelift` and `default-llvm` features are mutually exclusive.
-If you wish to use more than one compiler, you can simply create the own store. Eg.:
+#[cfg(feature = "sys")]
+mod sys;
 
-```
-use wasmer::{Store, Universal, Singlepass};
+#[cfg(feature = "sys")]
+pub use sys::*;
 
-let engine = Universal::new(Singlepass::default()).engine();
-let store = Store::new(&engine);
-```"#
-);
+#[cfg(feature = "js")]
+mod js;
 
-#[cfg(feature = "singlepass")]
-pub use wasmer_compiler_singlepass::Singlepass;
+#[cfg(feature = "js")]
+pub use js::*;
 
-#[cfg(feature = "cranelift")]
-pub use wasmer_compiler_cranelift::{Cranelift, CraneliftOptLevel};
+pub use crate::env::{FunctionEnv, FunctionEnvMut, HostEnvInitError, LazyInit, WasmerEnv};
+pub use crate::guest_allocator::{GuestAllocator, GuestBuffer};
 
-#[cfg(feature = "llvm")]
-pub use wasmer_compiler_llvm::{LLVMOptLevel, LLVM};
+/// Implement [`WasmerEnv`] for your type with `#[derive(WasmerEnv)]`.
+///
+/// See the [`WasmerEnv`] trait for more information.
+pub use wasmer_derive::WasmerEnv;
 
-#[cfg(feature = "universal")]
-pub use wasmer_engine_universal::{Universal, UniversalArtifact, UniversalEngine};
+/// Implement [`ValueType`](trait@ValueType) for your `#[repr(C)]` struct
+/// with `#[derive(ValueType)]`.
+///
+/// The derive fails to compile when a field isn't a `ValueType`, is a host
+/// pointer or reference, or when the struct has padding bytes, which are
+/// uninitialized. Padding is only checked for structs without generics;
+/// make it explicit with fields like `_padding: [u8; 3]`.
+///
+/// See [`WasmPtr`] for more information.
+pub use wasmer_derive::ValueType;
 
-#[cfg(feature = "dylib")]
-pub use wasmer_engine_dylib::{Dylib, DylibArtifact, DylibEngine};
+#[cfg(feature = "wat")]
+pub use wat::parse_bytes as wat2wasm;
 
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
-
-/// The Deprecated JIT Engine (please use `Universal` instead)
-#[cfg(feature = "jit")]
-#[deprecated(since = "2.0.0", note = "Please use the `universal` feature instead")]
-pub type JIT = Universal;
-
-/// The Deprecated Native Engine (please use `Dylib` instead)
-#[cfg(feature = "native")]
-#[deprecated(since = "2.0.0", note = "Please use the `native` feature instead")]
-pub type Native = Dylib;
@@ -0,0 +1,83 @@
+use std::cell::Cell;
+use std::fmt;
+
+/// A mutable location in the memory of a Wasm module, as returned by
+/// [`WasmPtr::deref`].
+///
+/// It is read and written like a [`Cell`], which it wraps: the memory is
+/// shared with the module, so there can't be a `&mut` to it. The `js`
+/// backend copies the values in and out of the memory instead, so this is
+/// the type to use to work with both backends.
+///
+/// [`WasmPtr::deref`]: crate::WasmPtr::deref
+#[repr(transparent)]
+pub struct WasmCell<'a, T> {
+    inner: &'a Cell<T>,
+}
+
+impl<'a, T> WasmCell<'a, T> {
+    /// Creates a new `WasmCell` wrapping `cell`.
+    #[inline]
+    pub const fn new(cell: &'a Cell<T>) -> Self {
+        Self { inner: cell }
+    }
+
+    /// Returns a raw pointer to the value in the memory.
+    ///
+    /// This is only available with the `sys` backend.
+    #[inline]
+    pub fn as_ptr(&self) -> *mut T {
+        self.inner.as_ptr()
+    }
+}
+
+impl<'a, T: Copy> WasmCell<'a, T> {
+    /// Returns a copy of the value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use wasmer::WasmCell;
+    ///
+    /// let cell = Cell::new(5);
+    /// let wasm_cell = WasmCell::new(&cell);
+    /// assert_eq!(wasm_cell.get(), 5);
+    /// ```
+    #[inline]
+    pub fn get(&self) -> T {
+        self.inner.get()
+    }
+
+    /// Sets the value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use wasmer::WasmCell;
+    ///
+    /// let cell = Cell::new(5);
+    /// let wasm_cell = WasmCell::new(&cell);
+    /// wasm_cell.set(10);
+    /// assert_eq!(cell.get(), 10);
+    /// ```
+    #[inline]
+    pub fn set(&self, val: T) {
+        self.inner.set(val);
+    }
+}
+
+impl<T> Clone for WasmCell<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WasmCell<'_, T> {}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for WasmCell<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("WasmCell").field(&self.get()).finish()
+    }
+}
@@ -1,10 +1,11 @@
-use crate::exports::{ExportError, Exportable};
-use crate::externals::Extern;
-use crate::store::Store;
-use crate::types::{Val, ValFuncRef};
+use crate::sys::exports::{ExportError, Exportable};
+use crate::sys::externals::{Extern, Table};
+use crate::sys::store::Store;
+use crate::sys::types::{Val, ValFuncRef};
 use crate::FunctionType;
 use crate::NativeFunc;
 use crate::RuntimeError;
+use crate::Type;
 use crate::WasmerEnv;
 pub use inner::{FromToNativeWasmType, HostFunction, WasmTypeList, WithEnv, WithoutEnv};
 
@@ -12,12 +13,13 @@ use loupe::MemoryUsage;
 use std::cmp::max;
 use std::ffi::c_void;
 use std::fmt;
+use std::ops::Deref;
 use std::sync::Arc;
 use wasmer_engine::{Export, ExportFunction, ExportFunctionMetadata};
 use wasmer_vm::{
-    raise_user_trap, resume_panic, wasmer_call_trampoline, ImportInitializerFuncPtr,
-    VMCallerCheckedAnyfunc, VMDynamicFunctionContext, VMFuncRef, VMFunction, VMFunctionBody,
-    VMFunctionEnvironment, VMFunctionKind, VMTrampoline,
+    raise_user_trap, wasmer_call_trampoline, ImportInitializerFuncPtr, VMCallerCheckedAnyfunc,
+    VMDynamicFunctionContext, VMFuncRef, VMFunction, VMFunctionBody, VMFunctionEnvironment,
+    VMFunctionKind, VMTrampoline,
 };
 
 /// A WebAssembly `function` instance.
@@ -198,12 +200,77 @@ impl Function {
     ///     Ok(vec![Value::I32(result)])
     /// });
     /// ```
-    #[allow(clippy::cast_ptr_alignment)]
     pub fn new_with_env<FT, F, Env>(store: &Store, ty: FT, env: Env, func: F) -> Self
     where
         FT: Into<FunctionType>,
         F: Fn(&Env, &[Val]) -> Result<Vec<Val>, RuntimeError> + 'static + Send + Sync,
         Env: Sized + WasmerEnv + 'static,
+    {
+        Self::new_raw_with_env(store, ty, env, move |env, call| {
+            let args = call.args();
+            let returns = func(env, &args)?;
+            call.ret(&returns)
+        })
+    }
+
+    /// Creates a new host `Function` (dynamic) with the provided signature,
+    /// reading its arguments from WebAssembly only when asked for them and
+    /// writing its results back directly, rather than through `Vec`s.
+    ///
+    /// This spares the allocations of [`Function::new`] on each call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value};
+    /// # let store = Store::default();
+    /// #
+    /// let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_raw(&store, &signature, |call| {
+    ///     let sum = call.arg(0).unwrap_i32() + call.arg(1).unwrap_i32();
+    ///     call.ret(&[Value::I32(sum)])
+    /// });
+    /// ```
+    pub fn new_raw<FT, F>(store: &Store, ty: FT, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(DynamicCall<'_>) -> Result<DynamicReturn, RuntimeError> + 'static + Send + Sync,
+    {
+        Self::new_raw_with_env(store, ty, WithoutEnv, move |_env: &WithoutEnv, call| {
+            func(call)
+        })
+    }
+
+    /// Creates a new host `Function` (dynamic) with the provided signature
+    /// and environment, reading its arguments and writing its results like
+    /// [`Function::new_raw`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value, WasmerEnv};
+    /// # let store = Store::default();
+    /// #
+    /// #[derive(WasmerEnv, Clone)]
+    /// struct Env {
+    ///   multiplier: i32,
+    /// };
+    /// let env = Env { multiplier: 2 };
+    ///
+    /// let signature = FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32]);
+    ///
+    /// let f = Function::new_raw_with_env(&store, &signature, env, |env, call| {
+    ///     let result = env.multiplier * (call.arg(0).unwrap_i32() + call.arg(1).unwrap_i32());
+    ///     call.ret(&[Value::I32(result)])
+    /// });
+    /// ```
+    #[allow(clippy::cast_ptr_alignment)]
+    pub fn new_raw_with_env<FT, F, Env>(store: &Store, ty: FT, env: Env, func: F) -> Self
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&Env, DynamicCall<'_>) -> Result<DynamicReturn, RuntimeError> + 'static + Send + Sync,
+        Env: Sized + WasmerEnv + 'static,
     {
         let ty: FunctionType = ty.into();
         let dynamic_ctx: VMDynamicFunctionContext<DynamicFunction<Env>> =
@@ -531,6 +598,21 @@ impl Function {
         unimplemented!("The function definition isn't supported for the moment");
     }
 
+    /// Gets the function at `index` in a `funcref` table, typically one
+    /// an instance registered as a callback, so that the host can call it.
+    ///
+    /// See [`Table::get_function`] for a variant accepting null elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `index` is out of bounds, if the table
+    /// doesn't hold functions, or if the element is null.
+    pub fn from_table_entry(table: &Table, index: u32) -> Result<Self, RuntimeError> {
+        table.get_function(index)?.ok_or_else(|| {
+            RuntimeError::new(format!("the table element at index `{}` is null", index))
+        })
+    }
+
     pub(crate) fn from_vm_export(store: &Store, wasmer_export: ExportFunction) -> Self {
         Self {
             store: store.clone(),
@@ -721,7 +803,7 @@ impl fmt::Debug for Function {
 
 /// This trait is one that all dynamic functions must fulfill.
 pub(crate) trait VMDynamicFunction: Send + Sync {
-    fn call(&self, args: &[Val]) -> Result<Vec<Val>, RuntimeError>;
+    fn call(&self, call: DynamicCall<'_>) -> Result<DynamicReturn, RuntimeError>;
     fn function_type(&self) -> &FunctionType;
     fn store(&self) -> &Store;
 }
@@ -732,7 +814,12 @@ where
 {
     function_type: FunctionType,
     #[allow(clippy::type_complexity)]
-    func: Arc<dyn Fn(&Env, &[Val]) -> Result<Vec<Val>, RuntimeError> + 'static + Send + Sync>,
+    func: Arc<
+        dyn Fn(&Env, DynamicCall<'_>) -> Result<DynamicReturn, RuntimeError>
+            + 'static
+            + Send
+            + Sync,
+    >,
     store: Store,
     env: Box<Env>,
 }
@@ -752,8 +839,8 @@ impl<Env> VMDynamicFunction for DynamicFunction<Env>
 where
     Env: Sized + 'static + Send + Sync,
 {
-    fn call(&self, args: &[Val]) -> Result<Vec<Val>, RuntimeError> {
-        (*self.func)(&*self.env, &args)
+    fn call(&self, call: DynamicCall<'_>) -> Result<DynamicReturn, RuntimeError> {
+        (*self.func)(&*self.env, call)
     }
     fn function_type(&self) -> &FunctionType {
         &self.function_type
@@ -763,6 +850,155 @@ where
     }
 }
 
+/// The number of arguments of a dynamic function call that are kept
+/// on the stack, above which they are collected in a `Vec`.
+const INLINE_DYNAMIC_ARGS: usize = 8;
+
+/// The arguments of a dynamic function call, read from the values
+/// buffer the dynamic trampoline passes to the host.
+///
+/// Host functions rarely take many arguments, so they are usually
+/// kept on the stack, sparing a heap allocation per call.
+struct DynamicArgs {
+    inline: [Val; INLINE_DYNAMIC_ARGS],
+    /// The arguments, when there are too many of them to be inline.
+    /// Being empty, it doesn't allocate otherwise.
+    heap: Vec<Val>,
+    len: usize,
+}
+
+impl DynamicArgs {
+    /// Reads the arguments of types `params` from `values_vec`.
+    ///
+    /// # Safety
+    ///
+    /// `values_vec` must hold a value of the matching type for each
+    /// of `params`.
+    unsafe fn read(store: &Store, values_vec: *const i128, params: &[Type]) -> Self {
+        let mut args = Self {
+            inline: [
+                Val::I32(0),
+                Val::I32(0),
+                Val::I32(0),
+                Val::I32(0),
+                Val::I32(0),
+                Val::I32(0),
+                Val::I32(0),
+                Val::I32(0),
+            ],
+            heap: Vec::new(),
+            len: params.len(),
+        };
+        let values = params
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| Val::read_value_from(store, values_vec.add(i), *ty));
+        if params.len() > INLINE_DYNAMIC_ARGS {
+            args.heap.extend(values);
+        } else {
+            for (slot, value) in args.inline.iter_mut().zip(values) {
+                *slot = value;
+            }
+        }
+        args
+    }
+}
+
+impl Deref for DynamicArgs {
+    type Target = [Val];
+
+    fn deref(&self) -> &[Val] {
+        if self.len > INLINE_DYNAMIC_ARGS {
+            &self.heap
+        } else {
+            &self.inline[..self.len]
+        }
+    }
+}
+
+/// A call to a host function created with [`Function::new_raw`], whose
+/// arguments are read from the buffer the WebAssembly code passes them in
+/// only when asked for, and whose results are written back to it.
+///
+/// The results overwrite the arguments, so [`DynamicCall::ret`] consumes
+/// the call.
+pub struct DynamicCall<'a> {
+    store: &'a Store,
+    ty: &'a FunctionType,
+    values: *mut i128,
+}
+
+/// The proof that the results of a [`DynamicCall`] were written, which
+/// the host functions created with [`Function::new_raw`] return.
+#[derive(Debug)]
+pub struct DynamicReturn {
+    _private: (),
+}
+
+impl<'a> DynamicCall<'a> {
+    /// Creates a call of a function of type `ty`, passing its arguments
+    /// and taking its results in `values`.
+    ///
+    /// # Safety
+    ///
+    /// `values` must hold a value of the matching type for each parameter
+    /// of `ty`, and have room for all its results.
+    pub(crate) unsafe fn new(store: &'a Store, ty: &'a FunctionType, values: *mut i128) -> Self {
+        Self { store, ty, values }
+    }
+
+    /// Returns the [`Store`] where the function belongs.
+    pub fn store(&self) -> &Store {
+        self.store
+    }
+
+    /// Returns the [`FunctionType`] of the function.
+    pub fn ty(&self) -> &FunctionType {
+        self.ty
+    }
+
+    /// Reads the argument at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds, like indexing the arguments of
+    /// the functions created with [`Function::new`]. The panic traps.
+    pub fn arg(&self, index: usize) -> Val {
+        let ty = self.ty.params()[index];
+        // The buffer holds a value of the type of each parameter
+        unsafe { Val::read_value_from(self.store, self.values.add(index), ty) }
+    }
+
+    /// Reads all the arguments.
+    fn args(&self) -> DynamicArgs {
+        // The buffer holds a value of the type of each parameter
+        unsafe { DynamicArgs::read(self.store, self.values, self.ty.params()) }
+    }
+
+    /// Writes the `results` of the call, which must match the result types
+    /// of the function.
+    pub fn ret(self, results: &[Val]) -> Result<DynamicReturn, RuntimeError> {
+        // We need to dynamically check that the returns
+        // match the expected types, as well as expected length.
+        if !results
+            .iter()
+            .map(|ret| ret.ty())
+            .eq(self.ty.results().iter().copied())
+        {
+            return Err(RuntimeError::new(format!(
+                "Dynamic function returned wrong signature. Expected {:?} but got {:?}",
+                self.ty.results(),
+                results.iter().map(|ret| ret.ty()).collect::<Vec<_>>()
+            )));
+        }
+        for (i, ret) in results.iter().enumerate() {
+            // The buffer has room for all the results
+            unsafe { ret.write_value_to(self.values.add(i)) };
+        }
+        Ok(DynamicReturn { _private: () })
+    }
+}
+
 trait VMDynamicFunctionCall<T: VMDynamicFunction> {
     fn from_context(ctx: T) -> Self;
     fn address_ptr() -> *const VMFunctionBody;
@@ -791,28 +1027,8 @@ impl<T: VMDynamicFunction> VMDynamicFunctionCall<T> for VMDynamicFunctionContext
     ) {
         use std::panic::{self, AssertUnwindSafe};
         let result = panic::catch_unwind(AssertUnwindSafe(|| {
-            let func_ty = self.ctx.function_type();
-            let mut args = Vec::with_capacity(func_ty.params().len());
-            let store = self.ctx.store();
-            for (i, ty) in func_ty.params().iter().enumerate() {
-                args.push(Val::read_value_from(store, values_vec.add(i), *ty));
-            }
-            let returns = self.ctx.call(&args)?;
-
-            // We need to dynamically check that the returns
-            // match the expected types, as well as expected length.
-            let return_types = returns.iter().map(|ret| ret.ty()).collect::<Vec<_>>();
-            if return_types != func_ty.results() {
-                return Err(RuntimeError::new(format!(
-                    "Dynamic function returned wrong signature. Expected {:?} but got {:?}",
-                    func_ty.results(),
-                    return_types
-                )));
-            }
-            for (i, ret) in returns.iter().enumerate() {
-                ret.write_value_to(values_vec.add(i));
-            }
-            Ok(())
+            let call = DynamicCall::new(self.ctx.store(), self.ctx.function_type(), values_vec);
+            self.ctx.call(call).map(|_| ())
         })); // We get extern ref drops at the end of this block that we don't need.
              // By preventing extern ref incs in the code above we can save the work of
              // incrementing and decrementing. However the logic as-is is correct.
@@ -820,7 +1036,9 @@ impl<T: VMDynamicFunction> VMDynamicFunctionCall<T> for VMDynamicFunctionContext
         match result {
             Ok(Ok(())) => {}
             Ok(Err(trap)) => raise_user_trap(Box::new(trap)),
-            Err(panic) => resume_panic(panic),
+            // Unwinding through the Wasm frames isn't supported, the panic
+            // traps instead
+            Err(panic) => raise_user_trap(Box::new(RuntimeError::from_panic(panic))),
         }
     }
 }
@@ -834,10 +1052,11 @@ mod inner {
     use std::marker::PhantomData;
     use std::panic::{self, AssertUnwindSafe};
 
+    use crate::RuntimeError;
     #[cfg(feature = "experimental-reference-types-extern-ref")]
     pub use wasmer_types::{ExternRef, VMExternRef};
     use wasmer_types::{FunctionType, NativeWasmType, Type};
-    use wasmer_vm::{raise_user_trap, resume_panic, VMFunctionBody};
+    use wasmer_vm::{raise_user_trap, VMFunctionBody};
 
     /// A trait to convert a Rust value to a `WasmNativeType` value,
     /// or to convert `WasmNativeType` value to a Rust value.
@@ -1303,7 +1522,9 @@ mod inner {
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(),
                             Ok(Err(trap)) => unsafe { raise_user_trap(Box::new(trap)) },
-                            Err(panic) => unsafe { resume_panic(panic) },
+                            Err(panic) => unsafe {
+                                raise_user_trap(Box::new(RuntimeError::from_panic(panic)))
+                            },
                         }
                     }
 
@@ -1347,7 +1568,9 @@ mod inner {
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(),
                             Ok(Err(trap)) => unsafe { raise_user_trap(Box::new(trap)) },
-                            Err(panic) => unsafe { resume_panic(panic) },
+                            Err(panic) => unsafe {
+                                raise_user_trap(Box::new(RuntimeError::from_panic(panic)))
+                            },
                         }
                     }
 
@@ -1398,6 +1621,12 @@ mod inner {
     impl_host_function!([C] S24, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21, A22, A23, A24);
     impl_host_function!([C] S25, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21, A22, A23, A24, A25);
     impl_host_function!([C] S26, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21, A22, A23, A24, A25, A26);
+    impl_host_function!([C] S27, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21, A22, A23, A24, A25, A26, A27);
+    impl_host_function!([C] S28, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21, A22, A23, A24, A25, A26, A27, A28);
+    impl_host_function!([C] S29, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21, A22, A23, A24, A25, A26, A27, A28, A29);
+    impl_host_function!([C] S30, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21, A22, A23, A24, A25, A26, A27, A28, A29, A30);
+    impl_host_function!([C] S31, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21, A22, A23, A24, A25, A26, A27, A28, A29, A30, A31);
+    impl_host_function!([C] S32, A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21, A22, A23, A24, A25, A26, A27, A28, A29, A30, A31, A32);
 
     // Implement `WasmTypeList` on `Infallible`, which means that
     // `Infallible` can be used as a returned type of a host function
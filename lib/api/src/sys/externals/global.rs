@@ -1,7 +1,7 @@
-use crate::exports::{ExportError, Exportable};
-use crate::externals::Extern;
-use crate::store::{Store, StoreObject};
-use crate::types::Val;
+use crate::sys::exports::{ExportError, Exportable};
+use crate::sys::externals::Extern;
+use crate::sys::store::{Store, StoreObject};
+use crate::sys::types::Val;
 use crate::GlobalType;
 use crate::Mutability;
 use crate::RuntimeError;
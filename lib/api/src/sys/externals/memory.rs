@@ -1,15 +1,39 @@
-use crate::exports::{ExportError, Exportable};
-use crate::externals::Extern;
-use crate::store::Store;
+use crate::sys::exports::{ExportError, Exportable};
+use crate::sys::externals::Extern;
+use crate::sys::store::Store;
 use crate::{MemoryType, MemoryView};
 use loupe::MemoryUsage;
 use std::convert::TryInto;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
 use std::slice;
 use std::sync::Arc;
-use wasmer_engine::Export;
-use wasmer_types::{Pages, ValueType};
+use thiserror::Error;
+use wasmer_engine::{Export, RuntimeError};
+use wasmer_types::{Atomic, Atomically, Pages, ValueType};
 use wasmer_vm::{MemoryError, VMMemory};
 
+/// An error while accessing the contents of a [`Memory`] with one of its
+/// helpers, like [`Memory::read`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MemoryAccessError {
+    /// The access goes past the end of the memory.
+    #[error("memory access out of bounds")]
+    HeapOutOfBounds,
+    /// The end address of the access doesn't fit in 64 bits.
+    #[error("address calculation overflow")]
+    Overflow,
+    /// The bytes read aren't valid UTF-8.
+    #[error("string is not valid utf-8")]
+    NonUtf8String,
+}
+
+impl From<MemoryAccessError> for RuntimeError {
+    fn from(err: MemoryAccessError) -> Self {
+        Self::new(err.to_string())
+    }
+}
+
 /// A WebAssembly `memory` instance.
 ///
 /// A memory instance is the runtime representation of a linear memory.
@@ -34,7 +58,7 @@ impl Memory {
     /// Creates a new host `Memory` from the provided [`MemoryType`].
     ///
     /// This function will construct the `Memory` using the store
-    /// [`BaseTunables`][crate::tunables::BaseTunables].
+    /// [`BaseTunables`][crate::BaseTunables].
     ///
     /// # Example
     ///
@@ -226,6 +250,173 @@ impl Memory {
         unsafe { MemoryView::new(base as _, length as u32) }
     }
 
+    /// Returns a pointer to the `len` bytes at `offset`, if they are in
+    /// bounds.
+    fn checked_ptr(&self, offset: u64, len: usize) -> Result<*mut u8, MemoryAccessError> {
+        let end = offset
+            .checked_add(len as u64)
+            .ok_or(MemoryAccessError::Overflow)?;
+        if end > self.data_size() {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        Ok(unsafe { self.data_ptr().add(offset as usize) })
+    }
+
+    /// Copies the bytes at `offset` to `buf`, which is filled entirely.
+    ///
+    /// Like [`Memory::view`], this doesn't synchronize with the other
+    /// threads accessing the memory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write(0x100, b"hello").unwrap();
+    ///
+    /// let mut buf = [0; 5];
+    /// m.read(0x100, &mut buf).unwrap();
+    /// assert_eq!(&buf, b"hello");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes to read go past the end of the memory,
+    /// in which case `buf` is left unchanged.
+    pub fn read(&self, offset: u64, buf: &mut [u8]) -> Result<(), MemoryAccessError> {
+        let src = self.checked_ptr(offset, buf.len())?;
+        unsafe { ptr::copy(src, buf.as_mut_ptr(), buf.len()) };
+        Ok(())
+    }
+
+    /// Copies `data` to the memory at `offset`.
+    ///
+    /// Like [`Memory::view`], this doesn't synchronize with the other
+    /// threads accessing the memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes to write go past the end of the memory,
+    /// in which case the memory is left unchanged.
+    pub fn write(&self, offset: u64, data: &[u8]) -> Result<(), MemoryAccessError> {
+        let dst = self.checked_ptr(offset, data.len())?;
+        unsafe { ptr::copy(data.as_ptr(), dst, data.len()) };
+        Ok(())
+    }
+
+    /// Reads a value of a plain type at `offset`, which doesn't need to be
+    /// aligned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write_value(0x101, 42u32).unwrap();
+    ///
+    /// assert_eq!(m.read_value::<u32>(0x101).unwrap(), 42);
+    /// ```
+    pub fn read_value<T: ValueType>(&self, offset: u64) -> Result<T, MemoryAccessError> {
+        let src = self.checked_ptr(offset, mem::size_of::<T>())?;
+        let mut value = MaybeUninit::<T>::uninit();
+        unsafe {
+            ptr::copy(src, value.as_mut_ptr() as *mut u8, mem::size_of::<T>());
+            Ok(value.assume_init())
+        }
+    }
+
+    /// Writes a value of a plain type at `offset`, which doesn't need to be
+    /// aligned.
+    pub fn write_value<T: ValueType>(
+        &self,
+        offset: u64,
+        value: T,
+    ) -> Result<(), MemoryAccessError> {
+        let dst = self.checked_ptr(offset, mem::size_of::<T>())?;
+        unsafe {
+            ptr::copy(&value as *const T as *const u8, dst, mem::size_of::<T>());
+        }
+        Ok(())
+    }
+
+    /// Reads the UTF-8 string of `len` bytes at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes go past the end of the memory, or
+    /// aren't valid UTF-8.
+    pub fn read_utf8(&self, offset: u64, len: usize) -> Result<String, MemoryAccessError> {
+        // Checked first, not to allocate for lengths going past the end
+        self.checked_ptr(offset, len)?;
+        let mut buf = vec![0; len];
+        self.read(offset, &mut buf)?;
+        String::from_utf8(buf).map_err(|_| MemoryAccessError::NonUtf8String)
+    }
+
+    /// Reads the nul-terminated UTF-8 string at `offset`, without its
+    /// terminator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write(0x100, b"hello\0world").unwrap();
+    ///
+    /// assert_eq!(m.read_cstring(0x100).unwrap(), "hello");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no nul byte between `offset` and the end
+    /// of the memory, or if the string isn't valid UTF-8.
+    pub fn read_cstring(&self, offset: u64) -> Result<String, MemoryAccessError> {
+        let available = self.data_size().saturating_sub(offset);
+        let start = self.checked_ptr(offset, available as usize)?;
+        let bytes = unsafe { slice::from_raw_parts(start, available as usize) };
+        let len = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or(MemoryAccessError::HeapOutOfBounds)?;
+        String::from_utf8(bytes[..len].to_vec()).map_err(|_| MemoryAccessError::NonUtf8String)
+    }
+
+    /// Return an atomic "view" of the currently accessible memory, like
+    /// [`MemoryView::atomically`] but without creating a non-atomic view
+    /// first: `view_atomic::<u32>()` derefs to a `[AtomicU32]`.
+    ///
+    /// This is the view to use with shared memories, to communicate with
+    /// the threads of the guest. The atomic instructions of WebAssembly are
+    /// sequentially consistent, so accesses synchronizing with them should
+    /// use [`Ordering::SeqCst`].
+    ///
+    /// [`Ordering::SeqCst`]: std::sync::atomic::Ordering::SeqCst
+    ///
+    /// # Usage:
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # use std::sync::atomic::Ordering;
+    /// # let store = Store::default();
+    /// let memory = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// let view = memory.view_atomic::<u32>();
+    ///
+    /// // Wakes a guest thread spinning on the word at 0x1000
+    /// view[0x1000 / 4].store(1, Ordering::SeqCst);
+    /// assert_eq!(view[0x1000 / 4].fetch_add(1, Ordering::SeqCst), 1);
+    /// ```
+    pub fn view_atomic<T: Atomic>(&self) -> MemoryView<T::Output, Atomically> {
+        let base = self.data_ptr();
+        // The atomics have the size of `T`, and are aligned as the memory
+        // starts on a page boundary
+        let length = self.size().bytes().0 / std::mem::size_of::<T::Output>();
+
+        unsafe { MemoryView::new_atomic(base as _, length as u32) }
+    }
+
     pub(crate) fn from_vm_export(store: &Store, vm_memory: VMMemory) -> Self {
         Self {
             store: store.clone(),
@@ -0,0 +1,186 @@
+use crate::sys::externals::Function;
+use crate::sys::store::Store;
+use crate::sys::types::Val;
+use crate::FunctionType;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use wasmer_engine::RuntimeError;
+
+type ScopedFn<'env> = dyn Fn(&[Val]) -> Result<Vec<Val>, RuntimeError> + Send + Sync + 'env;
+
+/// The closure of a scoped function, taken out when its scope ends, with
+/// the number of its calls in progress.
+struct Slot {
+    state: Mutex<SlotState>,
+    /// Notified when the last call in progress returns
+    returned: Condvar,
+}
+
+struct SlotState {
+    func: Option<Arc<ScopedFn<'static>>>,
+    calls: usize,
+}
+
+impl Slot {
+    fn lock(&self) -> MutexGuard<SlotState> {
+        // The state stays consistent even if a thread panicked with it
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// A call in progress of the closure of a slot, counted until dropped.
+struct Call<'a> {
+    slot: &'a Slot,
+    func: Option<Arc<ScopedFn<'static>>>,
+}
+
+impl<'a> Call<'a> {
+    /// Starts a call of the closure of `slot`, unless its scope ended.
+    fn start(slot: &'a Slot) -> Option<Self> {
+        let mut state = slot.lock();
+        let func = state.func.clone()?;
+        state.calls += 1;
+        Some(Self {
+            slot,
+            func: Some(func),
+        })
+    }
+}
+
+impl Drop for Call<'_> {
+    fn drop(&mut self) {
+        // The closure is released first, so that it's never dropped after
+        // the end of its scope
+        self.func = None;
+        let mut state = self.slot.lock();
+        state.calls -= 1;
+        if state.calls == 0 {
+            self.slot.returned.notify_all();
+        }
+    }
+}
+
+/// A scope in which host functions may borrow from the stack of the
+/// embedder, created with [`Function::scope`].
+///
+/// The functions created in a scope can outlive it, by being imported in
+/// an instance for example, but calling them after the scope ended traps.
+pub struct FunctionScope<'env> {
+    slots: Mutex<Vec<Arc<Slot>>>,
+    // Invariant over `'env`, like `std::thread::Scope`
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'env> FunctionScope<'env> {
+    /// Creates a new host `Function` (dynamic) with the provided signature,
+    /// whose closure may borrow anything outliving the scope.
+    ///
+    /// See [`Function::new`] for more information.
+    pub fn function<FT, F>(&self, store: &Store, ty: FT, func: F) -> Function
+    where
+        FT: Into<FunctionType>,
+        F: Fn(&[Val]) -> Result<Vec<Val>, RuntimeError> + Send + Sync + 'env,
+    {
+        let func: Arc<ScopedFn<'env>> = Arc::new(func);
+        // Safety: the scope doesn't end before the closure is taken out of
+        // the slot and all its calls returned, so it's never used after
+        // what it borrows is gone.
+        let func: Arc<ScopedFn<'static>> = unsafe { std::mem::transmute(func) };
+        let slot = Arc::new(Slot {
+            state: Mutex::new(SlotState {
+                func: Some(func),
+                calls: 0,
+            }),
+            returned: Condvar::new(),
+        });
+        self.slots.lock().unwrap().push(slot.clone());
+        Function::new(store, ty, move |args| {
+            // The lock isn't held during the call, for the function to be
+            // reentrant
+            let call = match Call::start(&slot) {
+                Some(call) => call,
+                None => {
+                    return Err(RuntimeError::new(
+                        "a scoped host function was called after the end of its scope",
+                    ))
+                }
+            };
+            let func = call.func.as_ref().expect("the call holds the closure");
+            func(args)
+        })
+    }
+}
+
+impl fmt::Debug for FunctionScope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FunctionScope")
+            .field("functions", &self.slots.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Drop for FunctionScope<'_> {
+    fn drop(&mut self) {
+        let slots = match self.slots.get_mut() {
+            Ok(slots) => slots,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for slot in slots.drain(..) {
+            let mut state = slot.lock();
+            state.func = None;
+            // Waits for the calls from other threads to return
+            while state.calls > 0 {
+                state = match slot.returned.wait(state) {
+                    Ok(state) => state,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+            }
+        }
+    }
+}
+
+impl Function {
+    /// Runs `f` with a [`FunctionScope`], in which host functions may be
+    /// created with closures borrowing from the stack, instead of being
+    /// `'static`.
+    ///
+    /// When the scope ends, the functions created in it stop calling their
+    /// closures and trap instead, after waiting for the calls in progress
+    /// on other threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{Function, FunctionType, Type, Store, Value};
+    /// # let store = Store::default();
+    /// let signature = FunctionType::new(vec![Type::I32], vec![]);
+    /// let mut total = std::sync::atomic::AtomicI32::new(0);
+    ///
+    /// Function::scope(|scope| {
+    ///     let add = scope.function(&store, &signature, |args| {
+    ///         total.fetch_add(args[0].unwrap_i32(), std::sync::atomic::Ordering::SeqCst);
+    ///         Ok(vec![])
+    ///     });
+    ///     // Instantiate a module importing `add` and run it
+    /// #   let _ = add;
+    /// });
+    ///
+    /// println!("the total is {}", total.get_mut());
+    /// ```
+    pub fn scope<'env, F, R>(f: F) -> R
+    where
+        F: FnOnce(&FunctionScope<'env>) -> R,
+    {
+        let scope = FunctionScope {
+            slots: Mutex::new(vec![]),
+            _env: PhantomData,
+        };
+        // The functions are disabled when `scope` is dropped, even if `f`
+        // panics
+        f(&scope)
+    }
+}
@@ -1,13 +1,13 @@
-use crate::exports::{ExportError, Exportable};
-use crate::externals::Extern;
-use crate::store::Store;
-use crate::types::{Val, ValFuncRef};
+use crate::sys::exports::{ExportError, Exportable};
+use crate::sys::externals::{Extern, Function};
+use crate::sys::store::Store;
+use crate::sys::types::{Val, ValFuncRef};
 use crate::RuntimeError;
 use crate::TableType;
 use loupe::MemoryUsage;
 use std::sync::Arc;
 use wasmer_engine::Export;
-use wasmer_vm::{Table as RuntimeTable, TableElement, VMTable};
+use wasmer_vm::{Table as RuntimeTable, TableElement, VMTable, WeakOrStrongInstanceRef};
 
 /// A WebAssembly `table` instance.
 ///
@@ -38,7 +38,7 @@ impl Table {
     /// All the elements in the table will be set to the `init` value.
     ///
     /// This function will construct the `Table` using the store
-    /// [`BaseTunables`][crate::tunables::BaseTunables].
+    /// [`BaseTunables`][crate::BaseTunables].
     pub fn new(store: &Store, ty: TableType, init: Val) -> Result<Self, RuntimeError> {
         let item = init.into_table_reference(store)?;
         let tunables = store.tunables();
@@ -77,6 +77,95 @@ impl Table {
         Some(ValFuncRef::from_table_reference(item, &self.store))
     }
 
+    /// Retrieves the function at the provided `index` of a `funcref`
+    /// table, or `None` if the element is null.
+    ///
+    /// This lets the host call the functions an instance registered in a
+    /// table, like the callbacks of a plugin, without the instance
+    /// exporting each of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmer::{imports, Instance, Module, Store, Value};
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, r#"(module
+    ///     (table (export "callbacks") 1 funcref)
+    ///     (elem (i32.const 0) $on_event)
+    ///     (func $on_event (param i32) (result i32)
+    ///         (i32.add (local.get 0) (i32.const 1))))"#).unwrap();
+    /// let instance = Instance::new(&module, &imports! {}).unwrap();
+    /// let callbacks = instance.exports.get_table("callbacks").unwrap();
+    ///
+    /// let on_event = callbacks.get_function(0).unwrap().unwrap();
+    /// assert_eq!(on_event.call(&[Value::I32(41)]).unwrap().to_vec(), vec![Value::I32(42)]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `index` is out of bounds, if the table
+    /// doesn't hold functions, if the instance owning the function is
+    /// being dropped, or if no instance declares the signature of a host
+    /// function.
+    pub fn get_function(&self, index: u32) -> Result<Option<Function>, RuntimeError> {
+        match self.get(index) {
+            Some(Val::FuncRef(Some(mut function))) => {
+                self.add_call_trampoline(&mut function)?;
+                Ok(Some(function))
+            }
+            Some(Val::FuncRef(None)) => Ok(None),
+            Some(_) => Err(RuntimeError::new(format!(
+                "the table holds `{}` elements, not functions",
+                self.ty().ty
+            ))),
+            None => Err(RuntimeError::new(format!(
+                "table index `{}` is out of bounds",
+                index
+            ))),
+        }
+    }
+
+    /// The functions read from a table can't be called from the host, as
+    /// tables don't hold the trampolines to call them, so this gets one
+    /// from the engine.
+    ///
+    /// The instance owning a function declares its signature, so it
+    /// provides the trampoline, and is kept alive with the function. The
+    /// host functions don't have an owner, so the trampoline comes from
+    /// any instance declaring their signature, which is kept alive instead
+    /// to keep the code of the trampoline loaded. The dropped instances are
+    /// removed from the engine, as the address of their `VMContext` can be
+    /// reused, so their functions are looked up like host functions: like
+    /// with `call_indirect`, only their code is kept loaded, not their
+    /// `VMContext`.
+    fn add_call_trampoline(&self, function: &mut Function) -> Result<(), RuntimeError> {
+        let vm_function = &mut function.exported.vm_function;
+        if vm_function.call_trampoline.is_some() {
+            return Ok(());
+        }
+        let engine = self.store.engine();
+        let signature = engine.register_signature(&vm_function.signature);
+        let (trampoline, instance) = match engine.lookup_function_owner(vm_function.vmctx) {
+            Some(owner) => {
+                let owner = owner.upgrade().ok_or_else(|| {
+                    RuntimeError::new("the instance owning the function was dropped")
+                })?;
+                let trampoline = owner.function_call_trampoline(signature);
+                trampoline.map(|trampoline| (trampoline, owner))
+            }
+            None => engine.lookup_function_call_trampoline(signature),
+        }
+        .ok_or_else(|| {
+            RuntimeError::new(format!(
+                "no instance declares the signature `{}` of the function",
+                vm_function.signature
+            ))
+        })?;
+        vm_function.call_trampoline = Some(trampoline);
+        vm_function.instance_ref = Some(WeakOrStrongInstanceRef::Strong(instance));
+        Ok(())
+    }
+
     /// Sets an element `val` in the Table at the provided `index`.
     pub fn set(&self, index: u32, val: Val) -> Result<(), RuntimeError> {
         let item = val.into_table_reference(&self.store)?;
@@ -1,12 +1,14 @@
 //! The import module contains the implementation data structures and helper functions used to
 //! manipulate and access a wasm module's imports including memories, tables, globals, and
 //! functions.
+use crate::{Extern, Store};
+use indexmap::IndexMap;
 use std::borrow::{Borrow, BorrowMut};
 use std::collections::VecDeque;
 use std::collections::{hash_map::Entry, HashMap};
 use std::fmt;
 use std::sync::{Arc, Mutex};
-use wasmer_engine::{Export, NamedResolver};
+use wasmer_engine::{Engine, Export, NamedResolver};
 
 /// The `LikeNamespace` trait represents objects that act as a namespace for imports.
 /// For example, an `Instance` or `Namespace` could be
@@ -16,6 +18,10 @@ pub trait LikeNamespace {
     fn get_namespace_export(&self, name: &str) -> Option<Export>;
     /// Gets all exports in the namespace.
     fn get_namespace_exports(&self) -> Vec<(String, Export)>;
+    /// Gets the store of an export by name, if the namespace knows it.
+    fn get_namespace_store(&self, _name: &str) -> Option<Store> {
+        None
+    }
 }
 
 /// All of the import data used when instantiating.
@@ -61,11 +67,7 @@ impl ImportObject {
     pub fn get_export(&self, module: &str, name: &str) -> Option<Export> {
         let guard = self.map.lock().unwrap();
         let map_ref = guard.borrow();
-        if map_ref.contains_key(module) {
-            let namespace = map_ref[module].as_ref();
-            return namespace.get_namespace_export(name);
-        }
-        None
+        map_ref.get(module)?.get_namespace_export(name)
     }
 
     /// Returns true if the ImportObject contains namespace with the provided name.
@@ -73,6 +75,67 @@ impl ImportObject {
         self.map.lock().unwrap().borrow().contains_key(name)
     }
 
+    /// Returns true if the ImportObject contains an import with the provided
+    /// name in the provided namespace.
+    pub fn contains(&self, module: &str, name: &str) -> bool {
+        self.get_export(module, name).is_some()
+    }
+
+    /// Adds the imports of `other` to this `ImportObject`.
+    ///
+    /// The namespaces which are in both are merged, and the imports of
+    /// `other` replace the ones with the same name. This is useful to
+    /// combine the imports of several host libraries.
+    ///
+    /// # Usage:
+    /// ```
+    /// # use wasmer::{imports, Global, Store, Value};
+    /// # let store = Store::default();
+    /// let mut import_object = imports! {
+    ///     "env" => {
+    ///         "foo" => Global::new(&store, Value::I32(1)),
+    ///     },
+    /// };
+    /// import_object.extend(&imports! {
+    ///     "env" => {
+    ///         "bar" => Global::new(&store, Value::I32(2)),
+    ///     },
+    /// });
+    ///
+    /// assert!(import_object.contains("env", "foo"));
+    /// assert!(import_object.contains("env", "bar"));
+    /// ```
+    pub fn extend(&mut self, other: &ImportObject) {
+        // Collected first, as `other` may share its map with `self`
+        let mut namespaces: IndexMap<String, IndexMap<String, Export>> = IndexMap::new();
+        for ((module, name), export) in other.get_objects() {
+            namespaces.entry(module).or_default().insert(name, export);
+        }
+
+        let mut guard = self.map.lock().unwrap();
+        let map = guard.borrow_mut();
+        for (module, exports) in namespaces {
+            let base = map.remove(&module);
+            map.insert(module, Box::new(ExtendedNamespace { base, exports }));
+        }
+    }
+
+    /// Iterates over the imports, as `(namespace, name, extern)`, to inspect
+    /// them before instantiating a module for example.
+    ///
+    /// The namespaces are sorted by name. The `store` is the one the
+    /// `Extern`s are created in.
+    pub fn iter<'a>(
+        &self,
+        store: &'a Store,
+    ) -> impl Iterator<Item = (String, String, Extern)> + 'a {
+        self.get_objects()
+            .into_iter()
+            .map(move |((module, name), export)| {
+                (module, name, Extern::from_vm_export(store, export))
+            })
+    }
+
     /// Register anything that implements `LikeNamespace` as a namespace.
     ///
     /// # Usage:
@@ -105,7 +168,9 @@ impl ImportObject {
         let mut out = VecDeque::new();
         let guard = self.map.lock().unwrap();
         let map = guard.borrow();
-        for (name, ns) in map.iter() {
+        let mut namespaces = map.iter().collect::<Vec<_>>();
+        namespaces.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, ns) in namespaces {
             for (id, exp) in ns.get_namespace_exports() {
                 out.push_back(((name.clone(), id), exp));
             }
@@ -114,10 +179,60 @@ impl ImportObject {
     }
 }
 
+/// A namespace extended by [`ImportObject::extend`], whose new exports
+/// take precedence over the ones of the namespace it replaced.
+struct ExtendedNamespace {
+    base: Option<Box<dyn LikeNamespace>>,
+    exports: IndexMap<String, Export>,
+}
+
+impl LikeNamespace for ExtendedNamespace {
+    fn get_namespace_export(&self, name: &str) -> Option<Export> {
+        self.exports.get(name).cloned().or_else(|| {
+            self.base
+                .as_ref()
+                .and_then(|base| base.get_namespace_export(name))
+        })
+    }
+
+    fn get_namespace_exports(&self) -> Vec<(String, Export)> {
+        let mut out = match &self.base {
+            Some(base) => base.get_namespace_exports(),
+            None => vec![],
+        };
+        out.retain(|(name, _)| !self.exports.contains_key(name));
+        out.extend(
+            self.exports
+                .iter()
+                .map(|(name, export)| (name.clone(), export.clone())),
+        );
+        out
+    }
+
+    fn get_namespace_store(&self, name: &str) -> Option<Store> {
+        if self.exports.contains_key(name) {
+            return None;
+        }
+        self.base
+            .as_ref()
+            .and_then(|base| base.get_namespace_store(name))
+    }
+}
+
 impl NamedResolver for ImportObject {
     fn resolve_by_name(&self, module: &str, name: &str) -> Option<Export> {
         self.get_export(module, name)
     }
+
+    fn resolve_engine_by_name(
+        &self,
+        module: &str,
+        name: &str,
+    ) -> Option<Arc<dyn Engine + Send + Sync>> {
+        let guard = self.map.lock().unwrap();
+        let store = guard.get(module)?.get_namespace_store(name)?;
+        Some(store.engine().clone())
+    }
 }
 
 /// Iterator for an `ImportObject`'s exports.
@@ -249,7 +364,7 @@ mod test {
     use super::*;
     use crate::{Global, Store, Val};
     use wasmer_engine::ChainableNamedResolver;
-    use wasmer_types::Type;
+    use wasmer_types::{ExternType, GlobalType, Mutability, Type};
 
     #[test]
     fn chaining_works() {
@@ -336,6 +451,55 @@ mod test {
         });
     }
 
+    #[test]
+    fn extend_merges_namespaces() {
+        let store = Store::default();
+        let g1 = Global::new(&store, Val::I32(0));
+        let g2 = Global::new(&store, Val::I64(0));
+
+        let mut imports1 = imports! {
+            "dog" => {
+                "happy" => g1.clone(),
+                "small" => g1.clone(),
+            },
+        };
+        let imports2 = imports! {
+            "dog" => {
+                "happy" => g2.clone(),
+            },
+            "cat" => {
+                "small" => g2,
+            },
+        };
+        imports1.extend(&imports2);
+
+        assert!(imports1.contains("dog", "happy"));
+        assert!(imports1.contains("dog", "small"));
+        assert!(imports1.contains("cat", "small"));
+        assert!(!imports1.contains("cat", "happy"));
+        assert!(!imports1.contains("bird", "small"));
+
+        let imports = imports1
+            .iter(&store)
+            .map(|(module, name, extern_)| (module, name, extern_.ty()))
+            .collect::<Vec<_>>();
+        let i32_global = ExternType::Global(GlobalType::new(Type::I32, Mutability::Const));
+        let i64_global = ExternType::Global(GlobalType::new(Type::I64, Mutability::Const));
+        assert_eq!(
+            imports,
+            vec![
+                ("cat".to_string(), "small".to_string(), i64_global.clone()),
+                ("dog".to_string(), "small".to_string(), i32_global),
+                ("dog".to_string(), "happy".to_string(), i64_global),
+            ]
+        );
+
+        // Extending with itself doesn't deadlock
+        let imports2_clone = imports1.clone();
+        imports1.extend(&imports2_clone);
+        assert_eq!(imports1.iter(&store).count(), 3);
+    }
+
     #[test]
     fn namespace() {
         let store = Store::default();
@@ -1,14 +1,16 @@
-use crate::exports::Exports;
-use crate::externals::Extern;
-use crate::module::Module;
-use crate::store::Store;
+use crate::sys::exports::Exports;
+use crate::sys::externals::Extern;
+use crate::sys::module::Module;
+use crate::sys::store::Store;
 use crate::{HostEnvInitError, LinkError, RuntimeError};
 use loupe::MemoryUsage;
+use std::cell::Cell;
 use std::fmt;
+use std::marker::PhantomData;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use wasmer_engine::Resolver;
-use wasmer_vm::{InstanceHandle, VMContext};
+use wasmer_vm::{InstanceHandle, InstanceMemoryUsage, VMContext};
 
 /// A WebAssembly Instance is a stateful, executable
 /// instance of a WebAssembly [`Module`].
@@ -18,26 +20,107 @@ use wasmer_vm::{InstanceHandle, VMContext};
 /// interacting with WebAssembly.
 ///
 /// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#module-instances>
+///
+/// # Threads
+///
+/// An `Instance` is [`Send`], so it can be moved to the threads of a
+/// worker pool. This is sound because everything an instance can call
+/// back into the host is checked to be thread-safe when it is created:
+/// the closures of host functions must be `Send`, and their environments
+/// [`WasmerEnv`], which requires `Send + Sync`. A host function capturing
+/// an `Rc`, for example, doesn't compile:
+///
+/// ```compile_fail
+/// # use std::rc::Rc;
+/// # use wasmer::{Function, Store};
+/// # let store = Store::default();
+/// let counter = Rc::new(0);
+/// let function = Function::new_native(&store, move || *counter);
+/// ```
+///
+/// An `Instance` isn't [`Sync`] though, as the calls into it aren't
+/// synchronized, so it can't be shared between threads:
+///
+/// ```compile_fail
+/// # use wasmer::Instance;
+/// fn is_sync<T: Sync>() {}
+/// is_sync::<Instance>();
+/// ```
+///
+/// Give each worker its own instance of the [`Module`], which is cheap to
+/// clone, or wrap a shared instance in a [`Mutex`] to serialize the calls.
+/// The clones of an `Instance` share its state, so they mustn't be called
+/// from several threads at once either.
+///
+/// ```
+/// # use std::thread;
+/// # use wasmer::{imports, Instance, Module, Store};
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let module = Module::new(&store, r#"(module
+///     (func (export "double") (param i32) (result i32)
+///         (i32.mul (local.get 0) (i32.const 2))))"#)?;
+///
+/// let workers = (0..4)
+///     .map(|i| {
+///         let instance = Instance::new(&module, &imports! {})?;
+///         Ok(thread::spawn(move || {
+///             let double = instance
+///                 .exports
+///                 .get_native_function::<i32, i32>("double")
+///                 .unwrap();
+///             double.call(i).unwrap()
+///         }))
+///     })
+///     .collect::<anyhow::Result<Vec<_>>>()?;
+/// let results = workers
+///     .into_iter()
+///     .map(|worker| worker.join().unwrap())
+///     .collect::<Vec<_>>();
+/// assert_eq!(results, vec![0, 2, 4, 6]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`WasmerEnv`]: crate::WasmerEnv
 #[derive(Clone, MemoryUsage)]
 pub struct Instance {
     handle: Arc<Mutex<InstanceHandle>>,
     module: Module,
     /// The exports for an instance.
     pub exports: Exports,
+    /// Makes the instance `Send` but not `Sync`.
+    _not_sync: PhantomData<Cell<()>>,
 }
 
 #[cfg(test)]
 mod send_test {
     use super::*;
+    use crate::{Function, Global, Memory, NativeFunc, Table};
 
     fn is_send<T: Send>() -> bool {
         true
     }
 
+    fn is_sync<T: Sync>() -> bool {
+        true
+    }
+
     #[test]
     fn instance_is_send() {
         assert!(is_send::<Instance>());
     }
+
+    #[test]
+    fn exports_are_send_and_sync() {
+        assert!(is_send::<Module>() && is_sync::<Module>());
+        assert!(is_send::<Exports>() && is_sync::<Exports>());
+        assert!(is_send::<Function>() && is_sync::<Function>());
+        assert!(is_send::<NativeFunc<i32, i32>>() && is_sync::<NativeFunc<i32, i32>>());
+        assert!(is_send::<Memory>() && is_sync::<Memory>());
+        assert!(is_send::<Global>() && is_sync::<Global>());
+        assert!(is_send::<Table>() && is_sync::<Table>());
+    }
 }
 
 /// An error while instantiating a module.
@@ -115,13 +198,23 @@ impl Instance {
     pub fn new(module: &Module, resolver: &dyn Resolver) -> Result<Self, InstantiationError> {
         let store = module.store();
         let handle = module.instantiate(resolver)?;
+        // The exports are looked up by their declaration, rather than
+        // by their name, and without computing their types, as this is
+        // on the path of every instantiation.
+        //
+        // The signatures are already registered once per module, when
+        // its artifact is created. The exports are still built eagerly,
+        // as `Exports` hands out references to them, and the imports are
+        // resolved again on each instantiation, as a `Resolver` may
+        // return different externs each time.
         let exports = module
-            .exports()
-            .map(|export| {
-                let name = export.name().to_string();
-                let export = handle.lookup(&name).expect("export");
+            .info()
+            .exports
+            .iter()
+            .map(|(name, index)| {
+                let export = handle.lookup_by_declaration(index);
                 let extern_ = Extern::from_vm_export(store, export.into());
-                (name, extern_)
+                (name.to_string(), extern_)
             })
             .collect::<Exports>();
 
@@ -129,6 +222,7 @@ impl Instance {
             handle: Arc::new(Mutex::new(handle)),
             module: module.clone(),
             exports,
+            _not_sync: PhantomData,
         };
 
         // # Safety
@@ -160,6 +254,14 @@ impl Instance {
         self.module.store()
     }
 
+    /// Returns the memory used by this instance: the size of its linear
+    /// memories and tables, and of its runtime state.
+    ///
+    /// The memories and tables imported by the instance aren't counted.
+    pub fn memory_usage(&self) -> InstanceMemoryUsage {
+        self.handle.lock().unwrap().memory_usage()
+    }
+
     #[doc(hidden)]
     pub fn vmctx_ptr(&self) -> *mut VMContext {
         self.handle.lock().unwrap().vmctx_ptr()
@@ -0,0 +1,805 @@
+use crate::sys::producers::Producers;
+use crate::sys::stats::{FunctionStats, ModuleStats};
+use crate::sys::store::Store;
+use crate::sys::types::{ExportType, ImportType};
+use crate::InstantiationError;
+use loupe::MemoryUsage;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+#[cfg(feature = "wat")]
+use wasmer_compiler::WasmError;
+use wasmer_compiler::{CancellationToken, CompileError, CompiledFunctionFrameInfo, SymbolRegistry};
+use wasmer_engine::{
+    Artifact, CompilationMetrics, DeserializeError, FunctionExtent, ImportError, LinkError,
+    Resolver, SerializeError,
+};
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::{FunctionIndex, LocalFunctionIndex};
+use wasmer_vm::{ExportsIterator, ImportsIterator, InstanceHandle, ModuleInfo};
+
+#[derive(Error, Debug)]
+pub enum IoCompileError {
+    /// An IO error
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A compilation error
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+}
+
+/// A WebAssembly Module contains stateless WebAssembly
+/// code that has already been compiled and can be instantiated
+/// multiple times.
+///
+/// ## Cloning a module
+///
+/// Cloning a module is cheap: it does a shallow copy of the compiled
+/// contents rather than a deep copy.
+#[derive(Clone, MemoryUsage)]
+pub struct Module {
+    store: Store,
+    artifact: Arc<dyn Artifact>,
+}
+
+impl Module {
+    /// Creates a new WebAssembly Module given the configuration
+    /// in the store.
+    ///
+    /// If the provided bytes are not WebAssembly-like (start with `b"\0asm"`),
+    /// and the "wat" feature is enabled for this crate, this function will try to
+    /// to convert the bytes assuming they correspond to the WebAssembly text
+    /// format.
+    ///
+    /// ## Security
+    ///
+    /// Before the code is compiled, it will be validated using the store
+    /// features.
+    ///
+    /// ## Errors
+    ///
+    /// Creating a WebAssembly module from bytecode can result in a
+    /// [`CompileError`] since this operation requires to transorm the Wasm
+    /// bytecode into code the machine can easily execute.
+    ///
+    /// ## Example
+    ///
+    /// Reading from a WAT file.
+    ///
+    /// ```
+    /// use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = "(module)";
+    /// let module = Module::new(&store, wat)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Reading from bytes:
+    ///
+    /// ```
+    /// use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// // The following is the same as:
+    /// // (module
+    /// //   (type $t0 (func (param i32) (result i32)))
+    /// //   (func $add_one (export "add_one") (type $t0) (param $p0 i32) (result i32)
+    /// //     get_local $p0
+    /// //     i32.const 1
+    /// //     i32.add)
+    /// // )
+    /// let bytes: Vec<u8> = vec![
+    ///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x06, 0x01, 0x60,
+    ///     0x01, 0x7f, 0x01, 0x7f, 0x03, 0x02, 0x01, 0x00, 0x07, 0x0b, 0x01, 0x07,
+    ///     0x61, 0x64, 0x64, 0x5f, 0x6f, 0x6e, 0x65, 0x00, 0x00, 0x0a, 0x09, 0x01,
+    ///     0x07, 0x00, 0x20, 0x00, 0x41, 0x01, 0x6a, 0x0b, 0x00, 0x1a, 0x04, 0x6e,
+    ///     0x61, 0x6d, 0x65, 0x01, 0x0a, 0x01, 0x00, 0x07, 0x61, 0x64, 0x64, 0x5f,
+    ///     0x6f, 0x6e, 0x65, 0x02, 0x07, 0x01, 0x00, 0x01, 0x00, 0x02, 0x70, 0x30,
+    /// ];
+    /// let module = Module::new(&store, bytes)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(store: &Store, bytes: impl AsRef<[u8]>) -> Result<Self, CompileError> {
+        let bytes = Self::parse_wat(bytes.as_ref())?;
+        Self::from_binary(store, &bytes)
+    }
+
+    /// Creates a new WebAssembly module like [`Module::new`], failing
+    /// with [`CompileError::Cancelled`] once `token` is cancelled or its
+    /// timeout expires.
+    ///
+    /// The token can be cancelled from another thread while the module
+    /// compiles. The cancellation is sticky, so a new token is needed
+    /// for each compilation.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let token = CancellationToken::with_timeout(Duration::from_secs(10));
+    /// let module = Module::new_cancellable(&store, "(module)", &token)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_cancellable(
+        store: &Store,
+        bytes: impl AsRef<[u8]>,
+        token: &CancellationToken,
+    ) -> Result<Self, CompileError> {
+        let bytes = Self::parse_wat(bytes.as_ref())?;
+        Self::validate(store, &bytes)?;
+        let artifact = store
+            .engine()
+            .compile_cancellable(&bytes, store.tunables(), token)?;
+        Ok(Self::from_artifact(store, artifact))
+    }
+
+    /// Converts the bytes to a Wasm binary if they're in the text format
+    /// and the "wat" feature is enabled.
+    fn parse_wat(bytes: &[u8]) -> Result<Cow<[u8]>, CompileError> {
+        #[cfg(feature = "wat")]
+        let bytes = wat::parse_bytes(bytes).map_err(|e| {
+            CompileError::Wasm(WasmError::Generic(format!(
+                "Error when converting wat: {}",
+                e
+            )))
+        })?;
+        #[cfg(not(feature = "wat"))]
+        let bytes = Cow::Borrowed(bytes);
+        Ok(bytes)
+    }
+
+    /// Creates a new WebAssembly module from a file path.
+    pub fn from_file(store: &Store, file: impl AsRef<Path>) -> Result<Self, IoCompileError> {
+        let file_ref = file.as_ref();
+        let canonical = file_ref.canonicalize()?;
+        let wasm_bytes = std::fs::read(file_ref)?;
+        let mut module = Self::new(store, &wasm_bytes)?;
+        // Set the module name to the absolute path of the filename.
+        // This is useful for debugging the stack traces.
+        let filename = canonical.as_path().to_str().unwrap();
+        module.set_name(filename);
+        Ok(module)
+    }
+
+    /// Creates a new WebAssembly module from a binary.
+    ///
+    /// Opposed to [`Module::new`], this function is not compatible with
+    /// the WebAssembly text format (if the "wat" feature is enabled for
+    /// this crate).
+    pub fn from_binary(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
+        Self::validate(store, binary)?;
+        unsafe { Self::from_binary_unchecked(store, binary) }
+    }
+
+    /// Creates a new WebAssembly module skipping any kind of validation.
+    ///
+    /// # Safety
+    ///
+    /// This can speed up compilation time a bit, but it should be only used
+    /// in environments where the WebAssembly modules are trusted and validated
+    /// beforehand.
+    pub unsafe fn from_binary_unchecked(
+        store: &Store,
+        binary: &[u8],
+    ) -> Result<Self, CompileError> {
+        let module = Self::compile(store, binary)?;
+        Ok(module)
+    }
+
+    /// Validates a new WebAssembly Module given the configuration
+    /// in the Store.
+    ///
+    /// This validation is normally pretty fast and checks the enabled
+    /// WebAssembly features in the Store Engine to assure deterministic
+    /// validation of the Module.
+    pub fn validate(store: &Store, binary: &[u8]) -> Result<(), CompileError> {
+        store.engine().validate(binary)
+    }
+
+    fn compile(store: &Store, binary: &[u8]) -> Result<Self, CompileError> {
+        let artifact = store.engine().compile(binary, store.tunables())?;
+        Ok(Self::from_artifact(store, artifact))
+    }
+
+    /// Serializes a module into a binary representation that the `Engine`
+    /// can later process via [`Module::deserialize`].
+    ///
+    /// With the Universal engine, compiling the same Wasm binary with the
+    /// same compiler configuration, features and target always produces
+    /// the same serialized bytes, so they can be content-addressed.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// # let module = Module::from_file(&store, "path/to/foo.wasm")?;
+    /// let serialized = module.serialize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        self.artifact.serialize()
+    }
+
+    /// Serializes a module into a binary like [`Module::serialize`],
+    /// without the information only used to debug it.
+    ///
+    /// The serialized module is smaller and faster to load, but the
+    /// backtraces of its traps are less precise, and native debuggers
+    /// and profilers may not unwind through its code. Each engine
+    /// documents what it strips; the engines that can't strip their
+    /// artifacts return an error.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// # let module = Module::from_file(&store, "path/to/foo.wasm")?;
+    /// let serialized = module.serialize_stripped()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serialize_stripped(&self) -> Result<Vec<u8>, SerializeError> {
+        self.artifact.serialize_stripped()
+    }
+
+    /// Serializes a module into a file that the `Engine`
+    /// can later process via [`Module::deserialize_from_file`].
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// # let module = Module::from_file(&store, "path/to/foo.wasm")?;
+    /// module.serialize_to_file("path/to/foo.so")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serialize_to_file(&self, path: impl AsRef<Path>) -> Result<(), SerializeError> {
+        self.artifact.serialize_to_file(path.as_ref())
+    }
+
+    /// Serializes a module into a writer, such as a file or a socket,
+    /// that the `Engine` can later process via
+    /// [`Module::deserialize_from_reader`].
+    ///
+    /// Contrary to [`Module::serialize`], the serialized artifact is not
+    /// returned as a whole but written directly into `writer`.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// # let module = Module::from_file(&store, "path/to/foo.wasm")?;
+    /// let mut file = std::fs::File::create("path/to/foo.wasmu")?;
+    /// module.serialize_to_writer(&mut file)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serialize_to_writer<W: Write>(&self, writer: &mut W) -> Result<(), SerializeError> {
+        self.artifact.serialize_to_writer(writer)
+    }
+
+    /// Deserializes a serialized Module binary into a `Module`.
+    /// > Note: the module has to be serialized before with the `serialize` method.
+    ///
+    /// # Safety
+    ///
+    /// This function is inherently **unsafe** as the provided bytes:
+    /// 1. Are going to be deserialized directly into Rust objects.
+    /// 2. Contains the function assembly bodies and, if intercepted,
+    ///    a malicious actor could inject code into executable
+    ///    memory.
+    ///
+    /// And as such, the `deserialize` method is unsafe.
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::deserialize(&store, serialized_data)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn deserialize(store: &Store, bytes: &[u8]) -> Result<Self, DeserializeError> {
+        let artifact = store.engine().deserialize(bytes)?;
+        Ok(Self::from_artifact(store, artifact))
+    }
+
+    /// Deserializes a a serialized Module located in a `Path` into a `Module`.
+    /// > Note: the module has to be serialized before with the `serialize` method.
+    ///
+    /// # Safety
+    ///
+    /// Please check [`Module::deserialize`].
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # let store = Store::default();
+    /// # fn main() -> anyhow::Result<()> {
+    /// let module = Module::deserialize_from_file(&store, path)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn deserialize_from_file(
+        store: &Store,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, DeserializeError> {
+        let artifact = store.engine().deserialize_from_file(path.as_ref())?;
+        Ok(Self::from_artifact(store, artifact))
+    }
+
+    /// Deserializes a serialized Module read from a reader, such as a
+    /// file or a socket, into a `Module`.
+    /// > Note: the module has to be serialized before with the `serialize`
+    /// > or `serialize_to_writer` methods.
+    ///
+    /// # Safety
+    ///
+    /// Please check [`Module::deserialize`].
+    ///
+    /// # Usage
+    ///
+    /// ```ignore
+    /// # use wasmer::*;
+    /// # let store = Store::default();
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut file = std::fs::File::open("path/to/foo.wasmu")?;
+    /// let module = Module::deserialize_from_reader(&store, &mut file)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub unsafe fn deserialize_from_reader<R: Read>(
+        store: &Store,
+        reader: &mut R,
+    ) -> Result<Self, DeserializeError> {
+        let artifact = store.engine().deserialize_from_reader(reader)?;
+        Ok(Self::from_artifact(store, artifact))
+    }
+
+    /// Moves the module to another [`Store`], which may use another
+    /// [`Engine`], so that it can be instantiated with the externs of that
+    /// store.
+    ///
+    /// When `store` uses the engine of the module, or a clone of it, the
+    /// artifact of the module is shared. Otherwise, it is serialized and
+    /// deserialized by the engine of `store`, without compiling the module
+    /// again. This is safe, as the serialized artifact never leaves the
+    /// process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the engine of `store` can't load the artifacts of
+    /// the engine of the module, like a Dylib engine loading the artifacts
+    /// of a Universal engine, or artifacts compiled for another target.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let module = Module::new(&store, "(module)")?;
+    ///
+    /// let other_store = Store::default();
+    /// let moved = module.clone_into(&other_store)?;
+    /// assert!(Store::same(moved.store(), &other_store));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Engine`]: wasmer_engine::Engine
+    pub fn clone_into(&self, store: &Store) -> Result<Self, DeserializeError> {
+        if Store::same(&self.store, store) {
+            return Ok(Self::from_artifact(store, self.artifact.clone()));
+        }
+        let bytes = self.serialize().map_err(|error| match error {
+            SerializeError::Io(error) => DeserializeError::Io(error),
+            SerializeError::Generic(message) => DeserializeError::Generic(message),
+        })?;
+        // Safety: the bytes were just serialized by the engine of the module
+        unsafe { Self::deserialize(store, &bytes) }
+    }
+
+    fn from_artifact(store: &Store, artifact: Arc<dyn Artifact>) -> Self {
+        Self {
+            store: store.clone(),
+            artifact,
+        }
+    }
+
+    pub(crate) fn instantiate(
+        &self,
+        resolver: &dyn Resolver,
+    ) -> Result<InstanceHandle, InstantiationError> {
+        self.check_import_engines(resolver)?;
+        unsafe {
+            let instance_handle = self.artifact.instantiate(
+                self.store.tunables(),
+                resolver,
+                Box::new((self.store.clone(), self.artifact.clone())),
+            )?;
+
+            // After the instance handle is created, we need to initialize
+            // the data, call the start function and so. However, if any
+            // of this steps traps, we still need to keep the instance alive
+            // as some of the Instance elements may have placed in other
+            // instance tables.
+            self.artifact
+                .finish_instantiation(&self.store, &instance_handle)?;
+
+            Ok(instance_handle)
+        }
+    }
+
+    /// Checks the imports don't come from another engine, which can only
+    /// be detected when the resolver knows where they come from.
+    fn check_import_engines(&self, resolver: &dyn Resolver) -> Result<(), InstantiationError> {
+        for (index, import) in self.imports().enumerate() {
+            let engine = match resolver.resolve_engine(index as u32, import.module(), import.name())
+            {
+                Some(engine) => engine,
+                None => continue,
+            };
+            if engine.id() != self.store.engine().id() {
+                return Err(InstantiationError::Link(LinkError::Import(
+                    import.module().to_string(),
+                    import.name().to_string(),
+                    ImportError::IncompatibleEngine,
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the name of the current module.
+    ///
+    /// This name is normally set in the WebAssembly bytecode by some
+    /// compilers, but can be also overwritten using the [`Module::set_name`] method.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = "(module $moduleName)";
+    /// let module = Module::new(&store, wat)?;
+    /// assert_eq!(module.name(), Some("moduleName"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn name(&self) -> Option<&str> {
+        self.artifact.module_ref().name.as_deref()
+    }
+
+    /// Sets the name of the current module.
+    /// This is normally useful for stacktraces and debugging.
+    ///
+    /// It will return `true` if the module name was changed successfully,
+    /// and return `false` otherwise (in case the module is already
+    /// instantiated).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = "(module)";
+    /// let mut module = Module::new(&store, wat)?;
+    /// assert_eq!(module.name(), None);
+    /// module.set_name("foo");
+    /// assert_eq!(module.name(), Some("foo"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_name(&mut self, name: &str) -> bool {
+        Arc::get_mut(&mut self.artifact)
+            .and_then(|artifact| artifact.module_mut())
+            .map(|mut module_info| {
+                module_info.name = Some(name.to_string());
+                true
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns an iterator over the imported types in the Module.
+    ///
+    /// The order of the imports is guaranteed to be the same as in the
+    /// WebAssembly bytecode.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (import "host" "func1" (func))
+    ///     (import "host" "func2" (func))
+    /// )"#;
+    /// let module = Module::new(&store, wat)?;
+    /// for import in module.imports() {
+    ///     assert_eq!(import.module(), "host");
+    ///     assert!(import.name().contains("func"));
+    ///     import.ty();
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn imports<'a>(&'a self) -> ImportsIterator<impl Iterator<Item = ImportType> + 'a> {
+        self.artifact.module_ref().imports()
+    }
+
+    /// Returns an iterator over the exported types in the Module.
+    ///
+    /// The order of the exports is guaranteed to be the same as in the
+    /// WebAssembly bytecode.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (func (export "namedfunc"))
+    ///     (memory (export "namedmemory") 1)
+    /// )"#;
+    /// let module = Module::new(&store, wat)?;
+    /// for export_ in module.exports() {
+    ///     assert!(export_.name().contains("named"));
+    ///     export_.ty();
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exports<'a>(&'a self) -> ExportsIterator<impl Iterator<Item = ExportType> + 'a> {
+        self.artifact.module_ref().exports()
+    }
+
+    /// Get the custom sections of the module given a `name`.
+    ///
+    /// # Important
+    ///
+    /// Following the WebAssembly spec, one name can have multiple
+    /// custom sections. That's why an iterator (rather than one element)
+    /// is returned.
+    ///
+    /// The `name` section is parsed at compilation and isn't kept, see
+    /// [`Module::name`] and [`Module::function_names`] instead.
+    pub fn custom_sections<'a>(&'a self, name: &'a str) -> impl Iterator<Item = Arc<[u8]>> + 'a {
+        self.artifact.module_ref().custom_sections(name)
+    }
+
+    /// Returns the name of the function at `index`, from the name section
+    /// of the module, if any.
+    ///
+    /// The index is the one of the function in the function index space,
+    /// which starts with the imported functions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (import "host" "log" (func $log (param i32)))
+    ///     (func $start)
+    /// )"#;
+    /// let module = Module::new(&store, wat)?;
+    /// assert_eq!(module.function_name(0), Some("log"));
+    /// assert_eq!(module.function_name(1), Some("start"));
+    /// assert_eq!(module.function_name(2), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn function_name(&self, index: u32) -> Option<&str> {
+        self.artifact
+            .module_ref()
+            .function_names
+            .get(&FunctionIndex::from_u32(index))
+            .map(|name| &**name)
+    }
+
+    /// Returns the names of the functions from the name section of the
+    /// module, with their indices, ordered by index.
+    pub fn function_names(&self) -> impl Iterator<Item = (u32, &str)> {
+        let mut names = self
+            .artifact
+            .module_ref()
+            .function_names
+            .iter()
+            .map(|(index, name)| (index.as_u32(), &**name))
+            .collect::<Vec<_>>();
+        names.sort_unstable_by_key(|&(index, _)| index);
+        names.into_iter()
+    }
+
+    /// Returns the parsed `producers` custom section of the module, listing
+    /// the languages and tools it was produced with.
+    ///
+    /// Returns `None` if the module has no producers section, or if it is
+    /// malformed.
+    pub fn producers(&self) -> Option<Producers> {
+        self.custom_sections("producers")
+            .next()
+            .and_then(|data| Producers::parse(&data))
+    }
+
+    /// Returns statistics about the size and complexity of the module, like
+    /// the sizes of its functions and data segments, for example to enforce
+    /// limits on the modules accepted by a platform.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"(module
+    ///     (memory 1)
+    ///     (func $sum (export "sum") (param i32 i32) (result i32) (local i64 i64)
+    ///         (i32.add (local.get 0) (local.get 1)))
+    ///     (data (i32.const 0) "hello")
+    /// )"#;
+    /// let module = Module::new(&store, wat)?;
+    /// let stats = module.stats();
+    /// assert_eq!(stats.num_exports, 1);
+    /// assert_eq!(stats.functions[0].name.as_deref(), Some("sum"));
+    /// assert_eq!(stats.functions[0].num_params, 2);
+    /// assert_eq!(stats.functions[0].num_locals, 2);
+    /// assert_eq!(stats.num_data_segments, 1);
+    /// assert_eq!(stats.data_size, 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> ModuleStats {
+        let info = self.artifact.module_ref();
+        let functions = info
+            .function_bodies
+            .iter()
+            .map(|(local_index, body)| {
+                let index = info.func_index(local_index);
+                FunctionStats {
+                    index: index.as_u32(),
+                    name: info.function_names.get(&index).map(|name| name.to_string()),
+                    code_size: body.size,
+                    num_params: info.signatures[info.functions[index]].params().len(),
+                    num_locals: body.num_locals,
+                }
+            })
+            .collect::<Vec<_>>();
+        let data_initializers = self.artifact.data_initializers();
+        ModuleStats {
+            num_imports: info.imports.len(),
+            num_exports: info.exports.len(),
+            num_imported_functions: info.num_imported_functions,
+            code_size: functions.iter().map(|f| f.code_size as u64).sum(),
+            functions,
+            num_data_segments: data_initializers.len() + info.passive_data.len(),
+            data_size: data_initializers
+                .iter()
+                .map(|data| data.data.len() as u64)
+                .chain(info.passive_data.values().map(|data| data.len() as u64))
+                .sum(),
+        }
+    }
+
+    /// Returns the metrics measured while compiling the module, like the
+    /// time spent in each phase or compiling each function.
+    ///
+    /// They're only available if the module was compiled rather than
+    /// deserialized, with an engine measuring them, like the Universal
+    /// engine.
+    pub fn compilation_metrics(&self) -> Option<&CompilationMetrics> {
+        self.artifact.compilation_metrics()
+    }
+
+    /// Returns the location and size of the machine code of the functions
+    /// defined by the module, for profilers and debuggers to symbolize
+    /// addresses, if the engine knows them.
+    pub fn function_extents(&self) -> Option<PrimaryMap<LocalFunctionIndex, FunctionExtent>> {
+        self.artifact.function_extents()
+    }
+
+    /// Returns the addresses of the trampolines calling the functions of
+    /// the module from the host, indexed by signature.
+    pub fn function_call_trampolines(&self) -> Vec<usize> {
+        self.artifact
+            .finished_function_call_trampolines()
+            .values()
+            .map(|&trampoline| trampoline as usize)
+            .collect()
+    }
+
+    /// Returns the addresses of the trampolines calling the dynamic host
+    /// functions from the module, indexed by imported function.
+    pub fn dynamic_function_trampolines(&self) -> Vec<usize> {
+        self.artifact
+            .finished_dynamic_function_trampolines()
+            .values()
+            .map(|trampoline| trampoline.0 as usize)
+            .collect()
+    }
+
+    /// Returns the frame information of the functions defined by the
+    /// module: the offsets of their traps, and the mapping of their
+    /// instructions to the offsets in the Wasm binary, if the engine keeps
+    /// it, like the Universal engine.
+    pub fn function_frame_info(
+        &self,
+    ) -> Option<&PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>> {
+        self.artifact.function_frame_info()
+    }
+
+    /// Returns the `.eh_frame` unwind information registered for the code
+    /// of the module, as it's mapped in memory, if any.
+    pub fn eh_frame(&self) -> Option<&[u8]> {
+        self.artifact.eh_frame()
+    }
+
+    /// Returns the registry naming the functions, sections and trampolines
+    /// of the module in the object file it was loaded from, for the engines
+    /// using object files, like the Dylib and Staticlib engines.
+    pub fn symbol_registry(&self) -> Option<&dyn SymbolRegistry> {
+        self.artifact.symbol_registry()
+    }
+
+    /// Returns the [`Store`] where the `Instance` belongs.
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /// The ABI of the ModuleInfo is very unstable, we refactor it very often.
+    /// This function is public because in some cases it can be useful to get some
+    /// extra information from the module.
+    ///
+    /// However, the usage is highly discouraged.
+    #[doc(hidden)]
+    pub fn info(&self) -> &ModuleInfo {
+        &self.artifact.module_ref()
+    }
+
+    /// Gets the [`Artifact`] used internally by the Module.
+    ///
+    /// This API is hidden because it's not necessarily stable;
+    /// this functionality is required for some core functionality though, like
+    /// the object file engine.
+    #[doc(hidden)]
+    pub fn artifact(&self) -> &Arc<dyn Artifact> {
+        &self.artifact
+    }
+}
+
+impl fmt::Debug for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Module")
+            .field("name", &self.name())
+            .finish()
+    }
+}
@@ -9,7 +9,7 @@
 //! ```
 use std::marker::PhantomData;
 
-use crate::externals::function::{DynamicFunction, VMDynamicFunction};
+use crate::sys::externals::function::{DynamicCall, DynamicFunction, VMDynamicFunction};
 use crate::{FromToNativeWasmType, Function, RuntimeError, Store, WasmTypeList};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use wasmer_engine::ExportFunction;
@@ -195,25 +195,33 @@ macro_rules! impl_native_traits {
                                 let f = std::mem::transmute::<_, unsafe extern "C" fn( VMFunctionEnvironment, $( $x, )*) -> Rets::CStruct>(self.address());
                                 // We always pass the vmctx
                                 f( self.vmctx(), $( $x, )* )
-                            })).map_err(|e| RuntimeError::new(format!("{:?}", e)))?;
+                            })).map_err(RuntimeError::from_panic)?;
                             Ok(Rets::from_c_struct(results))
                         },
                         VMFunctionKind::Dynamic => {
                             let params_list = [ $( $x.to_native().to_value() ),* ];
-                            let results = {
-                                type VMContextWithEnv = VMDynamicFunctionContext<DynamicFunction<std::ffi::c_void>>;
+                            let mut rets_list_array = Rets::empty_array();
+                            let num_rets = rets_list_array.as_mut().len();
+                            // The results overwrite the arguments
+                            let mut values_vec = vec![0i128; params_list.len().max(num_rets)];
+                            for (i, param) in params_list.iter().enumerate() {
                                 unsafe {
-                                    let ctx = self.vmctx().host_env as *mut VMContextWithEnv;
-                                    (*ctx).ctx.call(&params_list)?
+                                    param.write_value_to(&mut values_vec[i]);
                                 }
-                            };
-                            let mut rets_list_array = Rets::empty_array();
-                            let mut_rets = rets_list_array.as_mut() as *mut [i128] as *mut i128;
-                            for (i, ret) in results.iter().enumerate() {
+                            }
+                            {
+                                type VMContextWithEnv = VMDynamicFunctionContext<DynamicFunction<std::ffi::c_void>>;
                                 unsafe {
-                                    ret.write_value_to(mut_rets.add(i));
+                                    let ctx = self.vmctx().host_env as *mut VMContextWithEnv;
+                                    let call = DynamicCall::new(
+                                        (*ctx).ctx.store(),
+                                        (*ctx).ctx.function_type(),
+                                        values_vec.as_mut_ptr(),
+                                    );
+                                    (*ctx).ctx.call(call)?;
                                 }
                             }
+                            rets_list_array.as_mut().copy_from_slice(&values_vec[..num_rets]);
                             Ok(Rets::from_array(rets_list_array))
                         }
                     }
@@ -223,14 +231,16 @@ macro_rules! impl_native_traits {
         }
 
         #[allow(unused_parens)]
-        impl<'a, $( $x, )* Rets> crate::exports::ExportableWithGenerics<'a, ($( $x ),*), Rets> for NativeFunc<( $( $x ),* ), Rets>
+        impl<'a, $( $x, )* Rets> crate::sys::exports::ExportableWithGenerics<'a, ($( $x ),*), Rets> for NativeFunc<( $( $x ),* ), Rets>
         where
             $( $x: FromToNativeWasmType, )*
             Rets: WasmTypeList,
         {
-            fn get_self_from_extern_with_generics(_extern: &crate::externals::Extern) -> Result<Self, crate::exports::ExportError> {
-                use crate::exports::Exportable;
-                crate::Function::get_self_from_extern(_extern)?.native().map_err(|_| crate::exports::ExportError::IncompatibleType)
+            fn get_self_from_extern_with_generics(_extern: &crate::sys::externals::Extern) -> Result<Self, crate::sys::exports::ExportError> {
+                use crate::sys::exports::Exportable;
+                let function = crate::Function::get_self_from_extern(_extern)?;
+                // The name is filled in by `Exports::get_with_generics`
+                function.native().map_err(|_| crate::sys::exports::ExportError::incompatible_signature::<( $( $x ),* ), Rets>("", function.ty()))
             }
 
             fn into_weak_instance_ref(&mut self) {
@@ -267,3 +277,50 @@ impl_native_traits!(
 impl_native_traits!(
     A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20
 );
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25, A26
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25, A26, A27
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25, A26, A27, A28
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25, A26, A27, A28, A29
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25, A26, A27, A28, A29, A30
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25, A26, A27, A28, A29, A30, A31
+);
+impl_native_traits!(
+    A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16, A17, A18, A19, A20, A21,
+    A22, A23, A24, A25, A26, A27, A28, A29, A30, A31, A32
+);
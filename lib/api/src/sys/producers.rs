@@ -0,0 +1,131 @@
+//! Parsing of the [producers section], recording the languages and tools
+//! a module was produced with.
+//!
+//! [producers section]: https://github.com/WebAssembly/tool-conventions/blob/main/ProducersSection.md
+
+/// The content of the `producers` custom section of a module, returned by
+/// [`Module::producers`](crate::Module::producers).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Producers {
+    fields: Vec<ProducersField>,
+}
+
+/// A field of the producers section, like `language`, `processed-by` or
+/// `sdk`, with the producers it lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProducersField {
+    /// The name of the field.
+    pub name: String,
+    /// The producers listed in the field, in order.
+    pub values: Vec<ProducerVersion>,
+}
+
+/// A producer, like `rustc` or `clang`, with its version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProducerVersion {
+    /// The name of the producer.
+    pub name: String,
+    /// The version of the producer, which may be empty.
+    pub version: String,
+}
+
+impl Producers {
+    /// Parses the data of a producers section, returning `None` if it's
+    /// malformed.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader { data };
+        let fields = (0..reader.read_u32()?)
+            .map(|_| {
+                let name = reader.read_string()?;
+                let values = (0..reader.read_u32()?)
+                    .map(|_| {
+                        Some(ProducerVersion {
+                            name: reader.read_string()?,
+                            version: reader.read_string()?,
+                        })
+                    })
+                    .collect::<Option<_>>()?;
+                Some(ProducersField { name, values })
+            })
+            .collect::<Option<_>>()?;
+        if !reader.data.is_empty() {
+            return None;
+        }
+        Some(Self { fields })
+    }
+
+    /// Returns the fields of the section, in order.
+    pub fn fields(&self) -> &[ProducersField] {
+        &self.fields
+    }
+
+    /// Returns the field with the given name, if any.
+    pub fn field(&self, name: &str) -> Option<&ProducersField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Reads an unsigned LEB128 32 bits integer.
+    fn read_u32(&mut self) -> Option<u32> {
+        let mut result = 0u32;
+        for shift in (0..35).step_by(7) {
+            let (&byte, rest) = self.data.split_first()?;
+            self.data = rest;
+            if shift == 28 && byte > 0x0f {
+                return None;
+            }
+            result |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    fn read_string(&mut self) -> Option<String> {
+        let len = self.read_u32()? as usize;
+        if len > self.data.len() {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_producers() {
+        let data = b"\x02\
+            \x08language\x01\x04Rust\x00\
+            \x0cprocessed-by\x02\x05rustc\x061.51.0\x05clang\x0212";
+        let producers = Producers::parse(data).unwrap();
+        assert_eq!(producers.fields().len(), 2);
+        assert_eq!(
+            producers.field("language").unwrap().values,
+            vec![ProducerVersion {
+                name: "Rust".to_string(),
+                version: "".to_string(),
+            }]
+        );
+        let processed_by = &producers.field("processed-by").unwrap().values;
+        assert_eq!(processed_by[0].name, "rustc");
+        assert_eq!(processed_by[0].version, "1.51.0");
+        assert_eq!(processed_by[1].name, "clang");
+        assert_eq!(processed_by[1].version, "12");
+        assert!(producers.field("sdk").is_none());
+
+        // Truncated, or with trailing bytes
+        assert!(Producers::parse(&data[..data.len() - 1]).is_none());
+        assert!(Producers::parse(b"\x00\x00").is_none());
+        assert_eq!(Producers::parse(b"\x00"), Some(Producers::default()));
+    }
+}
@@ -6,7 +6,7 @@
 //! Therefore, you should use this abstraction whenever possible to avoid memory
 //! related bugs when implementing an ABI.
 
-use crate::{externals::Memory, FromToNativeWasmType};
+use crate::sys::{externals::Memory, FromToNativeWasmType, WasmCell};
 use std::{cell::Cell, fmt, marker::PhantomData, mem};
 use wasmer_types::ValueType;
 
@@ -34,7 +34,25 @@ pub struct Item;
 /// ```
 ///
 /// This type can also be used with primitive-filled structs, but be careful of
-/// guarantees required by `ValueType`.
+/// guarantees required by `ValueType`. The derive macro checks them for
+/// `#[repr(C)]` structs, which may contain other `WasmPtr`s:
+/// ```
+/// # use wasmer::{Array, Memory, ValueType, WasmPtr};
+/// #[derive(Copy, Clone, ValueType)]
+/// #[repr(C)]
+/// struct Buffer {
+///     data: WasmPtr<u8, Array>,
+///     len: u32,
+/// }
+///
+/// fn read_buffer(memory: &Memory, ptr: WasmPtr<Buffer>) -> Option<Vec<u8>> {
+///     let buffer = ptr.deref(memory)?.get();
+///     let bytes = buffer.data.slice(memory, buffer.len)?;
+///     Some(bytes.iter().map(|byte| byte.get()).collect())
+/// }
+/// ```
+///
+/// `ValueType` may also be implemented manually:
 /// ```
 /// # use wasmer::Memory;
 /// # use wasmer::WasmPtr;
@@ -96,14 +114,17 @@ fn align_pointer(ptr: usize, align: usize) -> usize {
 /// that implement [`ValueType`], meaning that they're valid for all possible
 /// bit patterns.
 impl<T: Copy + ValueType> WasmPtr<T, Item> {
-    /// Dereference the `WasmPtr` getting access to a `&Cell<T>` allowing for
-    /// reading and mutating of the inner value.
+    /// Dereference the `WasmPtr` getting access to a [`WasmCell`] allowing
+    /// for reading and mutating of the inner value.
+    ///
+    /// It returned a `&Cell<T>` up to 2.0.0-rc2, which the `js` backend can't
+    /// provide: [`WasmCell`] has the same `get` and `set` methods.
     ///
     /// This method is unsound if used with unsynchronized shared memory.
     /// If you're unsure what that means, it likely does not apply to you.
     /// This invariant will be enforced in the future.
     #[inline]
-    pub fn deref<'a>(self, memory: &'a Memory) -> Option<&'a Cell<T>> {
+    pub fn deref<'a>(self, memory: &'a Memory) -> Option<WasmCell<'a, T>> {
         if (self.offset as usize) + mem::size_of::<T>() > memory.size().bytes().0
             || mem::size_of::<T>() == 0
         {
@@ -114,7 +135,7 @@ impl<T: Copy + ValueType> WasmPtr<T, Item> {
                 memory.view::<u8>().as_ptr().add(self.offset as usize) as usize,
                 mem::align_of::<T>(),
             ) as *const Cell<T>;
-            Some(&*cell_ptr)
+            Some(WasmCell::new(&*cell_ptr))
         }
     }
 
@@ -144,14 +165,22 @@ impl<T: Copy + ValueType> WasmPtr<T, Item> {
 /// types that implement [`ValueType`], meaning that they're valid for all
 /// possible bit patterns.
 impl<T: Copy + ValueType> WasmPtr<T, Array> {
-    /// Dereference the `WasmPtr` getting access to a `&[Cell<T>]` allowing for
-    /// reading and mutating of the inner values.
+    /// Dereference the `WasmPtr` getting access to a [`WasmCell`] for each
+    /// of the `length` items from `index`, allowing for reading and mutating
+    /// of the inner values.
+    ///
+    /// It returned a `&[Cell<T>]` up to 2.0.0-rc2, see [`WasmPtr::deref`].
     ///
     /// This method is unsound if used with unsynchronized shared memory.
     /// If you're unsure what that means, it likely does not apply to you.
     /// This invariant will be enforced in the future.
     #[inline]
-    pub fn deref(self, memory: &Memory, index: u32, length: u32) -> Option<&[Cell<T>]> {
+    pub fn deref<'a>(
+        self,
+        memory: &'a Memory,
+        index: u32,
+        length: u32,
+    ) -> Option<Vec<WasmCell<'a, T>>> {
         // gets the size of the item in the array with padding added such that
         // for any index, we will always result an aligned memory access
         let item_size = mem::size_of::<T>();
@@ -172,7 +201,7 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
             ) as *const Cell<T>;
             let cell_ptrs = &std::slice::from_raw_parts(cell_ptr, slice_full_len)
                 [index as usize..slice_full_len];
-            Some(cell_ptrs)
+            Some(cell_ptrs.iter().map(WasmCell::new).collect())
         }
     }
 
@@ -212,6 +241,26 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
         Some(cell_ptrs)
     }
 
+    /// Dereference the `len` items starting at this `WasmPtr`, like
+    /// [`WasmPtr::deref`] from index 0.
+    #[inline]
+    pub fn slice<'a>(self, memory: &'a Memory, len: u32) -> Option<Vec<WasmCell<'a, T>>> {
+        self.deref(memory, 0, len)
+    }
+
+    /// Get a `WasmPtr` to the item at `index` in the array, or `None` if its
+    /// offset doesn't fit in 32 bits.
+    ///
+    /// It isn't checked against the bounds of a memory until it is
+    /// dereferenced.
+    #[inline]
+    pub fn index(self, index: u32) -> Option<WasmPtr<T, Item>> {
+        let offset = (mem::size_of::<T>() as u32)
+            .checked_mul(index)
+            .and_then(|relative| self.offset.checked_add(relative))?;
+        Some(WasmPtr::new(offset))
+    }
+
     /// Get a UTF-8 string from the `WasmPtr` with the given length.
     ///
     /// Note that . The
@@ -253,18 +302,7 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
             return None;
         }
 
-        // TODO: benchmark the internals of this function: there is likely room for
-        // micro-optimization here and this may be a fairly common function in user code.
-        let view = memory.view::<u8>();
-
-        let mut vec: Vec<u8> = Vec::with_capacity(str_len as usize);
-        let base = self.offset as usize;
-        for i in 0..(str_len as usize) {
-            let byte = view[base + i].get();
-            vec.push(byte);
-        }
-
-        String::from_utf8(vec).ok()
+        memory.read_utf8(self.offset as u64, str_len as usize).ok()
     }
 
     /// Get a UTF-8 string from the `WasmPtr`, where the string is nul-terminated.
@@ -276,7 +314,9 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     /// This method behaves similarly to [`WasmPtr::get_utf8_str`], all safety invariants on
     /// that method must also be upheld here.
     pub unsafe fn get_utf8_str_with_nul<'a>(self, memory: &'a Memory) -> Option<&'a str> {
-        memory.view::<u8>()[(self.offset as usize)..]
+        memory
+            .view::<u8>()
+            .get((self.offset as usize)..)?
             .iter()
             .map(|cell| cell.get())
             .position(|byte| byte == 0)
@@ -290,6 +330,25 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     pub fn get_utf8_string_with_nul(self, memory: &Memory) -> Option<String> {
         unsafe { self.get_utf8_str_with_nul(memory) }.map(|s| s.to_owned())
     }
+
+    /// Get a UTF-8 `String` from the `WasmPtr`, where the string is
+    /// nul-terminated within its first `max_len` bytes.
+    ///
+    /// Unlike [`WasmPtr::get_utf8_string_with_nul`], a missing terminator
+    /// doesn't make it read the whole memory, which guards against
+    /// untrusted guests.
+    pub fn get_utf8_string_with_nul_max(self, memory: &Memory, max_len: u32) -> Option<String> {
+        let memory_size = memory.size().bytes().0;
+        if self.offset as usize >= memory_size {
+            return None;
+        }
+        let start = self.offset as usize;
+        let end = memory_size.min(start + max_len as usize);
+        let len = memory.view::<u8>()[start..end]
+            .iter()
+            .position(|byte| byte.get() == 0)?;
+        self.get_utf8_string(memory, len as u32)
+    }
 }
 
 unsafe impl<T: Copy, Ty> FromToNativeWasmType for WasmPtr<T, Ty> {
@@ -423,4 +482,33 @@ mod test {
             assert!(unsafe { oob_end_array_ptr.deref_mut(&memory, 1, 0).is_none() });
         }
     }
+    #[test]
+    fn wasm_ptr_slices_and_strings() {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, Some(1), false)).unwrap();
+        let memory_size = memory.size().bytes().0 as u32;
+        memory.write(16, b"hello\0").unwrap();
+        memory.write((memory_size - 3) as u64, b"end").unwrap();
+
+        let array: WasmPtr<u32, Array> = WasmPtr::new(16);
+        assert_eq!(array.slice(&memory, 2).unwrap().len(), 2);
+        assert!(array.slice(&memory, memory_size).is_none());
+        assert_eq!(array.index(3), Some(WasmPtr::new(28)));
+        assert!(array.index(u32::max_value()).is_none());
+        let first: WasmPtr<u8> = WasmPtr::<u8, Array>::new(16).index(1).unwrap();
+        assert_eq!(first.deref(&memory).unwrap().get(), b'e');
+
+        let string: WasmPtr<u8, Array> = WasmPtr::new(16);
+        assert_eq!(
+            string.get_utf8_string_with_nul_max(&memory, 6).as_deref(),
+            Some("hello")
+        );
+        assert!(string.get_utf8_string_with_nul_max(&memory, 5).is_none());
+        let end: WasmPtr<u8, Array> = WasmPtr::new(memory_size - 3);
+        assert!(end.get_utf8_string_with_nul_max(&memory, 16).is_none());
+        assert!(end.get_utf8_string_with_nul(&memory).is_none());
+        let oob: WasmPtr<u8, Array> = WasmPtr::new(memory_size + 1);
+        assert!(oob.get_utf8_string_with_nul_max(&memory, 16).is_none());
+        assert!(oob.get_utf8_string_with_nul(&memory).is_none());
+    }
 }
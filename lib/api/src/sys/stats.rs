@@ -0,0 +1,40 @@
+/// Statistics about the size and complexity of a [`Module`], returned by
+/// [`Module::stats`].
+///
+/// [`Module`]: crate::Module
+/// [`Module::stats`]: crate::Module::stats
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleStats {
+    /// The number of imports.
+    pub num_imports: usize,
+    /// The number of exports.
+    pub num_exports: usize,
+    /// The number of imported functions.
+    pub num_imported_functions: usize,
+    /// The statistics of the functions defined by the module, in order.
+    pub functions: Vec<FunctionStats>,
+    /// The total size of the bodies of the functions defined by the
+    /// module, in bytes.
+    pub code_size: u64,
+    /// The number of data segments, active and passive.
+    pub num_data_segments: usize,
+    /// The total size of the data segments, in bytes.
+    pub data_size: u64,
+}
+
+/// Statistics about a function defined by a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionStats {
+    /// The index of the function, in the function index space which
+    /// starts with the imported functions.
+    pub index: u32,
+    /// The name of the function, from the name section of the module.
+    pub name: Option<String>,
+    /// The size of the body of the function in the Wasm binary, in bytes.
+    pub code_size: u32,
+    /// The number of parameters of the function.
+    pub num_params: usize,
+    /// The number of locals declared by the function, excluding its
+    /// parameters.
+    pub num_locals: u32,
+}
@@ -1,4 +1,4 @@
-use crate::tunables::BaseTunables;
+use crate::sys::tunables::BaseTunables;
 use loupe::MemoryUsage;
 use std::any::Any;
 use std::fmt;
@@ -145,6 +145,21 @@ impl Default for Store {
     }
 }
 
+// Without a default compiler, the default store is a headless one
+// loading the modules compiled ahead of time.
+#[cfg(all(
+    not(feature = "default-compiler"),
+    feature = "default-engine",
+    feature = "default-universal"
+))]
+impl Default for Store {
+    fn default() -> Self {
+        let engine = wasmer_engine_universal::Universal::headless().engine();
+        let tunables = BaseTunables::for_target(engine.target());
+        Self::new_with_tunables(&engine, tunables)
+    }
+}
+
 impl fmt::Debug for Store {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Store").finish()
@@ -30,6 +30,9 @@ pub struct BaseTunables {
 
     /// The size in bytes of the offset guard for dynamic heaps.
     pub dynamic_memory_offset_guard_size: u64,
+
+    /// The style of the tables.
+    pub table_style: TableStyle,
 }
 
 impl BaseTunables {
@@ -65,6 +68,131 @@ impl BaseTunables {
             static_memory_bound,
             static_memory_offset_guard_size,
             dynamic_memory_offset_guard_size,
+            table_style: TableStyle::CallerChecksSignature,
+        }
+    }
+
+    /// Create a [`BaseTunablesBuilder`] to configure the `BaseTunables`
+    /// without having to implement the [`Tunables`] trait.
+    ///
+    /// The values that are not explicitly set are the ones from
+    /// [`BaseTunables::for_target`].
+    ///
+    /// # Example
+    ///
+    /// Shrinking the address space reserved for each memory, for example
+    /// in 32-bit or address-space-constrained environments:
+    ///
+    /// ```
+    /// # use wasmer::{BaseTunables, Pages, Store};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let tunables = BaseTunables::builder()
+    ///     .static_memory_bound(Pages(0x100))
+    ///     .static_memory_offset_guard_size(0x1_0000)
+    ///     .dynamic_memory_offset_guard_size(0x1000)
+    ///     .build();
+    /// # let engine = Store::default().engine().clone();
+    /// let store = Store::new_with_tunables(&*engine, tunables);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> BaseTunablesBuilder {
+        BaseTunablesBuilder::default()
+    }
+}
+
+/// A builder for [`BaseTunables`].
+///
+/// See [`BaseTunables::builder`].
+#[derive(Clone, Default)]
+pub struct BaseTunablesBuilder {
+    target: Option<Target>,
+    static_memory_bound: Option<Pages>,
+    static_memory_offset_guard_size: Option<u64>,
+    dynamic_memory_offset_guard_size: Option<u64>,
+    table_style: Option<TableStyle>,
+}
+
+impl BaseTunablesBuilder {
+    /// Set the target the defaults are computed for.
+    ///
+    /// By default, the host target is used.
+    pub fn target(mut self, target: &Target) -> Self {
+        self.target = Some(target.clone());
+        self
+    }
+
+    /// Set the size in wasm pages of the heap protected by bounds
+    /// checking for static heaps.
+    ///
+    /// Memories with a maximum above this bound use the dynamic style.
+    pub fn static_memory_bound(mut self, bound: impl Into<Pages>) -> Self {
+        self.static_memory_bound = Some(bound.into());
+        self
+    }
+
+    /// Set the size in bytes of the offset guard for static heaps.
+    pub fn static_memory_offset_guard_size(mut self, size: u64) -> Self {
+        self.static_memory_offset_guard_size = Some(size);
+        self
+    }
+
+    /// Set the size in bytes of the offset guard for dynamic heaps.
+    pub fn dynamic_memory_offset_guard_size(mut self, size: u64) -> Self {
+        self.dynamic_memory_offset_guard_size = Some(size);
+        self
+    }
+
+    /// Use the dynamic style for all the memories that can grow, so no
+    /// address space is reserved up front.
+    ///
+    /// This is the same as setting a static memory bound of 0 pages.
+    pub fn dynamic_memories_only(self) -> Self {
+        self.static_memory_bound(Pages(0))
+    }
+
+    /// Set the style of the memories.
+    ///
+    /// With [`MemoryStyle::Static`], this sets the static memory bound
+    /// and its offset guard size, the memories with a maximum above the
+    /// bound still using the dynamic style. With [`MemoryStyle::Dynamic`],
+    /// all the memories that can grow use the dynamic style, with the
+    /// given offset guard size.
+    pub fn memory_style(self, style: MemoryStyle) -> Self {
+        match style {
+            MemoryStyle::Static {
+                bound,
+                offset_guard_size,
+            } => self
+                .static_memory_bound(bound)
+                .static_memory_offset_guard_size(offset_guard_size),
+            MemoryStyle::Dynamic { offset_guard_size } => self
+                .dynamic_memories_only()
+                .dynamic_memory_offset_guard_size(offset_guard_size),
+        }
+    }
+
+    /// Set the style of the tables.
+    pub fn table_style(mut self, style: TableStyle) -> Self {
+        self.table_style = Some(style);
+        self
+    }
+
+    /// Build the [`BaseTunables`] for this configuration.
+    pub fn build(self) -> BaseTunables {
+        let target = self.target.unwrap_or_default();
+        let defaults = BaseTunables::for_target(&target);
+        BaseTunables {
+            static_memory_bound: self
+                .static_memory_bound
+                .unwrap_or(defaults.static_memory_bound),
+            static_memory_offset_guard_size: self
+                .static_memory_offset_guard_size
+                .unwrap_or(defaults.static_memory_offset_guard_size),
+            dynamic_memory_offset_guard_size: self
+                .dynamic_memory_offset_guard_size
+                .unwrap_or(defaults.dynamic_memory_offset_guard_size),
+            table_style: self.table_style.unwrap_or(defaults.table_style),
         }
     }
 }
@@ -92,7 +220,7 @@ impl Tunables for BaseTunables {
 
     /// Get a [`TableStyle`] for the provided [`TableType`].
     fn table_style(&self, _table: &TableType) -> TableStyle {
-        TableStyle::CallerChecksSignature
+        self.table_style.clone()
     }
 
     /// Create a memory owned by the host given a [`MemoryType`] and a [`MemoryStyle`].
@@ -153,6 +281,7 @@ impl Tunables for BaseTunables {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ValType;
 
     #[test]
     fn memory_style() {
@@ -160,6 +289,7 @@ mod tests {
             static_memory_bound: Pages(2048),
             static_memory_offset_guard_size: 128,
             dynamic_memory_offset_guard_size: 256,
+            table_style: TableStyle::CallerChecksSignature,
         };
 
         // No maximum
@@ -192,4 +322,67 @@ mod tests {
             s => panic!("Unexpected memory style: {:?}", s),
         }
     }
+
+    #[test]
+    fn builder() {
+        let defaults = BaseTunables::for_target(&Target::default());
+
+        let tunables = BaseTunables::builder().build();
+        assert_eq!(tunables.static_memory_bound, defaults.static_memory_bound);
+        assert_eq!(
+            tunables.static_memory_offset_guard_size,
+            defaults.static_memory_offset_guard_size
+        );
+        assert_eq!(
+            tunables.dynamic_memory_offset_guard_size,
+            defaults.dynamic_memory_offset_guard_size
+        );
+
+        let tunables = BaseTunables::builder()
+            .static_memory_bound(Pages(16))
+            .static_memory_offset_guard_size(128)
+            .dynamic_memory_offset_guard_size(256)
+            .build();
+        assert_eq!(tunables.static_memory_bound, Pages(16));
+        assert_eq!(tunables.static_memory_offset_guard_size, 128);
+        assert_eq!(tunables.dynamic_memory_offset_guard_size, 256);
+
+        let tunables = BaseTunables::builder().dynamic_memories_only().build();
+        let requested = MemoryType::new(3, Some(16), true);
+        match tunables.memory_style(&requested) {
+            MemoryStyle::Dynamic { .. } => {}
+            s => panic!("Unexpected memory style: {:?}", s),
+        }
+
+        let tunables = BaseTunables::builder()
+            .memory_style(MemoryStyle::Dynamic {
+                offset_guard_size: 512,
+            })
+            .table_style(TableStyle::CallerChecksSignature)
+            .build();
+        assert_eq!(
+            tunables.memory_style(&requested),
+            MemoryStyle::Dynamic {
+                offset_guard_size: 512
+            }
+        );
+        assert_eq!(
+            tunables.table_style(&TableType::new(ValType::FuncRef, 1, None)),
+            TableStyle::CallerChecksSignature
+        );
+
+        let tunables = BaseTunables::builder()
+            .memory_style(MemoryStyle::Static {
+                bound: Pages(32),
+                offset_guard_size: 1024,
+            })
+            .build();
+        assert_eq!(
+            tunables.memory_style(&requested),
+            MemoryStyle::Static {
+                bound: Pages(32),
+                offset_guard_size: 1024,
+            }
+        );
+    }
 }
@@ -1,5 +1,5 @@
-use crate::externals::Function;
-use crate::store::{Store, StoreObject};
+use crate::sys::externals::Function;
+use crate::sys::store::{Store, StoreObject};
 use crate::RuntimeError;
 use wasmer_types::Value;
 pub use wasmer_types::{
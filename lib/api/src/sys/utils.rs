@@ -0,0 +1,186 @@
+use wasmer_compiler::WasmError;
+
+/// Check if the provided bytes are wasm-like
+pub fn is_wasm(bytes: impl AsRef<[u8]>) -> bool {
+    bytes.as_ref().starts_with(b"\0asm")
+}
+
+/// The custom sections removed by [`strip_custom_sections`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SectionFilter {
+    /// The debug information: the DWARF sections (`.debug_*`), and the
+    /// `sourceMappingURL` and `external_debug_info` sections.
+    Debug,
+    /// The `name` section, used for the names of the functions in the
+    /// backtraces.
+    Names,
+    /// The custom sections with the given name.
+    Custom(String),
+    /// All the custom sections.
+    AllCustom,
+}
+
+impl SectionFilter {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Debug => {
+                name.starts_with(".debug_")
+                    || name == "sourceMappingURL"
+                    || name == "external_debug_info"
+            }
+            Self::Names => name == "name",
+            Self::Custom(custom) => name == custom,
+            Self::AllCustom => true,
+        }
+    }
+}
+
+/// Removes the custom sections matching any of the `filters` from a
+/// WebAssembly binary, before compiling it, to reduce the size of the
+/// serialized modules.
+///
+/// The other sections are copied as they are, without being validated.
+///
+/// # Example
+///
+/// ```
+/// # use wasmer::*;
+/// # fn main() -> anyhow::Result<()> {
+/// # let store = Store::default();
+/// let wasm = wat2wasm(br#"(module $name (func $f))"#)?;
+/// let stripped = strip_custom_sections(&wasm, &[SectionFilter::Names])?;
+/// assert!(stripped.len() < wasm.len());
+///
+/// let module = Module::new(&store, stripped)?;
+/// assert_eq!(module.name(), None);
+/// # Ok(())
+/// # }
+/// ```
+pub fn strip_custom_sections(
+    bytes: impl AsRef<[u8]>,
+    filters: &[SectionFilter],
+) -> Result<Vec<u8>, WasmError> {
+    let bytes = bytes.as_ref();
+    if !is_wasm(bytes) || bytes.len() < 8 {
+        return Err(invalid("expected a WebAssembly binary", 0));
+    }
+    let mut stripped = bytes[..8].to_vec();
+    let mut offset = 8;
+    while offset < bytes.len() {
+        let start = offset;
+        let id = bytes[offset];
+        offset += 1;
+        let size = read_u32(bytes, &mut offset)? as usize;
+        let end = offset
+            .checked_add(size)
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| invalid("section out of bounds", start))?;
+        if id == 0 {
+            let name_len = read_u32(bytes, &mut offset)? as usize;
+            let name = offset
+                .checked_add(name_len)
+                .filter(|&name_end| name_end <= end)
+                .and_then(|name_end| std::str::from_utf8(&bytes[offset..name_end]).ok())
+                .ok_or_else(|| invalid("malformed custom section name", offset))?;
+            if filters.iter().any(|filter| filter.matches(name)) {
+                offset = end;
+                continue;
+            }
+        }
+        stripped.extend_from_slice(&bytes[start..end]);
+        offset = end;
+    }
+    Ok(stripped)
+}
+
+fn invalid(message: &str, offset: usize) -> WasmError {
+    WasmError::InvalidWebAssembly {
+        message: message.to_string(),
+        offset,
+    }
+}
+
+/// Reads an unsigned LEB128 32 bits integer at `offset`, and moves it
+/// after the integer.
+fn read_u32(bytes: &[u8], offset: &mut usize) -> Result<u32, WasmError> {
+    let start = *offset;
+    let mut result = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes
+            .get(*offset)
+            .ok_or_else(|| invalid("unexpected end of the binary", start))?;
+        *offset += 1;
+        if shift == 28 && byte > 0x0f {
+            break;
+        }
+        result |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+    Err(invalid("invalid LEB128 integer", start))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn custom_section(name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut content = vec![name.len() as u8];
+        content.extend_from_slice(name.as_bytes());
+        content.extend_from_slice(payload);
+        let mut section = vec![0, content.len() as u8];
+        section.extend(content);
+        section
+    }
+
+    #[test]
+    fn strip_sections() {
+        // A type section with a `() -> ()` function type
+        let types = [1, 4, 1, 0x60, 0, 0];
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend(custom_section(".debug_info", b"dwarf"));
+        wasm.extend(&types);
+        wasm.extend(custom_section("name", b"\0\x02\x01m"));
+        wasm.extend(custom_section("producers", b"\0"));
+
+        let stripped = strip_custom_sections(&wasm, &[SectionFilter::Debug]).unwrap();
+        let mut expected = b"\0asm\x01\0\0\0".to_vec();
+        expected.extend(&types);
+        expected.extend(custom_section("name", b"\0\x02\x01m"));
+        expected.extend(custom_section("producers", b"\0"));
+        assert_eq!(stripped, expected);
+
+        let stripped = strip_custom_sections(
+            &wasm,
+            &[
+                SectionFilter::Names,
+                SectionFilter::Custom("producers".to_string()),
+            ],
+        )
+        .unwrap();
+        let mut expected = b"\0asm\x01\0\0\0".to_vec();
+        expected.extend(custom_section(".debug_info", b"dwarf"));
+        expected.extend(&types);
+        assert_eq!(stripped, expected);
+
+        let stripped = strip_custom_sections(&wasm, &[SectionFilter::AllCustom]).unwrap();
+        let mut expected = b"\0asm\x01\0\0\0".to_vec();
+        expected.extend(&types);
+        assert_eq!(stripped, expected);
+
+        assert_eq!(strip_custom_sections(&wasm, &[]).unwrap(), wasm);
+    }
+
+    #[test]
+    fn strip_sections_malformed() {
+        assert!(strip_custom_sections(b"(module)", &[]).is_err());
+        // The size of the section is past the end
+        assert!(strip_custom_sections(b"\0asm\x01\0\0\0\x01\x05\0", &[]).is_err());
+        // The name of the custom section is past its end
+        assert!(
+            strip_custom_sections(b"\0asm\x01\0\0\0\0\x02\x05a", &[SectionFilter::AllCustom])
+                .is_err()
+        );
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "sys")]
+
 use anyhow::Result;
 use wasmer::*;
 use wasmer_vm::WeakOrStrongInstanceRef;
@@ -332,3 +334,49 @@ fn strong_weak_behavior_works_native_function() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn native_function_signature_mismatch() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        "(module
+           (func (export \"sum\") (param i32 i32) (result i32)
+             local.get 0
+             local.get 1
+             i32.add)
+           (memory (export \"memory\") 1))",
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let functions = instance
+        .exports
+        .iter_functions()
+        .map(|(name, function)| (name.as_str(), function.ty().clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        functions,
+        vec![(
+            "sum",
+            FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32])
+        )]
+    );
+
+    let expected = "Incompatible signature for export `sum`: it is `[I32, I32] -> [I32]` in Wasm, but `(i32,) -> i64` (`[I32] -> [I64]`) was requested";
+    let err = instance
+        .exports
+        .get_native_function::<(i32,), i64>("sum")
+        .unwrap_err();
+    assert_eq!(err.to_string(), expected);
+    let err = instance
+        .exports
+        .get_with_generics::<NativeFunc<(i32,), i64>, _, _>("sum")
+        .unwrap_err();
+    assert_eq!(err.to_string(), expected);
+
+    instance
+        .exports
+        .get_native_function::<(i32, i32), i32>("sum")?;
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "sys")]
+
 use anyhow::Result;
 use wasmer::*;
 
@@ -58,6 +60,59 @@ fn global_set() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn v128_values() -> Result<()> {
+    let store = Store::default();
+    let global = Global::new_mut(&store, Value::V128(1));
+    assert_eq!(global.ty().ty, Type::V128);
+    assert_eq!(global.get(), Value::V128(1));
+    global.set(Value::from(u128::MAX))?;
+    assert_eq!(global.get().unwrap_v128(), u128::MAX);
+
+    let module = Module::new(
+        &store,
+        r#"
+        (module
+          (import "env" "swap_halves" (func $swap_halves (param v128) (result v128)))
+          (global (export "zero") v128 (v128.const i64x2 0 0))
+          (func (export "xor") (param v128 v128) (result v128)
+            local.get 0
+            local.get 1
+            v128.xor)
+          (func (export "swap_halves") (param v128) (result v128)
+            local.get 0
+            call $swap_halves))
+        "#,
+    )?;
+    let swap_halves = Function::new(
+        &store,
+        FunctionType::new(vec![Type::V128], vec![Type::V128]),
+        |args: &[Value]| Ok(vec![Value::V128(args[0].unwrap_v128().rotate_left(64))]),
+    );
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "swap_halves" => swap_halves,
+            },
+        },
+    )?;
+
+    assert_eq!(instance.exports.get_global("zero")?.get(), Value::V128(0));
+    let xor = instance.exports.get_function("xor")?;
+    assert_eq!(
+        xor.call(&[Value::V128(0xff00), Value::V128(0x0ff0)])?[..],
+        [Value::V128(0xf0f0)]
+    );
+    let swap_halves = instance.exports.get_function("swap_halves")?;
+    assert_eq!(
+        swap_halves.call(&[Value::V128(1)])?[..],
+        [Value::V128(1 << 64)]
+    );
+
+    Ok(())
+}
+
 #[test]
 fn table_new() -> Result<()> {
     let store = Store::default();
@@ -127,6 +182,82 @@ fn table_grow() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn table_get_function() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+    (table (export "callbacks") 3 funcref)
+    (elem (i32.const 0) $double $fail)
+    (func $double (param i32) (result i32)
+        (i32.mul (local.get 0) (i32.const 2)))
+    (func $fail
+        unreachable))"#,
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let callbacks = instance.exports.get_table("callbacks")?;
+
+    let double = callbacks.get_function(0)?.unwrap();
+    assert_eq!(
+        double.call(&[Value::I32(21)])?.to_vec(),
+        vec![Value::I32(42)]
+    );
+    let double = double.native::<i32, i32>()?;
+    assert_eq!(double.call(4)?, 8);
+
+    let fail = Function::from_table_entry(callbacks, 1)?;
+    assert!(fail.call(&[]).is_err());
+
+    assert!(callbacks.get_function(2)?.is_none());
+    assert!(Function::from_table_entry(callbacks, 2).is_err());
+    assert!(callbacks.get_function(3).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn host_table_get_function() -> Result<()> {
+    let store = Store::default();
+    let table_type = TableType {
+        ty: Type::FuncRef,
+        minimum: 2,
+        maximum: None,
+    };
+    let table = Table::new(&store, table_type, Value::FuncRef(None))?;
+    let increment = Function::new_native(&store, |x: i32| x + 1);
+    table.set(1, Value::FuncRef(Some(increment)))?;
+    let module = Module::new(
+        &store,
+        r#"(module
+    (import "env" "callbacks" (table 2 funcref))
+    (elem (i32.const 0) $double)
+    (func $double (param i32) (result i32)
+        (i32.mul (local.get 0) (i32.const 2))))"#,
+    )?;
+    let import_object = imports! {
+        "env" => {
+            "callbacks" => table.clone(),
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+
+    // The host function gets the trampoline of the instance declaring its
+    // signature
+    let increment = table.get_function(1)?.unwrap();
+    assert_eq!(increment.native::<i32, i32>()?.call(41)?, 42);
+
+    // The function keeps the instance owning it alive
+    let double = table.get_function(0)?.unwrap();
+    drop(instance);
+    assert_eq!(double.native::<i32, i32>()?.call(4)?, 8);
+    drop(double);
+    drop(increment);
+    assert!(table.get_function(0).is_err());
+
+    Ok(())
+}
+
 #[test]
 #[ignore]
 fn table_copy() -> Result<()> {
@@ -177,6 +308,96 @@ fn memory_grow() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn memory_read_write() -> Result<()> {
+    let store = Store::default();
+    let memory = Memory::new(&store, MemoryType::new(Pages(1), None, false))?;
+    let size = memory.data_size();
+
+    memory.write(16, b"hello\0")?;
+    let mut buf = [0; 5];
+    memory.read(16, &mut buf)?;
+    assert_eq!(&buf, b"hello");
+    assert_eq!(memory.read_utf8(16, 5)?, "hello");
+    assert_eq!(memory.read_cstring(16)?, "hello");
+    assert_eq!(memory.read_cstring(21)?, "");
+
+    memory.write_value(33, 0x1234_5678u32)?;
+    assert_eq!(memory.read_value::<u32>(33)?, 0x1234_5678);
+    assert_eq!(memory.read_value::<u8>(33)?, 0x78);
+
+    // Out of bounds accesses are errors and leave the buffers unchanged
+    memory.write(size - 2, b"ab")?;
+    assert_eq!(
+        memory.write(size - 1, b"cd"),
+        Err(MemoryAccessError::HeapOutOfBounds)
+    );
+    assert_eq!(memory.read_utf8(size - 2, 2)?, "ab");
+    assert_eq!(
+        memory.read_utf8(0, usize::MAX),
+        Err(MemoryAccessError::HeapOutOfBounds)
+    );
+    let mut buf = [1; 4];
+    assert_eq!(
+        memory.read(size - 2, &mut buf),
+        Err(MemoryAccessError::HeapOutOfBounds)
+    );
+    assert_eq!(buf, [1; 4]);
+    assert_eq!(
+        memory.read_value::<u64>(u64::MAX - 2),
+        Err(MemoryAccessError::Overflow)
+    );
+    assert_eq!(
+        memory.read_cstring(size - 2),
+        Err(MemoryAccessError::HeapOutOfBounds)
+    );
+    assert_eq!(
+        memory.read_cstring(size + 1),
+        Err(MemoryAccessError::HeapOutOfBounds)
+    );
+
+    memory.write(64, &[0xff, 0xfe, 0])?;
+    assert_eq!(
+        memory.read_utf8(64, 2),
+        Err(MemoryAccessError::NonUtf8String)
+    );
+    assert_eq!(
+        memory.read_cstring(64),
+        Err(MemoryAccessError::NonUtf8String)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn memory_view_atomic() -> Result<()> {
+    use std::sync::atomic::Ordering;
+
+    let store = Store::default();
+    let memory = Memory::new(&store, MemoryType::new(Pages(1), None, false))?;
+    assert_eq!(memory.view_atomic::<u32>().len(), 0x4000);
+    assert_eq!(memory.view_atomic::<u64>().len(), 0x2000);
+
+    let threads = (0..4)
+        .map(|_| {
+            let memory = memory.clone();
+            std::thread::spawn(move || {
+                let view = memory.view_atomic::<u32>();
+                for _ in 0..1000 {
+                    view[4].fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(memory.view_atomic::<u32>()[4].load(Ordering::SeqCst), 4000);
+    assert_eq!(memory.read_value::<u32>(16)?, 4000);
+
+    Ok(())
+}
+
 #[test]
 fn function_new() -> Result<()> {
     let store = Store::default();
@@ -340,6 +561,142 @@ fn function_new_dynamic_env() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn function_scope() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+        (module
+          (import "env" "add" (func $add (param i32)))
+          (func (export "run") (param i32)
+            local.get 0
+            call $add))
+        "#,
+    )?;
+    let total = std::sync::Mutex::new(0);
+
+    let instance = Function::scope(|scope| -> Result<Instance> {
+        let signature = FunctionType::new(vec![Type::I32], vec![]);
+        let add = scope.function(&store, &signature, |args: &[Value]| {
+            *total.lock().unwrap() += args[0].unwrap_i32();
+            Ok(vec![])
+        });
+        let instance = Instance::new(&module, &imports! { "env" => { "add" => add } })?;
+        let run = instance.exports.get_function("run")?;
+        run.call(&[Value::I32(2)])?;
+        run.call(&[Value::I32(3)])?;
+        Ok(instance)
+    })?;
+    assert_eq!(*total.lock().unwrap(), 5);
+
+    // The function doesn't borrow `total` anymore
+    let run = instance.exports.get_function("run")?;
+    assert!(run.call(&[Value::I32(1)]).is_err());
+    assert_eq!(total.into_inner().unwrap(), 5);
+
+    Ok(())
+}
+
+#[test]
+fn function_env_mut() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+        (module
+          (import "env" "log" (func $log (param i32 i32)))
+          (import "env" "reenter" (func $reenter))
+          (memory (export "memory") 1)
+          (data (i32.const 16) "hello")
+          (func (export "run")
+            i32.const 16
+            i32.const 5
+            call $log)
+          (func (export "reenter")
+            call $reenter))
+        "#,
+    )?;
+
+    #[derive(Default)]
+    struct Log {
+        messages: Vec<String>,
+        callback: Option<Function>,
+    }
+
+    let env = FunctionEnv::new(Log::default());
+    let log = Function::new_native_with_env(
+        &store,
+        env.clone(),
+        |env: &FunctionEnv<Log>, ptr: WasmPtr<u8, Array>, len: u32| {
+            let mut env = env.borrow_mut()?;
+            let message = ptr.get_utf8_string(env.memory().unwrap(), len).unwrap();
+            env.messages.push(message);
+            Ok::<_, RuntimeError>(())
+        },
+    );
+    let reenter = Function::new_native_with_env(&store, env.clone(), |env: &FunctionEnv<Log>| {
+        let borrowed = env.borrow_mut()?;
+        borrowed.callback.as_ref().unwrap().call(&[])?;
+        Ok::<_, RuntimeError>(())
+    });
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "env" => {
+                "log" => log,
+                "reenter" => reenter,
+            }
+        },
+    )?;
+
+    instance.exports.get_function("run")?.call(&[])?;
+    instance.exports.get_function("run")?.call(&[])?;
+    assert_eq!(env.borrow_mut()?.messages, vec!["hello", "hello"]);
+
+    // Borrowing the environment again while it's borrowed traps
+    env.borrow_mut()?.callback = Some(instance.exports.get_function("reenter")?.clone());
+    let err = instance
+        .exports
+        .get_function("reenter")?
+        .call(&[])
+        .unwrap_err();
+    assert!(err.message().contains("already borrowed"));
+    env.borrow_mut()?.callback = None;
+    assert_eq!(env.borrow_mut()?.messages.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn function_scope_waits_for_calls() -> Result<()> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Mutex};
+
+    let store = Store::default();
+    let signature = FunctionType::new(vec![], vec![]);
+    let (started, start) = mpsc::channel();
+    let started = Mutex::new(started);
+    let returned = AtomicBool::new(false);
+
+    let thread = Function::scope(|scope| {
+        let slow = scope.function(&store, &signature, |_args: &[Value]| {
+            started.lock().unwrap().send(()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            returned.store(true, Ordering::SeqCst);
+            Ok(vec![])
+        });
+        let thread = std::thread::spawn(move || slow.call(&[]).map(|_| ()));
+        start.recv().unwrap();
+        thread
+    });
+    // The scope ended once the call returned
+    assert!(returned.load(Ordering::SeqCst));
+    thread.join().unwrap()?;
+
+    Ok(())
+}
+
 #[test]
 fn native_function_works() -> Result<()> {
     let store = Store::default();
@@ -374,6 +731,118 @@ fn native_function_works() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn native_function_wide_signatures() -> Result<()> {
+    #[rustfmt::skip]
+    type Args = (
+        i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+        i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32,
+    );
+
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+        (module
+          (func (export "sum") (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)
+            local.get 0
+            local.get 1
+            i32.add
+            local.get 2
+            i32.add
+            local.get 3
+            i32.add
+            local.get 4
+            i32.add
+            local.get 5
+            i32.add
+            local.get 6
+            i32.add
+            local.get 7
+            i32.add
+            local.get 8
+            i32.add
+            local.get 9
+            i32.add
+            local.get 10
+            i32.add
+            local.get 11
+            i32.add
+            local.get 12
+            i32.add
+            local.get 13
+            i32.add
+            local.get 14
+            i32.add
+            local.get 15
+            i32.add
+            local.get 16
+            i32.add
+            local.get 17
+            i32.add
+            local.get 18
+            i32.add
+            local.get 19
+            i32.add
+            local.get 20
+            i32.add
+            local.get 21
+            i32.add
+            local.get 22
+            i32.add
+            local.get 23
+            i32.add
+            local.get 24
+            i32.add
+            local.get 25
+            i32.add
+            local.get 26
+            i32.add
+            local.get 27
+            i32.add
+            local.get 28
+            i32.add
+            local.get 29
+            i32.add
+            local.get 30
+            i32.add
+            local.get 31
+            i32.add))
+        "#,
+    )?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let sum: NativeFunc<Args, i32> = instance.exports.get_native_function("sum")?;
+    #[rustfmt::skip]
+    let result = sum.call(
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+        17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+    )?;
+    assert_eq!(result, 528);
+
+    #[rustfmt::skip]
+    fn host_sum(
+        a1: i32, a2: i32, a3: i32, a4: i32, a5: i32, a6: i32, a7: i32, a8: i32,
+        a9: i32, a10: i32, a11: i32, a12: i32, a13: i32, a14: i32, a15: i32, a16: i32,
+        a17: i32, a18: i32, a19: i32, a20: i32, a21: i32, a22: i32, a23: i32, a24: i32,
+        a25: i32, a26: i32, a27: i32, a28: i32, a29: i32, a30: i32, a31: i32, a32: i32,
+    ) -> i32 {
+        a1 + a2 + a3 + a4 + a5 + a6 + a7 + a8 + a9 + a10 + a11 + a12 + a13 + a14 + a15 + a16
+            + a17 + a18 + a19 + a20 + a21 + a22 + a23 + a24 + a25 + a26 + a27 + a28 + a29
+            + a30 + a31 + a32
+    }
+    let function = Function::new_native(&store, host_sum);
+    assert_eq!(function.ty().params().len(), 32);
+    let host_sum: NativeFunc<Args, i32> = function.native()?;
+    #[rustfmt::skip]
+    let result = host_sum.call(
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+        17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+    )?;
+    assert_eq!(result, 528);
+
+    Ok(())
+}
+
 #[test]
 fn function_outlives_instance() -> Result<()> {
     let store = Store::default();
@@ -0,0 +1,118 @@
+#![cfg(feature = "sys")]
+
+use anyhow::Result;
+use wasmer::*;
+
+const WAT: &str = r#"(module
+    (memory (export "memory") 1)
+    (global $heap (mut i32) (i32.const 16))
+    (global $frees (export "frees") (mut i32) (i32.const 0))
+    (func $malloc (export "malloc") (param $size i32) (result i32)
+        (global.get $heap)
+        (global.set $heap (i32.add (global.get $heap) (local.get $size))))
+    (func (export "free") (param i32)
+        (global.set $frees (i32.add (global.get $frees) (i32.const 1))))
+    (func (export "sum_bytes") (param $ptr i32) (param $len i32) (result i32)
+        (local $sum i32)
+        (block $done
+            (loop $next
+                (br_if $done (i32.eqz (local.get $len)))
+                (local.set $sum (i32.add (local.get $sum) (i32.load8_u (local.get $ptr))))
+                (local.set $ptr (i32.add (local.get $ptr) (i32.const 1)))
+                (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+                (br $next)))
+        (local.get $sum))
+    (func (export "fail") (param i32 i32)
+        unreachable)
+    (func (export "greeting") (result i32)
+        (local $ptr i32)
+        (local.set $ptr (call $malloc (i32.const 2)))
+        (i32.store16 (local.get $ptr) (i32.const 0x6968))
+        (local.get $ptr)))"#;
+
+fn frees(instance: &Instance) -> Result<i32> {
+    Ok(instance.exports.get_global("frees")?.get().unwrap_i32())
+}
+
+#[test]
+fn pass_buffers_to_the_guest() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let allocator = GuestAllocator::new(&instance.exports)?;
+    let sum_bytes = instance
+        .exports
+        .get_native_function::<(i32, i32), i32>("sum_bytes")?;
+
+    let sum = allocator.with_bytes(&[1, 2, 3], |ptr, len| sum_bytes.call(ptr, len))?;
+    assert_eq!(sum, 6);
+    assert_eq!(frees(&instance)?, 1);
+
+    let sum = allocator.with_str("ab", |ptr, len| sum_bytes.call(ptr, len))?;
+    assert_eq!(sum, (b'a' + b'b') as i32);
+    assert_eq!(frees(&instance)?, 2);
+
+    // Empty buffers still get a valid pointer
+    let buffer = allocator.alloc_bytes(&[])?;
+    assert_ne!(buffer.ptr(), 0);
+    assert!(buffer.is_empty());
+    allocator.free(buffer)?;
+    assert_eq!(frees(&instance)?, 3);
+
+    Ok(())
+}
+
+#[test]
+fn buffers_are_freed_when_the_call_fails() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let allocator = GuestAllocator::new(&instance.exports)?;
+    let fail = instance
+        .exports
+        .get_native_function::<(i32, i32), ()>("fail")?;
+
+    assert!(allocator
+        .with_str("wasmer", |ptr, len| fail.call(ptr, len))
+        .is_err());
+    assert_eq!(frees(&instance)?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn take_buffers_from_the_guest() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, WAT)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let allocator = GuestAllocator::new(&instance.exports)?;
+    let greeting = instance
+        .exports
+        .get_native_function::<(), i32>("greeting")?;
+
+    let ptr = greeting.call()?;
+    assert_eq!(allocator.take_string(ptr, 2)?, "hi");
+    assert_eq!(frees(&instance)?, 1);
+
+    // A length past the end of the memory fails before allocating anything
+    let ptr = greeting.call()?;
+    let error = allocator.take_bytes(ptr, -1).unwrap_err();
+    assert_eq!(error.message(), "memory access out of bounds");
+    assert_eq!(frees(&instance)?, 2);
+
+    Ok(())
+}
+
+#[test]
+fn missing_allocator() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, r#"(module (memory (export "memory") 1))"#)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    match GuestAllocator::new(&instance.exports) {
+        Err(ExportError::Missing(name)) => assert_eq!(name, "malloc"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    Ok(())
+}
@@ -1,4 +1,9 @@
+#![cfg(feature = "sys")]
+
 use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
 use wasmer::*;
 
 #[test]
@@ -37,3 +42,209 @@ fn exports_work_after_multiple_instances_have_been_freed() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn instance_memory_usage() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+      (memory (export "memory") 2)
+      (table 10 funcref)
+      (global (mut i32) (i32.const 0))
+      (func (export "store") (param i32)
+        local.get 0
+        i32.const 1
+        i32.store))
+"#,
+    )?;
+    let instance = Instance::new(&module, &ImportObject::new())?;
+
+    let usage = instance.memory_usage();
+    assert_eq!(usage.memories_size, 2 * WASM_PAGE_SIZE);
+    assert_eq!(usage.tables_elements, 10);
+    assert!(usage.tables_size > 0);
+    assert!(usage.runtime_size > 0);
+    assert_eq!(
+        usage.total(),
+        usage.memories_size + usage.tables_size + usage.runtime_size
+    );
+
+    instance.exports.get_memory("memory")?.grow(1)?;
+    instance
+        .exports
+        .get_native_function::<i32, ()>("store")?
+        .call(0)?;
+    let usage = instance.memory_usage();
+    assert_eq!(usage.memories_size, 3 * WASM_PAGE_SIZE);
+    #[cfg(unix)]
+    assert!(usage.memories_resident_size.unwrap() > 0);
+
+    Ok(())
+}
+
+#[test]
+fn functions_outlive_their_module() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"
+    (module
+      (func (export "sum") (param i32 i32) (result i32)
+        local.get 0
+        local.get 1
+        i32.add))
+"#;
+    let sum = {
+        let module = Module::new(&store, wat)?;
+        let instance = Instance::new(&module, &ImportObject::new())?;
+        instance.exports.get_function("sum")?.clone()
+    };
+
+    // The module and instance are dropped, but `sum` keeps their code
+    // loaded.
+    unsafe { store.engine().gc() };
+    assert_eq!(
+        sum.call(&[Value::I32(1), Value::I32(2)])?.into_vec(),
+        vec![Value::I32(3)],
+    );
+
+    // Once nothing uses the code anymore, the engine can unload it and
+    // reclaim what it kept for it, without affecting the other modules.
+    drop(sum);
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &ImportObject::new())?;
+    // No table or global holds a reference to the unloaded `sum`.
+    unsafe { store.engine().gc() };
+    assert_eq!(
+        instance
+            .exports
+            .get_function("sum")?
+            .call(&[Value::I32(3), Value::I32(4)])?
+            .into_vec(),
+        vec![Value::I32(7)],
+    );
+
+    Ok(())
+}
+
+#[test]
+fn table_functions_outlive_their_instance() -> Result<()> {
+    let store = Store::default();
+    let table = Table::new(
+        &store,
+        TableType::new(ValType::FuncRef, 1, None),
+        Value::FuncRef(None),
+    )?;
+    {
+        let module = Module::new(
+            &store,
+            r#"(module (func (export "answer") (result i32) i32.const 42))"#,
+        )?;
+        let instance = Instance::new(&module, &ImportObject::new())?;
+        let answer = instance.exports.get_function("answer")?.clone();
+        table.set(0, Value::FuncRef(Some(answer)))?;
+    }
+
+    // The table only holds a raw reference to `answer`, whose code stays
+    // loaded with the engine.
+    let module = Module::new(
+        &store,
+        r#"
+    (module
+      (import "env" "table" (table 1 funcref))
+      (type $answer (func (result i32)))
+      (func (export "call") (result i32)
+        i32.const 0
+        call_indirect (type $answer)))
+"#,
+    )?;
+    let import_object = imports! {
+        "env" => {
+            "table" => table,
+        },
+    };
+    let instance = Instance::new(&module, &import_object)?;
+    let call = instance.exports.get_native_function::<(), i32>("call")?;
+    assert_eq!(call.call()?, 42);
+
+    Ok(())
+}
+
+#[test]
+fn imports_from_another_store_are_rejected() -> Result<()> {
+    let store = Store::default();
+    // A store with a clone of the engine shares it
+    assert!(Store::same(&store, &Store::new(store.engine().as_ref())));
+    let other_store = Store::default();
+    let module = Module::new(&store, r#"(module (import "env" "memory" (memory 1)))"#)?;
+    let import_object = imports! {
+        "env" => {
+            "memory" => Memory::new(&other_store, MemoryType::new(1, None, false))?,
+        },
+    };
+
+    match Instance::new(&module, &import_object) {
+        Err(InstantiationError::Link(LinkError::Import(
+            module,
+            name,
+            ImportError::IncompatibleEngine,
+        ))) => {
+            assert_eq!((module.as_str(), name.as_str()), ("env", "memory"));
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    // The module can be moved to the store of its imports
+    let module = module.clone_into(&other_store)?;
+    assert!(Store::same(module.store(), &other_store));
+    Instance::new(&module, &import_object)?;
+
+    Ok(())
+}
+
+#[test]
+fn instances_run_on_worker_threads() -> Result<()> {
+    #[derive(Clone, WasmerEnv)]
+    struct Env {
+        calls: Arc<AtomicUsize>,
+    }
+
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+    (import "env" "record" (func $record (param i32)))
+    (func (export "run") (param i32) (result i32)
+        (call $record (local.get 0))
+        (i32.add (local.get 0) (i32.const 1))))"#,
+    )?;
+    let env = Env {
+        calls: Arc::new(AtomicUsize::new(0)),
+    };
+    let record = Function::new_native_with_env(&store, env.clone(), |env: &Env, _: i32| {
+        env.calls.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let workers = (0..4)
+        .map(|i| {
+            let instance = Instance::new(
+                &module,
+                &imports! { "env" => { "record" => record.clone() } },
+            )?;
+            Ok(thread::spawn(move || -> Result<i32> {
+                let run = instance.exports.get_native_function::<i32, i32>("run")?;
+                Ok(run.call(i)?)
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut results = workers
+        .into_iter()
+        .map(|worker| worker.join().unwrap())
+        .collect::<Result<Vec<_>>>()?;
+    results.sort_unstable();
+
+    assert_eq!(results, vec![1, 2, 3, 4]);
+    assert_eq!(env.calls.load(Ordering::SeqCst), 4);
+
+    Ok(())
+}
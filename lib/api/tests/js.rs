@@ -0,0 +1,128 @@
+#![cfg(feature = "js")]
+
+use wasm_bindgen_test::*;
+use wasmer::*;
+
+#[wasm_bindgen_test]
+fn module_imports_and_exports() {
+    let store = Store::default();
+    let wat = r#"(module
+        (import "host" "func" (func (param i32) (result i32)))
+        (memory (export "mem") 1)
+        (global (export "glob") (mut i64) (i64.const 7))
+        (func (export "add_one") (param i32) (result i32)
+            local.get 0
+            i32.const 1
+            i32.add))"#;
+    let module = Module::new(&store, wat).unwrap();
+
+    assert_eq!(
+        module.imports().collect::<Vec<_>>(),
+        vec![ImportType::new(
+            "host",
+            "func",
+            ExternType::Function(FunctionType::new(vec![Type::I32], vec![Type::I32]))
+        )]
+    );
+    assert_eq!(
+        module.exports().collect::<Vec<_>>(),
+        vec![
+            ExportType::new("mem", ExternType::Memory(MemoryType::new(1, None, false))),
+            ExportType::new(
+                "glob",
+                ExternType::Global(GlobalType::new(Type::I64, Mutability::Var))
+            ),
+            ExportType::new(
+                "add_one",
+                ExternType::Function(FunctionType::new(vec![Type::I32], vec![Type::I32]))
+            ),
+        ]
+    );
+}
+
+#[wasm_bindgen_test]
+fn instance_calls_host_and_guest_functions() {
+    let store = Store::default();
+    let wat = r#"(module
+        (import "host" "double" (func $double (param i32) (result i32)))
+        (func (export "double_plus_one") (param i32) (result i32)
+            local.get 0
+            call $double
+            i32.const 1
+            i32.add))"#;
+    let module = Module::new(&store, wat).unwrap();
+    let import_object = imports! {
+        "host" => {
+            "double" => Function::new_native(&store, |x: i32| x * 2),
+        },
+    };
+    let instance = Instance::new(&module, &import_object).unwrap();
+
+    let double_plus_one = instance
+        .exports
+        .get_native_function::<i32, i32>("double_plus_one")
+        .unwrap();
+    assert_eq!(double_plus_one.call(20).unwrap(), 41);
+}
+
+#[wasm_bindgen_test]
+fn missing_import_is_a_link_error() {
+    let store = Store::default();
+    let wat = r#"(module (import "host" "func" (func)))"#;
+    let module = Module::new(&store, wat).unwrap();
+
+    match Instance::new(&module, &imports! {}) {
+        Err(InstantiationError::Link(LinkError::Import(module, name, _))) => {
+            assert_eq!((module.as_str(), name.as_str()), ("host", "func"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[wasm_bindgen_test]
+fn memory_is_shared_with_the_host() {
+    let store = Store::default();
+    let wat = r#"(module
+        (memory (export "mem") 1)
+        (func (export "load") (param i32) (result i32)
+            local.get 0
+            i32.load8_u))"#;
+    let module = Module::new(&store, wat).unwrap();
+    let instance = Instance::new(&module, &imports! {}).unwrap();
+    let memory = instance.exports.get_memory("mem").unwrap();
+    let load = instance
+        .exports
+        .get_native_function::<i32, i32>("load")
+        .unwrap();
+
+    memory.write(0x10, &[42]).unwrap();
+    assert_eq!(load.call(0x10).unwrap(), 42);
+
+    let ptr: WasmPtr<u32> = WasmPtr::new(0x20);
+    ptr.deref(memory).unwrap().set(0xdead_beef);
+    assert_eq!(memory.read_value::<u32>(0x20).unwrap(), 0xdead_beef);
+
+    assert_eq!(memory.grow(1).unwrap(), Pages(1));
+    assert_eq!(memory.size(), Pages(2));
+}
+
+#[wasm_bindgen_test]
+fn host_function_traps_are_runtime_errors() {
+    let store = Store::default();
+    let wat = r#"(module
+        (import "host" "fail" (func $fail))
+        (func (export "run") call $fail))"#;
+    let module = Module::new(&store, wat).unwrap();
+    let import_object = imports! {
+        "host" => {
+            "fail" => Function::new_native(&store, || -> Result<(), RuntimeError> {
+                Err(RuntimeError::new("host failure"))
+            }),
+        },
+    };
+    let instance = Instance::new(&module, &import_object).unwrap();
+    let run = instance.exports.get_function("run").unwrap();
+
+    let error = run.call(&[]).unwrap_err();
+    assert_eq!(error.message(), "host failure");
+}
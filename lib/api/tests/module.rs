@@ -1,4 +1,7 @@
+#![cfg(feature = "sys")]
+
 use anyhow::Result;
+use std::sync::Arc;
 use wasmer::*;
 
 #[test]
@@ -24,6 +27,171 @@ fn module_set_name() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn module_function_names() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"(module
+        (import "host" "log" (func $log (param i32)))
+        (func $first)
+        (func)
+        (func $third)
+    )"#;
+    let module = Module::new(&store, wat)?;
+    assert_eq!(module.function_name(0), Some("log"));
+    assert_eq!(module.function_name(2), None);
+    assert_eq!(
+        module.function_names().collect::<Vec<_>>(),
+        vec![(0, "log"), (1, "first"), (3, "third")]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn module_names_are_shared() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"(module
+        (import "env" "log" (func $log (param i32)))
+        (import "env" "now" (func (result i64)))
+        (import "env" "memory" (memory 1))
+        (func (export "log") (param i32) (call $log (local.get 0)))
+    )"#;
+    let module = Module::new(&store, wat)?;
+    let serialized = module.serialize()?;
+    let deserialized = unsafe { Module::deserialize(&store, serialized)? };
+
+    for module in &[module, deserialized] {
+        let info = module.info();
+        let module_names = info
+            .imports
+            .keys()
+            .map(|(module_name, _, _)| module_name)
+            .collect::<Vec<_>>();
+        assert_eq!(module_names.len(), 3);
+        assert!(module_names
+            .iter()
+            .all(|name| Arc::ptr_eq(name, module_names[0])));
+
+        let (export_name, _) = info.exports.get_index(0).unwrap();
+        let (_, import_name, _) = info.imports.get_index(0).unwrap().0;
+        assert!(Arc::ptr_eq(export_name, import_name));
+        let function_name = info.function_names.values().next().unwrap();
+        assert!(Arc::ptr_eq(export_name, function_name));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn module_custom_sections_and_producers() -> Result<()> {
+    fn custom_section(name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut content = vec![name.len() as u8];
+        content.extend_from_slice(name.as_bytes());
+        content.extend_from_slice(payload);
+        let mut section = vec![0, content.len() as u8];
+        section.extend(content);
+        section
+    }
+
+    let store = Store::default();
+    let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+    wasm.extend(custom_section("extra", b"metadata"));
+    wasm.extend(custom_section(
+        "producers",
+        b"\x01\x0cprocessed-by\x01\x05rustc\x061.51.0",
+    ));
+    let module = Module::new(&store, wasm)?;
+
+    let extra = module.custom_sections("extra").collect::<Vec<_>>();
+    assert_eq!(extra.len(), 1);
+    assert_eq!(&*extra[0], b"metadata");
+    assert_eq!(module.custom_sections("missing").count(), 0);
+
+    let producers = module.producers().unwrap();
+    assert_eq!(producers.fields().len(), 1);
+    let processed_by = producers.field("processed-by").unwrap();
+    assert_eq!(processed_by.values[0].name, "rustc");
+    assert_eq!(processed_by.values[0].version, "1.51.0");
+
+    let module = Module::new(&store, "(module)")?;
+    assert!(module.producers().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn module_stats() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"(module
+        (import "host" "log" (func $log (param i32)))
+        (import "host" "memory" (memory 1))
+        (func $run (export "run") (local i32 i64) (local f32)
+            (call $log (i32.const 0)))
+        (func (export "nop"))
+        (data (i32.const 0) "abc")
+        (data "passive")
+    )"#;
+    let module = Module::new(&store, wat)?;
+    let stats = module.stats();
+    assert_eq!(stats.num_imports, 2);
+    assert_eq!(stats.num_exports, 2);
+    assert_eq!(stats.num_imported_functions, 1);
+    assert_eq!(stats.functions.len(), 2);
+
+    let run = &stats.functions[0];
+    assert_eq!(run.index, 1);
+    assert_eq!(run.name.as_deref(), Some("run"));
+    assert_eq!(run.num_params, 0);
+    assert_eq!(run.num_locals, 3);
+    let nop = &stats.functions[1];
+    assert_eq!(nop.index, 2);
+    assert_eq!(nop.name, None);
+    assert_eq!(nop.num_locals, 0);
+    // The local declarations and the `end`
+    assert_eq!(nop.code_size, 2);
+    assert!(run.code_size > nop.code_size);
+    assert_eq!(stats.code_size, (run.code_size + nop.code_size) as u64);
+
+    assert_eq!(stats.num_data_segments, 2);
+    assert_eq!(stats.data_size, 10);
+
+    // The statistics are kept when the module is serialized
+    let serialized = module.serialize()?;
+    let module = unsafe { Module::deserialize(&store, serialized)? };
+    assert_eq!(module.stats(), stats);
+
+    Ok(())
+}
+
+#[test]
+fn module_introspection() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"(module
+        (import "host" "log" (func $log (param i32)))
+        (func (export "run") (call $log (i32.const 0)))
+        (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+    )"#;
+    let module = Module::new(&store, wat)?;
+
+    // One trampoline per signature, and one per imported function
+    assert_eq!(module.function_call_trampolines().len(), 3);
+    assert_eq!(module.dynamic_function_trampolines().len(), 1);
+
+    if let Some(extents) = module.function_extents() {
+        assert_eq!(extents.len(), 2);
+        for (_, extent) in extents.iter() {
+            assert!(!extent.ptr.0.is_null());
+            assert!(extent.length > 0);
+        }
+    }
+    if let Some(frame_info) = module.function_frame_info() {
+        assert_eq!(frame_info.len(), 2);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn imports() -> Result<()> {
     let store = Store::default();
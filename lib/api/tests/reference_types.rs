@@ -1,6 +1,8 @@
+#![cfg(feature = "sys")]
+
 use anyhow::Result;
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use wasmer::*;
 
@@ -339,6 +341,40 @@ fn extern_ref_ref_counting_table_basic() -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "experimental-reference-types-extern-ref")]
+#[test]
+fn extern_ref_finalizer() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"(module
+    (table $table (export "table") 1 1 externref)
+    (func (export "store") (param $er externref)
+          (table.set $table (i32.const 0) (local.get $er)))
+)"#;
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let store_er: NativeFunc<ExternRef, ()> = instance.exports.get_native_function("store")?;
+    let table: &Table = instance.exports.get_table("table")?;
+
+    let finalized = Arc::new(AtomicUsize::new(0));
+    let er = {
+        let finalized = finalized.clone();
+        ExternRef::new_with_finalizer(5usize, move |value: usize| {
+            finalized.fetch_add(value, Ordering::SeqCst);
+        })
+    };
+    assert_eq!(er.downcast::<usize>(), Some(&5));
+
+    // The guest keeps the data alive once the host drops its reference
+    store_er.call(er)?;
+    assert_eq!(finalized.load(Ordering::SeqCst), 0);
+
+    // Dropping the last guest reference runs the finalizer once
+    table.set(0, Val::ExternRef(ExternRef::null()))?;
+    assert_eq!(finalized.load(Ordering::SeqCst), 5);
+
+    Ok(())
+}
+
 #[cfg(feature = "experimental-reference-types-extern-ref")]
 #[test]
 // TODO(reftypes): reenable this test
@@ -0,0 +1,75 @@
+#![cfg(all(feature = "sys", unix))]
+//! The trap handlers are installed for the whole process, so they are
+//! tested in a binary of their own, by a single test.
+
+use anyhow::Result;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wasmer::vm::{
+    handle_trap_signal, install_trap_handlers, set_install_trap_handlers_on_init,
+    trap_handlers_installed, uninstall_trap_handlers,
+};
+use wasmer::*;
+
+/// The number of signals that went through `host_handler`.
+static HOST_HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+/// A handler of the host, forwarding the signals to Wasmer.
+unsafe extern "C" fn host_handler(
+    signum: libc::c_int,
+    siginfo: *mut libc::siginfo_t,
+    context: *mut libc::c_void,
+) {
+    HOST_HANDLED.fetch_add(1, Ordering::SeqCst);
+    if !handle_trap_signal(signum, siginfo, context) {
+        libc::abort();
+    }
+}
+
+/// Sets the action of `SIGILL`, raised by `unreachable`, to `handler`,
+/// returning the previous action.
+unsafe fn set_sigill_action(handler: libc::sighandler_t) -> libc::sigaction {
+    let mut action: libc::sigaction = mem::zeroed();
+    action.sa_flags = libc::SA_SIGINFO | libc::SA_NODEFER;
+    action.sa_sigaction = handler;
+    libc::sigemptyset(&mut action.sa_mask);
+    let mut previous: libc::sigaction = mem::zeroed();
+    assert_eq!(libc::sigaction(libc::SIGILL, &action, &mut previous), 0);
+    previous
+}
+
+fn assert_traps(function: &Function) {
+    let error = function.call(&[]).unwrap_err();
+    assert!(error.message().contains("unreachable"), "{}", error);
+}
+
+#[test]
+fn trap_handlers() -> Result<()> {
+    set_install_trap_handlers_on_init(false);
+    let store = Store::default();
+    let module = Module::new(&store, "(module (func (export \"trap\") unreachable))")?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let trap = instance.exports.get_function("trap")?;
+    assert!(!trap_handlers_installed());
+
+    // The host forwards the signals from its own handler.
+    let previous = unsafe { set_sigill_action(host_handler as usize) };
+    assert_traps(trap);
+    assert_eq!(HOST_HANDLED.load(Ordering::SeqCst), 1);
+
+    // The handlers installed later by Wasmer handle the traps first...
+    unsafe { install_trap_handlers()? };
+    assert!(trap_handlers_installed());
+    assert_traps(trap);
+    assert_eq!(HOST_HANDLED.load(Ordering::SeqCst), 1);
+
+    // ...and the handler of the host is back once they are uninstalled.
+    unsafe { uninstall_trap_handlers() };
+    assert!(!trap_handlers_installed());
+    assert_traps(trap);
+    assert_eq!(HOST_HANDLED.load(Ordering::SeqCst), 2);
+
+    unsafe { libc::sigaction(libc::SIGILL, &previous, ptr::null_mut()) };
+    Ok(())
+}
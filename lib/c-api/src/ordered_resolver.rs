@@ -5,7 +5,8 @@
 //! by index and not by module and name.
 
 use std::iter::FromIterator;
-use wasmer::{Export, Exportable, Extern, Resolver};
+use std::sync::Arc;
+use wasmer::{Engine, Export, Exportable, Extern, Resolver};
 
 /// An `OrderedResolver` stores all the `externs` provided to an Instance
 /// in a Vec, so we can retrieve them later based on index.
@@ -21,6 +22,17 @@ impl Resolver for OrderedResolver {
             .get(index as usize)
             .map(|extern_| extern_.to_export())
     }
+
+    fn resolve_engine(
+        &self,
+        index: u32,
+        _module: &str,
+        _name: &str,
+    ) -> Option<Arc<dyn Engine + Send + Sync>> {
+        self.externs
+            .get(index as usize)
+            .map(|extern_| extern_.store().engine().clone())
+    }
 }
 
 impl FromIterator<Extern> for OrderedResolver {
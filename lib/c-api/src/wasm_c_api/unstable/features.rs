@@ -17,9 +17,12 @@
 //!     // Now, let's enable de SIMD feature.
 //!     wasmer_features_simd(features, true);
 //!
-//!     // And also the memory64 feature.     
+//!     // And also the memory64 feature.
 //!     wasmer_features_memory64(features, true);
 //!
+//!     // And disable the multi-value feature, which is on by default.
+//!     wasmer_features_multi_value(features, false);
+//!
 //!     wasmer_features_delete(features);
 //!
 //!     return 0;
@@ -102,16 +105,14 @@ pub extern "C" fn wasmer_features_threads(
 /// Configures whether the WebAssembly reference types proposal will be
 /// enabled.
 ///
-/// The [WebAssembly reference types proposal][proposal] is not currently
-/// fully standardized and is undergoing development. Support for this
-/// feature can be enabled through this method for appropriate WebAssembly
-/// modules.
+/// The [WebAssembly reference types proposal][proposal] is now
+/// fully standardized and enabled by default.
 ///
 /// This feature gates items such as the `externref` type and multiple tables
 /// being in a module. Note that enabling the reference types feature will
 /// also enable the bulk memory feature.
 ///
-/// This is `false` by default.
+/// This is `true` by default.
 ///
 /// [proposal]: https://github.com/webassembly/reference-types
 ///
@@ -136,15 +137,13 @@ pub extern "C" fn wasmer_features_reference_types(
 /// Configures whether the WebAssembly SIMD proposal will be
 /// enabled.
 ///
-/// The [WebAssembly SIMD proposal][proposal] is not currently
-/// fully standardized and is undergoing development. Support for this
-/// feature can be enabled through this method for appropriate WebAssembly
-/// modules.
+/// The [WebAssembly SIMD proposal][proposal] is now fully
+/// standardized and enabled by default.
 ///
 /// This feature gates items such as the `v128` type and all of its
 /// operators being in a module.
 ///
-/// This is `false` by default.
+/// This is `true` by default.
 ///
 /// [proposal]: https://github.com/webassembly/simd
 ///
@@ -169,15 +168,13 @@ pub extern "C" fn wasmer_features_simd(
 /// Configures whether the WebAssembly bulk memory operations proposal will
 /// be enabled.
 ///
-/// The [WebAssembly bulk memory operations proposal][proposal] is not
-/// currently fully standardized and is undergoing development.
-/// Support for this feature can be enabled through this method for
-/// appropriate WebAssembly modules.
+/// The [WebAssembly bulk memory operations proposal][proposal] is
+/// now fully standardized and enabled by default.
 ///
 /// This feature gates items such as the `memory.copy` instruction, passive
 /// data/table segments, etc, being in a module.
 ///
-/// This is `false` by default.
+/// This is `true` by default.
 ///
 /// [proposal]: https://github.com/webassembly/bulk-memory-operations
 ///
@@ -202,15 +199,13 @@ pub extern "C" fn wasmer_features_bulk_memory(
 /// Configures whether the WebAssembly multi-value proposal will
 /// be enabled.
 ///
-/// The [WebAssembly multi-value proposal][proposal] is not
-/// currently fully standardized and is undergoing development.
-/// Support for this feature can be enabled through this method for
-/// appropriate WebAssembly modules.
+/// The [WebAssembly multi-value proposal][proposal] is now fully
+/// standardized and enabled by default.
 ///
 /// This feature gates functions and blocks returning multiple values in a
 /// module, for example.
 ///
-/// This is `false` by default.
+/// This is `true` by default.
 ///
 /// [proposal]: https://github.com/webassembly/multi-value
 ///
@@ -264,10 +259,10 @@ pub extern "C" fn wasmer_features_tail_call(
     true
 }
 
-/// Configures whether the WebAssembly tail-call proposal will
+/// Configures whether the WebAssembly module linking proposal will
 /// be enabled.
 ///
-/// The [WebAssembly tail-call proposal][proposal] is not
+/// The [WebAssembly module linking proposal][proposal] is not
 /// currently fully standardized and is undergoing development.
 /// Support for this feature can be enabled through this method for
 /// appropriate WebAssembly modules.
@@ -362,3 +357,36 @@ pub extern "C" fn wasmer_features_memory64(
 
     true
 }
+
+/// Configures whether the WebAssembly exception handling proposal will
+/// be enabled.
+///
+/// The [WebAssembly exception handling proposal][proposal] is not
+/// currently fully standardized and is undergoing development.
+/// Support for this feature can be enabled through this method for
+/// appropriate WebAssembly modules.
+///
+/// This feature gates the `try`, `catch` and `throw` instructions
+/// and the exception tags being in a module.
+///
+/// This is `false` by default.
+///
+/// [proposal]: https://github.com/WebAssembly/exception-handling
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_features_exceptions(
+    features: Option<&mut wasmer_features_t>,
+    enable: bool,
+) -> bool {
+    let features = match features {
+        Some(features) => features,
+        _ => return false,
+    };
+
+    features.inner.exceptions(enable);
+
+    true
+}
@@ -75,11 +75,11 @@
 #define WASMER_MIDDLEWARES_ENABLED
 
 // This file corresponds to the following Wasmer version.
-#define WASMER_VERSION "2.0.0-rc2"
+#define WASMER_VERSION "2.0.0-rc3"
 #define WASMER_VERSION_MAJOR 2
 #define WASMER_VERSION_MINOR 0
 #define WASMER_VERSION_PATCH 0
-#define WASMER_VERSION_PRE "rc2"
+#define WASMER_VERSION_PRE "rc3"
 
 #endif // WASMER_H_PRELUDE
 
@@ -785,6 +785,8 @@ bool wasmer_features_bulk_memory(struct wasmer_features_t *features, bool enable
 
 void wasmer_features_delete(struct wasmer_features_t *_features);
 
+bool wasmer_features_exceptions(struct wasmer_features_t *features, bool enable);
+
 bool wasmer_features_memory64(struct wasmer_features_t *features, bool enable);
 
 bool wasmer_features_module_linking(struct wasmer_features_t *features, bool enable);
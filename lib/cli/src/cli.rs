@@ -1,12 +1,16 @@
 //! The logic for the Wasmer CLI tool.
 
+#[cfg(target_os = "linux")]
+use crate::commands::Binfmt;
 #[cfg(feature = "compiler")]
 use crate::commands::Compile;
-#[cfg(all(feature = "staticlib", feature = "compiler"))]
-use crate::commands::CreateExe;
+#[cfg(feature = "fuzz-diff")]
+use crate::commands::FuzzDiff;
 #[cfg(feature = "wast")]
 use crate::commands::Wast;
-use crate::commands::{Cache, Config, Inspect, Run, SelfUpdate, Validate};
+use crate::commands::{Cache, Completions, Config, Inspect, Run, SelfUpdate, Validate};
+#[cfg(all(feature = "staticlib", feature = "compiler"))]
+use crate::commands::{CreateExe, CreateObj};
 use crate::error::PrettyError;
 use anyhow::Result;
 
@@ -49,11 +53,21 @@ enum WasmerCLIOptions {
     #[structopt(name = "create-exe")]
     CreateExe(CreateExe),
 
+    /// Compile a WebAssembly binary into a native object file and a C
+    /// header to link it with the Staticlib engine
+    #[cfg(all(feature = "staticlib", feature = "compiler"))]
+    #[structopt(name = "create-obj")]
+    CreateObj(CreateObj),
+
     /// Get various configuration information needed
     /// to compile programs which use Wasmer
     #[structopt(name = "config")]
     Config(Config),
 
+    /// Generate shell completions for wasmer
+    #[structopt(name = "completions")]
+    Completions(Completions),
+
     /// Update wasmer to the latest version
     #[structopt(name = "self-update")]
     SelfUpdate(SelfUpdate),
@@ -66,6 +80,17 @@ enum WasmerCLIOptions {
     #[cfg(feature = "wast")]
     #[structopt(name = "wast")]
     Wast(Wast),
+
+    /// Compare how two compilers run generated WebAssembly modules
+    #[cfg(feature = "fuzz-diff")]
+    #[structopt(name = "fuzz-diff")]
+    FuzzDiff(FuzzDiff),
+
+    /// Register wasmer as the binfmt_misc interpreter of WebAssembly
+    /// binaries, so they can be executed directly
+    #[cfg(target_os = "linux")]
+    #[structopt(name = "binfmt")]
+    Binfmt(Binfmt),
 }
 
 impl WasmerCLIOptions {
@@ -79,10 +104,17 @@ impl WasmerCLIOptions {
             Self::Compile(compile) => compile.execute(),
             #[cfg(all(feature = "staticlib", feature = "compiler"))]
             Self::CreateExe(create_exe) => create_exe.execute(),
+            #[cfg(all(feature = "staticlib", feature = "compiler"))]
+            Self::CreateObj(create_obj) => create_obj.execute(),
             Self::Config(config) => config.execute(),
+            Self::Completions(completions) => completions.execute(Self::clap()),
             Self::Inspect(inspect) => inspect.execute(),
             #[cfg(feature = "wast")]
             Self::Wast(wast) => wast.execute(),
+            #[cfg(feature = "fuzz-diff")]
+            Self::FuzzDiff(fuzz_diff) => fuzz_diff.execute(),
+            #[cfg(target_os = "linux")]
+            Self::Binfmt(binfmt) => binfmt.execute(),
         }
     }
 }
@@ -98,10 +130,18 @@ pub fn wasmer_main() {
     // In case that fails, we fallback trying the Run subcommand directly.
     // Eg. `wasmer myfile.wasm --dir=.`
     let args = std::env::args().collect::<Vec<_>>();
+    // A binary executed through binfmt_misc gets all its arguments, even
+    // the ones looking like options of wasmer.
+    #[cfg(target_os = "linux")]
+    if let Some(run_args) = crate::commands::binfmt_run_args(&args) {
+        PrettyError::report(WasmerCLIOptions::from_iter(run_args).execute());
+    }
     let command = args.get(1);
     let options = match command.unwrap_or(&"".to_string()).as_ref() {
-        "cache" | "compile" | "config" | "create-exe" | "help" | "inspect" | "run"
-        | "self-update" | "validate" | "wast" => WasmerCLIOptions::from_args(),
+        "binfmt" | "cache" | "compile" | "completions" | "config" | "create-exe" | "create-obj"
+        | "fuzz-diff" | "help" | "inspect" | "run" | "self-update" | "validate" | "wast" => {
+            WasmerCLIOptions::from_args()
+        }
         _ => {
             WasmerCLIOptions::from_iter_safe(args.iter()).unwrap_or_else(|e| {
                 match e.kind {
@@ -1,10 +1,17 @@
 //! The commands available in the Wasmer binary.
+#[cfg(target_os = "linux")]
+mod binfmt;
 mod cache;
 #[cfg(feature = "compiler")]
 mod compile;
+mod completions;
 mod config;
 #[cfg(all(feature = "staticlib", feature = "compiler"))]
 mod create_exe;
+#[cfg(all(feature = "staticlib", feature = "compiler"))]
+mod create_obj;
+#[cfg(feature = "fuzz-diff")]
+mod fuzz_diff;
 mod inspect;
 mod run;
 mod self_update;
@@ -12,10 +19,16 @@ mod validate;
 #[cfg(feature = "wast")]
 mod wast;
 
+#[cfg(target_os = "linux")]
+pub use binfmt::*;
 #[cfg(feature = "compiler")]
 pub use compile::*;
 #[cfg(all(feature = "staticlib", feature = "compiler"))]
 pub use create_exe::*;
+#[cfg(all(feature = "staticlib", feature = "compiler"))]
+pub use create_obj::*;
+#[cfg(feature = "fuzz-diff")]
+pub use fuzz_diff::*;
 #[cfg(feature = "wast")]
 pub use wast::*;
-pub use {cache::*, config::*, inspect::*, run::*, self_update::*, validate::*};
+pub use {cache::*, completions::*, config::*, inspect::*, run::*, self_update::*, validate::*};
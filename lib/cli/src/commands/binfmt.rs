@@ -0,0 +1,174 @@
+//! Registers wasmer as the interpreter of WebAssembly binaries with
+//! Linux `binfmt_misc`.
+use anyhow::{Context, Result};
+use std::env;
+use std::ffi::CStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// The name of the `binfmt_misc` entry for WebAssembly binaries
+const ENTRY_NAME: &str = "wasm32";
+
+/// The magic bytes (`\0asm` followed by version 1), escaped the way
+/// `binfmt_misc` expects them
+const WASM_MAGIC: &str = r"\x00asm\x01\x00\x00\x00";
+
+#[derive(Debug, StructOpt)]
+enum Action {
+    /// Register wasmer as the interpreter of WebAssembly binaries
+    #[structopt(name = "register")]
+    Register,
+
+    /// Unregister wasmer, if it was registered
+    #[structopt(name = "unregister")]
+    Unregister,
+
+    /// Unregister and register again, e.g. after wasmer was moved
+    #[structopt(name = "reregister")]
+    Reregister,
+
+    /// Print the configuration to persist the registration with
+    /// `systemd-binfmt`, usually as `/etc/binfmt.d/wasmer.conf`
+    #[structopt(name = "config")]
+    Config,
+}
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer binfmt` subcommand
+pub struct Binfmt {
+    #[structopt(subcommand)]
+    action: Action,
+
+    /// Mount point of the binfmt_misc filesystem
+    #[structopt(
+        long = "binfmt-misc",
+        default_value = "/proc/sys/fs/binfmt_misc",
+        parse(from_os_str)
+    )]
+    binfmt_misc: PathBuf,
+}
+
+impl Binfmt {
+    /// Runs logic for the `binfmt` subcommand
+    pub fn execute(&self) -> Result<()> {
+        match self.action {
+            Action::Register => self.register(),
+            Action::Unregister => self.unregister(),
+            Action::Reregister => {
+                self.unregister()?;
+                self.register()
+            }
+            Action::Config => {
+                println!("{}", registration()?);
+                Ok(())
+            }
+        }
+    }
+
+    fn register(&self) -> Result<()> {
+        let entry = self.binfmt_misc.join(ENTRY_NAME);
+        if entry.exists() {
+            bail!(
+                "`{}` is already registered, use `wasmer binfmt reregister` to replace it",
+                entry.display()
+            );
+        }
+        write_control(&self.binfmt_misc.join("register"), &registration()?)?;
+        eprintln!("Registered wasmer as the interpreter of WebAssembly binaries.");
+        Ok(())
+    }
+
+    fn unregister(&self) -> Result<()> {
+        let entry = self.binfmt_misc.join(ENTRY_NAME);
+        if !entry.exists() {
+            eprintln!("wasmer is not registered, nothing to do.");
+            return Ok(());
+        }
+        // Writing `-1` to an entry removes it
+        write_control(&entry, "-1")?;
+        eprintln!("Unregistered wasmer.");
+        Ok(())
+    }
+}
+
+/// Returns the `binfmt_misc` registration string for the running
+/// wasmer binary.
+///
+/// The `F` flag makes the kernel open the interpreter when the entry
+/// is registered, so it also works inside containers and chroots.
+fn registration() -> Result<String> {
+    let interpreter = env::current_exe()
+        .and_then(|path| path.canonicalize())
+        .context("failed to locate the wasmer binary")?;
+    Ok(format!(
+        ":{}:M::{}::{}:F",
+        ENTRY_NAME,
+        WASM_MAGIC,
+        interpreter.display()
+    ))
+}
+
+/// Returns the arguments running the WebAssembly binary the kernel
+/// executed with wasmer through `binfmt_misc`, or `None` if wasmer
+/// wasn't started this way.
+///
+/// The kernel passes the path of the binary followed by its own
+/// arguments, which all go to the module, even the ones looking like
+/// options of wasmer.
+pub fn binfmt_run_args(args: &[String]) -> Option<Vec<String>> {
+    let binary = args.get(1)?;
+    // The file given to `execve` is the binary itself rather than wasmer
+    // when the kernel went through binfmt_misc.
+    let execfn = unsafe { libc::getauxval(libc::AT_EXECFN) } as *const libc::c_char;
+    if execfn.is_null() || unsafe { CStr::from_ptr(execfn) }.to_bytes() != binary.as_bytes() {
+        return None;
+    }
+    let mut run_args = vec![
+        args[0].clone(),
+        "run".to_string(),
+        binary.clone(),
+        "--".to_string(),
+    ];
+    run_args.extend(args[2..].iter().cloned());
+    Some(run_args)
+}
+
+fn write_control(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents).with_context(|| {
+        format!(
+            "failed to write to `{}`. Is binfmt_misc mounted, and are you running as root?",
+            path.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_binfmt_invocations() {
+        let args = ["wasmer", "module.wasm", "-x"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(binfmt_run_args(&args), None);
+
+        // This test binary was executed from the file `AT_EXECFN` names,
+        // as a binary run through binfmt_misc would be.
+        let execfn = unsafe { CStr::from_ptr(libc::getauxval(libc::AT_EXECFN) as *const _) };
+        let binary = execfn.to_str().unwrap().to_string();
+        let args = vec!["wasmer".to_string(), binary.clone(), "--dir=.".to_string()];
+        assert_eq!(
+            binfmt_run_args(&args),
+            Some(vec![
+                "wasmer".to_string(),
+                "run".to_string(),
+                binary,
+                "--".to_string(),
+                "--dir=.".to_string(),
+            ])
+        );
+    }
+}
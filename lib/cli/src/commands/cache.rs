@@ -1,6 +1,8 @@
-use crate::common::get_cache_dir;
+use crate::common::{get_cache_dir, PACKAGES_CACHE_DIR};
 use anyhow::{Context, Result};
+use bytesize::ByteSize;
 use std::fs;
+use std::path::Path;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -13,6 +15,11 @@ pub enum Cache {
     /// Display the location of the cache
     #[structopt(name = "dir")]
     Dir,
+
+    /// Display the number of cached modules and their total size,
+    /// broken down by compiler
+    #[structopt(name = "stats")]
+    Stats,
 }
 
 impl Cache {
@@ -25,6 +32,9 @@ impl Cache {
             Cache::Dir => {
                 self.dir()?;
             }
+            Cache::Stats => {
+                self.stats().context("failed to read wasmer cache.")?;
+            }
         }
         Ok(())
     }
@@ -41,4 +51,59 @@ impl Cache {
         println!("{}", get_cache_dir().to_string_lossy());
         Ok(())
     }
+    fn stats(&self) -> Result<()> {
+        let cache_dir = get_cache_dir();
+        println!("Cache directory: {}", cache_dir.to_string_lossy());
+        if !cache_dir.exists() {
+            println!("Entries: 0");
+            println!("Total size: {}", ByteSize(0));
+            return Ok(());
+        }
+
+        // Each compiler stores its modules in its own subdirectory, next
+        // to the downloaded packages
+        let mut compilers = fs::read_dir(&cache_dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        compilers.retain(|path| path.is_dir() && !path.ends_with(PACKAGES_CACHE_DIR));
+        compilers.sort();
+
+        let mut total_entries = 0;
+        let mut total_size = 0;
+        let mut breakdown = vec![];
+        for compiler_dir in compilers.iter() {
+            let (entries, size) = dir_stats(compiler_dir)?;
+            total_entries += entries;
+            total_size += size;
+            let name = compiler_dir.file_name().unwrap_or_default();
+            breakdown.push((name.to_string_lossy().to_string(), entries, size));
+        }
+
+        println!("Entries: {}", total_entries);
+        println!("Total size: {}", ByteSize(total_size));
+        for (name, entries, size) in breakdown {
+            println!("  {}: {} entries, {}", name, entries, ByteSize(size));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of files in `dir` (recursively) and their
+/// total size in bytes.
+fn dir_stats(dir: &Path) -> Result<(usize, u64)> {
+    let mut entries = 0;
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            let (sub_entries, sub_size) = dir_stats(&entry.path())?;
+            entries += sub_entries;
+            size += sub_size;
+        } else {
+            entries += 1;
+            size += metadata.len();
+        }
+    }
+    Ok((entries, size))
 }
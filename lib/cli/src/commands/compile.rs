@@ -1,4 +1,6 @@
-use crate::store::{EngineType, StoreOptions};
+use crate::common::hint_disabled_proposal;
+use crate::store::{CompilerType, EngineType, StoreOptions};
+use crate::utils::parse_cpu_features;
 use crate::warning;
 use anyhow::{Context, Result};
 use std::path::PathBuf;
@@ -29,6 +31,16 @@ pub struct Compile {
 
     #[structopt(short = "m", multiple = true)]
     cpu_features: Vec<CpuFeature>,
+
+    /// CPU features to enable for the target, as a comma-separated list
+    /// (for example `+neon` or `+sse4.2,+avx`)
+    #[structopt(long = "cpu-features")]
+    target_cpu_features: Option<String>,
+
+    /// Remove the debug information (DWARF and source map sections) from
+    /// the module before compiling it
+    #[structopt(long = "strip-debug")]
+    strip_debug: bool,
 }
 
 impl Compile {
@@ -36,6 +48,7 @@ impl Compile {
     pub fn execute(&self) -> Result<()> {
         self.inner_execute()
             .context(format!("failed to compile `{}`", self.path.display()))
+            .map_err(hint_disabled_proposal)
     }
 
     pub(crate) fn get_recommend_extension(
@@ -60,24 +73,74 @@ impl Compile {
         })
     }
 
+    /// Checks that the chosen engine and compiler can produce an
+    /// artifact for `target`.
+    fn check_target(
+        target: &Target,
+        engine_type: &EngineType,
+        compiler_type: &CompilerType,
+    ) -> Result<()> {
+        let architecture = target.triple().architecture;
+        let is_x86 = matches!(architecture, Architecture::X86_64 | Architecture::X86_32(_));
+        let is_arm = matches!(
+            architecture,
+            Architecture::Aarch64(_) | Architecture::Arm(_)
+        );
+        for feature in target.cpu_features().iter() {
+            let supported = match feature {
+                CpuFeature::NEON => is_arm,
+                _ => is_x86,
+            };
+            if !supported {
+                bail!(
+                    "the CPU feature `{}` is not available on {}",
+                    feature.to_string(),
+                    architecture
+                );
+            }
+        }
+        if *compiler_type == CompilerType::Singlepass && architecture != Architecture::X86_64 {
+            bail!(
+                "the {} compiler can't generate code for {}",
+                compiler_type.to_string(),
+                architecture
+            );
+        }
+        if *engine_type == EngineType::Universal && architecture != Triple::host().architecture {
+            bail!(
+                "the {} engine can't cross-compile to {}; use the dylib or staticlib engine instead",
+                engine_type.to_string(),
+                target.triple()
+            );
+        }
+        Ok(())
+    }
+
     fn inner_execute(&self) -> Result<()> {
-        let target = self
-            .target_triple
-            .as_ref()
-            .map(|target_triple| {
+        let target = match &self.target_triple {
+            Some(target_triple) => {
                 let mut features = self
                     .cpu_features
                     .clone()
                     .into_iter()
                     .fold(CpuFeature::set(), |a, b| a | b);
+                if let Some(cpu_features) = &self.target_cpu_features {
+                    for feature in parse_cpu_features(cpu_features)? {
+                        features |= feature;
+                    }
+                }
                 // Cranelift requires SSE2, so we have this "hack" for now to facilitate
                 // usage
-                features |= CpuFeature::SSE2;
+                if target_triple.architecture == Architecture::X86_64 {
+                    features |= CpuFeature::SSE2;
+                }
                 Target::new(target_triple.clone(), features)
-            })
-            .unwrap_or_default();
+            }
+            None => Target::default(),
+        };
         let (store, engine_type, compiler_type) =
             self.store.get_store_for_target(target.clone())?;
+        Self::check_target(&target, &engine_type, &compiler_type)?;
         let output_filename = self
             .output
             .file_stem()
@@ -98,7 +161,15 @@ impl Compile {
         println!("Compiler: {}", compiler_type.to_string());
         println!("Target: {}", target.triple());
 
-        let module = Module::from_file(&store, &self.path)?;
+        let module = if self.strip_debug {
+            let bytes = std::fs::read(&self.path)?;
+            #[cfg(feature = "wat")]
+            let bytes = wat2wasm(&bytes)?;
+            let bytes = strip_custom_sections(&bytes, &[SectionFilter::Debug])?;
+            Module::new(&store, bytes)?
+        } else {
+            Module::from_file(&store, &self.path)?
+        };
         let _ = module.serialize_to_file(&self.output)?;
         eprintln!(
             "✔ File compiled successfully to `{}`.",
@@ -0,0 +1,23 @@
+//! Generates shell completions for the wasmer CLI
+use anyhow::Result;
+use std::io;
+use structopt::clap::{App, Shell};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer completions` subcommand
+pub struct Completions {
+    /// The shell to generate completions for
+    #[structopt(name = "SHELL", possible_values = &Shell::variants(), case_insensitive = true)]
+    shell: Shell,
+}
+
+impl Completions {
+    /// Runs logic for the `completions` subcommand, printing the
+    /// completions of `app` to stdout
+    pub fn execute(&self, mut app: App) -> Result<()> {
+        let name = app.get_name().to_string();
+        app.gen_completions_to(name, self.shell, &mut io::stdout());
+        Ok(())
+    }
+}
@@ -4,6 +4,14 @@ use std::env;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
+/// System libraries needed when linking statically against `libwasmer`
+#[cfg(target_os = "macos")]
+const PRIVATE_LIBS: &str = "-lpthread -ldl -lm -framework Security -framework CoreFoundation";
+#[cfg(target_os = "windows")]
+const PRIVATE_LIBS: &str = "-lws2_32 -luserenv -ladvapi32 -lbcrypt";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const PRIVATE_LIBS: &str = "-lpthread -ldl -lm";
+
 #[derive(Debug, StructOpt)]
 /// The options for the `wasmer config` subcommand
 pub struct Config {
@@ -44,19 +52,7 @@ impl Config {
             .context("failed to retrieve the wasmer config".to_string())
     }
     fn inner_execute(&self) -> Result<()> {
-        let key = "WASMER_DIR";
-        let wasmer_dir = env::var(key)
-            .or_else(|e| {
-                option_env!("WASMER_INSTALL_PREFIX")
-                    .map(str::to_string)
-                    .ok_or(e)
-            })
-            .context(format!(
-                "failed to retrieve the {} environment variables",
-                key
-            ))?;
-
-        let prefix = PathBuf::from(wasmer_dir);
+        let prefix = get_prefix()?;
 
         let prefixdir = prefix.display().to_string();
         let bindir = prefix.join("bin").display().to_string();
@@ -67,15 +63,16 @@ impl Config {
 
         if self.pkg_config {
             println!("prefix={}", prefixdir);
-            println!("exec_prefix={}", bindir);
-            println!("includedir={}", includedir);
-            println!("libdir={}", libdir);
+            println!("exec_prefix=${{prefix}}");
+            println!("includedir=${{prefix}}/include");
+            println!("libdir=${{exec_prefix}}/lib");
             println!();
             println!("Name: wasmer");
             println!("Description: The Wasmer library for running WebAssembly");
             println!("Version: {}", VERSION);
-            println!("Cflags: {}", cflags);
-            println!("Libs: {}", libs);
+            println!("Cflags: -I${{includedir}}");
+            println!("Libs: -L${{libdir}} -lwasmer");
+            println!("Libs.private: {}", PRIVATE_LIBS);
             return Ok(());
         }
 
@@ -100,3 +97,22 @@ impl Config {
         Ok(())
     }
 }
+
+/// Returns the installation prefix, from the `WASMER_DIR` environment
+/// variable, the prefix set at build time, or else the parent of the
+/// directory containing the running `wasmer` executable.
+fn get_prefix() -> Result<PathBuf> {
+    if let Ok(dir) = env::var("WASMER_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = option_env!("WASMER_INSTALL_PREFIX") {
+        return Ok(PathBuf::from(dir));
+    }
+    let exe = env::current_exe()
+        .and_then(|path| path.canonicalize())
+        .context("failed to locate the wasmer executable")?;
+    exe.parent()
+        .and_then(|bindir| bindir.parent())
+        .map(|prefix| prefix.to_path_buf())
+        .context("failed to retrieve the WASMER_DIR environment variable")
+}
@@ -36,6 +36,13 @@ pub struct CreateExe {
     /// This is useful for fixing linker errors that may occur on some systems.
     #[structopt(short = "l", multiple = true)]
     libraries: Vec<String>,
+
+    /// The static Wasmer library to link against.
+    /// Defaults to the one in `$WASMER_DIR/lib`. A headless build of the
+    /// library (`make build-capi-headless-staticlib`) only contains the
+    /// runtime and produces much smaller executables.
+    #[structopt(long = "libwasmer", parse(from_os_str))]
+    libwasmer: Option<PathBuf>,
 }
 
 impl CreateExe {
@@ -68,6 +75,10 @@ impl CreateExe {
         let working_dir = tempfile::tempdir()?;
         let starting_cd = env::current_dir()?;
         let output_path = starting_cd.join(&self.output);
+        let libwasmer_path = match &self.libwasmer {
+            Some(path) => starting_cd.join(path),
+            None => get_libwasmer_path()?,
+        };
         env::set_current_dir(&working_dir)?;
 
         #[cfg(not(windows))]
@@ -95,7 +106,7 @@ impl CreateExe {
         );
 
         generate_header(header_file_src.as_bytes())?;
-        self.compile_c(wasm_object_path, output_path)?;
+        self.compile_c(wasm_object_path, output_path, libwasmer_path)?;
 
         eprintln!(
             "✔ Native executable compiled successfully to `{}`.",
@@ -105,7 +116,12 @@ impl CreateExe {
         Ok(())
     }
 
-    fn compile_c(&self, wasm_object_path: PathBuf, output_path: PathBuf) -> anyhow::Result<()> {
+    fn compile_c(
+        &self,
+        wasm_object_path: PathBuf,
+        output_path: PathBuf,
+        libwasmer_path: PathBuf,
+    ) -> anyhow::Result<()> {
         use std::io::Write;
 
         // write C src to disk
@@ -129,6 +145,7 @@ impl CreateExe {
             object_paths: vec![c_src_obj, wasm_object_path],
             output_path,
             additional_libraries: self.libraries.clone(),
+            libwasmer_path,
             target: self.target_triple.clone(),
             ..Default::default()
         }
@@ -176,7 +193,6 @@ fn get_libwasmer_path() -> anyhow::Result<PathBuf> {
     let mut path = get_wasmer_dir()?;
     path.push("lib");
 
-    // TODO: prefer headless Wasmer if/when it's a separate library.
     #[cfg(not(windows))]
     path.push("libwasmer.a");
     #[cfg(windows)]
@@ -257,7 +273,7 @@ impl Default for LinkCode {
             object_paths: vec![],
             additional_libraries: vec![],
             output_path: PathBuf::from("a.out"),
-            libwasmer_path: get_libwasmer_path().unwrap(),
+            libwasmer_path: get_libwasmer_path().unwrap_or_default(),
             target: None,
         }
     }
@@ -0,0 +1,108 @@
+//! Create a native object file and its C header for a given Wasm file.
+
+use crate::store::{CompilerOptions, EngineType};
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use wasmer::*;
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer create-obj` subcommand
+pub struct CreateObj {
+    /// Input file
+    #[structopt(name = "FILE", parse(from_os_str))]
+    path: PathBuf,
+
+    /// Output file
+    #[structopt(name = "OUTPUT PATH", short = "o", parse(from_os_str))]
+    output: PathBuf,
+
+    /// Output path for the generated header file.
+    /// Defaults to the output file with a `.h` extension.
+    #[structopt(name = "HEADER PATH", long = "header", parse(from_os_str))]
+    header_path: Option<PathBuf>,
+
+    /// Compilation Target triple
+    #[structopt(long = "target")]
+    target_triple: Option<Triple>,
+
+    #[structopt(flatten)]
+    compiler: CompilerOptions,
+
+    #[structopt(short = "m", multiple = true)]
+    cpu_features: Vec<CpuFeature>,
+}
+
+impl CreateObj {
+    /// Runs logic for the `create-obj` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute()
+            .context(format!("failed to compile `{}`", self.path.display()))
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        let target = self
+            .target_triple
+            .as_ref()
+            .map(|target_triple| {
+                let mut features = self
+                    .cpu_features
+                    .clone()
+                    .into_iter()
+                    .fold(CpuFeature::set(), |a, b| a | b);
+                // Cranelift requires SSE2 on x86, so we have this "hack" for now
+                // to facilitate usage
+                if matches!(
+                    target_triple.architecture,
+                    Architecture::X86_64 | Architecture::X86_32(_)
+                ) {
+                    features |= CpuFeature::SSE2;
+                }
+                Target::new(target_triple.clone(), features)
+            })
+            .unwrap_or_default();
+        let engine_type = EngineType::Staticlib;
+        let (store, compiler_type) = self
+            .compiler
+            .get_store_for_target_and_engine(target.clone(), engine_type)?;
+
+        println!("Engine: {}", engine_type.to_string());
+        println!("Compiler: {}", compiler_type.to_string());
+        println!("Target: {}", target.triple());
+
+        let module = Module::from_file(&store, &self.path).context("failed to compile Wasm")?;
+        module.serialize_to_file(&self.output)?;
+        eprintln!(
+            "✔ Object file compiled successfully to `{}`.",
+            self.output.display(),
+        );
+
+        let artifact: &wasmer_engine_staticlib::StaticlibArtifact =
+            module.artifact().as_ref().downcast_ref().context(
+                "Engine type is Staticlib but could not downcast artifact into StaticlibArtifact",
+            )?;
+        let header_file_src = crate::c_gen::staticlib_header::generate_header_file(
+            module.info(),
+            artifact.symbol_registry(),
+            artifact.metadata_length(),
+        );
+        let header_path = self
+            .header_path
+            .clone()
+            .unwrap_or_else(|| self.output.with_extension("h"));
+        fs::File::create(&header_path)
+            .and_then(|mut header| header.write_all(header_file_src.as_bytes()))
+            .context(format!(
+                "failed to write the header file `{}`",
+                header_path.display()
+            ))?;
+        eprintln!(
+            "✔ Header file generated successfully at `{}`.",
+            header_path.display(),
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+//! Runs generated modules on two compilers and reports the differences
+//! in their behavior.
+use crate::store::CompilerType;
+use anyhow::{Context, Result};
+use arbitrary::{Arbitrary, Unstructured};
+use std::fs;
+use std::path::PathBuf;
+use std::slice;
+use std::time::{SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+use wasm_smith::{Config, ConfiguredModule};
+use wasmer::*;
+use wasmer_compiler::CompilerConfig;
+use wasmer_engine_universal::Universal;
+use wasmer_vm::TrapCode;
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer fuzz-diff` subcommand
+pub struct FuzzDiff {
+    /// The reference compiler: `singlepass`, `cranelift` or `llvm`
+    #[structopt(name = "LEFT")]
+    left: CompilerType,
+
+    /// The compiler compared to the reference one
+    #[structopt(name = "RIGHT")]
+    right: CompilerType,
+
+    /// The number of modules to generate
+    #[structopt(long, default_value = "1000")]
+    iterations: u64,
+
+    /// The seed of the first module, to reproduce a run. Each module is
+    /// generated from the seed of the previous one plus one
+    #[structopt(long)]
+    seed: Option<u64>,
+
+    /// The number of random bytes each module is generated from
+    #[structopt(long, default_value = "4096")]
+    input_size: usize,
+
+    /// The fuel of the generated modules, bounding how many loop
+    /// iterations and calls they run
+    #[structopt(long, default_value = "100000")]
+    fuel: u32,
+
+    /// The directory to write the modules which behave differently to
+    #[structopt(long, short, parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Stop at the first module which behaves differently
+    #[structopt(long)]
+    fail_fast: bool,
+}
+
+/// The modules generated: they can't import anything, and export at least
+/// a function, which is called if it has no parameters.
+#[derive(Arbitrary, Debug, Default, Clone, Copy)]
+struct DiffConfig;
+
+impl Config for DiffConfig {
+    fn max_imports(&self) -> usize {
+        0
+    }
+    fn max_memory_pages(&self) -> u32 {
+        16
+    }
+    fn min_funcs(&self) -> usize {
+        1
+    }
+    fn min_exports(&self) -> usize {
+        1
+    }
+}
+
+impl FuzzDiff {
+    /// Runs logic for the `fuzz-diff` subcommand
+    pub fn execute(&self) -> Result<()> {
+        let left = compiler_store(&self.left)
+            .with_context(|| format!("failed to set up `{}`", self.left.to_string()))?;
+        let right = compiler_store(&self.right)
+            .with_context(|| format!("failed to set up `{}`", self.right.to_string()))?;
+        if let Some(output) = &self.output {
+            fs::create_dir_all(output)
+                .with_context(|| format!("failed to create `{}`", output.display()))?;
+        }
+        let seed = self.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        eprintln!(
+            "Comparing `{}` to `{}` on {} modules from seed {}",
+            self.left.to_string(),
+            self.right.to_string(),
+            self.iterations,
+            seed
+        );
+
+        let (mut same, mut skipped, mut diverged) = (0, 0, 0);
+        for iteration in 0..self.iterations {
+            let case_seed = seed.wrapping_add(iteration);
+            let wasm = match self.generate(case_seed) {
+                Some(wasm) => wasm,
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            match compare(&run(&left, &wasm), &run(&right, &wasm)) {
+                Comparison::Same => same += 1,
+                Comparison::Inconclusive(_) => skipped += 1,
+                Comparison::Diverged(difference) => {
+                    diverged += 1;
+                    eprintln!("✘ seed {}: {}", case_seed, difference);
+                    if let Some(output) = &self.output {
+                        let path = output.join(format!("diverging-{}.wasm", case_seed));
+                        fs::write(&path, &wasm)
+                            .with_context(|| format!("failed to write `{}`", path.display()))?;
+                    }
+                    if self.fail_fast {
+                        break;
+                    }
+                }
+            }
+        }
+
+        eprintln!(
+            "{} modules behaved the same, {} were skipped, {} diverged.",
+            same, skipped, diverged
+        );
+        if diverged > 0 {
+            bail!(
+                "`{}` and `{}` behaved differently on {} modules",
+                self.left.to_string(),
+                self.right.to_string(),
+                diverged
+            );
+        }
+        Ok(())
+    }
+
+    /// Generates the module of `seed`, if the random bytes are enough to.
+    fn generate(&self, seed: u64) -> Option<Vec<u8>> {
+        let mut state = seed;
+        let bytes = (0..self.input_size)
+            .map(|_| splitmix64(&mut state) as u8)
+            .collect::<Vec<_>>();
+        let mut module =
+            ConfiguredModule::<DiffConfig>::arbitrary(&mut Unstructured::new(&bytes)).ok()?;
+        module.ensure_termination(self.fuel);
+        Some(module.to_bytes())
+    }
+}
+
+/// The SplitMix64 generator, which is enough to get reproducible random
+/// bytes from a seed.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Gets a store compiling with `compiler` and the Universal engine.
+///
+/// NaNs are canonicalized, since their bits are nondeterministic otherwise.
+fn compiler_store(compiler: &CompilerType) -> Result<Store> {
+    let mut config: Box<dyn CompilerConfig> = match compiler {
+        #[cfg(feature = "singlepass")]
+        CompilerType::Singlepass => Box::new(wasmer_compiler_singlepass::Singlepass::new()),
+        #[cfg(feature = "cranelift")]
+        CompilerType::Cranelift => Box::new(wasmer_compiler_cranelift::Cranelift::new()),
+        #[cfg(feature = "llvm")]
+        CompilerType::LLVM => Box::new(wasmer_compiler_llvm::LLVM::new()),
+        compiler => bail!(
+            "The `{}` compiler is not included in this binary.",
+            compiler.to_string()
+        ),
+    };
+    config.canonicalize_nans(true);
+    Ok(Store::new(&Universal::new(config).engine()))
+}
+
+/// The outcome of running a function.
+#[derive(Debug)]
+enum Outcome {
+    Values(Vec<Val>),
+    Trap(Option<TrapCode>),
+}
+
+/// What running a module did.
+#[derive(Debug)]
+enum Execution {
+    /// The module failed to compile, with the error given.
+    CompileError(String),
+    Ran(Run),
+}
+
+#[derive(Debug, Default)]
+struct Run {
+    /// The outcome of instantiating the module, which runs its start
+    /// function.
+    start: Option<Outcome>,
+    /// The outcome of calling each exported function without parameters,
+    /// in order.
+    calls: Vec<(String, Outcome)>,
+    /// The values of the exported globals once the functions ran.
+    globals: Vec<(String, Val)>,
+    /// The contents of the exported memories once the functions ran.
+    memories: Vec<(String, Vec<u8>)>,
+}
+
+fn run(store: &Store, wasm: &[u8]) -> Execution {
+    let module = match Module::new(store, wasm) {
+        Ok(module) => module,
+        Err(error) => return Execution::CompileError(error.to_string()),
+    };
+    let instance = match Instance::new(&module, &imports! {}) {
+        Ok(instance) => instance,
+        Err(InstantiationError::Start(error)) => {
+            return Execution::Ran(Run {
+                start: Some(Outcome::Trap(error.to_trap())),
+                ..Run::default()
+            })
+        }
+        Err(error) => return Execution::CompileError(error.to_string()),
+    };
+
+    let calls = instance
+        .exports
+        .iter()
+        .functions()
+        .filter(|(_, function)| function.ty().params().is_empty())
+        .map(|(name, function)| {
+            let outcome = match function.call(&[]) {
+                Ok(values) => Outcome::Values(values.into_vec()),
+                Err(error) => Outcome::Trap(error.to_trap()),
+            };
+            (name.clone(), outcome)
+        })
+        .collect();
+    let globals = instance
+        .exports
+        .iter()
+        .globals()
+        .map(|(name, global)| (name.clone(), global.get()))
+        .collect();
+    let memories = instance
+        .exports
+        .iter()
+        .memories()
+        .map(|(name, memory)| {
+            let mut contents = vec![0; memory.data_size() as usize];
+            memory
+                .read(0, &mut contents)
+                .expect("the memory to contain its own data");
+            (name.clone(), contents)
+        })
+        .collect();
+
+    Execution::Ran(Run {
+        start: None,
+        calls,
+        globals,
+        memories,
+    })
+}
+
+enum Comparison {
+    Same,
+    /// The compilers can't be compared on the module.
+    Inconclusive(String),
+    Diverged(String),
+}
+
+fn values_match(left: &[Val], right: &[Val]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .all(|(left, right)| match (left, right) {
+                (Val::F32(left), Val::F32(right)) => left.to_bits() == right.to_bits(),
+                (Val::F64(left), Val::F64(right)) => left.to_bits() == right.to_bits(),
+                (Val::I32(_), _) | (Val::I64(_), _) | (Val::V128(_), _) => left == right,
+                // References can only be compared by type across stores
+                (left, right) => left.ty() == right.ty(),
+            })
+}
+
+fn compare_outcomes(what: &str, left: &Outcome, right: &Outcome) -> Comparison {
+    match (left, right) {
+        (Outcome::Values(left_values), Outcome::Values(right_values))
+            if values_match(left_values, right_values) =>
+        {
+            Comparison::Same
+        }
+        (Outcome::Trap(left_trap), Outcome::Trap(right_trap)) if left_trap == right_trap => {
+            Comparison::Same
+        }
+        // The depth of the stack depends on the frames the compilers
+        // generate
+        (Outcome::Trap(Some(TrapCode::StackOverflow)), _)
+        | (_, Outcome::Trap(Some(TrapCode::StackOverflow))) => {
+            Comparison::Inconclusive(format!("{} exhausted the stack", what))
+        }
+        (left, right) => Comparison::Diverged(format!("{}: {:?} and {:?}", what, left, right)),
+    }
+}
+
+fn compare(left: &Execution, right: &Execution) -> Comparison {
+    let (left, right) = match (left, right) {
+        (Execution::Ran(left), Execution::Ran(right)) => (left, right),
+        // Compilers may not support all the proposals the modules use
+        (Execution::CompileError(error), _) | (_, Execution::CompileError(error)) => {
+            return Comparison::Inconclusive(error.clone())
+        }
+    };
+
+    match (&left.start, &right.start) {
+        (Some(left), Some(right)) => {
+            return compare_outcomes("the start function", left, right);
+        }
+        (None, None) => {}
+        (left, right) => {
+            return Comparison::Diverged(format!("the start function: {:?} and {:?}", left, right))
+        }
+    }
+    for ((name, left), (_, right)) in left.calls.iter().zip(&right.calls) {
+        match compare_outcomes(&format!("`{}`", name), left, right) {
+            Comparison::Same => {}
+            other => return other,
+        }
+    }
+    for ((name, left), (_, right)) in left.globals.iter().zip(&right.globals) {
+        if !values_match(slice::from_ref(left), slice::from_ref(right)) {
+            return Comparison::Diverged(format!("global `{}`: {:?} and {:?}", name, left, right));
+        }
+    }
+    for ((name, left), (_, right)) in left.memories.iter().zip(&right.memories) {
+        if left.len() != right.len() {
+            return Comparison::Diverged(format!(
+                "memory `{}`: {} and {} bytes",
+                name,
+                left.len(),
+                right.len()
+            ));
+        }
+        if let Some(offset) = left
+            .iter()
+            .zip(right)
+            .position(|(left, right)| left != right)
+        {
+            return Comparison::Diverged(format!(
+                "memory `{}` at {:#x}: {:#04x} and {:#04x}",
+                name, offset, left[offset], right[offset]
+            ));
+        }
+    }
+    Comparison::Same
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ran(calls: Vec<(&str, Outcome)>) -> Execution {
+        Execution::Ran(Run {
+            calls: calls
+                .into_iter()
+                .map(|(name, outcome)| (name.to_string(), outcome))
+                .collect(),
+            ..Run::default()
+        })
+    }
+
+    #[test]
+    fn compare_executions() {
+        let same = compare(
+            &ran(vec![("f", Outcome::Values(vec![Val::F32(f32::NAN)]))]),
+            &ran(vec![("f", Outcome::Values(vec![Val::F32(f32::NAN)]))]),
+        );
+        assert!(matches!(same, Comparison::Same));
+
+        let diverged = compare(
+            &ran(vec![("f", Outcome::Values(vec![Val::I32(1)]))]),
+            &ran(vec![(
+                "f",
+                Outcome::Trap(Some(TrapCode::UnreachableCodeReached)),
+            )]),
+        );
+        assert!(matches!(diverged, Comparison::Diverged(_)));
+
+        let stack_overflow = compare(
+            &ran(vec![("f", Outcome::Trap(Some(TrapCode::StackOverflow)))]),
+            &ran(vec![("f", Outcome::Values(vec![]))]),
+        );
+        assert!(matches!(stack_overflow, Comparison::Inconclusive(_)));
+
+        let unsupported = compare(
+            &Execution::CompileError("multi-value returns not yet implemented".to_string()),
+            &ran(vec![]),
+        );
+        assert!(matches!(unsupported, Comparison::Inconclusive(_)));
+    }
+
+    #[test]
+    fn generation_is_reproducible() {
+        let (mut a, mut b) = (42, 42);
+        assert_eq!(splitmix64(&mut a), splitmix64(&mut b));
+        assert_ne!(splitmix64(&mut a), splitmix64(&mut 43));
+    }
+}
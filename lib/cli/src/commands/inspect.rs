@@ -1,74 +1,237 @@
 use crate::store::StoreOptions;
 use anyhow::{Context, Result};
 use bytesize::ByteSize;
+use serde::Serialize;
 use std::path::PathBuf;
 use structopt::StructOpt;
 use wasmer::*;
+use wasmer_vm::ModuleInfo;
+
+#[cfg(feature = "disassembler")]
+mod disassemble;
 
 #[derive(Debug, StructOpt)]
-/// The options for the `wasmer validate` subcommand
+/// The options for the `wasmer inspect` subcommand
 pub struct Inspect {
-    /// File to validate as WebAssembly
+    /// File to inspect as WebAssembly
     #[structopt(name = "FILE", parse(from_os_str))]
     path: PathBuf,
 
+    /// Print the report as JSON, for consumption by other tools
+    #[structopt(long = "json")]
+    json: bool,
+
+    /// Print the machine code generated for the given function, by name
+    /// or index, or for all the functions if none is given
+    #[cfg(feature = "disassembler")]
+    #[structopt(long = "dis", name = "FUNCTION", conflicts_with = "json")]
+    disassemble: Option<Option<String>>,
+
     #[structopt(flatten)]
     store: StoreOptions,
 }
 
+/// An import or an export of the inspected module.
+#[derive(Debug, Serialize)]
+struct Entity {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module: Option<String>,
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+}
+
+/// The imports or the exports of the inspected module, by kind.
+#[derive(Debug, Serialize)]
+struct Entities {
+    functions: Vec<Entity>,
+    memories: Vec<Entity>,
+    tables: Vec<Entity>,
+    globals: Vec<Entity>,
+}
+
+/// Everything `wasmer inspect` reports about a module.
+#[derive(Debug, Serialize)]
+struct Report {
+    #[serde(rename = "type")]
+    ty: &'static str,
+    size: usize,
+    abi: &'static str,
+    features: Vec<&'static str>,
+    imports: Entities,
+    exports: Entities,
+    memories: Vec<String>,
+    tables: Vec<String>,
+    custom_sections: Vec<String>,
+}
+
 impl Inspect {
-    /// Runs logic for the `validate` subcommand
+    /// Runs logic for the `inspect` subcommand
     pub fn execute(&self) -> Result<()> {
         self.inner_execute()
             .context(format!("failed to inspect `{}`", self.path.display()))
     }
+
     fn inner_execute(&self) -> Result<()> {
         let (store, _engine_type, _compiler_type) = self.store.get_store()?;
         let module_contents = std::fs::read(&self.path)?;
         let module = Module::new(&store, &module_contents)?;
-        println!(
-            "Type: {}",
-            if !is_wasm(&module_contents) {
-                "wat"
-            } else {
-                "wasm"
-            }
-        );
-        println!("Size: {}", ByteSize(module_contents.len() as _));
-        println!("Imports:");
-        println!("  Functions:");
-        for f in module.imports().functions() {
-            println!("    \"{}\".\"{}\": {}", f.module(), f.name(), f.ty());
+        #[cfg(feature = "disassembler")]
+        if let Some(function) = &self.disassemble {
+            return disassemble::disassemble(&store, &module, function.as_deref());
         }
-        println!("  Memories:");
-        for f in module.imports().memories() {
-            println!("    \"{}\".\"{}\": {}", f.module(), f.name(), f.ty());
+        let report = Report::new(&module, &module_contents);
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            report.print();
         }
-        println!("  Tables:");
-        for f in module.imports().tables() {
-            println!("    \"{}\".\"{}\": {}", f.module(), f.name(), f.ty());
+        Ok(())
+    }
+}
+
+impl Entity {
+    fn import<T: std::fmt::Display>(import: ImportType<T>) -> Self {
+        Self {
+            module: Some(import.module().to_string()),
+            name: import.name().to_string(),
+            ty: import.ty().to_string(),
         }
-        println!("  Globals:");
-        for f in module.imports().globals() {
-            println!("    \"{}\".\"{}\": {}", f.module(), f.name(), f.ty());
+    }
+
+    fn export<T: std::fmt::Display>(export: ExportType<T>) -> Self {
+        Self {
+            module: None,
+            name: export.name().to_string(),
+            ty: export.ty().to_string(),
         }
+    }
+}
+
+impl Report {
+    fn new(module: &Module, module_contents: &[u8]) -> Self {
+        let info = module.info();
+        let imports = Entities {
+            functions: module.imports().functions().map(Entity::import).collect(),
+            memories: module.imports().memories().map(Entity::import).collect(),
+            tables: module.imports().tables().map(Entity::import).collect(),
+            globals: module.imports().globals().map(Entity::import).collect(),
+        };
+        let exports = Entities {
+            functions: module.exports().functions().map(Entity::export).collect(),
+            memories: module.exports().memories().map(Entity::export).collect(),
+            tables: module.exports().tables().map(Entity::export).collect(),
+            globals: module.exports().globals().map(Entity::export).collect(),
+        };
+        Self {
+            ty: if is_wasm(module_contents) {
+                "wasm"
+            } else {
+                "wat"
+            },
+            size: module_contents.len(),
+            abi: detect_abi(module),
+            features: required_features(info),
+            imports,
+            exports,
+            memories: info.memories.values().map(ToString::to_string).collect(),
+            tables: info.tables.values().map(ToString::to_string).collect(),
+            custom_sections: info.custom_sections.keys().cloned().collect(),
+        }
+    }
+
+    fn print(&self) {
+        println!("Type: {}", self.ty);
+        println!("Size: {}", ByteSize(self.size as _));
+        println!("ABI: {}", self.abi);
+        println!("Required features: {}", self.features.join(", "));
+        println!("Imports:");
+        print_entities(&self.imports);
         println!("Exports:");
-        println!("  Functions:");
-        for f in module.exports().functions() {
-            println!("    \"{}\": {}", f.name(), f.ty());
+        print_entities(&self.exports);
+        println!("Memories:");
+        for memory in &self.memories {
+            println!("  {}", memory);
         }
-        println!("  Memories:");
-        for f in module.exports().memories() {
-            println!("    \"{}\": {}", f.name(), f.ty());
+        println!("Tables:");
+        for table in &self.tables {
+            println!("  {}", table);
         }
-        println!("  Tables:");
-        for f in module.exports().tables() {
-            println!("    \"{}\": {}", f.name(), f.ty());
+        println!("Custom sections:");
+        for name in &self.custom_sections {
+            println!("  \"{}\"", name);
         }
-        println!("  Globals:");
-        for f in module.exports().globals() {
-            println!("    \"{}\": {}", f.name(), f.ty());
+    }
+}
+
+fn print_entities(entities: &Entities) {
+    for (kind, list) in &[
+        ("Functions", &entities.functions),
+        ("Memories", &entities.memories),
+        ("Tables", &entities.tables),
+        ("Globals", &entities.globals),
+    ] {
+        println!("  {}:", kind);
+        for entity in list.iter() {
+            match &entity.module {
+                Some(module) => println!("    \"{}\".\"{}\": {}", module, entity.name, entity.ty),
+                None => println!("    \"{}\": {}", entity.name, entity.ty),
+            }
         }
-        Ok(())
     }
 }
+
+/// The host ABI the module has been compiled against.
+fn detect_abi(module: &Module) -> &'static str {
+    #[cfg(feature = "emscripten")]
+    {
+        if wasmer_emscripten::is_emscripten_module(module) {
+            return "emscripten";
+        }
+    }
+    #[cfg(feature = "wasi")]
+    {
+        if wasmer_wasi::get_wasi_versions(module, false).is_some() {
+            return "wasi";
+        }
+    }
+    let _ = module;
+    "none"
+}
+
+/// The WebAssembly proposals the module declarations depend on.
+///
+/// Only the module declarations (types, memories, tables and
+/// segments) are looked at, not the function bodies.
+fn required_features(info: &ModuleInfo) -> Vec<&'static str> {
+    let mut features = vec![];
+    if info.memories.values().any(|memory| memory.shared) {
+        features.push("threads");
+    }
+    let types = info
+        .signatures
+        .values()
+        .flat_map(|signature| signature.params().iter().chain(signature.results()))
+        .chain(info.globals.values().map(|global| &global.ty))
+        .collect::<Vec<_>>();
+    if types.iter().any(|ty| **ty == Type::V128) {
+        features.push("simd");
+    }
+    if info
+        .signatures
+        .values()
+        .any(|signature| signature.results().len() > 1)
+    {
+        features.push("multi-value");
+    }
+    if !info.passive_data.is_empty() || !info.passive_elements.is_empty() {
+        features.push("bulk-memory");
+    }
+    if info.tables.len() > 1
+        || info.tables.values().any(|table| table.ty != Type::FuncRef)
+        || types.iter().any(|ty| **ty == Type::ExternRef)
+    {
+        features.push("reference-types");
+    }
+    features
+}
@@ -0,0 +1,125 @@
+//! Disassembles the machine code generated for the functions of a module.
+use anyhow::{anyhow, Result};
+use capstone::prelude::*;
+use wasmer::{ExportIndex, Module, Store};
+use wasmer_compiler::Architecture;
+use wasmer_engine_universal::UniversalArtifact;
+use wasmer_types::{FunctionIndex, LocalFunctionIndex};
+
+/// Prints the machine code of the local functions of `module`, or only
+/// of the one matching `function` (by name or index) if specified.
+pub fn disassemble(store: &Store, module: &Module, function: Option<&str>) -> Result<()> {
+    let artifact = module
+        .artifact()
+        .downcast_ref::<UniversalArtifact>()
+        .ok_or_else(|| anyhow!("disassembling is only supported with the universal engine"))?;
+    let capstone = disassembler(store.engine().target().triple().architecture)?;
+    let info = module.info();
+
+    let mut found = false;
+    for (local_index, extent) in artifact.finished_function_extents().iter() {
+        let index = info.func_index(local_index);
+        let names = function_names(module, index);
+        if let Some(function) = function {
+            if function != index.as_u32().to_string() && !names.iter().any(|name| name == function)
+            {
+                continue;
+            }
+        }
+        found = true;
+
+        // Safety: the function bodies stay mapped as long as the artifact
+        // is alive, and they are readable.
+        let code = unsafe { std::slice::from_raw_parts(extent.ptr.0 as *const u8, extent.length) };
+        print_header(module, local_index, index, &names);
+        let instructions = capstone
+            .disasm_all(code, 0)
+            .map_err(|error| anyhow!("failed to disassemble: {}", error))?;
+        for instruction in instructions.iter() {
+            println!(
+                "  {:6x}:  {:<32} {} {}",
+                instruction.address(),
+                instruction
+                    .bytes()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                instruction.mnemonic().unwrap_or(""),
+                instruction.op_str().unwrap_or("")
+            );
+        }
+        println!();
+    }
+
+    if !found {
+        match function {
+            Some(function) => bail!("the module defines no function `{}`", function),
+            None => eprintln!("The module defines no functions."),
+        }
+    }
+    Ok(())
+}
+
+/// Builds a disassembler for the target architecture, using the Intel
+/// syntax on x86.
+fn disassembler(architecture: Architecture) -> Result<Capstone> {
+    let capstone = match architecture {
+        Architecture::X86_64 => Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode64)
+            .syntax(arch::x86::ArchSyntax::Intel)
+            .build(),
+        Architecture::X86_32(_) => Capstone::new()
+            .x86()
+            .mode(arch::x86::ArchMode::Mode32)
+            .syntax(arch::x86::ArchSyntax::Intel)
+            .build(),
+        Architecture::Aarch64(_) => Capstone::new()
+            .arm64()
+            .mode(arch::arm64::ArchMode::Arm)
+            .build(),
+        other => bail!("disassembling {} code is not supported", other),
+    };
+    capstone.map_err(|error| anyhow!("failed to set up the disassembler: {}", error))
+}
+
+/// The names of a function: its name from the `name` section, if any,
+/// followed by the names it's exported as.
+fn function_names(module: &Module, index: FunctionIndex) -> Vec<String> {
+    let info = module.info();
+    info.function_names
+        .get(&index)
+        .map(|name| name.to_string())
+        .into_iter()
+        .chain(
+            info.exports
+                .iter()
+                .filter_map(|(name, export)| match export {
+                    ExportIndex::Function(exported) if *exported == index => Some(name.to_string()),
+                    _ => None,
+                }),
+        )
+        .collect()
+}
+
+fn print_header(
+    module: &Module,
+    local_index: LocalFunctionIndex,
+    index: FunctionIndex,
+    names: &[String],
+) {
+    let info = module.info();
+    let signature = &info.signatures[info.functions[index]];
+    let name = match names.first() {
+        Some(name) => format!(" `{}`", name),
+        None => String::new(),
+    };
+    println!(
+        "Function {}{} (local function {}): {}",
+        index.as_u32(),
+        name,
+        local_index.as_u32(),
+        signature
+    );
+}
@@ -1,39 +1,97 @@
-use crate::common::get_cache_dir;
+use crate::common::{get_cache_dir, hint_disabled_proposal};
 #[cfg(feature = "debug")]
 use crate::logging;
 use crate::store::{CompilerType, EngineType, StoreOptions};
 use crate::suggestions::suggest_function_exports;
+use crate::utils::{is_component, parse_duration};
 use crate::warning;
 use anyhow::{anyhow, Context, Result};
+use std::convert::TryFrom;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 use wasmer::*;
 #[cfg(feature = "cache")]
 use wasmer_cache::{Cache, FileSystemCache, Hash};
 
 use structopt::StructOpt;
 
+#[cfg(feature = "compiler")]
+mod compare;
+mod limits;
+mod link;
+mod package;
+#[cfg(unix)]
+mod profile;
+mod repl;
+#[cfg(unix)]
+mod signals;
 #[cfg(feature = "wasi")]
 mod wasi;
 
+use limits::ResourceLimits;
+use link::Linker;
+use package::PackageSource;
 #[cfg(feature = "wasi")]
 use wasi::Wasi;
 
 #[derive(Debug, StructOpt, Clone)]
 /// The options for the `wasmer run` subcommand
+#[structopt(setting = structopt::clap::AppSettings::AllowNegativeNumbers)]
 pub struct Run {
     /// Disable the cache
     #[structopt(long = "disable-cache")]
     disable_cache: bool,
 
-    /// File to run
+    /// File to run. Can also be a `.webc` package, a package from the
+    /// registry, such as `namespace/package@1.2.3`, or an http(s) URL
     #[structopt(name = "FILE", parse(from_os_str))]
     path: PathBuf,
 
-    /// Invoke a specified function
-    #[structopt(long = "invoke", short = "i")]
+    /// The command to run, for packages declaring several commands
+    #[structopt(long = "command")]
+    command: Option<String>,
+
+    /// The SHA-256 the downloaded package must have, in hex. Required
+    /// when running a package from a URL
+    #[structopt(long = "sha256")]
+    sha256: Option<String>,
+
+    /// Instantiate the given module before the one being run, so its
+    /// exports can be imported from the namespace named after its file
+    /// (eg. `helper` for `helper.wasm`). Can be repeated, each module
+    /// being able to import from the ones linked before it
+    #[structopt(
+        long = "link",
+        name = "LINKED_FILE",
+        number_of_values = 1,
+        parse(from_os_str)
+    )]
+    link: Vec<PathBuf>,
+
+    /// Invoke a specified function. WASI reactors, which have no
+    /// `_start` function, are initialized with `_initialize` first
+    #[structopt(long = "invoke", short = "i", visible_alias = "entrypoint")]
     invoke: Option<String>,
 
+    /// Call the function given with `--invoke` with each of the given
+    /// compilers (eg. `cranelift,llvm`), and report any divergence in
+    /// their results, traps or number of operators executed
+    #[cfg(feature = "compiler")]
+    #[structopt(long = "compare-compilers", requires = "invoke", use_delimiter = true)]
+    compare_compilers: Vec<CompilerType>,
+
+    /// Sample the execution of the module, and write a flamegraph of
+    /// the time spent in its functions to the given SVG file
+    #[cfg(unix)]
+    #[structopt(long = "profile", name = "PROFILE_SVG", parse(from_os_str))]
+    profile: Option<PathBuf>,
+
+    /// Start an interactive prompt to call the module exports and
+    /// inspect its memories and globals
+    #[structopt(long = "repl", conflicts_with = "invoke")]
+    repl: bool,
+
     /// The command name is a string that will override the first argument passed
     /// to the wasm program. This is used in wapm to provide nicer output in
     /// help commands and error messages of the running wasm program
@@ -49,11 +107,29 @@ pub struct Run {
     #[structopt(flatten)]
     store: StoreOptions,
 
+    #[structopt(flatten)]
+    limits: ResourceLimits,
+
+    /// How long to wait for the module to exit after receiving `SIGINT`
+    /// or `SIGTERM`, before terminating it. WASI modules are made to exit
+    /// at their next syscall
+    #[structopt(
+        long = "signal-grace-period",
+        default_value = "1s",
+        parse(try_from_str = parse_duration)
+    )]
+    signal_grace_period: Duration,
+
     // TODO: refactor WASI structure to allow shared options with Emscripten
     #[cfg(feature = "wasi")]
     #[structopt(flatten)]
     wasi: Wasi,
 
+    /// Let Emscripten modules use the sockets of the host
+    #[cfg(feature = "emscripten")]
+    #[structopt(long = "enable-emscripten-net")]
+    enable_emscripten_net: bool,
+
     /// Enable non-standard experimental IO devices
     #[cfg(feature = "io-devices")]
     #[structopt(long = "enable-io-devices")]
@@ -76,49 +152,100 @@ impl Run {
         if self.debug {
             logging::set_up_logging().unwrap();
         }
-        self.inner_execute().with_context(|| {
-            format!(
-                "failed to run `{}`{}",
-                self.path.display(),
-                if CompilerType::enabled().is_empty() {
-                    " (no compilers enabled)"
-                } else {
-                    ""
+        self.resolve_package()
+            .and_then(|run| {
+                #[cfg(unix)]
+                if let Some(path) = &run.profile {
+                    profile::start(path.clone());
                 }
-            )
+                let result = run.inner_execute();
+                // The error of the module takes precedence
+                #[cfg(unix)]
+                let result = result.and(profile::stop());
+                result
+            })
+            .with_context(|| {
+                format!(
+                    "failed to run `{}`{}",
+                    self.path.display(),
+                    if CompilerType::enabled().is_empty() {
+                        " (no compilers enabled)"
+                    } else {
+                        ""
+                    }
+                )
+            })
+    }
+
+    /// Downloads the package `FILE` refers to, if any, returning the
+    /// options to run its module.
+    fn resolve_package(&self) -> Result<Self> {
+        #[cfg(feature = "wasi-webc")]
+        if self.path.extension().map_or(false, |ext| ext == "webc") {
+            use wasmer_wasi::webc::WebcPackage;
+
+            let package = WebcPackage::open(&self.path)?;
+            let command = package.command(self.command.as_deref())?;
+            let mut run = self.clone();
+            run.command_name = self.command_name.clone().or(Some(command.name.clone()));
+            run.wasi.set_webc(package, command);
+            return Ok(run);
+        }
+        let source = match PackageSource::from_path(&self.path) {
+            Some(source) => source,
+            None => return Ok(self.clone()),
+        };
+        let package = source.fetch(self.command.as_deref(), self.sha256.as_deref())?;
+        Ok(Self {
+            path: package.module,
+            command_name: self.command_name.clone().or(Some(package.command)),
+            ..self.clone()
         })
     }
 
     fn inner_execute(&self) -> Result<()> {
+        #[cfg(feature = "compiler")]
+        if !self.compare_compilers.is_empty() {
+            let wasm = std::fs::read(&self.path)?;
+            return compare::compare(
+                &self.store,
+                &self.compare_compilers,
+                &wasm,
+                self.invoke.as_deref().unwrap_or_default(),
+                &self.args,
+            );
+        }
         let module = self.get_module()?;
-        // Do we want to invoke a function?
-        if let Some(ref invoke) = self.invoke {
-            let imports = imports! {};
+        self.limits.start_timer();
+        #[cfg(unix)]
+        let _signal_handlers = signals::install(self.signal_grace_period);
+        let mut linker = Linker::link(&module, &self.link)?;
+        #[cfg(feature = "wasi")]
+        let is_wasi = Wasi::get_versions(&module).map_or(false, |versions| !versions.is_empty());
+        #[cfg(not(feature = "wasi"))]
+        let is_wasi = false;
+        // Do we want to invoke a function? The WASI modules are
+        // instantiated with WASI first
+        if (self.invoke.is_some() || self.repl) && !is_wasi {
+            let imports = linker.imports_for(&module)?;
             let instance = Instance::new(&module, &imports)?;
-            let result = self.invoke_function(&instance, &invoke, &self.args)?;
-            println!(
-                "{}",
-                result
-                    .iter()
-                    .map(|val| val.to_string())
-                    .collect::<Vec<String>>()
-                    .join(" ")
-            );
-            return Ok(());
+            return self.call_entrypoint(&instance);
         }
         #[cfg(feature = "emscripten")]
         {
             use wasmer_emscripten::{
-                generate_emscripten_env, is_emscripten_module, run_emscripten_instance, EmEnv,
-                EmscriptenGlobals,
+                check_emscripten_imports, generate_emscripten_env, is_emscripten_module,
+                run_emscripten_instance, EmEnv, EmscriptenGlobals,
             };
             // TODO: refactor this
             if is_emscripten_module(&module) {
                 let mut emscripten_globals = EmscriptenGlobals::new(module.store(), &module)
                     .map_err(|e| anyhow!("{}", e))?;
                 let mut em_env = EmEnv::new(&emscripten_globals.data, Default::default());
+                em_env.set_networking(self.enable_emscripten_net);
                 let import_object =
                     generate_emscripten_env(module.store(), &mut emscripten_globals, &mut em_env);
+                check_emscripten_imports(&module, &import_object).map_err(|e| anyhow!("{}", e))?;
                 let mut instance = match Instance::new(&module, &import_object) {
                     Ok(instance) => instance,
                     Err(e) => {
@@ -133,18 +260,20 @@ impl Run {
                     }
                 };
 
-                run_emscripten_instance(
-                    &mut instance,
-                    &mut em_env,
-                    &mut emscripten_globals,
-                    if let Some(cn) = &self.command_name {
-                        cn
-                    } else {
-                        self.path.to_str().unwrap()
-                    },
-                    self.args.iter().map(|arg| arg.as_str()).collect(),
-                    None, //run.em_entrypoint.clone(),
-                )?;
+                sampled(|| {
+                    run_emscripten_instance(
+                        &mut instance,
+                        &mut em_env,
+                        &mut emscripten_globals,
+                        if let Some(cn) = &self.command_name {
+                            cn
+                        } else {
+                            self.path.to_str().unwrap()
+                        },
+                        self.args.iter().map(|arg| arg.as_str()).collect(),
+                        None, //run.em_entrypoint.clone(),
+                    )
+                })?;
                 return Ok(());
             }
         }
@@ -186,7 +315,13 @@ impl Run {
                         .unwrap_or_default();
                     return self
                         .wasi
-                        .execute(module, program_name, self.args.clone())
+                        .execute(
+                            module,
+                            program_name,
+                            self.args.clone(),
+                            linker,
+                            |instance| self.call_entrypoint(instance),
+                        )
                         .with_context(|| "WASI execution failed");
                 }
                 // not WASI
@@ -194,22 +329,56 @@ impl Run {
             }
         }
 
-        // Try to instantiate the wasm file, with no provided imports
-        let imports = imports! {};
+        // Try to instantiate the wasm file, with the imports of the linked
+        // modules only
+        let imports = linker.imports_for(&module)?;
         let instance = Instance::new(&module, &imports)?;
-        let start: Function = self.try_find_function(&instance, "_start", &[])?;
-        start.call(&[])?;
+        self.call_entrypoint(&instance)
+    }
 
+    /// Calls the function given with `--invoke` and prints what it
+    /// returns, or starts the REPL, or calls `_start`.
+    fn call_entrypoint(&self, instance: &Instance) -> Result<()> {
+        if let Some(ref invoke) = self.invoke {
+            let result = self.invoke_function(instance, invoke, &self.args)?;
+            println!("{}", format_values(&result));
+            return Ok(());
+        }
+        if self.repl {
+            return repl::run(instance);
+        }
+        #[cfg(feature = "wasi")]
+        if Wasi::has_wasi_imports(instance.module())
+            && wasmer_wasi::get_wasi_exec_model(instance.module())
+                == wasmer_wasi::WasiExecModel::Reactor
+        {
+            bail!("the module is a WASI reactor, with no `_start` function: call one of its exports with `--invoke`");
+        }
+        let start: Function = self.try_find_function(instance, "_start", &[])?;
+        sampled(|| start.call(&[]))?;
         Ok(())
     }
 
     fn get_module(&self) -> Result<Module> {
+        #[cfg(feature = "wasi-webc")]
+        let contents = match self.wasi.webc_atom() {
+            Some(atom) => atom.to_vec(),
+            None => std::fs::read(self.path.clone())?,
+        };
+        #[cfg(not(feature = "wasi-webc"))]
         let contents = std::fs::read(self.path.clone())?;
+        if is_component(&contents) {
+            bail!(
+                "`{}` is a WebAssembly component, but only core modules are supported. \
+                 Components targeting WASI preview2 can't be run yet",
+                self.path.display()
+            );
+        }
         #[cfg(feature = "dylib")]
         {
             if wasmer_engine_dylib::DylibArtifact::is_deserializable(&contents) {
                 let engine = wasmer_engine_dylib::Dylib::headless().engine();
-                let store = Store::new(&engine);
+                let store = self.limits.limit_store(Store::new(&engine));
                 let module = unsafe { Module::deserialize_from_file(&store, &self.path)? };
                 return Ok(module);
             }
@@ -218,12 +387,13 @@ impl Run {
         {
             if wasmer_engine_universal::UniversalArtifact::is_deserializable(&contents) {
                 let engine = wasmer_engine_universal::Universal::headless().engine();
-                let store = Store::new(&engine);
+                let store = self.limits.limit_store(Store::new(&engine));
                 let module = unsafe { Module::deserialize_from_file(&store, &self.path)? };
                 return Ok(module);
             }
         }
         let (store, engine_type, compiler_type) = self.store.get_store()?;
+        let store = self.limits.limit_store(store);
         #[cfg(feature = "cache")]
         let module_result: Result<Module> = if !self.disable_cache && contents.len() > 0x1000 {
             self.get_module_from_cache(&store, &contents, &engine_type, &compiler_type)
@@ -233,13 +403,15 @@ impl Run {
         #[cfg(not(feature = "cache"))]
         let module_result = Module::new(&store, &contents);
 
-        let mut module = module_result.with_context(|| {
-            format!(
-                "module instantiation failed (engine: {}, compiler: {})",
-                engine_type.to_string(),
-                compiler_type.to_string()
-            )
-        })?;
+        let mut module = module_result
+            .with_context(|| {
+                format!(
+                    "module instantiation failed (engine: {}, compiler: {})",
+                    engine_type.to_string(),
+                    compiler_type.to_string()
+                )
+            })
+            .map_err(hint_disabled_proposal)?;
         // We set the name outside the cache, to make sure we dont cache the name
         module.set_name(&self.path.file_name().unwrap_or_default().to_string_lossy());
 
@@ -357,6 +529,7 @@ impl Run {
                             name,
                             suggestion
                         ),
+                        e @ ExportError::IncompatibleSignature { .. } => anyhow!("{}", e),
                     }
                 }
             })?
@@ -375,7 +548,9 @@ impl Run {
         let provided_arguments = args.len();
         if required_arguments != provided_arguments {
             bail!(
-                "Function expected {} arguments, but received {}: \"{}\"",
+                "Function `{}` has signature {}, so it expects {} arguments, but received {}: \"{}\"",
+                invoke,
+                func_ty,
                 required_arguments,
                 provided_arguments,
                 self.args.join(" ")
@@ -384,34 +559,88 @@ impl Run {
         let invoke_args = args
             .iter()
             .zip(func_ty.params().iter())
-            .map(|(arg, param_type)| match param_type {
-                ValType::I32 => {
-                    Ok(Val::I32(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a i32", arg)
-                    })?))
-                }
-                ValType::I64 => {
-                    Ok(Val::I64(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a i64", arg)
-                    })?))
-                }
-                ValType::F32 => {
-                    Ok(Val::F32(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a f32", arg)
-                    })?))
-                }
-                ValType::F64 => {
-                    Ok(Val::F64(arg.parse().map_err(|_| {
-                        anyhow!("Can't convert `{}` into a f64", arg)
-                    })?))
-                }
-                _ => Err(anyhow!(
-                    "Don't know how to convert {} into {:?}",
-                    arg,
-                    param_type
-                )),
-            })
+            .map(|(arg, param_type)| parse_invoke_arg(arg, param_type))
             .collect::<Result<Vec<_>>>()?;
-        Ok(func.call(&invoke_args)?)
+        Ok(sampled(|| func.call(&invoke_args))?)
+    }
+}
+
+/// Runs `call`, sampling the guest code it runs if `--profile` is used.
+fn sampled<R>(call: impl FnOnce() -> R) -> R {
+    #[cfg(unix)]
+    return profile::sample(call);
+    #[cfg(not(unix))]
+    call()
+}
+
+/// Formats values as `value:type`, separated by spaces.
+fn format_values(values: &[Val]) -> String {
+    values
+        .iter()
+        .map(|val| format!("{}:{}", val.to_string(), val_type_name(&val.ty())))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Returns the name of a value type as written in the text format.
+fn val_type_name(ty: &ValType) -> &'static str {
+    match ty {
+        ValType::I32 => "i32",
+        ValType::I64 => "i64",
+        ValType::F32 => "f32",
+        ValType::F64 => "f64",
+        ValType::V128 => "v128",
+        ValType::ExternRef => "externref",
+        ValType::FuncRef => "funcref",
+    }
+}
+
+/// Parses an `--invoke` argument into a value of type `ty`.
+///
+/// Integers can be written in decimal or hexadecimal (`0x` prefix),
+/// optionally negated, and may use the full unsigned range of the
+/// type, so `0xffffffff` is a valid `i32` (`-1`).
+fn parse_invoke_arg(arg: &str, ty: &ValType) -> Result<Val> {
+    let value = match ty {
+        ValType::I32 => parse_integer(arg, i32::MIN as i128, u32::MAX as i128)
+            .map(|value| Val::I32(value as u32 as i32)),
+        ValType::I64 => parse_integer(arg, i64::MIN as i128, u64::MAX as i128)
+            .map(|value| Val::I64(value as u64 as i64)),
+        ValType::F32 => arg.parse().ok().map(Val::F32),
+        ValType::F64 => arg.parse().ok().map(Val::F64),
+        _ => bail!(
+            "Don't know how to convert `{}` into a {}",
+            arg,
+            val_type_name(ty)
+        ),
+    };
+    value.ok_or_else(|| anyhow!("Can't convert `{}` into a {}", arg, val_type_name(ty)))
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal integer, returning
+/// `None` if it's malformed or outside of `min..=max`.
+fn parse_integer(arg: &str, min: i128, max: i128) -> Option<i128> {
+    let (negative, digits) = match arg.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, arg),
+    };
+    if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let magnitude = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<u128>().ok()?,
+    };
+    let value = if negative {
+        -i128::try_from(magnitude).ok()?
+    } else {
+        i128::try_from(magnitude).ok()?
+    };
+    if value < min || value > max {
+        return None;
     }
+    Some(value)
 }
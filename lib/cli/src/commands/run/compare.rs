@@ -0,0 +1,109 @@
+//! Runs the same function with several compilers, reporting any
+//! divergence in the results, traps or metering points consumed.
+use super::{format_values, parse_invoke_arg};
+use crate::store::{CompilerType, StoreOptions};
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
+use wasmer::{imports, Instance, Module, Val};
+use wasmer_middlewares::metering::{get_remaining_points, MeteringPoints};
+use wasmer_middlewares::Metering;
+
+/// What happened when calling the function with one compiler.
+struct Outcome {
+    /// The results, or the trap message
+    result: Result<Box<[Val]>, String>,
+    /// The number of operators executed
+    gas: u64,
+}
+
+impl Outcome {
+    /// A representation of the outcome where floats are compared by
+    /// their bits, so that NaN payloads are checked too.
+    fn canonical(&self) -> (Result<Vec<String>, String>, u64) {
+        let result = match &self.result {
+            Ok(values) => Ok(values
+                .iter()
+                .map(|value| match value {
+                    Val::F32(f) => format!("f32:{:#x}", f.to_bits()),
+                    Val::F64(f) => format!("f64:{:#x}", f.to_bits()),
+                    value => format!("{:?}", value),
+                })
+                .collect()),
+            Err(trap) => Err(trap.clone()),
+        };
+        (result, self.gas)
+    }
+
+    fn describe(&self) -> String {
+        match &self.result {
+            Ok(values) => format!("{} ({} gas)", format_values(values), self.gas),
+            Err(trap) => format!("trap: {} ({} gas)", trap, self.gas),
+        }
+    }
+}
+
+/// Calls `invoke` with `args` in `wasm` once per compiler, and fails if
+/// the outcomes differ.
+pub fn compare(
+    store_options: &StoreOptions,
+    compilers: &[CompilerType],
+    wasm: &[u8],
+    invoke: &str,
+    args: &[String],
+) -> Result<()> {
+    let mut outcomes = vec![];
+    for compiler in compilers {
+        let outcome = run_with(store_options, compiler, wasm, invoke, args)
+            .with_context(|| format!("failed to run with {}", compiler.to_string()))?;
+        println!("{:>10}: {}", compiler.to_string(), outcome.describe());
+        outcomes.push(outcome);
+    }
+
+    let reference = outcomes[0].canonical();
+    if outcomes
+        .iter()
+        .any(|outcome| outcome.canonical() != reference)
+    {
+        bail!("the compilers diverged");
+    }
+    eprintln!("All {} compilers agree.", compilers.len());
+    Ok(())
+}
+
+fn run_with(
+    store_options: &StoreOptions,
+    compiler: &CompilerType,
+    wasm: &[u8],
+    invoke: &str,
+    args: &[String],
+) -> Result<Outcome> {
+    let metering = Arc::new(Metering::new(u64::MAX, |_: &Operator| 1));
+    let store = store_options.get_store_with_compiler(compiler, vec![metering])?;
+    let module = Module::new(&store, wasm)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    let func = instance.exports.get_function(invoke)?;
+    let func_ty = func.ty();
+    if func_ty.params().len() != args.len() {
+        bail!(
+            "Function `{}` has signature {}, so it expects {} arguments, but received {}",
+            invoke,
+            func_ty,
+            func_ty.params().len(),
+            args.len()
+        );
+    }
+    let args = args
+        .iter()
+        .zip(func_ty.params().iter())
+        .map(|(arg, param_type)| parse_invoke_arg(arg, param_type))
+        .collect::<Result<Vec<_>>>()?;
+
+    let result = func.call(&args).map_err(|trap| trap.message());
+    let gas = match get_remaining_points(&instance) {
+        MeteringPoints::Remaining(remaining) => u64::MAX - remaining,
+        MeteringPoints::Exhausted => u64::MAX,
+    };
+    Ok(Outcome { result, gas })
+}
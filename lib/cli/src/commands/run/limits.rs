@@ -0,0 +1,161 @@
+//! Resource limits for untrusted modules run from the CLI.
+use crate::utils::{parse_bytes, parse_duration};
+use colored::*;
+use loupe::MemoryUsage;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use structopt::StructOpt;
+use wasmer::vm::{
+    self, MemoryError, MemoryStyle, TableStyle, VMMemoryDefinition, VMTableDefinition,
+};
+use wasmer::{BaseTunables, MemoryType, Pages, Store, TableType, Tunables, WASM_PAGE_SIZE};
+
+#[derive(Debug, StructOpt, Clone)]
+/// Resource limits options
+pub struct ResourceLimits {
+    /// Maximum size of each linear memory, eg. `64MiB`
+    #[structopt(long = "max-memory", parse(try_from_str = parse_bytes))]
+    max_memory: Option<u64>,
+
+    /// Maximum number of elements of each table
+    #[structopt(long = "max-table-elements")]
+    max_table_elements: Option<u32>,
+
+    /// Terminate the execution after the given time, eg. `500ms` or `30s`
+    #[structopt(long = "timeout", parse(try_from_str = parse_duration))]
+    timeout: Option<Duration>,
+}
+
+impl ResourceLimits {
+    /// Returns a store using the same engine as `store`, enforcing the
+    /// memory and table limits, if any.
+    pub fn limit_store(&self, store: Store) -> Store {
+        if self.max_memory.is_none() && self.max_table_elements.is_none() {
+            return store;
+        }
+        let engine = store.engine().clone();
+        let tunables = LimitingTunables {
+            max_memory: self
+                .max_memory
+                .map(|bytes| Pages((bytes / WASM_PAGE_SIZE as u64).min(u32::MAX as u64) as u32)),
+            max_table_elements: self.max_table_elements,
+            base: BaseTunables::for_target(engine.target()),
+        };
+        Store::new_with_tunables(&*engine, tunables)
+    }
+
+    /// Starts a timer terminating the process once the timeout expires.
+    ///
+    /// Running WebAssembly code can't be interrupted from another thread,
+    /// so the whole process exits with the conventional `timeout(1)`
+    /// status code instead.
+    pub fn start_timer(&self) {
+        if let Some(timeout) = self.timeout {
+            thread::spawn(move || {
+                thread::sleep(timeout);
+                eprintln!(
+                    "{}: the execution exceeded the timeout of {:?}",
+                    "error".red().bold(),
+                    timeout
+                );
+                std::process::exit(124);
+            });
+        }
+    }
+}
+
+/// Tunables capping the size of memories and tables, delegating
+/// everything else to [`BaseTunables`].
+#[derive(MemoryUsage)]
+struct LimitingTunables {
+    max_memory: Option<Pages>,
+    max_table_elements: Option<u32>,
+    base: BaseTunables,
+}
+
+impl LimitingTunables {
+    /// Sets the maximum of the memory to the limit if it's missing or
+    /// higher, failing if the minimum already exceeds the limit.
+    fn limit_memory(&self, ty: &MemoryType) -> Result<MemoryType, MemoryError> {
+        let limit = match self.max_memory {
+            Some(limit) => limit,
+            None => return Ok(*ty),
+        };
+        if ty.minimum > limit {
+            return Err(MemoryError::Generic(format!(
+                "the memory requires at least {} bytes, exceeding the limit of {} bytes set by `--max-memory`",
+                ty.minimum.bytes().0,
+                limit.bytes().0
+            )));
+        }
+        let mut limited = *ty;
+        limited.maximum = Some(ty.maximum.map_or(limit, |maximum| maximum.min(limit)));
+        Ok(limited)
+    }
+
+    /// Same as `limit_memory`, for tables.
+    fn limit_table(&self, ty: &TableType) -> Result<TableType, String> {
+        let limit = match self.max_table_elements {
+            Some(limit) => limit,
+            None => return Ok(*ty),
+        };
+        if ty.minimum > limit {
+            return Err(format!(
+                "the table requires at least {} elements, exceeding the limit of {} set by `--max-table-elements`",
+                ty.minimum, limit
+            ));
+        }
+        let mut limited = *ty;
+        limited.maximum = Some(ty.maximum.map_or(limit, |maximum| maximum.min(limit)));
+        Ok(limited)
+    }
+}
+
+impl Tunables for LimitingTunables {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        let limited = self.limit_memory(memory).unwrap_or(*memory);
+        self.base.memory_style(&limited)
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(table)
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        self.base.create_host_memory(&self.limit_memory(ty)?, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+        self.base
+            .create_vm_memory(&self.limit_memory(ty)?, style, vm_definition_location)
+    }
+
+    fn create_host_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base.create_host_table(&self.limit_table(ty)?, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<Arc<dyn vm::Table>, String> {
+        self.base
+            .create_vm_table(&self.limit_table(ty)?, style, vm_definition_location)
+    }
+}
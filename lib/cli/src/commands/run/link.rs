@@ -0,0 +1,64 @@
+//! Links the modules given with `--link`, so their exports satisfy the
+//! imports of the module being run.
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use wasmer::{Exports, ImportObject, Instance, Memory, Module};
+
+/// The exports of the linked modules, by namespace.
+#[derive(Default)]
+pub struct Linker {
+    namespaces: HashMap<String, Exports>,
+}
+
+impl Linker {
+    /// Instantiates the modules at `paths` in order, in the store of
+    /// `module`.
+    ///
+    /// The exports of each module are available under its file name
+    /// without extension (`helper` for `helper.wasm`) to the modules
+    /// instantiated after it.
+    pub fn link(module: &Module, paths: &[PathBuf]) -> Result<Self> {
+        let mut linker = Self::default();
+        for path in paths {
+            let namespace = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let instance = (|| -> Result<Instance> {
+                let contents = std::fs::read(path)?;
+                let linked = Module::new(module.store(), &contents)?;
+                Ok(Instance::new(&linked, &linker.imports_for(&linked)?)?)
+            })()
+            .with_context(|| format!("failed to link `{}`", path.display()))?;
+
+            let exports = linker.namespaces.entry(namespace).or_default();
+            for (name, export) in instance.exports.iter() {
+                exports.insert(name.clone(), export.clone());
+            }
+        }
+        Ok(linker)
+    }
+
+    /// Returns the imports to instantiate `module` with.
+    ///
+    /// A memory imported by several modules is shared between them: the
+    /// first module importing it creates it, unless it's exported by a
+    /// linked module.
+    pub fn imports_for(&mut self, module: &Module) -> Result<ImportObject> {
+        for import in module.imports().memories() {
+            let exports = self
+                .namespaces
+                .entry(import.module().to_string())
+                .or_default();
+            if !exports.contains(import.name()) {
+                exports.insert(import.name(), Memory::new(module.store(), *import.ty())?);
+            }
+        }
+        let mut import_object = ImportObject::new();
+        for (namespace, exports) in &self.namespaces {
+            import_object.register(namespace, exports.clone());
+        }
+        Ok(import_object)
+    }
+}
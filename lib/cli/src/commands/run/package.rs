@@ -0,0 +1,398 @@
+//! Fetches packages from the registry or from a URL so they can be run
+//! directly, eg. `wasmer run namespace/package@1.2.3`.
+use crate::common::{get_cache_dir, PACKAGES_CACHE_DIR};
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// The registry used when `WASMER_REGISTRY` is not set
+const DEFAULT_REGISTRY: &str = "https://registry.wapm.io/graphql";
+
+/// Where a package comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PackageSource {
+    /// A package published in the registry, eg. `namespace/package@1.2.3`.
+    /// The latest version is used if no version is specified.
+    Registry {
+        name: String,
+        version: Option<String>,
+    },
+    /// A `.wasm` file or a `.tar.gz` package served over HTTP(S)
+    Url(String),
+}
+
+/// A package downloaded and unpacked in the cache, ready to run.
+#[derive(Debug)]
+pub struct ResolvedPackage {
+    /// The WebAssembly module to run
+    pub module: PathBuf,
+    /// The name of the command being run, passed as program name
+    pub command: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default, rename = "module")]
+    modules: Vec<ManifestModule>,
+    #[serde(default, rename = "command")]
+    commands: Vec<ManifestCommand>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestModule {
+    name: String,
+    source: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestCommand {
+    name: String,
+    module: String,
+}
+
+impl PackageSource {
+    /// Interprets the `FILE` argument of `wasmer run` as a package, unless
+    /// it's a file that exists locally.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        if path.exists() {
+            return None;
+        }
+        let path_str = path.to_str()?;
+        if path_str.starts_with("https://") || path_str.starts_with("http://") {
+            return Some(Self::Url(path_str.to_string()));
+        }
+        // A missing local module, rather than a package
+        if matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("wasm") | Some("wat")
+        ) {
+            return None;
+        }
+        let path = path_str;
+        let (name, version) = match path.find('@') {
+            Some(index) => (&path[..index], Some(path[index + 1..].to_string())),
+            None => (path, None),
+        };
+        let mut parts = name.split('/');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(namespace), Some(package), None)
+                if is_valid_name(namespace) && is_valid_name(package) =>
+            {
+                Some(Self::Registry {
+                    name: name.to_string(),
+                    version,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Downloads the package (unless it's already cached) and resolves the
+    /// module to run. `command` selects one of the commands declared by
+    /// the package, and is required if it declares more than one.
+    ///
+    /// The download is checked against `sha256`, the hex encoded SHA-256
+    /// of the module or archive. It is required for URLs, which are not
+    /// vouched for by the registry.
+    pub fn fetch(&self, command: Option<&str>, sha256: Option<&str>) -> Result<ResolvedPackage> {
+        let (url, key) = match self {
+            Self::Url(url) => {
+                if sha256.is_none() {
+                    bail!("the SHA-256 of packages downloaded from a URL must be given with `--sha256`");
+                }
+                (url.clone(), url_cache_key(url))
+            }
+            Self::Registry { name, version } => {
+                let (url, version) = query_registry(name, version.as_deref())?;
+                (url, format!("{}@{}", name.replace('/', "-"), version))
+            }
+        };
+
+        let package_dir = get_cache_dir().join(PACKAGES_CACHE_DIR).join(&key);
+        if !package_dir.exists() {
+            download(&url, sha256, &package_dir)
+                .with_context(|| format!("failed to download `{}`", url))?;
+        }
+
+        let manifest_path = package_dir.join("wapm.toml");
+        if !manifest_path.exists() {
+            // A single module, downloaded directly
+            let module = package_dir.join("module.wasm");
+            let command = url
+                .rsplit('/')
+                .next()
+                .and_then(|file| file.strip_suffix(".wasm"))
+                .unwrap_or("module")
+                .to_string();
+            return Ok(ResolvedPackage { module, command });
+        }
+
+        let manifest: Manifest = toml::from_str(&fs::read_to_string(&manifest_path)?)
+            .context("the package manifest `wapm.toml` is invalid")?;
+        let resolved = manifest.resolve(command)?;
+        Ok(ResolvedPackage {
+            module: package_dir.join(resolved.0),
+            command: resolved.1,
+        })
+    }
+}
+
+impl Manifest {
+    /// Returns the module source and name of the selected command.
+    fn resolve(&self, command: Option<&str>) -> Result<(PathBuf, String)> {
+        let command = match (command, self.commands.as_slice()) {
+            (Some(name), commands) => commands
+                .iter()
+                .find(|command| command.name == name)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "the package has no command `{}`. Available commands: {}",
+                        name,
+                        self.command_names()
+                    )
+                })?,
+            (None, [command]) => command,
+            (None, []) => bail!("the package doesn't declare any command"),
+            (None, _) => bail!(
+                "the package declares several commands, select one with `--command`: {}",
+                self.command_names()
+            ),
+        };
+        let module = self
+            .modules
+            .iter()
+            .find(|module| module.name == command.module)
+            .ok_or_else(|| {
+                anyhow!(
+                    "the command `{}` refers to the undeclared module `{}`",
+                    command.name,
+                    command.module
+                )
+            })?;
+        // The source is joined to the package directory, it must not
+        // escape it
+        if !module
+            .source
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            bail!(
+                "the module `{}` has an invalid source `{}`",
+                module.name,
+                module.source.display()
+            );
+        }
+        Ok((module.source.clone(), command.name.clone()))
+    }
+
+    fn command_names(&self) -> String {
+        self.commands
+            .iter()
+            .map(|command| format!("`{}`", command.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Turns a URL into a directory name, eg. `example.com-app.wasm`.
+fn url_cache_key(url: &str) -> String {
+    let url = url.splitn(2, "://").last().unwrap_or(url);
+    url.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Asks the registry for the download URL of a package version, returning
+/// it along with the resolved version.
+fn query_registry(name: &str, version: Option<&str>) -> Result<(String, String)> {
+    let registry = env::var("WASMER_REGISTRY").unwrap_or_else(|_| DEFAULT_REGISTRY.to_string());
+    let query = serde_json::json!({
+        "query": "query($name: String!, $version: String) { \
+            getPackageVersion(name: $name, version: $version) { \
+                version distribution { downloadUrl } } }",
+        "variables": { "name": name, "version": version },
+    });
+    let output = Command::new("curl")
+        .args(&[
+            "-sSfL",
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+        ])
+        .arg("--data")
+        .arg(query.to_string())
+        .arg(&registry)
+        .output()
+        .context("failed to run `curl`")?;
+    if !output.status.success() {
+        bail!(
+            "failed to query the registry `{}`: {}",
+            registry,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let response: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .context("the registry returned an invalid response")?;
+    let package = &response["data"]["getPackageVersion"];
+    match (
+        package["distribution"]["downloadUrl"].as_str(),
+        package["version"].as_str(),
+    ) {
+        (Some(url), Some(version)) => Ok((url.to_string(), version.to_string())),
+        _ => bail!(
+            "package `{}`{} was not found in the registry",
+            name,
+            version
+                .map(|v| format!(" version {}", v))
+                .unwrap_or_default()
+        ),
+    }
+}
+
+/// Downloads `url` and unpacks it into `package_dir`, checking it against
+/// the `sha256` if any.
+///
+/// Everything happens in a temporary directory which is only moved into
+/// place once the download is verified, so an interrupted or corrupted
+/// download is never cached.
+fn download(url: &str, sha256: Option<&str>, package_dir: &Path) -> Result<()> {
+    let parent = package_dir.parent().unwrap();
+    fs::create_dir_all(parent)?;
+    let staging = tempfile::tempdir_in(parent)?;
+    let file = staging.path().join("download");
+
+    let status = Command::new("curl")
+        .args(&["-sSfL", "-o"])
+        .arg(&file)
+        .arg(url)
+        .status()
+        .context("failed to run `curl`")?;
+    if !status.success() {
+        bail!("`curl` exited with {}", status);
+    }
+
+    let contents = fs::read(&file)?;
+    if let Some(expected) = sha256 {
+        let actual = hex::encode(Sha256::digest(&contents));
+        if !actual.eq_ignore_ascii_case(expected) {
+            bail!(
+                "the SHA-256 of the download is {}, expected {}",
+                actual,
+                expected
+            );
+        }
+    }
+    if contents.starts_with(b"\0asm") {
+        fs::rename(&file, staging.path().join("module.wasm"))?;
+    } else if contents.starts_with(&[0x1f, 0x8b]) {
+        // Entries escaping the staging directory are skipped by `unpack`
+        tar::Archive::new(GzDecoder::new(contents.as_slice()))
+            .unpack(staging.path())
+            .context("the package archive is corrupted")?;
+        fs::remove_file(&file)?;
+        if !staging.path().join("wapm.toml").exists() {
+            bail!("the package archive has no `wapm.toml` manifest");
+        }
+    } else {
+        bail!("the download is neither a WebAssembly module nor a package archive");
+    }
+
+    fs::rename(staging.into_path(), package_dir)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_source() {
+        assert_eq!(
+            PackageSource::from_path(Path::new("syrusakbary/qjs@0.0.3")),
+            Some(PackageSource::Registry {
+                name: "syrusakbary/qjs".to_string(),
+                version: Some("0.0.3".to_string()),
+            })
+        );
+        assert_eq!(
+            PackageSource::from_path(Path::new("syrusakbary/qjs")),
+            Some(PackageSource::Registry {
+                name: "syrusakbary/qjs".to_string(),
+                version: None,
+            })
+        );
+        assert_eq!(
+            PackageSource::from_path(Path::new("https://example.com/app.wasm")),
+            Some(PackageSource::Url(
+                "https://example.com/app.wasm".to_string()
+            ))
+        );
+        assert_eq!(PackageSource::from_path(Path::new("app.wasm")), None);
+        assert_eq!(PackageSource::from_path(Path::new("a/b/c.wasm")), None);
+    }
+
+    #[test]
+    fn test_resolve_command() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+            [[module]]
+            name = "qjs"
+            source = "qjs.wasm"
+
+            [[command]]
+            name = "qjs"
+            module = "qjs"
+
+            [[command]]
+            name = "repl"
+            module = "qjs"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest.resolve(Some("repl")).unwrap(),
+            (PathBuf::from("qjs.wasm"), "repl".to_string())
+        );
+        assert!(manifest.resolve(None).is_err());
+        assert!(manifest.resolve(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_escaping_source() {
+        for source in &["../qjs.wasm", "/tmp/qjs.wasm", "bin/../../qjs.wasm"] {
+            let manifest: Manifest = toml::from_str(&format!(
+                r#"
+                [[module]]
+                name = "qjs"
+                source = "{}"
+
+                [[command]]
+                name = "qjs"
+                module = "qjs"
+                "#,
+                source
+            ))
+            .unwrap();
+            assert!(manifest.resolve(None).is_err(), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! A sampling profiler for the guest code, writing a flamegraph.
+//!
+//! While the guest code runs (see [`sample`]), `SIGPROF` is delivered at
+//! a fixed rate of CPU time, and the signal handler records the program
+//! counters of the interrupted stack into a small ring of slots. Only
+//! async-signal-safe operations are allowed in the handler, so it walks
+//! the frame pointers itself, within the bounds of the stack, rather
+//! than using an unwinder. A thread empties the ring into the counts of
+//! each stack, and once the profiler stops, the program counters
+//! belonging to WebAssembly functions are symbolized, using the names
+//! from the `name` section when available, and folded into a flamegraph.
+use crate::warning;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufWriter;
+use std::mem;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use wasmer_engine::FRAME_INFO;
+
+/// How many samples are taken per second of CPU time
+const FREQUENCY: libc::suseconds_t = 99;
+/// The maximum number of frames recorded per sample
+const MAX_DEPTH: usize = 128;
+/// The number of sample slots, emptied every `DRAIN_INTERVAL`
+const RING_SIZE: usize = 64;
+/// How often the ring of samples is emptied
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+/// The size of a sample slot: its depth (0 if it's free) followed by its
+/// frames
+const SLOT_SIZE: usize = MAX_DEPTH + 1;
+
+/// The sample slots, allocated before the profiler starts since the
+/// signal handler can't allocate
+static SAMPLES: AtomicPtr<AtomicUsize> = AtomicPtr::new(ptr::null_mut());
+/// The index of the next sample slot
+static NEXT_SAMPLE: AtomicUsize = AtomicUsize::new(0);
+/// The number of samples dropped because the ring was full
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+/// Whether the guest code is being sampled
+static SAMPLING: AtomicBool = AtomicBool::new(false);
+/// The thread running the sampled guest code
+static SAMPLED_THREAD: AtomicUsize = AtomicUsize::new(0);
+/// The bounds of the stack of the sampled code, the frame pointers being
+/// followed only within them
+static STACK_START: AtomicUsize = AtomicUsize::new(0);
+static STACK_END: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static::lazy_static! {
+    /// The running profiling session, if any
+    static ref SESSION: Mutex<Option<Session>> = Mutex::new(None);
+}
+
+struct Session {
+    output: PathBuf,
+    samples: Arc<Vec<AtomicUsize>>,
+    stop: Arc<AtomicBool>,
+    /// The thread emptying the ring, returning how many times each stack
+    /// of program counters (innermost first) was sampled
+    drainer: JoinHandle<HashMap<Vec<usize>, usize>>,
+}
+
+/// The program counter, stack pointer and frame pointer of the
+/// interrupted code, if the frame pointers can be followed on this
+/// platform.
+unsafe fn registers(context: *mut libc::c_void) -> (usize, Option<(usize, usize)>) {
+    cfg_if::cfg_if! {
+        if #[cfg(all(target_os = "linux", target_arch = "x86_64"))] {
+            let cx = &*(context as *const libc::ucontext_t);
+            let gregs = &cx.uc_mcontext.gregs;
+            (
+                gregs[libc::REG_RIP as usize] as usize,
+                Some((
+                    gregs[libc::REG_RSP as usize] as usize,
+                    gregs[libc::REG_RBP as usize] as usize,
+                )),
+            )
+        } else if #[cfg(all(target_os = "linux", target_arch = "aarch64"))] {
+            let cx = &*(context as *const libc::ucontext_t);
+            let mcontext = &cx.uc_mcontext;
+            (
+                mcontext.pc as usize,
+                Some((mcontext.sp as usize, mcontext.regs[29] as usize)),
+            )
+        } else if #[cfg(all(target_os = "macos", target_arch = "x86_64"))] {
+            let cx = &*(context as *const libc::ucontext_t);
+            let state = &(*cx.uc_mcontext).__ss;
+            (
+                state.__rip as usize,
+                Some((state.__rsp as usize, state.__rbp as usize)),
+            )
+        } else if #[cfg(all(target_os = "macos", target_arch = "aarch64"))] {
+            // Not in `libc` yet, see `wasmer_vm`'s trap handlers
+            #[allow(non_camel_case_types)]
+            struct __darwin_arm_thread_state64 {
+                __x: [u64; 29],
+                __fp: u64,
+                __lr: u64,
+                __sp: u64,
+                __pc: u64,
+                __cpsr: u32,
+                __pad: u32,
+            }
+
+            let cx = &*(context as *const libc::ucontext_t);
+            let state = &(*cx.uc_mcontext).__ss as *const _;
+            let state = &*(state as *const __darwin_arm_thread_state64);
+            (
+                state.__pc as usize,
+                Some((state.__sp as usize, state.__fp as usize)),
+            )
+        } else {
+            let _ = context;
+            (0, None)
+        }
+    }
+}
+
+extern "C" fn on_sigprof(
+    _signal: libc::c_int,
+    _siginfo: *mut libc::siginfo_t,
+    context: *mut libc::c_void,
+) {
+    // The timer counts the CPU time of the whole process, so the signal
+    // may interrupt any thread
+    let samples = SAMPLES.load(Ordering::SeqCst);
+    if samples.is_null()
+        || !SAMPLING.load(Ordering::SeqCst)
+        || unsafe { libc::pthread_self() } as usize != SAMPLED_THREAD.load(Ordering::SeqCst)
+    {
+        return;
+    }
+    let index = NEXT_SAMPLE.fetch_add(1, Ordering::SeqCst) % RING_SIZE;
+    let slot = unsafe { std::slice::from_raw_parts(samples.add(index * SLOT_SIZE), SLOT_SIZE) };
+    if slot[0].load(Ordering::Acquire) != 0 {
+        DROPPED.fetch_add(1, Ordering::SeqCst);
+        return;
+    }
+
+    let (pc, stack) = unsafe { registers(context) };
+    slot[1].store(pc, Ordering::Relaxed);
+    let mut depth = 1;
+    if let Some((sp, mut fp)) = stack {
+        let start = STACK_START.load(Ordering::SeqCst);
+        let end = STACK_END.load(Ordering::SeqCst);
+        // Each frame holds the frame pointer of its caller followed by
+        // the return address. They are only read if they are within the
+        // stack, above the interrupted code and the previous frame.
+        let mut low = sp;
+        while depth < MAX_DEPTH
+            && low >= start
+            && fp >= low
+            && fp % mem::align_of::<usize>() == 0
+            && fp.saturating_add(2 * mem::size_of::<usize>()) <= end
+        {
+            let frame = fp as *const usize;
+            let (caller_fp, return_address) = unsafe { (*frame, *frame.add(1)) };
+            depth += 1;
+            slot[depth].store(return_address, Ordering::Relaxed);
+            low = fp + 2 * mem::size_of::<usize>();
+            fp = caller_fp;
+        }
+    }
+    slot[0].store(depth, Ordering::Release);
+}
+
+fn set_timer(interval: libc::suseconds_t) {
+    let interval = libc::timeval {
+        tv_sec: 0,
+        tv_usec: interval,
+    };
+    let timer = libc::itimerval {
+        it_interval: interval,
+        it_value: interval,
+    };
+    unsafe {
+        libc::setitimer(libc::ITIMER_PROF, &timer, ptr::null_mut());
+    }
+}
+
+/// Moves the samples out of the ring into `stacks`.
+fn drain(samples: &[AtomicUsize], stacks: &mut HashMap<Vec<usize>, usize>) {
+    for slot in samples.chunks(SLOT_SIZE) {
+        let depth = slot[0].load(Ordering::Acquire);
+        if depth == 0 {
+            continue;
+        }
+        let stack = slot[1..=depth]
+            .iter()
+            .map(|pc| pc.load(Ordering::Relaxed))
+            .collect();
+        slot[0].store(0, Ordering::Release);
+        *stacks.entry(stack).or_insert(0) += 1;
+    }
+}
+
+/// The bounds of the stack of the current thread, if they are known.
+fn current_stack() -> Option<(usize, usize)> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "linux")] {
+            unsafe {
+                let mut attr: libc::pthread_attr_t = mem::zeroed();
+                if libc::pthread_getattr_np(libc::pthread_self(), &mut attr) != 0 {
+                    return None;
+                }
+                let mut addr = ptr::null_mut();
+                let mut size = 0;
+                let result = libc::pthread_attr_getstack(&attr, &mut addr, &mut size);
+                libc::pthread_attr_destroy(&mut attr);
+                if result != 0 {
+                    return None;
+                }
+                Some((addr as usize, addr as usize + size))
+            }
+        } else if #[cfg(target_os = "macos")] {
+            unsafe {
+                let thread = libc::pthread_self();
+                let end = libc::pthread_get_stackaddr_np(thread) as usize;
+                Some((end - libc::pthread_get_stacksize_np(thread), end))
+            }
+        } else {
+            None
+        }
+    }
+}
+
+/// Starts profiling, to write the flamegraph to `output` once [`stop`]
+/// is called. The guest code is sampled within [`sample`] only.
+pub fn start(output: PathBuf) {
+    let samples = Arc::new(
+        (0..RING_SIZE * SLOT_SIZE)
+            .map(|_| AtomicUsize::new(0))
+            .collect::<Vec<_>>(),
+    );
+    NEXT_SAMPLE.store(0, Ordering::SeqCst);
+    DROPPED.store(0, Ordering::SeqCst);
+    SAMPLES.store(samples.as_ptr() as *mut AtomicUsize, Ordering::SeqCst);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let drainer = {
+        let samples = samples.clone();
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let mut stacks = HashMap::new();
+            while !stop.load(Ordering::SeqCst) {
+                drain(&samples, &mut stacks);
+                thread::sleep(DRAIN_INTERVAL);
+            }
+            drain(&samples, &mut stacks);
+            stacks
+        })
+    };
+    *SESSION.lock().unwrap() = Some(Session {
+        output,
+        samples,
+        stop,
+        drainer,
+    });
+
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        action.sa_sigaction = on_sigprof as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGPROF, &action, ptr::null_mut());
+    }
+}
+
+/// Runs `call`, sampling the guest code it runs if the profiler was
+/// started.
+pub fn sample<R>(call: impl FnOnce() -> R) -> R {
+    if SAMPLES.load(Ordering::SeqCst).is_null() || SAMPLING.load(Ordering::SeqCst) {
+        return call();
+    }
+    let (start, end) = current_stack().unwrap_or((usize::MAX, 0));
+    STACK_START.store(start, Ordering::SeqCst);
+    STACK_END.store(end, Ordering::SeqCst);
+    SAMPLED_THREAD.store(unsafe { libc::pthread_self() } as usize, Ordering::SeqCst);
+    SAMPLING.store(true, Ordering::SeqCst);
+    set_timer(1_000_000 / FREQUENCY);
+    let result = call();
+    set_timer(0);
+    SAMPLING.store(false, Ordering::SeqCst);
+    result
+}
+
+/// Stops profiling, and writes the flamegraph. Does nothing if the
+/// profiler isn't running.
+pub fn stop() -> Result<()> {
+    let session = match SESSION.lock().unwrap().take() {
+        Some(session) => session,
+        None => return Ok(()),
+    };
+    set_timer(0);
+    SAMPLES.store(ptr::null_mut(), Ordering::SeqCst);
+    session.stop.store(true, Ordering::SeqCst);
+    let stacks = session.drainer.join().unwrap();
+    drop(session.samples);
+
+    let taken = stacks.values().sum::<usize>();
+    let dropped = DROPPED.load(Ordering::SeqCst);
+    if dropped > 0 {
+        warning!(
+            "{} samples were dropped, the profile is incomplete",
+            dropped
+        );
+    }
+    let stacks = fold_stacks(&stacks);
+    if stacks.is_empty() {
+        warning!("no WebAssembly code was sampled, the profile is empty");
+    }
+
+    let file = File::create(&session.output)
+        .with_context(|| format!("failed to create `{}`", session.output.display()))?;
+    let lines = stacks
+        .iter()
+        .map(|(stack, count)| format!("{} {}", stack, count))
+        .collect::<Vec<_>>();
+    let mut options = inferno::flamegraph::Options::default();
+    options.title = "wasmer run".to_string();
+    inferno::flamegraph::from_lines(
+        &mut options,
+        lines.iter().map(String::as_str),
+        BufWriter::new(file),
+    )
+    .context("failed to write the flamegraph")?;
+    eprintln!(
+        "Wrote the profile of {} samples to `{}`.",
+        taken,
+        session.output.display()
+    );
+    Ok(())
+}
+
+/// Symbolizes the sampled stacks of program counters, and counts how
+/// many times each stack of WebAssembly functions (outermost first,
+/// `;`-separated) was sampled.
+fn fold_stacks(samples: &HashMap<Vec<usize>, usize>) -> BTreeMap<String, usize> {
+    let frame_info = FRAME_INFO.read().unwrap();
+    let mut stacks = BTreeMap::new();
+    for (pcs, count) in samples {
+        let names = pcs
+            .iter()
+            .rev()
+            .filter_map(|&pc| frame_info.lookup_frame_info(pc))
+            .map(|frame| match frame.function_name() {
+                Some(name) => format!("{}!{}", frame.module_name(), name),
+                None => format!(
+                    "{}!<wasm function {}>",
+                    frame.module_name(),
+                    frame.func_index()
+                ),
+            })
+            .collect::<Vec<_>>();
+        // Samples without guest code, eg. in the host before calling it,
+        // are skipped. Time spent in the host on behalf of the guest, eg.
+        // in syscalls, is attributed to the calling function.
+        if names.is_empty() {
+            continue;
+        }
+        *stacks.entry(names.join(";")).or_insert(0) += count;
+    }
+    stacks
+}
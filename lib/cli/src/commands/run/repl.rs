@@ -0,0 +1,167 @@
+//! An interactive prompt to call the exports of an instance.
+use super::{format_values, parse_invoke_arg};
+use anyhow::{anyhow, bail, Result};
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+use wasmer::{Extern, Instance};
+
+const HELP: &str = "\
+Commands:
+  <function> [ARGS...]        Call an exported function
+  :time <function> [ARGS...]  Call an exported function and report how long it took
+  :exports                    List the exports of the module
+  :global <name>              Print the value of an exported global
+  :memory <name> <offset> [LEN]
+                              Dump LEN bytes (default 64) of an exported memory
+  :help                       Print this message
+  :quit                       Exit the REPL";
+
+/// Runs the REPL over stdin until it's closed or `:quit` is entered.
+pub fn run(instance: &Instance) -> Result<()> {
+    eprintln!("Type `:help` for the list of commands.");
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        eprint!("> ");
+        io::stderr().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (*command, args),
+            None => continue,
+        };
+        let result = match command {
+            ":quit" | ":q" => break,
+            ":help" | ":h" => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            ":exports" => {
+                print_exports(instance);
+                Ok(())
+            }
+            ":global" => print_global(instance, args),
+            ":memory" => print_memory(instance, args),
+            ":time" => match args.split_first() {
+                Some((name, args)) => call(instance, name, args, true),
+                None => Err(anyhow!("Usage: :time <function> [ARGS...]")),
+            },
+            name if name.starts_with(':') => Err(anyhow!(
+                "Unknown command `{}`. Type `:help` for help.",
+                name
+            )),
+            name => call(instance, name, args, false),
+        };
+        if let Err(error) = result {
+            eprintln!("error: {}", error);
+        }
+    }
+    Ok(())
+}
+
+fn call(instance: &Instance, name: &str, args: &[&str], timed: bool) -> Result<()> {
+    let func = instance.exports.get_function(name)?;
+    let func_ty = func.ty();
+    if func_ty.params().len() != args.len() {
+        bail!(
+            "Function `{}` has signature {}, so it expects {} arguments, but received {}",
+            name,
+            func_ty,
+            func_ty.params().len(),
+            args.len()
+        );
+    }
+    let args = args
+        .iter()
+        .zip(func_ty.params().iter())
+        .map(|(arg, param_type)| parse_invoke_arg(arg, param_type))
+        .collect::<Result<Vec<_>>>()?;
+
+    let start = Instant::now();
+    let result = func.call(&args)?;
+    let elapsed = start.elapsed();
+
+    println!("{}", format_values(&result));
+    if timed {
+        println!("took {:?}", elapsed);
+    }
+    Ok(())
+}
+
+fn print_exports(instance: &Instance) {
+    for (name, export) in instance.exports.iter() {
+        match export {
+            Extern::Function(func) => println!("  func {}: {}", name, func.ty()),
+            Extern::Global(global) => println!("  global {}: {}", name, global.ty()),
+            Extern::Memory(memory) => println!("  memory {}: {}", name, memory.ty()),
+            Extern::Table(table) => println!("  table {}: {}", name, table.ty()),
+        }
+    }
+}
+
+fn print_global(instance: &Instance, args: &[&str]) -> Result<()> {
+    let name = match args {
+        [name] => name,
+        _ => bail!("Usage: :global <name>"),
+    };
+    let global = instance.exports.get_global(name)?;
+    println!("{}", format_values(&[global.get()]));
+    Ok(())
+}
+
+fn print_memory(instance: &Instance, args: &[&str]) -> Result<()> {
+    let (name, offset, len) = match args {
+        [name, offset] => (name, offset, "64"),
+        [name, offset, len] => (name, offset, *len),
+        _ => bail!("Usage: :memory <name> <offset> [LEN]"),
+    };
+    let offset = parse_usize(offset)?;
+    let len = parse_usize(len)?;
+    let memory = instance.exports.get_memory(name)?;
+    let view = memory.view::<u8>();
+    let end = offset
+        .checked_add(len)
+        .filter(|end| *end <= view.len())
+        .ok_or_else(|| {
+            anyhow!(
+                "Reading {} bytes at offset {} is out of bounds for a memory of {} bytes",
+                len,
+                offset,
+                view.len()
+            )
+        })?;
+    let bytes = view[offset..end]
+        .iter()
+        .map(|cell| cell.get())
+        .collect::<Vec<u8>>();
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        let hex = chunk
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        println!("{:08x}  {:<47}  {}", offset + i * 16, hex, ascii);
+    }
+    Ok(())
+}
+
+fn parse_usize(arg: &str) -> Result<usize> {
+    let parsed = match arg.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    parsed.map_err(|_| anyhow!("Can't convert `{}` into an offset", arg))
+}
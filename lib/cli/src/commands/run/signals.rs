@@ -0,0 +1,151 @@
+//! Graceful handling of `SIGINT` and `SIGTERM` while a module runs.
+//!
+//! WASI has no way to deliver signals to a guest, and running
+//! WebAssembly code can't be interrupted from another thread. Instead,
+//! the signal is delivered at the next WASI syscall of the guest (see
+//! [`Interrupt`]), which then exits with the conventional `128 + signal`
+//! status code once the host cleaned up. A guest not making any syscall
+//! within the grace period (or receiving a second signal) is terminated
+//! after flushing the standard streams and restoring the terminals.
+use colored::*;
+use std::io::{self, Write};
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The last signal received, or 0
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+/// The number of signals received
+static SIGNAL_COUNT: AtomicUsize = AtomicUsize::new(0);
+/// Whether the handlers were uninstalled, stopping the watcher thread
+static UNINSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// How often the watcher thread checks for signals
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The signals handled while a module runs
+const SIGNALS: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+extern "C" fn on_signal(signal: libc::c_int) {
+    // Only async-signal-safe operations are allowed here
+    SIGNAL.store(signal, Ordering::SeqCst);
+    SIGNAL_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+/// The signal handlers, restoring the previous ones when dropped.
+pub struct SignalHandlers {
+    previous: Vec<(libc::c_int, libc::sigaction)>,
+}
+
+impl Drop for SignalHandlers {
+    fn drop(&mut self) {
+        UNINSTALLED.store(true, Ordering::SeqCst);
+        for (signal, action) in &self.previous {
+            unsafe { libc::sigaction(*signal, action, ptr::null_mut()) };
+        }
+    }
+}
+
+/// Installs the signal handlers, and starts a thread terminating the
+/// process once `grace_period` elapses after a signal.
+pub fn install(grace_period: Duration) -> SignalHandlers {
+    let previous = SIGNALS
+        .iter()
+        .map(|&signal| unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = on_signal as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut previous: libc::sigaction = mem::zeroed();
+            libc::sigaction(signal, &action, &mut previous);
+            (signal, previous)
+        })
+        .collect();
+
+    thread::spawn(move || {
+        while pending().is_none() {
+            if UNINSTALLED.load(Ordering::SeqCst) {
+                return;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        let signal = SIGNAL.load(Ordering::SeqCst);
+        if grace_period > Duration::from_secs(0) {
+            eprintln!(
+                "{}: received {}, waiting up to {:?} for the module to exit (send it again to terminate now)",
+                "warning".yellow().bold(),
+                signal_name(signal),
+                grace_period
+            );
+            let deadline = Instant::now() + grace_period;
+            while Instant::now() < deadline && SIGNAL_COUNT.load(Ordering::SeqCst) == 1 {
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+        #[cfg(feature = "wasi")]
+        wasmer_wasi::restore_terminals();
+        std::process::exit(128 + SIGNAL.load(Ordering::SeqCst));
+    });
+
+    SignalHandlers { previous }
+}
+
+/// The signal received, if any.
+fn pending() -> Option<libc::c_int> {
+    if SIGNAL_COUNT.load(Ordering::SeqCst) == 0 {
+        None
+    } else {
+        Some(SIGNAL.load(Ordering::SeqCst))
+    }
+}
+
+/// A syscall hook delivering the signals to WASI modules, by making
+/// them exit with `128 + signal` at their next syscall.
+///
+/// The syscalls denied by a policy are only seen after being denied, so
+/// the signals are checked after the syscalls too.
+#[cfg(feature = "wasi")]
+pub struct Interrupt;
+
+#[cfg(feature = "wasi")]
+fn interrupt() {
+    if let Some(signal) = pending() {
+        let code = 128 + signal as wasmer_wasi::types::__wasi_exitcode_t;
+        wasmer::RuntimeError::raise(Box::new(wasmer_wasi::WasiError::Exit(code)));
+    }
+}
+
+#[cfg(feature = "wasi")]
+impl wasmer_wasi::SyscallHook for Interrupt {
+    fn before(
+        &self,
+        _env: &wasmer_wasi::WasiEnv,
+        _name: &str,
+        _args: &[wasmer::Value],
+    ) -> Option<wasmer_wasi::types::__wasi_errno_t> {
+        interrupt();
+        None
+    }
+
+    fn after(
+        &self,
+        _env: &wasmer_wasi::WasiEnv,
+        _name: &str,
+        _args: &[wasmer::Value],
+        errno: wasmer_wasi::types::__wasi_errno_t,
+    ) -> wasmer_wasi::types::__wasi_errno_t {
+        interrupt();
+        errno
+    }
+}
+
+fn signal_name(signal: libc::c_int) -> &'static str {
+    match signal {
+        libc::SIGINT => "SIGINT",
+        libc::SIGTERM => "SIGTERM",
+        _ => "a signal",
+    }
+}
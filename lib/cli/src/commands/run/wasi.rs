@@ -1,9 +1,23 @@
-use crate::utils::{parse_envvar, parse_mapdir};
-use anyhow::{Context, Result};
+use super::Linker;
+#[cfg(feature = "wasi-archive")]
+use crate::utils::parse_archive_mount;
+use crate::utils::{parse_env_file, parse_envvar, parse_mapdir, parse_overlay, parse_tmpfs};
+use crate::warning;
+use anyhow::{bail, Context, Result};
 use std::collections::BTreeSet;
+use std::env;
+use std::fs::{self, File};
+use std::io;
 use std::path::PathBuf;
-use wasmer::{Instance, Module};
-use wasmer_wasi::{get_wasi_versions, WasiError, WasiState, WasiVersion};
+use std::sync::Arc;
+use wasmer::{ChainableNamedResolver, Instance, Module, RuntimeError};
+#[cfg(feature = "wasi-webc")]
+use wasmer_wasi::webc::{WebcCommand, WebcPackage};
+use wasmer_wasi::{
+    get_wasi_versions, initialize_reactor, restore_terminals, HostFile, HostFileSystem,
+    MemFileSystem, OverlayFileSystem, Recorder, Replayer, SyscallCategory, WasiPolicy, WasiState,
+    WasiVersion,
+};
 
 use structopt::StructOpt;
 
@@ -18,15 +32,134 @@ pub struct Wasi {
     #[structopt(long = "mapdir", name = "GUEST_DIR:HOST_DIR", multiple = true, parse(try_from_str = parse_mapdir))]
     mapped_dirs: Vec<(String, PathBuf)>,
 
+    /// Map a host directory to a location for the Wasm module without
+    /// modifying it: the changes are written to UPPER_DIR, or to a
+    /// temporary directory removed after the run
+    #[structopt(
+        long = "overlay",
+        name = "GUEST_DIR:LOWER_DIR[:UPPER_DIR]",
+        multiple = true,
+        number_of_values = 1,
+        parse(try_from_str = parse_overlay)
+    )]
+    overlays: Vec<(String, PathBuf, Option<PathBuf>)>,
+
+    /// Mount an empty in-memory file system at a location for the Wasm
+    /// module, optionally limited to SIZE bytes (eg. `64MiB`) and INODES
+    /// files and directories
+    #[structopt(
+        long = "tmpfs",
+        name = "GUEST_DIR[:SIZE[:INODES]]",
+        multiple = true,
+        number_of_values = 1,
+        parse(try_from_str = parse_tmpfs)
+    )]
+    tmpfs: Vec<(String, Option<u64>, Option<usize>)>,
+
+    /// Mount the files of a `.zip`, `.tar` or `.tar.gz` archive read-only
+    /// at a location for the Wasm module, without extracting it
+    #[cfg(feature = "wasi-archive")]
+    #[structopt(
+        long = "mount-archive",
+        name = "GUEST_DIR:ARCHIVE",
+        multiple = true,
+        number_of_values = 1,
+        parse(try_from_str = parse_archive_mount)
+    )]
+    archives: Vec<(String, PathBuf)>,
+
     /// Pass custom environment variables
     #[structopt(long = "env", name = "KEY=VALUE", multiple = true, parse(try_from_str = parse_envvar))]
     env_vars: Vec<(String, String)>,
 
+    /// Pass the environment variables defined in a `.env` file
+    #[structopt(
+        long = "env-file",
+        name = "ENV_FILE",
+        multiple = true,
+        parse(from_os_str)
+    )]
+    env_files: Vec<PathBuf>,
+
+    /// Pass the host environment variables with the given name, or
+    /// starting with the given prefix if it ends with `*` (eg. `APP_*`)
+    #[structopt(long = "env-pass", name = "NAME", multiple = true)]
+    env_pass: Vec<String>,
+
+    /// Read the standard input of the module from the given file,
+    /// instead of inheriting the one of wasmer
+    #[structopt(long = "stdin", name = "STDIN_FILE", parse(from_os_str))]
+    stdin: Option<PathBuf>,
+
+    /// Enable the experimental TCP sockets, for any host and port unless
+    /// restricted with `--net-allow`
+    #[structopt(long = "enable-net")]
+    enable_net: bool,
+
+    /// Only allow the sockets to connect to or listen on the given host
+    /// and port, either of which can be `*`
+    #[structopt(
+        long = "net-allow",
+        name = "HOST:PORT",
+        multiple = true,
+        number_of_values = 1,
+        requires = "enable-net"
+    )]
+    net_allow: Vec<String>,
+
     /// Enable experimental IO devices
     #[cfg(feature = "experimental-io-devices")]
     #[structopt(long = "enable-experimental-io-devices")]
     enable_experimental_io_devices: bool,
 
+    /// Deny a category of WASI syscalls, which fail with `ENOTCAPABLE`:
+    /// `fs-write` (changing files and directories), `env` (reading the
+    /// environment variables) or `clock` (reading the clocks)
+    #[structopt(
+        long = "deny-syscalls",
+        name = "CATEGORY",
+        multiple = true,
+        number_of_values = 1
+    )]
+    deny_syscalls: Vec<SyscallCategory>,
+
+    /// Print the WASI syscalls denied by `--deny-syscalls` to stderr or
+    /// to the given file (`--audit-denied=AUDIT_FILE`)
+    #[structopt(
+        long = "audit-denied",
+        name = "AUDIT_FILE",
+        require_equals = true,
+        requires = "CATEGORY",
+        parse(from_os_str)
+    )]
+    audit_denied: Option<Option<PathBuf>>,
+
+    /// Print every WASI syscall with its arguments, result and duration,
+    /// to stderr or to the given file (`--trace-syscalls=FILE`)
+    #[structopt(
+        long = "trace-syscalls",
+        name = "TRACE_FILE",
+        require_equals = true,
+        parse(from_os_str)
+    )]
+    trace_syscalls: Option<Option<PathBuf>>,
+
+    /// Record the WASI syscalls made by the module and their results to
+    /// a file, to replay the run later with `--replay`
+    #[structopt(long = "record", name = "RECORDING", parse(from_os_str))]
+    record: Option<PathBuf>,
+
+    /// Replay a run recorded with `--record`, serving the recorded results
+    /// of the syscalls instead of making them. The module must be run
+    /// with the same arguments as when it was recorded
+    #[structopt(
+        long = "replay",
+        name = "RECORDED",
+        conflicts_with = "RECORDING",
+        parse(from_os_str)
+    )]
+    replay: Option<PathBuf>,
+
     /// Allow WASI modules to import multiple versions of WASI without a warning.
     #[structopt(long = "allow-multiple-wasi-versions")]
     pub allow_multiple_wasi_versions: bool,
@@ -34,6 +167,11 @@ pub struct Wasi {
     /// Require WASI modules to only import 1 version of WASI.
     #[structopt(long = "deny-multiple-wasi-versions")]
     pub deny_multiple_wasi_versions: bool,
+
+    /// The `.webc` package being run, with the command selected
+    #[cfg(feature = "wasi-webc")]
+    #[structopt(skip)]
+    webc: Option<(WebcPackage, WebcCommand)>,
 }
 
 #[allow(dead_code)]
@@ -52,16 +190,146 @@ impl Wasi {
         get_wasi_versions(&module, false).is_some()
     }
 
+    /// Runs the command of a `.webc` package: its atom is the module
+    /// executed, and its volumes, environment variables and arguments
+    /// are set up for it.
+    #[cfg(feature = "wasi-webc")]
+    pub fn set_webc(&mut self, package: WebcPackage, command: WebcCommand) {
+        self.webc = Some((package, command));
+    }
+
+    /// Gets the module of the command of the `.webc` package being run.
+    #[cfg(feature = "wasi-webc")]
+    pub fn webc_atom(&self) -> Option<&[u8]> {
+        let (package, command) = self.webc.as_ref()?;
+        package.atom(&command.atom)
+    }
+
+    /// Gets the environment variables to pass to the module: the ones
+    /// declared by the `.webc` command, then the ones from the
+    /// `--env-file`s, then the host ones selected with `--env-pass`,
+    /// then the `--env` ones, each overriding the previous.
+    fn get_env_vars(&self) -> Result<Vec<(String, String)>> {
+        let mut env_vars = vec![];
+        #[cfg(feature = "wasi-webc")]
+        if let Some((_, command)) = &self.webc {
+            env_vars.extend(command.env.iter().cloned());
+        }
+        for path in &self.env_files {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("failed to read `{}`", path.display()))?;
+            env_vars.extend(
+                parse_env_file(&contents)
+                    .with_context(|| format!("failed to parse `{}`", path.display()))?,
+            );
+        }
+        if !self.env_pass.is_empty() {
+            let mut host_vars = env::vars()
+                .filter(|(name, _)| {
+                    self.env_pass
+                        .iter()
+                        .any(|pattern| match pattern.strip_suffix('*') {
+                            Some(prefix) => name.starts_with(prefix),
+                            None => name == pattern,
+                        })
+                })
+                .collect::<Vec<_>>();
+            host_vars.sort();
+            env_vars.extend(host_vars);
+        }
+        env_vars.extend(self.env_vars.iter().cloned());
+
+        // Keep only the last definition of each variable
+        let mut seen = BTreeSet::new();
+        let mut deduplicated = env_vars
+            .into_iter()
+            .rev()
+            .filter(|(name, _)| seen.insert(name.clone()))
+            .collect::<Vec<_>>();
+        deduplicated.reverse();
+        Ok(deduplicated)
+    }
+
     /// Helper function for executing Wasi from the `Run` command.
-    pub fn execute(&self, module: Module, program_name: String, args: Vec<String>) -> Result<()> {
+    ///
+    /// `call` calls the entrypoint of the instance, once it's initialized
+    /// if it's a reactor.
+    pub fn execute(
+        &self,
+        module: Module,
+        program_name: String,
+        args: Vec<String>,
+        mut linker: Linker,
+        call: impl FnOnce(&Instance) -> Result<()>,
+    ) -> Result<()> {
+        #[cfg(feature = "wasi-webc")]
+        let args = match &self.webc {
+            Some((_, command)) => command.args.iter().cloned().chain(args).collect(),
+            None => args,
+        };
         let args = args.iter().cloned().map(|arg| arg.into_bytes());
 
         let mut wasi_state_builder = WasiState::new(program_name);
         wasi_state_builder
             .args(args)
-            .envs(self.env_vars.clone())
+            .envs(self.get_env_vars()?)
             .preopen_dirs(self.pre_opened_directories.clone())?
             .map_dirs(self.mapped_dirs.clone())?;
+        #[cfg(feature = "wasi-webc")]
+        if let Some((package, _)) = &self.webc {
+            package.mount_volumes(&mut wasi_state_builder)?;
+        }
+        // The upper directories of the overlays without one, removed when
+        // they are dropped
+        let mut scratch_dirs = vec![];
+        for (alias, lower_dir, upper_dir) in &self.overlays {
+            let upper_dir = match upper_dir {
+                Some(upper_dir) => upper_dir.clone(),
+                None => {
+                    let dir = tempfile::tempdir()
+                        .context("failed to create the upper directory of an overlay")?;
+                    let path = dir.path().to_path_buf();
+                    scratch_dirs.push(dir);
+                    path
+                }
+            };
+            let overlay = OverlayFileSystem::new(
+                HostFileSystem::new(lower_dir),
+                HostFileSystem::new(upper_dir),
+            )
+            .with_context(|| format!("failed to set up the overlay of `{}`", alias))?;
+            wasi_state_builder.mount(alias, overlay)?;
+        }
+        for (alias, max_size, max_inodes) in &self.tmpfs {
+            wasi_state_builder.mount(alias, MemFileSystem::new(*max_size, *max_inodes))?;
+        }
+        if let Some(path) = &self.stdin {
+            let file =
+                File::open(path).with_context(|| format!("failed to open `{}`", path.display()))?;
+            wasi_state_builder.stdin(Box::new(HostFile::new(
+                file,
+                path.clone(),
+                true,
+                false,
+                false,
+            )));
+        }
+
+        #[cfg(feature = "wasi-archive")]
+        for (alias, path) in &self.archives {
+            let archive = wasmer_wasi::ArchiveFileSystem::open(path)
+                .with_context(|| format!("failed to open the archive `{}`", path.display()))?;
+            wasi_state_builder.mount(alias, archive)?;
+        }
+
+        if self.enable_net {
+            if self.net_allow.is_empty() {
+                wasi_state_builder.allow_network("*");
+            }
+            for rule in &self.net_allow {
+                wasi_state_builder.allow_network(rule);
+            }
+        }
 
         #[cfg(feature = "experimental-io-devices")]
         {
@@ -72,26 +340,96 @@ impl Wasi {
         }
 
         let mut wasi_env = wasi_state_builder.finalize()?;
-        let resolver = wasi_env.import_object_for_all_wasi_versions(&module)?;
+        // Delivers the signals at the next syscall
+        #[cfg(unix)]
+        wasi_env.hook_syscall("*", super::signals::Interrupt);
+        if !self.deny_syscalls.is_empty() {
+            let mut policy = WasiPolicy::new();
+            for category in &self.deny_syscalls {
+                policy.deny(*category);
+            }
+            match &self.audit_denied {
+                Some(Some(path)) => {
+                    let file = File::create(path)
+                        .with_context(|| format!("failed to create `{}`", path.display()))?;
+                    policy.audit(Box::new(file));
+                }
+                Some(None) => {
+                    policy.audit(Box::new(io::stderr()));
+                }
+                None => {}
+            }
+            wasi_env.set_policy(policy);
+        }
+        match &self.trace_syscalls {
+            Some(Some(path)) => {
+                let file = File::create(path)
+                    .with_context(|| format!("failed to create `{}`", path.display()))?;
+                wasi_env.trace_syscalls(Box::new(file));
+            }
+            Some(None) => wasi_env.trace_syscalls(Box::new(io::stderr())),
+            None => {}
+        }
+        let recorder = match &self.record {
+            Some(path) => {
+                let file = File::create(path)
+                    .with_context(|| format!("failed to create `{}`", path.display()))?;
+                let recorder = Arc::new(Recorder::new(Box::new(io::BufWriter::new(file)))?);
+                wasi_env.hook_syscall("*", recorder.clone());
+                Some(recorder)
+            }
+            None => None,
+        };
+        let replayer = match &self.replay {
+            Some(path) => {
+                let file = File::open(path)
+                    .with_context(|| format!("failed to open `{}`", path.display()))?;
+                let replayer = Arc::new(
+                    Replayer::new(Box::new(io::BufReader::new(file)))
+                        .with_context(|| format!("failed to replay `{}`", path.display()))?,
+                );
+                wasi_env.hook_syscall("*", replayer.clone());
+                Some(replayer)
+            }
+            None => None,
+        };
+        let resolver = wasi_env
+            .import_object_for_all_wasi_versions(&module)?
+            .chain_back(linker.imports_for(&module)?);
         let instance = Instance::new(&module, &resolver)?;
 
-        let start = instance.exports.get_function("_start")?;
-        let result = start.call(&[]);
-
-        match result {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                let err: anyhow::Error = match err.downcast::<WasiError>() {
-                    Ok(WasiError::Exit(exit_code)) => {
-                        // We should exit with the provided exit code
-                        std::process::exit(exit_code as _);
-                    }
-                    Ok(err) => err.into(),
-                    Err(err) => err.into(),
-                };
-                Err(err)
+        let result = super::sampled(|| initialize_reactor(&instance))
+            .map_err(anyhow::Error::from)
+            .and_then(|()| call(&instance));
+        // The module may have left the terminal in raw mode
+        restore_terminals();
+        if let Some(recorder) = &recorder {
+            recorder
+                .finish()
+                .context("failed to write the recording of the syscalls")?;
+        }
+        if let (Some(replayer), Some(path)) = (&replayer, &self.replay) {
+            if let Some(error) = replayer.error() {
+                bail!("failed to replay `{}`: {}", path.display(), error);
+            }
+        }
+
+        let exit_code = match &result {
+            Err(err) => err
+                .downcast_ref::<RuntimeError>()
+                .and_then(|err| wasi_env.get_exit_code(err)),
+            Ok(()) => None,
+        };
+        if let Some(exit_code) = exit_code {
+            #[cfg(unix)]
+            if let Err(error) = super::profile::stop() {
+                warning!("{:#}", error);
             }
+            // `exit` doesn't run the destructors
+            drop(scratch_dirs);
+            // We should exit with the provided exit code
+            std::process::exit(exit_code as _);
         }
-        .with_context(|| "failed to run WASI `_start` function")
+        result.context("failed to run the WASI module")
     }
 }
@@ -1,3 +1,4 @@
+use crate::common::hint_disabled_proposal;
 use crate::store::StoreOptions;
 use anyhow::{Context, Result};
 use std::path::PathBuf;
@@ -20,12 +21,57 @@ impl Validate {
     pub fn execute(&self) -> Result<()> {
         self.inner_execute()
             .context(format!("failed to validate `{}`", self.path.display()))
+            .map_err(hint_disabled_proposal)
     }
     fn inner_execute(&self) -> Result<()> {
         let (store, _engine_type, _compiler_type) = self.store.get_store()?;
         let module_contents = std::fs::read(&self.path)?;
         Module::validate(&store, &module_contents)?;
         eprintln!("Validation passed for `{}`.", self.path.display());
+        #[cfg(feature = "compiler")]
+        {
+            let required = required_features(&module_contents);
+            if required.is_empty() {
+                eprintln!("The module doesn't require any proposal.");
+            } else {
+                eprintln!("The module requires: {}.", required.join(", "));
+            }
+        }
         Ok(())
     }
 }
+
+/// The proposals a valid module can't be validated without.
+#[cfg(feature = "compiler")]
+fn required_features(module_contents: &[u8]) -> Vec<&'static str> {
+    use wasmer_compiler::wasmparser::{Validator, WasmFeatures};
+
+    let all = WasmFeatures {
+        bulk_memory: true,
+        threads: true,
+        reference_types: true,
+        multi_value: true,
+        simd: true,
+        ..WasmFeatures::default()
+    };
+    let proposals: [(&str, fn(&mut WasmFeatures)); 5] = [
+        ("threads", |features| features.threads = false),
+        ("simd", |features| features.simd = false),
+        ("multi-value", |features| features.multi_value = false),
+        ("bulk-memory", |features| features.bulk_memory = false),
+        ("reference-types", |features| {
+            features.reference_types = false
+        }),
+    ];
+    proposals
+        .iter()
+        .filter(|(_, disable)| {
+            let mut features = all;
+            disable(&mut features);
+            let mut validator = Validator::new();
+            validator.wasm_features(features);
+            validator.validate_all(module_contents).is_err()
+        })
+        .map(|(name, _)| *name)
+        .collect()
+}
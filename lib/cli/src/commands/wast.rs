@@ -1,16 +1,17 @@
 //! Runs a .wast WebAssembly test suites
 use crate::store::StoreOptions;
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 use wasmer_wast::Wast as WastSpectest;
 
 #[derive(Debug, StructOpt)]
 /// The options for the `wasmer wast` subcommand
 pub struct Wast {
-    /// Wast file to run
-    #[structopt(name = "FILE", parse(from_os_str))]
-    path: PathBuf,
+    /// Wast files to run. Directories are searched for `.wast` files
+    #[structopt(name = "FILE", parse(from_os_str), required = true, min_values = 1)]
+    paths: Vec<PathBuf>,
 
     #[structopt(flatten)]
     store: StoreOptions,
@@ -21,17 +22,56 @@ pub struct Wast {
 }
 
 impl Wast {
-    /// Runs logic for the `validate` subcommand
+    /// Runs logic for the `wast` subcommand
     pub fn execute(&self) -> Result<()> {
-        self.inner_execute()
-            .context(format!("failed to test the wast `{}`", self.path.display()))
-    }
-    fn inner_execute(&self) -> Result<()> {
+        let mut files = vec![];
+        for path in &self.paths {
+            collect_wast_files(path, &mut files)
+                .with_context(|| format!("failed to read `{}`", path.display()))?;
+        }
         let (store, _engine_name, _compiler_name) = self.store.get_store()?;
-        let mut wast = WastSpectest::new_with_spectest(store);
-        wast.fail_fast = self.fail_fast;
-        wast.run_file(&self.path).with_context(|| "tests failed")?;
-        eprintln!("Wast tests succeeded for `{}`.", self.path.display());
+
+        let mut failures = 0;
+        for file in &files {
+            let mut wast = WastSpectest::new_with_spectest(store.clone());
+            wast.fail_fast = self.fail_fast;
+            match wast.run_file(file) {
+                Ok(()) => eprintln!("✔ {}", file.display()),
+                Err(error) => {
+                    eprintln!("✘ {}: {:?}", file.display(), error);
+                    failures += 1;
+                    if self.fail_fast {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if failures > 0 {
+            bail!("{} of {} wast files failed", failures, files.len());
+        }
+        eprintln!("Wast tests succeeded for {} files.", files.len());
         Ok(())
     }
 }
+
+/// Collects `path` if it's a file, or the `.wast` files it contains
+/// (recursively, in a stable order) if it's a directory.
+fn collect_wast_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_wast_files(&entry, files)?;
+        } else if entry.extension().map_or(false, |ext| ext == "wast") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
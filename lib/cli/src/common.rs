@@ -32,8 +32,72 @@ pub struct WasmFeatures {
     /// Enable support for all pre-standard proposals.
     #[structopt(long = "enable-all")]
     pub all: bool,
+
+    /// Disable support for the SIMD proposal, even if otherwise enabled.
+    #[structopt(long = "deny-simd")]
+    pub deny_simd: bool,
+
+    /// Disable support for the threads proposal, even if otherwise enabled.
+    #[structopt(long = "deny-threads")]
+    pub deny_threads: bool,
+
+    /// Disable support for the reference types proposal, even if otherwise enabled.
+    #[structopt(long = "deny-reference-types")]
+    pub deny_reference_types: bool,
+
+    /// Disable support for the multi value proposal, even if otherwise enabled.
+    #[structopt(long = "deny-multi-value")]
+    pub deny_multi_value: bool,
+
+    /// Disable support for the bulk memory proposal, even if otherwise enabled.
+    #[structopt(long = "deny-bulk-memory")]
+    pub deny_bulk_memory: bool,
 }
 
+/// The pre-standard proposals, with the words the validation errors use
+/// for them.
+const PROPOSALS: [(&str, &str); 5] = [
+    ("threads", "threads"),
+    ("simd", "simd"),
+    ("multi-value", "multi-value"),
+    ("bulk-memory", "bulk memory"),
+    ("reference-types", "reference types"),
+];
+
+/// Finds the proposal a validation error says is disabled, if any.
+pub fn disabled_proposal(message: &str) -> Option<&'static str> {
+    let message = message.to_lowercase();
+    if !(message.contains("not enabled")
+        || message.contains("must be enabled")
+        || message.contains("require"))
+    {
+        return None;
+    }
+    PROPOSALS
+        .iter()
+        .find(|(_, words)| message.contains(words))
+        .map(|(proposal, _)| *proposal)
+}
+
+/// Tells which flag enables the proposal a module requires when it failed
+/// to compile or validate because the proposal is disabled.
+pub fn hint_disabled_proposal(error: anyhow::Error) -> anyhow::Error {
+    let proposal = error
+        .chain()
+        .find_map(|cause| disabled_proposal(&cause.to_string()));
+    match proposal {
+        Some(proposal) => error.context(format!(
+            "module requires `{0}`; pass --enable-{0} (or --enable-all)",
+            proposal
+        )),
+        None => error,
+    }
+}
+
+/// The subdirectory of the cache dir where the downloaded packages are
+/// stored, next to the subdirectories of the compiled modules.
+pub const PACKAGES_CACHE_DIR: &str = "packages";
+
 /// Get the cache dir
 pub fn get_cache_dir() -> PathBuf {
     match env::var("WASMER_CACHE_DIR") {
@@ -51,3 +115,41 @@ pub fn get_cache_dir() -> PathBuf {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_disabled_proposals() {
+        assert_eq!(
+            disabled_proposal("Validation error: SIMD support is not enabled (at offset 32)"),
+            Some("simd")
+        );
+        assert_eq!(
+            disabled_proposal("threads must be enabled for shared memories"),
+            Some("threads")
+        );
+        assert_eq!(
+            disabled_proposal(
+                "func type returns multiple values but the multi-value feature is not enabled"
+            ),
+            Some("multi-value")
+        );
+        assert_eq!(
+            disabled_proposal("bulk memory support is not enabled"),
+            Some("bulk-memory")
+        );
+        assert_eq!(disabled_proposal("type mismatch: expected i32"), None);
+    }
+
+    #[test]
+    fn hints_the_flag() {
+        let error = anyhow::anyhow!("reference types support is not enabled")
+            .context("module instantiation failed");
+        assert_eq!(
+            hint_disabled_proposal(error).to_string(),
+            "module requires `reference-types`; pass --enable-reference-types (or --enable-all)"
+        );
+    }
+}
@@ -4,13 +4,14 @@
 use crate::common::WasmFeatures;
 use anyhow::Result;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::string::ToString;
 #[allow(unused_imports)]
 use std::sync::Arc;
 use structopt::StructOpt;
 use wasmer::*;
 #[cfg(feature = "compiler")]
-use wasmer_compiler::CompilerConfig;
+use wasmer_compiler::{CompilerConfig, ModuleMiddleware};
 
 #[derive(Debug, Clone, StructOpt)]
 /// The compiler and engine options
@@ -114,6 +115,21 @@ impl CompilerOptions {
         if self.features.reference_types || self.features.all {
             features.reference_types(true);
         }
+        if self.features.deny_threads {
+            features.threads(false);
+        }
+        if self.features.deny_multi_value {
+            features.multi_value(false);
+        }
+        if self.features.deny_simd {
+            features.simd(false);
+        }
+        if self.features.deny_bulk_memory {
+            features.bulk_memory(false);
+        }
+        if self.features.deny_reference_types {
+            features.reference_types(false);
+        }
         Ok(features)
     }
 
@@ -172,6 +188,13 @@ impl CompilerOptions {
     #[allow(unused_variables)]
     pub(crate) fn get_compiler_config(&self) -> Result<(Box<dyn CompilerConfig>, CompilerType)> {
         let compiler = self.get_compiler()?;
+        let compiler_config = self.get_compiler_config_for(&compiler)?;
+        Ok((compiler_config, compiler))
+    }
+
+    /// Get the Compiler Config of the given compiler for the current options
+    #[allow(unused_variables)]
+    fn get_compiler_config_for(&self, compiler: &CompilerType) -> Result<Box<dyn CompilerConfig>> {
         let compiler_config: Box<dyn CompilerConfig> = match compiler {
             CompilerType::Headless => bail!("The headless engine can't be chosen"),
             #[cfg(feature = "singlepass")]
@@ -303,12 +326,12 @@ impl CompilerOptions {
         };
 
         #[allow(unreachable_code)]
-        Ok((compiler_config, compiler))
+        Ok(compiler_config)
     }
 }
 
 /// The compiler used for the store
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CompilerType {
     /// Singlepass compiler
     Singlepass,
@@ -334,6 +357,19 @@ impl CompilerType {
     }
 }
 
+impl FromStr for CompilerType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "singlepass" => Ok(Self::Singlepass),
+            "cranelift" => Ok(Self::Cranelift),
+            "llvm" => Ok(Self::LLVM),
+            _ => bail!("Unknown compiler `{}`", s),
+        }
+    }
+}
+
 impl ToString for CompilerType {
     fn to_string(&self) -> String {
         match self {
@@ -385,6 +421,22 @@ impl StoreOptions {
         Ok((store, engine_type, compiler_type))
     }
 
+    /// Gets the store for the host target using the given compiler,
+    /// with the given middlewares, and the engine selected
+    pub fn get_store_with_compiler(
+        &self,
+        compiler: &CompilerType,
+        middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+    ) -> Result<Store> {
+        let mut compiler_config = self.compiler.get_compiler_config_for(compiler)?;
+        for middleware in middlewares {
+            compiler_config.push_middleware(middleware);
+        }
+        let (engine, _engine_type) =
+            self.get_engine_with_compiler(Target::default(), compiler_config)?;
+        Ok(Store::new(&*engine))
+    }
+
     fn get_engine_with_compiler(
         &self,
         target: Target,
@@ -1,7 +1,10 @@
 //! Utility functions for the WebAssembly module
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use std::env;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use wasmer::CpuFeature;
 
 /// Whether or not Wasmer should print with color
 pub fn wasmer_should_print_color() -> bool {
@@ -40,6 +43,73 @@ pub fn parse_mapdir(entry: &str) -> Result<(String, PathBuf)> {
     }
 }
 
+/// Parses an overlay, a mapdir optionally followed by the directory
+/// where the changes are written
+pub fn parse_overlay(entry: &str) -> Result<(String, PathBuf, Option<PathBuf>)> {
+    let parts = if entry.contains("::") {
+        entry.split("::").collect::<Vec<&str>>()
+    } else {
+        entry.split(':').collect::<Vec<&str>>()
+    };
+    let (alias, lower_dir, upper_dir) = match parts[..] {
+        [alias, lower_dir] => (alias, lower_dir, None),
+        [alias, lower_dir, upper_dir] => (alias, lower_dir, Some(upper_dir)),
+        _ => bail!(
+            "Overlays must be of the form `GUEST_DIR:LOWER_DIR[:UPPER_DIR]`. Found {}",
+            &entry
+        ),
+    };
+    let (alias, lower_dir) = retrieve_alias_pathbuf(alias, lower_dir)?;
+    let upper_dir = match upper_dir {
+        Some(upper_dir) => Some(retrieve_alias_pathbuf(&alias, upper_dir)?.1),
+        None => None,
+    };
+    Ok((alias, lower_dir, upper_dir))
+}
+
+/// Parses the mount of an archive, `GUEST_DIR:ARCHIVE`
+pub fn parse_archive_mount(entry: &str) -> Result<(String, PathBuf)> {
+    let (alias, archive) = if let Some(index) = entry.find("::") {
+        (&entry[..index], &entry[index + 2..])
+    } else if let Some(index) = entry.find(':') {
+        (&entry[..index], &entry[index + 1..])
+    } else {
+        bail!(
+            "Archive mounts must be of the form `GUEST_DIR:ARCHIVE`. Found {}",
+            &entry
+        )
+    };
+    let archive = PathBuf::from(archive);
+    if !archive.is_file() {
+        bail!("Archive \"{}\" does not exist", archive.display());
+    }
+    Ok((alias.to_string(), archive))
+}
+
+/// Parses an in-memory file system mount, `GUEST_DIR[:SIZE[:INODES]]`
+pub fn parse_tmpfs(entry: &str) -> Result<(String, Option<u64>, Option<usize>)> {
+    let parts = entry.splitn(3, ':').collect::<Vec<&str>>();
+    let size = match parts.get(1) {
+        Some(size) => Some(parse_bytes(size)?),
+        None => None,
+    };
+    let inodes = match parts.get(2) {
+        Some(inodes) => Some(
+            inodes
+                .parse::<usize>()
+                .map_err(|_| anyhow!("Invalid inode limit `{}`", inodes))?,
+        ),
+        None => None,
+    };
+    if parts[0].is_empty() {
+        bail!(
+            "Tmpfs mounts must be of the form `GUEST_DIR[:SIZE[:INODES]]`. Found {}",
+            &entry
+        )
+    }
+    Ok((parts[0].to_string(), size, inodes))
+}
+
 /// Parses an environment variable.
 pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
     let entry = entry.trim();
@@ -64,9 +134,133 @@ pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
     }
 }
 
+/// Parses the contents of a `.env` file into a list of environment
+/// variables.
+///
+/// Each line is a `KEY=VALUE` assignment, optionally prefixed by
+/// `export` and with the value optionally quoted. Empty lines and lines
+/// starting with `#` are ignored.
+pub fn parse_env_file(contents: &str) -> Result<Vec<(String, String)>> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let position = match line.find('=') {
+                Some(position) if position > 0 => position,
+                _ => bail!(
+                    "Line {} must be of the form `<name>=<value>`; found `{}`",
+                    line_number,
+                    line
+                ),
+            };
+            let name = line[..position].trim();
+            let value = line[position + 1..].trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|&quote| {
+                    value
+                        .strip_prefix(quote)
+                        .and_then(|value| value.strip_suffix(quote))
+                })
+                .unwrap_or(value);
+            Ok((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Parses a comma-separated list of CPU features, such as `+neon,+crc`.
+pub fn parse_cpu_features(entry: &str) -> Result<Vec<CpuFeature>> {
+    entry
+        .split(',')
+        .map(str::trim)
+        .filter(|feature| !feature.is_empty())
+        .map(|feature| {
+            if feature.starts_with('-') {
+                bail!(
+                    "Disabling CPU features is not supported; found `{}`",
+                    feature
+                );
+            }
+            let name = feature.trim_start_matches('+');
+            CpuFeature::from_str(name).map_err(|_| anyhow!("Unknown CPU feature `{}`", name))
+        })
+        .collect()
+}
+
+/// Splits a string such as `64MiB` into its number and its unit.
+fn split_unit(entry: &str) -> (&str, &str) {
+    let entry = entry.trim();
+    let index = entry
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or_else(|| entry.len());
+    (&entry[..index], entry[index..].trim())
+}
+
+/// Parses a size in bytes, optionally followed by a unit such as `KiB`,
+/// `MiB` or `GiB`.
+pub fn parse_bytes(entry: &str) -> Result<u64> {
+    let (number, unit) = split_unit(entry);
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => bail!(
+            "Unknown size unit `{}`; expected `B`, `KiB`, `MiB` or `GiB`",
+            unit
+        ),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| anyhow!("Invalid size `{}`", entry))
+}
+
+/// Parses a duration, such as `500ms`, `30s` or `2m`. Seconds are
+/// assumed when no unit is given.
+pub fn parse_duration(entry: &str) -> Result<Duration> {
+    let (number, unit) = split_unit(entry);
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| anyhow!("Invalid duration `{}`", entry))?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => bail!(
+            "Unknown duration unit `{}`; expected `ms`, `s`, `m` or `h`",
+            unit
+        ),
+    };
+    // `Duration::from_secs_f64` panics on negative, non-finite and
+    // overflowing values
+    if !(seconds >= 0.0 && seconds < u64::MAX as f64) {
+        bail!("Invalid duration `{}`", entry);
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Checks if the provided bytes are a WebAssembly component rather than
+/// a core module. Both start with the same magic number, but the header
+/// of components has a non-zero layer after the version.
+pub fn is_component(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"\0asm") && bytes.get(6..8).map_or(false, |layer| layer != [0, 0])
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_envvar;
+    use super::{
+        is_component, parse_archive_mount, parse_bytes, parse_cpu_features, parse_duration,
+        parse_env_file, parse_envvar, parse_overlay, parse_tmpfs,
+    };
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use wasmer::CpuFeature;
 
     #[test]
     fn test_parse_envvar() {
@@ -89,4 +283,114 @@ mod tests {
             ("A".into(), "B=C=D".into())
         );
     }
+
+    #[test]
+    fn test_parse_overlay() {
+        assert_eq!(
+            parse_overlay("/data:.").unwrap(),
+            ("/data".to_string(), PathBuf::from("."), None)
+        );
+        assert_eq!(
+            parse_overlay("/data::.::.").unwrap(),
+            (
+                "/data".to_string(),
+                PathBuf::from("."),
+                Some(PathBuf::from("."))
+            )
+        );
+        assert!(parse_overlay("/data").is_err());
+        assert!(parse_overlay("/data:.:does-not-exist").is_err());
+    }
+
+    #[test]
+    fn test_parse_archive_mount() {
+        assert_eq!(
+            parse_archive_mount("/app::Cargo.toml").unwrap(),
+            ("/app".to_string(), PathBuf::from("Cargo.toml"))
+        );
+        assert!(parse_archive_mount("/app").is_err());
+        assert!(parse_archive_mount("/app:src").is_err());
+    }
+
+    #[test]
+    fn test_parse_tmpfs() {
+        assert_eq!(
+            parse_tmpfs("/tmp").unwrap(),
+            ("/tmp".to_string(), None, None)
+        );
+        assert_eq!(
+            parse_tmpfs("/tmp:64MiB:1000").unwrap(),
+            ("/tmp".to_string(), Some(64 << 20), Some(1000))
+        );
+        assert!(parse_tmpfs("").is_err());
+        assert!(parse_tmpfs("/tmp:lots").is_err());
+        assert!(parse_tmpfs("/tmp:1MiB:-1").is_err());
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let contents = "# Comment\n\nA=B\nexport C = \"D E\"\nF='G'\nH=\n";
+        assert_eq!(
+            parse_env_file(contents).unwrap(),
+            vec![
+                ("A".into(), "B".into()),
+                ("C".into(), "D E".into()),
+                ("F".into(), "G".into()),
+                ("H".into(), "".into()),
+            ]
+        );
+        assert_eq!(
+            parse_env_file("A=B\nC").unwrap_err().to_string(),
+            "Line 2 must be of the form `<name>=<value>`; found `C`"
+        );
+    }
+
+    #[test]
+    fn test_parse_cpu_features() {
+        assert_eq!(parse_cpu_features("+neon").unwrap(), vec![CpuFeature::NEON]);
+        assert_eq!(
+            parse_cpu_features("sse2, +avx").unwrap(),
+            vec![CpuFeature::SSE2, CpuFeature::AVX]
+        );
+        assert_eq!(
+            parse_cpu_features("-avx").unwrap_err().to_string(),
+            "Disabling CPU features is not supported; found `-avx`"
+        );
+        assert_eq!(
+            parse_cpu_features("+foo").unwrap_err().to_string(),
+            "Unknown CPU feature `foo`"
+        );
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("4096").unwrap(), 4096);
+        assert_eq!(parse_bytes("64MiB").unwrap(), 64 << 20);
+        assert_eq!(parse_bytes("1 GiB").unwrap(), 1 << 30);
+        assert_eq!(
+            parse_bytes("1TiB").unwrap_err().to_string(),
+            "Unknown size unit `TiB`; expected `B`, `KiB`, `MiB` or `GiB`"
+        );
+        assert!(parse_bytes("MiB").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("10").unwrap(), Duration::from_secs(10));
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert!(parse_duration("2d").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("NaN").is_err());
+        assert!(parse_duration("inf").is_err());
+        assert!(parse_duration("1e30h").is_err());
+    }
+
+    #[test]
+    fn test_is_component() {
+        assert!(is_component(b"\0asm\x0d\x00\x01\x00"));
+        assert!(!is_component(b"\0asm\x01\x00\x00\x00"));
+        assert!(!is_component(b"(module)"));
+    }
 }
@@ -24,10 +24,10 @@ use std::sync::Arc;
 use wasmer_compiler::CompileError;
 use wasmer_compiler::{CallingConvention, ModuleTranslationState, Target};
 use wasmer_compiler::{
-    Compilation, CompileModuleInfo, CompiledFunction, CompiledFunctionFrameInfo,
+    CancellationToken, Compilation, CompileModuleInfo, CompiledFunction, CompiledFunctionFrameInfo,
     CompiledFunctionUnwindInfo, Compiler, Dwarf, FunctionBinaryReader, FunctionBody,
     FunctionBodyData, MiddlewareBinaryReader, ModuleMiddleware, ModuleMiddlewareChain,
-    SectionIndex,
+    ProgressReporter, SectionIndex,
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
@@ -65,6 +65,7 @@ impl Compiler for CraneliftCompiler {
         compile_info: &CompileModuleInfo,
         module_translation_state: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+        cancellation_token: Option<&CancellationToken>,
     ) -> Result<Compilation, CompileError> {
         let isa = self.config().isa(target);
         let frontend_config = isa.frontend_config();
@@ -102,11 +103,17 @@ impl Compiler for CraneliftCompiler {
             }
         };
 
+        let progress = ProgressReporter::new(
+            self.config.progress_callback.as_ref(),
+            cancellation_token,
+            &function_body_inputs,
+        );
         let functions = function_body_inputs
             .iter()
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .par_iter()
             .map_init(FuncTranslator::new, |func_translator, (i, input)| {
+                let started = progress.function_started()?;
                 let func_index = module.func_index(*i);
                 let mut context = Context::new();
                 let mut func_env = FuncEnvironment::new(
@@ -128,14 +135,17 @@ impl Compiler for CraneliftCompiler {
                         .middlewares
                         .generate_function_middleware_chain(*i),
                 );
+                reader.set_cancellation(progress.cancellation());
 
-                func_translator.translate(
-                    module_translation_state,
-                    &mut reader,
-                    &mut context.func,
-                    &mut func_env,
-                    *i,
-                )?;
+                func_translator
+                    .translate(
+                        module_translation_state,
+                        &mut reader,
+                        &mut context.func,
+                        &mut func_env,
+                        *i,
+                    )
+                    .map_err(|error| progress.or_cancelled(error))?;
 
                 let mut code_buf: Vec<u8> = Vec::new();
                 let mut reloc_sink = RelocSink::new(&module, func_index);
@@ -186,6 +196,7 @@ impl Compiler for CraneliftCompiler {
                 // We transform the Cranelift JumpTable's into compiler JumpTables
                 let func_jt_offsets = transform_jump_table(context.func.jt_offsets);
 
+                progress.function_compiled(*i, input, started);
                 Ok(CompiledFunction {
                     body: FunctionBody {
                         body: code_buf,
@@ -258,6 +269,7 @@ impl Compiler for CraneliftCompiler {
             function_call_trampolines,
             dynamic_function_trampolines,
             dwarf,
-        ))
+        )
+        .with_function_compile_times(progress.into_function_compile_times()))
     }
 }
@@ -4,7 +4,8 @@ use cranelift_codegen::settings::{self, Configurable};
 use loupe::MemoryUsage;
 use std::sync::Arc;
 use wasmer_compiler::{
-    Architecture, Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target,
+    Architecture, Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, OperatingSystem,
+    ProgressCallback, Target,
 };
 
 // Runtime Environment
@@ -36,6 +37,8 @@ pub struct Cranelift {
     opt_level: CraneliftOptLevel,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+    /// The callback receiving the progress of the compilations.
+    pub(crate) progress_callback: Option<ProgressCallback>,
 }
 
 impl Cranelift {
@@ -48,6 +51,7 @@ impl Cranelift {
             opt_level: CraneliftOptLevel::Speed,
             enable_pic: false,
             middlewares: vec![],
+            progress_callback: None,
         }
     }
 
@@ -68,8 +72,16 @@ impl Cranelift {
 
     /// Generates the ISA for the provided target
     pub fn isa(&self, target: &Target) -> Box<dyn TargetIsa> {
-        let mut builder =
-            lookup(target.triple().clone()).expect("construct Cranelift ISA for triple");
+        let mut triple = target.triple().clone();
+        if let (Architecture::Aarch64(_), OperatingSystem::Windows) =
+            (triple.architecture, triple.operating_system)
+        {
+            // Cranelift only knows the Windows calling convention for
+            // x86-64. Windows on ARM64 follows the standard AAPCS64
+            // convention for everything we generate, so compile as Linux.
+            triple.operating_system = OperatingSystem::Linux;
+        }
+        let mut builder = lookup(triple).expect("construct Cranelift ISA for triple");
         // Cpu Features
         let cpu_features = target.cpu_features();
         if target.triple().architecture == Architecture::X86_64
@@ -202,6 +214,11 @@ impl CompilerConfig for Cranelift {
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
         self.middlewares.push(middleware);
     }
+
+    /// Registers a callback receiving the progress of the compilations.
+    fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress_callback = Some(callback);
+    }
 }
 
 impl Default for Cranelift {
@@ -12,9 +12,9 @@ use rayon::iter::ParallelBridge;
 use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use std::sync::Arc;
 use wasmer_compiler::{
-    Compilation, CompileError, CompileModuleInfo, Compiler, CustomSection, CustomSectionProtection,
-    Dwarf, FunctionBodyData, ModuleMiddleware, ModuleTranslationState, RelocationTarget,
-    SectionBody, SectionIndex, Symbol, SymbolRegistry, Target,
+    CancellationToken, Compilation, CompileError, CompileModuleInfo, Compiler, CustomSection,
+    CustomSectionProtection, Dwarf, FunctionBodyData, ModuleMiddleware, ModuleTranslationState,
+    ProgressReporter, RelocationTarget, SectionBody, SectionIndex, Symbol, SymbolRegistry, Target,
 };
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
@@ -102,6 +102,7 @@ impl LLVMCompiler {
                     &compile_info.memory_styles,
                     &compile_info.table_styles,
                     symbol_registry,
+                    None,
                 )?;
                 Ok(module.write_bitcode_to_memory().as_slice().to_vec())
             },
@@ -222,6 +223,7 @@ impl Compiler for LLVMCompiler {
         compile_info: &'module CompileModuleInfo,
         module_translation: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+        cancellation_token: Option<&CancellationToken>,
     ) -> Result<Compilation, CompileError> {
         //let data = Arc::new(Mutex::new(0));
         let memory_styles = &compile_info.memory_styles;
@@ -234,6 +236,11 @@ impl Compiler for LLVMCompiler {
         let mut module_custom_sections = PrimaryMap::new();
         let mut frame_section_bytes = vec![];
         let mut frame_section_relocations = vec![];
+        let progress = ProgressReporter::new(
+            self.config.progress_callback.as_ref(),
+            cancellation_token,
+            &function_body_inputs,
+        );
         let functions = function_body_inputs
             .iter()
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
@@ -244,18 +251,24 @@ impl Compiler for LLVMCompiler {
                     FuncTranslator::new(target_machine)
                 },
                 |func_translator, (i, input)| {
+                    let started = progress.function_started()?;
                     // TODO: remove (to serialize)
                     //let _data = data.lock().unwrap();
-                    func_translator.translate(
-                        module,
-                        module_translation,
-                        i,
-                        input,
-                        self.config(),
-                        memory_styles,
-                        &table_styles,
-                        &ShortNames {},
-                    )
+                    let compiled_function = func_translator
+                        .translate(
+                            module,
+                            module_translation,
+                            i,
+                            input,
+                            self.config(),
+                            memory_styles,
+                            &table_styles,
+                            &ShortNames {},
+                            progress.cancellation(),
+                        )
+                        .map_err(|error| progress.or_cancelled(error))?;
+                    progress.function_compiled(*i, input, started);
+                    Ok(compiled_function)
                 },
             )
             .collect::<Result<Vec<_>, CompileError>>()?
@@ -367,6 +380,7 @@ impl Compiler for LLVMCompiler {
             function_call_trampolines,
             dynamic_function_trampolines,
             dwarf,
-        ))
+        )
+        .with_function_compile_times(progress.into_function_compile_times()))
     }
 }
@@ -9,7 +9,9 @@ use loupe::MemoryUsage;
 use std::fmt::Debug;
 use std::sync::Arc;
 use target_lexicon::Architecture;
-use wasmer_compiler::{Compiler, CompilerConfig, ModuleMiddleware, Target, Triple};
+use wasmer_compiler::{
+    Compiler, CompilerConfig, ModuleMiddleware, ProgressCallback, Target, Triple,
+};
 use wasmer_types::{FunctionType, LocalFunctionIndex};
 
 /// The InkWell ModuleInfo type
@@ -49,6 +51,8 @@ pub struct LLVM {
     pub(crate) callbacks: Option<Arc<dyn LLVMCallbacks>>,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+    /// The callback receiving the progress of the compilations.
+    pub(crate) progress_callback: Option<ProgressCallback>,
 }
 
 impl LLVM {
@@ -62,6 +66,7 @@ impl LLVM {
             is_pic: false,
             callbacks: None,
             middlewares: vec![],
+            progress_callback: None,
         }
     }
 
@@ -107,10 +112,11 @@ impl LLVM {
         } else {
             target_lexicon::BinaryFormat::Elf
         };
-        let operating_system = if target.triple().operating_system
-            == wasmer_compiler::OperatingSystem::Darwin
-            && !self.is_pic
-        {
+        let is_darwin = matches!(
+            target.triple().operating_system,
+            wasmer_compiler::OperatingSystem::Darwin | wasmer_compiler::OperatingSystem::Ios
+        );
+        let operating_system = if is_darwin && !self.is_pic {
             // LLVM detects static relocation + darwin + 64-bit and
             // force-enables PIC because MachO doesn't support that
             // combination. They don't check whether they're targeting
@@ -217,6 +223,11 @@ impl CompilerConfig for LLVM {
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
         self.middlewares.push(middleware);
     }
+
+    /// Registers a callback receiving the progress of the compilations.
+    fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress_callback = Some(callback);
+    }
 }
 
 impl Default for LLVM {
@@ -26,8 +26,9 @@ use crate::config::{CompiledKind, LLVM};
 use crate::object_file::{load_object_file, CompiledFunction};
 use wasmer_compiler::wasmparser::{MemoryImmediate, Operator};
 use wasmer_compiler::{
-    wptype_to_type, CompileError, FunctionBinaryReader, FunctionBodyData, MiddlewareBinaryReader,
-    ModuleMiddlewareChain, ModuleTranslationState, RelocationTarget, Symbol, SymbolRegistry,
+    wptype_to_type, Cancellation, CompileError, FunctionBinaryReader, FunctionBodyData,
+    MiddlewareBinaryReader, ModuleMiddlewareChain, ModuleTranslationState, RelocationTarget,
+    Symbol, SymbolRegistry,
 };
 use wasmer_types::entity::PrimaryMap;
 use wasmer_types::{
@@ -68,6 +69,7 @@ impl FuncTranslator {
         memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
         _table_styles: &PrimaryMap<TableIndex, TableStyle>,
         symbol_registry: &dyn SymbolRegistry,
+        cancellation: Option<Cancellation>,
     ) -> Result<Module, CompileError> {
         // The function type, used for the callbacks.
         let function = CompiledKind::Local(*local_func_index);
@@ -139,6 +141,7 @@ impl FuncTranslator {
                 .middlewares
                 .generate_function_middleware_chain(*local_func_index),
         );
+        reader.set_cancellation(cancellation);
 
         let mut params = vec![];
         let first_param =
@@ -277,6 +280,7 @@ impl FuncTranslator {
         memory_styles: &PrimaryMap<MemoryIndex, MemoryStyle>,
         table_styles: &PrimaryMap<TableIndex, TableStyle>,
         symbol_registry: &dyn SymbolRegistry,
+        cancellation: Option<Cancellation>,
     ) -> Result<CompiledFunction, CompileError> {
         let module = self.translate_to_module(
             wasm_module,
@@ -287,6 +291,7 @@ impl FuncTranslator {
             memory_styles,
             table_styles,
             symbol_registry,
+            cancellation,
         )?;
         let function = CompiledKind::Local(*local_func_index);
         let target_machine = &self.target_machine;
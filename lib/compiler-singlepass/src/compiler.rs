@@ -13,11 +13,12 @@ use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use std::sync::Arc;
 use wasmer_compiler::TrapInformation;
 use wasmer_compiler::{
-    Architecture, CompileModuleInfo, CompilerConfig, FunctionBinaryReader, MiddlewareBinaryReader,
-    ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState, OperatingSystem, Target,
+    Architecture, CancellationToken, CompileModuleInfo, CompilerConfig, FunctionBinaryReader,
+    MiddlewareBinaryReader, ModuleMiddleware, ModuleMiddlewareChain, ModuleTranslationState,
+    OperatingSystem, Target,
 };
 use wasmer_compiler::{Compilation, CompileError, CompiledFunction, Compiler, SectionIndex};
-use wasmer_compiler::{FunctionBody, FunctionBodyData};
+use wasmer_compiler::{FunctionBody, FunctionBodyData, ProgressReporter};
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, FunctionType, LocalFunctionIndex, MemoryIndex, TableIndex};
 use wasmer_vm::{ModuleInfo, TrapCode, VMOffsets};
@@ -55,6 +56,7 @@ impl Compiler for SinglepassCompiler {
         compile_info: &CompileModuleInfo,
         _module_translation: &ModuleTranslationState,
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+        cancellation_token: Option<&CancellationToken>,
     ) -> Result<Compilation, CompileError> {
         if target.triple().operating_system == OperatingSystem::Windows {
             return Err(CompileError::UnsupportedTarget(
@@ -81,11 +83,17 @@ impl Compiler for SinglepassCompiler {
             .collect::<Vec<_>>()
             .into_iter()
             .collect();
+        let progress = ProgressReporter::new(
+            self.config.progress_callback.as_ref(),
+            cancellation_token,
+            &function_body_inputs,
+        );
         let functions = function_body_inputs
             .iter()
             .collect::<Vec<(LocalFunctionIndex, &FunctionBodyData<'_>)>>()
             .into_par_iter_if_rayon()
             .map(|(i, input)| {
+                let started = progress.function_started()?;
                 let middleware_chain = self
                     .config
                     .middlewares
@@ -93,6 +101,7 @@ impl Compiler for SinglepassCompiler {
                 let mut reader =
                     MiddlewareBinaryReader::new_with_offset(input.data, input.module_offset);
                 reader.set_middleware_chain(middleware_chain);
+                reader.set_cancellation(progress.cancellation());
 
                 // This local list excludes arguments.
                 let mut locals = vec![];
@@ -117,10 +126,13 @@ impl Compiler for SinglepassCompiler {
 
                 while generator.has_control_frames() {
                     generator.set_srcloc(reader.original_position() as u32);
-                    let op = reader.read_operator()?;
+                    let op = reader
+                        .read_operator()
+                        .map_err(|error| progress.or_cancelled(error))?;
                     generator.feed_operator(op).map_err(to_compile_error)?;
                 }
 
+                progress.function_compiled(i, input, started);
                 Ok(generator.finalize(&input))
             })
             .collect::<Result<Vec<CompiledFunction>, CompileError>>()?
@@ -152,7 +164,8 @@ impl Compiler for SinglepassCompiler {
             function_call_trampolines,
             dynamic_function_trampolines,
             None,
-        ))
+        )
+        .with_function_compile_times(progress.into_function_compile_times()))
     }
 }
 
@@ -220,7 +233,7 @@ mod tests {
         // Compile for win64
         let win64 = Target::new(triple!("x86_64-pc-windows-msvc"), CpuFeature::for_host());
         let (mut info, translation, inputs) = dummy_compilation_ingredients();
-        let result = compiler.compile_module(&win64, &mut info, &translation, inputs);
+        let result = compiler.compile_module(&win64, &mut info, &translation, inputs, None);
         match result.unwrap_err() {
             CompileError::UnsupportedTarget(name) => assert_eq!(name, "windows"),
             error => panic!("Unexpected error: {:?}", error),
@@ -229,7 +242,7 @@ mod tests {
         // Compile for 32bit Linux
         let linux32 = Target::new(triple!("i686-unknown-linux-gnu"), CpuFeature::for_host());
         let (mut info, translation, inputs) = dummy_compilation_ingredients();
-        let result = compiler.compile_module(&linux32, &mut info, &translation, inputs);
+        let result = compiler.compile_module(&linux32, &mut info, &translation, inputs, None);
         match result.unwrap_err() {
             CompileError::UnsupportedTarget(name) => assert_eq!(name, "i686"),
             error => panic!("Unexpected error: {:?}", error),
@@ -238,7 +251,7 @@ mod tests {
         // Compile for win32
         let win32 = Target::new(triple!("i686-pc-windows-gnu"), CpuFeature::for_host());
         let (mut info, translation, inputs) = dummy_compilation_ingredients();
-        let result = compiler.compile_module(&win32, &mut info, &translation, inputs);
+        let result = compiler.compile_module(&win32, &mut info, &translation, inputs, None);
         match result.unwrap_err() {
             CompileError::UnsupportedTarget(name) => assert_eq!(name, "windows"), // Windows should be checked before architecture
             error => panic!("Unexpected error: {:?}", error),
@@ -4,7 +4,9 @@
 use crate::compiler::SinglepassCompiler;
 use loupe::MemoryUsage;
 use std::sync::Arc;
-use wasmer_compiler::{Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, Target};
+use wasmer_compiler::{
+    Compiler, CompilerConfig, CpuFeature, ModuleMiddleware, ProgressCallback, Target,
+};
 use wasmer_types::Features;
 
 #[derive(Debug, Clone, MemoryUsage)]
@@ -13,6 +15,8 @@ pub struct Singlepass {
     pub(crate) enable_stack_check: bool,
     /// The middleware chain.
     pub(crate) middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+    /// The callback receiving the progress of the compilations.
+    pub(crate) progress_callback: Option<ProgressCallback>,
 }
 
 impl Singlepass {
@@ -23,6 +27,7 @@ impl Singlepass {
             enable_nan_canonicalization: true,
             enable_stack_check: false,
             middlewares: vec![],
+            progress_callback: None,
         }
     }
 
@@ -70,6 +75,11 @@ impl CompilerConfig for Singlepass {
     fn push_middleware(&mut self, middleware: Arc<dyn ModuleMiddleware>) {
         self.middlewares.push(middleware);
     }
+
+    /// Registers a callback receiving the progress of the compilations.
+    fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress_callback = Some(callback);
+    }
 }
 
 impl Default for Singlepass {
@@ -4,7 +4,7 @@ use crate::x64_decl::{new_machine_state, X64Register};
 use smallvec::smallvec;
 use smallvec::SmallVec;
 use std::cmp;
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 use wasmer_compiler::wasmparser::Type as WpType;
 
 const NATIVE_PAGE_SIZE: usize = 4096;
@@ -12,8 +12,8 @@ const NATIVE_PAGE_SIZE: usize = 4096;
 struct MachineStackOffset(usize);
 
 pub struct Machine {
-    used_gprs: HashSet<GPR>,
-    used_xmms: HashSet<XMM>,
+    used_gprs: BTreeSet<GPR>,
+    used_xmms: BTreeSet<XMM>,
     stack_offset: MachineStackOffset,
     save_area_offset: Option<MachineStackOffset>,
     pub state: MachineState,
@@ -23,8 +23,8 @@ pub struct Machine {
 impl Machine {
     pub fn new() -> Self {
         Machine {
-            used_gprs: HashSet::new(),
-            used_xmms: HashSet::new(),
+            used_gprs: BTreeSet::new(),
+            used_xmms: BTreeSet::new(),
             stack_offset: MachineStackOffset(0),
             save_area_offset: None,
             state: new_machine_state(),
@@ -0,0 +1,59 @@
+//! Cancellation of compilations.
+
+use crate::lib::std::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use loupe::{MemoryUsage, MemoryUsageTracker};
+
+/// A token cancelling a compilation, given to the engine when the
+/// compilation starts, and cloned to cancel it from another thread.
+///
+/// The cancellation is sticky: once cancelled, the token cancels all the
+/// compilations using it, including the ones started afterwards. A new
+/// token should be created for each compilation.
+///
+/// The compilers check the token while they translate the functions, so
+/// a cancelled compilation still finishes generating the code of the
+/// functions already translated.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    timeout: Option<Duration>,
+}
+
+impl CancellationToken {
+    /// Creates a new `CancellationToken`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new `CancellationToken` which also cancels the
+    /// compilations taking more than `timeout`.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            timeout: Some(timeout),
+        }
+    }
+
+    /// Cancels the compilations using this token, in progress or not.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns the timeout of the compilations, if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+impl MemoryUsage for CancellationToken {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        core::mem::size_of_val(self)
+    }
+}
@@ -1,20 +1,22 @@
 //! This module mainly outputs the `Compiler` trait that custom
 //! compilers will need to implement.
 
+use crate::cancellation::CancellationToken;
 use crate::error::CompileError;
 use crate::function::Compilation;
 use crate::lib::std::boxed::Box;
 use crate::lib::std::sync::Arc;
 use crate::module::CompileModuleInfo;
+use crate::progress::ProgressCallback;
 use crate::target::Target;
 use crate::translator::ModuleMiddleware;
 use crate::FunctionBodyData;
 use crate::ModuleTranslationState;
-use crate::SectionIndex;
+use crate::SymbolRegistry;
 use loupe::MemoryUsage;
 use wasmer_types::entity::PrimaryMap;
-use wasmer_types::{Features, FunctionIndex, LocalFunctionIndex, SignatureIndex};
-use wasmparser::{Validator, WasmFeatures};
+use wasmer_types::{Features, LocalFunctionIndex, ValidationLimits};
+use wasmparser::{ImportSectionEntryType, Parser, Payload, Validator, WasmFeatures};
 
 /// The compiler configuration options.
 pub trait CompilerConfig {
@@ -56,6 +58,13 @@ pub trait CompilerConfig {
         // in case they create an IR that they can verify.
     }
 
+    /// Registers a callback receiving the progress of the compilations,
+    /// to show it while compiling large modules.
+    fn set_progress_callback(&mut self, _callback: ProgressCallback) {
+        // By default we do nothing, each backend will need to customize this
+        // in case it can report its progress.
+    }
+
     /// Gets the custom compiler config
     fn compiler(self: Box<Self>) -> Box<dyn Compiler>;
 
@@ -77,6 +86,95 @@ where
     }
 }
 
+/// Checks that a module respects the validation `limits`, before it's
+/// validated.
+///
+/// The malformed sections are left to the validator.
+fn check_limits(limits: &ValidationLimits, data: &[u8]) -> Result<(), CompileError> {
+    if *limits == ValidationLimits::default() {
+        return Ok(());
+    }
+    if let Some(max) = limits.max_module_size {
+        if data.len() as u64 > max {
+            return Err(CompileError::LimitExceeded(format!(
+                "the module is {} bytes, but the maximum is {} bytes",
+                data.len(),
+                max
+            )));
+        }
+    }
+    let check_table_size = |initial: u32| match limits.max_table_size {
+        Some(max) if initial > max => Err(CompileError::LimitExceeded(format!(
+            "a table has {} elements, but the maximum is {}",
+            initial, max
+        ))),
+        _ => Ok(()),
+    };
+    let mut num_functions = 0u32;
+    for payload in Parser::new(0).parse_all(data) {
+        let payload = match payload {
+            Ok(payload) => payload,
+            Err(_) => break,
+        };
+        match payload {
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    match import.map(|import| import.ty) {
+                        Ok(ImportSectionEntryType::Function(_)) => num_functions += 1,
+                        Ok(ImportSectionEntryType::Table(table)) => {
+                            check_table_size(table.limits.initial)?
+                        }
+                        Ok(_) => {}
+                        Err(_) => break,
+                    }
+                }
+            }
+            Payload::FunctionSection(functions) => {
+                num_functions = num_functions.saturating_add(functions.get_count());
+            }
+            Payload::TableSection(tables) => {
+                for table in tables {
+                    match table {
+                        Ok(table) => check_table_size(table.limits.initial)?,
+                        Err(_) => break,
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let max = match limits.max_locals_per_function {
+                    Some(max) => max,
+                    None => continue,
+                };
+                let mut locals = match body.get_locals_reader() {
+                    Ok(locals) => locals,
+                    Err(_) => break,
+                };
+                let mut num_locals = 0u32;
+                for _ in 0..locals.get_count() {
+                    match locals.read() {
+                        Ok((count, _)) => num_locals = num_locals.saturating_add(count),
+                        Err(_) => break,
+                    }
+                }
+                if num_locals > max {
+                    return Err(CompileError::LimitExceeded(format!(
+                        "a function declares {} locals, but the maximum is {}",
+                        num_locals, max
+                    )));
+                }
+            }
+            _ => {}
+        }
+    }
+    match limits.max_functions {
+        Some(max) if num_functions > max => Err(CompileError::LimitExceeded(format!(
+            "the module has {} functions, but the maximum is {}",
+            num_functions, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
 /// An implementation of a Compiler from parsed WebAssembly module to Compiled native code.
 pub trait Compiler: Send + MemoryUsage {
     /// Validates a module.
@@ -102,13 +200,15 @@ pub trait Compiler: Send + MemoryUsage {
             deterministic_only: false,
         };
         validator.wasm_features(wasm_features);
+        check_limits(&features.limits, data)?;
         validator
             .validate_all(data)
             .map_err(|e| CompileError::Validate(format!("{}", e)))?;
         Ok(())
     }
 
-    /// Compiles a parsed module.
+    /// Compiles a parsed module, until the `cancellation_token` cancels
+    /// the compilation.
     ///
     /// It returns the [`Compilation`] or a [`CompileError`].
     fn compile_module<'data, 'module>(
@@ -118,6 +218,7 @@ pub trait Compiler: Send + MemoryUsage {
         module_translation: &ModuleTranslationState,
         // The list of function bodies
         function_body_inputs: PrimaryMap<LocalFunctionIndex, FunctionBodyData<'data>>,
+        cancellation_token: Option<&CancellationToken>,
     ) -> Result<Compilation, CompileError>;
 
     /// Compiles a module into a native object file.
@@ -140,30 +241,3 @@ pub trait Compiler: Send + MemoryUsage {
     /// Get the middlewares for this compiler
     fn get_middlewares(&self) -> &[Arc<dyn ModuleMiddleware>];
 }
-
-/// The kinds of wasmer_types objects that might be found in a native object file.
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub enum Symbol {
-    /// A function defined in the wasm.
-    LocalFunction(LocalFunctionIndex),
-
-    /// A wasm section.
-    Section(SectionIndex),
-
-    /// The function call trampoline for a given signature.
-    FunctionCallTrampoline(SignatureIndex),
-
-    /// The dynamic function trampoline for a given function.
-    DynamicFunctionTrampoline(FunctionIndex),
-}
-
-/// This trait facilitates symbol name lookups in a native object file.
-pub trait SymbolRegistry: Send + Sync {
-    /// Given a `Symbol` it returns the name for that symbol in the object file
-    fn symbol_to_name(&self, symbol: Symbol) -> String;
-
-    /// Given a name it returns the `Symbol` for that name in the object file
-    ///
-    /// This function is the inverse of [`SymbolRegistry::symbol_to_name`]
-    fn name_to_symbol(&self, name: &str) -> Option<Symbol>;
-}
@@ -40,6 +40,27 @@ pub enum CompileError {
     /// Insufficient resources available for execution.
     #[cfg_attr(feature = "std", error("Insufficient resources: {0}"))]
     Resource(String),
+
+    /// The module exceeds one of the [`ValidationLimits`] of the features.
+    ///
+    /// [`ValidationLimits`]: wasmer_types::ValidationLimits
+    #[cfg_attr(feature = "std", error("Validation limit exceeded: {0}"))]
+    LimitExceeded(String),
+
+    /// The compilation was cancelled with its [`CancellationToken`].
+    ///
+    /// [`CancellationToken`]: crate::CancellationToken
+    #[cfg_attr(feature = "std", error("Compilation cancelled: {0}"))]
+    Cancelled(String),
+
+    /// The engine is headless: it has no compiler attached, so it can
+    /// only load modules compiled ahead of time. Contains the name of
+    /// the engine.
+    #[cfg_attr(
+        feature = "std",
+        error("The {0} is headless, so it can only execute already compiled modules")
+    )]
+    Headless(String),
 }
 
 impl From<WasmError> for CompileError {
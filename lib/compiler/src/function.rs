@@ -8,6 +8,7 @@ use crate::lib::std::vec::Vec;
 use crate::section::{CustomSection, SectionIndex};
 use crate::trap::TrapInformation;
 use crate::{CompiledFunctionUnwindInfo, FunctionAddressMap, JumpTableOffsets, Relocation};
+use core::time::Duration;
 use loupe::MemoryUsage;
 #[cfg(feature = "enable-rkyv")]
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
@@ -155,6 +156,10 @@ pub struct Compilation {
 
     /// Section ids corresponding to the Dwarf debug info
     debug: Option<Dwarf>,
+
+    /// The time each function took to compile, if measured.
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    function_compile_times: PrimaryMap<LocalFunctionIndex, Duration>,
 }
 
 impl Compilation {
@@ -172,9 +177,19 @@ impl Compilation {
             function_call_trampolines,
             dynamic_function_trampolines,
             debug,
+            function_compile_times: PrimaryMap::new(),
         }
     }
 
+    /// Sets the time each function took to compile.
+    pub fn with_function_compile_times(
+        mut self,
+        function_compile_times: PrimaryMap<LocalFunctionIndex, Duration>,
+    ) -> Self {
+        self.function_compile_times = function_compile_times;
+        self
+    }
+
     /// Gets the bytes of a single function
     pub fn get(&self, func: LocalFunctionIndex) -> &CompiledFunction {
         &self.functions[func]
@@ -249,6 +264,12 @@ impl Compilation {
     pub fn get_debug(&self) -> Option<Dwarf> {
         self.debug.clone()
     }
+
+    /// Gets the time each function took to compile, which is empty if the
+    /// compiler didn't measure it.
+    pub fn get_function_compile_times(&self) -> &PrimaryMap<LocalFunctionIndex, Duration> {
+        &self.function_compile_times
+    }
 }
 
 impl<'a> IntoIterator for &'a Compilation {
@@ -50,12 +50,15 @@ mod lib {
 }
 
 mod address_map;
+mod cancellation;
 #[cfg(feature = "translator")]
 mod compiler;
 mod error;
 mod function;
 mod jump_table;
 mod module;
+#[cfg(feature = "translator")]
+mod progress;
 mod relocation;
 mod target;
 mod trap;
@@ -65,10 +68,12 @@ mod unwind;
 mod translator;
 mod section;
 mod sourceloc;
+mod symbols;
 
 pub use crate::address_map::{FunctionAddressMap, InstructionAddressMap};
+pub use crate::cancellation::CancellationToken;
 #[cfg(feature = "translator")]
-pub use crate::compiler::{Compiler, CompilerConfig, Symbol, SymbolRegistry};
+pub use crate::compiler::{Compiler, CompilerConfig};
 pub use crate::error::{
     CompileError, MiddlewareError, ParseCpuFeatureError, WasmError, WasmResult,
 };
@@ -78,9 +83,12 @@ pub use crate::function::{
 };
 pub use crate::jump_table::{JumpTable, JumpTableOffsets};
 pub use crate::module::CompileModuleInfo;
+#[cfg(feature = "translator")]
+pub use crate::progress::{Cancellation, CompilationProgress, ProgressCallback, ProgressReporter};
 pub use crate::relocation::{Relocation, RelocationKind, RelocationTarget, Relocations};
 pub use crate::section::{CustomSection, CustomSectionProtection, SectionBody, SectionIndex};
 pub use crate::sourceloc::SourceLoc;
+pub use crate::symbols::{Symbol, SymbolRegistry};
 pub use crate::target::{
     Architecture, BinaryFormat, CallingConvention, CpuFeature, Endianness, OperatingSystem,
     PointerWidth, Target, Triple,
@@ -94,7 +102,7 @@ pub use crate::translator::{
 pub use crate::trap::TrapInformation;
 pub use crate::unwind::CompiledFunctionUnwindInfo;
 
-pub use wasmer_types::Features;
+pub use wasmer_types::{Features, ValidationLimits};
 
 #[cfg(feature = "translator")]
 /// wasmparser is exported as a module to slim compiler dependencies
@@ -0,0 +1,317 @@
+//! Reporting of the progress of compilations, for the embedders to show
+//! it while compiling large modules, and their cancellation.
+
+use crate::lib::std::fmt;
+use crate::lib::std::sync::Arc;
+use crate::{CancellationToken, CompileError, FunctionBodyData};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::sync::Mutex;
+use std::time::Instant;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::LocalFunctionIndex;
+
+/// The progress of the compilation of the functions of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompilationProgress {
+    /// The number of functions compiled so far.
+    pub functions_compiled: usize,
+    /// The number of functions defined by the module.
+    pub total_functions: usize,
+    /// The size of the bodies of the functions compiled so far, in bytes.
+    pub bytes_compiled: usize,
+    /// The size of the bodies of all the functions, in bytes.
+    pub total_bytes: usize,
+}
+
+/// A callback receiving the [`CompilationProgress`] of the compilations,
+/// registered with [`CompilerConfig::set_progress_callback`].
+///
+/// It's called after each function is compiled. As the functions can be
+/// compiled in parallel, it can be called from several threads at once,
+/// and the calls can be out of order.
+///
+/// [`CompilerConfig::set_progress_callback`]: crate::CompilerConfig::set_progress_callback
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(CompilationProgress) + Send + Sync>);
+
+impl ProgressCallback {
+    /// Creates a new `ProgressCallback` calling `callback`.
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(CompilationProgress) + Send + Sync + 'static,
+    {
+        Self(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProgressCallback").finish()
+    }
+}
+
+impl MemoryUsage for ProgressCallback {
+    fn size_of_val(&self, _: &mut dyn MemoryUsageTracker) -> usize {
+        core::mem::size_of_val(self)
+    }
+}
+
+/// The cancellation of a compilation in progress, by its
+/// [`CancellationToken`] or its timeout.
+///
+/// It's cloned into the readers of the function bodies, for them to stop
+/// translating the functions of a cancelled compilation.
+#[derive(Clone, Debug)]
+pub struct Cancellation {
+    token: CancellationToken,
+    deadline: Option<Instant>,
+}
+
+impl Cancellation {
+    /// Creates the cancellation of a compilation starting now.
+    pub fn new(token: &CancellationToken) -> Self {
+        Self {
+            token: token.clone(),
+            deadline: token.timeout().map(|timeout| Instant::now() + timeout),
+        }
+    }
+
+    /// Returns an error if the compilation was cancelled, or if its
+    /// timeout expired.
+    pub fn check(&self) -> Result<(), CompileError> {
+        if self.token.is_cancelled() {
+            return Err(CompileError::Cancelled(
+                "the compilation was cancelled".to_string(),
+            ));
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(CompileError::Cancelled(format!(
+                "the compilation took more than {:?}",
+                self.token.timeout().unwrap()
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Counts the functions compiled by a compiler, reports them to the
+/// [`ProgressCallback`] if there is one, checks the [`CancellationToken`]
+/// if there is one, and measures the compile time of the functions.
+pub struct ProgressReporter<'a> {
+    callback: Option<&'a ProgressCallback>,
+    cancellation: Option<Cancellation>,
+    compile_times: Mutex<Vec<(LocalFunctionIndex, Duration)>>,
+    total_functions: usize,
+    total_bytes: usize,
+    functions_compiled: AtomicUsize,
+    bytes_compiled: AtomicUsize,
+}
+
+impl<'a> ProgressReporter<'a> {
+    /// Creates a new `ProgressReporter` for the compilation of the
+    /// `function_body_inputs`, starting now.
+    pub fn new(
+        callback: Option<&'a ProgressCallback>,
+        cancellation_token: Option<&'a CancellationToken>,
+        function_body_inputs: &PrimaryMap<LocalFunctionIndex, FunctionBodyData<'_>>,
+    ) -> Self {
+        Self {
+            callback,
+            cancellation: cancellation_token.map(Cancellation::new),
+            compile_times: Mutex::new(Vec::with_capacity(function_body_inputs.len())),
+            total_functions: function_body_inputs.len(),
+            total_bytes: function_body_inputs
+                .values()
+                .map(|input| input.data.len())
+                .sum(),
+            functions_compiled: AtomicUsize::new(0),
+            bytes_compiled: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the cancellation of the compilation, to be given to the
+    /// readers of the function bodies, if it can be cancelled.
+    pub fn cancellation(&self) -> Option<Cancellation> {
+        self.cancellation.clone()
+    }
+
+    /// Returns an error if the compilation was cancelled, or if its
+    /// timeout expired.
+    pub fn check_cancelled(&self) -> Result<(), CompileError> {
+        match &self.cancellation {
+            Some(cancellation) => cancellation.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the error of the cancellation if the compilation was
+    /// cancelled, and `error` otherwise.
+    ///
+    /// The readers of the function bodies stop with an error of their
+    /// own once the compilation is cancelled, which is replaced by the
+    /// one of the cancellation.
+    pub fn or_cancelled(&self, error: impl Into<CompileError>) -> CompileError {
+        match self.check_cancelled() {
+            Err(cancelled) => cancelled,
+            Ok(()) => error.into(),
+        }
+    }
+
+    /// Records that the compilation of a function started, returning the
+    /// time it started at, or an error if the compilation was cancelled.
+    pub fn function_started(&self) -> Result<Instant, CompileError> {
+        self.check_cancelled()?;
+        Ok(Instant::now())
+    }
+
+    /// Records that the function at `index`, whose body is `input`, was
+    /// compiled after starting at `started`.
+    pub fn function_compiled(
+        &self,
+        index: LocalFunctionIndex,
+        input: &FunctionBodyData<'_>,
+        started: Instant,
+    ) {
+        let compile_time = started.elapsed();
+        self.compile_times
+            .lock()
+            .unwrap()
+            .push((index, compile_time));
+        let callback = match self.callback {
+            Some(callback) => callback,
+            None => return,
+        };
+        let functions_compiled = self.functions_compiled.fetch_add(1, Ordering::SeqCst) + 1;
+        let bytes_compiled = self
+            .bytes_compiled
+            .fetch_add(input.data.len(), Ordering::SeqCst)
+            + input.data.len();
+        (callback.0)(CompilationProgress {
+            functions_compiled,
+            total_functions: self.total_functions,
+            bytes_compiled,
+            total_bytes: self.total_bytes,
+        });
+    }
+
+    /// Returns the compile times of the functions, once they're all
+    /// compiled.
+    pub fn into_function_compile_times(self) -> PrimaryMap<LocalFunctionIndex, Duration> {
+        let mut compile_times = self.compile_times.into_inner().unwrap();
+        compile_times.sort_unstable_by_key(|&(index, _)| index);
+        compile_times
+            .into_iter()
+            .map(|(_, compile_time)| compile_time)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FunctionBinaryReader, MiddlewareBinaryReader};
+
+    #[test]
+    fn report_progress() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let callback = {
+            let reports = reports.clone();
+            ProgressCallback::new(move |progress| reports.lock().unwrap().push(progress))
+        };
+        let mut function_body_inputs = PrimaryMap::new();
+        function_body_inputs.push(FunctionBodyData {
+            data: &[0, 0x0b],
+            module_offset: 0,
+        });
+        function_body_inputs.push(FunctionBodyData {
+            data: &[0, 0x01, 0x0b],
+            module_offset: 2,
+        });
+
+        let reporter = ProgressReporter::new(Some(&callback), None, &function_body_inputs);
+        for (index, input) in function_body_inputs.iter() {
+            let started = reporter.function_started().unwrap();
+            reporter.function_compiled(index, input, started);
+        }
+        assert_eq!(
+            *reports.lock().unwrap(),
+            vec![
+                CompilationProgress {
+                    functions_compiled: 1,
+                    total_functions: 2,
+                    bytes_compiled: 2,
+                    total_bytes: 5,
+                },
+                CompilationProgress {
+                    functions_compiled: 2,
+                    total_functions: 2,
+                    bytes_compiled: 5,
+                    total_bytes: 5,
+                },
+            ]
+        );
+
+        // Without a callback, nothing is counted
+        let reporter = ProgressReporter::new(None, None, &function_body_inputs);
+        let index = LocalFunctionIndex::from_u32(0);
+        reporter.function_compiled(index, &function_body_inputs[index], Instant::now());
+        assert_eq!(reporter.functions_compiled.load(Ordering::SeqCst), 0);
+        // But the compile times are measured
+        assert_eq!(reporter.into_function_compile_times().len(), 1);
+    }
+
+    #[test]
+    fn cancel_compilations() {
+        let function_body_inputs = PrimaryMap::new();
+        let token = CancellationToken::new();
+        let reporter = ProgressReporter::new(None, Some(&token), &function_body_inputs);
+        assert!(reporter.check_cancelled().is_ok());
+        token.clone().cancel();
+        match reporter.check_cancelled() {
+            Err(CompileError::Cancelled(message)) => {
+                assert_eq!(message, "the compilation was cancelled")
+            }
+            _ => panic!("the compilation should be cancelled"),
+        }
+        // The cancellation is sticky
+        let reporter = ProgressReporter::new(None, Some(&token), &function_body_inputs);
+        assert!(reporter.check_cancelled().is_err());
+        // And the errors of the readers are replaced by it
+        assert!(matches!(
+            reporter.or_cancelled(CompileError::Codegen("reader".to_string())),
+            CompileError::Cancelled(_)
+        ));
+
+        let token = CancellationToken::with_timeout(Duration::from_millis(0));
+        let reporter = ProgressReporter::new(None, Some(&token), &function_body_inputs);
+        assert!(matches!(
+            reporter.check_cancelled(),
+            Err(CompileError::Cancelled(_))
+        ));
+        let token = CancellationToken::with_timeout(Duration::from_secs(3600));
+        let reporter = ProgressReporter::new(None, Some(&token), &function_body_inputs);
+        assert!(reporter.check_cancelled().is_ok());
+    }
+
+    #[test]
+    fn cancel_while_reading() {
+        // A function without locals made of 4096 `nop`s
+        let mut body = vec![0];
+        body.resize(4097, 0x01);
+        body.push(0x0b);
+
+        let token = CancellationToken::new();
+        let mut reader = MiddlewareBinaryReader::new_with_offset(&body, 0);
+        reader.set_cancellation(Some(Cancellation::new(&token)));
+        assert_eq!(reader.read_local_count().unwrap(), 0);
+        for _ in 0..1000 {
+            reader.read_operator().unwrap();
+        }
+        token.cancel();
+        // The cancellation is checked every 1024 operators
+        let operators_read = (0..3096).position(|_| reader.read_operator().is_err());
+        assert!(operators_read.unwrap() < 1024);
+    }
+}
@@ -54,6 +54,8 @@ pub enum RelocationKind {
     // RiscvCall,
     /// Elf x86_64 32 bit signed PC relative offset to two GOT entries for GD symbol.
     ElfX86_64TlsGd,
+    /// s390x PC-relative 4-byte offset, counted in halfwords
+    S390xPCRel32Dbl,
     // /// Mach-O x86_64 32 bit signed PC relative offset to a `__thread_vars` entry.
     // MachOX86_64Tlv,
 }
@@ -73,6 +75,7 @@ impl fmt::Display for RelocationKind {
             Self::X86GOTPCRel4 => write!(f, "GOTPCRel4"),
             Self::Arm32Call | Self::Arm64Call => write!(f, "Call"),
             Self::ElfX86_64TlsGd => write!(f, "ElfX86_64TlsGd"),
+            Self::S390xPCRel32Dbl => write!(f, "PCRel32Dbl"),
             // Self::MachOX86_64Tlv => write!(f, "MachOX86_64Tlv"),
         }
     }
@@ -155,6 +158,14 @@ impl Relocation {
                     .wrapping_add(reloc_addend as u32);
                 (reloc_address, reloc_delta_u32 as u64)
             }
+            RelocationKind::S390xPCRel32Dbl => {
+                let reloc_address = start + self.offset as usize;
+                let reloc_addend = self.addend as i64;
+                let reloc_delta = (target_func_address as i64)
+                    .wrapping_sub(reloc_address as i64)
+                    .wrapping_add(reloc_addend);
+                (reloc_address, ((reloc_delta >> 1) as u32) as u64)
+            }
             // RelocationKind::X86PCRelRodata4 => {
             //     (start, target_func_address)
             // }
@@ -0,0 +1,33 @@
+//! Symbols of the functions, sections and trampolines of a module in a
+//! native object file.
+
+use crate::lib::std::string::String;
+use crate::SectionIndex;
+use wasmer_types::{FunctionIndex, LocalFunctionIndex, SignatureIndex};
+
+/// The kinds of wasmer_types objects that might be found in a native object file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Symbol {
+    /// A function defined in the wasm.
+    LocalFunction(LocalFunctionIndex),
+
+    /// A wasm section.
+    Section(SectionIndex),
+
+    /// The function call trampoline for a given signature.
+    FunctionCallTrampoline(SignatureIndex),
+
+    /// The dynamic function trampoline for a given function.
+    DynamicFunctionTrampoline(FunctionIndex),
+}
+
+/// This trait facilitates symbol name lookups in a native object file.
+pub trait SymbolRegistry: Send + Sync {
+    /// Given a `Symbol` it returns the name for that symbol in the object file
+    fn symbol_to_name(&self, symbol: Symbol) -> String;
+
+    /// Given a name it returns the `Symbol` for that name in the object file
+    ///
+    /// This function is the inverse of [`SymbolRegistry::symbol_to_name`]
+    fn name_to_symbol(&self, name: &str) -> Option<Symbol>;
+}
@@ -40,6 +40,7 @@ pub enum CpuFeature {
     AVX512F,
     LZCNT,
     // ARM features
+    NEON,
     // Risc-V features
 }
 
@@ -93,7 +94,13 @@ impl CpuFeature {
         }
         features
     }
-    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    #[cfg(target_arch = "aarch64")]
+    /// Retrieves the features for the current Host
+    pub fn for_host() -> EnumSet<Self> {
+        // NEON is mandatory on AArch64
+        EnumSet::only(Self::NEON)
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
     /// Retrieves the features for the current Host
     pub fn for_host() -> EnumSet<Self> {
         // We default to an empty hash set
@@ -132,6 +139,7 @@ impl FromStr for CpuFeature {
             "avx512vl" => Ok(Self::AVX512VL),
             "avx512f" => Ok(Self::AVX512F),
             "lzcnt" => Ok(Self::LZCNT),
+            "neon" => Ok(Self::NEON),
             _ => Err(ParseCpuFeatureError::Missing(s.to_string())),
         }
     }
@@ -154,6 +162,7 @@ impl ToString for CpuFeature {
             Self::AVX512VL => "avx512vl",
             Self::AVX512F => "avx512f",
             Self::LZCNT => "lzcnt",
+            Self::NEON => "neon",
         }
         .to_string()
     }
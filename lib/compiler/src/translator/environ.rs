@@ -6,8 +6,9 @@ use super::state::ModuleTranslationState;
 use crate::lib::std::borrow::ToOwned;
 use crate::lib::std::string::ToString;
 use crate::lib::std::{boxed::Box, string::String, vec::Vec};
-use crate::wasmparser::{Operator, Range, Type};
+use crate::wasmparser::{BinaryReader, Operator, Range, Type};
 use crate::{WasmError, WasmResult};
+use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
 use wasmer_types::entity::PrimaryMap;
@@ -18,7 +19,7 @@ use wasmer_types::{
     LocalFunctionIndex, MemoryIndex, MemoryType, SignatureIndex, TableIndex, TableInitializer,
     TableType,
 };
-use wasmer_vm::ModuleInfo;
+use wasmer_vm::{FunctionBodyInfo, ModuleInfo};
 
 /// Contains function data: bytecode and its offset in the module.
 #[derive(Hash)]
@@ -80,6 +81,9 @@ pub struct ModuleEnvironment<'data> {
     /// The result to be filled in.
     pub result: ModuleInfoTranslation<'data>,
     imports: u32,
+    /// The names of the imports, exports and functions, to share the
+    /// repeated ones.
+    names: HashSet<Arc<str>>,
 }
 
 impl<'data> ModuleEnvironment<'data> {
@@ -93,6 +97,7 @@ impl<'data> ModuleEnvironment<'data> {
                 module_translation_state: None,
             },
             imports: 0,
+            names: HashSet::new(),
         }
     }
 
@@ -106,10 +111,7 @@ impl<'data> ModuleEnvironment<'data> {
     }
 
     pub(crate) fn declare_export(&mut self, export: ExportIndex, name: &str) -> WasmResult<()> {
-        self.result
-            .module
-            .exports
-            .insert(String::from(name), export);
+        self.result.module.exports.insert(self.intern(name), export);
         Ok(())
     }
 
@@ -119,13 +121,21 @@ impl<'data> ModuleEnvironment<'data> {
         module: &str,
         field: &str,
     ) -> WasmResult<()> {
-        self.result.module.imports.insert(
-            (String::from(module), String::from(field), self.imports),
-            import,
-        );
+        let key = (self.intern(module), self.intern(field), self.imports);
+        self.result.module.imports.insert(key, import);
         Ok(())
     }
 
+    /// Returns the shared copy of `name`.
+    fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(name) = self.names.get(name) {
+            return name.clone();
+        }
+        let name: Arc<str> = name.into();
+        self.names.insert(name.clone());
+        name
+    }
+
     pub(crate) fn reserve_signatures(&mut self, num: u32) -> WasmResult<()> {
         self.result
             .module
@@ -402,6 +412,17 @@ impl<'data> ModuleEnvironment<'data> {
         body_bytes: &'data [u8],
         body_offset: usize,
     ) -> WasmResult<()> {
+        let mut reader = BinaryReader::new_with_offset(body_bytes, body_offset);
+        let mut num_locals = 0u32;
+        for _ in 0..reader.read_var_u32()? {
+            let count = reader.read_var_u32()?;
+            reader.read_type()?;
+            num_locals = num_locals.saturating_add(count);
+        }
+        self.result.module.function_bodies.push(FunctionBodyInfo {
+            size: u32::try_from(body_bytes.len()).unwrap(),
+            num_locals,
+        });
         self.result.function_body_inputs.push(FunctionBodyData {
             data: body_bytes,
             module_offset: body_offset,
@@ -467,10 +488,8 @@ impl<'data> ModuleEnvironment<'data> {
         func_index: FunctionIndex,
         name: &'data str,
     ) -> WasmResult<()> {
-        self.result
-            .module
-            .function_names
-            .insert(func_index, name.to_string());
+        let name = self.intern(name);
+        self.result.module.function_names.insert(func_index, name);
         Ok(())
     }
 
@@ -10,9 +10,13 @@ use wasmer_types::LocalFunctionIndex;
 use wasmer_vm::ModuleInfo;
 use wasmparser::{BinaryReader, Operator, Range, Type};
 
-use crate::error::{MiddlewareError, WasmResult};
+use crate::error::{MiddlewareError, WasmError, WasmResult};
+use crate::progress::Cancellation;
 use crate::translator::environ::FunctionBinaryReader;
 
+/// The number of operators read between two checks of the cancellation.
+const CANCELLATION_CHECK_INTERVAL: u32 = 1024;
+
 /// A shared builder for function middlewares.
 pub trait ModuleMiddleware: Debug + Send + Sync + MemoryUsage {
     /// Generates a `FunctionMiddleware` for a given function.
@@ -50,6 +54,13 @@ pub struct MiddlewareBinaryReader<'a> {
 
     /// The backing middleware chain for this reader.
     chain: Vec<Box<dyn FunctionMiddleware>>,
+
+    /// The cancellation of the compilation, checked while reading.
+    cancellation: Option<Cancellation>,
+
+    /// The number of operators read, to check the cancellation
+    /// periodically.
+    operators_read: u32,
 }
 
 /// The state of the binary reader. Exposed to middlewares to push their outputs.
@@ -122,6 +133,8 @@ impl<'a> MiddlewareBinaryReader<'a> {
                 pending_operations: VecDeque::new(),
             },
             chain: vec![],
+            cancellation: None,
+            operators_read: 0,
         }
     }
 
@@ -129,6 +142,12 @@ impl<'a> MiddlewareBinaryReader<'a> {
     pub fn set_middleware_chain(&mut self, stages: Vec<Box<dyn FunctionMiddleware>>) {
         self.chain = stages;
     }
+
+    /// Sets the cancellation of the compilation, making the reader fail
+    /// once it's cancelled.
+    pub fn set_cancellation(&mut self, cancellation: Option<Cancellation>) {
+        self.cancellation = cancellation;
+    }
 }
 
 impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
@@ -143,6 +162,15 @@ impl<'a> FunctionBinaryReader<'a> for MiddlewareBinaryReader<'a> {
     }
 
     fn read_operator(&mut self) -> WasmResult<Operator<'a>> {
+        if let Some(cancellation) = &self.cancellation {
+            self.operators_read = self.operators_read.wrapping_add(1);
+            if self.operators_read % CANCELLATION_CHECK_INTERVAL == 0 {
+                cancellation
+                    .check()
+                    .map_err(|error| WasmError::Generic(error.to_string()))?;
+            }
+        }
+
         if self.chain.is_empty() {
             // We short-circuit in case no chain is used
             return Ok(self.state.inner.read_operator()?);
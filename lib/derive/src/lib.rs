@@ -1,11 +1,12 @@
 extern crate proc_macro;
 
 use proc_macro2::TokenStream;
-use proc_macro_error::{abort, proc_macro_error, set_dummy};
+use proc_macro_error::{abort, emit_error, proc_macro_error, set_dummy};
 use quote::{quote, quote_spanned, ToTokens};
 use syn::{spanned::Spanned, *};
 
 mod parse;
+mod value_type;
 
 use crate::parse::WasmerAttr;
 
@@ -17,6 +18,14 @@ pub fn derive_wasmer_env(input: proc_macro::TokenStream) -> proc_macro::TokenStr
     gen.into()
 }
 
+#[proc_macro_error]
+#[proc_macro_derive(ValueType)]
+pub fn derive_value_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+    let gen = value_type::impl_value_type(&input);
+    gen.into()
+}
+
 fn impl_wasmer_env_for_struct(
     name: &Ident,
     data: &DataStruct,
@@ -24,25 +33,53 @@ fn impl_wasmer_env_for_struct(
     _attrs: &[Attribute],
 ) -> TokenStream {
     let (trait_methods, helper_methods) = derive_struct_fields(data);
-    let lifetimes_and_generics = generics.params.clone();
-    let where_clause = generics.where_clause.clone();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let env_where_clause = env_where_clause(name, generics);
     quote! {
-        impl < #lifetimes_and_generics > ::wasmer::WasmerEnv for #name < #lifetimes_and_generics > #where_clause{
+        impl #impl_generics ::wasmer::WasmerEnv for #name #ty_generics #env_where_clause {
             #trait_methods
         }
 
         #[allow(dead_code)]
-        impl < #lifetimes_and_generics > #name < #lifetimes_and_generics > #where_clause {
+        impl #impl_generics #name #ty_generics #where_clause {
             #helper_methods
         }
     }
 }
 
+/// The where clause of the `WasmerEnv` impl: the one of the struct, with the
+/// supertraits of `WasmerEnv` required from the struct when it's generic over
+/// types, since they depend on its type parameters.
+fn env_where_clause(name: &Ident, generics: &Generics) -> Option<WhereClause> {
+    let mut where_clause = generics
+        .where_clause
+        .clone()
+        .unwrap_or_else(|| WhereClause {
+            where_token: Default::default(),
+            predicates: Default::default(),
+        });
+    if generics.type_params().next().is_some() {
+        let (_, ty_generics, _) = generics.split_for_impl();
+        where_clause.predicates.push(parse_quote! {
+            #name #ty_generics: ::std::clone::Clone + ::std::marker::Send + ::std::marker::Sync
+        });
+    }
+    if where_clause.predicates.is_empty() {
+        None
+    } else {
+        Some(where_clause)
+    }
+}
+
 fn impl_wasmer_env(input: &DeriveInput) -> TokenStream {
     let struct_name = &input.ident;
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+    let where_clause = env_where_clause(struct_name, &input.generics);
 
+    // Keeps the errors from the derive from cascading into the uses of the
+    // struct as an env
     set_dummy(quote! {
-        impl ::wasmer::WasmerEnv for #struct_name {
+        impl #impl_generics ::wasmer::WasmerEnv for #struct_name #ty_generics #where_clause {
             fn init_with_instance(&mut self, instance: &::wasmer::Instance) -> Result<(), ::wasmer::HostEnvInitError> {
                 Ok(())
             }
@@ -53,16 +90,17 @@ fn impl_wasmer_env(input: &DeriveInput) -> TokenStream {
         Data::Struct(ds) => {
             impl_wasmer_env_for_struct(struct_name, ds, &input.generics, &input.attrs)
         }
-        _ => todo!(),
+        Data::Enum(de) => abort!(
+            de.enum_token,
+            "`WasmerEnv` can only be derived for structs";
+            help = "implement `WasmerEnv` manually for enums"
+        ),
+        Data::Union(du) => abort!(
+            du.union_token,
+            "`WasmerEnv` can only be derived for structs";
+            help = "implement `WasmerEnv` manually for unions"
+        ),
     }
-    /*match input.data {
-        Struct(ds /*DataStruct {
-            fields: syn::Fields::Named(ref fields),
-            ..
-        }*/) => ,
-        Enum(ref e) => impl_wasmer_env_for_enum(struct_name, &e.variants, &input.attrs),
-        _ => abort_call_site!("Clap only supports non-tuple structs and enums"),
-    }*/
 }
 
 fn derive_struct_fields(data: &DataStruct) -> (TokenStream, TokenStream) {
@@ -82,23 +120,30 @@ fn derive_struct_fields(data: &DataStruct) -> (TokenStream, TokenStream) {
         touched_fields.push(name.clone());
         let mut wasmer_attr = None;
         for attr in &f.attrs {
-            // if / filter
-            if attr.path.is_ident(&Ident::new("wasmer", attr.span())) {
-                let tokens = attr.tokens.clone();
-                match syn::parse2(tokens) {
-                    Ok(attr) => {
-                        wasmer_attr = Some(attr);
-                        break;
-                    }
-                    Err(e) => {
-                        abort!(attr, "Failed to parse `wasmer` attribute: {}", e);
-                    }
+            if !attr.path.is_ident("wasmer") {
+                continue;
+            }
+            if wasmer_attr.is_some() {
+                abort!(
+                    attr,
+                    "Duplicate `wasmer` attribute";
+                    help = "put all the arguments in a single `#[wasmer(export(...))]`"
+                );
+            }
+            match syn::parse2(attr.tokens.clone()) {
+                Ok(attr) => wasmer_attr = Some(attr),
+                Err(e) => {
+                    abort!(
+                        e.span(),
+                        "Failed to parse `wasmer` attribute: {}", e;
+                        help = "the expected form is `#[wasmer(export(name = \"...\", optional, alias = \"...\"))]`"
+                    );
                 }
             }
         }
 
         if let Some(wasmer_attr) = wasmer_attr {
-            let inner_type = get_identifier(top_level_ty);
+            let inner_type = lazy_init_inner_type(top_level_ty);
             if let Some(name) = &name {
                 let name_ref_str = format!("{}_ref", name);
                 let name_ref = syn::Ident::new(&name_ref_str, name.span());
@@ -218,48 +263,44 @@ fn derive_struct_fields(data: &DataStruct) -> (TokenStream, TokenStream) {
     (trait_methods, helper_methods)
 }
 
-// TODO: name this something that makes sense
-fn get_identifier(ty: &Type) -> TokenStream {
-    match ty {
-        Type::Path(TypePath {
-            path: Path { segments, .. },
-            ..
-        }) => {
-            if let Some(PathSegment { ident, arguments }) = segments.last() {
-                if ident != "LazyInit" {
-                    abort!(
-                        ident,
-                        "WasmerEnv derive expects all `export`s to be wrapped in `LazyInit`"
-                    );
-                }
-                if let PathArguments::AngleBracketed(AngleBracketedGenericArguments {
-                    args, ..
-                }) = arguments
-                {
-                    // TODO: proper error handling
-                    assert_eq!(args.len(), 1);
-                    if let GenericArgument::Type(Type::Path(TypePath {
-                        path: Path { segments, .. },
-                        ..
-                    })) = &args[0]
-                    {
-                        segments
-                            .last()
-                            .expect("there must be at least one segment; TODO: error handling")
-                            .to_token_stream()
-                    } else {
-                        abort!(
-                            &args[0],
-                            "unrecognized type in first generic position on `LazyInit`"
-                        );
-                    }
-                } else {
-                    abort!(arguments, "Expected a generic parameter on `LazyInit`");
-                }
-            } else {
-                abort!(segments, "Unknown type found");
-            }
-        }
-        _ => abort!(ty, "Unrecognized/unsupported type"),
+/// Gets the type `T` of a `LazyInit<T>` field, or fails pointing at the
+/// field when it isn't one.
+fn lazy_init_inner_type(ty: &Type) -> TokenStream {
+    let segment = match ty {
+        Type::Path(TypePath { qself: None, path }) => path.segments.last(),
+        _ => None,
+    };
+    let PathSegment { ident, arguments } = match segment {
+        Some(segment) if segment.ident == "LazyInit" => segment,
+        _ => abort!(
+            ty,
+            "WasmerEnv derive expects all `export`s to be wrapped in `LazyInit`, found `{}`",
+            ty.to_token_stream();
+            help = "change the type of the field to `LazyInit<{}>`", ty.to_token_stream()
+        ),
+    };
+    let args = match arguments {
+        PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }) => args,
+        _ => abort!(
+            ident,
+            "Expected a generic parameter on `LazyInit`";
+            help = "specify the type of the export, like `LazyInit<Memory>`"
+        ),
+    };
+    if args.len() != 1 {
+        emit_error!(
+            args,
+            "`LazyInit` takes 1 generic parameter but {} were supplied",
+            args.len()
+        );
+    }
+    match args.first() {
+        Some(GenericArgument::Type(inner)) => inner.to_token_stream(),
+        Some(other) => abort!(
+            other,
+            "unrecognized type in first generic position on `LazyInit`";
+            help = "specify the type of the export, like `LazyInit<Memory>`"
+        ),
+        None => abort!(ident, "Expected a generic parameter on `LazyInit`"),
     }
 }
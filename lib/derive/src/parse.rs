@@ -37,24 +37,38 @@ impl Parse for ExportOptions {
         let mut aliases: Vec<LitStr> = vec![];
         loop {
             let ident = input.parse::<Ident>()?;
-            let _ = input.parse::<Token![=]>()?;
             let ident_str = ident.to_string();
 
             match ident_str.as_str() {
                 "name" => {
+                    if name.is_some() {
+                        abort!(
+                            ident,
+                            "`name` may only be specified once";
+                            help = "use `alias = \"string\"` for the other names of the export"
+                        );
+                    }
+                    let _ = input.parse::<Token![=]>()?;
                     name = Some(input.parse::<LitStr>()?);
                 }
+                // `optional` alone means `optional = true`
                 "optional" => {
-                    optional = input.parse::<LitBool>()?.value;
+                    optional = if input.peek(Token![=]) {
+                        let _ = input.parse::<Token![=]>()?;
+                        input.parse::<LitBool>()?.value
+                    } else {
+                        true
+                    };
                 }
                 "alias" => {
+                    let _ = input.parse::<Token![=]>()?;
                     let alias = input.parse::<LitStr>()?;
                     aliases.push(alias);
                 }
                 otherwise => {
                     abort!(
                         ident,
-                        "Unrecognized argument in export options: expected `name = \"string\"`, `optional = bool`, or `alias = \"string\"` found `{}`",
+                        "Unrecognized argument in export options: expected `name = \"string\"`, `optional`, `optional = bool`, or `alias = \"string\"` found `{}`",
                         otherwise
                     );
                 }
@@ -0,0 +1,117 @@
+use proc_macro2::TokenStream;
+use proc_macro_error::{abort, abort_call_site, emit_error, set_dummy};
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, *};
+
+pub fn impl_value_type(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    set_dummy(quote! {
+        unsafe impl #impl_generics ::wasmer::ValueType for #name #ty_generics #where_clause {}
+    });
+
+    let fields = match &input.data {
+        Data::Struct(ds) => &ds.fields,
+        Data::Enum(de) => abort!(
+            de.enum_token,
+            "`ValueType` can only be derived for structs";
+            help = "enums have invalid bit patterns, use an integer instead"
+        ),
+        Data::Union(du) => abort!(
+            du.union_token,
+            "`ValueType` can only be derived for structs";
+            help = "implement `ValueType` manually for unions"
+        ),
+    };
+    let transparent = has_repr(&input.attrs, "transparent");
+    if !transparent && !has_repr(&input.attrs, "C") {
+        abort_call_site!(
+            "`ValueType` can only be derived for `#[repr(C)]` or `#[repr(transparent)]` structs";
+            help = "add `#[repr(C)]` to the struct, for its layout to match the one in Wasm memory"
+        );
+    }
+    // The padding of generic structs depends on their parameters, so it
+    // can't be checked once for all of them. Transparent structs have no
+    // padding, being laid out as their only non zero-sized field.
+    if !transparent && !input.generics.params.is_empty() {
+        abort!(
+            input.generics,
+            "`ValueType` can only be derived for generic structs which are `#[repr(transparent)]`";
+            help = "implement `ValueType` manually for the instantiations without padding"
+        );
+    }
+
+    // Every field must be a `ValueType` for all bit patterns to be valid
+    let field_types = fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
+    for ty in &field_types {
+        check_not_a_pointer(ty);
+    }
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| WhereClause {
+        where_token: Default::default(),
+        predicates: Default::default(),
+    });
+    for ty in &field_types {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::wasmer::ValueType));
+    }
+
+    // The padding bytes are uninitialized, so they can't be copied to or
+    // from the memory. The generic structs, being transparent, have none.
+    let padding_check = if input.generics.params.is_empty() {
+        // The error is reported on the struct as "expected an array with a
+        // fixed size of 0 elements, found one with N elements", where N is
+        // the number of padding bytes
+        let no_padding = quote_spanned! {name.span()=>
+            let _: [(); 0] = [(); PADDING_BYTES];
+        };
+        quote! {
+            const _: () = {
+                const PADDING_BYTES: usize = ::std::mem::size_of::<#name>()
+                    - (0 #( + ::std::mem::size_of::<#field_types>() )*);
+                #no_padding
+            };
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        unsafe impl #impl_generics ::wasmer::ValueType for #name #ty_generics #where_clause {}
+
+        #padding_check
+    }
+}
+
+/// Whether the struct is `#[repr(<repr>)]`.
+fn has_repr(attrs: &[Attribute], repr: &str) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("repr"))
+        .filter_map(|attr| attr.parse_meta().ok())
+        .any(|meta| match meta {
+            Meta::List(list) => list.nested.iter().any(|nested| match nested {
+                NestedMeta::Meta(Meta::Path(path)) => path.is_ident(repr),
+                _ => false,
+            }),
+            _ => false,
+        })
+}
+
+/// Reports the fields which are host pointers or references, whose values
+/// are meaningless in the memory of an instance.
+fn check_not_a_pointer(ty: &Type) {
+    match ty {
+        Type::Ptr(_) | Type::Reference(_) | Type::BareFn(_) => emit_error!(
+            ty.span(),
+            "`ValueType` can't be derived for structs with host pointers";
+            help = "use `WasmPtr<T>` for pointers into the memory of the instance"
+        ),
+        Type::Array(array) => check_not_a_pointer(&array.elem),
+        Type::Tuple(tuple) => tuple.elems.iter().for_each(check_not_a_pointer),
+        Type::Paren(paren) => check_not_a_pointer(&paren.elem),
+        Type::Group(group) => check_not_a_pointer(&group.elem),
+        _ => {}
+    }
+}
@@ -1,6 +1,9 @@
 #![allow(dead_code)]
 
-use wasmer::{Function, Global, LazyInit, Memory, NativeFunc, Table, WasmerEnv};
+use wasmer::{
+    Array, Function, Global, LazyInit, Memory, MemoryType, NativeFunc, Store, Table, ValueType,
+    WasmPtr, WasmerEnv,
+};
 
 #[derive(WasmerEnv, Clone)]
 struct MyEnv {
@@ -117,3 +120,105 @@ struct StructWithAliases {
 fn test_derive_with_aliases() {
     assert!(impls_wasmer_env::<StructWithAliases>());
 }
+
+#[derive(WasmerEnv, Clone)]
+struct StructWithOptionalShorthand {
+    #[wasmer(export(optional))]
+    memory: LazyInit<Memory>,
+    #[wasmer(export(name = "real_memory", optional, alias = "_real_memory"))]
+    memory2: LazyInit<Memory>,
+}
+
+#[test]
+fn test_derive_with_optional_shorthand() {
+    assert!(impls_wasmer_env::<StructWithOptionalShorthand>());
+}
+
+#[derive(WasmerEnv, Clone)]
+struct StructWithGenerics<T: Clone, const N: usize> {
+    data: [T; N],
+    #[wasmer(export)]
+    memory: LazyInit<wasmer::Memory>,
+}
+
+#[derive(WasmerEnv, Clone)]
+struct StructWithGenericsAndWhereClause<'a, T>
+where
+    T: Clone + Send,
+{
+    data: &'a [T],
+    #[wasmer(export(optional))]
+    memory: LazyInit<Memory>,
+}
+
+#[test]
+fn test_derive_with_generics() {
+    assert!(impls_wasmer_env::<StructWithGenerics<u8, 4>>());
+    assert!(impls_wasmer_env::<StructWithGenericsAndWhereClause<u32>>());
+}
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(C)]
+struct Iovec {
+    base: WasmPtr<u8, Array>,
+    len: u32,
+}
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(C)]
+struct Request {
+    id: u64,
+    iovecs: [Iovec; 2],
+    next: WasmPtr<Request>,
+    tag: [u8; 4],
+}
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(transparent)]
+struct Wrapper<T: Copy>(T);
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(C)]
+struct ExplicitPadding {
+    a: u8,
+    _padding: [u8; 3],
+    b: u32,
+}
+
+#[test]
+fn test_derive_value_type() {
+    let store = Store::default();
+    let memory = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    memory.write(0x100, b"hello world").unwrap();
+    let request = Request {
+        id: 7,
+        iovecs: [
+            Iovec {
+                base: WasmPtr::new(0x100),
+                len: 5,
+            },
+            Iovec {
+                base: WasmPtr::new(0x106),
+                len: 5,
+            },
+        ],
+        next: WasmPtr::new(0),
+        tag: *b"abcd",
+    };
+    memory.write_value(0x200, request).unwrap();
+
+    let request = WasmPtr::<Request>::new(0x200).deref(&memory).unwrap().get();
+    assert_eq!(request.id, 7);
+    assert_eq!(&request.tag, b"abcd");
+    let strings = request
+        .iovecs
+        .iter()
+        .map(|iovec| iovec.base.get_utf8_string(&memory, iovec.len).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(strings, ["hello", "world"]);
+
+    let wrapped = memory.read_value::<Wrapper<u32>>(0x200).unwrap();
+    assert_eq!(wrapped.0, 7);
+    let padded = memory.read_value::<ExplicitPadding>(0x100).unwrap();
+    assert_eq!((padded.a, padded.b), (b'h', u32::from_le_bytes(*b"o wo")));
+}
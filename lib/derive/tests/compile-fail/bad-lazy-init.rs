@@ -0,0 +1,24 @@
+extern crate wasmer;
+
+use wasmer::{LazyInit, Memory, WasmerEnv};
+
+#[derive(WasmerEnv, Clone)]
+struct DuplicateName {
+    #[wasmer(export(name = "memory", name = "_memory"))] //~ `name` may only be specified once
+    memory: LazyInit<Memory>,
+}
+
+#[derive(WasmerEnv, Clone)]
+struct DuplicateAttribute {
+    #[wasmer(export)]
+    #[wasmer(export(optional))] //~ Duplicate `wasmer` attribute
+    memory: LazyInit<Memory>,
+}
+
+#[derive(WasmerEnv, Clone)]
+struct LazyInitWithoutType {
+    #[wasmer(export)]
+    memory: LazyInit, //~ Expected a generic parameter on `LazyInit`
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+extern crate wasmer;
+
+use wasmer::WasmerEnv;
+
+#[derive(WasmerEnv, Clone)]
+enum NotAStruct { //~ `WasmerEnv` can only be derived for structs
+    A,
+    B,
+}
+
+fn main() {}
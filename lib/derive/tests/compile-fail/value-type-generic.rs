@@ -0,0 +1,12 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(C)]
+struct Pair<T: Copy> { //~ `ValueType` can only be derived for generic structs which are `#[repr(transparent)]`
+    a: u8,
+    b: T,
+}
+
+fn main() {}
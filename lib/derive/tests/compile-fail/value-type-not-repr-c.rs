@@ -0,0 +1,11 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(Copy, Clone, ValueType)] //~ `ValueType` can only be derived for `#[repr(C)]` or `#[repr(transparent)]` structs
+struct NotReprC {
+    a: u32,
+    b: u64,
+}
+
+fn main() {}
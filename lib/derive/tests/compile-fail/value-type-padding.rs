@@ -0,0 +1,12 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(C)]
+struct WithPadding { //~ mismatched types
+    a: u8,
+    b: u32,
+}
+
+fn main() {}
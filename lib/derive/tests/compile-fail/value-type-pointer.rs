@@ -0,0 +1,18 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(C)]
+struct WithPointer {
+    len: u32,
+    data: *const u8, //~ `ValueType` can't be derived for structs with host pointers
+}
+
+#[derive(Copy, Clone, ValueType)]
+#[repr(C)]
+struct WithReferences {
+    values: [&'static u32; 2], //~ `ValueType` can't be derived for structs with host pointers
+}
+
+fn main() {}
@@ -0,0 +1,154 @@
+//! Detection of the ABI an Emscripten module was built for.
+//!
+//! The fastcomp backend, the default before Emscripten 2.0, imports the
+//! memory and the table from `env`, and the syscalls as `___syscallN`
+//! functions taking their arguments through varargs. The upstream LLVM
+//! backend, the default since Emscripten 2.0, exports the memory and the
+//! table, imports the standard streams from `wasi_snapshot_preview1`, and
+//! the syscalls as `__sys_open` (Emscripten 2) or `__syscall_openat`
+//! (Emscripten 3) functions taking their arguments directly. Its errnos are
+//! the ones of WASI instead of Linux.
+use crate::linking::read_leb;
+use std::fmt;
+use wasmer::{ImportObject, Module};
+
+/// The namespace of the WASI functions imported by the upstream ABI.
+pub(crate) const WASI_NAMESPACE: &str = "wasi_snapshot_preview1";
+
+/// The ABI of an Emscripten module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmscriptenAbi {
+    /// The ABI of the fastcomp backend
+    Fastcomp,
+    /// The ABI of the upstream LLVM backend
+    Upstream,
+}
+
+impl Default for EmscriptenAbi {
+    fn default() -> Self {
+        Self::Fastcomp
+    }
+}
+
+impl fmt::Display for EmscriptenAbi {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Fastcomp => "fastcomp",
+            Self::Upstream => "upstream",
+        })
+    }
+}
+
+/// The `emscripten_metadata` section, written by the versions of
+/// Emscripten from 1.38 to 2.0 with `-s EMIT_EMSCRIPTEN_METADATA=1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmscriptenMetadata {
+    /// The version of the format of the section
+    pub version: (u32, u32),
+    /// The version of the ABI
+    pub abi_version: (u32, u32),
+    /// The backend the module was built with
+    pub abi: EmscriptenAbi,
+}
+
+impl EmscriptenMetadata {
+    /// The major version of the format of the section supported.
+    const MAJOR_VERSION: u32 = 0;
+
+    /// Reads the `emscripten_metadata` section of the module, if it has one.
+    pub fn read(module: &Module) -> Result<Option<Self>, String> {
+        let section = match module.custom_sections("emscripten_metadata").next() {
+            Some(section) => section,
+            None => return Ok(None),
+        };
+        let metadata = parse_metadata(&section).ok_or("invalid `emscripten_metadata` section")?;
+        if metadata.version.0 != Self::MAJOR_VERSION {
+            return Err(format!(
+                "unsupported version {}.{} of the `emscripten_metadata` section",
+                metadata.version.0, metadata.version.1
+            ));
+        }
+        Ok(Some(metadata))
+    }
+}
+
+fn parse_metadata(section: &[u8]) -> Option<EmscriptenMetadata> {
+    let mut pos = 0;
+    let version = (read_leb(section, &mut pos)?, read_leb(section, &mut pos)?);
+    let abi_version = (read_leb(section, &mut pos)?, read_leb(section, &mut pos)?);
+    let abi = match read_leb(section, &mut pos)? {
+        0 => EmscriptenAbi::Fastcomp,
+        _ => EmscriptenAbi::Upstream,
+    };
+    Some(EmscriptenMetadata {
+        version,
+        abi_version,
+        abi,
+    })
+}
+
+/// Detects the ABI the Emscripten module was built for, from its metadata
+/// if it has some, or from its imports and exports otherwise.
+pub fn get_emscripten_abi(module: &Module) -> Result<EmscriptenAbi, String> {
+    if let Some(metadata) = EmscriptenMetadata::read(module)? {
+        return Ok(metadata.abi);
+    }
+    let exports_memory = module.imports().memories().next().is_none()
+        && module.exports().memories().next().is_some();
+    let has_upstream_imports = module.imports().any(|import| {
+        import.module() == WASI_NAMESPACE
+            || (import.module() == "env"
+                && (import.name().starts_with("__sys_") || import.name().starts_with("__syscall_")))
+    });
+    Ok(if exports_memory || has_upstream_imports {
+        EmscriptenAbi::Upstream
+    } else {
+        EmscriptenAbi::Fastcomp
+    })
+}
+
+/// Checks that all the imports of the Emscripten module are provided by
+/// `import_object`, to fail with a clear message when it was built with a
+/// version of Emscripten using imports which aren't supported.
+pub fn check_emscripten_imports(
+    module: &Module,
+    import_object: &ImportObject,
+) -> Result<(), String> {
+    let missing = module
+        .imports()
+        .filter(|import| {
+            import_object
+                .get_export(import.module(), import.name())
+                .is_none()
+        })
+        .map(|import| format!("`{}.{}`", import.module(), import.name()))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let version = match EmscriptenMetadata::read(module)? {
+        Some(metadata) => format!(" {}.{}", metadata.abi_version.0, metadata.abi_version.1),
+        None => String::new(),
+    };
+    Err(format!(
+        "this module was built for the {} ABI{} of Emscripten, whose imports {} aren't supported",
+        get_emscripten_abi(module)?,
+        version,
+        missing.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn metadata_section() {
+        // 0.3, ABI 0.29, upstream, followed by the sizes
+        let metadata = parse_metadata(&[0, 3, 0, 29, 1, 128, 2, 10]).unwrap();
+        assert_eq!(metadata.version, (0, 3));
+        assert_eq!(metadata.abi_version, (0, 29));
+        assert_eq!(metadata.abi, EmscriptenAbi::Upstream);
+        assert_eq!(parse_metadata(&[0, 3, 0]), None);
+    }
+}
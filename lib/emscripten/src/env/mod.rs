@@ -69,6 +69,18 @@ pub fn _times(ctx: &EmEnv, buffer: u32) -> u32 {
     0
 }
 
+/// The environment variables of the modules, the ones of the JavaScript
+/// runtime.
+pub(crate) const DEFAULT_VARS: &[[&str; 2]] = &[
+    ["USER", "web_user"],
+    ["LOGNAME", "web_user"],
+    ["PATH", "/"],
+    ["PWD", "/"],
+    ["HOME", "/home/web_user"],
+    ["LANG", "C.UTF-8"],
+    ["_", "thisProgram"],
+];
+
 #[allow(clippy::cast_ptr_alignment)]
 pub fn ___build_environment(ctx: &EmEnv, environ: c_int) {
     debug!("emscripten::___build_environment {}", environ);
@@ -89,18 +101,9 @@ pub fn ___build_environment(ctx: &EmEnv, environ: c_int) {
     };
 
     // *env_ptr = 0;
-    let default_vars = vec![
-        ["USER", "web_user"],
-        ["LOGNAME", "web_user"],
-        ["PATH", "/"],
-        ["PWD", "/"],
-        ["HOME", "/home/web_user"],
-        ["LANG", "C.UTF-8"],
-        ["_", "thisProgram"],
-    ];
     let mut strings = vec![];
     let mut total_size = 0;
-    for [key, val] in &default_vars {
+    for [key, val] in DEFAULT_VARS {
         let line = key.to_string() + "=" + val;
         total_size += line.len();
         strings.push(line);
@@ -3,7 +3,6 @@ use libc::{
     c_int, getenv, getgrnam as libc_getgrnam, getpwnam as libc_getpwnam, putenv, setenv, sysconf,
     unsetenv,
 };
-use std::cell::Cell;
 use std::ffi::CStr;
 use std::mem;
 use std::os::raw::c_char;
@@ -177,19 +176,13 @@ pub fn _getaddrinfo(
     debug!(" => node = {}", unsafe {
         node_ptr
             .deref(&memory)
-            .map(|np| {
-                std::ffi::CStr::from_ptr(np as *const Cell<c_char> as *const c_char)
-                    .to_string_lossy()
-            })
+            .map(|np| std::ffi::CStr::from_ptr(np.as_ptr() as *const c_char).to_string_lossy())
             .unwrap_or(std::borrow::Cow::Borrowed("null"))
     });
     debug!(" => server_str = {}", unsafe {
         service_str_ptr
             .deref(&memory)
-            .map(|np| {
-                std::ffi::CStr::from_ptr(np as *const Cell<c_char> as *const c_char)
-                    .to_string_lossy()
-            })
+            .map(|np| std::ffi::CStr::from_ptr(np.as_ptr() as *const c_char).to_string_lossy())
             .unwrap_or(std::borrow::Cow::Borrowed("null"))
     });
 
@@ -212,13 +205,11 @@ pub fn _getaddrinfo(
     // allocate equivalent memory for res_val_ptr
     let result = unsafe {
         libc::getaddrinfo(
-            (node_ptr
-                .deref(&memory)
-                .map(|m| m as *const Cell<c_char> as *const c_char))
-            .unwrap_or(std::ptr::null()),
+            (node_ptr.deref(&memory).map(|m| m.as_ptr() as *const c_char))
+                .unwrap_or(std::ptr::null()),
             service_str_ptr
                 .deref(&memory)
-                .map(|m| m as *const Cell<c_char> as *const c_char)
+                .map(|m| m.as_ptr() as *const c_char)
                 .unwrap_or(std::ptr::null()),
             hints
                 .as_ref()
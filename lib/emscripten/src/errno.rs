@@ -1,5 +1,11 @@
 // use std::collections::HashMap;
+#[cfg(unix)]
+use crate::env::get_emscripten_data;
 use crate::EmEnv;
+#[cfg(unix)]
+use crate::EmscriptenAbi;
+#[cfg(unix)]
+use libc::c_int;
 
 pub fn ___seterrno(_ctx: &EmEnv, _value: i32) {
     debug!("emscripten::___seterrno {}", _value);
@@ -8,6 +14,164 @@ pub fn ___seterrno(_ctx: &EmEnv, _value: i32) {
     // value
 }
 
+/// The errnos of the host, and the ones of Linux.
+#[cfg(unix)]
+const HOST_ERRNOS: &[(c_int, c_int)] = &[
+    (libc::EPERM, 1),
+    (libc::ENOENT, 2),
+    (libc::EINTR, 4),
+    (libc::EIO, 5),
+    (libc::EBADF, 9),
+    (libc::EAGAIN, 11),
+    (libc::ENOMEM, 12),
+    (libc::EACCES, 13),
+    (libc::EFAULT, 14),
+    (libc::EBUSY, 16),
+    (libc::EEXIST, 17),
+    (libc::EXDEV, 18),
+    (libc::ENOTDIR, 20),
+    (libc::EISDIR, 21),
+    (libc::EINVAL, 22),
+    (libc::ENFILE, 23),
+    (libc::EMFILE, 24),
+    (libc::ENOTTY, 25),
+    (libc::EFBIG, 27),
+    (libc::ENOSPC, 28),
+    (libc::ESPIPE, 29),
+    (libc::EROFS, 30),
+    (libc::EMLINK, 31),
+    (libc::EPIPE, 32),
+    (libc::ERANGE, 34),
+    (libc::ENAMETOOLONG, 36),
+    (libc::ENOSYS, 38),
+    (libc::ENOTEMPTY, 39),
+    (libc::ELOOP, 40),
+    (libc::ENOTSOCK, 88),
+    (libc::EDESTADDRREQ, 89),
+    (libc::EMSGSIZE, 90),
+    (libc::EPROTOTYPE, 91),
+    (libc::ENOPROTOOPT, 92),
+    (libc::EPROTONOSUPPORT, 93),
+    (libc::ESOCKTNOSUPPORT, 94),
+    (libc::EOPNOTSUPP, 95),
+    (libc::EPFNOSUPPORT, 96),
+    (libc::EAFNOSUPPORT, 97),
+    (libc::EADDRINUSE, 98),
+    (libc::EADDRNOTAVAIL, 99),
+    (libc::ENETDOWN, 100),
+    (libc::ENETUNREACH, 101),
+    (libc::ENETRESET, 102),
+    (libc::ECONNABORTED, 103),
+    (libc::ECONNRESET, 104),
+    (libc::ENOBUFS, 105),
+    (libc::EISCONN, 106),
+    (libc::ENOTCONN, 107),
+    (libc::ESHUTDOWN, 108),
+    (libc::ETIMEDOUT, 110),
+    (libc::ECONNREFUSED, 111),
+    (libc::EHOSTDOWN, 112),
+    (libc::EHOSTUNREACH, 113),
+    (libc::EALREADY, 114),
+    (libc::EINPROGRESS, 115),
+];
+
+/// The errnos of Linux, and the ones of WASI, which the modules of the
+/// upstream ABI use. The errnos WASI doesn't have get the closest one.
+#[cfg(unix)]
+const WASI_ERRNOS: &[(c_int, c_int)] = &[
+    (1, 63),
+    (2, 44),
+    (4, 27),
+    (5, 29),
+    (9, 8),
+    (11, 6),
+    (12, 48),
+    (13, 2),
+    (14, 21),
+    (16, 10),
+    (17, 20),
+    (18, 75),
+    (19, 43),
+    (20, 54),
+    (21, 31),
+    (22, 28),
+    (23, 41),
+    (24, 33),
+    (25, 59),
+    (27, 22),
+    (28, 51),
+    (29, 70),
+    (30, 69),
+    (31, 34),
+    (32, 64),
+    (34, 68),
+    (36, 37),
+    (38, 52),
+    (39, 55),
+    (40, 32),
+    (88, 57),
+    (89, 17),
+    (90, 35),
+    (91, 67),
+    (92, 50),
+    (93, 66),
+    (94, 58),
+    (95, 58),
+    (96, 5),
+    (97, 5),
+    (98, 3),
+    (99, 4),
+    (100, 38),
+    (101, 40),
+    (102, 39),
+    (103, 13),
+    (104, 15),
+    (105, 42),
+    (106, 30),
+    (107, 53),
+    (108, 64),
+    (110, 73),
+    (111, 14),
+    (112, 23),
+    (113, 23),
+    (114, 7),
+    (115, 26),
+];
+
+/// Gets the Linux errno of the last error of the host.
+#[cfg(unix)]
+pub(crate) fn last_errno() -> c_int {
+    let errno = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+    debug!("=> os error: {}", std::io::Error::from_raw_os_error(errno));
+    HOST_ERRNOS
+        .iter()
+        .find(|(host, _)| *host == errno)
+        .map(|(_, linux)| *linux)
+        // EIO
+        .unwrap_or(5)
+}
+
+/// Gets the WASI errno of a Linux errno.
+#[cfg(unix)]
+pub(crate) fn wasi_errno(errno: c_int) -> c_int {
+    WASI_ERRNOS
+        .iter()
+        .find(|(linux, _)| *linux == errno)
+        .map(|(_, wasi)| *wasi)
+        // EIO
+        .unwrap_or(29)
+}
+
+/// Gets the errno of the module for a Linux errno: the fastcomp ABI uses
+/// the errnos of Linux, and the upstream one the errnos of WASI.
+#[cfg(unix)]
+pub(crate) fn wasm_errno(ctx: &EmEnv, errno: c_int) -> c_int {
+    match get_emscripten_data(ctx).globals.abi {
+        EmscriptenAbi::Fastcomp => errno,
+        EmscriptenAbi::Upstream => wasi_errno(errno),
+    }
+}
+
 // pub enum ErrnoCodes {
 //     EPERM = 1,
 //     ENOENT = 2,
@@ -3,6 +3,7 @@ use super::process::abort_with_message;
 use libc::c_int;
 // use std::cell::UnsafeCell;
 use crate::EmEnv;
+use std::cell::Cell;
 use std::error::Error;
 use std::fmt;
 
@@ -61,17 +62,154 @@ impl Error for LongJumpRet {}
 #[allow(unreachable_code)]
 pub fn _longjmp(ctx: &EmEnv, env_addr: i32, val: c_int) {
     let val = if val == 0 { 1 } else { val };
-    get_emscripten_data(ctx)
+    let set_threw = get_emscripten_data(ctx)
         .set_threw_ref()
         .expect("set_threw is None")
+        .clone();
+    set_threw
         .call(env_addr, val)
         .expect("set_threw failed to call");
-    // TODO: return Err("longjmp")
+    // Unwinds to the `invoke_*` function which called the function which
+    // called `setjmp`
     RuntimeError::raise(Box::new(LongJumpRet));
     unreachable!();
 }
 
+// The functions calling `setjmp` keep a table of the `setjmp` calls they
+// made, as pairs of a setjmp id, also stored in the `jmp_buf`, and of the
+// label to jump back to, terminated by a 0 id.
+
+/// Traps, on a `setjmp` table which can't be used.
+fn invalid_setjmp_table(message: &str) -> ! {
+    RuntimeError::raise(format!("invalid setjmp table: {}", message).into())
+}
+
+/// The index of the `i`th entry of the setjmp table at `table`, in a view
+/// of the memory as `u32`s.
+fn setjmp_entry(table: u32, i: u32) -> Option<usize> {
+    let offset = i.checked_mul(8)?.checked_add(table)?;
+    Some((offset / 4) as usize)
+}
+
+/// Adds the setjmp `id` and its `label` to the first free entry of the
+/// setjmp table at `table`, returning `false` if the table is full.
+fn add_setjmp(
+    view: &[Cell<u32>],
+    table: u32,
+    size: u32,
+    id: u32,
+    label: u32,
+) -> Result<bool, &'static str> {
+    for i in 0..size {
+        let cells = setjmp_entry(table, i)
+            .and_then(|entry| view.get(entry..entry.checked_add(3)?))
+            .ok_or("out of the memory")?;
+        if cells[0].get() == 0 {
+            cells[0].set(id);
+            cells[1].set(label);
+            // prepare next slot
+            cells[2].set(0);
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Gets the label of the setjmp `id` in the setjmp table at `table`, or 0
+/// if the table doesn't have it.
+fn find_setjmp(view: &[Cell<u32>], id: u32, table: u32, size: u32) -> Result<u32, &'static str> {
+    for i in 0..size {
+        let cells = setjmp_entry(table, i)
+            .and_then(|entry| view.get(entry..entry.checked_add(2)?))
+            .ok_or("out of the memory")?;
+        let curr = cells[0].get();
+        if curr == 0 {
+            break;
+        }
+        if curr == id {
+            return Ok(cells[1].get());
+        }
+    }
+    Ok(0)
+}
+
+/// saveSetjmp
+#[allow(non_snake_case)]
+pub fn saveSetjmp(ctx: &EmEnv, env: u32, label: u32, table: u32, size: u32) -> u32 {
+    debug!("emscripten::saveSetjmp");
+    let setjmp_id = {
+        let mut data = get_emscripten_data(ctx);
+        data.setjmp_id += 1;
+        data.setjmp_id
+    };
+    let memory = ctx.memory(0);
+    let view = memory.view::<u32>();
+    match view.get((env / 4) as usize) {
+        Some(cell) => cell.set(setjmp_id),
+        None => invalid_setjmp_table("the `jmp_buf` is out of the memory"),
+    }
+    let added = match add_setjmp(&view, table, size, setjmp_id, label) {
+        Ok(added) => added,
+        Err(message) => invalid_setjmp_table(message),
+    };
+    if added {
+        get_emscripten_data(ctx).temp_ret_0 = size as i32;
+        return table;
+    }
+    // grow the table
+    let (size, table_len) = match size
+        .checked_mul(2)
+        .and_then(|size| Some((size, size.checked_add(1)?.checked_mul(8)?)))
+    {
+        Some(sizes) => sizes,
+        None => invalid_setjmp_table("too large"),
+    };
+    let realloc = match get_emscripten_data(ctx).realloc_ref() {
+        Some(realloc) => realloc.clone(),
+        None => invalid_setjmp_table("`realloc` isn't exported to grow it"),
+    };
+    let table = match realloc.call(table, table_len) {
+        Ok(0) => invalid_setjmp_table("`realloc` failed to grow it"),
+        Ok(table) => table,
+        Err(e) => RuntimeError::raise(Box::new(e)),
+    };
+    saveSetjmp(ctx, env, label, table, size)
+}
+
+/// testSetjmp
+#[allow(non_snake_case)]
+pub fn testSetjmp(ctx: &EmEnv, id: u32, table: u32, size: u32) -> u32 {
+    debug!("emscripten::testSetjmp");
+    let memory = ctx.memory(0);
+    match find_setjmp(&memory.view::<u32>(), id, table, size) {
+        Ok(label) => label,
+        Err(message) => invalid_setjmp_table(message),
+    }
+}
+
 // extern "C" {
 //     fn setjmp(env: *mut c_void) -> c_int;
 //     fn longjmp(env: *mut c_void, val: c_int) -> !;
 // }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn setjmp_table() {
+        let view = vec![Cell::new(0); 8];
+        // The table at 4 has 2 entries
+        assert_eq!(add_setjmp(&view, 4, 2, 1, 10), Ok(true));
+        assert_eq!(add_setjmp(&view, 4, 2, 2, 20), Ok(true));
+        assert_eq!(add_setjmp(&view, 4, 2, 3, 30), Ok(false));
+        assert_eq!(find_setjmp(&view, 2, 4, 2), Ok(20));
+        assert_eq!(find_setjmp(&view, 3, 4, 2), Ok(0));
+
+        // The tables out of the memory are rejected
+        assert!(add_setjmp(&view, 28, 1, 1, 10).is_err());
+        assert!(find_setjmp(&view, 1, 28, 1).is_err());
+        assert!(add_setjmp(&view, u32::MAX - 4, 2, 1, 10).is_err());
+        assert_eq!(setjmp_entry(u32::MAX - 4, 1), None);
+    }
+}
@@ -34,6 +34,8 @@ type LibcDir = u8;
 #[macro_use]
 mod macros;
 
+mod abi;
+
 // EMSCRIPTEN APIS
 mod bitwise;
 mod emscripten_target;
@@ -61,7 +63,11 @@ mod ucontext;
 mod unistd;
 mod utils;
 mod varargs;
+mod wasi;
 
+pub use self::abi::{
+    check_emscripten_imports, get_emscripten_abi, EmscriptenAbi, EmscriptenMetadata,
+};
 pub use self::storage::{align_memory, static_alloc};
 pub use self::utils::{
     allocate_cstr_on_stack, allocate_on_stack, get_emscripten_memory_size, get_emscripten_metadata,
@@ -72,13 +78,26 @@ pub use self::utils::{
 /// The environment provided to the Emscripten imports.
 pub struct EmEnv {
     memory: Arc<RwLock<Option<Memory>>>,
+    table: Arc<RwLock<Option<Table>>>,
     data: Arc<Mutex<EmscriptenData>>,
 }
 
 impl WasmerEnv for EmEnv {
     fn init_with_instance(&mut self, instance: &Instance) -> Result<(), wasmer::HostEnvInitError> {
+        // The modules of the upstream ABI export their memory and table
+        if self.memory.read().unwrap().is_none() {
+            if let Ok(memory) = instance.exports.get_memory("memory") {
+                self.set_memory(memory.clone());
+            }
+        }
+        if self.table.read().unwrap().is_none() {
+            if let Ok(table) = instance.exports.get_table("__indirect_function_table") {
+                self.set_table(table.clone());
+            }
+        }
         let mut ed = self.data.lock().unwrap();
         ed.init_with_instance(instance)?;
+        ed.dylibs.set_main_module(instance);
         Ok(())
     }
 }
@@ -87,6 +106,7 @@ impl EmEnv {
     pub fn new(data: &EmscriptenGlobalsData, mapped_dirs: HashMap<String, PathBuf>) -> Self {
         Self {
             memory: Arc::new(RwLock::new(None)),
+            table: Arc::new(RwLock::new(None)),
             data: Arc::new(Mutex::new(EmscriptenData::new(data.clone(), mapped_dirs))),
         }
     }
@@ -100,6 +120,22 @@ impl EmEnv {
     pub fn memory(&self, _mem_idx: u32) -> Memory {
         (&*self.memory.read().unwrap()).as_ref().cloned().unwrap()
     }
+
+    /// Set the table the side modules loaded with `dlopen` are linked into
+    pub fn set_table(&mut self, table: Table) {
+        let mut w = self.table.write().unwrap();
+        *w = Some(table);
+    }
+
+    /// Get a reference to the table, if it was set
+    pub fn table(&self) -> Option<Table> {
+        (&*self.table.read().unwrap()).as_ref().cloned()
+    }
+
+    /// Let the module use the sockets of the host, which it can't by default
+    pub fn set_networking(&mut self, enabled: bool) {
+        self.data.lock().unwrap().networking = enabled;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -124,6 +160,16 @@ const STATIC_BUMP: u32 = 215_536;
 lazy_static! {
     static ref OLD_ABORT_ON_CANNOT_GROW_MEMORY_SIG: FunctionType =
         FunctionType::new(vec![], vec![ValType::I32]);
+    static ref LEGALIZED_FD_SEEK_SIG: FunctionType = FunctionType::new(
+        vec![
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+            ValType::I32
+        ],
+        vec![ValType::I32]
+    );
 }
 
 // The address globals begin at. Very low in memory, for code size and optimization opportunities.
@@ -143,11 +189,18 @@ pub struct EmscriptenData {
     pub free: LazyInit<NativeFunc<u32>>,
     #[wasmer(export(alias = "_memalign", optional = true))]
     pub memalign: LazyInit<NativeFunc<(u32, u32), u32>>,
+    #[wasmer(export(alias = "_realloc", optional = true))]
+    pub realloc: LazyInit<NativeFunc<(u32, u32), u32>>,
     #[wasmer(export(alias = "_memset", optional = true))]
     pub memset: LazyInit<NativeFunc<(u32, u32, u32), u32>>,
-    #[wasmer(export(name = "stackAlloc", optional = true))]
+    #[wasmer(export(
+        name = "stackAlloc",
+        alias = "_emscripten_stack_alloc",
+        optional = true
+    ))]
     pub stack_alloc: LazyInit<NativeFunc<u32, u32>>,
     pub jumps: Arc<Mutex<Vec<[u32; 27]>>>,
+    pub(crate) dylibs: crate::linking::DynamicLibraries,
     pub opened_dirs: HashMap<i32, Box<LibcDirWrapper>>,
 
     #[wasmer(export(name = "dynCall_i", optional = true))]
@@ -265,14 +318,26 @@ pub struct EmscriptenData {
     pub dyn_call_viidddddddd:
         LazyInit<NativeFunc<(i32, i32, i32, f64, f64, f64, f64, f64, f64, f64, f64)>>,
     pub temp_ret_0: i32,
-
-    #[wasmer(export(name = "stackSave", optional = true))]
+    /// The id of the last `setjmp` call
+    pub setjmp_id: u32,
+
+    #[wasmer(export(
+        name = "stackSave",
+        alias = "emscripten_stack_get_current",
+        optional = true
+    ))]
     pub stack_save: LazyInit<NativeFunc<(), i32>>,
-    #[wasmer(export(name = "stackRestore", optional = true))]
+    #[wasmer(export(
+        name = "stackRestore",
+        alias = "_emscripten_stack_restore",
+        optional = true
+    ))]
     pub stack_restore: LazyInit<NativeFunc<i32>>,
     #[wasmer(export(name = "setThrew", alias = "_setThrew", optional = true))]
     pub set_threw: LazyInit<NativeFunc<(i32, i32)>>,
     pub mapped_dirs: HashMap<String, PathBuf>,
+    /// Whether the module can use the sockets of the host
+    pub networking: bool,
 }
 
 impl EmscriptenData {
@@ -295,6 +360,15 @@ impl EmscriptenData {
 /// before calling this function, please initialize `Ctx::data` with a pointer
 /// to [`EmscriptenData`].
 pub fn set_up_emscripten(instance: &mut Instance) -> Result<(), RuntimeError> {
+    // The modules of the upstream ABI set up their stack and call their
+    // constructors themselves
+    if let Ok(func) = instance.exports.get::<Function>("emscripten_stack_init") {
+        func.call(&[])?;
+    }
+    if let Ok(func) = instance.exports.get::<Function>("__wasm_call_ctors") {
+        func.call(&[])?;
+    }
+
     // ATINIT
     // (used by C++)
     if let Ok(func) = instance.exports.get::<Function>("globalCtors") {
@@ -320,12 +394,20 @@ pub fn emscripten_call_main(
     path: &str,
     args: &[&str],
 ) -> Result<(), RuntimeError> {
+    // The upstream ABI exports `main` as `__main_argc_argv` when it takes
+    // the arguments
     let (function_name, main_func) = match instance.exports.get::<Function>("_main") {
         Ok(func) => Ok(("_main", func)),
         Err(_e) => instance
             .exports
             .get::<Function>("main")
-            .map(|func| ("main", func)),
+            .map(|func| ("main", func))
+            .or_else(|_| {
+                instance
+                    .exports
+                    .get::<Function>("__main_argc_argv")
+                    .map(|func| ("__main_argc_argv", func))
+            }),
     }
     .map_err(|e| RuntimeError::new(e.to_string()))?;
     let num_params = main_func.ty().params().len();
@@ -367,7 +449,12 @@ pub fn run_emscripten_instance(
     args: Vec<&str>,
     entrypoint: Option<String>,
 ) -> Result<(), RuntimeError> {
-    env.set_memory(globals.memory.clone());
+    if let Some(memory) = &globals.memory {
+        env.set_memory(memory.clone());
+    }
+    if let Some(table) = &globals.table {
+        env.set_table(table.clone());
+    }
     set_up_emscripten(instance)?;
 
     // println!("running emscripten instance");
@@ -435,17 +522,23 @@ pub struct EmscriptenGlobalsData {
     table_base: u32,
     temp_double_ptr: u32,
     use_old_abort_on_cannot_grow_memory: bool,
+    use_legalized_fd_seek: bool,
+    abi: EmscriptenAbi,
 }
 
 pub struct EmscriptenGlobals {
     // The emscripten data
     pub data: EmscriptenGlobalsData,
-    // The emscripten memory
-    pub memory: Memory,
-    pub table: Table,
+    // The emscripten memory and table, which the modules of the upstream
+    // ABI export instead
+    pub memory: Option<Memory>,
+    pub table: Option<Table>,
     pub memory_min: Pages,
     pub memory_max: Option<Pages>,
     pub null_function_names: Vec<String>,
+    /// The `__syscall_*` socket functions the module imports, with their
+    /// signatures, which depend on the version of Emscripten
+    pub socket_syscalls: Vec<(String, FunctionType)>,
 }
 
 impl EmscriptenGlobals {
@@ -462,6 +555,60 @@ impl EmscriptenGlobals {
                 break;
             }
         }
+        let use_legalized_fd_seek = module.imports().functions().any(|import| {
+            import.module() == abi::WASI_NAMESPACE
+                && import.name() == "fd_seek"
+                && import.ty() == &*LEGALIZED_FD_SEEK_SIG
+        });
+
+        #[cfg(unix)]
+        let socket_syscalls = module
+            .imports()
+            .functions()
+            .filter(|import| {
+                import.module() == "env"
+                    && crate::syscalls::socket_syscall_number(import.name()).is_some()
+            })
+            .map(|import| (import.name().to_string(), import.ty().clone()))
+            .collect();
+        #[cfg(not(unix))]
+        let socket_syscalls = vec![];
+
+        let null_function_names = module
+            .imports()
+            .functions()
+            .filter(|import| {
+                import.module() == "env"
+                    && (import.name().starts_with("nullFunction_")
+                        || import.name().starts_with("nullFunc_"))
+            })
+            .map(|import| import.name().to_string())
+            .collect();
+
+        if get_emscripten_abi(module)? == EmscriptenAbi::Upstream {
+            // The module exports its memory and table, and manages its stack
+            // and heap itself
+            let memory_type = module
+                .exports()
+                .memories()
+                .next()
+                .ok_or("the module doesn't export its memory")?
+                .ty()
+                .clone();
+            return Ok(Self {
+                data: EmscriptenGlobalsData {
+                    use_legalized_fd_seek,
+                    abi: EmscriptenAbi::Upstream,
+                    ..Default::default()
+                },
+                memory: None,
+                table: None,
+                memory_min: memory_type.minimum,
+                memory_max: memory_type.maximum,
+                null_function_names,
+                socket_syscalls,
+            });
+        }
 
         let (table_min, table_max) = get_emscripten_table_size(&module)?;
         let (memory_min, memory_max, shared) = get_emscripten_memory_size(&module)?;
@@ -510,28 +657,21 @@ impl EmscriptenGlobals {
                 table_base,
                 temp_double_ptr,
                 use_old_abort_on_cannot_grow_memory,
+                use_legalized_fd_seek,
+                abi: EmscriptenAbi::Fastcomp,
             }
         };
 
         emscripten_set_up_memory(&memory, &data)?;
 
-        let mut null_function_names = vec![];
-        for import in module.imports().functions() {
-            if import.module() == "env"
-                && (import.name().starts_with("nullFunction_")
-                    || import.name().starts_with("nullFunc_"))
-            {
-                null_function_names.push(import.name().to_string())
-            }
-        }
-
         Ok(Self {
             data,
-            memory,
-            table,
+            memory: Some(memory),
+            table: Some(table),
             memory_min,
             memory_max,
             null_function_names,
+            socket_syscalls,
         })
     }
 }
@@ -556,9 +696,6 @@ pub fn generate_emscripten_env(
     };
 
     let mut env_ns: Exports = namespace! {
-        "memory" => globals.memory.clone(),
-        "table" => globals.table.clone(),
-
         // Globals
         "STACKTOP" => Global::new(store, Val::I32(globals.data.stacktop as i32)),
         "STACK_MAX" => Global::new(store, Val::I32(globals.data.stack_max as i32)),
@@ -848,6 +985,11 @@ pub fn generate_emscripten_env(
         "__longjmp" => Function::new_native_with_env(store, env.clone(), crate::jmp::__longjmp),
         "_longjmp" => Function::new_native_with_env(store, env.clone(), crate::jmp::_longjmp),
         "_emscripten_longjmp" => Function::new_native_with_env(store, env.clone(), crate::jmp::_longjmp),
+        "emscripten_longjmp" => Function::new_native_with_env(store, env.clone(), crate::jmp::_longjmp),
+        "saveSetjmp" => Function::new_native_with_env(store, env.clone(), crate::jmp::saveSetjmp),
+        "_saveSetjmp" => Function::new_native_with_env(store, env.clone(), crate::jmp::saveSetjmp),
+        "testSetjmp" => Function::new_native_with_env(store, env.clone(), crate::jmp::testSetjmp),
+        "_testSetjmp" => Function::new_native_with_env(store, env.clone(), crate::jmp::testSetjmp),
 
         // Bitwise
         "_llvm_bswap_i64" => Function::new_native_with_env(store, env.clone(), crate::bitwise::_llvm_bswap_i64),
@@ -1010,6 +1152,92 @@ pub fn generate_emscripten_env(
         env_ns.insert(k, v);
     }
 
+    if let Some(memory) = &globals.memory {
+        env_ns.insert("memory", memory.clone());
+    }
+    if let Some(table) = &globals.table {
+        env_ns.insert("table", table.clone());
+    }
+
+    // The upstream ABI
+    env_ns.insert(
+        "_emscripten_memcpy_js",
+        Function::new_native_with_env(store, env.clone(), crate::memory::_emscripten_memcpy_js),
+    );
+    env_ns.insert(
+        "emscripten_get_heap_max",
+        Function::new_native_with_env(store, env.clone(), crate::memory::emscripten_get_heap_max),
+    );
+    env_ns.insert(
+        "emscripten_date_now",
+        Function::new_native_with_env(store, env.clone(), crate::time::emscripten_date_now),
+    );
+    env_ns.insert(
+        "emscripten_get_now",
+        Function::new_native_with_env(store, env.clone(), crate::time::emscripten_get_now),
+    );
+    env_ns.insert(
+        "_emscripten_get_now_is_monotonic",
+        Function::new_native_with_env(
+            store,
+            env.clone(),
+            crate::time::_emscripten_get_now_is_monotonic,
+        ),
+    );
+    #[cfg(unix)]
+    {
+        let upstream_syscalls: Exports = namespace! {
+            "__syscall_openat" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_openat),
+            "__sys_open" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__sys_open),
+            "__syscall_fcntl64" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_fcntl64),
+            "__sys_fcntl64" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_fcntl64),
+            "__syscall_ioctl" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_ioctl),
+            "__sys_ioctl" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_ioctl),
+            "__syscall_fstat64" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_fstat64),
+            "__sys_fstat64" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__sys_fstat64),
+            "__syscall_stat64" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_stat64),
+            "__sys_stat64" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__sys_stat64),
+            "__syscall_lstat64" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_lstat64),
+            "__sys_lstat64" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__sys_lstat64),
+            "__syscall_newfstatat" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_newfstatat),
+            "__syscall_getcwd" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_getcwd),
+            "__sys_getcwd" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_getcwd),
+            "__syscall_mkdirat" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_mkdirat),
+            "__sys_mkdir" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__sys_mkdir),
+            "__syscall_unlinkat" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_unlinkat),
+            "__sys_unlink" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__sys_unlink),
+            "__syscall_rmdir" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_rmdir),
+            "__sys_rmdir" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_rmdir),
+            "__syscall_renameat" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_renameat),
+            "__sys_rename" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__sys_rename),
+            "__syscall_chdir" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_chdir),
+            "__sys_chdir" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_chdir),
+            "__syscall_dup" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_dup),
+            "__sys_dup" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_dup),
+            "__syscall_dup3" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_dup3),
+            "__sys_dup3" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_dup3),
+            "__syscall_faccessat" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_faccessat),
+            "__sys_access" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__sys_access),
+            "__syscall_readlinkat" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__syscall_readlinkat),
+            "__sys_readlink" => Function::new_native_with_env(store, env.clone(), crate::syscalls::__sys_readlink),
+        };
+        for (name, export) in upstream_syscalls.iter() {
+            env_ns.insert(name.as_str(), export.clone());
+        }
+
+        // The arity of the socket functions depends on the version of
+        // Emscripten, so they take the signature the module imports
+        for (name, ty) in globals.socket_syscalls.iter() {
+            let call = crate::syscalls::socket_syscall_number(name).unwrap();
+            let function = Function::new_with_env(store, ty, env.clone(), move |env, args| {
+                Ok(vec![Val::I32(crate::syscalls::socket_syscall(
+                    env, call, args,
+                ))])
+            });
+            env_ns.insert(name.as_str(), function);
+        }
+    }
+
     for null_function_name in globals.null_function_names.iter() {
         env_ns.insert(
             null_function_name.as_str(),
@@ -1017,8 +1245,24 @@ pub fn generate_emscripten_env(
         );
     }
 
+    let fd_seek = if globals.data.use_legalized_fd_seek {
+        Function::new_native_with_env(store, env.clone(), crate::wasi::fd_seek_legalized)
+    } else {
+        Function::new_native_with_env(store, env.clone(), crate::wasi::fd_seek)
+    };
+
     let import_object: ImportObject = imports! {
         "env" => env_ns,
+        abi::WASI_NAMESPACE => {
+            "fd_write" => Function::new_native_with_env(store, env.clone(), crate::wasi::fd_write),
+            "fd_read" => Function::new_native_with_env(store, env.clone(), crate::wasi::fd_read),
+            "fd_close" => Function::new_native_with_env(store, env.clone(), crate::wasi::fd_close),
+            "fd_seek" => fd_seek,
+            "fd_fdstat_get" => Function::new_native_with_env(store, env.clone(), crate::wasi::fd_fdstat_get),
+            "proc_exit" => Function::new_native_with_env(store, env.clone(), crate::wasi::proc_exit),
+            "environ_sizes_get" => Function::new_native_with_env(store, env.clone(), crate::wasi::environ_sizes_get),
+            "environ_get" => Function::new_native_with_env(store, env.clone(), crate::wasi::environ_get),
+        },
         "global" => {
           "NaN" => Global::new(store, Val::F64(f64::NAN)),
           "Infinity" => Global::new(store, Val::F64(f64::INFINITY)),
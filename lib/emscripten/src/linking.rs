@@ -1,27 +1,495 @@
-use crate::EmEnv;
+//! Dynamic linking of side modules, built with `-s SIDE_MODULE=1`, into a
+//! main module built with `-s MAIN_MODULE=1`, with `dlopen`.
+//!
+//! This follows the ABI of the fastcomp backend of Emscripten. A side module
+//! starts with a `dylink` section giving the sizes of the memory and of the
+//! table it needs, which are allocated when it's loaded and given to it as
+//! `__memory_base` and `__table_base`. It imports the addresses of the data it
+//! uses from the other modules with `g$<symbol>` functions, and the table
+//! indices of their functions with `fp$<symbol>$<signature>` functions. Its
+//! other imports are the Emscripten functions and the functions exported by
+//! the main module and by the libraries loaded before it.
 
-// TODO: Need to implement.
+use crate::env::{call_malloc, call_memset, get_emscripten_data};
+use crate::utils::{get_cstr_path, read_string_from_wasm};
+use crate::{generate_emscripten_env, EmEnv, EmscriptenGlobals, EmscriptenGlobalsData};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::{Arc, Mutex};
+use wasmer::{
+    ChainableNamedResolver, Exportable, Exports, Extern, Function, ImportObject, Instance, Module,
+    RuntimeError, Val,
+};
+
+/// The alignment of the memory of the side modules, at least the one of the stack
+const MIN_MEMORY_ALIGN: u32 = 16;
+
+/// A module whose symbols can be looked up with `dlsym`.
+#[derive(Clone)]
+struct Library {
+    name: String,
+    exports: Exports,
+    /// The address the data of the library starts at, added to the
+    /// addresses it exports
+    memory_base: u32,
+    /// Keeps the functions of a side module alive, since they are in the
+    /// table, behind a `Mutex` as an `Instance` isn't `Sync`. `None` for
+    /// the main module
+    _instance: Option<Arc<Mutex<Instance>>>,
+    refcount: u32,
+}
+
+/// The libraries loaded with `dlopen`.
+///
+/// Libraries are never unloaded, as in Emscripten: `dlclose` only releases
+/// the handle.
+#[derive(Clone, Default)]
+pub struct DynamicLibraries {
+    /// The main module and the libraries, by handle - 1
+    libraries: Vec<Library>,
+    /// The table indices of the functions whose address was taken, by
+    /// handle and name
+    function_pointers: HashMap<(u32, String), u32>,
+    /// The names of the libraries being loaded, with the ones they need
+    loading: Vec<String>,
+    /// The error of the last call which failed
+    error: Option<String>,
+    /// The last message returned by `dlerror`, freed by the next call
+    error_message: u32,
+}
+
+impl DynamicLibraries {
+    /// Makes the symbols of the main module available, once it's
+    /// instantiated.
+    pub(crate) fn set_main_module(&mut self, instance: &Instance) {
+        // The side modules are initialized with the same env
+        if !self.libraries.is_empty() {
+            return;
+        }
+        // The exports hold weak references, since the instance owns the env
+        let exports = instance
+            .exports
+            .iter()
+            .map(|(name, export)| {
+                let mut export = export.clone();
+                export.into_weak_instance_ref();
+                (name.clone(), export)
+            })
+            .collect();
+        self.libraries.push(Library {
+            name: "__self__".to_string(),
+            exports,
+            memory_base: 0,
+            _instance: None,
+            refcount: 1,
+        });
+    }
+
+    fn handle(&self, name: &str) -> Option<u32> {
+        self.libraries
+            .iter()
+            .position(|library| library.name == name)
+            .map(|index| index as u32 + 1)
+    }
+
+    fn library(&self, handle: u32) -> Option<&Library> {
+        self.libraries.get((handle as usize).checked_sub(1)?)
+    }
+
+    /// Finds `symbol` in the main module or in the libraries, in the order
+    /// they were loaded.
+    fn find(&self, symbol: &str) -> Option<(u32, Extern, u32)> {
+        self.libraries
+            .iter()
+            .enumerate()
+            .find_map(|(index, library)| {
+                let export = library.exports.get_extern(symbol)?;
+                Some((index as u32 + 1, export.clone(), library.memory_base))
+            })
+    }
+}
+
+/// The contents of the `dylink` section of a side module.
+#[derive(Debug, PartialEq)]
+struct Dylink {
+    memory_size: u32,
+    memory_align: u32,
+    table_size: u32,
+    /// The libraries to load before this one
+    needed: Vec<String>,
+}
+
+pub(crate) fn read_leb(bytes: &[u8], pos: &mut usize) -> Option<u32> {
+    let mut value = 0u32;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u32).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+fn parse_dylink(section: &[u8]) -> Option<Dylink> {
+    let mut pos = 0;
+    let memory_size = read_leb(section, &mut pos)?;
+    let memory_align = 1u32.checked_shl(read_leb(section, &mut pos)?)?;
+    let table_size = read_leb(section, &mut pos)?;
+    let _table_align = read_leb(section, &mut pos)?;
+    let mut needed = vec![];
+    // Older versions of Emscripten don't list the needed libraries
+    if pos < section.len() {
+        for _ in 0..read_leb(section, &mut pos)? {
+            let len = read_leb(section, &mut pos)? as usize;
+            let name = section.get(pos..pos + len)?;
+            needed.push(String::from_utf8_lossy(name).into_owned());
+            pos += len;
+        }
+    }
+    Some(Dylink {
+        memory_size,
+        memory_align: memory_align.max(MIN_MEMORY_ALIGN),
+        table_size,
+        needed,
+    })
+}
+
+/// Gets the address of an exported symbol: the one of its data, or the
+/// index of the function in the table.
+fn symbol_address(ctx: &EmEnv, symbol: &str) -> Result<u32, String> {
+    let (handle, export, memory_base) = get_emscripten_data(ctx)
+        .dylibs
+        .find(symbol)
+        .ok_or_else(|| format!("missing linked symbol `{}`", symbol))?;
+    export_address(ctx, handle, symbol, export, memory_base)
+}
+
+fn export_address(
+    ctx: &EmEnv,
+    handle: u32,
+    symbol: &str,
+    export: Extern,
+    memory_base: u32,
+) -> Result<u32, String> {
+    match export {
+        Extern::Global(global) => match global.get() {
+            Val::I32(address) => Ok(address as u32 + memory_base),
+            _ => Err(format!("`{}` isn't the address of data", symbol)),
+        },
+        Extern::Function(function) => {
+            let key = (handle, symbol.to_string());
+            if let Some(index) = get_emscripten_data(ctx).dylibs.function_pointers.get(&key) {
+                return Ok(*index);
+            }
+            let table = ctx.table().ok_or("the table isn't available")?;
+            let index = table
+                .grow(1, Val::FuncRef(Some(function)))
+                .map_err(|e| e.message())?;
+            get_emscripten_data(ctx)
+                .dylibs
+                .function_pointers
+                .insert(key, index);
+            Ok(index)
+        }
+        _ => Err(format!("`{}` isn't a function or data", symbol)),
+    }
+}
+
+/// Opens the library `name`, loading it if it isn't loaded yet, returning
+/// its handle.
+fn open(ctx: &EmEnv, name: &str) -> Result<u32, String> {
+    {
+        let mut data = get_emscripten_data(ctx);
+        if let Some(handle) = data.dylibs.handle(name) {
+            data.dylibs.libraries[handle as usize - 1].refcount += 1;
+            return Ok(handle);
+        }
+    }
+    let path = CString::new(name).map_err(|e| e.to_string())?;
+    let path = get_cstr_path(ctx, path.as_ptr() as *const i8)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_string());
+    let bytes =
+        std::fs::read(&path).map_err(|e| format!("Could not load dynamic lib: {}\n{}", name, e))?;
+    load(ctx, name, &bytes)
+}
+
+/// Adds `name` to the libraries being `loading`, unless it's already
+/// being loaded, which means that it needs itself.
+fn start_loading(loading: &mut Vec<String>, name: &str) -> Result<(), String> {
+    if loading.iter().any(|loading| loading == name) {
+        return Err(format!(
+            "cyclic dependency of dynamic libraries: {} -> {}",
+            loading.join(" -> "),
+            name
+        ));
+    }
+    loading.push(name.to_string());
+    Ok(())
+}
+
+/// Loads and links the side module `name`.
+fn load(ctx: &EmEnv, name: &str, bytes: &[u8]) -> Result<u32, String> {
+    start_loading(&mut get_emscripten_data(ctx).dylibs.loading, name)?;
+    let result = load_needed(ctx, name, bytes);
+    get_emscripten_data(ctx).dylibs.loading.pop();
+    result
+}
+
+/// Loads and links the side module `name`, after the libraries it needs.
+fn load_needed(ctx: &EmEnv, name: &str, bytes: &[u8]) -> Result<u32, String> {
+    let memory = ctx.memory(0);
+    let store = memory.store().clone();
+    let module = Module::new(&store, bytes).map_err(|e| e.to_string())?;
+    let dylink = module
+        .custom_sections("dylink")
+        .next()
+        .and_then(|section| parse_dylink(&section))
+        .ok_or_else(|| format!("`{}` isn't a side module", name))?;
+    for needed in &dylink.needed {
+        open(ctx, needed)?;
+    }
+
+    let memory_base = if dylink.memory_size > 0 {
+        let align = dylink.memory_align;
+        let base = call_malloc(ctx, dylink.memory_size + align);
+        let base = (base + align - 1) & !(align - 1);
+        call_memset(ctx, base, 0, dylink.memory_size);
+        base
+    } else {
+        0
+    };
+    let table = ctx.table().ok_or("the table isn't available")?;
+    let table_base = table
+        .grow(dylink.table_size, Val::FuncRef(None))
+        .map_err(|e| e.message())?;
+
+    // The imports of the side module are the ones of the main module, with
+    // its own memory and table bases
+    let globals_data = get_emscripten_data(ctx).globals.clone();
+    let memory_type = memory.ty();
+    let mut globals = EmscriptenGlobals {
+        data: EmscriptenGlobalsData {
+            memory_base,
+            table_base,
+            ..globals_data
+        },
+        memory: Some(memory.clone()),
+        table: Some(table),
+        memory_min: memory_type.minimum,
+        memory_max: memory_type.maximum,
+        null_function_names: module
+            .imports()
+            .functions()
+            .filter(|import| {
+                import.module() == "env"
+                    && (import.name().starts_with("nullFunction_")
+                        || import.name().starts_with("nullFunc_"))
+            })
+            .map(|import| import.name().to_string())
+            .collect(),
+        socket_syscalls: vec![],
+    };
+    let env_imports = generate_emscripten_env(&store, &mut globals, ctx);
+
+    let mut linked = Exports::new();
+    for import in module.imports().functions() {
+        let symbol = import.name();
+        if import.module() != "env" || env_imports.get_export("env", symbol).is_some() {
+            continue;
+        }
+        // The addresses are resolved when the module asks for them, since
+        // it may ask for its own symbols
+        let address_of = if let Some(data) = symbol.strip_prefix("g$") {
+            Some(data)
+        } else if let Some(function) = symbol.strip_prefix("fp$") {
+            function.split('$').next()
+        } else {
+            None
+        };
+        let function = match address_of {
+            Some(address_of) => {
+                let address_of = address_of.to_string();
+                Function::new_with_env(&store, import.ty(), ctx.clone(), move |ctx, _args| {
+                    let address = symbol_address(ctx, &address_of).map_err(RuntimeError::new)?;
+                    Ok(vec![Val::I32(address as i32)])
+                })
+            }
+            None => match get_emscripten_data(ctx).dylibs.find(symbol) {
+                Some((_, Extern::Function(function), _)) => function,
+                _ => return Err(format!("missing linked function `{}`", symbol)),
+            },
+        };
+        linked.insert(symbol, function);
+    }
+    let mut linked_imports = ImportObject::new();
+    linked_imports.register("env", linked);
+    let instance = Instance::new(&module, &linked_imports.chain_back(env_imports))
+        .map_err(|e| e.to_string())?;
+
+    let init = instance
+        .exports
+        .get_function("__post_instantiate")
+        .ok()
+        .cloned();
+    let handle = {
+        let mut data = get_emscripten_data(ctx);
+        data.dylibs.libraries.push(Library {
+            name: name.to_string(),
+            exports: instance.exports.clone(),
+            memory_base,
+            _instance: Some(Arc::new(Mutex::new(instance))),
+            refcount: 1,
+        });
+        data.dylibs.libraries.len() as u32
+    };
+    // Applies the relocations and runs the constructors
+    if let Some(init) = init {
+        init.call(&[]).map_err(|e| e.message())?;
+    }
+    Ok(handle)
+}
+
+fn fail(ctx: &EmEnv, error: String) {
+    debug!("emscripten::dl: {}", error);
+    get_emscripten_data(ctx).dylibs.error = Some(error);
+}
 
 /// emscripten: dlopen(filename: *const c_char, flag: c_int) -> *mut c_void
-pub fn _dlopen(_ctx: &EmEnv, _filename: u32, _flag: u32) -> i32 {
+pub fn _dlopen(ctx: &EmEnv, filename: u32, _flag: u32) -> i32 {
     debug!("emscripten::_dlopen");
-    -1
+    // The main module
+    if filename == 0 {
+        return 1;
+    }
+    let name = read_string_from_wasm(&ctx.memory(0), filename);
+    match open(ctx, &name) {
+        Ok(handle) => handle as i32,
+        Err(error) => {
+            fail(ctx, error);
+            0
+        }
+    }
 }
 
 /// emscripten: dlclose(handle: *mut c_void) -> c_int
-pub fn _dlclose(_ctx: &EmEnv, _filename: u32) -> i32 {
+pub fn _dlclose(ctx: &EmEnv, handle: u32) -> i32 {
     debug!("emscripten::_dlclose");
-    -1
+    let mut data = get_emscripten_data(ctx);
+    if let Some(library) = data
+        .dylibs
+        .libraries
+        .get_mut((handle as usize).wrapping_sub(1))
+        .filter(|library| library.refcount > 0)
+    {
+        library.refcount -= 1;
+        return 0;
+    }
+    data.dylibs.error = Some(format!("Tried to dlclose() unopened handle: {}", handle));
+    1
 }
 
 /// emscripten: dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void
-pub fn _dlsym(_ctx: &EmEnv, _filepath: u32, _symbol: u32) -> i32 {
+pub fn _dlsym(ctx: &EmEnv, handle: u32, symbol: u32) -> i32 {
     debug!("emscripten::_dlsym");
-    -1
+    let symbol = read_string_from_wasm(&ctx.memory(0), symbol);
+    let found = match get_emscripten_data(ctx).dylibs.library(handle) {
+        Some(library) if library.refcount > 0 => {
+            // The C symbols are exported with a `_` prefix
+            let mangled = format!("_{}", symbol);
+            library
+                .exports
+                .get_extern(&mangled)
+                .map(|export| (mangled, export.clone()))
+                .or_else(|| {
+                    let export = library.exports.get_extern(&symbol)?.clone();
+                    Some((symbol.clone(), export))
+                })
+                .map(|(symbol, export)| (symbol, export, library.memory_base))
+                .ok_or_else(|| {
+                    format!(
+                        "Tried to lookup unknown symbol \"{}\" in dynamic lib: {}",
+                        symbol, library.name
+                    )
+                })
+        }
+        _ => Err(format!(
+            "Tried to dlsym() from an unopened handle: {}",
+            handle
+        )),
+    };
+    match found.and_then(|(symbol, export, memory_base)| {
+        export_address(ctx, handle, &symbol, export, memory_base)
+    }) {
+        Ok(address) => address as i32,
+        Err(error) => {
+            fail(ctx, error);
+            0
+        }
+    }
 }
 
 /// emscripten: dlerror() -> *mut c_char
-pub fn _dlerror(_ctx: &EmEnv) -> i32 {
+pub fn _dlerror(ctx: &EmEnv) -> i32 {
     debug!("emscripten::_dlerror");
-    -1
+    let (error, previous_message, free) = {
+        let mut data = get_emscripten_data(ctx);
+        let error = match data.dylibs.error.take() {
+            Some(error) => error,
+            None => return 0,
+        };
+        let previous_message = std::mem::replace(&mut data.dylibs.error_message, 0);
+        (error, previous_message, data.free_ref().cloned())
+    };
+    if let (true, Some(free)) = (previous_message != 0, free) {
+        let _ = free.call(previous_message);
+    }
+    let message = call_malloc(ctx, error.len() as u32 + 1);
+    let memory = ctx.memory(0);
+    let view = memory.view::<u8>();
+    for (cell, byte) in view[message as usize..]
+        .iter()
+        .zip(error.bytes().chain(Some(0)))
+    {
+        cell.set(byte);
+    }
+    get_emscripten_data(ctx).dylibs.error_message = message;
+    message as i32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dylink_section() {
+        assert_eq!(
+            parse_dylink(&[0x80, 0x01, 3, 2, 0, 1, 6, b'l', b'i', b'b', b'.', b's', b'o']),
+            Some(Dylink {
+                memory_size: 128,
+                memory_align: 16,
+                table_size: 2,
+                needed: vec!["lib.so".to_string()],
+            })
+        );
+        assert_eq!(
+            parse_dylink(&[4, 5, 0, 0]).map(|dylink| dylink.memory_align),
+            Some(32)
+        );
+        assert_eq!(parse_dylink(&[4, 5]), None);
+    }
+
+    #[test]
+    fn cyclic_dependencies() {
+        let mut loading = vec![];
+        start_loading(&mut loading, "liba.so").unwrap();
+        start_loading(&mut loading, "libb.so").unwrap();
+        assert_eq!(
+            start_loading(&mut loading, "liba.so").unwrap_err(),
+            "cyclic dependency of dynamic libraries: liba.so -> libb.so -> liba.so"
+        );
+    }
 }
@@ -19,6 +19,25 @@ pub fn _emscripten_memcpy_big(ctx: &EmEnv, dest: u32, src: u32, len: u32) -> u32
     dest
 }
 
+/// emscripten: _emscripten_memcpy_js, the upstream `_emscripten_memcpy_big`
+pub fn _emscripten_memcpy_js(ctx: &EmEnv, dest: u32, src: u32, len: u32) {
+    _emscripten_memcpy_big(ctx, dest, src, len);
+}
+
+/// emscripten: emscripten_get_heap_max
+pub fn emscripten_get_heap_max(ctx: &EmEnv) -> u32 {
+    trace!("emscripten::emscripten_get_heap_max");
+    // The heap of 4GiB doesn't fit, so it ends a page before
+    let max = ctx
+        .memory(0)
+        .ty()
+        .maximum
+        .unwrap_or(WASM_MAX_PAGES)
+        .bytes()
+        .0;
+    usize::min(max, (u32::max_value() as usize + 1) - WASM_PAGE_SIZE) as u32
+}
+
 /// emscripten: _emscripten_get_heap_size
 pub fn _emscripten_get_heap_size(ctx: &EmEnv) -> u32 {
     trace!("emscripten::_emscripten_get_heap_size");
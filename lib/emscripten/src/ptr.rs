@@ -6,7 +6,7 @@
 #![allow(dead_code)]
 
 use std::{cell::Cell, fmt};
-pub use wasmer::{Array, FromToNativeWasmType, Memory, ValueType};
+pub use wasmer::{Array, FromToNativeWasmType, Memory, ValueType, WasmCell};
 
 #[repr(transparent)]
 pub struct WasmPtr<T: Copy, Ty = wasmer::Item>(wasmer::WasmPtr<T, Ty>);
@@ -59,7 +59,7 @@ impl<T: Copy, Ty> WasmPtr<T, Ty> {
 
 impl<T: Copy + ValueType> WasmPtr<T, wasmer::Item> {
     #[inline(always)]
-    pub fn deref<'a>(self, memory: &'a Memory) -> Option<&'a Cell<T>> {
+    pub fn deref<'a>(self, memory: &'a Memory) -> Option<WasmCell<'a, T>> {
         if self.0.offset() == 0 {
             None
         } else {
@@ -79,7 +79,12 @@ impl<T: Copy + ValueType> WasmPtr<T, wasmer::Item> {
 
 impl<T: Copy + ValueType> WasmPtr<T, wasmer::Array> {
     #[inline(always)]
-    pub fn deref<'a>(self, memory: &'a Memory, index: u32, length: u32) -> Option<&'a [Cell<T>]> {
+    pub fn deref<'a>(
+        self,
+        memory: &'a Memory,
+        index: u32,
+        length: u32,
+    ) -> Option<Vec<WasmCell<'a, T>>> {
         if self.0.offset() == 0 {
             None
         } else {
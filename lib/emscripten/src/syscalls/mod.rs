@@ -1,11 +1,21 @@
 #[cfg(unix)]
+mod socket;
+#[cfg(unix)]
 mod unix;
+#[cfg(unix)]
+mod upstream;
 
 #[cfg(windows)]
 mod windows;
 
+#[cfg(unix)]
+pub use self::socket::___syscall102;
+#[cfg(unix)]
+pub(crate) use self::socket::{socket_syscall, socket_syscall_number};
 #[cfg(unix)]
 pub use self::unix::*;
+#[cfg(unix)]
+pub use self::upstream::*;
 
 #[cfg(windows)]
 pub use self::windows::*;
@@ -50,6 +60,8 @@ use std::cell::Cell;
 #[allow(unused_imports)]
 use std::io::Error;
 use std::slice;
+#[cfg(unix)]
+use wasmer::Memory;
 
 /// exit
 pub fn ___syscall1(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) {
@@ -734,3 +746,54 @@ pub fn ___syscall345(_ctx: &EmEnv, _one: i32, _two: i32) -> i32 {
     debug!("emscripten::___syscall345");
     -1
 }
+
+/// Turns the return value of a host call into a result with the Linux
+/// errno of its error.
+#[cfg(unix)]
+fn check(ret: isize) -> Result<c_int, c_int> {
+    if ret < 0 {
+        Err(crate::errno::last_errno())
+    } else {
+        Ok(ret as c_int)
+    }
+}
+
+/// Translates the flags of the module to the ones of the host, or the
+/// other way around, with the pairs of flags of `table`.
+#[cfg(unix)]
+fn translate_flags(flags: c_int, table: &[(c_int, c_int)], to_host: bool) -> c_int {
+    let mut translated = 0;
+    for (wasm, host) in table {
+        let (from, to) = if to_host { (wasm, host) } else { (host, wasm) };
+        if flags & from != 0 {
+            translated |= to;
+        }
+    }
+    translated
+}
+
+// EFAULT
+#[cfg(unix)]
+const EFAULT: c_int = 14;
+
+#[cfg(unix)]
+fn read_u32(memory: &Memory, ptr: u32) -> Result<u32, c_int> {
+    let bytes = crate::utils::read_bytes(memory, ptr, 4).ok_or(EFAULT)?;
+    Ok(LittleEndian::read_u32(&bytes))
+}
+
+#[cfg(unix)]
+fn write_u32(memory: &Memory, ptr: u32, value: u32) -> Result<(), c_int> {
+    crate::utils::write_bytes(memory, ptr, &value.to_le_bytes()).ok_or(EFAULT)
+}
+
+/// Gets a pointer to the `len` bytes of the memory at `ptr`.
+#[cfg(unix)]
+fn buffer(memory: &Memory, ptr: u32, len: u32) -> Result<*mut c_void, c_int> {
+    match (ptr as u64).checked_add(len as u64) {
+        Some(end) if end <= memory.data_size() => {
+            Ok(unsafe { memory.data_ptr().add(ptr as usize) } as *mut c_void)
+        }
+        _ => Err(EFAULT),
+    }
+}
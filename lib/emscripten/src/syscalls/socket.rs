@@ -0,0 +1,685 @@
+//! The `socketcall` syscall, backed by the sockets of the host.
+//!
+//! The module sees the ABI of musl on Linux: its constants, `sockaddr`s,
+//! `msghdr`s and errnos are translated from and to the ones of the host,
+//! which lay them out differently on macOS and the BSDs. The file
+//! descriptors of the sockets are the ones of the host, like the ones of
+//! the files opened with `open`.
+//!
+//! The modules of the upstream ABI don't use `socketcall`, but a
+//! `__syscall_*` function for each of its calls.
+//!
+//! The sockets are only usable when networking was enabled with
+//! [`EmEnv::set_networking`], the calls failing with `EACCES` otherwise.
+use super::{buffer, check, read_u32, translate_flags, write_u32};
+use crate::env::get_emscripten_data;
+use crate::errno::wasm_errno;
+use crate::utils::{read_bytes, write_bytes};
+use crate::varargs::VarArgs;
+use crate::EmEnv;
+use libc::{c_int, c_void, sa_family_t, sockaddr, sockaddr_storage, socklen_t};
+use std::mem;
+use wasmer::{Memory, Val};
+
+// The constants of musl, as seen by the module.
+const WASM_AF_UNIX: c_int = 1;
+const WASM_AF_INET: c_int = 2;
+const WASM_AF_INET6: c_int = 10;
+
+const WASM_SOCK_STREAM: c_int = 1;
+const WASM_SOCK_DGRAM: c_int = 2;
+const WASM_SOCK_RAW: c_int = 3;
+const WASM_SOCK_SEQPACKET: c_int = 5;
+const WASM_SOCK_NONBLOCK: c_int = 0o4000;
+const WASM_SOCK_CLOEXEC: c_int = 0o2000000;
+
+const WASM_SOL_SOCKET: c_int = 1;
+const WASM_IPPROTO_IPV6: c_int = 41;
+const WASM_IPV6_V6ONLY: c_int = 26;
+const WASM_SO_RCVTIMEO: c_int = 20;
+const WASM_SO_SNDTIMEO: c_int = 21;
+
+const WASM_MSG_NOSIGNAL: c_int = 0x4000;
+
+const EFAULT: c_int = 14;
+const EINVAL: c_int = 22;
+const EACCES: c_int = 13;
+const EAFNOSUPPORT: c_int = 97;
+const EOPNOTSUPP: c_int = 95;
+const ENOSYS: c_int = 38;
+
+/// The `MSG_*` flags of the module, and the ones of the host.
+const MSG_FLAGS: &[(c_int, c_int)] = &[
+    (0x1, libc::MSG_OOB),
+    (0x2, libc::MSG_PEEK),
+    (0x4, libc::MSG_DONTROUTE),
+    (0x8, libc::MSG_CTRUNC),
+    (0x20, libc::MSG_TRUNC),
+    (0x40, libc::MSG_DONTWAIT),
+    (0x80, libc::MSG_EOR),
+    (0x100, libc::MSG_WAITALL),
+];
+
+fn translate_msg_flags(flags: c_int, to_host: bool) -> c_int {
+    translate_flags(flags, MSG_FLAGS, to_host)
+}
+
+/// The flags to send with: the sockets never raise `SIGPIPE`, on macOS
+/// thanks to `SO_NOSIGPIPE`.
+fn send_flags(flags: c_int) -> c_int {
+    let flags = translate_msg_flags(flags & !WASM_MSG_NOSIGNAL, true);
+    #[cfg(not(target_os = "macos"))]
+    let flags = flags | libc::MSG_NOSIGNAL;
+    flags
+}
+
+/// The offset of `sun_path` in the `sockaddr_un` of the host.
+fn sun_path_offset() -> usize {
+    let sun: libc::sockaddr_un = unsafe { mem::zeroed() };
+    sun.sun_path.as_ptr() as usize - &sun as *const _ as usize
+}
+
+/// Reads the `sockaddr` of `len` bytes of the module at `ptr`, as a
+/// `sockaddr` of the host and its length.
+fn read_sockaddr(
+    memory: &Memory,
+    ptr: u32,
+    len: u32,
+) -> Result<(sockaddr_storage, socklen_t), c_int> {
+    let bytes = read_bytes(memory, ptr, len).ok_or(EFAULT)?;
+    if bytes.len() < 2 {
+        return Err(EINVAL);
+    }
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let host_len = match u16::from_le_bytes([bytes[0], bytes[1]]) as c_int {
+        WASM_AF_INET => {
+            if bytes.len() < 8 {
+                return Err(EINVAL);
+            }
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as sa_family_t;
+            // The port and address are in network byte order on both sides
+            sin.sin_port = u16::from_ne_bytes([bytes[2], bytes[3]]);
+            sin.sin_addr.s_addr = u32::from_ne_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+            #[cfg(any(target_os = "freebsd", target_os = "macos"))]
+            {
+                sin.sin_len = mem::size_of::<libc::sockaddr_in>() as u8;
+            }
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        WASM_AF_INET6 => {
+            if bytes.len() < 24 {
+                return Err(EINVAL);
+            }
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as sa_family_t;
+            sin6.sin6_port = u16::from_ne_bytes([bytes[2], bytes[3]]);
+            sin6.sin6_flowinfo = u32::from_ne_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+            sin6.sin6_addr.s6_addr.copy_from_slice(&bytes[8..24]);
+            if bytes.len() >= 28 {
+                sin6.sin6_scope_id =
+                    u32::from_le_bytes([bytes[24], bytes[25], bytes[26], bytes[27]]);
+            }
+            #[cfg(any(target_os = "freebsd", target_os = "macos"))]
+            {
+                sin6.sin6_len = mem::size_of::<libc::sockaddr_in6>() as u8;
+            }
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+        WASM_AF_UNIX => {
+            let sun = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_un) };
+            sun.sun_family = libc::AF_UNIX as sa_family_t;
+            let path = &bytes[2..];
+            if path.len() > sun.sun_path.len() {
+                return Err(EINVAL);
+            }
+            for (c, byte) in sun.sun_path.iter_mut().zip(path) {
+                *c = *byte as _;
+            }
+            let len = sun_path_offset() + path.len();
+            #[cfg(any(target_os = "freebsd", target_os = "macos"))]
+            {
+                sun.sun_len = len as u8;
+            }
+            len
+        }
+        _ => return Err(EAFNOSUPPORT),
+    };
+    Ok((storage, host_len as socklen_t))
+}
+
+/// Lays out the `sockaddr` of the host of `len` bytes as the module sees it.
+fn wasm_sockaddr(storage: &sockaddr_storage, len: socklen_t) -> Vec<u8> {
+    let mut bytes = vec![];
+    match storage.ss_family as c_int {
+        libc::AF_INET => {
+            let sin = unsafe { &*(storage as *const _ as *const libc::sockaddr_in) };
+            bytes.extend_from_slice(&(WASM_AF_INET as u16).to_le_bytes());
+            bytes.extend_from_slice(&sin.sin_port.to_ne_bytes());
+            bytes.extend_from_slice(&sin.sin_addr.s_addr.to_ne_bytes());
+            bytes.extend_from_slice(&[0; 8]);
+        }
+        libc::AF_INET6 => {
+            let sin6 = unsafe { &*(storage as *const _ as *const libc::sockaddr_in6) };
+            bytes.extend_from_slice(&(WASM_AF_INET6 as u16).to_le_bytes());
+            bytes.extend_from_slice(&sin6.sin6_port.to_ne_bytes());
+            bytes.extend_from_slice(&sin6.sin6_flowinfo.to_ne_bytes());
+            bytes.extend_from_slice(&sin6.sin6_addr.s6_addr);
+            bytes.extend_from_slice(&sin6.sin6_scope_id.to_le_bytes());
+        }
+        libc::AF_UNIX => {
+            let sun = unsafe { &*(storage as *const _ as *const libc::sockaddr_un) };
+            bytes.extend_from_slice(&(WASM_AF_UNIX as u16).to_le_bytes());
+            let path_len = (len as usize).saturating_sub(sun_path_offset());
+            bytes.extend(sun.sun_path.iter().take(path_len).map(|c| *c as u8));
+        }
+        family => bytes.extend_from_slice(&(family as u16).to_le_bytes()),
+    }
+    bytes
+}
+
+/// Writes the `sockaddr` of the host to the buffer of the module at
+/// `ptr`, truncating it to the size of the buffer, read from `len_ptr`
+/// and replaced by the size of the `sockaddr`. Nothing is written if
+/// `ptr` is null.
+fn write_sockaddr(
+    memory: &Memory,
+    storage: &sockaddr_storage,
+    host_len: socklen_t,
+    ptr: u32,
+    len_ptr: u32,
+) -> Result<(), c_int> {
+    if ptr == 0 {
+        return Ok(());
+    }
+    let bytes = wasm_sockaddr(storage, host_len);
+    let len = read_u32(memory, len_ptr)? as usize;
+    write_bytes(memory, ptr, &bytes[..len.min(bytes.len())]).ok_or(EFAULT)?;
+    write_u32(memory, len_ptr, bytes.len() as u32)
+}
+
+/// Calls `f` with a `sockaddr` of the host to fill and its length, and
+/// writes it to the module as `write_sockaddr` does.
+fn with_sockaddr(
+    memory: &Memory,
+    ptr: u32,
+    len_ptr: u32,
+    f: impl FnOnce(*mut sockaddr, *mut socklen_t) -> isize,
+) -> Result<c_int, c_int> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let mut host_len = mem::size_of::<sockaddr_storage>() as socklen_t;
+    let ret = if ptr == 0 {
+        check(f(std::ptr::null_mut(), std::ptr::null_mut()))?
+    } else {
+        check(f(&mut storage as *mut _ as *mut sockaddr, &mut host_len))?
+    };
+    write_sockaddr(memory, &storage, host_len, ptr, len_ptr)?;
+    Ok(ret)
+}
+
+/// Applies the `SOCK_NONBLOCK` and `SOCK_CLOEXEC` flags to the new socket
+/// `fd`.
+fn set_up_socket(fd: c_int, flags: c_int) -> Result<(), c_int> {
+    unsafe {
+        if flags & WASM_SOCK_NONBLOCK != 0 {
+            let status = libc::fcntl(fd, libc::F_GETFL);
+            check(status as isize)?;
+            check(libc::fcntl(fd, libc::F_SETFL, status | libc::O_NONBLOCK) as isize)?;
+        }
+        if flags & WASM_SOCK_CLOEXEC != 0 {
+            check(libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) as isize)?;
+        }
+        // On macOS, the sockets raise `SIGPIPE` unless told not to
+        #[cfg(target_os = "macos")]
+        {
+            let on: c_int = 1;
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_NOSIGPIPE,
+                &on as *const _ as *const c_void,
+                mem::size_of::<c_int>() as socklen_t,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn translate_domain(domain: c_int) -> Result<c_int, c_int> {
+    match domain {
+        WASM_AF_UNIX => Ok(libc::AF_UNIX),
+        WASM_AF_INET => Ok(libc::AF_INET),
+        WASM_AF_INET6 => Ok(libc::AF_INET6),
+        _ => Err(EAFNOSUPPORT),
+    }
+}
+
+/// Splits the type of a socket from its flags, translating it.
+fn translate_type(ty_and_flags: c_int) -> Result<(c_int, c_int), c_int> {
+    let flags = ty_and_flags & (WASM_SOCK_NONBLOCK | WASM_SOCK_CLOEXEC);
+    let ty = match ty_and_flags & !flags {
+        WASM_SOCK_STREAM => libc::SOCK_STREAM,
+        WASM_SOCK_DGRAM => libc::SOCK_DGRAM,
+        WASM_SOCK_RAW => libc::SOCK_RAW,
+        WASM_SOCK_SEQPACKET => libc::SOCK_SEQPACKET,
+        _ => return Err(EINVAL),
+    };
+    Ok((ty, flags))
+}
+
+/// OSX and BSD have completely different values, we must translate from emscripten's Linuxy
+/// value into one that we can pass to native syscalls
+fn translate_socket_name_flag(name: i32) -> i32 {
+    match name {
+        1 => libc::SO_DEBUG,
+        2 => libc::SO_REUSEADDR,
+        3 => libc::SO_TYPE,
+        4 => libc::SO_ERROR,
+        5 => libc::SO_DONTROUTE,
+        6 => libc::SO_BROADCAST,
+        7 => libc::SO_SNDBUF,
+        8 => libc::SO_RCVBUF,
+        9 => libc::SO_KEEPALIVE,
+        10 => libc::SO_OOBINLINE,
+        13 => libc::SO_LINGER,
+        15 => libc::SO_REUSEPORT,
+        18 => libc::SO_RCVLOWAT,
+        19 => libc::SO_SNDLOWAT,
+        20 => libc::SO_RCVTIMEO,
+        21 => libc::SO_SNDTIMEO,
+        30 => libc::SO_ACCEPTCONN,
+        otherwise => otherwise,
+    }
+}
+
+/// Translates the level and name of a socket option.
+fn translate_sockopt(level: c_int, name: c_int) -> (c_int, c_int) {
+    match level {
+        WASM_SOL_SOCKET => (libc::SOL_SOCKET, translate_socket_name_flag(name)),
+        WASM_IPPROTO_IPV6 if name == WASM_IPV6_V6ONLY => (level, libc::IPV6_V6ONLY),
+        _ => (level, name),
+    }
+}
+
+/// Whether the option is a `timeval`, which has 32 bits fields in the
+/// module.
+fn is_timeval(level: c_int, name: c_int) -> bool {
+    level == WASM_SOL_SOCKET && (name == WASM_SO_RCVTIMEO || name == WASM_SO_SNDTIMEO)
+}
+
+/// Reads the iovecs of the module as iovecs of the host.
+fn read_iovecs(memory: &Memory, ptr: u32, count: u32) -> Result<Vec<libc::iovec>, c_int> {
+    (0..count)
+        .map(|i| {
+            let iov = ptr
+                .checked_add(i.checked_mul(8).ok_or(EFAULT)?)
+                .ok_or(EFAULT)?;
+            let base = read_u32(memory, iov)?;
+            let len = read_u32(memory, iov + 4)?;
+            Ok(libc::iovec {
+                iov_base: buffer(memory, base, len)?,
+                iov_len: len as usize,
+            })
+        })
+        .collect()
+}
+
+/// The fields of a `msghdr` of the module.
+struct WasmMsghdr {
+    name: u32,
+    namelen: u32,
+    iov: u32,
+    iovlen: u32,
+    control: u32,
+    controllen: u32,
+}
+
+impl WasmMsghdr {
+    fn read(memory: &Memory, ptr: u32) -> Result<Self, c_int> {
+        let field = |index: u32| read_u32(memory, ptr.wrapping_add(index * 4));
+        Ok(Self {
+            name: field(0)?,
+            namelen: field(1)?,
+            iov: field(2)?,
+            iovlen: field(3)?,
+            control: field(4)?,
+            controllen: field(5)?,
+        })
+    }
+}
+
+/// Makes the call `call` of `socketcall`, getting its arguments from `arg`.
+fn socketcall(ctx: &EmEnv, call: u32, arg: &mut dyn FnMut() -> i32) -> Result<c_int, c_int> {
+    let memory = ctx.memory(0);
+    match call {
+        1 => {
+            debug!("socket: socket");
+            let domain = translate_domain(arg())?;
+            let (ty, flags) = translate_type(arg())?;
+            let protocol = arg();
+            let fd = check(unsafe { libc::socket(domain, ty, protocol) } as isize)?;
+            if let Err(errno) = set_up_socket(fd, flags) {
+                unsafe { libc::close(fd) };
+                return Err(errno);
+            }
+            Ok(fd)
+        }
+        2 | 3 => {
+            debug!("socket: {}", if call == 2 { "bind" } else { "connect" });
+            let (socket, ptr, len) = (arg(), arg() as u32, arg() as u32);
+            let (address, host_len) = read_sockaddr(&memory, ptr, len)?;
+            let address = &address as *const _ as *const sockaddr;
+            check(unsafe {
+                if call == 2 {
+                    libc::bind(socket, address, host_len)
+                } else {
+                    libc::connect(socket, address, host_len)
+                }
+            } as isize)
+        }
+        4 => {
+            debug!("socket: listen");
+            let (socket, backlog) = (arg(), arg());
+            check(unsafe { libc::listen(socket, backlog) } as isize)
+        }
+        5 | 18 => {
+            debug!("socket: accept");
+            let (socket, ptr, len_ptr) = (arg(), arg() as u32, arg() as u32);
+            let flags = if call == 18 { arg() } else { 0 };
+            let fd = with_sockaddr(&memory, ptr, len_ptr, |address, len| unsafe {
+                libc::accept(socket, address, len) as isize
+            })?;
+            if let Err(errno) = set_up_socket(fd, flags) {
+                unsafe { libc::close(fd) };
+                return Err(errno);
+            }
+            Ok(fd)
+        }
+        6 | 7 => {
+            debug!(
+                "socket: {}",
+                if call == 6 {
+                    "getsockname"
+                } else {
+                    "getpeername"
+                }
+            );
+            let (socket, ptr, len_ptr) = (arg(), arg() as u32, arg() as u32);
+            if ptr == 0 {
+                return Err(EFAULT);
+            }
+            with_sockaddr(&memory, ptr, len_ptr, |address, len| unsafe {
+                if call == 6 {
+                    libc::getsockname(socket, address, len) as isize
+                } else {
+                    libc::getpeername(socket, address, len) as isize
+                }
+            })
+        }
+        8 => {
+            debug!("socket: socketpair");
+            let domain = translate_domain(arg())?;
+            let (ty, flags) = translate_type(arg())?;
+            let (protocol, sv) = (arg(), arg() as u32);
+            let mut fds = [0; 2];
+            check(unsafe { libc::socketpair(domain, ty, protocol, fds.as_mut_ptr()) } as isize)?;
+            let result = set_up_socket(fds[0], flags)
+                .and_then(|_| set_up_socket(fds[1], flags))
+                .and_then(|_| write_u32(&memory, sv, fds[0] as u32))
+                .and_then(|_| write_u32(&memory, sv + 4, fds[1] as u32));
+            if let Err(errno) = result {
+                unsafe {
+                    libc::close(fds[0]);
+                    libc::close(fds[1]);
+                }
+                return Err(errno);
+            }
+            Ok(0)
+        }
+        9 | 11 => {
+            debug!("socket: {}", if call == 9 { "send" } else { "sendto" });
+            let (socket, buf, len, flags) = (arg(), arg() as u32, arg() as u32, arg());
+            let buf = buffer(&memory, buf, len)?;
+            let flags = send_flags(flags);
+            let (ptr, address_len) = if call == 11 {
+                (arg() as u32, arg() as u32)
+            } else {
+                (0, 0)
+            };
+            if ptr == 0 {
+                return check(unsafe { libc::send(socket, buf, len as usize, flags) });
+            }
+            let (address, host_len) = read_sockaddr(&memory, ptr, address_len)?;
+            check(unsafe {
+                libc::sendto(
+                    socket,
+                    buf,
+                    len as usize,
+                    flags,
+                    &address as *const _ as *const sockaddr,
+                    host_len,
+                )
+            })
+        }
+        10 | 12 => {
+            debug!("socket: {}", if call == 10 { "recv" } else { "recvfrom" });
+            let (socket, buf, len, flags) = (arg(), arg() as u32, arg() as u32, arg());
+            let buf = buffer(&memory, buf, len)?;
+            let flags = translate_msg_flags(flags, true);
+            let (ptr, len_ptr) = if call == 12 {
+                (arg() as u32, arg() as u32)
+            } else {
+                (0, 0)
+            };
+            with_sockaddr(&memory, ptr, len_ptr, |address, address_len| unsafe {
+                libc::recvfrom(socket, buf, len as usize, flags, address, address_len)
+            })
+        }
+        13 => {
+            debug!("socket: shutdown");
+            // `SHUT_RD`, `SHUT_WR` and `SHUT_RDWR` are the same everywhere
+            let (socket, how) = (arg(), arg());
+            check(unsafe { libc::shutdown(socket, how) } as isize)
+        }
+        14 => {
+            debug!("socket: setsockopt");
+            let (socket, wasm_level, wasm_name) = (arg(), arg(), arg());
+            let (value, len) = (arg() as u32, arg() as u32);
+            let (level, name) = translate_sockopt(wasm_level, wasm_name);
+            if is_timeval(wasm_level, wasm_name) {
+                if len < 8 {
+                    return Err(EINVAL);
+                }
+                let timeval = libc::timeval {
+                    tv_sec: read_u32(&memory, value)? as i32 as _,
+                    tv_usec: read_u32(&memory, value + 4)? as i32 as _,
+                };
+                return check(unsafe {
+                    libc::setsockopt(
+                        socket,
+                        level,
+                        name,
+                        &timeval as *const _ as *const c_void,
+                        mem::size_of::<libc::timeval>() as socklen_t,
+                    )
+                } as isize);
+            }
+            let value = buffer(&memory, value, len)?;
+            check(unsafe { libc::setsockopt(socket, level, name, value, len) } as isize)
+        }
+        15 => {
+            debug!("socket: getsockopt");
+            let (socket, wasm_level, wasm_name) = (arg(), arg(), arg());
+            let (value, len_ptr) = (arg() as u32, arg() as u32);
+            let (level, name) = translate_sockopt(wasm_level, wasm_name);
+            let len = read_u32(&memory, len_ptr)?;
+            if is_timeval(wasm_level, wasm_name) {
+                let mut timeval: libc::timeval = unsafe { mem::zeroed() };
+                let mut host_len = mem::size_of::<libc::timeval>() as socklen_t;
+                check(unsafe {
+                    libc::getsockopt(
+                        socket,
+                        level,
+                        name,
+                        &mut timeval as *mut _ as *mut c_void,
+                        &mut host_len,
+                    )
+                } as isize)?;
+                let mut bytes = (timeval.tv_sec as u32).to_le_bytes().to_vec();
+                bytes.extend_from_slice(&(timeval.tv_usec as u32).to_le_bytes());
+                write_bytes(&memory, value, &bytes[..(len as usize).min(8)]).ok_or(EFAULT)?;
+                write_u32(&memory, len_ptr, 8)?;
+                return Ok(0);
+            }
+            let value = buffer(&memory, value, len)?;
+            let mut host_len = len as socklen_t;
+            check(unsafe { libc::getsockopt(socket, level, name, value, &mut host_len) } as isize)?;
+            write_u32(&memory, len_ptr, host_len as u32)?;
+            Ok(0)
+        }
+        16 | 17 => {
+            debug!("socket: {}", if call == 16 { "sendmsg" } else { "recvmsg" });
+            let (socket, msg, flags) = (arg(), arg() as u32, arg());
+            let wasm_msg = WasmMsghdr::read(&memory, msg)?;
+            let mut iovecs = read_iovecs(&memory, wasm_msg.iov, wasm_msg.iovlen)?;
+            let mut host_msg: libc::msghdr = unsafe { mem::zeroed() };
+            host_msg.msg_iov = iovecs.as_mut_ptr();
+            host_msg.msg_iovlen = iovecs.len() as _;
+            let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+            if call == 16 {
+                // Ancillary data, which may pass file descriptors, isn't
+                // supported
+                if wasm_msg.controllen != 0 {
+                    return Err(EOPNOTSUPP);
+                }
+                if wasm_msg.name != 0 {
+                    let (address, host_len) =
+                        read_sockaddr(&memory, wasm_msg.name, wasm_msg.namelen)?;
+                    storage = address;
+                    host_msg.msg_name = &mut storage as *mut _ as *mut c_void;
+                    host_msg.msg_namelen = host_len;
+                }
+                return check(unsafe { libc::sendmsg(socket, &host_msg, send_flags(flags)) });
+            }
+            if wasm_msg.name != 0 {
+                host_msg.msg_name = &mut storage as *mut _ as *mut c_void;
+                host_msg.msg_namelen = mem::size_of::<sockaddr_storage>() as socklen_t;
+            }
+            let flags = translate_msg_flags(flags, true);
+            let ret = check(unsafe { libc::recvmsg(socket, &mut host_msg, flags) })?;
+            // The fields of the msghdr are 4 bytes each
+            if wasm_msg.name != 0 {
+                write_sockaddr(
+                    &memory,
+                    &storage,
+                    host_msg.msg_namelen,
+                    wasm_msg.name,
+                    msg + 4,
+                )?;
+            }
+            let mut msg_flags = translate_msg_flags(host_msg.msg_flags, false);
+            if wasm_msg.control != 0 && wasm_msg.controllen != 0 {
+                msg_flags |= translate_msg_flags(libc::MSG_CTRUNC, false);
+            }
+            write_u32(&memory, msg + 20, 0)?;
+            write_u32(&memory, msg + 24, msg_flags as u32)?;
+            Ok(ret)
+        }
+        _ => Err(ENOSYS),
+    }
+}
+
+/// socketcall
+pub fn ___syscall102(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
+    debug!("emscripten::___syscall102 (socketcall) {}", _which);
+    let call: u32 = varargs.get(ctx);
+    let mut socket_varargs: VarArgs = varargs.get(ctx);
+    if !get_emscripten_data(ctx).networking {
+        debug!("=> networking is disabled");
+        return -wasm_errno(ctx, EACCES);
+    }
+    let ret = socketcall(ctx, call, &mut || socket_varargs.get::<i32>(ctx))
+        .unwrap_or_else(|errno| -wasm_errno(ctx, errno));
+    debug!("=> call: {}, ret: {}", call, ret);
+    ret
+}
+
+/// The calls of `socketcall` the upstream ABI imports as `__syscall_*`
+/// functions.
+const SOCKET_SYSCALLS: &[(&str, u32)] = &[
+    ("__syscall_socket", 1),
+    ("__syscall_bind", 2),
+    ("__syscall_connect", 3),
+    ("__syscall_listen", 4),
+    ("__syscall_getsockname", 6),
+    ("__syscall_getpeername", 7),
+    ("__syscall_socketpair", 8),
+    ("__syscall_sendto", 11),
+    ("__syscall_recvfrom", 12),
+    ("__syscall_shutdown", 13),
+    ("__syscall_setsockopt", 14),
+    ("__syscall_getsockopt", 15),
+    ("__syscall_sendmsg", 16),
+    ("__syscall_recvmsg", 17),
+    ("__syscall_accept4", 18),
+];
+
+/// Gets the call of `socketcall` of the `__syscall_*` function `name`.
+pub(crate) fn socket_syscall_number(name: &str) -> Option<u32> {
+    SOCKET_SYSCALLS
+        .iter()
+        .find(|(syscall, _)| *syscall == name)
+        .map(|(_, call)| *call)
+}
+
+/// Makes the call `call` of `socketcall` for the `__syscall_*` function
+/// with the arguments `args`. Their number depends on the version of
+/// Emscripten, the functions being declared with the signatures the
+/// module imports.
+pub(crate) fn socket_syscall(ctx: &EmEnv, call: u32, args: &[Val]) -> c_int {
+    debug!("emscripten::socket_syscall {}", call);
+    if !get_emscripten_data(ctx).networking {
+        debug!("=> networking is disabled");
+        return -wasm_errno(ctx, EACCES);
+    }
+    let mut args = args.iter().map(|arg| arg.i32().unwrap_or(0));
+    socketcall(ctx, call, &mut || args.next().unwrap_or(0))
+        .unwrap_or_else(|errno| -wasm_errno(ctx, errno))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flags() {
+        let wasm = 0x2 | 0x40 | 0x100;
+        let host = translate_msg_flags(wasm, true);
+        assert_eq!(
+            host,
+            libc::MSG_PEEK | libc::MSG_DONTWAIT | libc::MSG_WAITALL
+        );
+        assert_eq!(translate_msg_flags(host, false), wasm);
+
+        assert_eq!(
+            translate_type(WASM_SOCK_STREAM | WASM_SOCK_NONBLOCK),
+            Ok((libc::SOCK_STREAM, WASM_SOCK_NONBLOCK))
+        );
+        assert_eq!(translate_type(42), Err(EINVAL));
+        assert_eq!(translate_domain(WASM_AF_INET6), Ok(libc::AF_INET6));
+    }
+
+    #[test]
+    fn sockaddr_layout() {
+        let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+        let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+        sin.sin_family = libc::AF_INET as sa_family_t;
+        sin.sin_port = 8080u16.to_be();
+        sin.sin_addr.s_addr = u32::from_ne_bytes([127, 0, 0, 1]);
+        let bytes = wasm_sockaddr(&storage, mem::size_of::<libc::sockaddr_in>() as socklen_t);
+        assert_eq!(
+            bytes,
+            [2, 0, 0x1f, 0x90, 127, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+}
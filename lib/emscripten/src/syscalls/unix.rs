@@ -4,16 +4,13 @@ use libc::size_t;
 /// NOTE: TODO: These syscalls only support wasm_32 for now because they assume offsets are u32
 /// Syscall list: https://www.cs.utexas.edu/~bismith/test/syscalls/syscalls32.html
 use libc::{
-    accept,
     access,
-    bind,
     c_char,
     c_int,
     c_ulong,
     c_void,
     chown,
     // fcntl, setsockopt, getppid
-    connect,
     dup,
     dup2,
     fchmod,
@@ -25,23 +22,16 @@ use libc::{
     geteuid,
     getgid,
     getgroups,
-    getpeername,
     getpgid,
     getrusage,
-    getsockname,
-    getsockopt,
     getuid,
     gid_t,
-    in_addr_t,
-    in_port_t,
     ioctl,
     lchown,
     link,
     // iovec,
-    listen,
     mkdir,
     mode_t,
-    msghdr,
     nice,
     off_t,
     open,
@@ -50,20 +40,11 @@ use libc::{
     pwrite,
     readdir,
     // readv,
-    recvfrom,
-    recvmsg,
     // ENOTTY,
     rusage,
-    sa_family_t,
     // writev,
     select,
-    sendmsg,
-    sendto,
     setpgid,
-    setsockopt,
-    sockaddr,
-    socket,
-    socklen_t,
     stat,
     symlink,
     uid_t,
@@ -75,7 +56,6 @@ use libc::{
     FIONBIO,
     F_GETFD,
     F_SETFD,
-    SOL_SOCKET,
     TIOCGWINSZ,
     TIOCSPGRP,
     // TCGETS,
@@ -113,7 +93,6 @@ fn translate_ioctl(wasm_ioctl: u32) -> c_ulong {
 #[allow(unused_imports)]
 use std::ffi::CStr;
 
-use crate::env::EmSockAddr;
 use crate::utils::{self, get_cstr_path};
 use crate::EmEnv;
 #[allow(unused_imports)]
@@ -147,14 +126,6 @@ use libc::madvise;
 #[cfg(not(any(target_os = "freebsd", target_os = "macos")))]
 use libc::{fdatasync, ftruncate64, lstat, madvise, wait4};
 
-// Another conditional constant for name resolution: Macos et iOS use
-// SO_NOSIGPIPE as a setsockopt flag to disable SIGPIPE emission on socket.
-// Other platforms do otherwise.
-#[cfg(target_os = "macos")]
-use libc::SO_NOSIGPIPE;
-#[cfg(not(target_os = "macos"))]
-const SO_NOSIGPIPE: c_int = 0;
-
 /// open
 pub fn ___syscall5(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall5 (open) {}", _which);
@@ -474,6 +445,11 @@ pub fn ___syscall54(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
 
     let fd: i32 = varargs.get(ctx);
     let request: u32 = varargs.get(ctx);
+    ioctl_varargs(ctx, fd, request, varargs)
+}
+
+/// Makes the ioctl `request`, getting its argument from `varargs`.
+pub(super) fn ioctl_varargs(ctx: &EmEnv, fd: i32, request: u32, mut varargs: VarArgs) -> c_int {
     debug!("=> fd: {}, op: {}", fd, request);
 
     // Got the equivalents here: https://code.woboq.org/linux/linux/include/uapi/asm-generic/ioctls.h.html
@@ -506,325 +482,6 @@ pub fn ___syscall54(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     }
 }
 
-const SOCK_NON_BLOCK: i32 = 2048;
-const SOCK_CLOEXC: i32 = 0x80000;
-
-// socketcall
-#[allow(clippy::cast_ptr_alignment)]
-pub fn ___syscall102(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
-    debug!("emscripten::___syscall102 (socketcall) {}", _which);
-    let call: u32 = varargs.get(ctx);
-    let mut socket_varargs: VarArgs = varargs.get(ctx);
-    let memory = ctx.memory(0);
-
-    // migrating to EmSockAddr, port being separate here is nice, should update that too
-    #[repr(C)]
-    pub struct GuestSockaddrIn {
-        pub sin_family: sa_family_t, // u16
-        pub sin_port: in_port_t,     // u16
-        pub sin_addr: GuestInAddr,   // u32
-        pub sin_zero: [u8; 8],       // u8 * 8
-                                     // 2 + 2 + 4 + 8 = 16
-    }
-
-    #[repr(C)]
-    pub struct GuestInAddr {
-        pub s_addr: in_addr_t, // u32
-    }
-
-    match call {
-        1 => {
-            debug!("socket: socket");
-            // socket (domain: c_int, ty: c_int, protocol: c_int) -> c_int
-            let domain: i32 = socket_varargs.get(ctx);
-            let ty_and_flags: i32 = socket_varargs.get(ctx);
-            let protocol: i32 = socket_varargs.get(ctx);
-            let ty = ty_and_flags & (!SOCK_NON_BLOCK) & (!SOCK_CLOEXC);
-            let fd = unsafe { socket(domain, ty, protocol) };
-
-            if ty_and_flags & SOCK_CLOEXC != 0 {
-                // set_cloexec
-                unsafe {
-                    ioctl(fd, translate_ioctl(WASM_FIOCLEX) as _);
-                };
-            }
-
-            if ty_and_flags & SOCK_NON_BLOCK != 0 {
-                // do something here
-                unimplemented!("non blocking sockets");
-            }
-
-            // why is this here?
-            type T = u32;
-            let payload = 1 as *const T as _;
-            unsafe {
-                setsockopt(
-                    fd,
-                    SOL_SOCKET,
-                    SO_NOSIGPIPE,
-                    payload,
-                    mem::size_of::<T>() as socklen_t,
-                );
-            };
-
-            debug!(
-                "=> domain: {}, type: {}, protocol: {} = fd: {}",
-                domain, ty, protocol, fd
-            );
-            fd as _
-        }
-        2 => {
-            debug!("socket: bind");
-            // bind (socket: c_int, address: *const sockaddr, address_len: socklen_t) -> c_int
-            // TODO: Emscripten has a different signature.
-            let socket = socket_varargs.get(ctx);
-            let address: u32 = socket_varargs.get(ctx);
-            let address_len = socket_varargs.get(ctx);
-            let address = emscripten_memory_pointer!(&memory, address) as *mut sockaddr;
-
-            // Debug received address
-            let _proper_address = address as *const GuestSockaddrIn;
-            debug!(
-                    "=> address.sin_family: {:?}, address.sin_port: {:?}, address.sin_addr.s_addr: {:?}",
-                unsafe { (*_proper_address).sin_family }, unsafe { (*_proper_address).sin_port }, unsafe { (*_proper_address).sin_addr.s_addr }
-                );
-
-            let status = unsafe { bind(socket, address, address_len) };
-            // debug!("=> status: {}", status);
-            debug!(
-                "=> socketfd: {}, address: {:?}, address_len: {} = status: {}",
-                socket, address, address_len, status
-            );
-            status
-            // -1
-        }
-        3 => {
-            debug!("socket: connect");
-            // connect (socket: c_int, address: *const sockaddr, len: socklen_t) -> c_int
-            // TODO: Emscripten has a different signature.
-            let socket = socket_varargs.get(ctx);
-            let address: u32 = socket_varargs.get(ctx);
-            let address_len = socket_varargs.get(ctx);
-            let address = emscripten_memory_pointer!(&memory, address) as *mut sockaddr;
-            unsafe { connect(socket, address, address_len) }
-        }
-        4 => {
-            debug!("socket: listen");
-            // listen (socket: c_int, backlog: c_int) -> c_int
-            let socket = socket_varargs.get(ctx);
-            let backlog: i32 = socket_varargs.get(ctx);
-            let status = unsafe { listen(socket, backlog) };
-            debug!(
-                "=> socketfd: {}, backlog: {} = status: {}",
-                socket, backlog, status
-            );
-            status
-        }
-        5 => {
-            debug!("socket: accept");
-            // accept (socket: c_int, address: *mut sockaddr, address_len: *mut socklen_t) -> c_int
-            let socket: i32 = socket_varargs.get(ctx);
-            let address: WasmPtr<EmSockAddr> = socket_varargs.get(ctx);
-            let address_len: WasmPtr<u32> = socket_varargs.get(ctx);
-
-            debug!(
-                "=> socket: {}, address: {:?}, address_len: {}",
-                socket,
-                address.deref(&memory).unwrap().get(),
-                address_len.deref(&memory).unwrap().get()
-            );
-            let address_len_addr = unsafe { address_len.deref_mut(&memory).unwrap().get_mut() };
-            // let mut address_len_addr: socklen_t = 0;
-
-            let mut host_address: sockaddr = sockaddr {
-                sa_family: Default::default(),
-                sa_data: Default::default(),
-                #[cfg(any(target_os = "freebsd", target_os = "macos"))]
-                sa_len: Default::default(),
-            };
-            let fd = unsafe { accept(socket, &mut host_address, address_len_addr) };
-            let address_addr = unsafe { address.deref_mut(&memory).unwrap().get_mut() };
-
-            address_addr.sa_family = host_address.sa_family as _;
-            address_addr.sa_data = host_address.sa_data;
-
-            // why is this here?
-            // set_cloexec
-            unsafe {
-                ioctl(fd, translate_ioctl(WASM_FIOCLEX) as _);
-            };
-
-            debug!(
-                "address: {:?}, len: {}, result fd = {}",
-                address_addr, address_len_addr, fd
-            );
-
-            fd as _
-        }
-        6 => {
-            debug!("socket: getsockname");
-            // getsockname (socket: c_int, address: *mut sockaddr, address_len: *mut socklen_t) -> c_int
-            let socket: i32 = socket_varargs.get(ctx);
-            let address: WasmPtr<EmSockAddr> = socket_varargs.get(ctx);
-            let address_len: WasmPtr<u32> = socket_varargs.get(ctx);
-            let address_len_addr = unsafe { address_len.deref_mut(&memory).unwrap().get_mut() };
-
-            let mut sock_addr_host: sockaddr = sockaddr {
-                sa_family: Default::default(),
-                sa_data: Default::default(),
-                #[cfg(any(target_os = "freebsd", target_os = "macos"))]
-                sa_len: Default::default(),
-            };
-            let ret = unsafe {
-                getsockname(
-                    socket,
-                    &mut sock_addr_host as *mut sockaddr,
-                    address_len_addr as *mut u32,
-                )
-            };
-            // translate from host data into emscripten data
-            let mut address_mut = unsafe { address.deref_mut(&memory).unwrap().get_mut() };
-            address_mut.sa_family = sock_addr_host.sa_family as _;
-            address_mut.sa_data = sock_addr_host.sa_data;
-
-            debug!(
-                "=> socket: {}, address, {:?}, address_len: {}, result = {}",
-                socket, address_mut, address_len_addr, ret
-            );
-
-            ret
-        }
-        7 => {
-            debug!("socket: getpeername");
-            // getpeername (socket: c_int, address: *mut sockaddr, address_len: *mut socklen_t) -> c_int
-            let socket = socket_varargs.get(ctx);
-            let address: u32 = socket_varargs.get(ctx);
-            let address_len: u32 = socket_varargs.get(ctx);
-            let address = emscripten_memory_pointer!(memory, address) as *mut sockaddr;
-            let address_len_addr =
-                emscripten_memory_pointer!(memory, address_len) as *mut socklen_t;
-            unsafe { getpeername(socket, address, address_len_addr) }
-        }
-        11 => {
-            debug!("socket: sendto");
-            // sendto (socket: c_int, buf: *const c_void, len: size_t, flags: c_int, addr: *const sockaddr, addrlen: socklen_t) -> ssize_t
-            let socket = socket_varargs.get(ctx);
-            let buf: u32 = socket_varargs.get(ctx);
-            let flags = socket_varargs.get(ctx);
-            let len: i32 = socket_varargs.get(ctx);
-            let address: u32 = socket_varargs.get(ctx);
-            let address_len = socket_varargs.get(ctx);
-            let buf_addr = emscripten_memory_pointer!(memory, buf) as _;
-            let address = emscripten_memory_pointer!(memory, address) as *mut sockaddr;
-            unsafe { sendto(socket, buf_addr, flags, len, address, address_len) as i32 }
-        }
-        12 => {
-            debug!("socket: recvfrom");
-            // recvfrom (socket: c_int, buf: *const c_void, len: size_t, flags: c_int, addr: *const sockaddr, addrlen: socklen_t) -> ssize_t
-            let socket = socket_varargs.get(ctx);
-            let buf: u32 = socket_varargs.get(ctx);
-            let len: i32 = socket_varargs.get(ctx);
-            let flags: i32 = socket_varargs.get(ctx);
-            let address: u32 = socket_varargs.get(ctx);
-            let address_len: u32 = socket_varargs.get(ctx);
-            let buf_addr = emscripten_memory_pointer!(memory, buf) as _;
-            let address = emscripten_memory_pointer!(memory, address) as *mut sockaddr;
-            let address_len_addr =
-                emscripten_memory_pointer!(memory, address_len) as *mut socklen_t;
-            unsafe {
-                recvfrom(
-                    socket,
-                    buf_addr,
-                    len as usize,
-                    flags,
-                    address,
-                    address_len_addr,
-                ) as i32
-            }
-        }
-        14 => {
-            debug!("socket: setsockopt");
-            // OSX and BSD have completely different values, be very careful here
-            //      https://github.com/openbsd/src/blob/master/sys/sys/socket.h#L156
-            // setsockopt (socket: c_int, level: c_int, name: c_int, value: *const c_void, option_len: socklen_t) -> c_int
-
-            let socket = socket_varargs.get(ctx);
-            let level: i32 = socket_varargs.get(ctx);
-            let level = if level == 1 { SOL_SOCKET } else { level };
-            let untranslated_name: i32 = socket_varargs.get(ctx);
-            let value: u32 = socket_varargs.get(ctx);
-            let option_len: u32 = socket_varargs.get(ctx);
-            let value_addr = emscripten_memory_pointer!(memory, value) as *const libc::c_void;
-            let name: i32 = translate_socket_name_flag(untranslated_name);
-
-            let ret = unsafe { setsockopt(socket, level, name, value_addr, option_len) };
-
-            debug!("=> socketfd: {}, level: {}, name: {}, value_addr: {:?}, option_len: {} = status: {}", socket, level, untranslated_name, value_addr, option_len, ret);
-            ret
-        }
-        15 => {
-            debug!("socket: getsockopt");
-            // getsockopt (sockfd: c_int, level: c_int, optname: c_int, optval: *mut c_void, optlen: *mut socklen_t) -> c_int
-            let socket = socket_varargs.get(ctx);
-            let level: i32 = socket_varargs.get(ctx);
-            let level = if level == 1 { SOL_SOCKET } else { level };
-            let untranslated_name: i32 = socket_varargs.get(ctx);
-            let name: i32 = translate_socket_name_flag(untranslated_name);
-            let value: u32 = socket_varargs.get(ctx);
-            let option_len: u32 = socket_varargs.get(ctx);
-            let value_addr = emscripten_memory_pointer!(memory, value) as _;
-            let option_len_addr = emscripten_memory_pointer!(memory, option_len) as *mut socklen_t;
-            unsafe { getsockopt(socket, level, name, value_addr, option_len_addr) }
-        }
-        16 => {
-            debug!("socket: sendmsg");
-            // sendmsg (fd: c_int, msg: *const msghdr, flags: c_int) -> ssize_t
-            let socket: i32 = socket_varargs.get(ctx);
-            let msg: u32 = socket_varargs.get(ctx);
-            let flags: i32 = socket_varargs.get(ctx);
-            let msg_addr = emscripten_memory_pointer!(memory, msg) as *const msghdr;
-            unsafe { sendmsg(socket, msg_addr, flags) as i32 }
-        }
-        17 => {
-            debug!("socket: recvmsg");
-            // recvmsg (fd: c_int, msg: *mut msghdr, flags: c_int) -> ssize_t
-            let socket: i32 = socket_varargs.get(ctx);
-            let msg: u32 = socket_varargs.get(ctx);
-            let flags: i32 = socket_varargs.get(ctx);
-            let msg_addr = emscripten_memory_pointer!(memory, msg) as *mut msghdr;
-            unsafe { recvmsg(socket, msg_addr, flags) as i32 }
-        }
-        _ => {
-            // others
-            -1
-        }
-    }
-}
-
-/// OSX and BSD have completely different values, we must translate from emscripten's Linuxy
-/// value into one that we can pass to native syscalls
-fn translate_socket_name_flag(name: i32) -> i32 {
-    match name {
-        2 => libc::SO_REUSEADDR,
-        3 => libc::SO_TYPE,
-        4 => libc::SO_ERROR,
-        5 => libc::SO_DONTROUTE,
-        6 => libc::SO_BROADCAST,
-        7 => libc::SO_SNDBUF,
-        8 => libc::SO_RCVBUF,
-        9 => libc::SO_KEEPALIVE,
-        10 => libc::SO_OOBINLINE,
-        13 => libc::SO_LINGER,
-        18 => libc::SO_RCVLOWAT,
-        19 => libc::SO_SNDLOWAT,
-        20 => libc::SO_RCVTIMEO,
-        21 => libc::SO_SNDTIMEO,
-        // SO_DEBUG missing
-        30 => libc::SO_ACCEPTCONN,
-        otherwise => otherwise,
-    }
-}
-
 /// getpgid
 pub fn ___syscall132(ctx: &EmEnv, _which: c_int, mut varargs: VarArgs) -> c_int {
     debug!("emscripten::___syscall132 (getpgid)");
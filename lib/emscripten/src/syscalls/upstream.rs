@@ -0,0 +1,540 @@
+//! The syscalls of the upstream ABI, which take their arguments directly
+//! instead of through varargs, and return the negated WASI errno of their
+//! error. Emscripten 2 names them after the syscalls (`__sys_open`), and
+//! Emscripten 3 after the `*at` syscalls they became (`__syscall_openat`).
+use super::{buffer, check, translate_flags, EFAULT};
+use crate::errno::{last_errno, wasm_errno};
+use crate::utils::{get_cstr_path, get_current_directory, write_bytes};
+use crate::varargs::VarArgs;
+use crate::EmEnv;
+use libc::{c_char, c_int};
+use std::ffi::{CStr, CString};
+use std::mem;
+use wasmer::Memory;
+
+const WASM_AT_FDCWD: c_int = -100;
+const WASM_AT_SYMLINK_NOFOLLOW: c_int = 0x100;
+const WASM_AT_REMOVEDIR: c_int = 0x200;
+const WASM_AT_EACCESS: c_int = 0x200;
+
+const WASM_O_ACCMODE: c_int = 0o3;
+const WASM_O_CREAT: c_int = 0o100;
+const WASM_O_CLOEXEC: c_int = 0o2000000;
+
+const WASM_F_DUPFD: c_int = 0;
+const WASM_F_GETFD: c_int = 1;
+const WASM_F_SETFD: c_int = 2;
+const WASM_F_GETFL: c_int = 3;
+const WASM_F_SETFL: c_int = 4;
+const WASM_F_GETLK: c_int = 12;
+const WASM_F_SETLK: c_int = 13;
+const WASM_F_SETLKW: c_int = 14;
+const WASM_F_DUPFD_CLOEXEC: c_int = 1030;
+const WASM_F_UNLCK: i16 = 2;
+
+const EINVAL: c_int = 22;
+const ERANGE: c_int = 34;
+
+/// The `O_*` flags of the module, and the ones of the host. The access
+/// modes are the same everywhere.
+const OPEN_FLAGS: &[(c_int, c_int)] = &[
+    (0o100, libc::O_CREAT),
+    (0o200, libc::O_EXCL),
+    (0o400, libc::O_NOCTTY),
+    (0o1000, libc::O_TRUNC),
+    (0o2000, libc::O_APPEND),
+    (0o4000, libc::O_NONBLOCK),
+    (0o200000, libc::O_DIRECTORY),
+    (0o400000, libc::O_NOFOLLOW),
+    (0o2000000, libc::O_CLOEXEC),
+];
+
+/// The offsets of the fields of `struct stat` in the module, whose layout
+/// changed in Emscripten 3.
+struct StatLayout {
+    dev: usize,
+    ino_truncated: Option<usize>,
+    mode: usize,
+    nlink: usize,
+    uid: usize,
+    gid: usize,
+    rdev: usize,
+    size: usize,
+    blksize: usize,
+    blocks: usize,
+    times: [usize; 3],
+    ino: usize,
+    /// Whether `time_t` has 64 bits
+    time64: bool,
+}
+
+const STAT_LAYOUT_2: StatLayout = StatLayout {
+    dev: 0,
+    ino_truncated: Some(8),
+    mode: 12,
+    nlink: 16,
+    uid: 20,
+    gid: 24,
+    rdev: 28,
+    size: 40,
+    blksize: 48,
+    blocks: 52,
+    times: [56, 64, 72],
+    ino: 80,
+    time64: false,
+};
+
+const STAT_LAYOUT_3: StatLayout = StatLayout {
+    dev: 0,
+    ino_truncated: None,
+    mode: 4,
+    nlink: 8,
+    uid: 12,
+    gid: 16,
+    rdev: 20,
+    size: 24,
+    blksize: 32,
+    blocks: 36,
+    times: [40, 56, 72],
+    ino: 88,
+    time64: true,
+};
+
+/// Turns the result of a syscall into its return value.
+fn ret(ctx: &EmEnv, result: Result<c_int, c_int>) -> c_int {
+    result.unwrap_or_else(|errno| -wasm_errno(ctx, errno))
+}
+
+/// Reads the path at `path`, rebased on the mapped directories.
+fn read_path(ctx: &EmEnv, path: u32) -> CString {
+    let ptr = emscripten_memory_pointer!(ctx.memory(0), path) as *const c_char;
+    get_cstr_path(ctx, ptr as *const _).unwrap_or_else(|| unsafe { CStr::from_ptr(ptr) }.into())
+}
+
+fn translate_dirfd(dirfd: c_int) -> c_int {
+    if dirfd == WASM_AT_FDCWD {
+        libc::AT_FDCWD
+    } else {
+        dirfd
+    }
+}
+
+fn translate_open_flags(flags: c_int, to_host: bool) -> c_int {
+    // The access modes are the same everywhere
+    (flags & WASM_O_ACCMODE) | translate_flags(flags, OPEN_FLAGS, to_host)
+}
+
+fn write_stat(
+    memory: &Memory,
+    buf: u32,
+    stat: &libc::stat,
+    layout: &StatLayout,
+) -> Result<(), c_int> {
+    let mut bytes = vec![0; layout.ino + 8];
+    let mut put = |offset: usize, value: &[u8]| {
+        bytes[offset..offset + value.len()].copy_from_slice(value);
+    };
+    put(layout.dev, &(stat.st_dev as u32).to_le_bytes());
+    if let Some(offset) = layout.ino_truncated {
+        put(offset, &(stat.st_ino as u32).to_le_bytes());
+    }
+    put(layout.mode, &(stat.st_mode as u32).to_le_bytes());
+    put(layout.nlink, &(stat.st_nlink as u32).to_le_bytes());
+    put(layout.uid, &(stat.st_uid as u32).to_le_bytes());
+    put(layout.gid, &(stat.st_gid as u32).to_le_bytes());
+    put(layout.rdev, &(stat.st_rdev as u32).to_le_bytes());
+    put(layout.size, &(stat.st_size as i64).to_le_bytes());
+    put(layout.blksize, &(stat.st_blksize as i32).to_le_bytes());
+    put(layout.blocks, &(stat.st_blocks as i32).to_le_bytes());
+    let times = [
+        (stat.st_atime, stat.st_atime_nsec),
+        (stat.st_mtime, stat.st_mtime_nsec),
+        (stat.st_ctime, stat.st_ctime_nsec),
+    ];
+    for (offset, (sec, nsec)) in layout.times.iter().zip(times.iter()) {
+        if layout.time64 {
+            put(*offset, &(*sec as i64).to_le_bytes());
+            put(offset + 8, &(*nsec as i32).to_le_bytes());
+        } else {
+            put(*offset, &(*sec as i32).to_le_bytes());
+            put(offset + 4, &(*nsec as i32).to_le_bytes());
+        }
+    }
+    put(layout.ino, &(stat.st_ino as u64).to_le_bytes());
+    write_bytes(memory, buf, &bytes).ok_or(EFAULT)
+}
+
+fn fstat(ctx: &EmEnv, fd: c_int, buf: u32, layout: &StatLayout) -> c_int {
+    debug!("=> fd: {}, buf: {}", fd, buf);
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    ret(
+        ctx,
+        check(unsafe { libc::fstat(fd, &mut stat) } as isize)
+            .and_then(|_| write_stat(&ctx.memory(0), buf, &stat, layout))
+            .map(|_| 0),
+    )
+}
+
+fn stat_at(
+    ctx: &EmEnv,
+    dirfd: c_int,
+    path: u32,
+    buf: u32,
+    flags: c_int,
+    layout: &StatLayout,
+) -> c_int {
+    let path = read_path(ctx, path);
+    debug!("=> dirfd: {}, path: {:?}, buf: {}", dirfd, path, buf);
+    let host_flags = if flags & WASM_AT_SYMLINK_NOFOLLOW != 0 {
+        libc::AT_SYMLINK_NOFOLLOW
+    } else {
+        0
+    };
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    ret(
+        ctx,
+        check(
+            unsafe { libc::fstatat(translate_dirfd(dirfd), path.as_ptr(), &mut stat, host_flags) }
+                as isize,
+        )
+        .and_then(|_| write_stat(&ctx.memory(0), buf, &stat, layout))
+        .map(|_| 0),
+    )
+}
+
+/// openat
+pub fn __syscall_openat(
+    ctx: &EmEnv,
+    dirfd: c_int,
+    path: u32,
+    flags: c_int,
+    mut varargs: VarArgs,
+) -> c_int {
+    debug!("emscripten::__syscall_openat");
+    let path = read_path(ctx, path);
+    let mode: u32 = if flags & WASM_O_CREAT != 0 {
+        varargs.get(ctx)
+    } else {
+        0
+    };
+    debug!("=> path: {:?}, flags: {}, mode: {}", path, flags, mode);
+    ret(
+        ctx,
+        check(unsafe {
+            libc::openat(
+                translate_dirfd(dirfd),
+                path.as_ptr(),
+                translate_open_flags(flags, true),
+                mode as libc::c_uint,
+            )
+        } as isize),
+    )
+}
+
+/// open
+pub fn __sys_open(ctx: &EmEnv, path: u32, flags: c_int, varargs: VarArgs) -> c_int {
+    debug!("emscripten::__sys_open");
+    __syscall_openat(ctx, WASM_AT_FDCWD, path, flags, varargs)
+}
+
+/// fcntl64
+pub fn __syscall_fcntl64(ctx: &EmEnv, fd: c_int, cmd: c_int, mut varargs: VarArgs) -> c_int {
+    debug!("emscripten::__syscall_fcntl64 {} {}", fd, cmd);
+    let result = unsafe {
+        match cmd {
+            WASM_F_DUPFD | WASM_F_DUPFD_CLOEXEC => {
+                let min: c_int = varargs.get(ctx);
+                let host_cmd = if cmd == WASM_F_DUPFD {
+                    libc::F_DUPFD
+                } else {
+                    libc::F_DUPFD_CLOEXEC
+                };
+                check(libc::fcntl(fd, host_cmd, min) as isize)
+            }
+            // `FD_CLOEXEC` is the same everywhere
+            WASM_F_GETFD => check(libc::fcntl(fd, libc::F_GETFD) as isize),
+            WASM_F_SETFD => {
+                let flags: c_int = varargs.get(ctx);
+                check(libc::fcntl(fd, libc::F_SETFD, flags) as isize)
+            }
+            WASM_F_GETFL => check(libc::fcntl(fd, libc::F_GETFL) as isize)
+                .map(|flags| translate_open_flags(flags, false)),
+            WASM_F_SETFL => {
+                let flags: c_int = varargs.get(ctx);
+                check(libc::fcntl(fd, libc::F_SETFL, translate_open_flags(flags, true)) as isize)
+            }
+            // The locks always succeed, like in the JavaScript runtime
+            WASM_F_GETLK => {
+                let lock: u32 = varargs.get(ctx);
+                write_bytes(&ctx.memory(0), lock, &WASM_F_UNLCK.to_le_bytes())
+                    .ok_or(EFAULT)
+                    .map(|_| 0)
+            }
+            WASM_F_SETLK | WASM_F_SETLKW => Ok(0),
+            _ => Err(EINVAL),
+        }
+    };
+    ret(ctx, result)
+}
+
+/// ioctl
+pub fn __syscall_ioctl(ctx: &EmEnv, fd: c_int, request: u32, varargs: VarArgs) -> c_int {
+    debug!("emscripten::__syscall_ioctl");
+    let result = super::unix::ioctl_varargs(ctx, fd, request, varargs);
+    if result < 0 {
+        -wasm_errno(ctx, last_errno())
+    } else {
+        result
+    }
+}
+
+/// fstat64
+pub fn __syscall_fstat64(ctx: &EmEnv, fd: c_int, buf: u32) -> c_int {
+    debug!("emscripten::__syscall_fstat64");
+    fstat(ctx, fd, buf, &STAT_LAYOUT_3)
+}
+
+/// fstat64, of Emscripten 2
+pub fn __sys_fstat64(ctx: &EmEnv, fd: c_int, buf: u32) -> c_int {
+    debug!("emscripten::__sys_fstat64");
+    fstat(ctx, fd, buf, &STAT_LAYOUT_2)
+}
+
+/// stat64
+pub fn __syscall_stat64(ctx: &EmEnv, path: u32, buf: u32) -> c_int {
+    debug!("emscripten::__syscall_stat64");
+    stat_at(ctx, WASM_AT_FDCWD, path, buf, 0, &STAT_LAYOUT_3)
+}
+
+/// stat64, of Emscripten 2
+pub fn __sys_stat64(ctx: &EmEnv, path: u32, buf: u32) -> c_int {
+    debug!("emscripten::__sys_stat64");
+    stat_at(ctx, WASM_AT_FDCWD, path, buf, 0, &STAT_LAYOUT_2)
+}
+
+/// lstat64
+pub fn __syscall_lstat64(ctx: &EmEnv, path: u32, buf: u32) -> c_int {
+    debug!("emscripten::__syscall_lstat64");
+    stat_at(
+        ctx,
+        WASM_AT_FDCWD,
+        path,
+        buf,
+        WASM_AT_SYMLINK_NOFOLLOW,
+        &STAT_LAYOUT_3,
+    )
+}
+
+/// lstat64, of Emscripten 2
+pub fn __sys_lstat64(ctx: &EmEnv, path: u32, buf: u32) -> c_int {
+    debug!("emscripten::__sys_lstat64");
+    stat_at(
+        ctx,
+        WASM_AT_FDCWD,
+        path,
+        buf,
+        WASM_AT_SYMLINK_NOFOLLOW,
+        &STAT_LAYOUT_2,
+    )
+}
+
+/// newfstatat
+pub fn __syscall_newfstatat(ctx: &EmEnv, dirfd: c_int, path: u32, buf: u32, flags: c_int) -> c_int {
+    debug!("emscripten::__syscall_newfstatat");
+    stat_at(ctx, dirfd, path, buf, flags, &STAT_LAYOUT_3)
+}
+
+/// getcwd
+pub fn __syscall_getcwd(ctx: &EmEnv, buf: u32, size: u32) -> c_int {
+    debug!("emscripten::__syscall_getcwd");
+    let cwd = match get_current_directory(ctx) {
+        Some(cwd) => cwd.to_string_lossy().into_owned(),
+        // EIO
+        None => return -wasm_errno(ctx, 5),
+    };
+    let mut bytes = cwd.into_bytes();
+    bytes.push(0);
+    if bytes.len() > size as usize {
+        return -wasm_errno(ctx, ERANGE);
+    }
+    ret(
+        ctx,
+        write_bytes(&ctx.memory(0), buf, &bytes)
+            .ok_or(EFAULT)
+            .map(|_| bytes.len() as c_int),
+    )
+}
+
+/// mkdirat
+pub fn __syscall_mkdirat(ctx: &EmEnv, dirfd: c_int, path: u32, mode: u32) -> c_int {
+    debug!("emscripten::__syscall_mkdirat");
+    let path = read_path(ctx, path);
+    ret(
+        ctx,
+        check(unsafe { libc::mkdirat(translate_dirfd(dirfd), path.as_ptr(), mode as _) } as isize),
+    )
+}
+
+/// mkdir
+pub fn __sys_mkdir(ctx: &EmEnv, path: u32, mode: u32) -> c_int {
+    debug!("emscripten::__sys_mkdir");
+    __syscall_mkdirat(ctx, WASM_AT_FDCWD, path, mode)
+}
+
+/// unlinkat
+pub fn __syscall_unlinkat(ctx: &EmEnv, dirfd: c_int, path: u32, flags: c_int) -> c_int {
+    debug!("emscripten::__syscall_unlinkat");
+    let path = read_path(ctx, path);
+    let host_flags = if flags & WASM_AT_REMOVEDIR != 0 {
+        libc::AT_REMOVEDIR
+    } else {
+        0
+    };
+    ret(
+        ctx,
+        check(
+            unsafe { libc::unlinkat(translate_dirfd(dirfd), path.as_ptr(), host_flags) } as isize,
+        ),
+    )
+}
+
+/// unlink
+pub fn __sys_unlink(ctx: &EmEnv, path: u32) -> c_int {
+    debug!("emscripten::__sys_unlink");
+    __syscall_unlinkat(ctx, WASM_AT_FDCWD, path, 0)
+}
+
+/// rmdir
+pub fn __syscall_rmdir(ctx: &EmEnv, path: u32) -> c_int {
+    debug!("emscripten::__syscall_rmdir");
+    __syscall_unlinkat(ctx, WASM_AT_FDCWD, path, WASM_AT_REMOVEDIR)
+}
+
+/// renameat
+pub fn __syscall_renameat(
+    ctx: &EmEnv,
+    old_dirfd: c_int,
+    old_path: u32,
+    new_dirfd: c_int,
+    new_path: u32,
+) -> c_int {
+    debug!("emscripten::__syscall_renameat");
+    let old_path = read_path(ctx, old_path);
+    let new_path = read_path(ctx, new_path);
+    ret(
+        ctx,
+        check(unsafe {
+            libc::renameat(
+                translate_dirfd(old_dirfd),
+                old_path.as_ptr(),
+                translate_dirfd(new_dirfd),
+                new_path.as_ptr(),
+            )
+        } as isize),
+    )
+}
+
+/// rename
+pub fn __sys_rename(ctx: &EmEnv, old_path: u32, new_path: u32) -> c_int {
+    debug!("emscripten::__sys_rename");
+    __syscall_renameat(ctx, WASM_AT_FDCWD, old_path, WASM_AT_FDCWD, new_path)
+}
+
+/// chdir
+pub fn __syscall_chdir(ctx: &EmEnv, path: u32) -> c_int {
+    debug!("emscripten::__syscall_chdir");
+    let path = read_path(ctx, path);
+    ret(ctx, check(unsafe { libc::chdir(path.as_ptr()) } as isize))
+}
+
+/// dup
+pub fn __syscall_dup(ctx: &EmEnv, fd: c_int) -> c_int {
+    debug!("emscripten::__syscall_dup");
+    ret(ctx, check(unsafe { libc::dup(fd) } as isize))
+}
+
+/// dup3
+pub fn __syscall_dup3(ctx: &EmEnv, fd: c_int, new_fd: c_int, flags: c_int) -> c_int {
+    debug!("emscripten::__syscall_dup3");
+    if fd == new_fd {
+        return -wasm_errno(ctx, EINVAL);
+    }
+    let result = check(unsafe { libc::dup2(fd, new_fd) } as isize).and_then(|new_fd| {
+        if flags & WASM_O_CLOEXEC != 0 {
+            check(unsafe { libc::fcntl(new_fd, libc::F_SETFD, libc::FD_CLOEXEC) } as isize)?;
+        }
+        Ok(new_fd)
+    });
+    ret(ctx, result)
+}
+
+/// faccessat
+pub fn __syscall_faccessat(
+    ctx: &EmEnv,
+    dirfd: c_int,
+    path: u32,
+    mode: c_int,
+    flags: c_int,
+) -> c_int {
+    debug!("emscripten::__syscall_faccessat");
+    let path = read_path(ctx, path);
+    // The modes are the same everywhere
+    let host_flags = if flags & WASM_AT_EACCESS != 0 {
+        libc::AT_EACCESS
+    } else {
+        0
+    };
+    ret(
+        ctx,
+        check(
+            unsafe { libc::faccessat(translate_dirfd(dirfd), path.as_ptr(), mode, host_flags) }
+                as isize,
+        ),
+    )
+}
+
+/// access
+pub fn __sys_access(ctx: &EmEnv, path: u32, mode: c_int) -> c_int {
+    debug!("emscripten::__sys_access");
+    __syscall_faccessat(ctx, WASM_AT_FDCWD, path, mode, 0)
+}
+
+/// readlinkat
+pub fn __syscall_readlinkat(ctx: &EmEnv, dirfd: c_int, path: u32, buf: u32, size: u32) -> c_int {
+    debug!("emscripten::__syscall_readlinkat");
+    let path = read_path(ctx, path);
+    let result = buffer(&ctx.memory(0), buf, size).and_then(|buf| {
+        check(unsafe {
+            libc::readlinkat(
+                translate_dirfd(dirfd),
+                path.as_ptr(),
+                buf as *mut c_char,
+                size as usize,
+            )
+        })
+    });
+    ret(ctx, result)
+}
+
+/// readlink
+pub fn __sys_readlink(ctx: &EmEnv, path: u32, buf: u32, size: u32) -> c_int {
+    debug!("emscripten::__sys_readlink");
+    __syscall_readlinkat(ctx, WASM_AT_FDCWD, path, buf, size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn open_flags() {
+        // O_WRONLY | O_CREAT | O_TRUNC | O_CLOEXEC
+        let flags = 0o1 | 0o100 | 0o1000 | 0o2000000;
+        let host = translate_open_flags(flags, true);
+        assert_eq!(
+            host,
+            libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC | libc::O_CLOEXEC
+        );
+        assert_eq!(translate_open_flags(host, false), flags);
+    }
+}
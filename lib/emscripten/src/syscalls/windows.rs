@@ -205,7 +205,8 @@ pub fn ___syscall102(_ctx: &EmEnv, which: c_int, mut _varargs: VarArgs) -> c_int
     debug!("emscripten::___syscall102 (socketcall) {}", which);
     #[cfg(not(feature = "debug"))]
     let _ = which;
-    -1
+    // ENOSYS, the sockets are only supported on unix
+    -38
 }
 
 /// fsync
@@ -127,6 +127,27 @@ pub fn ___clock_gettime(ctx: &EmEnv, clk_id: clockid_t, tp: c_int) -> c_int {
     _clock_gettime(ctx, clk_id, tp)
 }
 
+/// emscripten: emscripten_date_now, the milliseconds since the epoch
+pub fn emscripten_date_now(_ctx: &EmEnv) -> f64 {
+    debug!("emscripten::emscripten_date_now");
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
+}
+
+/// emscripten: emscripten_get_now, the milliseconds of a monotonic clock
+pub fn emscripten_get_now(_ctx: &EmEnv) -> f64 {
+    debug!("emscripten::emscripten_get_now");
+    time::precise_time_ns() as f64 / 1_000_000.0
+}
+
+/// emscripten: _emscripten_get_now_is_monotonic
+pub fn _emscripten_get_now_is_monotonic(_ctx: &EmEnv) -> i32 {
+    debug!("emscripten::_emscripten_get_now_is_monotonic");
+    1
+}
+
 /// emscripten: _clock
 pub fn _clock(_ctx: &EmEnv) -> c_int {
     debug!("emscripten::_clock");
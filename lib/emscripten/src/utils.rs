@@ -3,6 +3,7 @@ use super::env::get_emscripten_data;
 use crate::storage::align_memory;
 use crate::EmEnv;
 use libc::stat;
+use std::cell::Cell;
 use std::ffi::CStr;
 use std::mem::size_of;
 use std::os::raw::c_char;
@@ -12,11 +13,20 @@ use wasmer::{GlobalInit, Memory, Module, Pages};
 
 /// We check if a provided module is an Emscripten generated one
 pub fn is_emscripten_module(module: &Module) -> bool {
+    if module
+        .custom_sections("emscripten_metadata")
+        .next()
+        .is_some()
+    {
+        return true;
+    }
     for import in module.imports().functions() {
         let name = import.name();
         let module = import.module();
         if (name == "_emscripten_memcpy_big"
             || name == "emscripten_memcpy_big"
+            || name == "_emscripten_memcpy_js"
+            || name == "emscripten_resize_heap"
             || name == "__map_file")
             && module == "env"
         {
@@ -212,6 +222,26 @@ pub unsafe fn copy_stat_into_wasm(ctx: &EmEnv, buf: u32, stat: &stat) {
     (*stat_ptr).st_ino = stat.st_ino as _;
 }
 
+/// Reads the `len` bytes of the memory at `ptr`, if they're in bounds.
+pub(crate) fn read_bytes(memory: &Memory, ptr: u32, len: u32) -> Option<Vec<u8>> {
+    let start = ptr as usize;
+    let end = start.checked_add(len as usize)?;
+    let view = memory.view::<u8>();
+    Some(view.get(start..end)?.iter().map(Cell::get).collect())
+}
+
+/// Writes `bytes` to the memory at `ptr`, if they're in bounds.
+pub(crate) fn write_bytes(memory: &Memory, ptr: u32, bytes: &[u8]) -> Option<()> {
+    let start = ptr as usize;
+    let end = start.checked_add(bytes.len())?;
+    let view = memory.view::<u8>();
+    view.get(start..end)?
+        .iter()
+        .zip(bytes)
+        .for_each(|(cell, byte)| cell.set(*byte));
+    Some(())
+}
+
 #[allow(dead_code)] // it's used in `env/windows/mod.rs`.
 pub fn read_string_from_wasm(memory: &Memory, offset: u32) -> String {
     let v: Vec<u8> = memory.view()[(offset as usize)..]
@@ -0,0 +1,221 @@
+//! The WASI functions the modules of the upstream ABI import for the
+//! standard streams, the environment and exiting. They work on the file
+//! descriptors of the host, like the syscalls, and return a WASI errno.
+use crate::env::DEFAULT_VARS;
+use crate::utils::{read_bytes, write_bytes};
+use crate::EmEnv;
+use byteorder::{ByteOrder, LittleEndian};
+use libc::c_void;
+
+const WASI_ESUCCESS: i32 = 0;
+const WASI_EFAULT: i32 = 21;
+
+const WASI_FILETYPE_UNKNOWN: u8 = 0;
+const WASI_FILETYPE_CHARACTER_DEVICE: u8 = 2;
+#[cfg(unix)]
+const WASI_FILETYPE_DIRECTORY: u8 = 3;
+#[cfg(unix)]
+const WASI_FILETYPE_REGULAR_FILE: u8 = 4;
+#[cfg(unix)]
+const WASI_FILETYPE_SOCKET_STREAM: u8 = 6;
+
+const WASI_RIGHT_FD_SEEK: u64 = 1 << 2;
+const WASI_RIGHT_FD_TELL: u64 = 1 << 5;
+
+/// Gets the WASI errno of the last error of the host.
+fn last_wasi_errno() -> i32 {
+    #[cfg(unix)]
+    {
+        crate::errno::wasi_errno(crate::errno::last_errno())
+    }
+    #[cfg(not(unix))]
+    {
+        // EIO
+        29
+    }
+}
+
+/// Calls `op` with each buffer of the `iovs_len` iovecs at `iovs` and
+/// writes the total of the sizes it returns to `nwritten`.
+fn for_each_iovec(
+    ctx: &EmEnv,
+    iovs: u32,
+    iovs_len: u32,
+    nwritten: u32,
+    mut op: impl FnMut(*mut c_void, usize) -> isize,
+) -> i32 {
+    let memory = ctx.memory(0);
+    let iovecs = match read_bytes(&memory, iovs, iovs_len.saturating_mul(8)) {
+        Some(iovecs) => iovecs,
+        None => return WASI_EFAULT,
+    };
+    let mut total = 0u32;
+    for iovec in iovecs.chunks(8) {
+        let base = LittleEndian::read_u32(&iovec[0..4]);
+        let len = LittleEndian::read_u32(&iovec[4..8]);
+        if (base as u64 + len as u64) > memory.data_size() {
+            return WASI_EFAULT;
+        }
+        let ptr = unsafe { memory.data_ptr().add(base as usize) } as *mut c_void;
+        let ret = op(ptr, len as usize);
+        if ret < 0 {
+            return last_wasi_errno();
+        }
+        total += ret as u32;
+        // A short read or write ends the call
+        if (ret as u32) < len {
+            break;
+        }
+    }
+    match write_bytes(&memory, nwritten, &total.to_le_bytes()) {
+        Some(()) => WASI_ESUCCESS,
+        None => WASI_EFAULT,
+    }
+}
+
+/// fd_write
+pub fn fd_write(ctx: &EmEnv, fd: i32, iovs: u32, iovs_len: u32, nwritten: u32) -> i32 {
+    debug!("emscripten::wasi::fd_write {}", fd);
+    for_each_iovec(ctx, iovs, iovs_len, nwritten, |ptr, len| unsafe {
+        libc::write(fd, ptr, len as _) as isize
+    })
+}
+
+/// fd_read
+pub fn fd_read(ctx: &EmEnv, fd: i32, iovs: u32, iovs_len: u32, nread: u32) -> i32 {
+    debug!("emscripten::wasi::fd_read {}", fd);
+    for_each_iovec(ctx, iovs, iovs_len, nread, |ptr, len| unsafe {
+        libc::read(fd, ptr, len as _) as isize
+    })
+}
+
+/// fd_close
+pub fn fd_close(_ctx: &EmEnv, fd: i32) -> i32 {
+    debug!("emscripten::wasi::fd_close {}", fd);
+    if unsafe { libc::close(fd) } < 0 {
+        last_wasi_errno()
+    } else {
+        WASI_ESUCCESS
+    }
+}
+
+/// fd_seek
+pub fn fd_seek(ctx: &EmEnv, fd: i32, offset: i64, whence: i32, newoffset: u32) -> i32 {
+    debug!("emscripten::wasi::fd_seek {} {} {}", fd, offset, whence);
+    // The whences of WASI are the ones of the host
+    let ret = unsafe { libc::lseek(fd, offset as _, whence) } as i64;
+    if ret < 0 {
+        return last_wasi_errno();
+    }
+    match write_bytes(&ctx.memory(0), newoffset, &ret.to_le_bytes()) {
+        Some(()) => WASI_ESUCCESS,
+        None => WASI_EFAULT,
+    }
+}
+
+/// fd_seek, with its 64 bits offset split in two, for the modules built
+/// without `WASM_BIGINT`
+pub fn fd_seek_legalized(
+    ctx: &EmEnv,
+    fd: i32,
+    offset_low: u32,
+    offset_high: u32,
+    whence: i32,
+    newoffset: u32,
+) -> i32 {
+    let offset = ((offset_high as u64) << 32 | offset_low as u64) as i64;
+    fd_seek(ctx, fd, offset, whence, newoffset)
+}
+
+#[cfg(unix)]
+fn filetype(fd: i32) -> u8 {
+    if unsafe { libc::isatty(fd) } == 1 {
+        return WASI_FILETYPE_CHARACTER_DEVICE;
+    }
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } < 0 {
+        return WASI_FILETYPE_UNKNOWN;
+    }
+    match stat.st_mode & libc::S_IFMT {
+        libc::S_IFREG => WASI_FILETYPE_REGULAR_FILE,
+        libc::S_IFDIR => WASI_FILETYPE_DIRECTORY,
+        libc::S_IFCHR => WASI_FILETYPE_CHARACTER_DEVICE,
+        libc::S_IFSOCK => WASI_FILETYPE_SOCKET_STREAM,
+        _ => WASI_FILETYPE_UNKNOWN,
+    }
+}
+
+#[cfg(not(unix))]
+fn filetype(_fd: i32) -> u8 {
+    WASI_FILETYPE_UNKNOWN
+}
+
+/// fd_fdstat_get
+///
+/// The libc of Emscripten only uses it for `isatty`, which checks for a
+/// character device which can't seek.
+pub fn fd_fdstat_get(ctx: &EmEnv, fd: i32, buf: u32) -> i32 {
+    debug!("emscripten::wasi::fd_fdstat_get {}", fd);
+    let filetype = filetype(fd);
+    let rights = if filetype == WASI_FILETYPE_CHARACTER_DEVICE {
+        !(WASI_RIGHT_FD_SEEK | WASI_RIGHT_FD_TELL)
+    } else {
+        u64::max_value()
+    };
+    // filetype: u8, flags: u16, rights_base: u64, rights_inheriting: u64
+    let mut fdstat = [0; 24];
+    fdstat[0] = filetype;
+    LittleEndian::write_u64(&mut fdstat[8..16], rights);
+    LittleEndian::write_u64(&mut fdstat[16..24], rights);
+    match write_bytes(&ctx.memory(0), buf, &fdstat) {
+        Some(()) => WASI_ESUCCESS,
+        None => WASI_EFAULT,
+    }
+}
+
+/// proc_exit
+pub fn proc_exit(ctx: &EmEnv, code: i32) {
+    debug!("emscripten::wasi::proc_exit {}", code);
+    crate::exit::exit(ctx, code);
+}
+
+fn environment() -> Vec<String> {
+    DEFAULT_VARS
+        .iter()
+        .map(|[key, value]| format!("{}={}\0", key, value))
+        .collect()
+}
+
+/// environ_sizes_get
+pub fn environ_sizes_get(ctx: &EmEnv, environ_count: u32, environ_buf_size: u32) -> i32 {
+    debug!("emscripten::wasi::environ_sizes_get");
+    let environment = environment();
+    let size: usize = environment.iter().map(String::len).sum();
+    let memory = ctx.memory(0);
+    match write_bytes(
+        &memory,
+        environ_count,
+        &(environment.len() as u32).to_le_bytes(),
+    )
+    .and_then(|()| write_bytes(&memory, environ_buf_size, &(size as u32).to_le_bytes()))
+    {
+        Some(()) => WASI_ESUCCESS,
+        None => WASI_EFAULT,
+    }
+}
+
+/// environ_get
+pub fn environ_get(ctx: &EmEnv, environ: u32, environ_buf: u32) -> i32 {
+    debug!("emscripten::wasi::environ_get");
+    let memory = ctx.memory(0);
+    let mut offset = environ_buf;
+    for (i, var) in environment().iter().enumerate() {
+        let written = write_bytes(&memory, environ + i as u32 * 4, &offset.to_le_bytes())
+            .and_then(|()| write_bytes(&memory, offset, var.as_bytes()));
+        if written.is_none() {
+            return WASI_EFAULT;
+        }
+        offset += var.len() as u32;
+    }
+    WASI_ESUCCESS
+}
@@ -2,27 +2,27 @@
 //! to be done as separate steps.
 
 use crate::engine::{DylibEngine, DylibEngineInner};
-use crate::serialize::{ArchivedModuleMetadata, ModuleMetadata};
+use crate::serialize::{ArchivedModuleMetadata, ModuleMetadata, ModuleMetadataSymbolRegistry};
 use libloading::{Library, Symbol as LibrarySymbol};
 use loupe::MemoryUsage;
+use std::any::Any;
 use std::error::Error;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-#[cfg(feature = "compiler")]
 use std::process::Command;
 use std::sync::{Arc, Mutex};
 use tempfile::NamedTempFile;
 #[cfg(feature = "compiler")]
 use tracing::trace;
+#[cfg(feature = "compiler")]
 use wasmer_compiler::{
-    CompileError, CompiledFunctionFrameInfo, Features, FunctionAddressMap, OperatingSystem, Symbol,
-    SymbolRegistry, Triple,
+    CancellationToken, CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment,
+    ModuleMiddlewareChain, ModuleTranslationState,
 };
-#[cfg(feature = "compiler")]
 use wasmer_compiler::{
-    CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment, ModuleMiddlewareChain,
-    ModuleTranslationState,
+    CompileError, CompiledFunctionFrameInfo, Features, FunctionAddressMap, OperatingSystem, Symbol,
+    SymbolRegistry, Triple,
 };
 use wasmer_engine::{
     register_frame_info, Artifact, DeserializeError, FunctionExtent, GlobalFrameInfoRegistration,
@@ -56,6 +56,11 @@ pub struct DylibArtifact {
     func_data_registry: Arc<FuncDataRegistry>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
+    /// The library holding the compiled code, unloaded once the artifact
+    /// and all its instances are dropped.
+    #[loupe(skip)]
+    library: Option<Arc<Library>>,
+    symbol_registry: ModuleMetadataSymbolRegistry,
 }
 
 fn to_compile_error(err: impl Error) -> CompileError {
@@ -64,6 +69,18 @@ fn to_compile_error(err: impl Error) -> CompileError {
 
 const WASMER_METADATA_SYMBOL: &[u8] = b"WASMER_METADATA";
 
+const DYNAMIC_LOADING_UNSUPPORTED: &str = "Shared objects can't be loaded by a statically \
+     linked musl binary; build the host with `-C target-feature=-crt-static` or use the \
+     Staticlib engine";
+
+/// Whether the current process can load shared objects.
+///
+/// The `dlopen` of statically linked musl binaries (the default for
+/// the `*-linux-musl` Rust targets) always fails.
+fn dynamic_loading_supported() -> bool {
+    !cfg!(all(target_env = "musl", target_feature = "crt-static"))
+}
+
 impl DylibArtifact {
     // Mach-O header in Mac
     #[allow(dead_code)]
@@ -161,6 +178,21 @@ impl DylibArtifact {
         engine: &DylibEngine,
         data: &[u8],
         tunables: &dyn Tunables,
+    ) -> Result<Self, CompileError> {
+        Self::new_cancellable(engine, data, tunables, None)
+    }
+
+    /// Compile a data buffer into a `DylibArtifact`, until the
+    /// `cancellation_token` cancels the compilation.
+    ///
+    /// The compilations to native objects, done by LLVM, aren't
+    /// cancelled.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn new_cancellable(
+        engine: &DylibEngine,
+        data: &[u8],
+        tunables: &dyn Tunables,
+        cancellation_token: Option<&CancellationToken>,
     ) -> Result<Self, CompileError> {
         let mut engine_inner = engine.inner_mut();
         let target = engine.target();
@@ -240,6 +272,7 @@ impl DylibArtifact {
                     &compile_info,
                     module_translation.as_ref().unwrap(),
                     function_body_inputs,
+                    cancellation_token,
                 )?;
                 let mut obj = get_object_for_target(&target_triple).map_err(to_compile_error)?;
                 emit_data(
@@ -342,6 +375,11 @@ impl DylibArtifact {
         if is_cross_compiling {
             Self::from_parts_crosscompiled(metadata, shared_filepath)
         } else {
+            if !dynamic_loading_supported() {
+                return Err(CompileError::Resource(
+                    DYNAMIC_LOADING_UNSUPPORTED.to_string(),
+                ));
+            }
             let lib = unsafe { Library::new(&shared_filepath).map_err(to_compile_error)? };
             Self::from_parts(&mut engine_inner, metadata, shared_filepath, lib)
         }
@@ -369,6 +407,7 @@ impl DylibArtifact {
         let finished_dynamic_function_trampolines: PrimaryMap<FunctionIndex, FunctionBodyPtr> =
             PrimaryMap::new();
         let signatures: PrimaryMap<SignatureIndex, VMSharedSignatureIndex> = PrimaryMap::new();
+        let symbol_registry = metadata.get_symbol_registry();
         Ok(Self {
             dylib_path,
             metadata,
@@ -380,6 +419,8 @@ impl DylibArtifact {
             func_data_registry: Arc::new(FuncDataRegistry::new()),
             signatures: signatures.into_boxed_slice(),
             frame_info_registration: Mutex::new(None),
+            library: None,
+            symbol_registry,
         })
     }
 
@@ -390,12 +431,12 @@ impl DylibArtifact {
         dylib_path: PathBuf,
         lib: Library,
     ) -> Result<Self, CompileError> {
+        let symbol_registry = metadata.get_symbol_registry();
         let mut finished_functions: PrimaryMap<LocalFunctionIndex, FunctionBodyPtr> =
             PrimaryMap::new();
         for (function_local_index, _function_len) in metadata.function_body_lengths.iter() {
-            let function_name = metadata
-                .get_symbol_registry()
-                .symbol_to_name(Symbol::LocalFunction(function_local_index));
+            let function_name =
+                symbol_registry.symbol_to_name(Symbol::LocalFunction(function_local_index));
             unsafe {
                 // We use a fake function signature `fn()` because we just
                 // want to get the function address.
@@ -412,9 +453,8 @@ impl DylibArtifact {
         let mut finished_function_call_trampolines: PrimaryMap<SignatureIndex, VMTrampoline> =
             PrimaryMap::with_capacity(metadata.compile_info.module.signatures.len());
         for sig_index in metadata.compile_info.module.signatures.keys() {
-            let function_name = metadata
-                .get_symbol_registry()
-                .symbol_to_name(Symbol::FunctionCallTrampoline(sig_index));
+            let function_name =
+                symbol_registry.symbol_to_name(Symbol::FunctionCallTrampoline(sig_index));
             unsafe {
                 let trampoline: LibrarySymbol<VMTrampoline> = lib
                     .get(function_name.as_bytes())
@@ -434,9 +474,8 @@ impl DylibArtifact {
             .keys()
             .take(metadata.compile_info.module.num_imported_functions)
         {
-            let function_name = metadata
-                .get_symbol_registry()
-                .symbol_to_name(Symbol::DynamicFunctionTrampoline(func_index));
+            let function_name =
+                symbol_registry.symbol_to_name(Symbol::DynamicFunctionTrampoline(func_index));
             unsafe {
                 let trampoline: LibrarySymbol<unsafe extern "C" fn()> = lib
                     .get(function_name.as_bytes())
@@ -472,7 +511,8 @@ impl DylibArtifact {
                 .collect::<PrimaryMap<_, _>>()
         };
 
-        engine_inner.add_library(lib);
+        let library = Arc::new(lib);
+        engine_inner.register_library(&library);
 
         Ok(Self {
             dylib_path,
@@ -485,6 +525,8 @@ impl DylibArtifact {
             func_data_registry: engine_inner.func_data().clone(),
             signatures: signatures.into_boxed_slice(),
             frame_info_registration: Mutex::new(None),
+            library: Some(library),
+            symbol_registry,
         })
     }
 
@@ -550,6 +592,11 @@ impl DylibArtifact {
         engine: &DylibEngine,
         path: &Path,
     ) -> Result<Self, DeserializeError> {
+        if !dynamic_loading_supported() {
+            return Err(DeserializeError::Incompatible(
+                DYNAMIC_LOADING_UNSUPPORTED.to_string(),
+            ));
+        }
         let lib = Library::new(&path).map_err(|e| {
             DeserializeError::CorruptedBinary(format!("Library loading failed: {}", e))
         })?;
@@ -765,6 +812,29 @@ impl Artifact for DylibArtifact {
         &self.func_data_registry
     }
 
+    fn function_extents(&self) -> Option<PrimaryMap<LocalFunctionIndex, FunctionExtent>> {
+        Some(
+            self.finished_functions
+                .values()
+                .zip(self.metadata.function_body_lengths.values())
+                .map(|(&ptr, &length)| FunctionExtent {
+                    ptr,
+                    length: length as usize,
+                })
+                .collect(),
+        )
+    }
+
+    fn symbol_registry(&self) -> Option<&dyn SymbolRegistry> {
+        Some(&self.symbol_registry)
+    }
+
+    fn code_owner(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.library
+            .clone()
+            .map(|library| library as Arc<dyn Any + Send + Sync>)
+    }
+
     fn preinstantiate(&self) -> Result<(), InstantiationError> {
         Ok(())
     }
@@ -773,4 +843,30 @@ impl Artifact for DylibArtifact {
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
         Ok(std::fs::read(&self.dylib_path)?)
     }
+
+    /// Serialize a `DylibArtifact` without the debug sections of its
+    /// shared object, removed by the `strip` tool of the host.
+    ///
+    /// The function names are kept, as the metadata holding them is
+    /// linked into the shared object.
+    fn serialize_stripped(&self) -> Result<Vec<u8>, SerializeError> {
+        let stripped_path = tempfile::Builder::new()
+            .prefix("wasmer_dylib_")
+            .tempfile()?
+            .into_temp_path();
+        std::fs::copy(&self.dylib_path, &stripped_path)?;
+
+        let output = Command::new("strip")
+            .arg("-S")
+            .arg(&stripped_path)
+            .output()?;
+        if !output.status.success() {
+            return Err(SerializeError::Generic(format!(
+                "Stripping the shared object failed with:\nstderr:{}\nstdout:{}",
+                String::from_utf8_lossy(&output.stderr).trim_end(),
+                String::from_utf8_lossy(&output.stdout).trim_end()
+            )));
+        }
+        Ok(std::fs::read(&stripped_path)?)
+    }
 }
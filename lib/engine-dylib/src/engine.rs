@@ -6,15 +6,16 @@ use loupe::MemoryUsage;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
-use wasmer_compiler::{CompileError, Target};
 #[cfg(feature = "compiler")]
-use wasmer_compiler::{Compiler, Triple};
+use wasmer_compiler::{CancellationToken, Compiler, Triple};
+use wasmer_compiler::{CompileError, Target};
 use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
 #[cfg(feature = "compiler")]
 use wasmer_types::Features;
 use wasmer_types::FunctionType;
 use wasmer_vm::{
-    FuncDataRegistry, SignatureRegistry, VMCallerCheckedAnyfunc, VMFuncRef, VMSharedSignatureIndex,
+    FuncDataRegistry, InstanceRef, SignatureRegistry, VMCallerCheckedAnyfunc, VMFuncRef,
+    VMFunctionEnvironment, VMSharedSignatureIndex, VMTrampoline, WeakInstanceRef,
 };
 
 /// A WebAssembly `Dylib` Engine.
@@ -131,6 +132,23 @@ impl Engine for DylibEngine {
         compiler.signatures().lookup(sig)
     }
 
+    fn lookup_function_owner(&self, vmctx: VMFunctionEnvironment) -> Option<WeakInstanceRef> {
+        let compiler = self.inner();
+        compiler.func_data().function_owner(vmctx)
+    }
+
+    fn lookup_function_call_trampoline(
+        &self,
+        sig: VMSharedSignatureIndex,
+    ) -> Option<(VMTrampoline, InstanceRef)> {
+        let compiler = self.inner();
+        compiler.func_data().function_call_trampoline(sig)
+    }
+
+    unsafe fn gc(&self) {
+        self.inner_mut().gc();
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
         self.inner().validate(binary)
@@ -146,6 +164,22 @@ impl Engine for DylibEngine {
         Ok(Arc::new(DylibArtifact::new(&self, binary, tunables)?))
     }
 
+    /// Compile a WebAssembly binary, until `token` cancels the compilation
+    #[cfg(feature = "compiler")]
+    fn compile_cancellable(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+        token: &CancellationToken,
+    ) -> Result<Arc<dyn Artifact>, CompileError> {
+        Ok(Arc::new(DylibArtifact::new_cancellable(
+            &self,
+            binary,
+            tunables,
+            Some(token),
+        )?))
+    }
+
     /// Compile a WebAssembly binary (it will fail because the `compiler` flag is disabled).
     #[cfg(not(feature = "compiler"))]
     fn compile(
@@ -153,10 +187,7 @@ impl Engine for DylibEngine {
         _binary: &[u8],
         _tunables: &dyn Tunables,
     ) -> Result<Arc<dyn Artifact>, CompileError> {
-        Err(CompileError::Codegen(
-            "The `DylibEngine` is operating in headless mode, so it cannot compile a module."
-                .to_string(),
-        ))
+        Err(CompileError::Headless("`DylibEngine`".to_string()))
     }
 
     /// Deserializes a WebAssembly module (binary content of a shared object file)
@@ -260,9 +291,10 @@ pub struct DylibEngineInner {
     /// The linker to use.
     linker: Linker,
 
-    /// List of libraries loaded by this engine.
+    /// The libraries holding the code of the artifacts, kept loaded
+    /// until `gc` unloads the unused ones.
     #[loupe(skip)]
-    libraries: Vec<Library>,
+    libraries: Vec<Arc<Library>>,
 }
 
 impl DylibEngineInner {
@@ -270,7 +302,7 @@ impl DylibEngineInner {
     #[cfg(feature = "compiler")]
     pub fn compiler(&self) -> Result<&dyn Compiler, CompileError> {
         if self.compiler.is_none() {
-            return Err(CompileError::Codegen("The `DylibEngine` is operating in headless mode, so it can only execute already compiled Modules.".to_string()));
+            return Err(CompileError::Headless("`DylibEngine`".to_string()));
         }
         Ok(&**self
             .compiler
@@ -301,9 +333,7 @@ impl DylibEngineInner {
     /// Validate the module
     #[cfg(not(feature = "compiler"))]
     pub fn validate<'data>(&self, _data: &'data [u8]) -> Result<(), CompileError> {
-        Err(CompileError::Validate(
-            "The `DylibEngine` is not compiled with compiler support, which is required for validating".to_string(),
-        ))
+        Err(CompileError::Headless("`DylibEngine`".to_string()))
     }
 
     /// Shared signature registry.
@@ -320,11 +350,24 @@ impl DylibEngineInner {
         self.is_cross_compiling
     }
 
-    pub(crate) fn linker(&self) -> Linker {
-        self.linker
+    /// Keep the `library` of an artifact loaded, as the function
+    /// references to its functions don't keep it alive, until `gc`
+    /// unloads it.
+    pub(crate) fn register_library(&mut self, library: &Arc<Library>) {
+        self.libraries.push(library.clone());
     }
 
-    pub(crate) fn add_library(&mut self, library: Library) {
-        self.libraries.push(library);
+    /// Unload the libraries only the engine still holds.
+    ///
+    /// # Safety
+    ///
+    /// See [`Engine::gc`].
+    unsafe fn gc(&mut self) {
+        self.libraries
+            .retain(|library| Arc::strong_count(library) > 1);
+    }
+
+    pub(crate) fn linker(&self) -> Linker {
+        self.linker
     }
 }
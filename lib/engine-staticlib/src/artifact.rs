@@ -8,13 +8,15 @@ use std::collections::BTreeMap;
 use std::error::Error;
 use std::mem;
 use std::sync::Arc;
-use wasmer_compiler::{CompileError, Features, OperatingSystem, SymbolRegistry, Triple};
 #[cfg(feature = "compiler")]
 use wasmer_compiler::{
-    CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment, ModuleMiddlewareChain,
-    ModuleTranslationState,
+    CancellationToken, CompileModuleInfo, Compiler, FunctionBodyData, ModuleEnvironment,
+    ModuleMiddlewareChain, ModuleTranslationState,
+};
+use wasmer_compiler::{CompileError, Features, OperatingSystem, SymbolRegistry, Triple};
+use wasmer_engine::{
+    Artifact, DeserializeError, FunctionExtent, InstantiationError, SerializeError,
 };
-use wasmer_engine::{Artifact, DeserializeError, InstantiationError, SerializeError};
 #[cfg(feature = "compiler")]
 use wasmer_engine::{Engine, Tunables};
 #[cfg(feature = "compiler")]
@@ -153,6 +155,21 @@ impl StaticlibArtifact {
         engine: &StaticlibEngine,
         data: &[u8],
         tunables: &dyn Tunables,
+    ) -> Result<Self, CompileError> {
+        Self::new_cancellable(engine, data, tunables, None)
+    }
+
+    /// Compile a data buffer into a `StaticlibArtifact`, until the
+    /// `cancellation_token` cancels the compilation.
+    ///
+    /// The compilations to native objects, done by LLVM, aren't
+    /// cancelled.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn new_cancellable(
+        engine: &StaticlibEngine,
+        data: &[u8],
+        tunables: &dyn Tunables,
+        cancellation_token: Option<&CancellationToken>,
     ) -> Result<Self, CompileError> {
         let mut engine_inner = engine.inner_mut();
         let target = engine.target();
@@ -232,6 +249,7 @@ impl StaticlibArtifact {
                 &metadata.compile_info,
                 module_translation.as_ref().unwrap(),
                 function_body_inputs,
+                cancellation_token,
             )?;
             // there's an ordering issue, but we can update function_body_lengths here.
             /*
@@ -482,6 +500,23 @@ impl Artifact for StaticlibArtifact {
         &self.func_data_registry
     }
 
+    fn function_extents(&self) -> Option<PrimaryMap<LocalFunctionIndex, FunctionExtent>> {
+        Some(
+            self.finished_functions
+                .values()
+                .zip(self.metadata.function_body_lengths.values())
+                .map(|(&ptr, &length)| FunctionExtent {
+                    ptr,
+                    length: length as usize,
+                })
+                .collect(),
+        )
+    }
+
+    fn symbol_registry(&self) -> Option<&dyn SymbolRegistry> {
+        Some(&self.symbol_registry)
+    }
+
     fn preinstantiate(&self) -> Result<(), InstantiationError> {
         Ok(())
     }
@@ -4,14 +4,15 @@ use std::io::Read;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 #[cfg(feature = "compiler")]
-use wasmer_compiler::Compiler;
+use wasmer_compiler::{CancellationToken, Compiler};
 use wasmer_compiler::{CompileError, Target};
 use wasmer_engine::{Artifact, DeserializeError, Engine, EngineId, Tunables};
 #[cfg(feature = "compiler")]
 use wasmer_types::Features;
 use wasmer_types::FunctionType;
 use wasmer_vm::{
-    FuncDataRegistry, SignatureRegistry, VMCallerCheckedAnyfunc, VMFuncRef, VMSharedSignatureIndex,
+    FuncDataRegistry, InstanceRef, SignatureRegistry, VMCallerCheckedAnyfunc, VMFuncRef,
+    VMFunctionEnvironment, VMSharedSignatureIndex, VMTrampoline, WeakInstanceRef,
 };
 
 /// A WebAssembly `Staticlib` Engine.
@@ -120,6 +121,19 @@ impl Engine for StaticlibEngine {
         compiler.signatures().lookup(sig)
     }
 
+    fn lookup_function_owner(&self, vmctx: VMFunctionEnvironment) -> Option<WeakInstanceRef> {
+        let compiler = self.inner();
+        compiler.func_data().function_owner(vmctx)
+    }
+
+    fn lookup_function_call_trampoline(
+        &self,
+        sig: VMSharedSignatureIndex,
+    ) -> Option<(VMTrampoline, InstanceRef)> {
+        let compiler = self.inner();
+        compiler.func_data().function_call_trampoline(sig)
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
         self.inner().validate(binary)
@@ -135,6 +149,22 @@ impl Engine for StaticlibEngine {
         Ok(Arc::new(StaticlibArtifact::new(&self, binary, tunables)?))
     }
 
+    /// Compile a WebAssembly binary, until `token` cancels the compilation
+    #[cfg(feature = "compiler")]
+    fn compile_cancellable(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+        token: &CancellationToken,
+    ) -> Result<Arc<dyn Artifact>, CompileError> {
+        Ok(Arc::new(StaticlibArtifact::new_cancellable(
+            &self,
+            binary,
+            tunables,
+            Some(token),
+        )?))
+    }
+
     /// Compile a WebAssembly binary (it will fail because the `compiler` flag is disabled).
     #[cfg(not(feature = "compiler"))]
     fn compile(
@@ -142,10 +172,7 @@ impl Engine for StaticlibEngine {
         _binary: &[u8],
         _tunables: &dyn Tunables,
     ) -> Result<Arc<dyn Artifact>, CompileError> {
-        Err(CompileError::Codegen(
-            "The `StaticlibEngine` is operating in headless mode, so it cannot compile a module."
-                .to_string(),
-        ))
+        Err(CompileError::Headless("`StaticlibEngine`".to_string()))
     }
 
     /// Deserializes a WebAssembly module (binary content of a static object file)
@@ -209,7 +236,7 @@ impl StaticlibEngineInner {
     #[cfg(feature = "compiler")]
     pub fn compiler(&self) -> Result<&dyn Compiler, CompileError> {
         if self.compiler.is_none() {
-            return Err(CompileError::Codegen("The `StaticlibEngine` is operating in headless mode, so it can only execute already compiled Modules.".to_string()));
+            return Err(CompileError::Headless("`StaticlibEngine`".to_string()));
         }
         Ok(&**self
             .compiler
@@ -240,9 +267,7 @@ impl StaticlibEngineInner {
     /// Validate the module
     #[cfg(not(feature = "compiler"))]
     pub fn validate<'data>(&self, _data: &'data [u8]) -> Result<(), CompileError> {
-        Err(CompileError::Validate(
-            "The `StaticlibEngine` is not compiled with compiler support, which is required for validating".to_string(),
-        ))
+        Err(CompileError::Headless("`StaticlibEngine`".to_string()))
     }
 
     /// Shared signature registry.
@@ -1,19 +1,32 @@
 //! Define `UniversalArtifact` to allow compiling and instantiating to be
 //! done as separate steps.
 
+use crate::code_memory::CodeMemory;
 use crate::engine::{UniversalEngine, UniversalEngineInner};
 use crate::link::link_module;
 #[cfg(feature = "compiler")]
 use crate::serialize::SerializableCompilation;
 use crate::serialize::SerializableModule;
 use loupe::MemoryUsage;
+use memmap2::Mmap;
+use rkyv::AlignedVec;
+use std::any::Any;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
-use wasmer_compiler::{CompileError, Features, Triple};
 #[cfg(feature = "compiler")]
-use wasmer_compiler::{CompileModuleInfo, ModuleEnvironment, ModuleMiddlewareChain};
+use std::time::Instant;
+#[cfg(feature = "compiler")]
+use wasmer_compiler::{
+    CancellationToken, CompileModuleInfo, ModuleEnvironment, ModuleMiddlewareChain,
+};
+use wasmer_compiler::{CompileError, CompiledFunctionFrameInfo, Features, Triple};
 use wasmer_engine::{
-    register_frame_info, Artifact, DeserializeError, FunctionExtent, GlobalFrameInfoRegistration,
-    SerializeError,
+    register_frame_info, Artifact, CompilationMetrics, DeserializeError, FunctionExtent,
+    GlobalFrameInfoRegistration, SerializeError,
 };
 #[cfg(feature = "compiler")]
 use wasmer_engine::{Engine, Tunables};
@@ -27,9 +40,42 @@ use wasmer_vm::{
     VMTrampoline,
 };
 
+/// The serialized artifacts start with a header made of:
+/// * the magic header, `\0wasmer-universal\0`
+/// * the format version, a little-endian `u16`
+/// * the compression of the metadata, a `u8`, and a reserved byte
+/// * the metadata length, LEB128-encoded, padded up to the metadata
+///
+/// When the metadata is compressed, the machine code of the functions,
+/// which doesn't compress well, is stored first as it is, preceded by its
+/// length, a little-endian `u64`. The rest of the module follows,
+/// compressed, preceded by its decompressed length, a little-endian
+/// `u64`, bounding its decompression.
+const SERIALIZED_FORMAT_VERSION_OFFSET: usize = 18;
+const SERIALIZED_COMPRESSION_OFFSET: usize = 20;
 const SERIALIZED_METADATA_LENGTH_OFFSET: usize = 22;
 const SERIALIZED_METADATA_CONTENT_OFFSET: usize = 32;
 
+/// The version of the serialization format, which must be bumped at
+/// each change of the header or of the layout of `SerializableModule`
+/// (including the types it contains, like `ModuleInfo` or `Features`).
+///
+/// The artifacts of another version are rejected rather than misread.
+///
+/// * 0: the artifacts serialized before the format was versioned
+/// * 1: the sizes of the function bodies in `ModuleInfo`
+/// * 2: the validation limits in `Features`
+/// * 3: the hash maps of `ModuleInfo` archived as sorted entries
+/// * 4: the names of `ModuleInfo` archived as shared strings
+/// * 5: the decompressed length preceding the compressed metadata
+/// * 6: the machine code stored apart from the compressed metadata
+const FORMAT_VERSION: u16 = 6;
+
+/// The metadata is stored as it is.
+const COMPRESSION_NONE: u8 = 0;
+/// The metadata is compressed with zstd.
+const COMPRESSION_ZSTD: u8 = 1;
+
 /// A compiled wasm module, ready to be instantiated.
 #[derive(MemoryUsage)]
 pub struct UniversalArtifact {
@@ -40,14 +86,34 @@ pub struct UniversalArtifact {
     finished_dynamic_function_trampolines: BoxedSlice<FunctionIndex, FunctionBodyPtr>,
     signatures: BoxedSlice<SignatureIndex, VMSharedSignatureIndex>,
     func_data_registry: Arc<FuncDataRegistry>,
+    /// The memory holding the compiled code, unmapped once the artifact
+    /// and all its instances are dropped.
+    code_memory: Arc<CodeMemory>,
+    /// The addresses of the `.eh_frame` section in `code_memory`, if any.
+    #[loupe(skip)]
+    eh_frame: Option<Range<usize>>,
     frame_info_registration: Mutex<Option<GlobalFrameInfoRegistration>>,
     finished_function_lengths: BoxedSlice<LocalFunctionIndex, usize>,
+    /// The memory-mapped file this artifact was deserialized from, if any.
+    ///
+    /// When set, the compiled code is not kept in `serializable` once it
+    /// has been published, and serializing the artifact writes the
+    /// mapped bytes back as they are.
+    #[loupe(skip)]
+    mmap: Option<Mmap>,
+    #[loupe(skip)]
+    compilation_metrics: Option<CompilationMetrics>,
+    /// Whether the metadata is compressed when the artifact is serialized.
+    compress: bool,
 }
 
 impl UniversalArtifact {
-    const MAGIC_HEADER: &'static [u8; 22] = b"\0wasmer-universal\0\0\0\0\0";
+    const MAGIC_HEADER: &'static [u8; 18] = b"\0wasmer-universal\0";
 
     /// Check if the provided bytes look like a serialized `UniversalArtifact`.
+    ///
+    /// The artifacts serialized by other versions of the engine are
+    /// recognized too, but fail to deserialize.
     pub fn is_deserializable(bytes: &[u8]) -> bool {
         bytes.starts_with(Self::MAGIC_HEADER)
     }
@@ -59,6 +125,19 @@ impl UniversalArtifact {
         data: &[u8],
         tunables: &dyn Tunables,
     ) -> Result<Self, CompileError> {
+        Self::new_cancellable(engine, data, tunables, None)
+    }
+
+    /// Compile a data buffer into a `UniversalArtifact`, until the
+    /// `cancellation_token` cancels the compilation.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn new_cancellable(
+        engine: &UniversalEngine,
+        data: &[u8],
+        tunables: &dyn Tunables,
+        cancellation_token: Option<&CancellationToken>,
+    ) -> Result<Self, CompileError> {
+        let started = Instant::now();
         let environ = ModuleEnvironment::new();
         let mut inner_engine = engine.inner_mut();
         let features = inner_engine.features();
@@ -71,11 +150,26 @@ impl UniversalArtifact {
         let mut module = translation.module;
         let middlewares = compiler.get_middlewares();
         middlewares.apply_on_module_info(&mut module);
+        let strip_debug_info = inner_engine.strip_debug_info();
+        if strip_debug_info {
+            module.function_names.clear();
+        }
 
+        let explicit_bounds_checks = inner_engine.explicit_bounds_checks();
         let memory_styles: PrimaryMap<MemoryIndex, MemoryStyle> = module
             .memories
             .values()
-            .map(|memory_type| tunables.memory_style(memory_type))
+            .map(|memory_type| {
+                if explicit_bounds_checks {
+                    // A dynamic memory without offset guard makes the
+                    // compilers check every access explicitly.
+                    MemoryStyle::Dynamic {
+                        offset_guard_size: 0,
+                    }
+                } else {
+                    tunables.memory_style(memory_type)
+                }
+            })
             .collect();
         let table_styles: PrimaryMap<TableIndex, TableStyle> = module
             .tables
@@ -90,7 +184,10 @@ impl UniversalArtifact {
             table_styles,
         };
 
+        let translation_time = started.elapsed();
+
         // Compile the Module
+        let started = Instant::now();
         let compilation = compiler.compile_module(
             &engine.target(),
             &compile_info,
@@ -99,7 +196,9 @@ impl UniversalArtifact {
             // `module_translation_state`.
             translation.module_translation_state.as_ref().unwrap(),
             translation.function_body_inputs,
+            cancellation_token,
         )?;
+        let compilation_time = started.elapsed();
         let function_call_trampolines = compilation.get_function_call_trampolines();
         let dynamic_function_trampolines = compilation.get_dynamic_function_trampolines();
 
@@ -112,7 +211,7 @@ impl UniversalArtifact {
 
         let frame_infos = compilation.get_frame_info();
 
-        let serializable_compilation = SerializableCompilation {
+        let mut serializable_compilation = SerializableCompilation {
             function_bodies: compilation.get_function_bodies(),
             function_relocations: compilation.get_relocations(),
             function_jt_offsets: compilation.get_jt_offsets(),
@@ -123,12 +222,43 @@ impl UniversalArtifact {
             custom_section_relocations: compilation.get_custom_section_relocations(),
             debug: compilation.get_debug(),
         };
+        if strip_debug_info {
+            serializable_compilation.strip_debug_info();
+        }
+        let code_size: usize = serializable_compilation
+            .function_bodies
+            .values()
+            .chain(serializable_compilation.function_call_trampolines.values())
+            .chain(
+                serializable_compilation
+                    .dynamic_function_trampolines
+                    .values(),
+            )
+            .map(|function_body| function_body.body.len())
+            .sum();
+        let num_relocations: usize = serializable_compilation
+            .function_relocations
+            .values()
+            .chain(serializable_compilation.custom_section_relocations.values())
+            .map(Vec::len)
+            .sum();
         let serializable = SerializableModule {
             compilation: serializable_compilation,
             compile_info,
             data_initializers,
         };
-        Self::from_parts(&mut inner_engine, serializable)
+
+        let started = Instant::now();
+        let mut artifact = Self::from_parts(&mut inner_engine, serializable)?;
+        artifact.compilation_metrics = Some(CompilationMetrics {
+            translation_time,
+            compilation_time,
+            link_time: started.elapsed(),
+            code_size,
+            num_relocations,
+            function_compile_times: compilation.get_function_compile_times().clone(),
+        });
+        Ok(artifact)
     }
 
     /// Compile a data buffer into a `UniversalArtifact`, which may then be instantiated.
@@ -148,25 +278,103 @@ impl UniversalArtifact {
         universal: &UniversalEngine,
         bytes: &[u8],
     ) -> Result<Self, DeserializeError> {
+        let serializable = Self::deserialize_serializable(bytes)?;
+        Self::from_parts(&mut universal.inner_mut(), serializable)
+            .map_err(DeserializeError::Compiler)
+    }
+
+    /// Deserialize a `UniversalArtifact` from a file path.
+    ///
+    /// The file is memory-mapped rather than read into a buffer. The
+    /// compiled code is still copied once into executable memory, where
+    /// it's relocated, but the artifact then keeps the mapping instead
+    /// of a second copy of the code.
+    ///
+    /// # Safety
+    ///
+    /// The file's content must represent a serialized WebAssembly module,
+    /// and it must not be modified while the artifact is alive.
+    pub unsafe fn deserialize_from_file(
+        universal: &UniversalEngine,
+        path: &Path,
+    ) -> Result<Self, DeserializeError> {
+        let file = File::open(&path)?;
+        let mmap = Mmap::map(&file)?;
+        let serializable = Self::deserialize_serializable(&mmap)?;
+        let mut artifact = Self::from_parts(&mut universal.inner_mut(), serializable)
+            .map_err(DeserializeError::Compiler)?;
+
+        // The code is now living in the artifact code memory, and can be
+        // recovered from the mapping if the artifact is serialized again.
+        let compilation = &mut artifact.serializable.compilation;
+        compilation.function_bodies = PrimaryMap::new();
+        compilation.function_call_trampolines = PrimaryMap::new();
+        compilation.dynamic_function_trampolines = PrimaryMap::new();
+        artifact.mmap = Some(mmap);
+
+        Ok(artifact)
+    }
+
+    /// Read the `SerializableModule` out of the serialized bytes of a
+    /// `UniversalArtifact`.
+    ///
+    /// # Safety
+    /// This function is unsafe because rkyv reads directly without validating
+    /// the data.
+    unsafe fn deserialize_serializable(
+        bytes: &[u8],
+    ) -> Result<SerializableModule, DeserializeError> {
         if !Self::is_deserializable(bytes) {
             return Err(DeserializeError::Incompatible(
                 "The provided bytes are not wasmer-universal".to_string(),
             ));
         }
 
+        let header = bytes
+            .get(..SERIALIZED_METADATA_LENGTH_OFFSET)
+            .ok_or_else(|| {
+                DeserializeError::CorruptedBinary("The header is truncated".to_string())
+            })?;
+        let version = u16::from_le_bytes([
+            header[SERIALIZED_FORMAT_VERSION_OFFSET],
+            header[SERIALIZED_FORMAT_VERSION_OFFSET + 1],
+        ]);
+        if version != FORMAT_VERSION {
+            return Err(DeserializeError::Incompatible(format!(
+                "The artifact has the format version {}, but this engine supports {}",
+                version, FORMAT_VERSION
+            )));
+        }
+        let compression = header[SERIALIZED_COMPRESSION_OFFSET];
+
         let mut inner_bytes = &bytes[SERIALIZED_METADATA_LENGTH_OFFSET..];
 
         let metadata_len = leb128::read::unsigned(&mut inner_bytes).map_err(|_e| {
             DeserializeError::CorruptedBinary("Can't read metadata size".to_string())
         })?;
-        let metadata_slice: &[u8] = std::slice::from_raw_parts(
-            &bytes[SERIALIZED_METADATA_CONTENT_OFFSET] as *const u8,
-            metadata_len as usize,
-        );
-
-        let serializable = SerializableModule::deserialize(metadata_slice)?;
-        Self::from_parts(&mut universal.inner_mut(), serializable)
-            .map_err(DeserializeError::Compiler)
+        let metadata_slice = usize::try_from(metadata_len)
+            .ok()
+            .and_then(|len| SERIALIZED_METADATA_CONTENT_OFFSET.checked_add(len))
+            .and_then(|end| bytes.get(SERIALIZED_METADATA_CONTENT_OFFSET..end))
+            .ok_or_else(|| {
+                DeserializeError::CorruptedBinary("The metadata is truncated".to_string())
+            })?;
+
+        match compression {
+            COMPRESSION_NONE => SerializableModule::deserialize(metadata_slice),
+            COMPRESSION_ZSTD => {
+                // The module is deserialized into owned data, so the
+                // decompressed metadata can be dropped right after.
+                let (code, metadata) = decompress(metadata_slice)?;
+                let mut serializable = SerializableModule::deserialize(&metadata)?;
+                serializable.join_code(code)?;
+                Ok(serializable)
+            }
+            _ => Err(DeserializeError::Incompatible(format!(
+                "The metadata has an unknown compression ({})",
+                compression
+            ))),
+        }
     }
 
     /// Construct a `UniversalArtifact` from component parts.
@@ -174,12 +382,14 @@ impl UniversalArtifact {
         inner_engine: &mut UniversalEngineInner,
         serializable: SerializableModule,
     ) -> Result<Self, CompileError> {
+        let mut code_memory = CodeMemory::new();
         let (
             finished_functions,
             finished_function_call_trampolines,
             finished_dynamic_function_trampolines,
             custom_sections,
         ) = inner_engine.allocate(
+            &mut code_memory,
             &serializable.compile_info.module,
             &serializable.compilation.function_bodies,
             &serializable.compilation.function_call_trampolines,
@@ -222,9 +432,16 @@ impl UniversalArtifact {
             None => None,
         };
         // Make all code compiled thus far executable.
-        inner_engine.publish_compiled_code();
+        inner_engine.publish_compiled_code(&mut code_memory)?;
+
+        inner_engine.publish_eh_frame(&mut code_memory, eh_frame)?;
 
-        inner_engine.publish_eh_frame(eh_frame)?;
+        let code_memory = Arc::new(code_memory);
+        inner_engine.register_code_memory(&code_memory);
+        let eh_frame = eh_frame.map(|eh_frame| {
+            let start = eh_frame.as_ptr() as usize;
+            start..start + eh_frame.len()
+        });
 
         let finished_function_lengths = finished_functions
             .values()
@@ -252,9 +469,54 @@ impl UniversalArtifact {
             frame_info_registration: Mutex::new(None),
             finished_function_lengths,
             func_data_registry,
+            code_memory,
+            eh_frame,
+            mmap: None,
+            compilation_metrics: None,
+            compress: inner_engine.compress_artifacts(),
         })
     }
 
+    /// Serialize the metadata, compressed if the engine compresses the
+    /// artifacts, and return it with the header preceding it.
+    fn serialize_metadata(
+        &self,
+        serializable: &SerializableModule,
+    ) -> Result<(Vec<u8>, Vec<u8>), SerializeError> {
+        if self.compress {
+            let (serializable, code) = serializable.split_code();
+            let metadata = compress(&code, &serializable.serialize()?)?;
+            let header = Self::serialized_header(COMPRESSION_ZSTD, metadata.len());
+            return Ok((header, metadata));
+        }
+        let metadata = serializable.serialize()?;
+        let header = Self::serialized_header(COMPRESSION_NONE, metadata.len());
+        Ok((header, metadata))
+    }
+
+    /// Build the header that precedes the serialized metadata, padded
+    /// up to `SERIALIZED_METADATA_CONTENT_OFFSET`.
+    fn serialized_header(compression: u8, metadata_len: usize) -> Vec<u8> {
+        let mut header = Self::MAGIC_HEADER.to_vec();
+        header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header.push(compression);
+        header.resize(SERIALIZED_METADATA_CONTENT_OFFSET, 0);
+        let mut writable_leb = &mut header[SERIALIZED_METADATA_LENGTH_OFFSET..];
+        leb128::write::unsigned(&mut writable_leb, metadata_len as u64)
+            .expect("Should write number");
+        header
+    }
+
+    /// The location and size of the machine code of each local function.
+    pub fn finished_function_extents(&self) -> PrimaryMap<LocalFunctionIndex, FunctionExtent> {
+        self.finished_functions
+            .values()
+            .copied()
+            .zip(self.finished_function_lengths.values().copied())
+            .map(|(ptr, length)| FunctionExtent { ptr, length })
+            .collect()
+    }
+
     /// Get the default extension when serializing this artifact
     pub fn get_default_extension(_triple: &Triple) -> &'static str {
         // `.wasmu` is the default extension for all the triples. It
@@ -283,14 +545,7 @@ impl Artifact for UniversalArtifact {
             return;
         }
 
-        let finished_function_extents = self
-            .finished_functions
-            .values()
-            .copied()
-            .zip(self.finished_function_lengths.values().copied())
-            .map(|(ptr, length)| FunctionExtent { ptr, length })
-            .collect::<PrimaryMap<LocalFunctionIndex, _>>()
-            .into_boxed_slice();
+        let finished_function_extents = self.finished_function_extents().into_boxed_slice();
 
         let frame_infos = &self.serializable.compilation.function_frame_info;
         *info = register_frame_info(
@@ -335,21 +590,159 @@ impl Artifact for UniversalArtifact {
     fn func_data_registry(&self) -> &FuncDataRegistry {
         &self.func_data_registry
     }
+
+    fn compilation_metrics(&self) -> Option<&CompilationMetrics> {
+        self.compilation_metrics.as_ref()
+    }
+
+    fn function_extents(&self) -> Option<PrimaryMap<LocalFunctionIndex, FunctionExtent>> {
+        Some(self.finished_function_extents())
+    }
+
+    fn function_frame_info(
+        &self,
+    ) -> Option<&PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>> {
+        Some(&self.serializable.compilation.function_frame_info)
+    }
+
+    fn eh_frame(&self) -> Option<&[u8]> {
+        self.eh_frame.as_ref().map(|eh_frame| unsafe {
+            // The section is kept alive by `code_memory`.
+            std::slice::from_raw_parts(eh_frame.start as *const u8, eh_frame.len())
+        })
+    }
+
+    fn code_owner(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+        Some(self.code_memory.clone())
+    }
+
     fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
-        // Prepend the header.
-        let mut serialized = Self::MAGIC_HEADER.to_vec();
+        if let Some(mmap) = &self.mmap {
+            return Ok(mmap.to_vec());
+        }
 
-        serialized.resize(SERIALIZED_METADATA_CONTENT_OFFSET, 0);
-        let mut writable_leb = &mut serialized[SERIALIZED_METADATA_LENGTH_OFFSET..];
-        let serialized_data = self.serializable.serialize()?;
-        let length = serialized_data.len();
-        leb128::write::unsigned(&mut writable_leb, length as u64).expect("Should write number");
+        let (mut serialized, serialized_data) = self.serialize_metadata(&self.serializable)?;
 
         let offset = pad_and_extend::<SerializableModule>(&mut serialized, &serialized_data);
         assert_eq!(offset, SERIALIZED_METADATA_CONTENT_OFFSET);
 
         Ok(serialized)
     }
+
+    /// Serialize a `UniversalArtifact` without the map from its machine
+    /// code to the Wasm instructions, the unwinding information of its
+    /// functions, and the function names of its `name` section, like
+    /// [`Universal::strip_debug_info`] does when compiling.
+    ///
+    /// [`Universal::strip_debug_info`]: crate::Universal::strip_debug_info
+    fn serialize_stripped(&self) -> Result<Vec<u8>, SerializeError> {
+        // The compiled code may only live in the mapped file, so the
+        // serializable module is read back from the serialized artifact.
+        let serialized = self.serialize()?;
+        let mut serializable = unsafe { Self::deserialize_serializable(&serialized) }
+            .map_err(|error| SerializeError::Generic(error.to_string()))?;
+        serializable.strip_debug_info();
+
+        let (mut stripped, serialized_data) = self.serialize_metadata(&serializable)?;
+
+        let offset = pad_and_extend::<SerializableModule>(&mut stripped, &serialized_data);
+        assert_eq!(offset, SERIALIZED_METADATA_CONTENT_OFFSET);
+
+        Ok(stripped)
+    }
+
+    fn serialize_to_writer(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        if let Some(mmap) = &self.mmap {
+            writer.write_all(&mmap)?;
+            return Ok(());
+        }
+
+        // The header is already padded to the metadata alignment, so
+        // the metadata can be written right after it without copying
+        // it into a new buffer first.
+        let (header, serialized_data) = self.serialize_metadata(&self.serializable)?;
+        writer.write_all(&header)?;
+        writer.write_all(&serialized_data)?;
+        Ok(())
+    }
+}
+
+/// The length of the lengths preceding the code and the compressed
+/// metadata.
+#[cfg(feature = "compression")]
+const LENGTH_SIZE: usize = 8;
+
+#[cfg(feature = "compression")]
+fn compress(code: &[u8], metadata: &[u8]) -> Result<Vec<u8>, SerializeError> {
+    let mut compressed = (code.len() as u64).to_le_bytes().to_vec();
+    compressed.extend_from_slice(code);
+    compressed.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+    zstd::stream::copy_encode(metadata, &mut compressed, 0)?;
+    Ok(compressed)
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress(_code: &[u8], _metadata: &[u8]) -> Result<Vec<u8>, SerializeError> {
+    Err(SerializeError::Generic(
+        "compressing artifacts requires the `compression` feature".to_string(),
+    ))
+}
+
+/// Split the code out of the compressed metadata, and decompress the
+/// rest of it into a buffer aligned for rkyv.
+#[cfg(feature = "compression")]
+fn decompress(metadata: &[u8]) -> Result<(&[u8], AlignedVec), DeserializeError> {
+    use std::convert::TryInto;
+    use std::io::{ErrorKind, Read};
+
+    let truncated =
+        || DeserializeError::CorruptedBinary("The compressed metadata is truncated".to_string());
+    if metadata.len() < LENGTH_SIZE {
+        return Err(truncated());
+    }
+    let (code_len, rest) = metadata.split_at(LENGTH_SIZE);
+    let code_len = u64::from_le_bytes(code_len.try_into().unwrap());
+    let (code, rest) = usize::try_from(code_len)
+        .ok()
+        .filter(|&code_len| code_len.saturating_add(LENGTH_SIZE) <= rest.len())
+        .map(|code_len| rest.split_at(code_len))
+        .ok_or_else(truncated)?;
+    let (len, compressed) = rest.split_at(LENGTH_SIZE);
+    let len = u64::from_le_bytes(len.try_into().unwrap());
+
+    let corrupted = |error: std::io::Error| {
+        DeserializeError::CorruptedBinary(format!("Can't decompress the metadata: {}", error))
+    };
+    // The decompression stops one byte past the declared length, so that
+    // a longer metadata is detected without decompressing all of it.
+    let mut decoder = zstd::stream::read::Decoder::new(compressed)
+        .map_err(corrupted)?
+        .take(len.saturating_add(1));
+    let mut decompressed = AlignedVec::new();
+    let mut chunk = [0; 16 * 1024];
+    loop {
+        match decoder.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => decompressed.extend_from_slice(&chunk[..read]),
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(corrupted(error)),
+        }
+    }
+    if decompressed.len() as u64 != len {
+        return Err(DeserializeError::CorruptedBinary(format!(
+            "The decompressed metadata has {} bytes, but {} were declared",
+            decompressed.len(),
+            len
+        )));
+    }
+    Ok((code, decompressed))
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(_metadata: &[u8]) -> Result<(&[u8], AlignedVec), DeserializeError> {
+    Err(DeserializeError::Incompatible(
+        "The artifact is compressed, which requires the `compression` feature".to_string(),
+    ))
 }
 
 /// It pads the data with the desired alignment
@@ -383,4 +776,36 @@ mod tests {
             &[1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0]
         );
     }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_decompress_bounded() {
+        use super::{compress, decompress, LENGTH_SIZE};
+
+        let code = vec![1, 2, 3];
+        let metadata = vec![7; 4096];
+        let compressed = compress(&code, &metadata).unwrap();
+        let (decompressed_code, decompressed) = decompress(&compressed).unwrap();
+        assert_eq!(decompressed_code, &code[..]);
+        assert_eq!(&decompressed[..], &metadata[..]);
+        // The decompressed metadata is aligned for rkyv
+        assert_eq!(decompressed.as_ptr() as usize % 16, 0);
+
+        // The metadata decompressing past its declared length is rejected
+        let len = LENGTH_SIZE + code.len();
+        let mut understated = compressed.clone();
+        understated[len..len + LENGTH_SIZE].copy_from_slice(&16u64.to_le_bytes());
+        assert!(decompress(&understated).is_err());
+
+        let mut overstated = compressed.clone();
+        overstated[len..len + LENGTH_SIZE].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(decompress(&overstated).is_err());
+
+        // So is the code running past the metadata
+        let mut truncated = compressed;
+        truncated[..LENGTH_SIZE].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(decompress(&truncated).is_err());
+
+        assert!(decompress(&[0; 4]).is_err());
+    }
 }
@@ -1,16 +1,22 @@
 use crate::UniversalEngine;
-use wasmer_compiler::{CompilerConfig, Features, Target};
+#[cfg(feature = "compiler")]
+use wasmer_compiler::CompilerConfig;
+use wasmer_compiler::{Features, Target};
 
 /// The Universal builder
 pub struct Universal {
-    #[allow(dead_code)]
+    #[cfg(feature = "compiler")]
     compiler_config: Option<Box<dyn CompilerConfig>>,
     target: Option<Target>,
     features: Option<Features>,
+    explicit_bounds_checks: bool,
+    compress_artifacts: bool,
+    strip_debug_info: bool,
 }
 
 impl Universal {
     /// Create a new Universal
+    #[cfg(feature = "compiler")]
     pub fn new<T>(compiler_config: T) -> Self
     where
         T: Into<Box<dyn CompilerConfig>>,
@@ -19,15 +25,28 @@ impl Universal {
             compiler_config: Some(compiler_config.into()),
             target: None,
             features: None,
+            explicit_bounds_checks: false,
+            compress_artifacts: false,
+            strip_debug_info: false,
         }
     }
 
     /// Create a new headless Universal
+    ///
+    /// The engine it builds has no compiler: it can only deserialize
+    /// modules compiled ahead of time by a Universal engine with a
+    /// compiler, and it's available without the `compiler` feature.
+    /// The target and features should match the ones the modules were
+    /// compiled with.
     pub fn headless() -> Self {
         Self {
+            #[cfg(feature = "compiler")]
             compiler_config: None,
             target: None,
             features: None,
+            explicit_bounds_checks: false,
+            compress_artifacts: false,
+            strip_debug_info: false,
         }
     }
 
@@ -43,24 +62,86 @@ impl Universal {
         self
     }
 
-    /// Build the `UniversalEngine` for this configuration
-    #[cfg(feature = "compiler")]
-    pub fn engine(self) -> UniversalEngine {
-        let target = self.target.unwrap_or_default();
-        if let Some(compiler_config) = self.compiler_config {
-            let features = self
-                .features
-                .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
-            let compiler = compiler_config.compiler();
-            UniversalEngine::new(compiler, target, features)
-        } else {
-            UniversalEngine::headless()
-        }
+    /// Emit explicit bounds checks for all the memory accesses.
+    ///
+    /// By default, memories that can be reserved up front are accessed
+    /// without any bounds check, relying on guard pages and the trap
+    /// signal handlers to catch out-of-bounds accesses. When enabled,
+    /// every memory is compiled with the dynamic style and no offset
+    /// guard, so out-of-bounds accesses are always caught by the
+    /// generated code itself instead of faulting on a guard page.
+    /// Integer divisions are always checked explicitly by the compilers.
+    ///
+    /// This doesn't avoid traps: a failed check still traps with an
+    /// illegal instruction (`ud2` on x86-64), which the trap signal
+    /// handlers turn into a `RuntimeError`, so they must still be
+    /// installed. It's slower, but it removes the need for guard pages
+    /// and for memory accesses that fault, for example when no large
+    /// virtual memory reservations can be made.
+    pub fn explicit_bounds_checks(mut self, enable: bool) -> Self {
+        self.explicit_bounds_checks = enable;
+        self
+    }
+
+    /// Compress the artifacts with zstd when they are serialized.
+    ///
+    /// Only the metadata of the module is compressed: the machine code
+    /// of its functions, which doesn't compress well, is stored as it is.
+    ///
+    /// Serialized artifacts are usually several times larger than the
+    /// Wasm modules they are compiled from. Compressed, they are much
+    /// cheaper to cache or to send over the network, at the cost of
+    /// decompressing them in memory when they are deserialized, which
+    /// also happens when they are deserialized from a file.
+    ///
+    /// Compressed artifacts are always recognized when deserializing,
+    /// whatever this setting is.
+    #[cfg(feature = "compression")]
+    pub fn compress_artifacts(mut self, enable: bool) -> Self {
+        self.compress_artifacts = enable;
+        self
+    }
+
+    /// Compile the modules without the information only used to debug
+    /// them, which is then left out of their serialized artifacts too.
+    ///
+    /// The artifacts lose the map from their machine code to the Wasm
+    /// instructions, the unwinding information of their functions
+    /// (`.eh_frame`), and the function names of the `name` section.
+    /// They are smaller and faster to load, but the backtraces of their
+    /// traps point to the start of the functions instead of the
+    /// instructions, with the functions named by their index, and
+    /// native debuggers and profilers can't unwind through them.
+    ///
+    /// To only leave the debug info out of some serialized artifacts,
+    /// serialize them with `Module::serialize_stripped` instead.
+    pub fn strip_debug_info(mut self, enable: bool) -> Self {
+        self.strip_debug_info = enable;
+        self
     }
 
     /// Build the `UniversalEngine` for this configuration
-    #[cfg(not(feature = "compiler"))]
     pub fn engine(self) -> UniversalEngine {
-        UniversalEngine::headless()
+        let target = self.target.unwrap_or_default();
+        #[cfg(feature = "compiler")]
+        let engine = match self.compiler_config {
+            Some(compiler_config) => {
+                let features = self
+                    .features
+                    .unwrap_or_else(|| compiler_config.default_features_for_target(&target));
+                UniversalEngine::new(compiler_config.compiler(), target, features)
+            }
+            None => UniversalEngine::headless_for_target(target, self.features.unwrap_or_default()),
+        };
+        #[cfg(not(feature = "compiler"))]
+        let engine =
+            UniversalEngine::headless_for_target(target, self.features.unwrap_or_default());
+        {
+            let mut inner = engine.inner_mut();
+            inner.set_explicit_bounds_checks(self.explicit_bounds_checks);
+            inner.set_compress_artifacts(self.compress_artifacts);
+            inner.set_strip_debug_info(self.strip_debug_info);
+        }
+        engine
     }
 }
@@ -4,6 +4,7 @@
 //! Memory management for executable code.
 use crate::unwind::UnwindRegistry;
 use loupe::MemoryUsage;
+use std::ops::Range;
 use wasmer_compiler::{CompiledFunctionUnwindInfo, CustomSection, FunctionBody};
 use wasmer_vm::{Mmap, VMFunctionBody};
 
@@ -18,12 +19,62 @@ const ARCH_FUNCTION_ALIGNMENT: usize = 16;
 ///
 const DATA_SECTION_ALIGNMENT: usize = 64;
 
+cfg_if::cfg_if! {
+    if #[cfg(all(target_os = "macos", target_arch = "aarch64"))] {
+        use std::os::raw::{c_int, c_void};
+
+        extern "C" {
+            fn pthread_jit_write_protect_np(enabled: c_int);
+            fn sys_icache_invalidate(start: *mut c_void, len: usize);
+        }
+
+        /// Allows the current thread to write into the `MAP_JIT` pages,
+        /// making them non-executable for it, until it is dropped.
+        struct JitWriteGuard;
+
+        impl JitWriteGuard {
+            fn new() -> Self {
+                unsafe { pthread_jit_write_protect_np(0) };
+                Self
+            }
+        }
+
+        impl Drop for JitWriteGuard {
+            /// Make the `MAP_JIT` pages executable again for the current
+            /// thread, whether the code was published or not.
+            fn drop(&mut self) {
+                unsafe { pthread_jit_write_protect_np(1) };
+            }
+        }
+
+        /// Make sure the current thread doesn't see stale instructions.
+        fn invalidate_icache(code: &mut [u8]) {
+            unsafe { sys_icache_invalidate(code.as_mut_ptr() as *mut c_void, code.len()) };
+        }
+    } else {
+        /// The pages are writable until they are published on the other
+        /// platforms, whatever the thread.
+        struct JitWriteGuard;
+
+        impl JitWriteGuard {
+            fn new() -> Self {
+                Self
+            }
+        }
+    }
+}
+
 /// Memory manager for executable code.
 #[derive(MemoryUsage)]
 pub struct CodeMemory {
     unwind_registry: UnwindRegistry,
     mmap: Mmap,
     start_of_nonexecutable_pages: usize,
+    /// Keeps the memory writable from `allocate` to `publish`, and makes
+    /// it executable again if the code memory is dropped in between, on
+    /// an error for example.
+    #[loupe(skip)]
+    jit_write: Option<JitWriteGuard>,
 }
 
 impl CodeMemory {
@@ -33,9 +84,16 @@ impl CodeMemory {
             unwind_registry: UnwindRegistry::new(),
             mmap: Mmap::new(),
             start_of_nonexecutable_pages: 0,
+            jit_write: None,
         }
     }
 
+    /// Get the range of addresses of the allocated memory.
+    pub fn address_range(&self) -> Range<usize> {
+        let start = self.mmap.as_ptr() as usize;
+        start..start + self.mmap.len()
+    }
+
     /// Mutably get the UnwindRegistry.
     pub fn unwind_registry_mut(&mut self) -> &mut UnwindRegistry {
         &mut self.unwind_registry
@@ -80,7 +138,8 @@ impl CodeMemory {
 
         // 2. Allocate the pages. Mark them all read-write.
 
-        self.mmap = Mmap::with_at_least(total_len)?;
+        self.mmap = Mmap::jit_with_at_least(total_len)?;
+        self.jit_write = Some(JitWriteGuard::new());
 
         // 3. Determine where the pointers to each function, executable section
         // or data section are. Copy the functions. Collect the addresses of each and return them.
@@ -138,9 +197,25 @@ impl CodeMemory {
     }
 
     /// Apply the page permissions.
-    pub fn publish(&mut self) {
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    pub fn publish(&mut self) -> Result<(), String> {
+        // `MAP_JIT` pages can't be made executable with `mprotect`;
+        // they become executable again once writing is disabled.
+        drop(self.jit_write.take());
+        let start_of_nonexecutable_pages = self.start_of_nonexecutable_pages;
+        invalidate_icache(&mut self.mmap.as_mut_slice()[..start_of_nonexecutable_pages]);
+        Ok(())
+    }
+
+    /// Apply the page permissions.
+    ///
+    /// This fails when the system forbids executable memory (for
+    /// instance a hardened SELinux policy denying `execmem`).
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    pub fn publish(&mut self) -> Result<(), String> {
+        drop(self.jit_write.take());
         if self.mmap.is_empty() || self.start_of_nonexecutable_pages == 0 {
-            return;
+            return Ok(());
         }
         assert!(self.mmap.len() >= self.start_of_nonexecutable_pages);
         unsafe {
@@ -150,7 +225,7 @@ impl CodeMemory {
                 region::Protection::READ_EXECUTE,
             )
         }
-        .expect("unable to make memory readonly and executable");
+        .map_err(|e| format!("unable to make memory readonly and executable: {}", e))
     }
 
     /// Calculates the allocation size of the given compiled function.
@@ -2,9 +2,11 @@
 
 use crate::{CodeMemory, UniversalArtifact};
 use loupe::MemoryUsage;
+use std::ops::Range;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 #[cfg(feature = "compiler")]
-use wasmer_compiler::Compiler;
+use wasmer_compiler::{CancellationToken, Compiler};
 use wasmer_compiler::{
     CompileError, CustomSection, CustomSectionProtection, FunctionBody, SectionIndex, Target,
 };
@@ -13,8 +15,9 @@ use wasmer_types::entity::PrimaryMap;
 use wasmer_types::Features;
 use wasmer_types::{FunctionIndex, FunctionType, LocalFunctionIndex, SignatureIndex};
 use wasmer_vm::{
-    FuncDataRegistry, FunctionBodyPtr, ModuleInfo, SectionBodyPtr, SignatureRegistry,
-    VMCallerCheckedAnyfunc, VMFuncRef, VMFunctionBody, VMSharedSignatureIndex, VMTrampoline,
+    FuncDataRegistry, FunctionBodyPtr, InstanceRef, ModuleInfo, SectionBodyPtr, SignatureRegistry,
+    VMCallerCheckedAnyfunc, VMFuncRef, VMFunctionBody, VMFunctionEnvironment,
+    VMSharedSignatureIndex, VMTrampoline, WeakInstanceRef,
 };
 
 /// A WebAssembly `Universal` Engine.
@@ -37,6 +40,9 @@ impl UniversalEngine {
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
                 features,
+                explicit_bounds_checks: false,
+                compress_artifacts: false,
+                strip_debug_info: false,
             })),
             target: Arc::new(target),
             engine_id: EngineId::default(),
@@ -56,7 +62,16 @@ impl UniversalEngine {
     ///
     /// Headless engines can't compile or validate any modules,
     /// they just take already processed Modules (via `Module::serialize`).
+    /// Attempting to compile or validate fails with
+    /// [`CompileError::Headless`].
     pub fn headless() -> Self {
+        Self::headless_for_target(Target::default(), Features::default())
+    }
+
+    /// Create a headless `UniversalEngine` for the given target and
+    /// features, which should match the ones of the engine that compiled
+    /// the modules it will load.
+    pub fn headless_for_target(target: Target, features: Features) -> Self {
         Self {
             inner: Arc::new(Mutex::new(UniversalEngineInner {
                 #[cfg(feature = "compiler")]
@@ -64,13 +79,29 @@ impl UniversalEngine {
                 code_memory: vec![],
                 signatures: SignatureRegistry::new(),
                 func_data: Arc::new(FuncDataRegistry::new()),
-                features: Features::default(),
+                features,
+                explicit_bounds_checks: false,
+                compress_artifacts: false,
+                strip_debug_info: false,
             })),
-            target: Arc::new(Target::default()),
+            target: Arc::new(target),
             engine_id: EngineId::default(),
         }
     }
 
+    /// Whether the engine is headless, i.e. has no compiler attached
+    /// and can only load already compiled modules.
+    pub fn is_headless(&self) -> bool {
+        #[cfg(feature = "compiler")]
+        {
+            self.inner().compiler.is_none()
+        }
+        #[cfg(not(feature = "compiler"))]
+        {
+            true
+        }
+    }
+
     pub(crate) fn inner(&self) -> std::sync::MutexGuard<'_, UniversalEngineInner> {
         self.inner.lock().unwrap()
     }
@@ -103,6 +134,19 @@ impl Engine for UniversalEngine {
         compiler.signatures().lookup(sig)
     }
 
+    fn lookup_function_owner(&self, vmctx: VMFunctionEnvironment) -> Option<WeakInstanceRef> {
+        let compiler = self.inner();
+        compiler.func_data().function_owner(vmctx)
+    }
+
+    fn lookup_function_call_trampoline(
+        &self,
+        sig: VMSharedSignatureIndex,
+    ) -> Option<(VMTrampoline, InstanceRef)> {
+        let compiler = self.inner();
+        compiler.func_data().function_call_trampoline(sig)
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError> {
         self.inner().validate(binary)
@@ -118,6 +162,22 @@ impl Engine for UniversalEngine {
         Ok(Arc::new(UniversalArtifact::new(&self, binary, tunables)?))
     }
 
+    /// Compile a WebAssembly binary, until `token` cancels the compilation
+    #[cfg(feature = "compiler")]
+    fn compile_cancellable(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+        token: &CancellationToken,
+    ) -> Result<Arc<dyn Artifact>, CompileError> {
+        Ok(Arc::new(UniversalArtifact::new_cancellable(
+            &self,
+            binary,
+            tunables,
+            Some(token),
+        )?))
+    }
+
     /// Compile a WebAssembly binary
     #[cfg(not(feature = "compiler"))]
     fn compile(
@@ -125,10 +185,7 @@ impl Engine for UniversalEngine {
         _binary: &[u8],
         _tunables: &dyn Tunables,
     ) -> Result<Arc<dyn Artifact>, CompileError> {
-        Err(CompileError::Codegen(
-            "The UniversalEngine is operating in headless mode, so it can not compile Modules."
-                .to_string(),
-        ))
+        Err(CompileError::Headless("UniversalEngine".to_string()))
     }
 
     /// Deserializes a WebAssembly module
@@ -136,6 +193,21 @@ impl Engine for UniversalEngine {
         Ok(Arc::new(UniversalArtifact::deserialize(&self, &bytes)?))
     }
 
+    /// Deserializes a WebAssembly module from a path, memory-mapping it
+    /// instead of reading it into memory.
+    unsafe fn deserialize_from_file(
+        &self,
+        file_ref: &Path,
+    ) -> Result<Arc<dyn Artifact>, DeserializeError> {
+        Ok(Arc::new(UniversalArtifact::deserialize_from_file(
+            &self, &file_ref,
+        )?))
+    }
+
+    unsafe fn gc(&self) {
+        self.inner_mut().gc();
+    }
+
     fn id(&self) -> &EngineId {
         &self.engine_id
     }
@@ -153,15 +225,25 @@ pub struct UniversalEngineInner {
     compiler: Option<Box<dyn Compiler>>,
     /// The features to compile the Wasm module with
     features: Features,
-    /// The code memory is responsible of publishing the compiled
-    /// functions to memory.
-    code_memory: Vec<CodeMemory>,
+    /// Whether all memories are compiled with explicit bounds checks,
+    /// regardless of the memory style chosen by the tunables.
+    explicit_bounds_checks: bool,
+    /// Whether the artifacts are compressed when they are serialized.
+    compress_artifacts: bool,
+    /// Whether the artifacts are compiled without their debug info.
+    strip_debug_info: bool,
+    /// The code memories of the artifacts, kept loaded until `gc`
+    /// unloads the unused ones, with their address ranges to reclaim the
+    /// function data pointing to them.
+    #[loupe(skip)]
+    code_memory: Vec<(Arc<CodeMemory>, Range<usize>)>,
     /// The signature registry is used mainly to operate with trampolines
     /// performantly.
     signatures: SignatureRegistry,
     /// The backing storage of `VMFuncRef`s. This centralized store ensures that 2
     /// functions with the same `VMCallerCheckedAnyfunc` will have the same `VMFuncRef`.
-    /// It also guarantees that the `VMFuncRef`s stay valid until the engine is dropped.
+    /// It also guarantees that the `VMFuncRef`s stay valid until the engine is dropped,
+    /// or until `gc` reclaims those of unloaded code.
     func_data: Arc<FuncDataRegistry>,
 }
 
@@ -170,7 +252,7 @@ impl UniversalEngineInner {
     #[cfg(feature = "compiler")]
     pub fn compiler(&self) -> Result<&dyn Compiler, CompileError> {
         if self.compiler.is_none() {
-            return Err(CompileError::Codegen("The UniversalEngine is operating in headless mode, so it can only execute already compiled Modules.".to_string()));
+            return Err(CompileError::Headless("UniversalEngine".to_string()));
         }
         Ok(&**self.compiler.as_ref().unwrap())
     }
@@ -184,10 +266,7 @@ impl UniversalEngineInner {
     /// Validate the module
     #[cfg(not(feature = "compiler"))]
     pub fn validate<'data>(&self, _data: &'data [u8]) -> Result<(), CompileError> {
-        Err(CompileError::Validate(
-            "The UniversalEngine is not compiled with compiler support, which is required for validating"
-                .to_string(),
-        ))
+        Err(CompileError::Headless("UniversalEngine".to_string()))
     }
 
     /// The Wasm features
@@ -195,10 +274,41 @@ impl UniversalEngineInner {
         &self.features
     }
 
-    /// Allocate compiled functions into memory
+    /// Whether all memories are compiled with explicit bounds checks.
+    pub fn explicit_bounds_checks(&self) -> bool {
+        self.explicit_bounds_checks
+    }
+
+    /// Set whether all memories are compiled with explicit bounds checks.
+    pub(crate) fn set_explicit_bounds_checks(&mut self, enable: bool) {
+        self.explicit_bounds_checks = enable;
+    }
+
+    /// Whether the artifacts are compressed when they are serialized.
+    pub fn compress_artifacts(&self) -> bool {
+        self.compress_artifacts
+    }
+
+    /// Set whether the artifacts are compressed when they are serialized.
+    pub(crate) fn set_compress_artifacts(&mut self, enable: bool) {
+        self.compress_artifacts = enable;
+    }
+
+    /// Whether the artifacts are compiled without their debug info.
+    pub fn strip_debug_info(&self) -> bool {
+        self.strip_debug_info
+    }
+
+    /// Set whether the artifacts are compiled without their debug info.
+    pub(crate) fn set_strip_debug_info(&mut self, enable: bool) {
+        self.strip_debug_info = enable;
+    }
+
+    /// Allocate compiled functions into `code_memory`
     #[allow(clippy::type_complexity)]
     pub(crate) fn allocate(
-        &mut self,
+        &self,
+        code_memory: &mut CodeMemory,
         _module: &ModuleInfo,
         functions: &PrimaryMap<LocalFunctionIndex, FunctionBody>,
         function_call_trampolines: &PrimaryMap<SignatureIndex, FunctionBody>,
@@ -221,12 +331,8 @@ impl UniversalEngineInner {
         let (executable_sections, data_sections): (Vec<_>, _) = custom_sections
             .values()
             .partition(|section| section.protection == CustomSectionProtection::ReadExecute);
-        self.code_memory.push(CodeMemory::new());
-
         let (mut allocated_functions, allocated_executable_sections, allocated_data_sections) =
-            self.code_memory
-                .last_mut()
-                .unwrap()
+            code_memory
                 .allocate(
                     function_bodies.as_slice(),
                     executable_sections.as_slice(),
@@ -288,16 +394,27 @@ impl UniversalEngineInner {
         ))
     }
 
-    /// Make memory containing compiled code executable.
-    pub(crate) fn publish_compiled_code(&mut self) {
-        self.code_memory.last_mut().unwrap().publish();
+    /// Make `code_memory`, containing compiled code, executable.
+    pub(crate) fn publish_compiled_code(
+        &self,
+        code_memory: &mut CodeMemory,
+    ) -> Result<(), CompileError> {
+        code_memory.publish().map_err(|e| {
+            CompileError::Resource(format!(
+                "{}; if this system forbids executable memory, precompile the module \
+                 with the Dylib or Staticlib engine instead",
+                e
+            ))
+        })
     }
 
     /// Register DWARF-type exception handling information associated with the code.
-    pub(crate) fn publish_eh_frame(&mut self, eh_frame: Option<&[u8]>) -> Result<(), CompileError> {
-        self.code_memory
-            .last_mut()
-            .unwrap()
+    pub(crate) fn publish_eh_frame(
+        &self,
+        code_memory: &mut CodeMemory,
+        eh_frame: Option<&[u8]>,
+    ) -> Result<(), CompileError> {
+        code_memory
             .unwind_registry_mut()
             .publish(eh_frame)
             .map_err(|e| {
@@ -306,6 +423,41 @@ impl UniversalEngineInner {
         Ok(())
     }
 
+    /// Keep the published `code_memory` of an artifact loaded, as the
+    /// function references to its functions don't keep it alive, until
+    /// `gc` unloads it.
+    pub(crate) fn register_code_memory(&mut self, code_memory: &Arc<CodeMemory>) {
+        self.code_memory
+            .push((code_memory.clone(), code_memory.address_range()));
+    }
+
+    /// Unload the code memories only the engine still holds, and reclaim
+    /// the function data pointing to them.
+    ///
+    /// # Safety
+    ///
+    /// See [`Engine::gc`].
+    unsafe fn gc(&mut self) {
+        let (unloaded, loaded): (Vec<_>, Vec<_>) = self
+            .code_memory
+            .drain(..)
+            .partition(|(code_memory, _)| Arc::strong_count(code_memory) == 1);
+        self.code_memory = loaded;
+        if unloaded.is_empty() {
+            return;
+        }
+        let contains = |ranges: &[(Arc<CodeMemory>, Range<usize>)], address: usize| {
+            ranges.iter().any(|(_, range)| range.contains(&address))
+        };
+        self.func_data.retain(|anyfunc| {
+            let address = anyfunc.func_ptr as usize;
+            !contains(&unloaded, address)
+        });
+        // The code is unmapped once the function data pointing to it is
+        // gone.
+        drop(unloaded);
+    }
+
     /// Shared signature registry.
     pub fn signatures(&self) -> &SignatureRegistry {
         &self.signatures
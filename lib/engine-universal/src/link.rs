@@ -54,6 +54,10 @@ fn apply_relocation(
             write_unaligned(reloc_address as *mut u32, reloc_delta as _);
         },
         RelocationKind::X86PCRelRodata4 => {}
+        RelocationKind::S390xPCRel32Dbl => unsafe {
+            let (reloc_address, reloc_delta) = r.for_address(body, target_func_address as u64);
+            write_unaligned(reloc_address as *mut u32, reloc_delta as _);
+        },
         kind => panic!(
             "Relocation kind unsupported in the current architecture {}",
             kind
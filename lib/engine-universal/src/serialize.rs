@@ -6,12 +6,14 @@ use rkyv::{
     ser::{serializers::WriteSerializer, Serializer as RkyvSerializer},
     Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize,
 };
+use std::convert::{TryFrom, TryInto};
+use std::sync::Arc;
 use wasmer_compiler::{
     CompileModuleInfo, CompiledFunctionFrameInfo, CustomSection, Dwarf, FunctionBody,
-    JumpTableOffsets, Relocation, SectionIndex,
+    JumpTableOffsets, Relocation, SectionBody, SectionIndex,
 };
 use wasmer_engine::{DeserializeError, SerializeError};
-use wasmer_types::entity::PrimaryMap;
+use wasmer_types::entity::{EntityRef, PrimaryMap};
 use wasmer_types::{FunctionIndex, LocalFunctionIndex, OwnedDataInitializer, SignatureIndex};
 
 /// The compilation related data for a serialized modules
@@ -29,6 +31,27 @@ pub struct SerializableCompilation {
     pub debug: Option<Dwarf>,
 }
 
+impl SerializableCompilation {
+    /// Drop the information only used to debug the module: the map
+    /// from the machine code to the Wasm instructions, and the
+    /// unwinding information of the functions.
+    ///
+    /// The traps are kept, as they tell why the code trapped.
+    pub fn strip_debug_info(&mut self) {
+        for frame_info in self.function_frame_info.values_mut() {
+            frame_info.address_map.instructions = Vec::new();
+        }
+        if let Some(debug) = self.debug.take() {
+            // Nothing refers to the section, so it's emptied rather than
+            // removed, which would shift the indices of the next ones.
+            let eh_frame = &mut self.custom_sections[debug.eh_frame];
+            eh_frame.bytes = SectionBody::default();
+            eh_frame.relocations = Vec::new();
+            self.custom_section_relocations[debug.eh_frame] = Vec::new();
+        }
+    }
+}
+
 /// Serializable struct that is able to serialize from and to
 /// a `UniversalArtifactInfo`.
 #[derive(MemoryUsage, Archive, RkyvDeserialize, RkyvSerialize)]
@@ -43,6 +66,66 @@ fn to_serialize_error(err: impl std::error::Error) -> SerializeError {
 }
 
 impl SerializableModule {
+    /// Drop the information only used to debug the module: the one of
+    /// its compilation, and the function names of its `name` section.
+    pub fn strip_debug_info(&mut self) {
+        self.compilation.strip_debug_info();
+        Arc::make_mut(&mut self.compile_info.module)
+            .function_names
+            .clear();
+    }
+
+    /// Split the machine code of the functions and trampolines, which
+    /// doesn't compress well, out of the module, so only the rest of the
+    /// module is compressed.
+    ///
+    /// The code is returned as the bytes of each body preceded by their
+    /// length, a little-endian `u64`, in the order `join_code` reads them.
+    pub fn split_code(&self) -> (Self, Vec<u8>) {
+        let compilation = &self.compilation;
+        let mut code = Vec::new();
+        let function_bodies = split_bodies(&compilation.function_bodies, &mut code);
+        let function_call_trampolines =
+            split_bodies(&compilation.function_call_trampolines, &mut code);
+        let dynamic_function_trampolines =
+            split_bodies(&compilation.dynamic_function_trampolines, &mut code);
+        let module = Self {
+            compilation: SerializableCompilation {
+                function_bodies,
+                function_relocations: compilation.function_relocations.clone(),
+                function_jt_offsets: compilation.function_jt_offsets.clone(),
+                function_frame_info: compilation.function_frame_info.clone(),
+                function_call_trampolines,
+                dynamic_function_trampolines,
+                custom_sections: compilation.custom_sections.clone(),
+                custom_section_relocations: compilation.custom_section_relocations.clone(),
+                debug: compilation.debug.clone(),
+            },
+            compile_info: CompileModuleInfo {
+                features: self.compile_info.features.clone(),
+                module: self.compile_info.module.clone(),
+                memory_styles: self.compile_info.memory_styles.clone(),
+                table_styles: self.compile_info.table_styles.clone(),
+            },
+            data_initializers: self.data_initializers.clone(),
+        };
+        (module, code)
+    }
+
+    /// Put back the machine code split out of the module by `split_code`.
+    pub fn join_code(&mut self, mut code: &[u8]) -> Result<(), DeserializeError> {
+        let compilation = &mut self.compilation;
+        join_bodies(&mut compilation.function_bodies, &mut code)?;
+        join_bodies(&mut compilation.function_call_trampolines, &mut code)?;
+        join_bodies(&mut compilation.dynamic_function_trampolines, &mut code)?;
+        if !code.is_empty() {
+            return Err(DeserializeError::CorruptedBinary(
+                "The code has more bodies than the module".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Serialize a Module into bytes
     /// The bytes will have the following format:
     /// RKYV serialization (any length) + POS (8 bytes)
@@ -101,3 +184,45 @@ impl SerializableModule {
             .map_err(|e| DeserializeError::CorruptedBinary(format!("{:?}", e)))
     }
 }
+
+/// Append the bytes of `bodies` to `code`, preceded by their length, and
+/// return the bodies without them.
+fn split_bodies<K: EntityRef>(
+    bodies: &PrimaryMap<K, FunctionBody>,
+    code: &mut Vec<u8>,
+) -> PrimaryMap<K, FunctionBody> {
+    bodies
+        .values()
+        .map(|body| {
+            code.extend_from_slice(&(body.body.len() as u64).to_le_bytes());
+            code.extend_from_slice(&body.body);
+            FunctionBody {
+                body: Vec::new(),
+                unwind_info: body.unwind_info.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Read the bytes of `bodies` from the start of `code`, as written by
+/// `split_bodies`.
+fn join_bodies<K: EntityRef>(
+    bodies: &mut PrimaryMap<K, FunctionBody>,
+    code: &mut &[u8],
+) -> Result<(), DeserializeError> {
+    for body in bodies.values_mut() {
+        let remaining: &[u8] = *code;
+        let end = remaining
+            .get(..8)
+            .map(|len| u64::from_le_bytes(len.try_into().unwrap()))
+            .and_then(|len| usize::try_from(len).ok())
+            .and_then(|len| len.checked_add(8))
+            .filter(|&end| end <= remaining.len())
+            .ok_or_else(|| {
+                DeserializeError::CorruptedBinary("The code is truncated".to_string())
+            })?;
+        body.body = remaining[8..end].to_vec();
+        *code = &remaining[end..];
+    }
+    Ok(())
+}
@@ -1,12 +1,15 @@
 use crate::{
-    resolve_imports, InstantiationError, Resolver, RuntimeError, SerializeError, Tunables,
+    resolve_imports, CompilationMetrics, FunctionExtent, InstantiationError, Resolver,
+    RuntimeError, SerializeError, Tunables,
 };
 use loupe::MemoryUsage;
 use std::any::Any;
 use std::fs;
-use std::path::Path;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use wasmer_compiler::Features;
+use wasmer_compiler::{CompiledFunctionFrameInfo, Features, SymbolRegistry};
 use wasmer_types::entity::{BoxedSlice, PrimaryMap};
 use wasmer_types::{
     DataInitializer, FunctionIndex, LocalFunctionIndex, MemoryIndex, OwnedDataInitializer,
@@ -70,13 +73,97 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
     /// Get the func data registry
     fn func_data_registry(&self) -> &FuncDataRegistry;
 
+    /// Returns the metrics measured while compiling this `Artifact`, if it
+    /// was compiled rather than deserialized, and the engine measures them.
+    fn compilation_metrics(&self) -> Option<&CompilationMetrics> {
+        None
+    }
+
+    /// Returns the location and size of the machine code of the local
+    /// functions, for profilers and debuggers to symbolize addresses.
+    ///
+    /// Engines which don't know the size of the functions return `None`.
+    fn function_extents(&self) -> Option<PrimaryMap<LocalFunctionIndex, FunctionExtent>> {
+        None
+    }
+
+    /// Returns the frame information of the local functions: the offsets
+    /// of their traps, and the mapping of their instructions to the
+    /// offsets in the Wasm binary, if the engine keeps it.
+    fn function_frame_info(
+        &self,
+    ) -> Option<&PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>> {
+        None
+    }
+
+    /// Returns the `.eh_frame` unwind information registered for the code
+    /// of this `Artifact`, as it's mapped in memory, if any.
+    fn eh_frame(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Returns the registry naming the functions, sections and trampolines
+    /// of this `Artifact` in the object file it was loaded from, if any.
+    fn symbol_registry(&self) -> Option<&dyn SymbolRegistry> {
+        None
+    }
+
+    /// Returns the owner of the memory holding the compiled code of this
+    /// `Artifact`, which the instances keep alive.
+    ///
+    /// The engine keeps the code loaded too, until [`Engine::gc`] unloads
+    /// the code no artifact or instance uses anymore.
+    ///
+    /// Engines whose code lives as long as the process return `None`.
+    ///
+    /// [`Engine::gc`]: crate::Engine::gc
+    fn code_owner(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+        None
+    }
+
     /// Serializes an artifact into bytes
     fn serialize(&self) -> Result<Vec<u8>, SerializeError>;
 
+    /// Serializes an artifact into bytes, without the information only
+    /// used to debug it, for the engines able to strip it.
+    ///
+    /// The artifact itself is left as it is.
+    fn serialize_stripped(&self) -> Result<Vec<u8>, SerializeError> {
+        Err(SerializeError::Generic(
+            "this engine can't strip the debug info of its artifacts".to_string(),
+        ))
+    }
+
+    /// Serializes an artifact into a writer
+    ///
+    /// Engines that can produce their serialized representation in
+    /// pieces should override this, so the whole artifact doesn't
+    /// need to be buffered in memory before being written.
+    fn serialize_to_writer(&self, writer: &mut dyn Write) -> Result<(), SerializeError> {
+        let serialized = self.serialize()?;
+        writer.write_all(&serialized)?;
+        Ok(())
+    }
+
     /// Serializes an artifact into a file path
+    ///
+    /// The artifact is written to a temporary file next to `path`, which
+    /// then replaces it: `path` may be the file a loaded artifact is
+    /// memory-mapped from, which must not be truncated while it's mapped.
     fn serialize_to_file(&self, path: &Path) -> Result<(), SerializeError> {
-        let serialized = self.serialize()?;
-        fs::write(&path, serialized)?;
+        let temporary_path = temporary_path(path);
+        let written = fs::File::create(&temporary_path)
+            .map_err(SerializeError::from)
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                self.serialize_to_writer(&mut writer)?;
+                writer.flush()?;
+                Ok(())
+            });
+        if let Err(error) = written.and_then(|()| Ok(fs::rename(&temporary_path, path)?)) {
+            let _ = fs::remove_file(&temporary_path);
+            return Err(error);
+        }
         Ok(())
     }
 
@@ -149,6 +236,7 @@ pub trait Artifact: Send + Sync + Upcastable + MemoryUsage {
             self.func_data_registry(),
             host_state,
             import_function_envs,
+            self.code_owner(),
         )
         .map_err(|trap| InstantiationError::Start(RuntimeError::from_trap(trap)))?;
         Ok(handle)
@@ -214,3 +302,16 @@ impl dyn Artifact + 'static {
         self.upcast_any_mut().downcast_mut::<T>()
     }
 }
+
+/// Returns a path next to `path`, unique to this process and call, to
+/// write a file that is then renamed to `path`.
+fn temporary_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(file_name)
+}
@@ -4,12 +4,16 @@ use crate::tunables::Tunables;
 use crate::{Artifact, DeserializeError};
 use loupe::MemoryUsage;
 use memmap2::Mmap;
+use std::io::Read;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
-use wasmer_compiler::{CompileError, Target};
+use wasmer_compiler::{CancellationToken, CompileError, Target};
 use wasmer_types::FunctionType;
-use wasmer_vm::{VMCallerCheckedAnyfunc, VMFuncRef, VMSharedSignatureIndex};
+use wasmer_vm::{
+    InstanceRef, VMCallerCheckedAnyfunc, VMFuncRef, VMFunctionEnvironment, VMSharedSignatureIndex,
+    VMTrampoline, WeakInstanceRef,
+};
 
 /// A unimplemented Wasmer `Engine`.
 ///
@@ -30,6 +34,21 @@ pub trait Engine: MemoryUsage {
     /// Lookup a signature
     fn lookup_signature(&self, sig: VMSharedSignatureIndex) -> Option<FunctionType>;
 
+    /// Lookup the instance owning the functions with the environment
+    /// `vmctx`, even if it was dropped, or `None` for the host functions.
+    fn lookup_function_owner(&self, _vmctx: VMFunctionEnvironment) -> Option<WeakInstanceRef> {
+        None
+    }
+
+    /// Lookup a trampoline to call the functions of the signature `sig`
+    /// from the host, with the instance keeping its code loaded.
+    fn lookup_function_call_trampoline(
+        &self,
+        _sig: VMSharedSignatureIndex,
+    ) -> Option<(VMTrampoline, InstanceRef)> {
+        None
+    }
+
     /// Validates a WebAssembly module
     fn validate(&self, binary: &[u8]) -> Result<(), CompileError>;
 
@@ -40,6 +59,26 @@ pub trait Engine: MemoryUsage {
         tunables: &dyn Tunables,
     ) -> Result<Arc<dyn Artifact>, CompileError>;
 
+    /// Compile a WebAssembly binary, failing with
+    /// [`CompileError::Cancelled`] once `token` is cancelled or its
+    /// timeout expires.
+    ///
+    /// By default, the token is only checked before compiling, for the
+    /// engines which can't interrupt their compilations.
+    fn compile_cancellable(
+        &self,
+        binary: &[u8],
+        tunables: &dyn Tunables,
+        token: &CancellationToken,
+    ) -> Result<Arc<dyn Artifact>, CompileError> {
+        if token.is_cancelled() {
+            return Err(CompileError::Cancelled(
+                "the compilation was cancelled".to_string(),
+            ));
+        }
+        self.compile(binary, tunables)
+    }
+
     /// Deserializes a WebAssembly module
     ///
     /// # Safety
@@ -47,6 +86,23 @@ pub trait Engine: MemoryUsage {
     /// The serialized content must represent a serialized WebAssembly module.
     unsafe fn deserialize(&self, bytes: &[u8]) -> Result<Arc<dyn Artifact>, DeserializeError>;
 
+    /// Deserializes a WebAssembly module from a reader
+    ///
+    /// The reader is read until it is exhausted, and the artifact is
+    /// deserialized from the bytes read.
+    ///
+    /// # Safety
+    ///
+    /// The read content must represent a serialized WebAssembly module.
+    unsafe fn deserialize_from_reader(
+        &self,
+        reader: &mut dyn Read,
+    ) -> Result<Arc<dyn Artifact>, DeserializeError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        self.deserialize(&bytes)
+    }
+
     /// Deserializes a WebAssembly module from a path
     ///
     /// # Safety
@@ -61,18 +117,39 @@ pub trait Engine: MemoryUsage {
         self.deserialize(&mmap)
     }
 
+    /// Unloads the compiled code of the artifacts which were dropped with
+    /// all their instances and functions, and reclaims the memory the
+    /// engine holds for it, like the function references of their
+    /// instances.
+    ///
+    /// The engine otherwise keeps the compiled code of all its artifacts
+    /// loaded until it's dropped. Long-running hosts compiling many
+    /// modules can call this periodically to free it.
+    ///
+    /// # Safety
+    ///
+    /// The function references to the functions of the unloaded
+    /// artifacts are dangling afterwards. The tables and globals hold raw
+    /// function references, which don't keep the code of the functions
+    /// loaded, so no table or global still alive may hold a reference to
+    /// a function of an artifact which was dropped, even if the instance
+    /// owning the table or global is still alive.
+    unsafe fn gc(&self) {}
+
     /// A unique identifier for this object.
     ///
     /// This exists to allow us to compare two Engines for equality. Otherwise,
     /// comparing two trait objects unsafely relies on implementation details
     /// of trait representation.
+    ///
+    /// The clones of an engine share its state, so they have its identifier.
     fn id(&self) -> &EngineId;
 
     /// Clone the engine
     fn cloned(&self) -> Arc<dyn Engine + Send + Sync>;
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, MemoryUsage)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, MemoryUsage)]
 #[repr(transparent)]
 /// A unique identifier for an Engine.
 pub struct EngineId {
@@ -86,12 +163,6 @@ impl EngineId {
     }
 }
 
-impl Clone for EngineId {
-    fn clone(&self) -> Self {
-        Self::default()
-    }
-}
-
 impl Default for EngineId {
     fn default() -> Self {
         static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
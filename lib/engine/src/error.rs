@@ -54,6 +54,12 @@ pub enum ImportError {
     /// This error occurs when an import was expected but not provided.
     #[error("unknown import. Expected {0:?}")]
     UnknownImport(ExternType),
+
+    /// Incompatible Engine.
+    /// This error occurs when the import was created with another engine
+    /// than the module, like in another `Store`.
+    #[error("incompatible engine. The import was created with another engine than the module, like in another `Store`")]
+    IncompatibleEngine,
 }
 
 /// The WebAssembly.LinkError object indicates an error during
@@ -24,6 +24,7 @@ mod artifact;
 mod engine;
 mod error;
 mod export;
+mod metrics;
 mod resolver;
 mod trap;
 mod tunables;
@@ -34,6 +35,7 @@ pub use crate::error::{
     DeserializeError, ImportError, InstantiationError, LinkError, SerializeError,
 };
 pub use crate::export::{Export, ExportFunction, ExportFunctionMetadata};
+pub use crate::metrics::CompilationMetrics;
 pub use crate::resolver::{
     resolve_imports, ChainableNamedResolver, NamedResolver, NamedResolverChain, NullResolver,
     Resolver,
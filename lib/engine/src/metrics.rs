@@ -0,0 +1,34 @@
+use std::time::Duration;
+use wasmer_types::entity::PrimaryMap;
+use wasmer_types::LocalFunctionIndex;
+
+/// Metrics measured while compiling a module into an [`Artifact`], to
+/// monitor the performance of the compilers.
+///
+/// [`Artifact`]: crate::Artifact
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompilationMetrics {
+    /// The time spent parsing the module and translating it for the
+    /// compiler.
+    pub translation_time: Duration,
+    /// The time spent compiling the functions and the trampolines.
+    pub compilation_time: Duration,
+    /// The time spent linking the compiled code and making it executable.
+    pub link_time: Duration,
+    /// The size of the generated code of the functions and the
+    /// trampolines, in bytes.
+    pub code_size: usize,
+    /// The number of relocations applied to the functions and the custom
+    /// sections.
+    pub num_relocations: usize,
+    /// The time each function took to compile, if the compiler measures
+    /// it. With several threads, their sum can exceed `compilation_time`.
+    pub function_compile_times: PrimaryMap<LocalFunctionIndex, Duration>,
+}
+
+impl CompilationMetrics {
+    /// Returns the total time of the compilation.
+    pub fn total_time(&self) -> Duration {
+        self.translation_time + self.compilation_time + self.link_time
+    }
+}
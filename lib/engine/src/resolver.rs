@@ -1,8 +1,9 @@
 //! Define the `Resolver` trait, allowing custom resolution for external
 //! references.
 
-use crate::{Export, ExportFunctionMetadata, ImportError, LinkError};
+use crate::{Engine, Export, ExportFunctionMetadata, ImportError, LinkError};
 use more_asserts::assert_ge;
+use std::sync::Arc;
 use wasmer_types::entity::{BoxedSlice, EntityRef, PrimaryMap};
 use wasmer_types::{ExternType, FunctionIndex, ImportIndex, MemoryIndex, TableIndex};
 
@@ -34,6 +35,20 @@ pub trait Resolver {
     /// )
     /// ```
     fn resolve(&self, _index: u32, module: &str, field: &str) -> Option<Export>;
+
+    /// Returns the engine the export an import resolves to was created
+    /// with, if the resolver knows it.
+    ///
+    /// Exports can only be imported by modules of the same engine, which
+    /// this lets instantiation check, instead of failing in confusing ways.
+    fn resolve_engine(
+        &self,
+        _index: u32,
+        _module: &str,
+        _field: &str,
+    ) -> Option<Arc<dyn Engine + Send + Sync>> {
+        None
+    }
 }
 
 /// Import resolver connects imports with available exported values.
@@ -47,6 +62,18 @@ pub trait NamedResolver {
     /// It receives the `module` and `field` names and return the [`Export`] in
     /// case it's found.
     fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export>;
+
+    /// Returns the engine the export of `module` and `field` was created
+    /// with, if the resolver knows it.
+    ///
+    /// See [`Resolver::resolve_engine`].
+    fn resolve_engine_by_name(
+        &self,
+        _module: &str,
+        _field: &str,
+    ) -> Option<Arc<dyn Engine + Send + Sync>> {
+        None
+    }
 }
 
 // All NamedResolvers should extend `Resolver`.
@@ -56,18 +83,46 @@ impl<T: NamedResolver> Resolver for T {
     fn resolve(&self, _index: u32, module: &str, field: &str) -> Option<Export> {
         self.resolve_by_name(module, field)
     }
+
+    /// By default this method will be calling
+    /// [`NamedResolver::resolve_engine_by_name`], dismissing the provided
+    /// `index`.
+    fn resolve_engine(
+        &self,
+        _index: u32,
+        module: &str,
+        field: &str,
+    ) -> Option<Arc<dyn Engine + Send + Sync>> {
+        self.resolve_engine_by_name(module, field)
+    }
 }
 
 impl<T: NamedResolver> NamedResolver for &T {
     fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
         (**self).resolve_by_name(module, field)
     }
+
+    fn resolve_engine_by_name(
+        &self,
+        module: &str,
+        field: &str,
+    ) -> Option<Arc<dyn Engine + Send + Sync>> {
+        (**self).resolve_engine_by_name(module, field)
+    }
 }
 
 impl NamedResolver for Box<dyn NamedResolver> {
     fn resolve_by_name(&self, module: &str, field: &str) -> Option<Export> {
         (**self).resolve_by_name(module, field)
     }
+
+    fn resolve_engine_by_name(
+        &self,
+        module: &str,
+        field: &str,
+    ) -> Option<Arc<dyn Engine + Send + Sync>> {
+        (**self).resolve_engine_by_name(module, field)
+    }
 }
 
 impl NamedResolver for () {
@@ -367,6 +422,18 @@ where
             .resolve_by_name(module, field)
             .or_else(|| self.b.resolve_by_name(module, field))
     }
+
+    fn resolve_engine_by_name(
+        &self,
+        module: &str,
+        field: &str,
+    ) -> Option<Arc<dyn Engine + Send + Sync>> {
+        if self.a.resolve_by_name(module, field).is_some() {
+            self.a.resolve_engine_by_name(module, field)
+        } else {
+            self.b.resolve_engine_by_name(module, field)
+        }
+    }
 }
 
 impl<A, B> Clone for NamedResolverChain<A, B>
@@ -1,8 +1,9 @@
 use super::frame_info::{FrameInfo, GlobalFrameInfo, FRAME_INFO};
 use backtrace::Backtrace;
+use std::any::Any;
 use std::error::Error;
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use wasmer_vm::{raise_user_trap, Trap, TrapCode};
 
 /// A struct representing an aborted instruction execution, with a message
@@ -19,6 +20,8 @@ enum RuntimeErrorSource {
     OOM,
     User(Box<dyn Error + Send + Sync>),
     Trap(TrapCode),
+    /// A panic of a host function, with its message and payload
+    Panic(String, Mutex<Option<Box<dyn Any + Send>>>),
 }
 
 impl fmt::Display for RuntimeErrorSource {
@@ -28,6 +31,7 @@ impl fmt::Display for RuntimeErrorSource {
             Self::User(s) => write!(f, "{}", s),
             Self::OOM => write!(f, "Wasmer VM out of memory"),
             Self::Trap(s) => write!(f, "{}", s.message()),
+            Self::Panic(s, _) => write!(f, "host function panicked: {}", s),
         }
     }
 }
@@ -64,6 +68,33 @@ impl RuntimeError {
         )
     }
 
+    /// Creates a `RuntimeError` from the payload of a panic of a host
+    /// function, which traps instead of unwinding through the Wasm frames.
+    ///
+    /// # Example
+    /// ```
+    /// let payload = std::panic::catch_unwind(|| panic!("oops")).unwrap_err();
+    /// let trap = wasmer_engine::RuntimeError::from_panic(payload);
+    /// assert_eq!("host function panicked: oops", trap.message());
+    /// assert!(trap.is_panic());
+    /// ```
+    pub fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let info = FRAME_INFO.read().unwrap();
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "Box<Any>".to_string()
+        };
+        Self::new_with_trace(
+            &info,
+            None,
+            RuntimeErrorSource::Panic(message, Mutex::new(Some(payload))),
+            Backtrace::new_unresolved(),
+        )
+    }
+
     /// Create a new RuntimeError from a Trap.
     pub fn from_trap(trap: Trap) -> Self {
         let info = FRAME_INFO.read().unwrap();
@@ -182,6 +213,17 @@ impl RuntimeError {
         }
     }
 
+    /// Attempts to downcast the `RuntimeError` to a reference of a
+    /// concrete type, which works on a shared error unlike
+    /// [`RuntimeError::downcast`].
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        match &self.inner.source {
+            // We only try to downcast user errors
+            RuntimeErrorSource::User(err) => err.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+
     /// Returns trap code, if it's a Trap
     pub fn to_trap(self) -> Option<TrapCode> {
         if let RuntimeErrorSource::Trap(trap_code) = self.inner.source {
@@ -191,6 +233,25 @@ impl RuntimeError {
         }
     }
 
+    /// Returns true if the `RuntimeError` comes from a panic of a host
+    /// function.
+    pub fn is_panic(&self) -> bool {
+        matches!(self.inner.source, RuntimeErrorSource::Panic(..))
+    }
+
+    /// Takes the payload of the panic of the host function this error
+    /// comes from, to resume it with [`std::panic::resume_unwind`] for
+    /// example. It's only returned once, since the error may be shared.
+    pub fn take_panic_payload(&self) -> Option<Box<dyn Any + Send>> {
+        match &self.inner.source {
+            RuntimeErrorSource::Panic(_, payload) => match payload.lock() {
+                Ok(mut payload) => payload.take(),
+                Err(poisoned) => poisoned.into_inner().take(),
+            },
+            _ => None,
+        }
+    }
+
     /// Returns true if the `RuntimeError` is the same as T
     pub fn is<T: Error + 'static>(&self) -> bool {
         match &self.inner.source {
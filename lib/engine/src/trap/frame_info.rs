@@ -141,7 +141,11 @@ impl GlobalFrameInfo {
         Some(FrameInfo {
             module_name: module.module.name(),
             func_index: func_index.index() as u32,
-            function_name: module.module.function_names.get(&func_index).cloned(),
+            function_name: module
+                .module
+                .function_names
+                .get(&func_index)
+                .map(|name| name.to_string()),
             instr,
             func_start: instr_map.start_srcloc,
         })
@@ -179,7 +183,7 @@ impl Drop for GlobalFrameInfoRegistration {
 
 /// Represents a continuous region of executable memory starting with a function
 /// entry point.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct FunctionExtent {
     /// Entry point for normal entry of the function. All addresses in the
@@ -131,7 +131,7 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
             .push(GlobalInit::I64Const(self.initial_limit as i64));
 
         module_info.exports.insert(
-            "wasmer_metering_remaining_points".to_string(),
+            "wasmer_metering_remaining_points".into(),
             ExportIndex::Global(remaining_points_global_index),
         );
 
@@ -145,7 +145,7 @@ impl<F: Fn(&Operator) -> u64 + Send + Sync + 'static> ModuleMiddleware for Meter
             .push(GlobalInit::I32Const(0));
 
         module_info.exports.insert(
-            "wasmer_metering_points_exhausted".to_string(),
+            "wasmer_metering_points_exhausted".into(),
             ExportIndex::Global(points_exhausted_global_index),
         );
 
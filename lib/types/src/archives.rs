@@ -8,23 +8,14 @@ use std::{collections::HashMap, hash::Hash};
 #[derive(Serialize, Deserialize, Archive)]
 /// Rkyv Archivable IndexMap
 pub struct ArchivableIndexMap<K: Hash + Eq + Archive, V: Archive> {
-    indices: HashMap<K, u64>,
     entries: Vec<(K, V)>,
 }
 
 impl<K: Hash + Eq + Archive + Clone, V: Archive> From<IndexMap<K, V>> for ArchivableIndexMap<K, V> {
     fn from(it: IndexMap<K, V>) -> ArchivableIndexMap<K, V> {
-        let mut r = ArchivableIndexMap {
-            indices: HashMap::new(),
-            entries: Vec::new(),
-        };
-        let mut i: u64 = 0;
-        for (k, v) in it.into_iter() {
-            r.indices.insert(k.clone(), i);
-            r.entries.push((k, v));
-            i += 1;
+        ArchivableIndexMap {
+            entries: it.into_iter().collect(),
         }
-        r
     }
 }
 
@@ -37,3 +28,26 @@ impl<K: Hash + Eq + Archive + Clone, V: Archive> Into<IndexMap<K, V>> for Archiv
         r
     }
 }
+
+#[derive(Serialize, Deserialize, Archive)]
+/// Rkyv Archivable HashMap
+///
+/// The entries are sorted by key, so the same map is always archived
+/// to the same bytes, whatever the iteration order of the `HashMap`.
+pub struct ArchivableHashMap<K: Hash + Ord + Archive, V: Archive> {
+    entries: Vec<(K, V)>,
+}
+
+impl<K: Hash + Ord + Archive, V: Archive> From<HashMap<K, V>> for ArchivableHashMap<K, V> {
+    fn from(it: HashMap<K, V>) -> ArchivableHashMap<K, V> {
+        let mut entries: Vec<(K, V)> = it.into_iter().collect();
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        ArchivableHashMap { entries }
+    }
+}
+
+impl<K: Hash + Ord + Archive, V: Archive> Into<HashMap<K, V>> for ArchivableHashMap<K, V> {
+    fn into(self) -> HashMap<K, V> {
+        self.entries.into_iter().collect()
+    }
+}
@@ -8,10 +8,10 @@ use crate::entity::keys::Keys;
 use crate::entity::EntityRef;
 use crate::lib::std::boxed::Box;
 use crate::lib::std::marker::PhantomData;
+use crate::lib::std::mem;
 use crate::lib::std::ops::{Index, IndexMut};
 use crate::lib::std::slice;
 use loupe::{MemoryUsage, MemoryUsageTracker};
-use std::mem;
 
 /// A slice mapping `K -> V` allocating dense entity references.
 ///
@@ -9,6 +9,7 @@ use crate::entity::EntityRef;
 use crate::lib::std::boxed::Box;
 use crate::lib::std::iter::FromIterator;
 use crate::lib::std::marker::PhantomData;
+use crate::lib::std::mem;
 use crate::lib::std::ops::{Index, IndexMut};
 use crate::lib::std::slice;
 use crate::lib::std::vec::Vec;
@@ -17,7 +18,6 @@ use loupe::{MemoryUsage, MemoryUsageTracker};
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
-use std::mem;
 
 /// A primary mapping `K -> V` allocating dense entity references.
 ///
@@ -8,6 +8,7 @@ use crate::entity::keys::Keys;
 use crate::entity::EntityRef;
 use crate::lib::std::cmp::min;
 use crate::lib::std::marker::PhantomData;
+use crate::lib::std::mem;
 use crate::lib::std::ops::{Index, IndexMut};
 use crate::lib::std::slice;
 use crate::lib::std::vec::Vec;
@@ -20,7 +21,6 @@ use serde::{
     ser::{SerializeSeq, Serializer},
     Deserialize, Serialize,
 };
-use std::mem;
 
 /// A mapping `K -> V` for densely indexed entity references.
 ///
@@ -1,6 +1,8 @@
-use std::any::Any;
-use std::ptr;
-use std::sync::atomic;
+use crate::lib::std::any::Any;
+use crate::lib::std::boxed::Box;
+use crate::lib::std::fmt;
+use crate::lib::std::ptr;
+use crate::lib::std::sync::atomic;
 
 /// This type does not do reference counting automatically, reference counting can be done with
 /// [`Self::ref_clone`] and [`Self::ref_drop`].
@@ -10,7 +12,7 @@ pub struct VMExternRef(*const VMExternRefInner);
 
 impl VMExternRef {
     /// The maximum number of references allowed to this data.
-    const MAX_REFCOUNT: usize = std::usize::MAX - 1;
+    const MAX_REFCOUNT: usize = usize::MAX - 1;
 
     /// Checks if the given ExternRef is null.
     pub fn is_null(&self) -> bool {
@@ -47,6 +49,22 @@ impl VMExternRef {
         Self(Box::into_raw(Box::new(VMExternRefInner::new::<T>(value))))
     }
 
+    /// Make a new extern reference, calling `finalizer` with the value
+    /// once the last reference to it is dropped.
+    pub fn new_with_finalizer<T, F>(value: T, finalizer: F) -> Self
+    where
+        T: Any + Send + Sync + 'static + Sized,
+        F: FnOnce(T) + Send + Sync + 'static,
+    {
+        let mut inner = VMExternRefInner::new::<T>(value);
+        inner.finalizer = Some(Box::new(move |data: Box<dyn Any + Send + Sync>| {
+            if let Ok(value) = data.downcast::<T>() {
+                finalizer(*value)
+            }
+        }));
+        Self(Box::into_raw(Box::new(inner)))
+    }
+
     /// Try to downcast to the given value
     pub fn downcast<T>(&self) -> Option<&T>
     where
@@ -130,7 +148,7 @@ impl VMExternRef {
                     ref_inner.decrement_and_drop()
                 };
                 if should_drop {
-                    let _ = Box::from_raw(self.0 as *mut VMExternRefInner);
+                    Box::from_raw(self.0 as *mut VMExternRefInner).finalize();
                 }
             }
         }
@@ -147,13 +165,26 @@ impl VMExternRef {
     }
 }
 
-#[derive(Debug)]
+type Finalizer = Box<dyn FnOnce(Box<dyn Any + Send + Sync + 'static>) + Send + Sync + 'static>;
+
 #[repr(C)]
 pub(crate) struct VMExternRefInner {
     strong: atomic::AtomicUsize,
     /// Do something obviously correct to get started. This can "easily" be improved
     /// to be an inline allocation later as the logic is fully encapsulated.
     data: Box<dyn Any + Send + Sync + 'static>,
+    /// Called with the data when the last reference is dropped.
+    finalizer: Option<Finalizer>,
+}
+
+impl fmt::Debug for VMExternRefInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VMExternRefInner")
+            .field("strong", &self.strong)
+            .field("data", &self.data)
+            .field("finalizer", &self.finalizer.is_some())
+            .finish()
+    }
 }
 
 impl VMExternRefInner {
@@ -164,6 +195,14 @@ impl VMExternRefInner {
         Self {
             strong: atomic::AtomicUsize::new(1),
             data: Box::new(value),
+            finalizer: None,
+        }
+    }
+
+    /// Drops the data, passing it to the finalizer first if there is one.
+    fn finalize(self) {
+        if let Some(finalizer) = self.finalizer {
+            finalizer(self.data);
         }
     }
 
@@ -263,6 +302,26 @@ impl ExternRef {
         }
     }
 
+    #[cfg(feature = "experimental-reference-types-extern-ref")]
+    /// Make a new extern reference holding host data, like a file handle
+    /// or a database connection, which `finalizer` releases once the last
+    /// reference to it is dropped, whether it is held by the host or by a
+    /// table or a global of an instance.
+    ///
+    /// The finalizer runs on the thread dropping the last reference, which
+    /// may be in the middle of a call into WebAssembly, so it should not
+    /// block nor panic: a panic can't unwind through the WebAssembly code
+    /// dropping the reference, and aborts the process.
+    pub fn new_with_finalizer<T, F>(value: T, finalizer: F) -> Self
+    where
+        T: Any + Send + Sync + 'static + Sized,
+        F: FnOnce(T) + Send + Sync + 'static,
+    {
+        Self {
+            inner: VMExternRef::new_with_finalizer(value, finalizer),
+        }
+    }
+
     #[cfg(feature = "experimental-reference-types-extern-ref")]
     /// Try to downcast to the given value
     pub fn downcast<T>(&self) -> Option<&T>
@@ -289,7 +348,7 @@ impl From<ExternRef> for VMExternRef {
     fn from(other: ExternRef) -> Self {
         let out = other.inner;
         // We want to make this transformation without decrementing the count.
-        std::mem::forget(other);
+        crate::lib::std::mem::forget(other);
         out
     }
 }
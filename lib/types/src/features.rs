@@ -35,6 +35,33 @@ pub struct Features {
     pub memory64: bool,
     /// Wasm exceptions proposal should be enabled
     pub exceptions: bool,
+    /// The limits the modules must respect to pass validation
+    pub limits: ValidationLimits,
+}
+
+/// Limits on the size of the modules, checked when they are validated,
+/// before they are compiled.
+///
+/// They protect the hosts running untrusted modules from modules that
+/// would take too much time or memory to compile or instantiate. All the
+/// limits are disabled by default.
+#[derive(Clone, Debug, Default, Eq, PartialEq, MemoryUsage)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+pub struct ValidationLimits {
+    /// The maximum size of the Wasm binary, in bytes
+    pub max_module_size: Option<u64>,
+    /// The maximum number of functions, imported and defined
+    pub max_functions: Option<u32>,
+    /// The maximum number of locals declared by a function, excluding
+    /// its parameters
+    pub max_locals_per_function: Option<u32>,
+    /// The maximum initial size of the tables, imported and defined, in
+    /// elements
+    pub max_table_size: Option<u32>,
 }
 
 impl Features {
@@ -55,6 +82,7 @@ impl Features {
             multi_memory: false,
             memory64: false,
             exceptions: false,
+            limits: ValidationLimits::default(),
         }
     }
 
@@ -231,6 +259,34 @@ impl Features {
         self.memory64 = enable;
         self
     }
+
+    /// Configures whether the WebAssembly exception handling proposal will
+    /// be enabled.
+    ///
+    /// The [WebAssembly exception handling proposal][proposal] is not
+    /// currently fully standardized and is undergoing development.
+    /// Support for this feature can be enabled through this method for
+    /// appropriate WebAssembly modules.
+    ///
+    /// This feature gates the `try`, `catch` and `throw` instructions
+    /// and the exception tags being in a module.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/WebAssembly/exception-handling
+    pub fn exceptions(&mut self, enable: bool) -> &mut Self {
+        self.exceptions = enable;
+        self
+    }
+
+    /// Configures the limits the modules must respect to pass validation,
+    /// like their number of functions.
+    ///
+    /// There are no limits by default.
+    pub fn limits(&mut self, limits: ValidationLimits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
 }
 
 impl Default for Features {
@@ -258,6 +314,7 @@ mod test_features {
                 multi_memory: false,
                 memory64: false,
                 exceptions: false,
+                limits: ValidationLimits::default(),
             }
         );
     }
@@ -337,4 +394,15 @@ mod test_features {
         features.memory64(true);
         assert!(features.memory64);
     }
+
+    #[test]
+    fn set_limits() {
+        let mut features = Features::new();
+        features.limits(ValidationLimits {
+            max_functions: Some(10),
+            ..Default::default()
+        });
+        assert_eq!(features.limits.max_functions, Some(10));
+        assert_eq!(features.limits.max_module_size, None);
+    }
 }
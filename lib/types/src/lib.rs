@@ -69,7 +69,7 @@ mod values;
 /// The entity module, with common helpers for Rust structures
 pub mod entity;
 pub use crate::extern_ref::{ExternRef, VMExternRef};
-pub use crate::features::Features;
+pub use crate::features::{Features, ValidationLimits};
 pub use crate::indexes::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, FunctionIndex, GlobalIndex, ImportIndex,
     LocalFunctionIndex, LocalGlobalIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
@@ -78,7 +78,7 @@ pub use crate::indexes::{
 pub use crate::initializers::{
     DataInitializer, DataInitializerLocation, OwnedDataInitializer, TableInitializer,
 };
-pub use crate::memory_view::{Atomically, MemoryView};
+pub use crate::memory_view::{Atomic, Atomically, MemoryView};
 pub use crate::native::{NativeWasmType, ValueType};
 pub use crate::units::{
     Bytes, PageCountOutOfRange, Pages, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
@@ -90,7 +90,7 @@ pub use types::{
 };
 
 #[cfg(feature = "enable-rkyv")]
-pub use archives::ArchivableIndexMap;
+pub use archives::{ArchivableHashMap, ArchivableIndexMap};
 
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -7,7 +7,10 @@ use crate::lib::std::sync::atomic::{
 };
 use crate::native::ValueType;
 
+/// A type with an atomic counterpart of the same size, through which it
+/// can be accessed in an atomic [`MemoryView`].
 pub trait Atomic {
+    /// The atomic type.
     type Output;
 }
 
@@ -66,6 +69,18 @@ where
     }
 }
 
+impl<'a, T> MemoryView<'a, T, Atomically> {
+    /// Creates a new atomic MemoryView of the `length` atomics at `ptr`,
+    /// which must be aligned for `T`.
+    pub unsafe fn new_atomic(ptr: *mut T, length: u32) -> Self {
+        Self {
+            ptr,
+            length: length as usize,
+            _phantom: PhantomData,
+        }
+    }
+}
+
 impl<'a, T: Atomic> MemoryView<'a, T> {
     /// Get atomic access to a memory view.
     pub fn atomically(&self) -> MemoryView<'a, T::Output, Atomically> {
@@ -268,3 +268,8 @@ macro_rules! impl_value_type_for {
 }
 
 impl_value_type_for!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+// An array is valid for all bit patterns if its items are, and has no padding:
+// the size of a type is a multiple of its alignment, so the items follow each
+// other, and they have no padding themselves, being `ValueType`s
+unsafe impl<T: ValueType, const N: usize> ValueType for [T; N] {}
@@ -1,4 +1,5 @@
 use crate::lib::std::convert::TryFrom;
+use crate::lib::std::convert::TryInto;
 use crate::lib::std::fmt;
 use crate::lib::std::ops::{Add, Sub};
 use loupe::MemoryUsage;
@@ -6,7 +7,7 @@ use loupe::MemoryUsage;
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
+#[cfg(feature = "std")]
 use thiserror::Error;
 
 /// WebAssembly page sizes are fixed to be 64KiB.
@@ -118,8 +119,9 @@ where
 }
 
 /// The only error that can happen when converting `Bytes` to `Pages`
-#[derive(Debug, Clone, Copy, PartialEq, Error)]
-#[error("Number of pages exceeds uint32 range")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[cfg_attr(feature = "std", error("Number of pages exceeds uint32 range"))]
 pub struct PageCountOutOfRange;
 
 impl TryFrom<Bytes> for Pages {
@@ -1,4 +1,5 @@
 use crate::extern_ref::ExternRef;
+use crate::lib::std::any::Any;
 use crate::lib::std::convert::TryFrom;
 use crate::lib::std::fmt;
 use crate::lib::std::ptr;
@@ -63,7 +64,7 @@ macro_rules! accessors {
 
 /// Trait for reading and writing Wasm values into binary for use on the layer
 /// between the API and the VM internals, specifically with `wasmer_types::Value`.
-pub trait WasmValueType: std::fmt::Debug + 'static {
+pub trait WasmValueType: fmt::Debug + 'static {
     /// Write the value
     unsafe fn write_value_to(&self, p: *mut i128);
 
@@ -72,13 +73,13 @@ pub trait WasmValueType: std::fmt::Debug + 'static {
     // structure of our crates. We need to talk about the store in the rest of the
     // VM (for example where this method is used) but cannot do so. Fixing this
     // may be non-trivial.
-    unsafe fn read_value_from(store: &dyn std::any::Any, p: *const i128) -> Self;
+    unsafe fn read_value_from(store: &dyn Any, p: *const i128) -> Self;
 }
 
 impl WasmValueType for () {
     unsafe fn write_value_to(&self, _p: *mut i128) {}
 
-    unsafe fn read_value_from(_store: &dyn std::any::Any, _p: *const i128) -> Self {
+    unsafe fn read_value_from(_store: &dyn Any, _p: *const i128) -> Self {
         ()
     }
 }
@@ -131,7 +132,7 @@ where
     /// `p` must be:
     /// - Properly aligned to the specified `ty`'s Rust equivalent
     /// - Non-null and pointing to valid memory
-    pub unsafe fn read_value_from(store: &dyn std::any::Any, p: *const i128, ty: Type) -> Self {
+    pub unsafe fn read_value_from(store: &dyn Any, p: *const i128, ty: Type) -> Self {
         match ty {
             Type::I32 => Self::I32(ptr::read(p as *const i32)),
             Type::I64 => Self::I64(ptr::read(p as *const i64)),
@@ -256,6 +257,15 @@ where
     }
 }
 
+impl<T> From<u128> for Value<T>
+where
+    T: WasmValueType,
+{
+    fn from(val: u128) -> Self {
+        Self::V128(val)
+    }
+}
+
 impl<T> From<ExternRef> for Value<T>
 where
     T: WasmValueType,
@@ -275,6 +285,7 @@ const NOT_I32: &str = "Value is not of Wasm type i32";
 const NOT_I64: &str = "Value is not of Wasm type i64";
 const NOT_F32: &str = "Value is not of Wasm type f32";
 const NOT_F64: &str = "Value is not of Wasm type f64";
+const NOT_V128: &str = "Value is not of Wasm type v128";
 
 impl<T> TryFrom<Value<T>> for i32
 where
@@ -342,6 +353,17 @@ where
     }
 }
 
+impl<T> TryFrom<Value<T>> for u128
+where
+    T: WasmValueType,
+{
+    type Error = &'static str;
+
+    fn try_from(value: Value<T>) -> Result<Self, Self::Error> {
+        value.v128().ok_or(NOT_V128)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -473,4 +495,15 @@ mod tests {
         let result = f64::try_from(value);
         assert_eq!(result.unwrap_err(), "Value is not of Wasm type f64");
     }
+
+    #[test]
+    fn convert_value_to_u128() {
+        let value = Value::<()>::from(u128::MAX);
+        let result = u128::try_from(value);
+        assert_eq!(result.unwrap(), u128::MAX);
+
+        let value = Value::<()>::I64(42);
+        let result = u128::try_from(value);
+        assert_eq!(result.unwrap_err(), "Value is not of Wasm type v128");
+    }
 }
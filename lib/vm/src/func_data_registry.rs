@@ -4,10 +4,13 @@
 //! This registry also helps ensure that the `VMFuncRef`s can stay valid for as
 //! long as we need them to.
 
-use crate::vmcontext::VMCallerCheckedAnyfunc;
+use crate::instance::{InstanceRef, WeakInstanceRef};
+use crate::vmcontext::{
+    VMCallerCheckedAnyfunc, VMFunctionEnvironment, VMSharedSignatureIndex, VMTrampoline,
+};
 use loupe::MemoryUsage;
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 /// The registry that holds the values that `VMFuncRef`s point to.
 #[derive(Debug, MemoryUsage)]
@@ -17,7 +20,7 @@ pub struct FuncDataRegistry {
     // threads, and ideally we can compile across many threads. As a result we
     // use interior mutability here with a lock to avoid having callers to
     // externally synchronize calls to compilation.
-    inner: Mutex<Inner>,
+    inner: Arc<Mutex<Inner>>,
 }
 
 // We use raw pointers but the data never moves, so it's not a problem
@@ -91,6 +94,15 @@ unsafe impl Sync for VMFuncRef {}
 struct Inner {
     func_data: Vec<Box<VMCallerCheckedAnyfunc>>,
     anyfunc_to_index: HashMap<VMCallerCheckedAnyfunc, usize>,
+    /// The instances owning the functions, by the address of their
+    /// `VMContext`. The instances are removed when they are dropped, by
+    /// their `InstanceRegistration`, as the address can be reused.
+    #[loupe(skip)]
+    owners: HashMap<usize, WeakInstanceRef>,
+    /// The trampolines to call the functions of each signature from the
+    /// host, with the instance keeping their code loaded.
+    #[loupe(skip)]
+    trampolines: HashMap<VMSharedSignatureIndex, Vec<(VMTrampoline, WeakInstanceRef)>>,
 }
 
 impl FuncDataRegistry {
@@ -118,4 +130,121 @@ impl FuncDataRegistry {
             VMFuncRef(inner_ptr)
         }
     }
+
+    /// Create the registration of the instance with the `VMContext`
+    /// `vmctx` and the signatures `signatures`, which must be dropped with
+    /// the instance to unregister it.
+    pub(crate) fn instance_registration(
+        &self,
+        vmctx: VMFunctionEnvironment,
+        signatures: Vec<VMSharedSignatureIndex>,
+    ) -> InstanceRegistration {
+        InstanceRegistration {
+            inner: self.inner.clone(),
+            vmctx: unsafe { vmctx.host_env } as usize,
+            signatures,
+        }
+    }
+
+    /// Register `instance` as the owner of the functions with the
+    /// `VMContext` `vmctx`, and the trampolines of its module to call the
+    /// functions of their signatures from the host.
+    pub(crate) fn register_instance(
+        &self,
+        instance: &InstanceRef,
+        vmctx: VMFunctionEnvironment,
+        trampolines: impl Iterator<Item = (VMSharedSignatureIndex, VMTrampoline)>,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        let owner = instance.downgrade();
+        inner
+            .owners
+            .insert(unsafe { vmctx.host_env } as usize, owner.clone());
+        for (signature, trampoline) in trampolines {
+            let trampolines = inner.trampolines.entry(signature).or_default();
+            trampolines.retain(|(_, instance)| instance.is_alive());
+            trampolines.push((trampoline, owner.clone()));
+        }
+    }
+
+    /// Return the instance owning the functions with the environment
+    /// `vmctx`, or `None` for the host functions and the functions of the
+    /// dropped instances.
+    pub fn function_owner(&self, vmctx: VMFunctionEnvironment) -> Option<WeakInstanceRef> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .owners
+            .get(&(unsafe { vmctx.host_env } as usize))
+            .cloned()
+    }
+
+    /// Return a trampoline to call the functions of the signature
+    /// `signature` from the host, with the instance keeping it loaded, if
+    /// an instance still alive declares the signature.
+    pub fn function_call_trampoline(
+        &self,
+        signature: VMSharedSignatureIndex,
+    ) -> Option<(VMTrampoline, InstanceRef)> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .trampolines
+            .get(&signature)?
+            .iter()
+            .find_map(|(trampoline, instance)| Some((*trampoline, instance.upgrade()?)))
+    }
+
+    /// Remove the function data for which `f` returns `false`.
+    ///
+    /// # Safety
+    ///
+    /// The `VMFuncRef`s pointing to the removed data are dangling
+    /// afterwards, so they must not be used anymore: none may be left in
+    /// a table or a global.
+    pub unsafe fn retain<F>(&self, mut f: F)
+    where
+        F: FnMut(&VMCallerCheckedAnyfunc) -> bool,
+    {
+        let mut inner = self.inner.lock().unwrap();
+        inner.func_data.retain(|anyfunc| f(anyfunc));
+        let anyfunc_to_index = inner
+            .func_data
+            .iter()
+            .enumerate()
+            .map(|(idx, anyfunc)| (**anyfunc, idx))
+            .collect();
+        inner.anyfunc_to_index = anyfunc_to_index;
+    }
+
+    /// Return the number of function data registered.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().func_data.len()
+    }
+
+    /// Return whether no function data is registered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The registration of an instance in a `FuncDataRegistry`, held by the
+/// instance to remove it from the registry when it is dropped.
+pub(crate) struct InstanceRegistration {
+    inner: Arc<Mutex<Inner>>,
+    vmctx: usize,
+    signatures: Vec<VMSharedSignatureIndex>,
+}
+
+impl Drop for InstanceRegistration {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.owners.remove(&self.vmctx);
+        for signature in &self.signatures {
+            if let Some(trampolines) = inner.trampolines.get_mut(signature) {
+                trampolines.retain(|(_, instance)| instance.is_alive());
+                if trampolines.is_empty() {
+                    inner.trampolines.remove(signature);
+                }
+            }
+        }
+    }
 }
@@ -14,11 +14,12 @@ pub use allocator::InstanceAllocator;
 pub use r#ref::{InstanceRef, WeakInstanceRef, WeakOrStrongInstanceRef};
 
 use crate::export::VMExtern;
-use crate::func_data_registry::{FuncDataRegistry, VMFuncRef};
+use crate::func_data_registry::{FuncDataRegistry, InstanceRegistration, VMFuncRef};
 use crate::global::Global;
 use crate::imports::Imports;
 use crate::memory::{Memory, MemoryError};
-use crate::table::{Table, TableElement};
+use crate::mmap::resident_size;
+use crate::table::{RawTableElement, Table, TableElement};
 use crate::trap::{catch_traps, Trap, TrapCode, TrapHandler};
 use crate::vmcontext::{
     VMBuiltinFunctionsArray, VMCallerCheckedAnyfunc, VMContext, VMFunctionBody,
@@ -98,6 +99,17 @@ pub(crate) struct Instance {
     /// Hosts can store arbitrary per-instance information here.
     host_state: Box<dyn Any>,
 
+    /// The owner of the memory holding the compiled code of the
+    /// functions and trampolines, kept alive as long as the instance
+    /// so the code isn't unmapped while it can still run.
+    #[loupe(skip)]
+    code_owner: Option<Arc<dyn Any + Send + Sync>>,
+
+    /// The registration of the instance as the owner of its functions,
+    /// removed from the registry when the instance is dropped.
+    #[loupe(skip)]
+    registration: Option<InstanceRegistration>,
+
     /// Functions to operate on host environments in the imports
     /// and pointers to the environments.
     ///
@@ -113,6 +125,36 @@ pub(crate) struct Instance {
     vmctx: VMContext,
 }
 
+/// The memory used by an instance, returned by
+/// [`InstanceHandle::memory_usage`].
+///
+/// Only the memories and tables defined by the instance are counted, the
+/// imported ones being accounted to the instances or hosts defining them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InstanceMemoryUsage {
+    /// The current size of the linear memories, in bytes.
+    pub memories_size: usize,
+    /// How much of the linear memories is resident in physical memory, in
+    /// bytes, or `None` if it can't be determined on this platform.
+    pub memories_resident_size: Option<usize>,
+    /// The number of elements of the tables.
+    pub tables_elements: usize,
+    /// The size of the elements of the tables, in bytes.
+    pub tables_size: usize,
+    /// The size of the runtime state of the instance, in bytes: the
+    /// instance itself, its `VMContext` and its globals.
+    pub runtime_size: usize,
+}
+
+impl InstanceMemoryUsage {
+    /// Returns the total memory used by the instance, in bytes. The
+    /// linear memories are counted with their current size, whether
+    /// they're resident or not.
+    pub fn total(&self) -> usize {
+        self.memories_size + self.tables_size + self.runtime_size
+    }
+}
+
 /// A collection of data about host envs used by imported functions.
 #[derive(Debug)]
 pub enum ImportFunctionEnv {
@@ -235,6 +277,23 @@ impl Instance {
         unsafe { self.vmctx_plus_offset(self.offsets.vmctx_signature_ids_begin()) }
     }
 
+    /// Return the trampoline to call, from the host, the functions of the
+    /// shared signature `signature`, if the module declares it.
+    ///
+    /// The trampolines only depend on the signature, so they can call the
+    /// functions of other instances too, like the ones in a shared table.
+    pub(crate) fn function_call_trampoline(
+        &self,
+        signature: VMSharedSignatureIndex,
+    ) -> Option<VMTrampoline> {
+        let signature_ids = self.signature_ids_ptr();
+        self.module
+            .signatures
+            .keys()
+            .find(|index| unsafe { *signature_ids.add(index.index()) } == signature)
+            .map(|index| self.function_call_trampolines[index])
+    }
+
     /// Return the indexed `VMFunctionImport`.
     fn imported_function(&self, index: FunctionIndex) -> &VMFunctionImport {
         let index = usize::try_from(index.as_u32()).unwrap();
@@ -852,6 +911,30 @@ impl Instance {
         self.tables[index].as_ref()
     }
 
+    /// Return the memory used by this instance.
+    pub(crate) fn memory_usage(&self) -> InstanceMemoryUsage {
+        let mut usage = InstanceMemoryUsage {
+            memories_resident_size: Some(0),
+            ..Default::default()
+        };
+        for memory in self.memories.values() {
+            let definition = unsafe { memory.vmmemory().as_ref() };
+            let length = definition.current_length as usize;
+            usage.memories_size += length;
+            usage.memories_resident_size = usage
+                .memories_resident_size
+                .and_then(|size| Some(size + resident_size(definition.base, length)?));
+        }
+        for table in self.tables.values() {
+            usage.tables_elements += table.size() as usize;
+        }
+        usage.tables_size = usage.tables_elements * mem::size_of::<RawTableElement>();
+        usage.runtime_size = mem::size_of::<Self>()
+            + self.offsets.size_of_vmctx() as usize
+            + self.globals.len() * mem::size_of::<Global>();
+        usage
+    }
+
     /// Get an imported, foreign table.
     pub(crate) fn get_foreign_table(&self, index: TableIndex) -> &dyn Table {
         let import = self.imported_table(index);
@@ -892,6 +975,8 @@ impl InstanceHandle {
     ///   all the local tables.
     /// - The memory at `instance.memories_ptr()` must be initialized with data for
     ///   all the local memories.
+    /// - `code_owner` must keep the memory of `finished_functions` and
+    ///   `finished_function_call_trampolines` alive, if they're not static.
     #[allow(clippy::too_many_arguments)]
     pub unsafe fn new(
         allocator: InstanceAllocator,
@@ -906,6 +991,7 @@ impl InstanceHandle {
         func_data_registry: &FuncDataRegistry,
         host_state: Box<dyn Any>,
         imported_function_envs: BoxedSlice<FunctionIndex, ImportFunctionEnv>,
+        code_owner: Option<Arc<dyn Any + Send + Sync>>,
     ) -> Result<Self, Trap> {
         let vmctx_globals = finished_globals
             .values()
@@ -930,6 +1016,8 @@ impl InstanceHandle {
                 passive_elements: Default::default(),
                 passive_data,
                 host_state,
+                code_owner,
+                registration: None,
                 funcrefs,
                 imported_function_envs,
                 vmctx: VMContext {},
@@ -949,6 +1037,10 @@ impl InstanceHandle {
                     &vmshared_signatures,
                     vmctx_ptr,
                 );
+                instance.registration = Some(func_data_registry.instance_registration(
+                    VMFunctionEnvironment { vmctx: vmctx_ptr },
+                    vmshared_signatures.values().cloned().collect(),
+                ));
             }
 
             Self {
@@ -962,6 +1054,15 @@ impl InstanceHandle {
             instance.signature_ids_ptr() as *mut VMSharedSignatureIndex,
             vmshared_signatures.len(),
         );
+        func_data_registry.register_instance(
+            handle.instance(),
+            VMFunctionEnvironment {
+                vmctx: instance.vmctx_ptr(),
+            },
+            vmshared_signatures
+                .iter()
+                .map(|(index, signature)| (*signature, instance.function_call_trampolines[index])),
+        );
         ptr::copy(
             imports.functions.values().as_slice().as_ptr(),
             instance.imported_functions_ptr() as *mut VMFunctionImport,
@@ -1226,6 +1327,12 @@ impl InstanceHandle {
         self.instance().as_ref().get_local_table(index)
     }
 
+    /// Return the memory used by this instance: its linear memories,
+    /// tables and runtime state.
+    pub fn memory_usage(&self) -> InstanceMemoryUsage {
+        self.instance().as_ref().memory_usage()
+    }
+
     /// Initializes the host environments.
     ///
     /// # Safety
@@ -1,4 +1,5 @@
 use super::Instance;
+use crate::vmcontext::{VMSharedSignatureIndex, VMTrampoline};
 use loupe::{MemoryUsage, MemoryUsageTracker};
 use std::alloc::Layout;
 use std::convert::TryFrom;
@@ -137,6 +138,22 @@ impl InstanceRef {
         (&*self.0).as_ref()
     }
 
+    /// Get the trampoline to call, from the host, the functions of the
+    /// shared signature `signature`, if the module of the instance declares
+    /// it.
+    pub fn function_call_trampoline(
+        &self,
+        signature: VMSharedSignatureIndex,
+    ) -> Option<VMTrampoline> {
+        self.as_ref().function_call_trampoline(signature)
+    }
+
+    /// Create a weak reference to the `Instance`, which doesn't keep it
+    /// alive.
+    pub fn downgrade(&self) -> WeakInstanceRef {
+        WeakInstanceRef(Arc::downgrade(&self.0))
+    }
+
     /// Only succeeds if ref count is 1.
     #[inline]
     pub(super) fn as_mut(&mut self) -> Option<&mut Instance> {
@@ -175,6 +192,11 @@ impl WeakInstanceRef {
         let inner = self.0.upgrade()?;
         Some(InstanceRef(inner))
     }
+
+    /// Whether the `Instance` wasn't dropped yet.
+    pub(crate) fn is_alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
 }
 
 impl MemoryUsage for WeakInstanceRef {
@@ -29,6 +29,7 @@ mod instance;
 mod memory;
 mod mmap;
 mod module;
+mod mpk;
 mod probestack;
 mod sig_registry;
 mod table;
@@ -44,11 +45,12 @@ pub use crate::global::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{
     ImportFunctionEnv, ImportInitializerFuncPtr, InstanceAllocator, InstanceHandle,
-    WeakOrStrongInstanceRef,
+    InstanceMemoryUsage, InstanceRef, WeakInstanceRef, WeakOrStrongInstanceRef,
 };
 pub use crate::memory::{LinearMemory, Memory, MemoryError, MemoryStyle};
 pub use crate::mmap::Mmap;
-pub use crate::module::{ExportsIterator, ImportsIterator, ModuleInfo};
+pub use crate::module::{ExportsIterator, FunctionBodyInfo, ImportsIterator, ModuleInfo};
+pub use crate::mpk::{ProtectionKey, ProtectionKeyAccess};
 pub use crate::probestack::PROBESTACK;
 pub use crate::sig_registry::SignatureRegistry;
 pub use crate::table::{LinearTable, Table, TableElement, TableStyle};
@@ -48,6 +48,8 @@ use loupe::MemoryUsage;
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
 use wasmer_types::{
     DataIndex, ElemIndex, FunctionIndex, LocalMemoryIndex, LocalTableIndex, MemoryIndex,
     TableIndex, Type,
@@ -514,9 +516,15 @@ pub unsafe extern "C" fn wasmer_vm_externref_inc(externref: VMExternRef) {
 ///
 /// This function must only be called at precise locations, otherwise use-after-free
 /// and other serious memory bugs may occur.
+///
+/// Dropping the last reference runs the finalizer of the data, and a panic
+/// can't unwind through the WebAssembly frames calling this, so it aborts
+/// the process.
 #[no_mangle]
 pub unsafe extern "C" fn wasmer_vm_externref_dec(mut externref: VMExternRef) {
-    externref.ref_drop()
+    if panic::catch_unwind(AssertUnwindSafe(|| externref.ref_drop())).is_err() {
+        process::abort();
+    }
 }
 
 /// Implementation of `elem.drop`.
@@ -179,6 +179,54 @@ impl Mmap {
         })
     }
 
+    /// Create a new `Mmap` pointing to at least `size` bytes of page-aligned memory
+    /// suitable for JIT-compiled code.
+    ///
+    /// On macOS on Apple Silicon, the hardened runtime only allows executable pages
+    /// that were mapped with `MAP_JIT`. These pages are readable, writable and
+    /// executable, but a given thread can only either write or execute them at a
+    /// time, see `pthread_jit_write_protect_np`.
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    pub fn jit_with_at_least(size: usize) -> Result<Self, String> {
+        // Not exposed by the `libc` crate yet.
+        const MAP_JIT: libc::c_int = 0x0800;
+
+        let page_size = region::page::size();
+        let rounded_size = round_up_to_page_size(size, page_size);
+        if rounded_size == 0 {
+            return Ok(Self::new());
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                rounded_size,
+                libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC,
+                libc::MAP_PRIVATE | libc::MAP_ANON | MAP_JIT,
+                -1,
+                0,
+            )
+        };
+        if ptr as isize == -1_isize {
+            return Err(io::Error::last_os_error().to_string());
+        }
+
+        Ok(Self {
+            ptr: ptr as usize,
+            len: rounded_size,
+        })
+    }
+
+    /// Create a new `Mmap` pointing to at least `size` bytes of page-aligned memory
+    /// suitable for JIT-compiled code.
+    ///
+    /// The memory is accessible (read-write), and must be made executable once
+    /// the code is written into it.
+    #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+    pub fn jit_with_at_least(size: usize) -> Result<Self, String> {
+        Self::with_at_least(size)
+    }
+
     /// Make the memory starting at `start` and extending for `len` bytes accessible.
     /// `start` and `len` must be native page-size multiples and describe a range within
     /// `self`'s reserved memory.
@@ -288,6 +336,32 @@ impl MemoryUsage for Mmap {
     }
 }
 
+/// Returns how many of the `len` bytes mapped at `ptr` are resident in
+/// physical memory, or `None` if it can't be determined.
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn resident_size(ptr: *const u8, len: usize) -> Option<usize> {
+    let page_size = region::page::size();
+    let start = ptr as usize & !(page_size - 1);
+    let len = round_up_to_page_size(ptr as usize + len - start, page_size);
+    if len == 0 {
+        return Some(0);
+    }
+    let mut pages = vec![0; len / page_size];
+    let r = unsafe { libc::mincore(start as *mut libc::c_void, len, pages.as_mut_ptr()) };
+    if r != 0 {
+        return None;
+    }
+    let resident_pages = pages.iter().filter(|&&page| page & 1 != 0).count();
+    Some(resident_pages * page_size)
+}
+
+/// Returns how many of the `len` bytes mapped at `ptr` are resident in
+/// physical memory, or `None` if it can't be determined.
+#[cfg(target_os = "windows")]
+pub(crate) fn resident_size(_ptr: *const u8, _len: usize) -> Option<usize> {
+    None
+}
+
 fn _assert() {
     fn _assert_send_sync<T: Send + Sync>() {}
     _assert_send_sync::<Mmap>();
@@ -304,4 +378,15 @@ mod tests {
         assert_eq!(round_up_to_page_size(4096, 4096), 4096);
         assert_eq!(round_up_to_page_size(4097, 4096), 8192);
     }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_resident_size() {
+        let page_size = region::page::size();
+        let mut mmap = Mmap::with_at_least(2 * page_size).unwrap();
+        mmap.as_mut_slice()[0] = 1;
+        let resident_size = resident_size(mmap.as_ptr(), mmap.len()).unwrap();
+        assert!(resident_size >= page_size);
+        assert!(resident_size <= mmap.len());
+    }
 }
@@ -21,7 +21,7 @@ use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::sync::Arc;
 use wasmer_types::entity::{EntityRef, PrimaryMap};
 #[cfg(feature = "enable-rkyv")]
-use wasmer_types::ArchivableIndexMap;
+use wasmer_types::{ArchivableHashMap, ArchivableIndexMap};
 use wasmer_types::{
     CustomSectionIndex, DataIndex, ElemIndex, ExportIndex, ExportType, ExternType, FunctionIndex,
     FunctionType, GlobalIndex, GlobalInit, GlobalType, ImportIndex, ImportType, LocalFunctionIndex,
@@ -53,6 +53,20 @@ impl Default for ModuleId {
     }
 }
 
+/// The size and the number of locals of the body of a local function.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, MemoryUsage)]
+#[cfg_attr(
+    feature = "enable-rkyv",
+    derive(RkyvSerialize, RkyvDeserialize, Archive)
+)]
+pub struct FunctionBodyInfo {
+    /// The size of the body in the Wasm binary, in bytes.
+    pub size: u32,
+    /// The number of locals declared by the body, excluding the
+    /// parameters.
+    pub num_locals: u32,
+}
+
 /// A translated WebAssembly module, excluding the function bodies and
 /// memory initializers.
 #[derive(Debug, Clone, Serialize, Deserialize, MemoryUsage)]
@@ -74,10 +88,13 @@ pub struct ModuleInfo {
     /// Keeping the `index_of_the_import` is important, as there can be
     /// two same references to the same import, and we don't want to confuse
     /// them.
-    pub imports: IndexMap<(String, String, u32), ImportIndex>,
+    ///
+    /// The names are interned: the module names, usually shared by many
+    /// imports, are stored once, also in the serialized module.
+    pub imports: IndexMap<(Arc<str>, Arc<str>, u32), ImportIndex>,
 
     /// Exported entities.
-    pub exports: IndexMap<String, ExportIndex>,
+    pub exports: IndexMap<Arc<str>, ExportIndex>,
 
     /// The module "start" function, if present.
     pub start_function: Option<FunctionIndex>,
@@ -94,8 +111,12 @@ pub struct ModuleInfo {
     /// WebAssembly global initializers.
     pub global_initializers: PrimaryMap<LocalGlobalIndex, GlobalInit>,
 
-    /// WebAssembly function names.
-    pub function_names: HashMap<FunctionIndex, String>,
+    /// WebAssembly function names, sharing the names of the imports
+    /// and exports they are equal to.
+    pub function_names: HashMap<FunctionIndex, Arc<str>>,
+
+    /// The bodies of the local functions.
+    pub function_bodies: PrimaryMap<LocalFunctionIndex, FunctionBodyInfo>,
 
     /// WebAssembly function signatures.
     pub signatures: PrimaryMap<SignatureIndex, FunctionType>,
@@ -136,14 +157,15 @@ pub struct ModuleInfo {
 #[derive(RkyvSerialize, RkyvDeserialize, Archive)]
 pub struct ArchivableModuleInfo {
     name: Option<String>,
-    imports: ArchivableIndexMap<(String, String, u32), ImportIndex>,
-    exports: ArchivableIndexMap<String, ExportIndex>,
+    imports: ArchivableIndexMap<(Arc<str>, Arc<str>, u32), ImportIndex>,
+    exports: ArchivableIndexMap<Arc<str>, ExportIndex>,
     start_function: Option<FunctionIndex>,
     table_initializers: Vec<TableInitializer>,
-    passive_elements: HashMap<ElemIndex, Box<[FunctionIndex]>>,
-    passive_data: HashMap<DataIndex, Arc<[u8]>>,
+    passive_elements: ArchivableHashMap<ElemIndex, Box<[FunctionIndex]>>,
+    passive_data: ArchivableHashMap<DataIndex, Arc<[u8]>>,
     global_initializers: PrimaryMap<LocalGlobalIndex, GlobalInit>,
-    function_names: HashMap<FunctionIndex, String>,
+    function_names: ArchivableHashMap<FunctionIndex, Arc<str>>,
+    function_bodies: PrimaryMap<LocalFunctionIndex, FunctionBodyInfo>,
     signatures: PrimaryMap<SignatureIndex, FunctionType>,
     functions: PrimaryMap<FunctionIndex, SignatureIndex>,
     tables: PrimaryMap<TableIndex, TableType>,
@@ -166,10 +188,11 @@ impl From<ModuleInfo> for ArchivableModuleInfo {
             exports: ArchivableIndexMap::from(it.exports),
             start_function: it.start_function,
             table_initializers: it.table_initializers,
-            passive_elements: it.passive_elements,
-            passive_data: it.passive_data,
+            passive_elements: ArchivableHashMap::from(it.passive_elements),
+            passive_data: ArchivableHashMap::from(it.passive_data),
             global_initializers: it.global_initializers,
-            function_names: it.function_names,
+            function_names: ArchivableHashMap::from(it.function_names),
+            function_bodies: it.function_bodies,
             signatures: it.signatures,
             functions: it.functions,
             tables: it.tables,
@@ -195,10 +218,11 @@ impl From<ArchivableModuleInfo> for ModuleInfo {
             exports: it.exports.into(),
             start_function: it.start_function,
             table_initializers: it.table_initializers,
-            passive_elements: it.passive_elements,
-            passive_data: it.passive_data,
+            passive_elements: it.passive_elements.into(),
+            passive_data: it.passive_data.into(),
             global_initializers: it.global_initializers,
-            function_names: it.function_names,
+            function_names: it.function_names.into(),
+            function_bodies: it.function_bodies,
             signatures: it.signatures,
             functions: it.functions,
             tables: it.tables,
@@ -261,6 +285,7 @@ impl PartialEq for ModuleInfo {
             && self.passive_data == other.passive_data
             && self.global_initializers == other.global_initializers
             && self.function_names == other.function_names
+            && self.function_bodies == other.function_bodies
             && self.signatures == other.signatures
             && self.functions == other.functions
             && self.tables == other.tables
@@ -291,6 +316,7 @@ impl ModuleInfo {
             passive_data: HashMap::new(),
             global_initializers: PrimaryMap::new(),
             function_names: HashMap::new(),
+            function_bodies: PrimaryMap::new(),
             signatures: PrimaryMap::new(),
             functions: PrimaryMap::new(),
             tables: PrimaryMap::new(),
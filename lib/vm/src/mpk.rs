@@ -0,0 +1,205 @@
+//! Memory protection keys (MPK) support.
+//!
+//! On x86-64 CPUs with the PKU feature, Linux lets a process tag its
+//! pages with one of a few protection keys, and each thread can then
+//! allow or deny access to all the pages tagged with a key by writing
+//! to a per-thread register, without any system call.
+//!
+//! This is the building block to isolate many instance memories living
+//! in the same address space: each memory is tagged with its own key,
+//! and only the key of the instance being executed is accessible to the
+//! thread, making switching between instances much cheaper than changing
+//! page protections.
+//!
+//! On any other platform, or when the CPU or the kernel don't support
+//! protection keys, [`ProtectionKey::is_supported`] returns `false` and
+//! no key can be allocated.
+
+use crate::mmap::Mmap;
+use loupe::MemoryUsage;
+
+/// The access rights a thread has on the pages tagged with a
+/// [`ProtectionKey`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtectionKeyAccess {
+    /// The pages can be read and written.
+    ReadWrite,
+    /// The pages can be read but not written.
+    ReadOnly,
+    /// The pages can't be accessed at all.
+    None,
+}
+
+/// A memory protection key allocated for this process.
+///
+/// The key is freed when this value is dropped.
+#[derive(Debug, MemoryUsage)]
+pub struct ProtectionKey {
+    key: i32,
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(target_os = "linux", target_arch = "x86_64", target_env = "gnu"))] {
+        use std::io;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Once;
+
+        extern "C" {
+            // Provided by glibc since 2.27; they read and write the
+            // PKRU register of the current thread.
+            fn pkey_set(key: libc::c_int, access_rights: libc::c_uint) -> libc::c_int;
+        }
+
+        const PKEY_DISABLE_ACCESS: libc::c_uint = 0x1;
+        const PKEY_DISABLE_WRITE: libc::c_uint = 0x2;
+
+        impl ProtectionKey {
+            /// Returns whether protection keys are supported by both the
+            /// CPU and the kernel.
+            pub fn is_supported() -> bool {
+                static INIT: Once = Once::new();
+                static SUPPORTED: AtomicBool = AtomicBool::new(false);
+                INIT.call_once(|| {
+                    SUPPORTED.store(Self::new().is_ok(), Ordering::SeqCst);
+                });
+                SUPPORTED.load(Ordering::SeqCst)
+            }
+
+            /// Allocate a new protection key.
+            ///
+            /// The pages tagged with it are initially accessible by all the
+            /// threads.
+            pub fn new() -> Result<Self, String> {
+                let key = unsafe { libc::syscall(libc::SYS_pkey_alloc, 0, 0) };
+                if key < 0 {
+                    return Err(io::Error::last_os_error().to_string());
+                }
+                Ok(Self { key: key as i32 })
+            }
+
+            /// Tag `len` bytes of `mmap`, starting at `start`, with this key.
+            ///
+            /// The pages are made readable and writable, the key then
+            /// restricts it further depending on the access rights of each
+            /// thread. `start` and `len` must be native page-size multiples.
+            ///
+            /// # Safety
+            ///
+            /// The pages lose their current protection: they must not be
+            /// guard pages, code, or any other pages whose protection is
+            /// relied upon, like the inaccessible pages of a linear memory.
+            pub unsafe fn protect(
+                &self,
+                mmap: &mut Mmap,
+                start: usize,
+                len: usize,
+            ) -> Result<(), String> {
+                let page_size = region::page::size();
+                assert_eq!(start & (page_size - 1), 0);
+                assert_eq!(len & (page_size - 1), 0);
+                assert!(start.checked_add(len).map_or(false, |end| end <= mmap.len()));
+
+                let ret = libc::syscall(
+                    libc::SYS_pkey_mprotect,
+                    mmap.as_mut_ptr().add(start),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    self.key,
+                );
+                if ret != 0 {
+                    return Err(io::Error::last_os_error().to_string());
+                }
+                Ok(())
+            }
+
+            /// Set the access rights of the current thread on the pages
+            /// tagged with this key.
+            pub fn set_access(&self, access: ProtectionKeyAccess) -> Result<(), String> {
+                let rights = match access {
+                    ProtectionKeyAccess::ReadWrite => 0,
+                    ProtectionKeyAccess::ReadOnly => PKEY_DISABLE_WRITE,
+                    ProtectionKeyAccess::None => PKEY_DISABLE_ACCESS,
+                };
+                if unsafe { pkey_set(self.key, rights) } != 0 {
+                    return Err(io::Error::last_os_error().to_string());
+                }
+                Ok(())
+            }
+        }
+
+        impl Drop for ProtectionKey {
+            fn drop(&mut self) {
+                unsafe {
+                    libc::syscall(libc::SYS_pkey_free, self.key);
+                }
+            }
+        }
+    } else {
+        impl ProtectionKey {
+            /// Returns whether protection keys are supported by both the
+            /// CPU and the kernel.
+            pub fn is_supported() -> bool {
+                false
+            }
+
+            /// Allocate a new protection key.
+            pub fn new() -> Result<Self, String> {
+                Err("memory protection keys are not supported on this platform".to_string())
+            }
+
+            /// Tag `len` bytes of `mmap`, starting at `start`, with this key.
+            ///
+            /// # Safety
+            ///
+            /// The pages lose their current protection: they must not be
+            /// guard pages, code, or any other pages whose protection is
+            /// relied upon.
+            pub unsafe fn protect(
+                &self,
+                _mmap: &mut Mmap,
+                _start: usize,
+                _len: usize,
+            ) -> Result<(), String> {
+                unreachable!("memory protection keys can't be allocated on this platform")
+            }
+
+            /// Set the access rights of the current thread on the pages
+            /// tagged with this key.
+            pub fn set_access(&self, _access: ProtectionKeyAccess) -> Result<(), String> {
+                unreachable!("memory protection keys can't be allocated on this platform")
+            }
+        }
+    }
+}
+
+impl ProtectionKey {
+    /// The index of this key, as used by the hardware.
+    pub fn index(&self) -> u32 {
+        self.key as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protect_and_switch_access() {
+        if !ProtectionKey::is_supported() {
+            assert!(ProtectionKey::new().is_err());
+            return;
+        }
+
+        let page_size = region::page::size();
+        let mut mmap = Mmap::with_at_least(page_size).unwrap();
+        let key = ProtectionKey::new().unwrap();
+        unsafe { key.protect(&mut mmap, 0, page_size).unwrap() };
+
+        mmap.as_mut_slice()[0] = 42;
+        key.set_access(ProtectionKeyAccess::ReadOnly).unwrap();
+        assert_eq!(mmap.as_slice()[0], 42);
+        key.set_access(ProtectionKeyAccess::ReadWrite).unwrap();
+        mmap.as_mut_slice()[0] = 43;
+        assert_eq!(mmap.as_slice()[0], 43);
+    }
+}
@@ -7,8 +7,13 @@ mod trapcode;
 mod traphandlers;
 
 pub use trapcode::TrapCode;
+#[cfg(unix)]
+pub use traphandlers::handle_trap_signal;
 pub use traphandlers::{
     catch_traps, catch_traps_with_result, raise_lib_trap, raise_user_trap, wasmer_call_trampoline,
     TlsRestore, Trap, TrapHandler, TrapHandlerFn,
 };
-pub use traphandlers::{init_traps, resume_panic};
+pub use traphandlers::{
+    init_traps, install_trap_handlers, resume_panic, set_install_trap_handlers_on_init,
+    trap_handlers_installed, uninstall_trap_handlers,
+};
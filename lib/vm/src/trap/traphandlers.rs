@@ -13,6 +13,7 @@ use std::error::Error;
 use std::io;
 use std::mem::{self, MaybeUninit};
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Once;
 pub use tls::TlsRestore;
 
@@ -42,8 +43,32 @@ cfg_if::cfg_if! {
         static mut PREV_SIGILL: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
         static mut PREV_SIGFPE: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
 
-        unsafe fn platform_init() {
-            let register = |slot: &mut MaybeUninit<libc::sigaction>, signal: i32| {
+        /// The signals handled by Wasmer, with the slot where the
+        /// previous action for each of them is stored.
+        unsafe fn trap_signals() -> Vec<(&'static mut MaybeUninit<libc::sigaction>, libc::c_int)> {
+            // Allow handling OOB with signals on all architectures
+            let mut signals = vec![(&mut PREV_SIGSEGV, libc::SIGSEGV)];
+
+            // Handle `unreachable` instructions which execute `ud2` right now
+            signals.push((&mut PREV_SIGILL, libc::SIGILL));
+
+            // x86 uses SIGFPE to report division by zero
+            if cfg!(target_arch = "x86") || cfg!(target_arch = "x86_64") {
+                signals.push((&mut PREV_SIGFPE, libc::SIGFPE));
+            }
+
+            // On ARM, handle Unaligned Accesses.
+            // On Darwin, guard page accesses are raised as SIGBUS.
+            if cfg!(target_arch = "arm") || cfg!(target_os = "macos") || cfg!(target_os = "ios") {
+                signals.push((&mut PREV_SIGBUS, libc::SIGBUS));
+            }
+
+            signals
+        }
+
+        unsafe fn platform_install() -> io::Result<()> {
+            let mut registered = vec![];
+            for (slot, signal) in trap_signals() {
                 let mut handler: libc::sigaction = mem::zeroed();
                 // The flags here are relatively careful, and they are...
                 //
@@ -62,32 +87,32 @@ cfg_if::cfg_if! {
                 handler.sa_sigaction = trap_handler as usize;
                 libc::sigemptyset(&mut handler.sa_mask);
                 if libc::sigaction(signal, &handler, slot.as_mut_ptr()) != 0 {
-                    panic!(
-                        "unable to install signal handler: {}",
-                        io::Error::last_os_error(),
-                    );
+                    let error = io::Error::last_os_error();
+                    // Leave the process as we found it.
+                    for (slot, signal) in registered {
+                        restore_previous_action(slot, signal);
+                    }
+                    return Err(error);
                 }
-            };
-
-            // Allow handling OOB with signals on all architectures
-            register(&mut PREV_SIGSEGV, libc::SIGSEGV);
-
-            // Handle `unreachable` instructions which execute `ud2` right now
-            register(&mut PREV_SIGILL, libc::SIGILL);
-
-            // x86 uses SIGFPE to report division by zero
-            if cfg!(target_arch = "x86") || cfg!(target_arch = "x86_64") {
-                register(&mut PREV_SIGFPE, libc::SIGFPE);
+                registered.push((slot, signal));
             }
+            Ok(())
+        }
 
-            // On ARM, handle Unaligned Accesses.
-            // On Darwin, guard page accesses are raised as SIGBUS.
-            if cfg!(target_arch = "arm") || cfg!(target_os = "macos") {
-                register(&mut PREV_SIGBUS, libc::SIGBUS);
+        unsafe fn platform_uninstall() {
+            for (slot, signal) in trap_signals() {
+                restore_previous_action(slot, signal);
             }
         }
 
-        #[cfg(target_os = "macos")]
+        unsafe fn restore_previous_action(
+            slot: &MaybeUninit<libc::sigaction>,
+            signal: libc::c_int,
+        ) {
+            libc::sigaction(signal, slot.as_ptr(), ptr::null_mut());
+        }
+
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
         unsafe fn thread_stack() -> (usize, usize) {
             let this_thread = libc::pthread_self();
             let stackaddr = libc::pthread_get_stackaddr_np(this_thread);
@@ -95,7 +120,7 @@ cfg_if::cfg_if! {
             (stackaddr as usize - stacksize, stacksize)
         }
 
-        #[cfg(not(target_os = "macos"))]
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
         unsafe fn thread_stack() -> (usize, usize) {
             let this_thread = libc::pthread_self();
             let mut thread_attrs: libc::pthread_attr_t = mem::zeroed();
@@ -109,18 +134,30 @@ cfg_if::cfg_if! {
             (stackaddr as usize, stacksize)
         }
 
-        unsafe extern "C" fn trap_handler(
+        /// Handles a signal that may have been raised by WebAssembly code.
+        ///
+        /// This is what the trap handlers installed by Wasmer do before
+        /// forwarding the signal to the previously installed handler. It is
+        /// exposed for hosts that manage the signal handlers themselves
+        /// (see [`set_install_trap_handlers_on_init`]) and need to hand the
+        /// signals over to Wasmer from their own handlers.
+        ///
+        /// It returns `false` if the signal wasn't raised by WebAssembly
+        /// code, in which case the caller is responsible for handling it,
+        /// and `true` if the signal was handled and the execution can
+        /// resume. If the signal is a WebAssembly trap, this function
+        /// doesn't return but unwinds up to the function that called into
+        /// WebAssembly.
+        ///
+        /// # Safety
+        ///
+        /// This function must only be called from a signal handler, with
+        /// the arguments it has received.
+        pub unsafe fn handle_trap_signal(
             signum: libc::c_int,
             siginfo: *mut libc::siginfo_t,
             context: *mut libc::c_void,
-        ) {
-            let previous = match signum {
-                libc::SIGSEGV => &PREV_SIGSEGV,
-                libc::SIGBUS => &PREV_SIGBUS,
-                libc::SIGFPE => &PREV_SIGFPE,
-                libc::SIGILL => &PREV_SIGILL,
-                _ => panic!("unknown signal: {}", signum),
-            };
+        ) -> bool {
             // We try to get the Code trap associated to this signal
             let maybe_signal_trap = match signum {
                 libc::SIGSEGV | libc::SIGBUS => {
@@ -137,7 +174,7 @@ cfg_if::cfg_if! {
                 }
                 _ => None,
             };
-            let handled = tls::with(|info| {
+            tls::with(|info| {
                 // If no wasm code is executing, we don't handle this as a wasm
                 // trap.
                 let info = match info {
@@ -170,7 +207,22 @@ cfg_if::cfg_if! {
                 } else {
                     wasmer_unwind(jmp_buf)
                 }
-            });
+            })
+        }
+
+        unsafe extern "C" fn trap_handler(
+            signum: libc::c_int,
+            siginfo: *mut libc::siginfo_t,
+            context: *mut libc::c_void,
+        ) {
+            let previous = match signum {
+                libc::SIGSEGV => &PREV_SIGSEGV,
+                libc::SIGBUS => &PREV_SIGBUS,
+                libc::SIGFPE => &PREV_SIGFPE,
+                libc::SIGILL => &PREV_SIGILL,
+                _ => panic!("unknown signal: {}", signum),
+            };
+            let handled = handle_trap_signal(signum, siginfo, context);
 
             if handled {
                 return;
@@ -219,10 +271,34 @@ cfg_if::cfg_if! {
                 } else if #[cfg(all(target_os = "android", target_arch = "aarch64"))] {
                     let cx = &*(cx as *const libc::ucontext_t);
                     cx.uc_mcontext.pc as *const u8
-                } else if #[cfg(all(target_os = "macos", target_arch = "x86_64"))] {
+                } else if #[cfg(all(target_os = "linux", target_arch = "s390x"))] {
+                    let cx = &*(cx as *const libc::ucontext_t);
+                    cx.uc_mcontext.psw.addr as *const u8
+                } else if #[cfg(all(target_os = "linux", target_arch = "riscv64"))] {
+                    // TODO: This should be integrated into rust/libc
+                    // The layout follows `struct ucontext` in the kernel's
+                    // `arch/riscv/include/uapi/asm/ucontext.h`.
+                    #[repr(C, align(16))]
+                    #[allow(non_camel_case_types)]
+                    pub struct mcontext_t {
+                        pub __gregs: [libc::c_ulong; 32], /* pc, followed by x1-x31 */
+                    }
+                    #[repr(C)]
+                    #[allow(non_camel_case_types)]
+                    pub struct ucontext_t {
+                        pub uc_flags: libc::c_ulong,
+                        pub uc_link: *mut ucontext_t,
+                        pub uc_stack: libc::stack_t,
+                        pub uc_sigmask: [u8; 128],
+                        pub uc_mcontext: mcontext_t,
+                    }
+
+                    let cx = &*(cx as *const ucontext_t);
+                    cx.uc_mcontext.__gregs[0] as *const u8
+                } else if #[cfg(all(any(target_os = "macos", target_os = "ios"), target_arch = "x86_64"))] {
                     let cx = &*(cx as *const libc::ucontext_t);
                     (*cx.uc_mcontext).__ss.__rip as *const u8
-                } else if #[cfg(all(target_os = "macos", target_arch = "aarch64"))] {
+                } else if #[cfg(all(any(target_os = "macos", target_os = "ios"), target_arch = "aarch64"))] {
                     use std::mem;
                     // TODO: This should be integrated into rust/libc
                     // Related issue: https://github.com/rust-lang/libc/issues/1977
@@ -296,12 +372,36 @@ cfg_if::cfg_if! {
         use winapi::um::minwinbase::*;
         use winapi::vc::excpt::*;
 
-        unsafe fn platform_init() {
+        static mut VECTORED_EXCEPTION_HANDLER: PVOID = ptr::null_mut();
+
+        unsafe fn platform_install() -> io::Result<()> {
             // our trap handler needs to go first, so that we can recover from
             // wasm faults and continue execution, so pass `1` as a true value
             // here.
-            if AddVectoredExceptionHandler(1, Some(exception_handler)).is_null() {
-                panic!("failed to add exception handler: {}", io::Error::last_os_error());
+            let handler = AddVectoredExceptionHandler(1, Some(exception_handler));
+            if handler.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            VECTORED_EXCEPTION_HANDLER = handler;
+            Ok(())
+        }
+
+        unsafe fn platform_uninstall() {
+            if !VECTORED_EXCEPTION_HANDLER.is_null() {
+                RemoveVectoredExceptionHandler(VECTORED_EXCEPTION_HANDLER);
+                VECTORED_EXCEPTION_HANDLER = ptr::null_mut();
+            }
+        }
+
+        unsafe fn get_pc(exception_info: PEXCEPTION_POINTERS) -> *const u8 {
+            cfg_if::cfg_if! {
+                if #[cfg(target_arch = "x86_64")] {
+                    (*(*exception_info).ContextRecord).Rip as *const u8
+                } else if #[cfg(target_arch = "aarch64")] {
+                    (*(*exception_info).ContextRecord).Pc as *const u8
+                } else {
+                    compile_error!("unsupported platform");
+                }
             }
         }
 
@@ -341,7 +441,7 @@ cfg_if::cfg_if! {
                     None => return EXCEPTION_CONTINUE_SEARCH,
                 };
                 let jmp_buf = info.handle_trap(
-                    (*(*exception_info).ContextRecord).Rip as *const u8,
+                    get_pc(exception_info),
                     record.ExceptionCode == EXCEPTION_STACK_OVERFLOW,
                     // TODO: fix the signal trap associated to memory access in Windows
                     None,
@@ -366,6 +466,12 @@ cfg_if::cfg_if! {
 /// `wasmer` currently.
 static mut IS_WASM_PC: fn(usize) -> bool = |_| false;
 
+/// Whether [`init_traps`] installs the trap handlers.
+static INSTALL_TRAP_HANDLERS_ON_INIT: AtomicBool = AtomicBool::new(true);
+
+/// Whether the trap handlers are currently installed.
+static TRAP_HANDLERS_INSTALLED: AtomicBool = AtomicBool::new(false);
+
 /// This function is required to be called before any WebAssembly is entered.
 /// This will configure global state such as signal handlers to prepare the
 /// process to receive wasm traps.
@@ -379,14 +485,80 @@ static mut IS_WASM_PC: fn(usize) -> bool = |_| false;
 /// program counter is the pc of an actual wasm trap or not. This is then used
 /// to disambiguate faults that happen due to wasm and faults that happen due to
 /// bugs in Rust or elsewhere.
+///
+/// The signal handlers are not installed if
+/// [`set_install_trap_handlers_on_init`] was called with `false` before.
 pub fn init_traps(is_wasm_pc: fn(usize) -> bool) {
     static INIT: Once = Once::new();
     INIT.call_once(|| unsafe {
         IS_WASM_PC = is_wasm_pc;
-        platform_init();
+        if INSTALL_TRAP_HANDLERS_ON_INIT.load(Ordering::SeqCst) {
+            if let Err(e) = install_trap_handlers() {
+                panic!("unable to install signal handler: {}", e);
+            }
+        }
     });
 }
 
+/// Sets whether the trap handlers (the signal handlers on Unix, the
+/// vectored exception handler on Windows) are installed by [`init_traps`],
+/// which is called when the first `Store` is created.
+///
+/// By default they are. Embedders that manage these signals themselves
+/// can disable it, and then either install the handlers when it suits
+/// them with [`install_trap_handlers`], or forward the signals from their
+/// own handlers with `handle_trap_signal` on Unix.
+///
+/// This must be called before the first `Store` is created to have any
+/// effect.
+pub fn set_install_trap_handlers_on_init(install: bool) {
+    INSTALL_TRAP_HANDLERS_ON_INIT.store(install, Ordering::SeqCst);
+}
+
+/// Installs the trap handlers, if they are not installed already.
+///
+/// On Unix, the actions previously installed for the handled signals are
+/// saved, and signals that are not raised by WebAssembly code are
+/// forwarded to them.
+///
+/// # Safety
+///
+/// This must not be called concurrently with [`uninstall_trap_handlers`].
+pub unsafe fn install_trap_handlers() -> io::Result<()> {
+    if TRAP_HANDLERS_INSTALLED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    platform_install().map_err(|e| {
+        TRAP_HANDLERS_INSTALLED.store(false, Ordering::SeqCst);
+        e
+    })
+}
+
+/// Uninstalls the trap handlers, if they are installed.
+///
+/// On Unix, the actions that were installed for the handled signals
+/// before [`install_trap_handlers`] are restored. Note that this also
+/// discards any handler that was installed after Wasmer's for these
+/// signals.
+///
+/// # Safety
+///
+/// Once the trap handlers are uninstalled, WebAssembly traps that rely on
+/// signals (such as out-of-bounds memory accesses or `unreachable`) are
+/// not caught anymore, and will crash the process unless the host forwards
+/// them to Wasmer. No WebAssembly code can be running while this is called,
+/// and it must not be called concurrently with [`install_trap_handlers`].
+pub unsafe fn uninstall_trap_handlers() {
+    if TRAP_HANDLERS_INSTALLED.swap(false, Ordering::SeqCst) {
+        platform_uninstall();
+    }
+}
+
+/// Returns whether the trap handlers are currently installed.
+pub fn trap_handlers_installed() -> bool {
+    TRAP_HANDLERS_INSTALLED.load(Ordering::SeqCst)
+}
+
 /// Raises a user-defined trap immediately.
 ///
 /// This function performs as-if a wasm trap was just executed, only the trap
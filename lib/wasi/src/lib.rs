@@ -14,30 +14,49 @@
 
 #[macro_use]
 mod macros;
+#[cfg(feature = "nn")]
+pub mod nn;
+mod policy;
 mod ptr;
+mod replay;
 mod state;
 mod syscalls;
+mod trace;
 mod utils;
+#[cfg(feature = "webc")]
+pub mod webc;
 
 use crate::syscalls::*;
+use crate::trace::{syscall_function, SyscallHooks, SyscallTracer};
 
+pub use crate::policy::{SyscallCategory, WasiPolicy};
+pub use crate::replay::{Recorder, ReplayError, Replayer};
+#[cfg(feature = "archive")]
+pub use crate::state::ArchiveFileSystem;
 pub use crate::state::{
-    Fd, Pipe, Stderr, Stdin, Stdout, WasiFile, WasiFs, WasiFsError, WasiState, WasiStateBuilder,
+    restore_terminals, DirEntry, Fd, FileSystem, FixedClock, HostFile, HostFileSystem,
+    MemFileSystem, Metadata, NetworkPolicy, OpenOptions, OverlayFileSystem, Pipe, SeededRandom,
+    Socket, Stderr, Stdin, Stdout, WasiFile, WasiFs, WasiFsError, WasiState, WasiStateBuilder,
     WasiStateCreationError, ALL_RIGHTS, VIRTUAL_ROOT_FD,
 };
 pub use crate::syscalls::types;
-pub use crate::utils::{get_wasi_version, get_wasi_versions, is_wasi_module, WasiVersion};
+pub use crate::trace::SyscallHook;
+pub use crate::utils::{
+    get_wasi_exec_model, get_wasi_version, get_wasi_versions, initialize_reactor, is_wasi_module,
+    WasiExecModel, WasiVersion,
+};
 
 use thiserror::Error;
 use wasmer::{
-    imports, ChainableNamedResolver, Function, ImportObject, LazyInit, Memory, Module,
-    NamedResolver, Store, WasmerEnv,
+    imports, ChainableNamedResolver, ImportObject, LazyInit, Memory, Module, NamedResolver,
+    RuntimeError, Store, WasmerEnv,
 };
 
+use std::io::Write;
 use std::sync::{Arc, Mutex, MutexGuard};
 
 /// This is returned in `RuntimeError`.
-/// Use `downcast` or `downcast_ref` to retrieve the `ExitCode`.
+/// Use [`WasiEnv::get_exit_code`], `downcast` or `downcast_ref` to retrieve the `ExitCode`.
 #[derive(Error, Debug)]
 pub enum WasiError {
     #[error("WASI exited with code: {0}")]
@@ -58,6 +77,8 @@ pub struct WasiEnv {
     pub state: Arc<Mutex<WasiState>>,
     #[wasmer(export)]
     memory: LazyInit<Memory>,
+    syscall_tracer: Option<SyscallTracer>,
+    syscall_hooks: SyscallHooks,
 }
 
 impl WasiEnv {
@@ -65,6 +86,49 @@ impl WasiEnv {
         Self {
             state: Arc::new(Mutex::new(state)),
             memory: LazyInit::new(),
+            syscall_tracer: None,
+            syscall_hooks: SyscallHooks::default(),
+        }
+    }
+
+    /// Traces every WASI syscall made by the module to `writer`, with
+    /// its arguments, result and duration, one per line.
+    ///
+    /// This must be called before creating the import object.
+    pub fn trace_syscalls(&mut self, writer: Box<dyn Write + Send>) {
+        self.syscall_tracer = Some(SyscallTracer::new(writer));
+    }
+
+    /// Calls `hook` around the calls of the syscall `name`, eg. `path_open`,
+    /// or of all of them if `name` is `*`, to audit, deny or replace them.
+    /// The hooks of a syscall are called in the order they were added.
+    ///
+    /// This must be called before creating the import object.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is neither `*` nor the name of a WASI syscall, so
+    /// that a misspelt name doesn't leave the syscall unhooked.
+    pub fn hook_syscall<H: SyscallHook>(&mut self, name: &str, hook: H) {
+        self.syscall_hooks.add(name, Arc::new(hook));
+    }
+
+    /// Denies the syscalls of the categories denied by `policy`.
+    ///
+    /// This must be called before creating the import object. The policy
+    /// is enforced before any of the hooks is called, whenever they were
+    /// added, and they only see the denied calls in [`SyscallHook::after`].
+    pub fn set_policy(&mut self, policy: WasiPolicy) {
+        self.syscall_hooks.add_first(Arc::new(policy));
+    }
+
+    /// Gets the exit code of the program from the error returned by the
+    /// call of one of its functions: `Some` if it exited with `proc_exit`,
+    /// eg. by calling `exit`, and `None` if it trapped or failed otherwise.
+    pub fn get_exit_code(&self, err: &RuntimeError) -> Option<syscalls::types::__wasi_exitcode_t> {
+        match err.downcast_ref::<WasiError>() {
+            Some(WasiError::Exit(code)) => Some(*code),
+            _ => None,
         }
     }
 
@@ -93,6 +157,28 @@ impl WasiEnv {
                 generate_import_object_from_env(module.store(), self.clone(), *version);
             resolver = Box::new(new_import_object.chain_front(resolver));
         }
+        #[cfg(feature = "nn")]
+        if nn::is_wasi_nn_module(module) {
+            let nn_import_object = nn::WasiNnEnv::new().import_object(module.store());
+            resolver = Box::new(nn_import_object.chain_front(resolver));
+        }
+        if module
+            .imports()
+            .functions()
+            .any(|import| import.module() == sockets::SOCKETS_NAMESPACE)
+        {
+            let sockets_import_object =
+                generate_import_object_sockets(module.store(), self.clone());
+            resolver = Box::new(sockets_import_object.chain_front(resolver));
+        }
+        if module
+            .imports()
+            .functions()
+            .any(|import| import.module() == tty::TTY_NAMESPACE)
+        {
+            let tty_import_object = generate_import_object_tty(module.store(), self.clone());
+            resolver = Box::new(tty_import_object.chain_front(resolver));
+        }
         Ok(resolver)
     }
 
@@ -141,51 +227,51 @@ pub fn generate_import_object_from_env(
 fn generate_import_object_snapshot0(store: &Store, env: WasiEnv) -> ImportObject {
     imports! {
         "wasi_unstable" => {
-            "args_get" => Function::new_native_with_env(store, env.clone(), args_get),
-            "args_sizes_get" => Function::new_native_with_env(store, env.clone(), args_sizes_get),
-            "clock_res_get" => Function::new_native_with_env(store, env.clone(), clock_res_get),
-            "clock_time_get" => Function::new_native_with_env(store, env.clone(), clock_time_get),
-            "environ_get" => Function::new_native_with_env(store, env.clone(), environ_get),
-            "environ_sizes_get" => Function::new_native_with_env(store, env.clone(), environ_sizes_get),
-            "fd_advise" => Function::new_native_with_env(store, env.clone(), fd_advise),
-            "fd_allocate" => Function::new_native_with_env(store, env.clone(), fd_allocate),
-            "fd_close" => Function::new_native_with_env(store, env.clone(), fd_close),
-            "fd_datasync" => Function::new_native_with_env(store, env.clone(), fd_datasync),
-            "fd_fdstat_get" => Function::new_native_with_env(store, env.clone(), fd_fdstat_get),
-            "fd_fdstat_set_flags" => Function::new_native_with_env(store, env.clone(), fd_fdstat_set_flags),
-            "fd_fdstat_set_rights" => Function::new_native_with_env(store, env.clone(), fd_fdstat_set_rights),
-            "fd_filestat_get" => Function::new_native_with_env(store, env.clone(), legacy::snapshot0::fd_filestat_get),
-            "fd_filestat_set_size" => Function::new_native_with_env(store, env.clone(), fd_filestat_set_size),
-            "fd_filestat_set_times" => Function::new_native_with_env(store, env.clone(), fd_filestat_set_times),
-            "fd_pread" => Function::new_native_with_env(store, env.clone(), fd_pread),
-            "fd_prestat_get" => Function::new_native_with_env(store, env.clone(), fd_prestat_get),
-            "fd_prestat_dir_name" => Function::new_native_with_env(store, env.clone(), fd_prestat_dir_name),
-            "fd_pwrite" => Function::new_native_with_env(store, env.clone(), fd_pwrite),
-            "fd_read" => Function::new_native_with_env(store, env.clone(), fd_read),
-            "fd_readdir" => Function::new_native_with_env(store, env.clone(), fd_readdir),
-            "fd_renumber" => Function::new_native_with_env(store, env.clone(), fd_renumber),
-            "fd_seek" => Function::new_native_with_env(store, env.clone(), legacy::snapshot0::fd_seek),
-            "fd_sync" => Function::new_native_with_env(store, env.clone(), fd_sync),
-            "fd_tell" => Function::new_native_with_env(store, env.clone(), fd_tell),
-            "fd_write" => Function::new_native_with_env(store, env.clone(), fd_write),
-            "path_create_directory" => Function::new_native_with_env(store, env.clone(), path_create_directory),
-            "path_filestat_get" => Function::new_native_with_env(store, env.clone(), legacy::snapshot0::path_filestat_get),
-            "path_filestat_set_times" => Function::new_native_with_env(store, env.clone(), path_filestat_set_times),
-            "path_link" => Function::new_native_with_env(store, env.clone(), path_link),
-            "path_open" => Function::new_native_with_env(store, env.clone(), path_open),
-            "path_readlink" => Function::new_native_with_env(store, env.clone(), path_readlink),
-            "path_remove_directory" => Function::new_native_with_env(store, env.clone(), path_remove_directory),
-            "path_rename" => Function::new_native_with_env(store, env.clone(), path_rename),
-            "path_symlink" => Function::new_native_with_env(store, env.clone(), path_symlink),
-            "path_unlink_file" => Function::new_native_with_env(store, env.clone(), path_unlink_file),
-            "poll_oneoff" => Function::new_native_with_env(store, env.clone(), legacy::snapshot0::poll_oneoff),
-            "proc_exit" => Function::new_native_with_env(store, env.clone(), proc_exit),
-            "proc_raise" => Function::new_native_with_env(store, env.clone(), proc_raise),
-            "random_get" => Function::new_native_with_env(store, env.clone(), random_get),
-            "sched_yield" => Function::new_native_with_env(store, env.clone(), sched_yield),
-            "sock_recv" => Function::new_native_with_env(store, env.clone(), sock_recv),
-            "sock_send" => Function::new_native_with_env(store, env.clone(), sock_send),
-            "sock_shutdown" => Function::new_native_with_env(store, env.clone(), sock_shutdown),
+            "args_get" => syscall_function(store, env.clone(), args_get),
+            "args_sizes_get" => syscall_function(store, env.clone(), args_sizes_get),
+            "clock_res_get" => syscall_function(store, env.clone(), clock_res_get),
+            "clock_time_get" => syscall_function(store, env.clone(), clock_time_get),
+            "environ_get" => syscall_function(store, env.clone(), environ_get),
+            "environ_sizes_get" => syscall_function(store, env.clone(), environ_sizes_get),
+            "fd_advise" => syscall_function(store, env.clone(), fd_advise),
+            "fd_allocate" => syscall_function(store, env.clone(), fd_allocate),
+            "fd_close" => syscall_function(store, env.clone(), fd_close),
+            "fd_datasync" => syscall_function(store, env.clone(), fd_datasync),
+            "fd_fdstat_get" => syscall_function(store, env.clone(), fd_fdstat_get),
+            "fd_fdstat_set_flags" => syscall_function(store, env.clone(), fd_fdstat_set_flags),
+            "fd_fdstat_set_rights" => syscall_function(store, env.clone(), fd_fdstat_set_rights),
+            "fd_filestat_get" => syscall_function(store, env.clone(), legacy::snapshot0::fd_filestat_get),
+            "fd_filestat_set_size" => syscall_function(store, env.clone(), fd_filestat_set_size),
+            "fd_filestat_set_times" => syscall_function(store, env.clone(), fd_filestat_set_times),
+            "fd_pread" => syscall_function(store, env.clone(), fd_pread),
+            "fd_prestat_get" => syscall_function(store, env.clone(), fd_prestat_get),
+            "fd_prestat_dir_name" => syscall_function(store, env.clone(), fd_prestat_dir_name),
+            "fd_pwrite" => syscall_function(store, env.clone(), fd_pwrite),
+            "fd_read" => syscall_function(store, env.clone(), fd_read),
+            "fd_readdir" => syscall_function(store, env.clone(), fd_readdir),
+            "fd_renumber" => syscall_function(store, env.clone(), fd_renumber),
+            "fd_seek" => syscall_function(store, env.clone(), legacy::snapshot0::fd_seek),
+            "fd_sync" => syscall_function(store, env.clone(), fd_sync),
+            "fd_tell" => syscall_function(store, env.clone(), fd_tell),
+            "fd_write" => syscall_function(store, env.clone(), fd_write),
+            "path_create_directory" => syscall_function(store, env.clone(), path_create_directory),
+            "path_filestat_get" => syscall_function(store, env.clone(), legacy::snapshot0::path_filestat_get),
+            "path_filestat_set_times" => syscall_function(store, env.clone(), path_filestat_set_times),
+            "path_link" => syscall_function(store, env.clone(), path_link),
+            "path_open" => syscall_function(store, env.clone(), path_open),
+            "path_readlink" => syscall_function(store, env.clone(), path_readlink),
+            "path_remove_directory" => syscall_function(store, env.clone(), path_remove_directory),
+            "path_rename" => syscall_function(store, env.clone(), path_rename),
+            "path_symlink" => syscall_function(store, env.clone(), path_symlink),
+            "path_unlink_file" => syscall_function(store, env.clone(), path_unlink_file),
+            "poll_oneoff" => syscall_function(store, env.clone(), legacy::snapshot0::poll_oneoff),
+            "proc_exit" => syscall_function(store, env.clone(), proc_exit),
+            "proc_raise" => syscall_function(store, env.clone(), proc_raise),
+            "random_get" => syscall_function(store, env.clone(), random_get),
+            "sched_yield" => syscall_function(store, env.clone(), sched_yield),
+            "sock_recv" => syscall_function(store, env.clone(), sock_recv),
+            "sock_send" => syscall_function(store, env.clone(), sock_send),
+            "sock_shutdown" => syscall_function(store, env.clone(), sock_shutdown),
         },
     }
 }
@@ -194,51 +280,76 @@ fn generate_import_object_snapshot0(store: &Store, env: WasiEnv) -> ImportObject
 fn generate_import_object_snapshot1(store: &Store, env: WasiEnv) -> ImportObject {
     imports! {
         "wasi_snapshot_preview1" => {
-            "args_get" => Function::new_native_with_env(store, env.clone(), args_get),
-            "args_sizes_get" => Function::new_native_with_env(store, env.clone(), args_sizes_get),
-            "clock_res_get" => Function::new_native_with_env(store, env.clone(), clock_res_get),
-            "clock_time_get" => Function::new_native_with_env(store, env.clone(), clock_time_get),
-            "environ_get" => Function::new_native_with_env(store, env.clone(), environ_get),
-            "environ_sizes_get" => Function::new_native_with_env(store, env.clone(), environ_sizes_get),
-            "fd_advise" => Function::new_native_with_env(store, env.clone(), fd_advise),
-            "fd_allocate" => Function::new_native_with_env(store, env.clone(), fd_allocate),
-            "fd_close" => Function::new_native_with_env(store, env.clone(), fd_close),
-            "fd_datasync" => Function::new_native_with_env(store, env.clone(), fd_datasync),
-            "fd_fdstat_get" => Function::new_native_with_env(store, env.clone(), fd_fdstat_get),
-            "fd_fdstat_set_flags" => Function::new_native_with_env(store, env.clone(), fd_fdstat_set_flags),
-            "fd_fdstat_set_rights" => Function::new_native_with_env(store, env.clone(), fd_fdstat_set_rights),
-            "fd_filestat_get" => Function::new_native_with_env(store, env.clone(), fd_filestat_get),
-            "fd_filestat_set_size" => Function::new_native_with_env(store, env.clone(), fd_filestat_set_size),
-            "fd_filestat_set_times" => Function::new_native_with_env(store, env.clone(), fd_filestat_set_times),
-            "fd_pread" => Function::new_native_with_env(store, env.clone(), fd_pread),
-            "fd_prestat_get" => Function::new_native_with_env(store, env.clone(), fd_prestat_get),
-            "fd_prestat_dir_name" => Function::new_native_with_env(store, env.clone(), fd_prestat_dir_name),
-            "fd_pwrite" => Function::new_native_with_env(store, env.clone(), fd_pwrite),
-            "fd_read" => Function::new_native_with_env(store, env.clone(), fd_read),
-            "fd_readdir" => Function::new_native_with_env(store, env.clone(), fd_readdir),
-            "fd_renumber" => Function::new_native_with_env(store, env.clone(), fd_renumber),
-            "fd_seek" => Function::new_native_with_env(store, env.clone(), fd_seek),
-            "fd_sync" => Function::new_native_with_env(store, env.clone(), fd_sync),
-            "fd_tell" => Function::new_native_with_env(store, env.clone(), fd_tell),
-            "fd_write" => Function::new_native_with_env(store, env.clone(), fd_write),
-            "path_create_directory" => Function::new_native_with_env(store, env.clone(), path_create_directory),
-            "path_filestat_get" => Function::new_native_with_env(store, env.clone(), path_filestat_get),
-            "path_filestat_set_times" => Function::new_native_with_env(store, env.clone(), path_filestat_set_times),
-            "path_link" => Function::new_native_with_env(store, env.clone(), path_link),
-            "path_open" => Function::new_native_with_env(store, env.clone(), path_open),
-            "path_readlink" => Function::new_native_with_env(store, env.clone(), path_readlink),
-            "path_remove_directory" => Function::new_native_with_env(store, env.clone(), path_remove_directory),
-            "path_rename" => Function::new_native_with_env(store, env.clone(), path_rename),
-            "path_symlink" => Function::new_native_with_env(store, env.clone(), path_symlink),
-            "path_unlink_file" => Function::new_native_with_env(store, env.clone(), path_unlink_file),
-            "poll_oneoff" => Function::new_native_with_env(store, env.clone(), poll_oneoff),
-            "proc_exit" => Function::new_native_with_env(store, env.clone(), proc_exit),
-            "proc_raise" => Function::new_native_with_env(store, env.clone(), proc_raise),
-            "random_get" => Function::new_native_with_env(store, env.clone(), random_get),
-            "sched_yield" => Function::new_native_with_env(store, env.clone(), sched_yield),
-            "sock_recv" => Function::new_native_with_env(store, env.clone(), sock_recv),
-            "sock_send" => Function::new_native_with_env(store, env.clone(), sock_send),
-            "sock_shutdown" => Function::new_native_with_env(store, env.clone(), sock_shutdown),
+            "args_get" => syscall_function(store, env.clone(), args_get),
+            "args_sizes_get" => syscall_function(store, env.clone(), args_sizes_get),
+            "clock_res_get" => syscall_function(store, env.clone(), clock_res_get),
+            "clock_time_get" => syscall_function(store, env.clone(), clock_time_get),
+            "environ_get" => syscall_function(store, env.clone(), environ_get),
+            "environ_sizes_get" => syscall_function(store, env.clone(), environ_sizes_get),
+            "fd_advise" => syscall_function(store, env.clone(), fd_advise),
+            "fd_allocate" => syscall_function(store, env.clone(), fd_allocate),
+            "fd_close" => syscall_function(store, env.clone(), fd_close),
+            "fd_datasync" => syscall_function(store, env.clone(), fd_datasync),
+            "fd_fdstat_get" => syscall_function(store, env.clone(), fd_fdstat_get),
+            "fd_fdstat_set_flags" => syscall_function(store, env.clone(), fd_fdstat_set_flags),
+            "fd_fdstat_set_rights" => syscall_function(store, env.clone(), fd_fdstat_set_rights),
+            "fd_filestat_get" => syscall_function(store, env.clone(), fd_filestat_get),
+            "fd_filestat_set_size" => syscall_function(store, env.clone(), fd_filestat_set_size),
+            "fd_filestat_set_times" => syscall_function(store, env.clone(), fd_filestat_set_times),
+            "fd_pread" => syscall_function(store, env.clone(), fd_pread),
+            "fd_prestat_get" => syscall_function(store, env.clone(), fd_prestat_get),
+            "fd_prestat_dir_name" => syscall_function(store, env.clone(), fd_prestat_dir_name),
+            "fd_pwrite" => syscall_function(store, env.clone(), fd_pwrite),
+            "fd_read" => syscall_function(store, env.clone(), fd_read),
+            "fd_readdir" => syscall_function(store, env.clone(), fd_readdir),
+            "fd_renumber" => syscall_function(store, env.clone(), fd_renumber),
+            "fd_seek" => syscall_function(store, env.clone(), fd_seek),
+            "fd_sync" => syscall_function(store, env.clone(), fd_sync),
+            "fd_tell" => syscall_function(store, env.clone(), fd_tell),
+            "fd_write" => syscall_function(store, env.clone(), fd_write),
+            "path_create_directory" => syscall_function(store, env.clone(), path_create_directory),
+            "path_filestat_get" => syscall_function(store, env.clone(), path_filestat_get),
+            "path_filestat_set_times" => syscall_function(store, env.clone(), path_filestat_set_times),
+            "path_link" => syscall_function(store, env.clone(), path_link),
+            "path_open" => syscall_function(store, env.clone(), path_open),
+            "path_readlink" => syscall_function(store, env.clone(), path_readlink),
+            "path_remove_directory" => syscall_function(store, env.clone(), path_remove_directory),
+            "path_rename" => syscall_function(store, env.clone(), path_rename),
+            "path_symlink" => syscall_function(store, env.clone(), path_symlink),
+            "path_unlink_file" => syscall_function(store, env.clone(), path_unlink_file),
+            "poll_oneoff" => syscall_function(store, env.clone(), poll_oneoff),
+            "proc_exit" => syscall_function(store, env.clone(), proc_exit),
+            "proc_raise" => syscall_function(store, env.clone(), proc_raise),
+            "random_get" => syscall_function(store, env.clone(), random_get),
+            "sched_yield" => syscall_function(store, env.clone(), sched_yield),
+            "sock_recv" => syscall_function(store, env.clone(), sock_recv),
+            "sock_send" => syscall_function(store, env.clone(), sock_send),
+            "sock_shutdown" => syscall_function(store, env.clone(), sock_shutdown),
+            "sock_accept" => syscall_function(store, env.clone(), sock_accept),
         }
     }
 }
+
+/// Combines a state generating function with the import list for the
+/// experimental socket functions
+fn generate_import_object_sockets(store: &Store, env: WasiEnv) -> ImportObject {
+    imports! {
+        sockets::SOCKETS_NAMESPACE => {
+            "sock_connect" => syscall_function(store, env.clone(), sockets::sock_connect),
+            "sock_listen" => syscall_function(store, env.clone(), sockets::sock_listen),
+            "sock_addr_local" => syscall_function(store, env.clone(), sockets::sock_addr_local),
+            "sock_addr_peer" => syscall_function(store, env.clone(), sockets::sock_addr_peer),
+        },
+    }
+}
+
+/// Combines a state generating function with the import list for the
+/// experimental terminal functions
+fn generate_import_object_tty(store: &Store, env: WasiEnv) -> ImportObject {
+    imports! {
+        tty::TTY_NAMESPACE => {
+            "tty_get_size" => syscall_function(store, env.clone(), tty::tty_get_size),
+            "tty_set_raw" => syscall_function(store, env.clone(), tty::tty_set_raw),
+        },
+    }
+}
@@ -0,0 +1,349 @@
+//! An implementation of [wasi-nn], the WASI API for machine learning
+//! inference, in its `wasi_ephemeral_nn` version.
+//!
+//! Only ONNX models are supported for now, run on the CPU with [`tract`].
+//!
+//! [wasi-nn]: https://github.com/WebAssembly/wasi-nn
+//! [`tract`]: https://github.com/sonos/tract
+
+mod tract;
+
+use self::tract::{ExecutionContext, Graph};
+use crate::ptr::{Array, WasmPtr};
+use std::convert::TryInto;
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+use wasmer::{
+    imports, Function, ImportObject, LazyInit, Memory, Module, Store, ValueType, WasmerEnv,
+};
+
+/// The namespace of the wasi-nn imports.
+pub(crate) const NN_NAMESPACE: &str = "wasi_ephemeral_nn";
+
+pub type __wasi_nn_errno_t = u16;
+pub const __WASI_NN_ESUCCESS: u16 = 0;
+pub const __WASI_NN_EINVALID_ARGUMENT: u16 = 1;
+pub const __WASI_NN_EINVALID_ENCODING: u16 = 2;
+pub const __WASI_NN_EMISSING_MEMORY: u16 = 3;
+pub const __WASI_NN_EBUSY: u16 = 4;
+pub const __WASI_NN_ERUNTIME_ERROR: u16 = 5;
+
+pub type __wasi_nn_graph_encoding_t = u32;
+pub const __WASI_NN_GRAPH_ENCODING_OPENVINO: u32 = 0;
+pub const __WASI_NN_GRAPH_ENCODING_ONNX: u32 = 1;
+pub const __WASI_NN_GRAPH_ENCODING_TENSORFLOW: u32 = 2;
+pub const __WASI_NN_GRAPH_ENCODING_PYTORCH: u32 = 3;
+
+pub type __wasi_nn_execution_target_t = u32;
+pub const __WASI_NN_EXECUTION_TARGET_CPU: u32 = 0;
+pub const __WASI_NN_EXECUTION_TARGET_GPU: u32 = 1;
+pub const __WASI_NN_EXECUTION_TARGET_TPU: u32 = 2;
+
+pub type __wasi_nn_tensor_type_t = u8;
+pub const __WASI_NN_TENSOR_TYPE_F16: u8 = 0;
+pub const __WASI_NN_TENSOR_TYPE_F32: u8 = 1;
+pub const __WASI_NN_TENSOR_TYPE_U8: u8 = 2;
+pub const __WASI_NN_TENSOR_TYPE_I32: u8 = 3;
+
+pub type __wasi_nn_graph_t = u32;
+pub type __wasi_nn_graph_execution_context_t = u32;
+
+/// A buffer of the model, eg. its weights or its topology.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct __wasi_nn_graph_builder_t {
+    pub buf: WasmPtr<u8, Array>,
+    pub buf_len: u32,
+}
+
+unsafe impl ValueType for __wasi_nn_graph_builder_t {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct __wasi_nn_tensor_t {
+    pub dimensions: WasmPtr<u32, Array>,
+    pub dimensions_len: u32,
+    pub ty: __wasi_nn_tensor_type_t,
+    pub data: WasmPtr<u8, Array>,
+    pub data_len: u32,
+}
+
+unsafe impl ValueType for __wasi_nn_tensor_t {}
+
+/// A tensor copied out of the guest memory.
+#[derive(Debug)]
+pub(crate) struct Tensor {
+    pub dimensions: Vec<usize>,
+    pub ty: __wasi_nn_tensor_type_t,
+    pub data: Vec<u8>,
+}
+
+/// The graphs and execution contexts created by a module.
+#[derive(Default)]
+struct NnState {
+    graphs: Vec<Arc<Graph>>,
+    contexts: Vec<ExecutionContext>,
+}
+
+/// The environment provided to the wasi-nn imports.
+#[derive(Clone, Default, WasmerEnv)]
+pub struct WasiNnEnv {
+    #[wasmer(export)]
+    memory: LazyInit<Memory>,
+    state: Arc<Mutex<NnState>>,
+}
+
+impl WasiNnEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get an `ImportObject` providing the wasi-nn functions.
+    pub fn import_object(&self, store: &Store) -> ImportObject {
+        imports! {
+            NN_NAMESPACE => {
+                "load" => Function::new_native_with_env(store, self.clone(), load),
+                "init_execution_context" => Function::new_native_with_env(store, self.clone(), init_execution_context),
+                "set_input" => Function::new_native_with_env(store, self.clone(), set_input),
+                "compute" => Function::new_native_with_env(store, self.clone(), compute),
+                "get_output" => Function::new_native_with_env(store, self.clone(), get_output),
+            }
+        }
+    }
+
+    fn memory(&self) -> Result<&Memory, __wasi_nn_errno_t> {
+        self.memory_ref().ok_or(__WASI_NN_EMISSING_MEMORY)
+    }
+}
+
+/// Checks if the module imports any wasi-nn function.
+pub fn is_wasi_nn_module(module: &Module) -> bool {
+    module
+        .imports()
+        .functions()
+        .any(|import| import.module() == NN_NAMESPACE)
+}
+
+/// Turns the result of a wasi-nn function into its error code.
+fn errno(result: Result<(), __wasi_nn_errno_t>) -> __wasi_nn_errno_t {
+    match result {
+        Ok(()) => __WASI_NN_ESUCCESS,
+        Err(errno) => {
+            debug!("wasi-nn error: {}", errno);
+            errno
+        }
+    }
+}
+
+/// ### `load()`
+/// Loads a graph from the buffers making up its model.
+/// Inputs:
+/// - `const __wasi_nn_graph_builder_t *builder`
+///     The buffers of the model; ONNX models consist of a single buffer
+/// - `u32 builder_len`
+///     The number of buffers
+/// - `__wasi_nn_graph_encoding_t encoding`
+///     The format of the model
+/// - `__wasi_nn_execution_target_t target`
+///     The device to run the model on
+/// Output:
+/// - `__wasi_nn_graph_t *graph`
+///     The handle of the loaded graph
+pub fn load(
+    env: &WasiNnEnv,
+    builder: WasmPtr<__wasi_nn_graph_builder_t, Array>,
+    builder_len: u32,
+    encoding: __wasi_nn_graph_encoding_t,
+    target: __wasi_nn_execution_target_t,
+    graph: WasmPtr<__wasi_nn_graph_t>,
+) -> __wasi_nn_errno_t {
+    debug!("wasi_nn::load encoding: {}, target: {}", encoding, target);
+    errno((|| {
+        if encoding != __WASI_NN_GRAPH_ENCODING_ONNX {
+            return Err(__WASI_NN_EINVALID_ENCODING);
+        }
+        if target != __WASI_NN_EXECUTION_TARGET_CPU {
+            return Err(__WASI_NN_EINVALID_ARGUMENT);
+        }
+        let memory = env.memory()?;
+        let builders = builder
+            .deref(memory, 0, builder_len)
+            .map_err(|_| __WASI_NN_EINVALID_ARGUMENT)?
+            .iter()
+            .map(|builder| {
+                let builder = builder.get();
+                read_bytes(memory, builder.buf, builder.buf_len)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let loaded = Graph::load(&builders)?;
+
+        let mut state = env.state.lock().unwrap();
+        let handle = new_handle(state.graphs.len())?;
+        graph
+            .deref(memory)
+            .map_err(|_| __WASI_NN_EINVALID_ARGUMENT)?
+            .set(handle);
+        state.graphs.push(Arc::new(loaded));
+        Ok(())
+    })())
+}
+
+/// ### `init_execution_context()`
+/// Creates an execution context, holding the inputs and outputs of an
+/// inference on a graph.
+/// Inputs:
+/// - `__wasi_nn_graph_t graph`
+///     The graph to run
+/// Output:
+/// - `__wasi_nn_graph_execution_context_t *context`
+///     The handle of the created execution context
+pub fn init_execution_context(
+    env: &WasiNnEnv,
+    graph: __wasi_nn_graph_t,
+    context: WasmPtr<__wasi_nn_graph_execution_context_t>,
+) -> __wasi_nn_errno_t {
+    debug!("wasi_nn::init_execution_context graph: {}", graph);
+    errno((|| {
+        let memory = env.memory()?;
+        let mut state = env.state.lock().unwrap();
+        let graph = state
+            .graphs
+            .get(graph as usize)
+            .ok_or(__WASI_NN_EINVALID_ARGUMENT)?
+            .clone();
+        let handle = new_handle(state.contexts.len())?;
+        context
+            .deref(memory)
+            .map_err(|_| __WASI_NN_EINVALID_ARGUMENT)?
+            .set(handle);
+        state.contexts.push(ExecutionContext::new(graph));
+        Ok(())
+    })())
+}
+
+/// ### `set_input()`
+/// Sets an input of an inference.
+/// Inputs:
+/// - `__wasi_nn_graph_execution_context_t context`
+///     The execution context of the inference
+/// - `u32 index`
+///     The index of the input of the graph
+/// - `const __wasi_nn_tensor_t *tensor`
+///     The value of the input
+pub fn set_input(
+    env: &WasiNnEnv,
+    context: __wasi_nn_graph_execution_context_t,
+    index: u32,
+    tensor: WasmPtr<__wasi_nn_tensor_t>,
+) -> __wasi_nn_errno_t {
+    debug!("wasi_nn::set_input context: {}, index: {}", context, index);
+    errno((|| {
+        let memory = env.memory()?;
+        let tensor = tensor
+            .deref(memory)
+            .map_err(|_| __WASI_NN_EINVALID_ARGUMENT)?
+            .get();
+        let dimensions = tensor
+            .dimensions
+            .deref(memory, 0, tensor.dimensions_len)
+            .map_err(|_| __WASI_NN_EINVALID_ARGUMENT)?
+            .iter()
+            .map(|dimension| dimension.get() as usize)
+            .collect();
+        let tensor = Tensor {
+            dimensions,
+            ty: tensor.ty,
+            data: read_bytes(memory, tensor.data, tensor.data_len)?,
+        };
+        let mut state = env.state.lock().unwrap();
+        context_mut(&mut state, context)?.set_input(index as usize, tensor)
+    })())
+}
+
+/// ### `compute()`
+/// Runs the inference, once all its inputs are set.
+/// Inputs:
+/// - `__wasi_nn_graph_execution_context_t context`
+///     The execution context of the inference
+pub fn compute(env: &WasiNnEnv, context: __wasi_nn_graph_execution_context_t) -> __wasi_nn_errno_t {
+    debug!("wasi_nn::compute context: {}", context);
+    errno((|| {
+        let mut state = env.state.lock().unwrap();
+        context_mut(&mut state, context)?.compute()
+    })())
+}
+
+/// ### `get_output()`
+/// Copies an output of the inference, as little-endian values.
+/// Inputs:
+/// - `__wasi_nn_graph_execution_context_t context`
+///     The execution context of the inference
+/// - `u32 index`
+///     The index of the output of the graph
+/// - `u8 *out_buffer`
+///     The buffer to copy the output to
+/// - `u32 out_buffer_max_size`
+///     The size of the buffer
+/// Output:
+/// - `u32 *bytes_written`
+///     The size of the output
+pub fn get_output(
+    env: &WasiNnEnv,
+    context: __wasi_nn_graph_execution_context_t,
+    index: u32,
+    out_buffer: WasmPtr<u8, Array>,
+    out_buffer_max_size: u32,
+    bytes_written: WasmPtr<u32>,
+) -> __wasi_nn_errno_t {
+    debug!("wasi_nn::get_output context: {}, index: {}", context, index);
+    errno((|| {
+        let memory = env.memory()?;
+        let mut state = env.state.lock().unwrap();
+        let output = context_mut(&mut state, context)?.output(index as usize)?;
+        let len: u32 = output
+            .len()
+            .try_into()
+            .map_err(|_| __WASI_NN_ERUNTIME_ERROR)?;
+        if len > out_buffer_max_size {
+            return Err(__WASI_NN_EINVALID_ARGUMENT);
+        }
+        let out_buffer = out_buffer
+            .deref(memory, 0, len)
+            .map_err(|_| __WASI_NN_EINVALID_ARGUMENT)?;
+        for (cell, byte) in out_buffer.iter().zip(output) {
+            cell.set(byte);
+        }
+        bytes_written
+            .deref(memory)
+            .map_err(|_| __WASI_NN_EINVALID_ARGUMENT)?
+            .set(len);
+        Ok(())
+    })())
+}
+
+fn read_bytes(
+    memory: &Memory,
+    ptr: WasmPtr<u8, Array>,
+    len: u32,
+) -> Result<Vec<u8>, __wasi_nn_errno_t> {
+    Ok(ptr
+        .deref(memory, 0, len)
+        .map_err(|_| __WASI_NN_EINVALID_ARGUMENT)?
+        .iter()
+        .map(|byte| byte.get())
+        .collect())
+}
+
+fn new_handle(len: usize) -> Result<u32, __wasi_nn_errno_t> {
+    len.try_into().map_err(|_| __WASI_NN_ERUNTIME_ERROR)
+}
+
+fn context_mut(
+    state: &mut NnState,
+    context: __wasi_nn_graph_execution_context_t,
+) -> Result<&mut ExecutionContext, __wasi_nn_errno_t> {
+    state
+        .contexts
+        .get_mut(context as usize)
+        .ok_or(__WASI_NN_EINVALID_ARGUMENT)
+}
@@ -0,0 +1,259 @@
+//! The wasi-nn backend running ONNX models with `tract`.
+use super::*;
+use std::convert::TryInto;
+use tract_onnx::prelude::{
+    tract_ndarray, DatumType, Framework, InferenceFact, InferenceModel, InferenceModelExt, TVec,
+    Tensor as TractTensor,
+};
+
+/// A loaded model.
+pub(crate) struct Graph {
+    model: InferenceModel,
+}
+
+impl Graph {
+    pub fn load(builders: &[Vec<u8>]) -> Result<Self, __wasi_nn_errno_t> {
+        // ONNX models are made of a single buffer
+        let mut model = match builders {
+            [model] => model.as_slice(),
+            _ => return Err(__WASI_NN_EINVALID_ARGUMENT),
+        };
+        let model = tract_onnx::onnx()
+            .model_for_read(&mut model)
+            .map_err(|error| {
+                debug!("wasi_nn: failed to load the ONNX model: {}", error);
+                __WASI_NN_EINVALID_ARGUMENT
+            })?;
+        Ok(Self { model })
+    }
+}
+
+/// The inputs and outputs of an inference.
+pub(crate) struct ExecutionContext {
+    graph: Arc<Graph>,
+    inputs: Vec<Option<TractTensor>>,
+    outputs: TVec<Arc<TractTensor>>,
+}
+
+impl ExecutionContext {
+    pub fn new(graph: Arc<Graph>) -> Self {
+        Self {
+            graph,
+            inputs: vec![],
+            outputs: TVec::new(),
+        }
+    }
+
+    pub fn set_input(&mut self, index: usize, tensor: Tensor) -> Result<(), __wasi_nn_errno_t> {
+        if index >= self.graph.model.inputs.len() {
+            return Err(__WASI_NN_EINVALID_ARGUMENT);
+        }
+        if self.inputs.len() <= index {
+            self.inputs.resize_with(index + 1, || None);
+        }
+        self.inputs[index] = Some(to_tract_tensor(tensor)?);
+        Ok(())
+    }
+
+    /// Runs the model. Since the shapes of the inputs are only known
+    /// once they are set, the model is optimized for them here.
+    pub fn compute(&mut self) -> Result<(), __wasi_nn_errno_t> {
+        let inputs = (0..self.graph.model.inputs.len())
+            .map(|index| self.inputs.get(index).cloned().flatten())
+            .collect::<Option<TVec<_>>>()
+            .ok_or(__WASI_NN_EINVALID_ARGUMENT)?;
+
+        let runtime_error = |error: tract_onnx::prelude::TractError| {
+            debug!("wasi_nn: failed to run the ONNX model: {}", error);
+            __WASI_NN_ERUNTIME_ERROR
+        };
+        let mut model = self.graph.model.clone();
+        for (index, input) in inputs.iter().enumerate() {
+            model = model
+                .with_input_fact(
+                    index,
+                    InferenceFact::dt_shape(input.datum_type(), input.shape().to_vec()),
+                )
+                .map_err(runtime_error)?;
+        }
+        let plan = model
+            .into_optimized()
+            .and_then(|model| model.into_runnable())
+            .map_err(runtime_error)?;
+        self.outputs = plan.run(inputs).map_err(runtime_error)?;
+        Ok(())
+    }
+
+    /// Returns an output of the last inference, as little-endian values.
+    pub fn output(&self, index: usize) -> Result<Vec<u8>, __wasi_nn_errno_t> {
+        let output = self.outputs.get(index).ok_or(__WASI_NN_EINVALID_ARGUMENT)?;
+        let bytes = match output.datum_type() {
+            DatumType::F32 => output.as_slice::<f32>().map(|values| {
+                values
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes().to_vec())
+                    .collect()
+            }),
+            DatumType::I32 => output.as_slice::<i32>().map(|values| {
+                values
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes().to_vec())
+                    .collect()
+            }),
+            DatumType::U8 => output.as_slice::<u8>().map(|values| values.to_vec()),
+            _ => return Err(__WASI_NN_ERUNTIME_ERROR),
+        };
+        bytes.map_err(|_| __WASI_NN_ERUNTIME_ERROR)
+    }
+}
+
+fn to_tract_tensor(tensor: Tensor) -> Result<TractTensor, __wasi_nn_errno_t> {
+    fn from_le_bytes<T, const N: usize>(
+        tensor: &Tensor,
+        from_bytes: fn([u8; N]) -> T,
+    ) -> Result<TractTensor, __wasi_nn_errno_t>
+    where
+        T: tract_onnx::prelude::Datum,
+    {
+        let values = tensor
+            .data
+            .chunks_exact(N)
+            .map(|bytes| from_bytes(bytes.try_into().unwrap()))
+            .collect::<Vec<T>>();
+        if values.len() * N != tensor.data.len() {
+            return Err(__WASI_NN_EINVALID_ARGUMENT);
+        }
+        tract_ndarray::ArrayD::from_shape_vec(tensor.dimensions.clone(), values)
+            .map(TractTensor::from)
+            .map_err(|_| __WASI_NN_EINVALID_ARGUMENT)
+    }
+
+    match tensor.ty {
+        __WASI_NN_TENSOR_TYPE_F32 => from_le_bytes(&tensor, f32::from_le_bytes),
+        __WASI_NN_TENSOR_TYPE_I32 => from_le_bytes(&tensor, i32::from_le_bytes),
+        __WASI_NN_TENSOR_TYPE_U8 => from_le_bytes(&tensor, u8::from_le_bytes),
+        // `tract` has no support for half-precision floats
+        _ => Err(__WASI_NN_EINVALID_ARGUMENT),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Encodes a protobuf field holding `data`.
+    fn field(number: u64, data: &[u8]) -> Vec<u8> {
+        let mut bytes = varint(number << 3 | 2);
+        bytes.extend(varint(data.len() as u64));
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    /// Encodes a protobuf field holding an integer.
+    fn int_field(number: u64, value: u64) -> Vec<u8> {
+        let mut bytes = varint(number << 3);
+        bytes.extend(varint(value));
+        bytes
+    }
+
+    fn varint(mut value: u64) -> Vec<u8> {
+        let mut bytes = vec![];
+        while value >= 0x80 {
+            bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
+        bytes
+    }
+
+    /// An ONNX model applying `Relu` to a tensor of floats, `x`.
+    fn relu_model() -> Vec<u8> {
+        let float_tensor = field(2, &field(1, &int_field(1, 1)));
+        let node = [field(1, b"x"), field(2, b"y"), field(4, b"Relu")].concat();
+        let graph = [
+            field(1, &node),
+            field(2, b"relu"),
+            field(11, &[field(1, b"x"), float_tensor.clone()].concat()),
+            field(12, &[field(1, b"y"), float_tensor].concat()),
+        ]
+        .concat();
+        [
+            int_field(1, 7),
+            field(8, &[field(1, b""), int_field(2, 13)].concat()),
+            field(7, &graph),
+        ]
+        .concat()
+    }
+
+    fn f32_tensor(dimensions: Vec<usize>, values: &[f32]) -> Tensor {
+        Tensor {
+            dimensions,
+            ty: __WASI_NN_TENSOR_TYPE_F32,
+            data: values
+                .iter()
+                .flat_map(|v| v.to_le_bytes().to_vec())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn load() {
+        assert!(Graph::load(&[relu_model()]).is_ok());
+        assert_eq!(
+            Graph::load(&[b"not a model".to_vec()]).err(),
+            Some(__WASI_NN_EINVALID_ARGUMENT)
+        );
+        // ONNX models are a single buffer
+        assert_eq!(
+            Graph::load(&[relu_model(), relu_model()]).err(),
+            Some(__WASI_NN_EINVALID_ARGUMENT)
+        );
+        assert_eq!(Graph::load(&[]).err(), Some(__WASI_NN_EINVALID_ARGUMENT));
+    }
+
+    #[test]
+    fn compute() {
+        let graph = Arc::new(Graph::load(&[relu_model()]).unwrap());
+        let mut context = ExecutionContext::new(graph);
+        // The input isn't set yet
+        assert_eq!(context.compute(), Err(__WASI_NN_EINVALID_ARGUMENT));
+        assert_eq!(context.output(0), Err(__WASI_NN_EINVALID_ARGUMENT));
+
+        let input = f32_tensor(vec![2, 2], &[-1.0, 2.0, -3.0, 4.0]);
+        assert_eq!(
+            context.set_input(1, f32_tensor(vec![1], &[0.0])),
+            Err(__WASI_NN_EINVALID_ARGUMENT)
+        );
+        context.set_input(0, input).unwrap();
+        context.compute().unwrap();
+        assert_eq!(
+            context.output(0).unwrap(),
+            f32_tensor(vec![2, 2], &[0.0, 2.0, 0.0, 4.0]).data
+        );
+        assert_eq!(context.output(1), Err(__WASI_NN_EINVALID_ARGUMENT));
+    }
+
+    #[test]
+    fn invalid_tensors() {
+        // The data doesn't match the dimensions
+        assert!(to_tract_tensor(f32_tensor(vec![3], &[1.0, 2.0])).is_err());
+        // The data isn't a whole number of values
+        let mut tensor = f32_tensor(vec![1], &[1.0]);
+        tensor.data.push(0);
+        assert!(to_tract_tensor(tensor).is_err());
+        // Half-precision floats aren't supported
+        let mut tensor = f32_tensor(vec![2], &[1.0]);
+        tensor.ty = __WASI_NN_TENSOR_TYPE_F16;
+        assert!(to_tract_tensor(tensor).is_err());
+
+        let tensor = Tensor {
+            dimensions: vec![3],
+            ty: __WASI_NN_TENSOR_TYPE_U8,
+            data: vec![1, 2, 3],
+        };
+        assert_eq!(
+            to_tract_tensor(tensor).unwrap(),
+            TractTensor::from(tract_ndarray::arr1(&[1u8, 2, 3]).into_dyn())
+        );
+    }
+}
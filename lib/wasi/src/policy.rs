@@ -0,0 +1,278 @@
+//! A policy denying whole categories of WASI syscalls to a module, on top
+//! of the capabilities it's given by the [`WasiStateBuilder`](crate::WasiStateBuilder).
+use crate::syscalls::types::*;
+use crate::{SyscallHook, WasiEnv};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Mutex;
+use wasmer::Value;
+
+/// A category of syscalls which can be denied by a [`WasiPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyscallCategory {
+    /// Changing files and directories: creating, removing, renaming them,
+    /// changing their metadata, and writing to the files (but not to the
+    /// standard streams, pipes or sockets)
+    FilesystemWrite,
+    /// Reading the environment variables
+    Environment,
+    /// Reading the clocks, and waiting with `poll_oneoff`, which tells
+    /// how much time passed
+    Clock,
+}
+
+impl SyscallCategory {
+    /// The name of the category, as parsed by `from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FilesystemWrite => "fs-write",
+            Self::Environment => "env",
+            Self::Clock => "clock",
+        }
+    }
+}
+
+impl fmt::Display for SyscallCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SyscallCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Self::FilesystemWrite, Self::Environment, Self::Clock]
+            .iter()
+            .copied()
+            .find(|category| category.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown syscall category `{}`, expected `fs-write`, `env` or `clock`",
+                    s
+                )
+            })
+    }
+}
+
+/// The rights of the files opened to change them.
+const WRITE_RIGHTS: __wasi_rights_t =
+    __WASI_RIGHT_FD_WRITE | __WASI_RIGHT_FD_ALLOCATE | __WASI_RIGHT_FD_FILESTAT_SET_SIZE;
+
+fn arg(args: &[Value], index: usize) -> i64 {
+    match args.get(index) {
+        Some(Value::I32(value)) => *value as u32 as i64,
+        Some(Value::I64(value)) => *value,
+        _ => 0,
+    }
+}
+
+/// Whether `fd` is a file of a file system, which isn't a standard stream.
+fn is_file(env: &WasiEnv, fd: i64) -> bool {
+    if fd <= __WASI_STDERR_FILENO as i64 {
+        return false;
+    }
+    match env.state().fs.filestat_fd(fd as __wasi_fd_t) {
+        Ok(stat) => stat.st_filetype == __WASI_FILETYPE_REGULAR_FILE,
+        Err(_) => false,
+    }
+}
+
+/// Gets the category of the call of the syscall `name` with `args`, if
+/// it has one.
+fn categorize(env: &WasiEnv, name: &str, args: &[Value]) -> Option<SyscallCategory> {
+    Some(match name {
+        "fd_allocate"
+        | "fd_filestat_set_size"
+        | "fd_filestat_set_times"
+        | "path_create_directory"
+        | "path_filestat_set_times"
+        | "path_link"
+        | "path_remove_directory"
+        | "path_rename"
+        | "path_symlink"
+        | "path_unlink_file" => SyscallCategory::FilesystemWrite,
+        "fd_write" | "fd_pwrite" if is_file(env, arg(args, 0)) => SyscallCategory::FilesystemWrite,
+        // path_open(dirfd, dirflags, path, path_len, o_flags, fs_rights_base, ...)
+        "path_open"
+            if arg(args, 4) as __wasi_oflags_t & (__WASI_O_CREAT | __WASI_O_TRUNC) != 0
+                || arg(args, 5) as __wasi_rights_t & WRITE_RIGHTS != 0 =>
+        {
+            SyscallCategory::FilesystemWrite
+        }
+        "environ_get" | "environ_sizes_get" => SyscallCategory::Environment,
+        "clock_res_get" | "clock_time_get" | "poll_oneoff" => SyscallCategory::Clock,
+        _ => return None,
+    })
+}
+
+/// A [`SyscallHook`] denying categories of syscalls, which fail with
+/// `ENOTCAPABLE`, and logging the denied calls.
+///
+/// Usage:
+///
+/// ```no_run
+/// # use wasmer_wasi::{SyscallCategory, WasiPolicy, WasiState};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut policy = WasiPolicy::new();
+/// policy
+///     .deny(SyscallCategory::FilesystemWrite)
+///     .deny(SyscallCategory::Clock)
+///     .audit(Box::new(std::io::stderr()));
+/// let mut wasi_env = WasiState::new("program_name").finalize()?;
+/// wasi_env.set_policy(policy);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct WasiPolicy {
+    denied: HashSet<SyscallCategory>,
+    audit: Option<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl WasiPolicy {
+    /// Creates a policy allowing everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Denies the syscalls of `category`.
+    pub fn deny(&mut self, category: SyscallCategory) -> &mut Self {
+        self.denied.insert(category);
+        self
+    }
+
+    /// Logs the denied calls to `writer`, one per line.
+    pub fn audit(&mut self, writer: Box<dyn Write + Send>) -> &mut Self {
+        self.audit = Some(Mutex::new(writer));
+        self
+    }
+
+    /// Whether the syscalls of `category` are denied.
+    pub fn denies(&self, category: SyscallCategory) -> bool {
+        self.denied.contains(&category)
+    }
+}
+
+impl fmt::Debug for WasiPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WasiPolicy")
+            .field("denied", &self.denied)
+            .finish()
+    }
+}
+
+impl SyscallHook for WasiPolicy {
+    fn before(&self, env: &WasiEnv, name: &str, args: &[Value]) -> Option<__wasi_errno_t> {
+        if self.denied.is_empty() {
+            return None;
+        }
+        let category = categorize(env, name, args).filter(|category| self.denies(*category))?;
+        if let Some(audit) = &self.audit {
+            let args = (0..args.len())
+                .map(|index| arg(args, index).to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut writer = audit.lock().unwrap();
+            // Failing to log must not change the behavior of the module
+            let _ = writeln!(writer, "denied {}({}): {}", name, args, category);
+            let _ = writer.flush();
+        }
+        Some(__WASI_ENOTCAPABLE)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::WasiState;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn categories() {
+        for category in &[
+            SyscallCategory::FilesystemWrite,
+            SyscallCategory::Environment,
+            SyscallCategory::Clock,
+        ] {
+            assert_eq!(category.name().parse(), Ok(*category));
+        }
+        assert!("network".parse::<SyscallCategory>().is_err());
+
+        let mut policy = WasiPolicy::new();
+        policy.deny(SyscallCategory::Clock);
+        assert!(policy.denies(SyscallCategory::Clock));
+        assert!(!policy.denies(SyscallCategory::Environment));
+    }
+
+    /// Counts the calls it sees before and after they are made.
+    #[derive(Default)]
+    struct Count {
+        before: AtomicUsize,
+        after: AtomicUsize,
+    }
+
+    impl SyscallHook for Count {
+        fn before(&self, _env: &WasiEnv, _name: &str, _args: &[Value]) -> Option<__wasi_errno_t> {
+            self.before.fetch_add(1, Ordering::SeqCst);
+            None
+        }
+
+        fn after(
+            &self,
+            _env: &WasiEnv,
+            _name: &str,
+            _args: &[Value],
+            errno: __wasi_errno_t,
+        ) -> __wasi_errno_t {
+            self.after.fetch_add(1, Ordering::SeqCst);
+            errno
+        }
+    }
+
+    #[test]
+    fn deny() {
+        let store = wasmer::Store::default();
+        let module = wasmer::Module::new(
+            &store,
+            r#"
+            (module
+              (import "wasi_snapshot_preview1" "clock_time_get"
+                (func $clock_time_get (param i32 i64 i32) (result i32)))
+              (import "wasi_snapshot_preview1" "environ_sizes_get"
+                (func $environ_sizes_get (param i32 i32) (result i32)))
+              (memory (export "memory") 1)
+              (func (export "now") (result i32)
+                (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 0)))
+              (func (export "environ") (result i32)
+                (call $environ_sizes_get (i32.const 0) (i32.const 4))))
+            "#,
+        )
+        .unwrap();
+
+        let mut wasi_env = WasiState::new("test_prog").finalize().unwrap();
+        let count = Arc::new(Count::default());
+        wasi_env.hook_syscall("*", count.clone());
+        let mut policy = WasiPolicy::new();
+        policy.deny(SyscallCategory::Clock);
+        wasi_env.set_policy(policy);
+        let import_object = wasi_env.import_object(&module).unwrap();
+        let instance = wasmer::Instance::new(&module, &import_object).unwrap();
+        let call = |name: &str| {
+            let function = instance.exports.get_native_function::<(), i32>(name);
+            function.unwrap().call().unwrap()
+        };
+
+        // The hook added before the policy only sees the denied call after it
+        assert_eq!(call("now"), __WASI_ENOTCAPABLE as i32);
+        assert_eq!(count.before.load(Ordering::SeqCst), 0);
+        assert_eq!(count.after.load(Ordering::SeqCst), 1);
+
+        assert_eq!(call("environ"), __WASI_ESUCCESS as i32);
+        assert_eq!(count.before.load(Ordering::SeqCst), 1);
+        assert_eq!(count.after.load(Ordering::SeqCst), 2);
+    }
+}
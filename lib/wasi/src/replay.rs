@@ -0,0 +1,436 @@
+//! Recording of the WASI syscalls made by a module, and replay of the
+//! recordings, to reproduce the runs of nondeterministic programs.
+//!
+//! A recording holds every syscall with its arguments, the errno it
+//! returned, and the bytes it changed in the memory of the module. When
+//! replaying it, the syscalls aren't made: their recorded results are
+//! written to the memory instead, so the module behaves exactly as when it
+//! was recorded, as long as it's run with the same arguments.
+use crate::syscalls::types::*;
+use crate::{SyscallHook, WasiEnv};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::sync::Mutex;
+use thiserror::Error;
+use wasmer::Value;
+
+/// The bytes at the start of a recording.
+const MAGIC: &[u8; 8] = b"WASIREC1";
+
+/// `proc_exit` doesn't return, so it's recorded before being made.
+const PROC_EXIT: &str = "proc_exit";
+
+/// A syscall made by the module.
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    name: String,
+    args: Vec<i64>,
+    /// `None` if the syscall doesn't return
+    errno: Option<__wasi_errno_t>,
+    /// The bytes changed in the memory, by offset
+    writes: Vec<(u32, Vec<u8>)>,
+}
+
+fn raw_args(args: &[Value]) -> Vec<i64> {
+    args.iter()
+        .map(|arg| match arg {
+            Value::I32(value) => *value as i64,
+            Value::I64(value) => *value,
+            _ => 0,
+        })
+        .collect()
+}
+
+/// The ranges of the memory which the syscall `name` can write to, as
+/// `(offset, len)`, found from its arguments.
+fn output_ranges(env: &WasiEnv, name: &str, args: &[i64]) -> Vec<(u64, u64)> {
+    let arg = |i: usize| args.get(i).map_or(0, |arg| *arg as u32 as u64);
+    // The buffers of the iovecs `args[i]`, `args[i + 1]` being their count
+    let iovecs = |i: usize| {
+        let size = size_of::<__wasi_iovec_t>();
+        let bytes = read_ranges(env, &[(arg(i), arg(i + 1) * size)]);
+        bytes[0]
+            .1
+            .chunks_exact(size as usize)
+            .map(|iovec| {
+                let field = |j: usize| {
+                    u32::from_le_bytes([iovec[j], iovec[j + 1], iovec[j + 2], iovec[j + 3]])
+                };
+                (field(0) as u64, field(4) as u64)
+            })
+            .collect::<Vec<_>>()
+    };
+    // The pointers to the strings of `strings`, and the strings
+    let string_array = |strings: &[Vec<u8>]| {
+        let len = strings.iter().map(|string| string.len() as u64 + 1).sum();
+        vec![(arg(0), strings.len() as u64 * 4), (arg(1), len)]
+    };
+    // The snapshot 0 `__wasi_filestat_t` is smaller
+    let filestat_size = size_of::<__wasi_filestat_t>();
+    match name {
+        "args_get" => string_array(&env.state().args),
+        "environ_get" => string_array(&env.state().envs),
+        "args_sizes_get" | "environ_sizes_get" => vec![(arg(0), 4), (arg(1), 4)],
+        "clock_res_get" => vec![(arg(1), 8)],
+        "clock_time_get" => vec![(arg(2), 8)],
+        "fd_fdstat_get" => vec![(arg(1), size_of::<__wasi_fdstat_t>())],
+        "fd_filestat_get" => vec![(arg(1), filestat_size)],
+        "fd_prestat_get" => vec![(arg(1), size_of::<__wasi_prestat_t>())],
+        "fd_prestat_dir_name" | "random_get" => vec![(arg(1), arg(2))],
+        "fd_read" => [iovecs(1), vec![(arg(3), 4)]].concat(),
+        "fd_pread" => [iovecs(1), vec![(arg(4), 4)]].concat(),
+        "fd_write" => vec![(arg(3), 4)],
+        "fd_pwrite" | "sock_send" => vec![(arg(4), 4)],
+        "fd_readdir" => vec![(arg(1), arg(2)), (arg(4), 4)],
+        "fd_seek" => vec![(arg(3), 8)],
+        "fd_tell" => vec![(arg(1), 8)],
+        "path_filestat_get" => vec![(arg(4), filestat_size)],
+        "path_open" => vec![(arg(8), 4)],
+        "path_readlink" => vec![(arg(3), arg(4)), (arg(5), 4)],
+        "poll_oneoff" => vec![(arg(1), arg(2) * size_of::<__wasi_event_t>()), (arg(3), 4)],
+        "sock_recv" => [
+            iovecs(1),
+            vec![(arg(4), 4), (arg(5), size_of::<__wasi_roflags_t>())],
+        ]
+        .concat(),
+        "sock_accept" | "sock_connect" | "sock_listen" => vec![(arg(2), 4)],
+        "sock_addr_local" | "sock_addr_peer" => vec![(arg(1), arg(2)), (arg(3), 4)],
+        "tty_get_size" => vec![(arg(1), 4), (arg(2), 4)],
+        _ => vec![],
+    }
+}
+
+fn size_of<T>() -> u64 {
+    mem::size_of::<T>() as u64
+}
+
+/// Reads the `ranges` of the memory, as `(offset, bytes)`, ignoring the
+/// bytes out of the memory.
+fn read_ranges(env: &WasiEnv, ranges: &[(u64, u64)]) -> Vec<(u64, Vec<u8>)> {
+    let memory = env.memory();
+    let size = memory.data_size();
+    ranges
+        .iter()
+        .map(|&(offset, len)| {
+            let offset = offset.min(size);
+            let mut bytes = vec![0; len.min(size - offset) as usize];
+            memory
+                .read(offset, &mut bytes)
+                .expect("the range is in bounds");
+            (offset, bytes)
+        })
+        .collect()
+}
+
+/// Finds the ranges of bytes which are different in `after`.
+fn diff(before: &[u8], after: &[u8]) -> Vec<(u32, Vec<u8>)> {
+    let mut writes: Vec<(u32, Vec<u8>)> = vec![];
+    let mut next_offset = None;
+    for (offset, byte) in after.iter().enumerate() {
+        if before.get(offset) == Some(byte) {
+            continue;
+        }
+        match writes.last_mut() {
+            Some((_, bytes)) if next_offset == Some(offset) => bytes.push(*byte),
+            _ => writes.push((offset as u32, vec![*byte])),
+        }
+        next_offset = Some(offset + 1);
+    }
+    writes
+}
+
+/// A [`SyscallHook`] recording the syscalls.
+///
+/// It must be added for all the syscalls, as `*`. Recording copies the
+/// parts of the memory of the module that each syscall can write to, so
+/// reading large buffers is slower.
+///
+/// Usage:
+///
+/// ```no_run
+/// # use wasmer_wasi::{Recorder, WasiState};
+/// # use std::{fs::File, sync::Arc};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let recorder = Arc::new(Recorder::new(Box::new(File::create("run.rec")?))?);
+/// let mut wasi_env = WasiState::new("program_name").finalize()?;
+/// wasi_env.hook_syscall("*", recorder.clone());
+/// // Instantiate and run the module, then
+/// recorder.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Recorder {
+    inner: Mutex<RecorderInner>,
+}
+
+struct RecorderInner {
+    writer: Box<dyn Write + Send>,
+    /// The parts of the memory the syscall being made can write to,
+    /// before it's made
+    memory: Option<Vec<(u64, Vec<u8>)>>,
+    /// The first error writing the recording
+    error: Option<io::Error>,
+}
+
+impl RecorderInner {
+    fn write(&mut self, record: &Record) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = bincode::serialize_into(&mut self.writer, record) {
+            self.error = Some(match *e {
+                bincode::ErrorKind::Io(e) => e,
+                e => io::Error::new(io::ErrorKind::Other, e),
+            });
+        }
+    }
+}
+
+impl Recorder {
+    /// Creates a recorder writing to `writer`.
+    pub fn new(mut writer: Box<dyn Write + Send>) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        Ok(Self {
+            inner: Mutex::new(RecorderInner {
+                writer,
+                memory: None,
+                error: None,
+            }),
+        })
+    }
+
+    /// Flushes the recording, returning the first error writing it.
+    pub fn finish(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.error.take() {
+            Some(e) => Err(e),
+            None => inner.writer.flush(),
+        }
+    }
+}
+
+impl SyscallHook for Recorder {
+    fn before(&self, env: &WasiEnv, name: &str, args: &[Value]) -> Option<__wasi_errno_t> {
+        let mut inner = self.inner.lock().unwrap();
+        if name == PROC_EXIT {
+            inner.write(&Record {
+                name: name.to_string(),
+                args: raw_args(args),
+                errno: None,
+                writes: vec![],
+            });
+            let _ = inner.writer.flush();
+        } else {
+            let ranges = output_ranges(env, name, &raw_args(args));
+            inner.memory = Some(read_ranges(env, &ranges));
+        }
+        None
+    }
+
+    fn after(
+        &self,
+        env: &WasiEnv,
+        name: &str,
+        args: &[Value],
+        errno: __wasi_errno_t,
+    ) -> __wasi_errno_t {
+        let mut inner = self.inner.lock().unwrap();
+        // The memory isn't read when another hook skipped the syscall
+        let writes = match inner.memory.take() {
+            Some(before) => before
+                .into_iter()
+                .flat_map(|(offset, before)| {
+                    let len = before.len() as u64;
+                    let after = read_ranges(env, &[(offset, len)]).remove(0).1;
+                    diff(&before, &after)
+                        .into_iter()
+                        .map(move |(start, bytes)| ((offset + start as u64) as u32, bytes))
+                })
+                .collect(),
+            None => vec![],
+        };
+        inner.write(&Record {
+            name: name.to_string(),
+            args: raw_args(args),
+            errno: Some(errno),
+            writes,
+        });
+        errno
+    }
+}
+
+/// Error returned when a recording can't be replayed.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    #[error("not a recording of WASI syscalls")]
+    NotARecording,
+    #[error("invalid recording: {0}")]
+    InvalidRecording(String),
+    #[error("the recording ended before the call of `{0}`")]
+    RecordingEnded(String),
+    #[error("the module called `{found}` where the recording has `{expected}`")]
+    Diverged { expected: String, found: String },
+}
+
+/// A [`SyscallHook`] replaying a recording of a [`Recorder`].
+///
+/// It must be added for all the syscalls, as `*`. Once the module stops
+/// doing what was recorded, the syscalls fail with `ENOTCAPABLE`, and
+/// [`Replayer::error`] tells why.
+pub struct Replayer {
+    inner: Mutex<ReplayerInner>,
+}
+
+struct ReplayerInner {
+    reader: Box<dyn Read + Send>,
+    error: Option<ReplayError>,
+}
+
+impl ReplayerInner {
+    fn next(&mut self, name: &str, args: &[Value]) -> Result<Record, ReplayError> {
+        if let Some(e) = &self.error {
+            return Err(e.clone());
+        }
+        let record: Record = bincode::deserialize_from(&mut self.reader).map_err(|e| match *e {
+            bincode::ErrorKind::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                ReplayError::RecordingEnded(name.to_string())
+            }
+            e => ReplayError::InvalidRecording(e.to_string()),
+        })?;
+        if record.name != name || record.args != raw_args(args) {
+            return Err(ReplayError::Diverged {
+                expected: format!("{}{:?}", record.name, record.args),
+                found: format!("{}{:?}", name, raw_args(args)),
+            });
+        }
+        Ok(record)
+    }
+}
+
+impl Replayer {
+    /// Creates a replayer reading the recording from `reader`.
+    pub fn new(mut reader: Box<dyn Read + Send>) -> Result<Self, ReplayError> {
+        let mut magic = [0; 8];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| ReplayError::NotARecording)?;
+        if &magic != MAGIC {
+            return Err(ReplayError::NotARecording);
+        }
+        Ok(Self {
+            inner: Mutex::new(ReplayerInner {
+                reader,
+                error: None,
+            }),
+        })
+    }
+
+    /// Why the module stopped doing what was recorded, if it did.
+    pub fn error(&self) -> Option<ReplayError> {
+        self.inner.lock().unwrap().error.clone()
+    }
+}
+
+impl SyscallHook for Replayer {
+    fn before(&self, env: &WasiEnv, name: &str, args: &[Value]) -> Option<__wasi_errno_t> {
+        let mut inner = self.inner.lock().unwrap();
+        let record = match inner.next(name, args) {
+            Ok(record) => record,
+            Err(e) => {
+                inner.error = Some(e);
+                return Some(__WASI_ENOTCAPABLE);
+            }
+        };
+        let memory = env.memory();
+        for (offset, bytes) in &record.writes {
+            match memory.write(*offset as u64, bytes) {
+                Ok(()) => {}
+                Err(_) => {
+                    inner.error = Some(ReplayError::InvalidRecording(format!(
+                        "`{}` writes out of the memory",
+                        name
+                    )));
+                    return Some(__WASI_ENOTCAPABLE);
+                }
+            }
+        }
+        // `proc_exit` is made, to exit with the recorded code
+        record.errno
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn memory_diff() {
+        assert_eq!(diff(&[1, 2, 3, 4], &[1, 2, 3, 4]), []);
+        assert_eq!(
+            diff(&[1, 2, 3, 4, 5, 6], &[0, 2, 7, 8, 5, 9, 10]),
+            [(0, vec![0]), (2, vec![7, 8]), (5, vec![9, 10])]
+        );
+    }
+
+    fn replayer(records: &[Record]) -> Replayer {
+        let mut bytes = MAGIC.to_vec();
+        for record in records {
+            bincode::serialize_into(&mut bytes, record).unwrap();
+        }
+        Replayer::new(Box::new(io::Cursor::new(bytes))).unwrap()
+    }
+
+    #[test]
+    fn replay() {
+        let record = Record {
+            name: "fd_write".to_string(),
+            args: vec![1, 1024, 1, 2048],
+            errno: Some(__WASI_ESUCCESS),
+            writes: vec![(2048, vec![5, 0, 0, 0])],
+        };
+        let args = [1, 1024, 1, 2048]
+            .iter()
+            .map(|arg| Value::I32(*arg))
+            .collect::<Vec<_>>();
+
+        let replayer = replayer(&[record]);
+        let mut inner = replayer.inner.lock().unwrap();
+        let replayed = inner.next("fd_write", &args).unwrap();
+        assert_eq!(replayed.writes, [(2048, vec![5, 0, 0, 0])]);
+        assert_eq!(
+            inner.next("fd_write", &args).unwrap_err(),
+            ReplayError::RecordingEnded("fd_write".to_string())
+        );
+    }
+
+    #[test]
+    fn replay_errors() {
+        let record = Record {
+            name: "fd_write".to_string(),
+            args: vec![1],
+            errno: Some(__WASI_ESUCCESS),
+            writes: vec![],
+        };
+        let replayer = replayer(&[record]);
+        assert_eq!(
+            replayer
+                .inner
+                .lock()
+                .unwrap()
+                .next("fd_read", &[Value::I32(1)])
+                .unwrap_err(),
+            ReplayError::Diverged {
+                expected: "fd_write[1]".to_string(),
+                found: "fd_read[1]".to_string(),
+            }
+        );
+
+        assert_eq!(
+            Replayer::new(Box::new(io::Cursor::new(b"not a recording".to_vec())))
+                .err()
+                .unwrap(),
+            ReplayError::NotARecording
+        );
+    }
+}
@@ -0,0 +1,514 @@
+//! A read-only file system serving the files of a `.zip`, `.tar` or
+//! `.tar.gz` archive, without extracting it.
+use crate::state::{
+    DirEntry, FileSystem, Metadata, OpenOptions, ReadOnlyFile, WasiFile, WasiFsError,
+};
+use crate::syscalls::types::*;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::ops::Bound;
+use std::path::{Component, Path, PathBuf};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// How many symlinks are followed before giving up.
+const MAX_SYMLINKS: usize = 128;
+
+/// The most memory reserved upfront to read a file, the rest being
+/// allocated as it's read.
+const MAX_PREALLOCATED_LEN: u64 = 1024 * 1024;
+
+/// A read-only file system made of the files of an archive.
+///
+/// The archive is indexed when it's opened, and the contents of its files
+/// are read from it when they are opened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "PathBuf", into = "PathBuf")]
+pub struct ArchiveFileSystem {
+    path: PathBuf,
+    format: Format,
+    /// The entries of the archive by their absolute path, including the
+    /// directories which aren't in the archive but contain its files
+    entries: BTreeMap<PathBuf, Entry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    kind: EntryKind,
+    len: u64,
+    modified: __wasi_timestamp_t,
+}
+
+#[derive(Debug, Clone)]
+enum EntryKind {
+    Dir,
+    /// A file, with the index of the file in a zip, or the offset of its
+    /// contents in a tar
+    File {
+        position: u64,
+    },
+    Symlink {
+        target: PathBuf,
+    },
+}
+
+impl Entry {
+    fn dir(modified: __wasi_timestamp_t) -> Self {
+        Self {
+            kind: EntryKind::Dir,
+            len: 0,
+            modified,
+        }
+    }
+
+    fn metadata(&self) -> Metadata {
+        let filetype = match self.kind {
+            EntryKind::Dir => __WASI_FILETYPE_DIRECTORY,
+            EntryKind::File { .. } => __WASI_FILETYPE_REGULAR_FILE,
+            EntryKind::Symlink { .. } => __WASI_FILETYPE_SYMBOLIC_LINK,
+        };
+        Metadata {
+            filetype,
+            len: self.len,
+            accessed: self.modified,
+            modified: self.modified,
+            created: self.modified,
+        }
+    }
+}
+
+impl ArchiveFileSystem {
+    /// Indexes the archive at `path`. Its format is found from its contents.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, WasiFsError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+        let mut magic = [0; 4];
+        let magic_len = file.read(&mut magic)?;
+        file.seek(io::SeekFrom::Start(0))?;
+        let format = match &magic[..magic_len] {
+            [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => Format::Zip,
+            [0x1f, 0x8b, ..] => Format::TarGz,
+            _ => Format::Tar,
+        };
+
+        let mut entries = BTreeMap::new();
+        entries.insert(PathBuf::from("/"), Entry::dir(0));
+        match format {
+            Format::Zip => index_zip(file, &mut entries)?,
+            Format::Tar => index_tar(BufReader::new(file), &mut entries)?,
+            Format::TarGz => index_tar(GzDecoder::new(BufReader::new(file)), &mut entries)?,
+        }
+        Ok(Self {
+            path,
+            format,
+            entries,
+        })
+    }
+
+    /// Gets the entry at `path`, without following symlinks.
+    fn entry(&self, path: &Path) -> Result<&Entry, WasiFsError> {
+        self.entries.get(path).ok_or(WasiFsError::EntityNotFound)
+    }
+
+    /// Gets the entry at `path` and its path, following symlinks.
+    fn resolve(&self, path: &Path) -> Result<(PathBuf, &Entry), WasiFsError> {
+        let mut path = path.to_path_buf();
+        for _ in 0..MAX_SYMLINKS {
+            let entry = self.entry(&path)?;
+            match &entry.kind {
+                EntryKind::Symlink { target } => {
+                    let parent = path.parent().unwrap_or_else(|| Path::new("/"));
+                    path = normalize(&parent.join(target)).ok_or(WasiFsError::EntityNotFound)?;
+                }
+                _ => return Ok((path, entry)),
+            }
+        }
+        Err(WasiFsError::UnknownError(__WASI_ELOOP))
+    }
+
+    /// Reads the contents of the file at `position` in the archive.
+    fn read_contents(&self, position: u64, len: u64) -> Result<Vec<u8>, WasiFsError> {
+        let mut file = File::open(&self.path)?;
+        // The length comes from the archive, it can't be trusted
+        let mut contents = Vec::with_capacity(len.min(MAX_PREALLOCATED_LEN) as usize);
+        match self.format {
+            Format::Zip => {
+                let mut archive = ZipArchive::new(file).map_err(zip_error)?;
+                let mut zip_file = archive.by_index(position as usize).map_err(zip_error)?;
+                zip_file.read_to_end(&mut contents)?;
+            }
+            Format::Tar => {
+                file.seek(io::SeekFrom::Start(position))?;
+                file.take(len).read_to_end(&mut contents)?;
+            }
+            Format::TarGz => {
+                // gzip streams can't be seeked, so the archive is
+                // decompressed up to the file
+                let mut reader = GzDecoder::new(BufReader::new(file));
+                io::copy(&mut (&mut reader).take(position), &mut io::sink())?;
+                reader.take(len).read_to_end(&mut contents)?;
+            }
+        }
+        if contents.len() as u64 != len {
+            return Err(WasiFsError::UnexpectedEof);
+        }
+        Ok(contents)
+    }
+}
+
+impl TryFrom<PathBuf> for ArchiveFileSystem {
+    type Error = WasiFsError;
+
+    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        Self::open(path)
+    }
+}
+
+// Only the path of the archive is serialized, it's indexed again when
+// it's deserialized.
+impl From<ArchiveFileSystem> for PathBuf {
+    fn from(file_system: ArchiveFileSystem) -> Self {
+        file_system.path
+    }
+}
+
+#[typetag::serde]
+impl FileSystem for ArchiveFileSystem {
+    fn metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        Ok(self.resolve(path)?.1.metadata())
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        Ok(self.entry(path)?.metadata())
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        let (path, entry) = self.resolve(path)?;
+        if !entry.metadata().is_dir() {
+            return Err(WasiFsError::BaseNotDirectory);
+        }
+        let path = path.as_path();
+        // The descendants of a directory directly follow it in the index
+        Ok(self
+            .entries
+            .range::<Path, _>((Bound::Excluded(path), Bound::Unbounded))
+            .take_while(|(entry_path, _)| entry_path.starts_with(path))
+            .filter(|(entry_path, _)| entry_path.parent() == Some(path))
+            .map(|(entry_path, entry)| DirEntry {
+                name: entry_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                filetype: entry.metadata().filetype,
+            })
+            .collect())
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, WasiFsError> {
+        match &self.entry(path)?.kind {
+            EntryKind::Symlink { target } => Ok(target.clone()),
+            _ => Err(WasiFsError::InvalidInput),
+        }
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+
+    fn remove_file(&self, _path: &Path) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        let (_, entry) = match self.resolve(path) {
+            Err(WasiFsError::EntityNotFound) if options.create || options.create_new => {
+                return Err(WasiFsError::PermissionDenied)
+            }
+            entry => entry?,
+        };
+        if options.write || options.append || options.truncate || options.create_new {
+            return Err(WasiFsError::PermissionDenied);
+        }
+        match entry.kind {
+            EntryKind::File { position } => Ok(Box::new(ReadOnlyFile::new(
+                self.read_contents(position, entry.len)?,
+                entry.modified,
+            ))),
+            _ => Err(WasiFsError::NotAFile),
+        }
+    }
+}
+
+/// Gets the absolute path of a file in an archive, or `None` if it's
+/// outside of the archive.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    Some(normalized)
+}
+
+/// Adds an entry to the index, along with its missing parents.
+fn insert_entry(entries: &mut BTreeMap<PathBuf, Entry>, path: PathBuf, entry: Entry) {
+    for parent in path.ancestors().skip(1) {
+        entries
+            .entry(parent.to_path_buf())
+            .or_insert_with(|| Entry::dir(entry.modified));
+    }
+    entries.insert(path, entry);
+}
+
+fn index_tar<R: Read>(
+    reader: R,
+    entries: &mut BTreeMap<PathBuf, Entry>,
+) -> Result<(), WasiFsError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut hard_links = vec![];
+    for tar_entry in archive.entries()? {
+        let tar_entry = tar_entry?;
+        let path = match normalize(&tar_entry.path()?) {
+            Some(path) => path,
+            None => continue,
+        };
+        let header = tar_entry.header();
+        let modified = header.mtime().unwrap_or(0).saturating_mul(1_000_000_000);
+        let kind = match header.entry_type() {
+            tar::EntryType::Directory => EntryKind::Dir,
+            tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File {
+                position: tar_entry.raw_file_position(),
+            },
+            tar::EntryType::Symlink => match tar_entry.link_name()? {
+                Some(target) => EntryKind::Symlink {
+                    target: target.to_path_buf(),
+                },
+                None => continue,
+            },
+            tar::EntryType::Link => {
+                if let Some(target) = tar_entry.link_name()?.as_deref().and_then(normalize) {
+                    hard_links.push((path, target));
+                }
+                continue;
+            }
+            // devices and FIFOs can't be served from an archive
+            _ => continue,
+        };
+        let entry = Entry {
+            kind,
+            len: tar_entry.size(),
+            modified,
+        };
+        insert_entry(entries, path, entry);
+    }
+    // Hard links are copies of the entries they point to
+    for (path, target) in hard_links {
+        if let Some(entry) = entries.get(&target).cloned() {
+            insert_entry(entries, path, entry);
+        }
+    }
+    Ok(())
+}
+
+fn index_zip(file: File, entries: &mut BTreeMap<PathBuf, Entry>) -> Result<(), WasiFsError> {
+    let mut archive = ZipArchive::new(file).map_err(zip_error)?;
+    for index in 0..archive.len() {
+        let mut zip_file = archive.by_index(index).map_err(zip_error)?;
+        let path = match normalize(Path::new(zip_file.name())) {
+            Some(path) => path,
+            None => continue,
+        };
+        let modified = zip_timestamp(zip_file.last_modified());
+        // Symlinks are stored as files containing their target, with the
+        // file type in the unix mode
+        let is_symlink = zip_file
+            .unix_mode()
+            .map_or(false, |mode| mode & 0o170000 == 0o120000);
+        let kind = if zip_file.is_dir() {
+            EntryKind::Dir
+        } else if is_symlink {
+            let mut target = String::new();
+            zip_file.read_to_string(&mut target)?;
+            EntryKind::Symlink {
+                target: PathBuf::from(target),
+            }
+        } else {
+            EntryKind::File {
+                position: index as u64,
+            }
+        };
+        let entry = Entry {
+            kind,
+            len: zip_file.size(),
+            modified,
+        };
+        insert_entry(entries, path, entry);
+    }
+    Ok(())
+}
+
+fn zip_error(error: ZipError) -> WasiFsError {
+    match error {
+        ZipError::Io(error) => error.into(),
+        ZipError::FileNotFound => WasiFsError::EntityNotFound,
+        ZipError::InvalidArchive(_) | ZipError::UnsupportedArchive(_) => WasiFsError::InvalidData,
+    }
+}
+
+/// Converts the date of a zip file, which has no time zone, to a UNIX
+/// timestamp as if it was in UTC.
+fn zip_timestamp(date: zip::DateTime) -> __wasi_timestamp_t {
+    // The number of days since the UNIX epoch, from
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let (month, day) = (date.month() as i64, date.day() as i64);
+    let year = date.year() as i64 - if month <= 2 { 1 } else { 0 };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400
+        + date.hour() as i64 * 3600
+        + date.minute() as i64 * 60
+        + date.second() as i64;
+    seconds.max(0) as u64 * 1_000_000_000
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+
+    fn read(file_system: &ArchiveFileSystem, path: &str) -> String {
+        let mut contents = String::new();
+        file_system
+            .open(Path::new(path), OpenOptions::new().read(true))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    fn names(file_system: &ArchiveFileSystem, path: &str) -> Vec<String> {
+        let mut names = file_system
+            .read_dir(Path::new(path))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn tar_gz_archive() {
+        let path = std::env::temp_dir().join(format!("wasmer-wasi-{}.tar.gz", std::process::id()));
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        ));
+        for (name, contents) in &[("app/index.html", "<html>"), ("app/js/main.js", "main()")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let file_system = ArchiveFileSystem::open(&path).unwrap();
+        assert_eq!(names(&file_system, "/"), ["app"]);
+        assert_eq!(names(&file_system, "/app"), ["index.html", "js"]);
+        assert_eq!(read(&file_system, "/app/js/main.js"), "main()");
+        assert_eq!(read(&file_system, "/app/index.html"), "<html>");
+        assert!(matches!(
+            file_system.open(Path::new("/app/index.html"), OpenOptions::new().write(true)),
+            Err(WasiFsError::PermissionDenied)
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tar_archive_far_in_the_future() {
+        let path = std::env::temp_dir().join(format!("wasmer-wasi-{}.tar", std::process::id()));
+        let mut builder = tar::Builder::new(fs::File::create(&path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.set_mtime(u64::MAX);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "data.txt", &b"data"[..])
+            .unwrap();
+        builder.into_inner().unwrap();
+
+        let file_system = ArchiveFileSystem::open(&path).unwrap();
+        let metadata = file_system.metadata(Path::new("/data.txt")).unwrap();
+        assert_eq!(metadata.modified, u64::MAX);
+        assert_eq!(read(&file_system, "/data.txt"), "data");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn zip_archive() {
+        let path = std::env::temp_dir().join(format!("wasmer-wasi-{}.zip", std::process::id()));
+        let mut writer = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        writer
+            .start_file("assets/data.txt", zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(b"data").unwrap();
+        writer.finish().unwrap();
+
+        let file_system = ArchiveFileSystem::open(&path).unwrap();
+        assert_eq!(names(&file_system, "/assets"), ["data.txt"]);
+        assert_eq!(read(&file_system, "/assets/data.txt"), "data");
+        assert_eq!(
+            file_system
+                .metadata(Path::new("/assets/data.txt"))
+                .unwrap()
+                .len,
+            4
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn zip_timestamps() {
+        let date = zip::DateTime::from_date_and_time(2021, 6, 15, 12, 30, 10).unwrap();
+        assert_eq!(zip_timestamp(date), 1_623_760_210 * 1_000_000_000);
+    }
+}
@@ -1,7 +1,13 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
-use crate::state::{WasiFile, WasiFs, WasiFsError, WasiState};
-use crate::syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO};
+use crate::state::{
+    mount_root, FileSystem, FixedClock, NetworkPolicy, SeededRandom, WasiFile, WasiFs, WasiFsError,
+    WasiState,
+};
+use crate::syscalls::types::{
+    __wasi_fd_t, __wasi_rights_t, __wasi_timestamp_t, __WASI_STDERR_FILENO, __WASI_STDIN_FILENO,
+    __WASI_STDOUT_FILENO,
+};
 use crate::WasiEnv;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
@@ -42,6 +48,11 @@ pub struct WasiStateBuilder {
     stdout_override: Option<Box<dyn WasiFile>>,
     stderr_override: Option<Box<dyn WasiFile>>,
     stdin_override: Option<Box<dyn WasiFile>>,
+    network_rules: Vec<String>,
+    file_systems: Vec<Box<dyn FileSystem>>,
+    passed_fds: Vec<(__wasi_fd_t, Box<dyn WasiFile>)>,
+    clock: Option<FixedClock>,
+    random: Option<SeededRandom>,
 }
 
 impl std::fmt::Debug for WasiStateBuilder {
@@ -54,6 +65,11 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("stdout_override exists", &self.stdout_override.is_some())
             .field("stderr_override exists", &self.stderr_override.is_some())
             .field("stdin_override exists", &self.stdin_override.is_some())
+            .field("network_rules", &self.network_rules)
+            .field("file_systems", &self.file_systems)
+            .field("passed_fds", &self.passed_fds)
+            .field("clock", &self.clock)
+            .field("random", &self.random)
             .finish()
     }
 }
@@ -77,6 +93,10 @@ pub enum WasiStateCreationError {
     WasiFsSetupError(String),
     #[error(transparent)]
     WasiFsError(WasiFsError),
+    #[error("invalid network rule: `{0}`")]
+    NetworkRuleError(String),
+    #[error("file descriptor {0} is already used, or comes before a preopened directory")]
+    FileDescriptorInUse(__wasi_fd_t),
 }
 
 fn validate_mapped_dir_alias(alias: &str) -> Result<(), WasiStateCreationError> {
@@ -174,16 +194,43 @@ impl WasiStateBuilder {
         Ok(self)
     }
 
+    /// Preopen a directory without write permissions
+    ///
+    /// This opens the given directory at the virtual root, `/`, and only
+    /// allows the WASI module to read the given directory: files can't be
+    /// written, created, renamed or removed in it.
+    pub fn preopen_dir_readonly<FilePath>(
+        &mut self,
+        po_dir: FilePath,
+    ) -> Result<&mut Self, WasiStateCreationError>
+    where
+        FilePath: AsRef<Path>,
+    {
+        let mut pdb = PreopenDirBuilder::new();
+        pdb.directory(po_dir.as_ref()).read(true);
+        let preopen = pdb.build()?;
+
+        self.preopens.push(preopen);
+
+        Ok(self)
+    }
+
     /// Preopen a directory and configure it.
     ///
     /// Usage:
     ///
     /// ```no_run
-    /// # use wasmer_wasi::{WasiState, WasiStateCreationError};
+    /// # use wasmer_wasi::{types, WasiState, WasiStateCreationError};
     /// # fn main() -> Result<(), WasiStateCreationError> {
     /// WasiState::new("program_name")
     ///    .preopen(|p| p.directory("src").read(true).write(true).create(true))?
     ///    .preopen(|p| p.directory(".").alias("dot").read(true))?
+    ///    .preopen(|p| {
+    ///        p.directory("logs")
+    ///            .read(true)
+    ///            .write(true)
+    ///            .rights(!types::__WASI_RIGHT_PATH_UNLINK_FILE)
+    ///    })?
     ///    .build()?;
     /// # Ok(())
     /// # }
@@ -258,6 +305,58 @@ impl WasiStateBuilder {
         Ok(self)
     }
 
+    /// Mount a file system so the WASI program sees it as a preopened
+    /// directory named `alias`, instead of a directory of the host.
+    pub fn mount<FS>(
+        &mut self,
+        alias: &str,
+        file_system: FS,
+    ) -> Result<&mut Self, WasiStateCreationError>
+    where
+        FS: FileSystem,
+    {
+        let mut pdb = PreopenDirBuilder::new();
+        pdb.alias(alias).read(true).write(true).create(true);
+        let preopen = pdb.build_with_path(mount_root(self.file_systems.len()))?;
+
+        self.file_systems.push(Box::new(file_system));
+        self.preopens.push(preopen);
+
+        Ok(self)
+    }
+
+    /// Give the WASI program an already open file, pipe or socket as the
+    /// file descriptor `fd`, eg. one inherited from a supervisor.
+    ///
+    /// The program can use it without opening any path, but can't open
+    /// other files relative to it. Sockets must be given as a
+    /// [`Socket`](crate::Socket), or as a [`HostFile`](crate::HostFile) of
+    /// a host socket.
+    ///
+    /// WASI programs look for their preopened directories in the file
+    /// descriptors following `stderr`, up to the first one which isn't,
+    /// so `fd` must come after all the preopened directories and mounts.
+    ///
+    /// Usage:
+    ///
+    /// ```no_run
+    /// # use wasmer_wasi::{Socket, WasiState};
+    /// # use std::net::TcpListener;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let listener = TcpListener::bind("127.0.0.1:8080")?;
+    /// WasiState::new("program_name")
+    ///    .preopen_dir("static")?
+    ///    .preopen_fd(4, Box::new(Socket::Listener(listener)))
+    ///    .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preopen_fd(&mut self, fd: __wasi_fd_t, file: Box<dyn WasiFile>) -> &mut Self {
+        self.passed_fds.push((fd, file));
+
+        self
+    }
+
     /// Overwrite the default WASI `stdout`, if you want to hold on to the
     /// original `stdout` use [`WasiFs::swap_file`] after building.
     pub fn stdout(&mut self, new_file: Box<dyn WasiFile>) -> &mut Self {
@@ -293,6 +392,47 @@ impl WasiStateBuilder {
         self
     }
 
+    /// Allow the module to open TCP sockets to, or listen on, the hosts
+    /// and ports matching `rule`.
+    ///
+    /// The rule is of the form `host:port`, where either can be `*` to
+    /// match any host or port; `*` alone allows everything. The network
+    /// is disabled unless at least one rule is given.
+    pub fn allow_network<R>(&mut self, rule: R) -> &mut Self
+    where
+        R: AsRef<str>,
+    {
+        self.network_rules.push(rule.as_ref().to_string());
+
+        self
+    }
+
+    /// Make the clocks of the module start at `start` nanoseconds, and
+    /// advance by `step` nanoseconds each time they are read, instead of
+    /// reading the clocks of the host.
+    ///
+    /// Waiting with `poll_oneoff` still takes the time it's asked to.
+    pub fn fixed_clock(
+        &mut self,
+        start: __wasi_timestamp_t,
+        step: __wasi_timestamp_t,
+    ) -> &mut Self {
+        self.clock = Some(FixedClock::new(start, step));
+
+        self
+    }
+
+    /// Give the module random numbers generated from `seed`, instead of
+    /// random numbers of the host.
+    ///
+    /// They are the same from one run to the other, and aren't
+    /// cryptographically secure.
+    pub fn random_seed(&mut self, seed: u64) -> &mut Self {
+        self.random = Some(SeededRandom::new(seed));
+
+        self
+    }
+
     /// Consumes the [`WasiStateBuilder`] and produces a [`WasiState`]
     ///
     /// Returns the error from `WasiFs::new` if there's an error
@@ -359,9 +499,17 @@ impl WasiStateBuilder {
             }
         }
 
+        let mut network = NetworkPolicy::default();
+        for rule in self.network_rules.iter() {
+            network
+                .allow(rule)
+                .map_err(WasiStateCreationError::NetworkRuleError)?;
+        }
+
         // self.preopens are checked in [`PreopenDirBuilder::build`]
 
-        let mut wasi_fs = WasiFs::new_with_preopen(&self.preopens)
+        let file_systems = std::mem::take(&mut self.file_systems);
+        let mut wasi_fs = WasiFs::new_with_preopen(&self.preopens, file_systems)
             .map_err(WasiStateCreationError::WasiFsCreationError)?;
         // set up the file system, overriding base files and calling the setup function
         if let Some(stdin_override) = self.stdin_override.take() {
@@ -379,6 +527,13 @@ impl WasiStateBuilder {
                 .swap_file(__WASI_STDERR_FILENO, stderr_override)
                 .map_err(WasiStateCreationError::WasiFsError)?;
         }
+        let mut passed_fds = std::mem::take(&mut self.passed_fds);
+        passed_fds.sort_by_key(|(fd, _)| *fd);
+        for (fd, file) in passed_fds {
+            wasi_fs
+                .create_passed_fd(fd, file)
+                .map_err(|_| WasiStateCreationError::FileDescriptorInUse(fd))?;
+        }
         if let Some(f) = &self.setup_fs_fn {
             f(&mut wasi_fs).map_err(WasiStateCreationError::WasiFsSetupError)?;
         }
@@ -397,6 +552,9 @@ impl WasiStateBuilder {
                     env
                 })
                 .collect(),
+            network,
+            clock: self.clock.clone(),
+            random: self.random.clone(),
         })
     }
 
@@ -417,6 +575,7 @@ pub struct PreopenDirBuilder {
     read: bool,
     write: bool,
     create: bool,
+    rights: Option<__wasi_rights_t>,
 }
 
 /// The built version of `PreopenDirBuilder`
@@ -427,6 +586,8 @@ pub(crate) struct PreopenedDir {
     pub(crate) read: bool,
     pub(crate) write: bool,
     pub(crate) create: bool,
+    /// The mask applied to the rights given by `read`, `write` and `create`
+    pub(crate) rights: Option<__wasi_rights_t>,
 }
 
 impl PreopenDirBuilder {
@@ -482,6 +643,17 @@ impl PreopenDirBuilder {
         self
     }
 
+    /// Restrict the rights of the directory, and of the files and
+    /// directories opened in it, to the `__WASI_RIGHT_*` flags of `rights`
+    ///
+    /// The rights are masked: this can only remove some of the rights given
+    /// by `read`, `write` and `create`.
+    pub fn rights(&mut self, rights: __wasi_rights_t) -> &mut Self {
+        self.rights = Some(rights);
+
+        self
+    }
+
     pub(crate) fn build(&self) -> Result<PreopenedDir, WasiStateCreationError> {
         // ensure at least one is set
         if !(self.read || self.write || self.create) {
@@ -498,6 +670,11 @@ impl PreopenDirBuilder {
         if !path.exists() {
             return Err(WasiStateCreationError::PreopenedDirectoryNotFound(path));
         }
+        self.build_with_path(path)
+    }
+
+    /// Builds a preopened directory whose path isn't on the host.
+    fn build_with_path(&self, path: PathBuf) -> Result<PreopenedDir, WasiStateCreationError> {
         if let Some(alias) = &self.alias {
             validate_mapped_dir_alias(alias)?;
         }
@@ -508,6 +685,7 @@ impl PreopenDirBuilder {
             read: self.read,
             write: self.write,
             create: self.create,
+            rights: self.rights,
         })
     }
 }
@@ -570,4 +748,123 @@ mod test {
             _ => assert!(false),
         }
     }
+
+    #[test]
+    fn passed_fds() {
+        use crate::syscalls::types::*;
+
+        let state = create_wasi_state("test_prog")
+            .preopen_dir(std::env::temp_dir())
+            .unwrap()
+            .preopen_fd(5, Box::new(crate::Pipe::new()))
+            .preopen_fd(4, Box::new(crate::Pipe::new()))
+            .build()
+            .unwrap();
+        assert_eq!(state.fs.preopen_fds, [3]);
+        for fd in &[4, 5] {
+            let fd = &state.fs.fd_map[fd];
+            assert_ne!(fd.rights & __WASI_RIGHT_FD_WRITE, 0);
+            assert_eq!(fd.rights & __WASI_RIGHT_PATH_OPEN, 0);
+        }
+        assert_eq!(state.fs.prestat_fd(4).unwrap_err(), __WASI_EBADF);
+        // the fds opened by the program come after the passed ones
+        assert_eq!(state.fs.next_fd.get(), 6);
+
+        assert_eq!(
+            create_wasi_state("test_prog")
+                .preopen_dir(std::env::temp_dir())
+                .unwrap()
+                .preopen_fd(3, Box::new(crate::Pipe::new()))
+                .build()
+                .unwrap_err(),
+            WasiStateCreationError::FileDescriptorInUse(3)
+        );
+    }
+
+    #[test]
+    fn preopen_rights() {
+        use crate::syscalls::types::*;
+
+        let dir = std::env::temp_dir();
+        let state = create_wasi_state("test_prog")
+            .preopen_dir_readonly(&dir)
+            .unwrap()
+            .preopen(|p| {
+                p.directory(&dir)
+                    .alias("no-unlink")
+                    .read(true)
+                    .create(true)
+                    .rights(!__WASI_RIGHT_PATH_UNLINK_FILE)
+            })
+            .unwrap()
+            .build()
+            .unwrap();
+        let rights = |i: usize| {
+            let fd = &state.fs.fd_map[&state.fs.preopen_fds[i]];
+            assert_eq!(fd.rights, fd.rights_inheriting);
+            fd.rights
+        };
+
+        assert_ne!(rights(0) & __WASI_RIGHT_FD_READ, 0);
+        for right in &[
+            __WASI_RIGHT_FD_WRITE,
+            __WASI_RIGHT_PATH_CREATE_FILE,
+            __WASI_RIGHT_PATH_CREATE_DIRECTORY,
+            __WASI_RIGHT_PATH_RENAME_TARGET,
+            __WASI_RIGHT_PATH_UNLINK_FILE,
+            __WASI_RIGHT_PATH_REMOVE_DIRECTORY,
+        ] {
+            assert_eq!(rights(0) & right, 0);
+        }
+        assert_ne!(rights(1) & __WASI_RIGHT_PATH_CREATE_FILE, 0);
+        assert_eq!(rights(1) & __WASI_RIGHT_PATH_UNLINK_FILE, 0);
+    }
+
+    #[test]
+    fn fd_table() {
+        use crate::state::Kind;
+        use crate::syscalls::types::*;
+
+        let mut state = create_wasi_state("test_prog")
+            .preopen_dir(std::env::temp_dir())
+            .unwrap()
+            .preopen_fd(5, Box::new(crate::Pipe::new()))
+            .build()
+            .unwrap();
+        let fs = &mut state.fs;
+        let preopen = fs.preopen_fds[0];
+
+        // the lowest free fd is reused
+        let dup = fs.dup_fd(preopen).unwrap();
+        assert_eq!(dup, 4);
+        assert!(fs.prestat_fd(dup).is_ok());
+        fs.close_fd(preopen).unwrap();
+        assert_eq!(fs.preopen_fds, [dup]);
+        assert_eq!(fs.dup_fd(5).unwrap(), preopen);
+        assert_eq!(fs.dup_fd(5).unwrap(), 6);
+
+        // renumbering closes the target
+        fs.renumber_fd(dup, 6).unwrap();
+        assert_eq!(fs.preopen_fds, [6]);
+        assert_eq!(fs.get_fd(dup).unwrap_err(), __WASI_EBADF);
+        assert!(fs.prestat_fd(6).is_ok());
+        fs.renumber_fd(5, 9).unwrap();
+        assert_eq!(fs.get_fd(5).unwrap_err(), __WASI_EBADF);
+        assert_eq!(fs.renumber_fd(5, 9).unwrap_err(), __WASI_EBADF);
+        // the pipe stays open through its other fd
+        match &fs.inodes[fs.get_fd(9).unwrap().inode].kind {
+            Kind::File { handle, .. } => assert!(handle.is_some()),
+            _ => unreachable!(),
+        }
+
+        // the fds of the standard devices are only given to files explicitly
+        fs.close_fd(__WASI_STDIN_FILENO).unwrap();
+        assert_eq!(fs.dup_fd(9).unwrap(), 4);
+        assert_eq!(
+            fs.renumber_fd(6, __WASI_STDOUT_FILENO).unwrap_err(),
+            __WASI_EBADF
+        );
+        fs.renumber_fd(9, __WASI_STDOUT_FILENO).unwrap();
+        assert!(fs.stdout().unwrap().is_some());
+    }
 }
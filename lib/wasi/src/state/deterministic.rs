@@ -0,0 +1,93 @@
+//! Replacements for the clocks and the random numbers of the host, giving
+//! modules the same results from one run to the other.
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+
+/// A clock returning fixed times instead of the time of the host.
+///
+/// All the clocks start at the same time, and advance by a fixed step
+/// each time one of them is read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixedClock {
+    now: __wasi_timestamp_t,
+    step: __wasi_timestamp_t,
+}
+
+impl FixedClock {
+    /// Creates a clock starting at `start` nanoseconds, and advancing by
+    /// `step` nanoseconds each time it's read.
+    pub fn new(start: __wasi_timestamp_t, step: __wasi_timestamp_t) -> Self {
+        Self { now: start, step }
+    }
+
+    /// Reads the clock, and advances it.
+    pub(crate) fn tick(&mut self) -> __wasi_timestamp_t {
+        let now = self.now;
+        self.now = self.now.wrapping_add(self.step);
+        now
+    }
+
+    /// The resolution of the clock, which can't be 0.
+    pub(crate) fn resolution(&self) -> __wasi_timestamp_t {
+        self.step.max(1)
+    }
+}
+
+/// A random number generator seeded by the host, instead of the random
+/// numbers of the host.
+///
+/// This is SplitMix64, which is fast and statistically good, but isn't
+/// cryptographically secure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Fills `buf` with the next random bytes.
+    pub(crate) fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixed_clock() {
+        let mut clock = FixedClock::new(1_000, 10);
+        assert_eq!(clock.tick(), 1_000);
+        assert_eq!(clock.tick(), 1_010);
+        assert_eq!(clock.resolution(), 10);
+        assert_eq!(FixedClock::new(0, 0).resolution(), 1);
+    }
+
+    #[test]
+    fn seeded_random() {
+        let mut random = SeededRandom::new(0);
+        assert_eq!(random.next_u64(), 0xE220_A839_7B1D_CDAF);
+
+        let (mut a, mut b) = ([0; 13], [0; 13]);
+        SeededRandom::new(42).fill(&mut a);
+        SeededRandom::new(42).fill(&mut b);
+        assert_eq!(a, b);
+        SeededRandom::new(43).fill(&mut b);
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,406 @@
+//! The file systems backing the directories seen by WASI: the host file
+//! system, or custom ones mounted with [`WasiStateBuilder::mount`].
+//!
+//! [`WasiStateBuilder::mount`]: crate::WasiStateBuilder::mount
+use crate::state::{host_file_type_to_wasi_file_type, HostFile, WasiFile, WasiFsError};
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A file system which can be mounted in the WASI file system.
+///
+/// Paths are absolute within the file system, `/` being its root. The
+/// files it opens are renamed and removed through it rather than with
+/// [`WasiFile::rename_file`] and [`WasiFile::unlink`].
+#[typetag::serde(tag = "type")]
+pub trait FileSystem: fmt::Debug + Send + 'static {
+    /// Gets the metadata of `path`, following symlinks
+    fn metadata(&self, path: &Path) -> Result<Metadata, WasiFsError>;
+
+    /// Gets the metadata of `path`, without following symlinks. The default
+    /// implementation is for file systems without symlinks.
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        self.metadata(path)
+    }
+
+    /// Lists the entries of the directory at `path`
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError>;
+
+    /// Reads the target of the symlink at `path`. The default implementation
+    /// is for file systems without symlinks.
+    fn read_link(&self, _path: &Path) -> Result<PathBuf, WasiFsError> {
+        Err(WasiFsError::InvalidInput)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError>;
+
+    /// Removes the empty directory at `path`
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError>;
+
+    fn remove_file(&self, path: &Path) -> Result<(), WasiFsError>;
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError>;
+
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError>;
+}
+
+/// The metadata of a file in a [`FileSystem`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub filetype: __wasi_filetype_t,
+    /// The size of the file in bytes
+    pub len: u64,
+    /// The last time the file was accessed in nanoseconds as a UNIX timestamp
+    pub accessed: __wasi_timestamp_t,
+    /// The last time the file was modified in nanoseconds as a UNIX timestamp
+    pub modified: __wasi_timestamp_t,
+    /// The time at which the file was created in nanoseconds as a UNIX timestamp
+    pub created: __wasi_timestamp_t,
+}
+
+impl Metadata {
+    pub fn is_dir(&self) -> bool {
+        self.filetype == __WASI_FILETYPE_DIRECTORY
+    }
+
+    pub(crate) fn to_filestat(&self) -> __wasi_filestat_t {
+        __wasi_filestat_t {
+            st_filetype: self.filetype,
+            st_size: self.len,
+            st_atim: self.accessed,
+            st_mtim: self.modified,
+            st_ctim: self.created,
+            ..__wasi_filestat_t::default()
+        }
+    }
+}
+
+/// An entry of a directory in a [`FileSystem`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub filetype: __wasi_filetype_t,
+}
+
+/// How to open a file, like [`std::fs::OpenOptions`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    pub read: bool,
+    pub write: bool,
+    pub append: bool,
+    pub truncate: bool,
+    pub create: bool,
+    pub create_new: bool,
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+}
+
+/// The file system of the host, or of one of its directories.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HostFileSystem {
+    /// The directory which is the root of the file system, the root of
+    /// the host if `None`
+    root: Option<PathBuf>,
+}
+
+/// The file system of the files which aren't on a mounted file system.
+pub(crate) static HOST_FILE_SYSTEM: HostFileSystem = HostFileSystem { root: None };
+
+impl HostFileSystem {
+    /// Creates a file system whose root is the host directory `root`.
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: Some(root.as_ref().to_path_buf()),
+        }
+    }
+
+    /// Gets the path on the host of `path`.
+    fn host_path(&self, path: &Path) -> PathBuf {
+        match &self.root {
+            Some(root) => root.join(path.strip_prefix("/").unwrap_or(path)),
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+#[typetag::serde]
+impl FileSystem for HostFileSystem {
+    fn metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        Ok(host_metadata(self.host_path(path).metadata()?))
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        Ok(host_metadata(self.host_path(path).symlink_metadata()?))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        fs::read_dir(self.host_path(path))?
+            .map(|entry| -> Result<DirEntry, WasiFsError> {
+                let entry = entry?;
+                Ok(DirEntry {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    filetype: host_file_type(entry.file_type()?),
+                })
+            })
+            .collect()
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, WasiFsError> {
+        fs::read_link(self.host_path(path)).map_err(Into::into)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        fs::create_dir(self.host_path(path)).map_err(Into::into)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        fs::remove_dir(self.host_path(path)).map_err(Into::into)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), WasiFsError> {
+        fs::remove_file(self.host_path(path)).map_err(Into::into)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError> {
+        fs::rename(self.host_path(from), self.host_path(to)).map_err(Into::into)
+    }
+
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        let path = self.host_path(path);
+        let file = fs::OpenOptions::new()
+            .read(options.read)
+            .write(options.write)
+            .append(options.append)
+            .truncate(options.truncate)
+            .create(options.create)
+            .create_new(options.create_new)
+            .open(&path)?;
+        Ok(Box::new(HostFile::new(
+            file,
+            path,
+            options.read,
+            options.write,
+            options.append,
+        )))
+    }
+}
+
+fn host_metadata(metadata: fs::Metadata) -> Metadata {
+    let timestamp = |time: std::io::Result<SystemTime>| {
+        time.ok()
+            .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map_or(0, |time| time.as_nanos() as u64)
+    };
+    Metadata {
+        filetype: host_file_type(metadata.file_type()),
+        len: metadata.len(),
+        accessed: timestamp(metadata.accessed()),
+        modified: timestamp(metadata.modified()),
+        created: timestamp(metadata.created()),
+    }
+}
+
+/// Gets the WASI type of a host file, including the special files.
+fn host_file_type(file_type: fs::FileType) -> __wasi_filetype_t {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_char_device() {
+            return __WASI_FILETYPE_CHARACTER_DEVICE;
+        } else if file_type.is_block_device() {
+            return __WASI_FILETYPE_BLOCK_DEVICE;
+        } else if file_type.is_socket() {
+            // TODO: how do we know if it's a `__WASI_FILETYPE_SOCKET_STREAM` or
+            // a `__WASI_FILETYPE_SOCKET_DGRAM`?
+            return __WASI_FILETYPE_SOCKET_STREAM;
+        }
+    }
+    // FIFOs don't seem to fit any other type, so they're unknown
+    host_file_type_to_wasi_file_type(file_type)
+}
+
+/// A read-only file whose contents are in memory, like the files of
+/// archives and packages.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ReadOnlyFile {
+    contents: Vec<u8>,
+    position: u64,
+    modified: __wasi_timestamp_t,
+}
+
+impl ReadOnlyFile {
+    pub(crate) fn new(contents: Vec<u8>, modified: __wasi_timestamp_t) -> Self {
+        Self {
+            contents,
+            position: 0,
+            modified,
+        }
+    }
+}
+
+impl Read for ReadOnlyFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = (self.position as usize).min(self.contents.len());
+        let amt = (&self.contents[start..]).read(buf)?;
+        self.position += amt as u64;
+        Ok(amt)
+    }
+}
+
+impl Write for ReadOnlyFile {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "can not write to a read-only file",
+        ))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ReadOnlyFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            io::SeekFrom::Start(offset) => Some(offset as i64),
+            io::SeekFrom::End(offset) => (self.contents.len() as i64).checked_add(offset),
+            io::SeekFrom::Current(offset) => (self.position as i64).checked_add(offset),
+        };
+        match position {
+            Some(position) if position >= 0 => {
+                self.position = position as u64;
+                Ok(self.position)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for ReadOnlyFile {
+    fn last_accessed(&self) -> __wasi_timestamp_t {
+        self.modified
+    }
+    fn last_modified(&self) -> __wasi_timestamp_t {
+        self.modified
+    }
+    fn created_time(&self) -> __wasi_timestamp_t {
+        self.modified
+    }
+    fn size(&self) -> u64 {
+        self.contents.len() as u64
+    }
+    fn set_len(&mut self, _new_size: __wasi_filesize_t) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        Ok(self.contents.len().saturating_sub(self.position as usize))
+    }
+}
+
+/// The paths of the files of a mounted file system are made of this
+/// component, the index of the file system, and then the path within it.
+/// A nul byte can't be part of a host path, so they can't be confused.
+const MOUNT_PREFIX: &str = "\0mount";
+
+/// Gets the path of the root of the `index`th mounted file system.
+pub(crate) fn mount_root(index: usize) -> PathBuf {
+    Path::new(MOUNT_PREFIX).join(index.to_string())
+}
+
+/// Splits a path into the index of the mounted file system it's on, if
+/// any, and the path within that file system.
+pub(crate) fn split_mount_path(path: &Path) -> (Option<usize>, PathBuf) {
+    let mut components = path.components();
+    if components.next().map(|c| c.as_os_str()) == Some(OsStr::new(MOUNT_PREFIX)) {
+        if let Some(index) = components
+            .next()
+            .and_then(|c| c.as_os_str().to_str()?.parse().ok())
+        {
+            return (Some(index), Path::new("/").join(components.as_path()));
+        }
+    }
+    (None, path.to_path_buf())
+}
+
+/// Whether two paths are on the same file system, either both on the
+/// host or both on the same mounted file system.
+pub(crate) fn same_file_system(path: &Path, other: &Path) -> bool {
+    split_mount_path(path).0 == split_mount_path(other).0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mount_paths() {
+        let root = mount_root(3);
+        assert_eq!(split_mount_path(&root), (Some(3), PathBuf::from("/")));
+        assert_eq!(
+            split_mount_path(&root.join("a/b.txt")),
+            (Some(3), PathBuf::from("/a/b.txt"))
+        );
+        assert_eq!(
+            split_mount_path(Path::new("/home/mount/3")),
+            (None, PathBuf::from("/home/mount/3"))
+        );
+    }
+
+    #[test]
+    fn same_file_systems() {
+        let host = Path::new("/home/user/a.txt");
+        let first = mount_root(0).join("a.txt");
+        let second = mount_root(1).join("a.txt");
+        assert!(same_file_system(host, Path::new("/tmp/b.txt")));
+        assert!(same_file_system(&first, &mount_root(0).join("dir/b.txt")));
+        assert!(!same_file_system(&first, &second));
+        assert!(!same_file_system(host, &first));
+        assert!(!same_file_system(&second, host));
+    }
+}
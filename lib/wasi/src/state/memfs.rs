@@ -0,0 +1,518 @@
+//! An in-memory file system, like a tmpfs, with optional limits on the
+//! size of its files and on how many files and directories it can hold.
+use crate::state::{DirEntry, FileSystem, Metadata, OpenOptions, WasiFile, WasiFsError};
+use crate::syscalls::platform_clock_time_get;
+use crate::syscalls::types::*;
+use serde::{de, ser, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// An in-memory file system.
+///
+/// Writing past the size quota, or creating files and directories past
+/// the inode limit, fails with `ENOSPC`.
+#[derive(Debug, Clone)]
+pub struct MemFileSystem {
+    inner: Arc<Mutex<MemFs>>,
+}
+
+#[derive(Debug)]
+struct MemFs {
+    /// The files and directories by absolute path, `/` included
+    nodes: BTreeMap<PathBuf, Node>,
+    usage: Arc<Usage>,
+    max_inodes: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Dir { created: __wasi_timestamp_t },
+    File(Arc<Mutex<FileData>>),
+}
+
+/// The number of bytes used by the files, shared with the files so they
+/// can check the quota when they are written.
+#[derive(Debug)]
+struct Usage {
+    bytes: Mutex<u64>,
+    max_bytes: Option<u64>,
+}
+
+impl Usage {
+    /// Accounts for a file growing or shrinking from `old_len` to `new_len`.
+    fn resize(&self, old_len: usize, new_len: usize) -> io::Result<()> {
+        let mut bytes = self.bytes.lock().unwrap();
+        let new_bytes = *bytes - old_len as u64 + new_len as u64;
+        if new_len > old_len && self.max_bytes.map_or(false, |max| new_bytes > max) {
+            // Mapped to `ENOSPC`
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "the file system is full",
+            ));
+        }
+        *bytes = new_bytes;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct FileData {
+    contents: Vec<u8>,
+    accessed: __wasi_timestamp_t,
+    modified: __wasi_timestamp_t,
+    created: __wasi_timestamp_t,
+    usage: Arc<Usage>,
+}
+
+impl FileData {
+    fn set_len(&mut self, len: usize) -> io::Result<()> {
+        self.usage.resize(self.contents.len(), len)?;
+        self.contents.resize(len, 0);
+        self.modified = now();
+        Ok(())
+    }
+}
+
+// The space of a file is released once it's removed and closed.
+impl Drop for FileData {
+    fn drop(&mut self) {
+        let _ = self.usage.resize(self.contents.len(), 0);
+    }
+}
+
+fn now() -> __wasi_timestamp_t {
+    platform_clock_time_get(__WASI_CLOCK_REALTIME, 1).unwrap_or(0)
+}
+
+impl MemFileSystem {
+    /// Creates an empty file system, whose files can take up to
+    /// `max_size` bytes, holding up to `max_inodes` files and directories.
+    pub fn new(max_size: Option<u64>, max_inodes: Option<usize>) -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(PathBuf::from("/"), Node::Dir { created: now() });
+        Self {
+            inner: Arc::new(Mutex::new(MemFs {
+                nodes,
+                usage: Arc::new(Usage {
+                    bytes: Mutex::new(0),
+                    max_bytes: max_size,
+                }),
+                max_inodes,
+            })),
+        }
+    }
+
+    /// The number of bytes used by the files.
+    pub fn size(&self) -> u64 {
+        *self.inner.lock().unwrap().usage.bytes.lock().unwrap()
+    }
+}
+
+impl Default for MemFileSystem {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl MemFs {
+    /// Checks that a new file or directory can be created at `path`.
+    fn check_new(&self, path: &Path) -> Result<(), WasiFsError> {
+        self.check_parent(path)?;
+        // The root doesn't count
+        if self
+            .max_inodes
+            .map_or(false, |max| self.nodes.len() - 1 >= max)
+        {
+            // Mapped to `ENOSPC`
+            return Err(WasiFsError::WriteZero);
+        }
+        Ok(())
+    }
+
+    /// Checks that nothing is at `path`, in an existing directory.
+    fn check_parent(&self, path: &Path) -> Result<(), WasiFsError> {
+        if self.nodes.contains_key(path) {
+            return Err(WasiFsError::AlreadyExists);
+        }
+        match path.parent().and_then(|parent| self.nodes.get(parent)) {
+            Some(Node::Dir { .. }) => Ok(()),
+            Some(Node::File(_)) => Err(WasiFsError::BaseNotDirectory),
+            None => Err(WasiFsError::EntityNotFound),
+        }
+    }
+
+    /// The paths of the files and directories within the directory `path`.
+    fn descendants<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a PathBuf> + 'a {
+        // The descendants of a directory directly follow it
+        self.nodes
+            .range::<Path, _>((Bound::Excluded(path), Bound::Unbounded))
+            .map(|(path, _)| path)
+            .take_while(move |descendant| descendant.starts_with(path))
+    }
+}
+
+#[typetag::serde]
+impl FileSystem for MemFileSystem {
+    fn metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        let fs = self.inner.lock().unwrap();
+        match fs.nodes.get(path).ok_or(WasiFsError::EntityNotFound)? {
+            Node::Dir { created } => Ok(Metadata {
+                filetype: __WASI_FILETYPE_DIRECTORY,
+                len: 0,
+                accessed: *created,
+                modified: *created,
+                created: *created,
+            }),
+            Node::File(data) => {
+                let data = data.lock().unwrap();
+                Ok(Metadata {
+                    filetype: __WASI_FILETYPE_REGULAR_FILE,
+                    len: data.contents.len() as u64,
+                    accessed: data.accessed,
+                    modified: data.modified,
+                    created: data.created,
+                })
+            }
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        let fs = self.inner.lock().unwrap();
+        if let Node::File(_) = fs.nodes.get(path).ok_or(WasiFsError::EntityNotFound)? {
+            return Err(WasiFsError::BaseNotDirectory);
+        }
+        Ok(fs
+            .descendants(path)
+            .filter(|child| child.parent() == Some(path))
+            .map(|child| DirEntry {
+                name: child
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                filetype: match fs.nodes[child] {
+                    Node::Dir { .. } => __WASI_FILETYPE_DIRECTORY,
+                    Node::File(_) => __WASI_FILETYPE_REGULAR_FILE,
+                },
+            })
+            .collect())
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        let mut fs = self.inner.lock().unwrap();
+        fs.check_new(path)?;
+        fs.nodes
+            .insert(path.to_path_buf(), Node::Dir { created: now() });
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        let mut fs = self.inner.lock().unwrap();
+        match fs.nodes.get(path) {
+            Some(Node::Dir { .. }) if path.parent().is_some() => {}
+            Some(Node::Dir { .. }) => return Err(WasiFsError::PermissionDenied),
+            Some(Node::File(_)) => return Err(WasiFsError::BaseNotDirectory),
+            None => return Err(WasiFsError::EntityNotFound),
+        }
+        if fs.descendants(path).next().is_some() {
+            return Err(WasiFsError::UnknownError(__WASI_ENOTEMPTY));
+        }
+        fs.nodes.remove(path);
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), WasiFsError> {
+        let mut fs = self.inner.lock().unwrap();
+        match fs.nodes.get(path) {
+            Some(Node::File(_)) => {}
+            Some(Node::Dir { .. }) => return Err(WasiFsError::UnknownError(__WASI_EISDIR)),
+            None => return Err(WasiFsError::EntityNotFound),
+        }
+        fs.nodes.remove(path);
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError> {
+        let mut guard = self.inner.lock().unwrap();
+        let fs = &mut *guard;
+        let is_dir = match fs.nodes.get(from).ok_or(WasiFsError::EntityNotFound)? {
+            Node::Dir { .. } => true,
+            Node::File(_) => false,
+        };
+        if from == to {
+            return Ok(());
+        }
+        if from.parent().is_none() || to.starts_with(from) {
+            return Err(WasiFsError::InvalidInput);
+        }
+        // Like on POSIX, an existing file or empty directory is replaced
+        match fs.nodes.get(to) {
+            Some(Node::Dir { .. }) if !is_dir => {
+                return Err(WasiFsError::UnknownError(__WASI_EISDIR))
+            }
+            Some(Node::File(_)) if is_dir => return Err(WasiFsError::BaseNotDirectory),
+            Some(Node::Dir { .. }) if fs.descendants(to).next().is_some() => {
+                return Err(WasiFsError::UnknownError(__WASI_ENOTEMPTY))
+            }
+            Some(_) => {
+                fs.nodes.remove(to);
+            }
+            None => fs.check_parent(to)?,
+        }
+        let moved = std::iter::once(from.to_path_buf())
+            .chain(fs.descendants(from).cloned())
+            .collect::<Vec<_>>();
+        for path in moved {
+            let node = fs.nodes.remove(&path).unwrap();
+            let new_path = to.join(path.strip_prefix(from).unwrap());
+            fs.nodes.insert(new_path, node);
+        }
+        Ok(())
+    }
+
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        let mut guard = self.inner.lock().unwrap();
+        let fs = &mut *guard;
+        let data = match fs.nodes.get(path) {
+            Some(_) if options.create_new => return Err(WasiFsError::AlreadyExists),
+            Some(Node::File(data)) => data.clone(),
+            Some(Node::Dir { .. }) => return Err(WasiFsError::NotAFile),
+            None if options.create || options.create_new => {
+                fs.check_new(path)?;
+                let now = now();
+                let data = Arc::new(Mutex::new(FileData {
+                    contents: vec![],
+                    accessed: now,
+                    modified: now,
+                    created: now,
+                    usage: fs.usage.clone(),
+                }));
+                fs.nodes
+                    .insert(path.to_path_buf(), Node::File(data.clone()));
+                data
+            }
+            None => return Err(WasiFsError::EntityNotFound),
+        };
+        if options.truncate && options.write {
+            data.lock().unwrap().set_len(0)?;
+        }
+        Ok(Box::new(MemFile {
+            data,
+            position: 0,
+            read: options.read,
+            write: options.write || options.append,
+            append: options.append,
+        }))
+    }
+}
+
+// The files are shared with the open file descriptors, so they can't be
+// serialized with the rest of the state.
+impl Serialize for MemFileSystem {
+    fn serialize<S: ser::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom(
+            "in-memory file systems can not be serialized",
+        ))
+    }
+}
+
+impl<'de> Deserialize<'de> for MemFileSystem {
+    fn deserialize<D: de::Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
+        Err(de::Error::custom(
+            "in-memory file systems can not be deserialized",
+        ))
+    }
+}
+
+/// An open file of a [`MemFileSystem`].
+#[derive(Debug)]
+struct MemFile {
+    data: Arc<Mutex<FileData>>,
+    position: u64,
+    read: bool,
+    write: bool,
+    append: bool,
+}
+
+impl Read for MemFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.read {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "file not opened for reading",
+            ));
+        }
+        let mut data = self.data.lock().unwrap();
+        let start = (self.position as usize).min(data.contents.len());
+        let amt = (&data.contents[start..]).read(buf)?;
+        data.accessed = now();
+        self.position += amt as u64;
+        Ok(amt)
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.write {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "file not opened for writing",
+            ));
+        }
+        let mut data = self.data.lock().unwrap();
+        if self.append {
+            self.position = data.contents.len() as u64;
+        }
+        let start = self.position as usize;
+        let end = start + buf.len();
+        if end > data.contents.len() {
+            data.set_len(end)?;
+        }
+        data.contents[start..end].copy_from_slice(buf);
+        data.modified = now();
+        self.position = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let len = self.data.lock().unwrap().contents.len();
+        let position = match pos {
+            io::SeekFrom::Start(offset) => Some(offset as i64),
+            io::SeekFrom::End(offset) => (len as i64).checked_add(offset),
+            io::SeekFrom::Current(offset) => (self.position as i64).checked_add(offset),
+        };
+        match position {
+            Some(position) if position >= 0 => {
+                self.position = position as u64;
+                Ok(self.position)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+impl Serialize for MemFile {
+    fn serialize<S: ser::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("in-memory files can not be serialized"))
+    }
+}
+
+impl<'de> Deserialize<'de> for MemFile {
+    fn deserialize<D: de::Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
+        Err(de::Error::custom("in-memory files can not be deserialized"))
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for MemFile {
+    fn last_accessed(&self) -> __wasi_timestamp_t {
+        self.data.lock().unwrap().accessed
+    }
+    fn last_modified(&self) -> __wasi_timestamp_t {
+        self.data.lock().unwrap().modified
+    }
+    fn created_time(&self) -> __wasi_timestamp_t {
+        self.data.lock().unwrap().created
+    }
+    fn set_last_accessed(&self, last_accessed: __wasi_timestamp_t) {
+        self.data.lock().unwrap().accessed = last_accessed;
+    }
+    fn set_last_modified(&self, last_modified: __wasi_timestamp_t) {
+        self.data.lock().unwrap().modified = last_modified;
+    }
+    fn size(&self) -> u64 {
+        self.data.lock().unwrap().contents.len() as u64
+    }
+    fn set_len(&mut self, new_size: __wasi_filesize_t) -> Result<(), WasiFsError> {
+        self.data
+            .lock()
+            .unwrap()
+            .set_len(new_size as usize)
+            .map_err(Into::into)
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        let len = self.data.lock().unwrap().contents.len();
+        Ok(len.saturating_sub(self.position as usize))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn open(fs: &MemFileSystem, path: &str) -> Box<dyn WasiFile> {
+        fs.open(
+            Path::new(path),
+            OpenOptions::new().read(true).write(true).create(true),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn files_and_directories() {
+        let fs = MemFileSystem::default();
+        fs.create_dir(Path::new("/tmp")).unwrap();
+        open(&fs, "/tmp/a.txt").write_all(b"hello").unwrap();
+        fs.rename(Path::new("/tmp"), Path::new("/scratch")).unwrap();
+
+        let mut contents = String::new();
+        open(&fs, "/scratch/a.txt")
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "hello");
+        assert_eq!(
+            fs.read_dir(Path::new("/"))
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect::<Vec<_>>(),
+            ["scratch"]
+        );
+        assert!(matches!(
+            fs.remove_dir(Path::new("/scratch")),
+            Err(WasiFsError::UnknownError(__WASI_ENOTEMPTY))
+        ));
+        fs.remove_file(Path::new("/scratch/a.txt")).unwrap();
+        fs.remove_dir(Path::new("/scratch")).unwrap();
+        assert_eq!(fs.size(), 0);
+    }
+
+    #[test]
+    fn quotas() {
+        let fs = MemFileSystem::new(Some(8), Some(2));
+        let mut file = open(&fs, "/a");
+        file.write_all(b"12345678").unwrap();
+        assert_eq!(
+            file.write(b"9").unwrap_err().kind(),
+            io::ErrorKind::WriteZero
+        );
+        assert_eq!(fs.size(), 8);
+
+        fs.create_dir(Path::new("/dir")).unwrap();
+        assert!(matches!(
+            fs.create_dir(Path::new("/other")),
+            Err(WasiFsError::WriteZero)
+        ));
+
+        // The space is released once the file is removed and closed
+        fs.remove_file(Path::new("/a")).unwrap();
+        assert_eq!(fs.size(), 8);
+        drop(file);
+        assert_eq!(fs.size(), 0);
+    }
+}
@@ -15,10 +15,29 @@
 
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
+#[cfg(feature = "archive")]
+mod archive;
 mod builder;
+mod deterministic;
+mod filesystem;
+mod memfs;
+mod overlay;
+mod socket;
+mod terminal;
 mod types;
 
+#[cfg(feature = "archive")]
+pub use self::archive::*;
 pub use self::builder::*;
+pub use self::deterministic::*;
+pub use self::filesystem::*;
+pub use self::memfs::*;
+pub use self::overlay::*;
+pub use self::socket::*;
+#[cfg(unix)]
+pub(crate) use self::terminal::is_terminal;
+pub use self::terminal::restore_terminals;
+pub(crate) use self::terminal::{set_raw_mode, terminal_size};
 pub use self::types::*;
 use crate::syscalls::types::*;
 use generational_arena::Arena;
@@ -31,7 +50,6 @@ use std::{
     fs,
     io::Write,
     path::{Path, PathBuf},
-    time::SystemTime,
 };
 use tracing::debug;
 
@@ -52,6 +70,27 @@ const STDOUT_DEFAULT_RIGHTS: __wasi_rights_t = __WASI_RIGHT_FD_DATASYNC
     | __WASI_RIGHT_FD_FILESTAT_GET
     | __WASI_RIGHT_POLL_FD_READWRITE;
 const STDERR_DEFAULT_RIGHTS: __wasi_rights_t = STDOUT_DEFAULT_RIGHTS;
+const SOCKET_DEFAULT_RIGHTS: __wasi_rights_t = __WASI_RIGHT_FD_READ
+    | __WASI_RIGHT_FD_WRITE
+    | __WASI_RIGHT_FD_FDSTAT_SET_FLAGS
+    | __WASI_RIGHT_FD_FILESTAT_GET
+    | __WASI_RIGHT_POLL_FD_READWRITE
+    | __WASI_RIGHT_SOCK_SHUTDOWN;
+/// The rights of the files given to the module with an fd, which it can
+/// use but not open other files relative to
+const FILE_DEFAULT_RIGHTS: __wasi_rights_t = __WASI_RIGHT_FD_DATASYNC
+    | __WASI_RIGHT_FD_READ
+    | __WASI_RIGHT_FD_SEEK
+    | __WASI_RIGHT_FD_FDSTAT_SET_FLAGS
+    | __WASI_RIGHT_FD_SYNC
+    | __WASI_RIGHT_FD_TELL
+    | __WASI_RIGHT_FD_WRITE
+    | __WASI_RIGHT_FD_ADVISE
+    | __WASI_RIGHT_FD_ALLOCATE
+    | __WASI_RIGHT_FD_FILESTAT_GET
+    | __WASI_RIGHT_FD_FILESTAT_SET_SIZE
+    | __WASI_RIGHT_FD_FILESTAT_SET_TIMES
+    | __WASI_RIGHT_POLL_FD_READWRITE;
 
 /// A completely aribtrary "big enough" number used as the upper limit for
 /// the number of symlinks that can be traversed when resolving a path
@@ -117,7 +156,7 @@ pub enum Kind {
     },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Fd {
     pub rights: __wasi_rights_t,
     pub rights_inheriting: __wasi_rights_t,
@@ -163,12 +202,43 @@ pub struct WasiFs {
     inode_counter: Cell<u64>,
     /// for fds still open after the file has been deleted
     pub orphan_fds: HashMap<Inode, InodeVal>,
+    /// The mounted file systems, by index
+    file_systems: Vec<Box<dyn FileSystem>>,
+}
+
+/// Gets the file type of a standard device: the one of the host file
+/// backing it if any, a character device otherwise.
+fn std_dev_filetype(handle: &dyn WasiFile) -> __wasi_filetype_t {
+    #[cfg(unix)]
+    {
+        if let Some(host_fd) = handle.get_raw_fd() {
+            return host_file_type(host_fd);
+        }
+    }
+    let _ = handle;
+    __WASI_FILETYPE_CHARACTER_DEVICE
+}
+
+/// Whether a standard device is backed by a terminal of the host.
+fn std_dev_is_terminal(handle: &dyn WasiFile) -> bool {
+    #[cfg(unix)]
+    {
+        if let Some(host_fd) = handle.get_raw_fd() {
+            return is_terminal(host_fd);
+        }
+    }
+    let _ = handle;
+    false
 }
 
 impl WasiFs {
     /// Created for the builder API. like `new` but with more information
-    pub(crate) fn new_with_preopen(preopens: &[PreopenedDir]) -> Result<Self, String> {
+    pub(crate) fn new_with_preopen(
+        preopens: &[PreopenedDir],
+        file_systems: Vec<Box<dyn FileSystem>>,
+    ) -> Result<Self, String> {
         let (mut wasi_fs, root_inode) = Self::new_init()?;
+        wasi_fs.file_systems = file_systems;
 
         for PreopenedDir {
             path,
@@ -176,6 +246,7 @@ impl WasiFs {
             read,
             write,
             create,
+            rights: rights_mask,
         } in preopens
         {
             debug!(
@@ -183,7 +254,8 @@ impl WasiFs {
                 &path.to_string_lossy(),
                 &alias
             );
-            let cur_dir_metadata = path.metadata().map_err(|e| {
+            let (file_system, fs_path) = wasi_fs.file_system(path);
+            let cur_dir_metadata = file_system.metadata(&fs_path).map_err(|e| {
                 format!(
                     "Could not get metadata for file {:?}: {}",
                     path,
@@ -244,7 +316,7 @@ impl WasiFs {
                         | __WASI_RIGHT_PATH_RENAME_TARGET;
                 }
 
-                rights
+                rights & rights_mask.unwrap_or(!0)
             };
             let inode = if let Some(alias) = &alias {
                 wasi_fs.create_inode(kind, true, alias.clone())
@@ -305,6 +377,7 @@ impl WasiFs {
             next_fd: Cell::new(3),
             inode_counter: Cell::new(1024),
             orphan_fds: HashMap::new(),
+            file_systems: Vec::new(),
         };
         wasi_fs.create_stdin();
         wasi_fs.create_stdout();
@@ -508,7 +581,7 @@ impl WasiFs {
                 let kind = Kind::File {
                     handle: Some(file),
                     path: PathBuf::from(""),
-                    fd: Some(self.next_fd.get()),
+                    fd: Some(self.lowest_free_fd()),
                 };
 
                 let inode = self
@@ -541,6 +614,7 @@ impl WasiFs {
         fd: __wasi_fd_t,
         file: Box<dyn WasiFile>,
     ) -> Result<Option<Box<dyn WasiFile>>, WasiFsError> {
+        let filetype = std_dev_filetype(file.as_ref());
         let mut ret = Some(file);
         match fd {
             __WASI_STDIN_FILENO => {
@@ -562,9 +636,13 @@ impl WasiFs {
                     }
                     _ => return Err(WasiFsError::NotAFile),
                 }
+                return Ok(ret);
             }
         }
 
+        // The standard devices take the file type of their new backing
+        let inode = self.get_fd(fd).map_err(WasiFsError::from_wasi_err)?.inode;
+        self.inodes[inode].stat.st_filetype = filetype;
         Ok(ret)
     }
 
@@ -657,65 +735,55 @@ impl WasiFs {
                                 cd.push(component);
                                 cd
                             };
-                            let metadata = file.symlink_metadata().ok().ok_or(__WASI_EINVAL)?;
-                            let file_type = metadata.file_type();
+                            let (file_system, fs_path) = self.file_system(&file);
+                            let metadata = file_system
+                                .symlink_metadata(&fs_path)
+                                .ok()
+                                .ok_or(__WASI_EINVAL)?;
                             // we want to insert newly opened dirs and files, but not transient symlinks
                             // TODO: explain why (think about this deeply when well rested)
                             let should_insert;
 
-                            let kind = if file_type.is_dir() {
-                                should_insert = true;
-                                // load DIR
-                                Kind::Dir {
-                                    parent: Some(cur_inode),
-                                    path: file.clone(),
-                                    entries: Default::default(),
+                            let kind = match metadata.filetype {
+                                __WASI_FILETYPE_DIRECTORY => {
+                                    should_insert = true;
+                                    // load DIR
+                                    Kind::Dir {
+                                        parent: Some(cur_inode),
+                                        path: file.clone(),
+                                        entries: Default::default(),
+                                    }
                                 }
-                            } else if file_type.is_file() {
-                                should_insert = true;
-                                // load file
-                                Kind::File {
-                                    handle: None,
-                                    path: file.clone(),
-                                    fd: None,
+                                __WASI_FILETYPE_REGULAR_FILE => {
+                                    should_insert = true;
+                                    // load file
+                                    Kind::File {
+                                        handle: None,
+                                        path: file.clone(),
+                                        fd: None,
+                                    }
                                 }
-                            } else if file_type.is_symlink() {
-                                should_insert = false;
-                                let link_value = file.read_link().ok().ok_or(__WASI_EIO)?;
-                                debug!("attempting to decompose path {:?}", link_value);
-
-                                let (pre_open_dir_fd, relative_path) = if link_value.is_relative() {
-                                    self.path_into_pre_open_and_relative_path(&file)?
-                                } else {
-                                    unimplemented!("Absolute symlinks are not yet supported");
-                                };
-                                loop_for_symlink = true;
-                                symlink_count += 1;
-                                Kind::Symlink {
-                                    base_po_dir: pre_open_dir_fd,
-                                    path_to_symlink: relative_path.to_owned(),
-                                    relative_path: link_value,
+                                __WASI_FILETYPE_SYMBOLIC_LINK => {
+                                    should_insert = false;
+                                    let link_value =
+                                        file_system.read_link(&fs_path).ok().ok_or(__WASI_EIO)?;
+                                    debug!("attempting to decompose path {:?}", link_value);
+
+                                    if !link_value.is_relative() {
+                                        unimplemented!("Absolute symlinks are not yet supported");
+                                    }
+                                    let (pre_open_dir_fd, relative_path) =
+                                        self.path_into_pre_open_and_relative_path(&file)?;
+                                    loop_for_symlink = true;
+                                    symlink_count += 1;
+                                    Kind::Symlink {
+                                        base_po_dir: pre_open_dir_fd,
+                                        path_to_symlink: relative_path.to_owned(),
+                                        relative_path: link_value,
+                                    }
                                 }
-                            } else {
-                                #[cfg(unix)]
-                                {
-                                    use std::os::unix::fs::FileTypeExt;
-                                    let file_type: __wasi_filetype_t = if file_type.is_char_device()
-                                    {
-                                        __WASI_FILETYPE_CHARACTER_DEVICE
-                                    } else if file_type.is_block_device() {
-                                        __WASI_FILETYPE_BLOCK_DEVICE
-                                    } else if file_type.is_fifo() {
-                                        // FIFO doesn't seem to fit any other type, so unknown
-                                        __WASI_FILETYPE_UNKNOWN
-                                    } else if file_type.is_socket() {
-                                        // TODO: how do we know if it's a `__WASI_FILETYPE_SOCKET_STREAM` or
-                                        // a `__WASI_FILETYPE_SOCKET_DGRAM`?
-                                        __WASI_FILETYPE_SOCKET_STREAM
-                                    } else {
-                                        unimplemented!("state::get_inode_at_path unknown file type: not file, directory, symlink, char device, block device, fifo, or socket");
-                                    };
-
+                                // special files, like devices
+                                file_type => {
                                     let kind = Kind::File {
                                         handle: None,
                                         path: file.clone(),
@@ -746,8 +814,6 @@ impl WasiFs {
                                     // perhaps just continue with symlink resolution and return at the end
                                     return Ok(new_inode);
                                 }
-                                #[cfg(not(unix))]
-                                unimplemented!("state::get_inode_at_path unknown file type: not file, directory, or symlink");
                             };
 
                             let new_inode =
@@ -981,29 +1047,52 @@ impl WasiFs {
         Ok(self.inodes[fd.inode].stat)
     }
 
+    /// Gets the rights reported for a standard device. Programs tell if
+    /// one is a terminal by looking for a character device which can't
+    /// seek, so the devices which aren't terminals are reported seekable.
+    fn std_dev_rights(
+        &self,
+        fd: __wasi_fd_t,
+        rights: __wasi_rights_t,
+    ) -> Result<__wasi_rights_t, __wasi_errno_t> {
+        let inode = self.get_fd(fd)?.inode;
+        let is_terminal = match &self.inodes[inode].kind {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => std_dev_is_terminal(handle.as_ref()),
+            _ => false,
+        };
+        Ok(if is_terminal {
+            rights
+        } else {
+            rights | __WASI_RIGHT_FD_SEEK | __WASI_RIGHT_FD_TELL
+        })
+    }
+
     pub fn fdstat(&self, fd: __wasi_fd_t) -> Result<__wasi_fdstat_t, __wasi_errno_t> {
         match fd {
             __WASI_STDIN_FILENO => {
                 return Ok(__wasi_fdstat_t {
-                    fs_filetype: __WASI_FILETYPE_CHARACTER_DEVICE,
+                    fs_filetype: self.filestat_fd(fd)?.st_filetype,
                     fs_flags: 0,
-                    fs_rights_base: STDIN_DEFAULT_RIGHTS,
+                    fs_rights_base: self.std_dev_rights(fd, STDIN_DEFAULT_RIGHTS)?,
                     fs_rights_inheriting: 0,
                 })
             }
             __WASI_STDOUT_FILENO => {
                 return Ok(__wasi_fdstat_t {
-                    fs_filetype: __WASI_FILETYPE_CHARACTER_DEVICE,
+                    fs_filetype: self.filestat_fd(fd)?.st_filetype,
                     fs_flags: __WASI_FDFLAG_APPEND,
-                    fs_rights_base: STDOUT_DEFAULT_RIGHTS,
+                    fs_rights_base: self.std_dev_rights(fd, STDOUT_DEFAULT_RIGHTS)?,
                     fs_rights_inheriting: 0,
                 })
             }
             __WASI_STDERR_FILENO => {
                 return Ok(__wasi_fdstat_t {
-                    fs_filetype: __WASI_FILETYPE_CHARACTER_DEVICE,
+                    fs_filetype: self.filestat_fd(fd)?.st_filetype,
                     fs_flags: __WASI_FDFLAG_APPEND,
-                    fs_rights_base: STDERR_DEFAULT_RIGHTS,
+                    fs_rights_base: self.std_dev_rights(fd, STDERR_DEFAULT_RIGHTS)?,
                     fs_rights_inheriting: 0,
                 })
             }
@@ -1023,7 +1112,11 @@ impl WasiFs {
         debug!("fdstat: {:?}", fd);
 
         Ok(__wasi_fdstat_t {
-            fs_filetype: match self.inodes[fd.inode].kind {
+            fs_filetype: match &self.inodes[fd.inode].kind {
+                Kind::File {
+                    handle: Some(handle),
+                    ..
+                } if handle.downcast_ref::<Socket>().is_some() => __WASI_FILETYPE_SOCKET_STREAM,
                 Kind::File { .. } => __WASI_FILETYPE_REGULAR_FILE,
                 Kind::Dir { .. } => __WASI_FILETYPE_DIRECTORY,
                 Kind::Symlink { .. } => __WASI_FILETYPE_SYMBOLIC_LINK,
@@ -1145,9 +1238,8 @@ impl WasiFs {
         open_flags: u16,
         inode: Inode,
     ) -> Result<__wasi_fd_t, __wasi_errno_t> {
-        let idx = self.next_fd.get();
-        self.next_fd.set(idx + 1);
-        self.fd_map.insert(
+        let idx = self.lowest_free_fd();
+        self.insert_fd(
             idx,
             Fd {
                 rights,
@@ -1161,6 +1253,176 @@ impl WasiFs {
         Ok(idx)
     }
 
+    /// Gets the lowest file descriptor which isn't open, which is the one
+    /// given to the next opened file, as in POSIX.
+    ///
+    /// The file descriptors of the standard devices are never given to
+    /// other files, even once closed, since the syscalls handle them apart.
+    fn lowest_free_fd(&self) -> __wasi_fd_t {
+        (__WASI_STDERR_FILENO + 1..)
+            .find(|fd| !self.fd_map.contains_key(fd))
+            .expect("all the file descriptors are open")
+    }
+
+    fn insert_fd(&mut self, fd: __wasi_fd_t, entry: Fd) {
+        self.fd_map.insert(fd, entry);
+        // `next_fd` stays after all the file descriptors ever opened, for
+        // `create_passed_fd`
+        if fd >= self.next_fd.get() {
+            self.next_fd.set(fd + 1);
+        }
+    }
+
+    /// Duplicates the file descriptor `fd` as the lowest free one, as `dup`
+    /// does, returning it.
+    ///
+    /// The duplicate has the same rights, and is preopened if `fd` is. Its
+    /// offset starts at the one of `fd` but moves on its own.
+    pub fn dup_fd(&mut self, fd: __wasi_fd_t) -> Result<__wasi_fd_t, __wasi_errno_t> {
+        let entry = self.get_fd(fd)?.clone();
+        let idx = self.lowest_free_fd();
+        self.insert_fd(idx, entry);
+        Ok(idx)
+    }
+
+    /// Moves the file descriptor `from` to `to`, closing `to` first if it's
+    /// open, as `dup2` followed by closing `from` does.
+    ///
+    /// Only a file with the rights the standard device needs can be moved
+    /// to the file descriptor of a standard device, as its rights aren't
+    /// checked when it's used.
+    pub fn renumber_fd(
+        &mut self,
+        from: __wasi_fd_t,
+        to: __wasi_fd_t,
+    ) -> Result<(), __wasi_errno_t> {
+        let entry = self.get_fd(from)?;
+        if from == to {
+            return Ok(());
+        }
+        if to <= __WASI_STDERR_FILENO {
+            if !matches!(self.inodes[entry.inode].kind, Kind::File { .. }) {
+                return Err(__WASI_EBADF);
+            }
+            let right = if to == __WASI_STDIN_FILENO {
+                __WASI_RIGHT_FD_READ
+            } else {
+                __WASI_RIGHT_FD_WRITE
+            };
+            if entry.rights & right == 0 {
+                return Err(__WASI_ENOTCAPABLE);
+            }
+        }
+        if self.fd_map.contains_key(&to) {
+            self.close_fd(to)?;
+        }
+        let entry = self.fd_map.remove(&from).unwrap();
+        self.insert_fd(to, entry);
+        if let Some(i) = self.preopen_fds.iter().position(|po_fd| *po_fd == from) {
+            // closing `to` may have made `from` the preopen of its directory
+            if self.preopen_fds.contains(&to) {
+                self.preopen_fds.remove(i);
+            } else {
+                self.preopen_fds[i] = to;
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates a file descriptor for a socket.
+    pub(crate) fn create_socket_fd(
+        &mut self,
+        socket: Socket,
+        flags: __wasi_fdflags_t,
+    ) -> Result<__wasi_fd_t, __wasi_errno_t> {
+        let kind = Kind::File {
+            handle: Some(Box::new(socket)),
+            path: PathBuf::new(),
+            fd: None,
+        };
+        let stat = __wasi_filestat_t {
+            st_filetype: __WASI_FILETYPE_SOCKET_STREAM,
+            ..__wasi_filestat_t::default()
+        };
+        let inode = self.create_inode_with_stat(kind, false, "socket".to_string(), stat);
+        self.create_fd(
+            SOCKET_DEFAULT_RIGHTS,
+            SOCKET_DEFAULT_RIGHTS,
+            flags,
+            0,
+            inode,
+        )
+    }
+
+    /// Gives the module an already open file, pipe or socket as `fd`.
+    ///
+    /// `fd` must come after the standard streams and the preopened
+    /// directories.
+    pub(crate) fn create_passed_fd(
+        &mut self,
+        fd: __wasi_fd_t,
+        handle: Box<dyn WasiFile>,
+    ) -> Result<(), WasiFsError> {
+        if fd < self.next_fd.get() || self.fd_map.contains_key(&fd) {
+            return Err(WasiFsError::AlreadyExists);
+        }
+        let (filetype, rights) = if handle.downcast_ref::<Socket>().is_some() {
+            (__WASI_FILETYPE_SOCKET_STREAM, SOCKET_DEFAULT_RIGHTS)
+        } else {
+            match std_dev_filetype(handle.as_ref()) {
+                filetype @ __WASI_FILETYPE_SOCKET_STREAM => (filetype, SOCKET_DEFAULT_RIGHTS),
+                filetype => (filetype, FILE_DEFAULT_RIGHTS),
+            }
+        };
+        let stat = __wasi_filestat_t {
+            st_filetype: filetype,
+            ..__wasi_filestat_t::default()
+        };
+        let kind = Kind::File {
+            handle: Some(handle),
+            path: PathBuf::new(),
+            fd: None,
+        };
+        let inode = self.create_inode_with_stat(kind, false, format!("fd{}", fd), stat);
+        self.fd_map.insert(
+            fd,
+            Fd {
+                rights,
+                rights_inheriting: 0,
+                flags: 0,
+                offset: 0,
+                open_flags: 0,
+                inode,
+            },
+        );
+        // the file descriptors opened by the module come after this one
+        self.next_fd.set(fd + 1);
+        Ok(())
+    }
+
+    /// Gets the socket behind a file descriptor.
+    pub(crate) fn get_socket_mut(
+        &mut self,
+        fd: __wasi_fd_t,
+    ) -> Result<&mut Socket, __wasi_errno_t> {
+        match &mut self.get_inodeval_mut(fd)?.kind {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => handle.downcast_mut::<Socket>().ok_or(__WASI_ENOTSOCK),
+            _ => Err(__WASI_ENOTSOCK),
+        }
+    }
+
+    /// Gets the file system a file or directory is on, and its path within
+    /// that file system.
+    pub(crate) fn file_system(&self, path: &Path) -> (&dyn FileSystem, PathBuf) {
+        match split_mount_path(path) {
+            (Some(index), fs_path) => (self.file_systems[index].as_ref(), fs_path),
+            (None, host_path) => (&HOST_FILE_SYSTEM, host_path),
+        }
+    }
+
     /// Low level function to remove an inode, that is it deletes the WASI FS's
     /// knowledge of a file.
     ///
@@ -1228,7 +1490,7 @@ impl WasiFs {
         fd_flags: __wasi_fdflags_t,
     ) {
         let stat = __wasi_filestat_t {
-            st_filetype: __WASI_FILETYPE_CHARACTER_DEVICE,
+            st_filetype: std_dev_filetype(handle.as_ref()),
             st_ino: self.get_next_inode_index(),
             ..__wasi_filestat_t::default()
         };
@@ -1271,9 +1533,15 @@ impl WasiFs {
                         ..__wasi_filestat_t::default()
                     })
                 }
-                None => path.metadata().ok()?,
+                None => {
+                    let (file_system, fs_path) = self.file_system(path);
+                    file_system.metadata(&fs_path).ok()?
+                }
             },
-            Kind::Dir { path, .. } => path.metadata().ok()?,
+            Kind::Dir { path, .. } => {
+                let (file_system, fs_path) = self.file_system(path);
+                file_system.metadata(&fs_path).ok()?
+            }
             Kind::Symlink {
                 base_po_dir,
                 path_to_symlink,
@@ -1283,7 +1551,8 @@ impl WasiFs {
                 let base_po_inode_v = &self.inodes[*base_po_inode];
                 match &base_po_inode_v.kind {
                     Kind::Root { .. } => {
-                        path_to_symlink.clone().symlink_metadata().ok()?
+                        let (file_system, fs_path) = self.file_system(path_to_symlink);
+                        file_system.symlink_metadata(&fs_path).ok()?
                     }
                     Kind::Dir { path, .. } => {
                         let mut real_path = path.clone();
@@ -1294,7 +1563,8 @@ impl WasiFs {
                         // TODO: adjust size of symlink, too
                         //      for all paths adjusted think about this
                         real_path.push(path_to_symlink);
-                        real_path.symlink_metadata().ok()?
+                        let (file_system, fs_path) = self.file_system(&real_path);
+                        file_system.symlink_metadata(&fs_path).ok()?
                     }
                     // if this triggers, there's a bug in the symlink code
                     _ => unreachable!("Symlink pointing to something that's not a directory as its base preopened directory"),
@@ -1302,40 +1572,28 @@ impl WasiFs {
             }
             _ => return None,
         };
-        Some(__wasi_filestat_t {
-            st_filetype: host_file_type_to_wasi_file_type(md.file_type()),
-            st_size: md.len(),
-            st_atim: md
-                .accessed()
-                .ok()?
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .ok()?
-                .as_nanos() as u64,
-            st_mtim: md
-                .modified()
-                .ok()?
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .ok()?
-                .as_nanos() as u64,
-            st_ctim: md
-                .created()
-                .ok()
-                .and_then(|ct| ct.duration_since(SystemTime::UNIX_EPOCH).ok())
-                .map(|ct| ct.as_nanos() as u64)
-                .unwrap_or(0),
-            ..__wasi_filestat_t::default()
-        })
+        Some(md.to_filestat())
     }
 
     /// Closes an open FD, handling all details such as FD being preopen
+    ///
+    /// The file or directory stays open while other FDs refer to it, either
+    /// duplicated from this one or opened again with `path_open`.
     pub(crate) fn close_fd(&mut self, fd: __wasi_fd_t) -> Result<(), __wasi_errno_t> {
-        let inodeval_mut = self.get_inodeval_mut(fd)?;
-        let is_preopened = inodeval_mut.is_preopened;
-
-        match &mut inodeval_mut.kind {
+        let inode = self.get_fd(fd)?.inode;
+        let other_fd = self
+            .fd_map
+            .iter()
+            .filter(|(other_fd, entry)| **other_fd != fd && entry.inode == inode)
+            .map(|(other_fd, _)| *other_fd)
+            .min();
+        let preopen_idx = self.preopen_fds.iter().position(|po_fd| *po_fd == fd);
+
+        match &mut self.get_inodeval_mut(fd)?.kind {
             Kind::File { ref mut handle, .. } => {
-                let mut empty_handle = None;
-                std::mem::swap(handle, &mut empty_handle);
+                if other_fd.is_none() {
+                    *handle = None;
+                }
             }
             Kind::Dir { parent, path, .. } => {
                 debug!("Closing dir {:?}", &path);
@@ -1345,29 +1603,16 @@ impl WasiFs {
                     .to_string_lossy()
                     .to_string();
                 if let Some(p) = *parent {
-                    match &mut self.inodes[p].kind {
-                        Kind::Dir { entries, .. } | Kind::Root { entries } => {
-                            self.fd_map.remove(&fd).unwrap();
-                            if is_preopened {
-                                let mut idx = None;
-                                for (i, po_fd) in self.preopen_fds.iter().enumerate() {
-                                    if *po_fd == fd {
-                                        idx = Some(i);
-                                        break;
-                                    }
-                                }
-                                if let Some(i) = idx {
-                                    // only remove entry properly if this is the original preopen FD
-                                    // calling `path_open` can give you an fd to the same inode as a preopen fd
-                                    entries.remove(&key);
-                                    self.preopen_fds.remove(i);
-                                    // Maybe recursively closes fds if original preopen?
-                                }
+                    // only remove entry properly if this is the last FD of the preopened directory
+                    if preopen_idx.is_some() && other_fd.is_none() {
+                        match &mut self.inodes[p].kind {
+                            Kind::Dir { entries, .. } | Kind::Root { entries } => {
+                                entries.remove(&key);
                             }
+                            _ => unreachable!(
+                                "Fatal internal logic error, directory's parent is not a directory"
+                            ),
                         }
-                        _ => unreachable!(
-                            "Fatal internal logic error, directory's parent is not a directory"
-                        ),
                     }
                 } else {
                     // this shouldn't be possible anymore due to Root
@@ -1379,6 +1624,16 @@ impl WasiFs {
             Kind::Symlink { .. } | Kind::Buffer { .. } => return Err(__WASI_EINVAL),
         }
 
+        self.fd_map.remove(&fd);
+        if let Some(i) = preopen_idx {
+            match other_fd {
+                // the directory stays preopened through its other FD
+                Some(other_fd) => self.preopen_fds[i] = other_fd,
+                None => {
+                    self.preopen_fds.remove(i);
+                }
+            }
+        }
         Ok(())
     }
 }
@@ -1416,6 +1671,12 @@ pub struct WasiState {
     pub fs: WasiFs,
     pub args: Vec<Vec<u8>>,
     pub envs: Vec<Vec<u8>>,
+    pub network: NetworkPolicy,
+    /// The clock read by the module instead of the clocks of the host
+    pub clock: Option<FixedClock>,
+    /// The random numbers given to the module instead of the ones of the
+    /// host
+    pub random: Option<SeededRandom>,
 }
 
 impl WasiState {
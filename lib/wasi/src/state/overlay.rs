@@ -0,0 +1,365 @@
+//! A file system layering a writable file system over another one, eg. a
+//! scratch directory over a read-only image.
+use crate::state::{DirEntry, FileSystem, Metadata, OpenOptions, WasiFile, WasiFsError};
+use crate::syscalls::types::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The file of `upper` listing the paths removed from `lower`, so they
+/// stay removed when the same `upper` is used again. The module can't
+/// see it.
+const WHITEOUTS_PATH: &str = "/.wasmer-whiteouts";
+
+/// A file system merging an `upper` file system over a `lower` one.
+///
+/// Files are looked up in `upper` first, then in `lower`. All the changes
+/// are made in `upper`: files of `lower` are copied to `upper` before being
+/// written, and the files removed from `lower` are only hidden.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverlayFileSystem {
+    lower: Box<dyn FileSystem>,
+    upper: Box<dyn FileSystem>,
+    /// The paths removed from `lower`, hiding everything below them as well
+    whiteouts: Mutex<HashSet<PathBuf>>,
+}
+
+impl OverlayFileSystem {
+    /// Creates the overlay, with the files removed from `lower` by the
+    /// previous uses of `upper` hidden.
+    pub fn new<L: FileSystem, U: FileSystem>(lower: L, upper: U) -> Result<Self, WasiFsError> {
+        let whiteouts = match upper.open(Path::new(WHITEOUTS_PATH), OpenOptions::new().read(true)) {
+            Ok(mut file) => {
+                let mut bytes = vec![];
+                file.read_to_end(&mut bytes)?;
+                bincode::deserialize(&bytes).map_err(|_| WasiFsError::InvalidData)?
+            }
+            Err(WasiFsError::EntityNotFound) => HashSet::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            lower: Box::new(lower),
+            upper: Box::new(upper),
+            whiteouts: Mutex::new(whiteouts),
+        })
+    }
+
+    /// Whether `path` was removed from `lower`, or one of its parents was.
+    fn is_hidden(&self, path: &Path) -> bool {
+        let whiteouts = self.whiteouts.lock().unwrap();
+        path.ancestors()
+            .any(|ancestor| whiteouts.contains(ancestor))
+    }
+
+    /// Hides `path` of `lower`, saving the whiteouts to `upper`.
+    fn hide(&self, path: &Path) -> Result<(), WasiFsError> {
+        let mut whiteouts = self.whiteouts.lock().unwrap();
+        whiteouts.insert(path.to_path_buf());
+        let bytes = bincode::serialize(&*whiteouts).map_err(|_| WasiFsError::IOError)?;
+        let mut file = self.upper.open(
+            Path::new(WHITEOUTS_PATH),
+            OpenOptions::new().write(true).create(true).truncate(true),
+        )?;
+        file.write_all(&bytes)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Fails with `error` if `path` is the file of the whiteouts.
+    fn check_visible(path: &Path, error: WasiFsError) -> Result<(), WasiFsError> {
+        if path == Path::new(WHITEOUTS_PATH) {
+            Err(error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Gets the metadata of `path` in `lower`, if it's not hidden.
+    fn lower_metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        if self.is_hidden(path) {
+            return Err(WasiFsError::EntityNotFound);
+        }
+        self.lower.symlink_metadata(path)
+    }
+
+    fn in_upper(&self, path: &Path) -> bool {
+        self.upper.symlink_metadata(path).is_ok()
+    }
+
+    fn in_lower(&self, path: &Path) -> bool {
+        self.lower_metadata(path).is_ok()
+    }
+
+    /// Creates in `upper` the directories `dir` and its parents which are
+    /// only in `lower`.
+    fn copy_up_dirs(&self, dir: &Path) -> Result<(), WasiFsError> {
+        let mut dirs = dir.ancestors().collect::<Vec<_>>();
+        dirs.reverse();
+        for dir in dirs {
+            if self.in_upper(dir) {
+                continue;
+            }
+            if !self.lower_metadata(dir)?.is_dir() {
+                return Err(WasiFsError::BaseNotDirectory);
+            }
+            self.upper.create_dir(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Copies the file at `path` to `upper`, if it's only in `lower`.
+    /// Symlinks are copied as the file they point to.
+    fn copy_up(&self, path: &Path) -> Result<(), WasiFsError> {
+        if self.in_upper(path) {
+            return Ok(());
+        }
+        let metadata = self.lower_metadata(path)?;
+        if let Some(parent) = path.parent() {
+            self.copy_up_dirs(parent)?;
+        }
+        if metadata.is_dir() {
+            return self.upper.create_dir(path);
+        }
+        let mut from = self.lower.open(path, OpenOptions::new().read(true))?;
+        let mut to = self
+            .upper
+            .open(path, OpenOptions::new().write(true).create_new(true))?;
+        io::copy(&mut from, &mut to)?;
+        to.flush()?;
+        Ok(())
+    }
+}
+
+#[typetag::serde]
+impl FileSystem for OverlayFileSystem {
+    fn metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        Self::check_visible(path, WasiFsError::EntityNotFound)?;
+        match self.upper.metadata(path) {
+            Err(WasiFsError::EntityNotFound) if !self.is_hidden(path) => self.lower.metadata(path),
+            result => result,
+        }
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        Self::check_visible(path, WasiFsError::EntityNotFound)?;
+        match self.upper.symlink_metadata(path) {
+            Err(WasiFsError::EntityNotFound) => self.lower_metadata(path),
+            result => result,
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        let upper = match self.upper.read_dir(path) {
+            Ok(entries) => Some(entries),
+            Err(WasiFsError::EntityNotFound) => None,
+            Err(e) => return Err(e),
+        };
+        let lower = match self.lower_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => Some(self.lower.read_dir(path)?),
+            _ => None,
+        };
+        let (mut entries, lower) = match (upper, lower) {
+            (None, None) => return Err(WasiFsError::EntityNotFound),
+            (Some(upper), lower) => (upper, lower.unwrap_or_default()),
+            (None, Some(lower)) => (vec![], lower),
+        };
+        for entry in lower {
+            let entry_path = path.join(&entry.name);
+            if !self.is_hidden(&entry_path) && !entries.iter().any(|e| e.name == entry.name) {
+                entries.push(entry);
+            }
+        }
+        entries.retain(|entry| {
+            Self::check_visible(&path.join(&entry.name), WasiFsError::EntityNotFound).is_ok()
+        });
+        Ok(entries)
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, WasiFsError> {
+        Self::check_visible(path, WasiFsError::EntityNotFound)?;
+        match self.upper.read_link(path) {
+            Err(WasiFsError::EntityNotFound) if !self.is_hidden(path) => self.lower.read_link(path),
+            result => result,
+        }
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        Self::check_visible(path, WasiFsError::PermissionDenied)?;
+        if self.in_upper(path) || self.in_lower(path) {
+            return Err(WasiFsError::AlreadyExists);
+        }
+        if let Some(parent) = path.parent() {
+            self.copy_up_dirs(parent)?;
+        }
+        self.upper.create_dir(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<(), WasiFsError> {
+        if !self.read_dir(path)?.is_empty() {
+            return Err(WasiFsError::UnknownError(__WASI_ENOTEMPTY));
+        }
+        if self.in_upper(path) {
+            self.upper.remove_dir(path)?;
+        }
+        if self.in_lower(path) {
+            self.hide(path)?;
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), WasiFsError> {
+        Self::check_visible(path, WasiFsError::EntityNotFound)?;
+        let (in_upper, in_lower) = (self.in_upper(path), self.in_lower(path));
+        if !in_upper && !in_lower {
+            return Err(WasiFsError::EntityNotFound);
+        }
+        if in_upper {
+            self.upper.remove_file(path)?;
+        }
+        if in_lower {
+            self.hide(path)?;
+        }
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), WasiFsError> {
+        Self::check_visible(from, WasiFsError::EntityNotFound)?;
+        Self::check_visible(to, WasiFsError::PermissionDenied)?;
+        let in_lower = self.in_lower(from);
+        // Like other overlay file systems, directories of `lower` aren't
+        // moved, since all their files would have to be copied
+        if in_lower && self.lower_metadata(from)?.is_dir() {
+            return Err(WasiFsError::UnknownError(__WASI_EXDEV));
+        }
+        self.copy_up(from)?;
+        if let Some(parent) = to.parent() {
+            self.copy_up_dirs(parent)?;
+        }
+        self.upper.rename(from, to)?;
+        if in_lower {
+            self.hide(from)?;
+        }
+        Ok(())
+    }
+
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        if options.create || options.create_new {
+            Self::check_visible(path, WasiFsError::PermissionDenied)?;
+        } else {
+            Self::check_visible(path, WasiFsError::EntityNotFound)?;
+        }
+        if options.create_new && (self.in_upper(path) || self.in_lower(path)) {
+            return Err(WasiFsError::AlreadyExists);
+        }
+        let writes = options.write || options.append || options.truncate;
+        if writes || options.create || options.create_new {
+            match self.copy_up(path) {
+                // the file is created in `upper`
+                Err(WasiFsError::EntityNotFound) if options.create || options.create_new => {
+                    if let Some(parent) = path.parent() {
+                        self.copy_up_dirs(parent)?;
+                    }
+                }
+                result => result?,
+            }
+            return self.upper.open(path, options);
+        }
+        match self.upper.open(path, options) {
+            Err(WasiFsError::EntityNotFound) if !self.is_hidden(path) => {
+                self.lower.open(path, options)
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::HostFileSystem;
+    use std::fs;
+    use std::io::Read;
+
+    #[test]
+    fn overlay() {
+        let dir = std::env::temp_dir().join(format!("wasmer-wasi-overlay-{}", std::process::id()));
+        let (lower_dir, upper_dir) = (dir.join("lower"), dir.join("upper"));
+        fs::create_dir_all(lower_dir.join("etc")).unwrap();
+        fs::create_dir_all(&upper_dir).unwrap();
+        fs::write(lower_dir.join("etc/config"), "lower").unwrap();
+        fs::write(lower_dir.join("removed"), "").unwrap();
+
+        let new_overlay = || {
+            OverlayFileSystem::new(
+                HostFileSystem::new(&lower_dir),
+                HostFileSystem::new(&upper_dir),
+            )
+            .unwrap()
+        };
+        let overlay = new_overlay();
+        let read = |path: &str| {
+            let mut contents = String::new();
+            overlay
+                .open(Path::new(path), OpenOptions::new().read(true))
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            contents
+        };
+        assert_eq!(read("/etc/config"), "lower");
+
+        let mut file = overlay
+            .open(
+                Path::new("/etc/config"),
+                OpenOptions::new().write(true).append(true),
+            )
+            .unwrap();
+        file.write_all(b" and upper").unwrap();
+        drop(file);
+        assert_eq!(read("/etc/config"), "lower and upper");
+        assert_eq!(
+            fs::read_to_string(lower_dir.join("etc/config")).unwrap(),
+            "lower"
+        );
+
+        overlay.remove_file(Path::new("/removed")).unwrap();
+        assert!(lower_dir.join("removed").exists());
+        assert!(matches!(
+            overlay.metadata(Path::new("/removed")),
+            Err(WasiFsError::EntityNotFound)
+        ));
+
+        overlay.create_dir(Path::new("/tmp")).unwrap();
+        let mut names = overlay
+            .read_dir(Path::new("/"))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["etc", "tmp"]);
+        assert!(!lower_dir.join("tmp").exists());
+
+        // The removed files stay removed when `upper` is used again
+        drop(overlay);
+        let overlay = new_overlay();
+        assert!(matches!(
+            overlay.metadata(Path::new("/removed")),
+            Err(WasiFsError::EntityNotFound)
+        ));
+        assert!(matches!(
+            overlay.metadata(Path::new(WHITEOUTS_PATH)),
+            Err(WasiFsError::EntityNotFound)
+        ));
+        assert!(overlay
+            .open(
+                Path::new(WHITEOUTS_PATH),
+                OpenOptions::new().write(true).create(true)
+            )
+            .is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
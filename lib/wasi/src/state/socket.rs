@@ -0,0 +1,251 @@
+//! TCP sockets, and the policy deciding which hosts and ports a module
+//! may use them with.
+use crate::state::types::host_file_bytes_available;
+use crate::state::{WasiFile, WasiFsError};
+use crate::syscalls::types::*;
+use serde::{de, ser, Deserialize, Serialize};
+use std::io::{self, Read, Seek, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream};
+
+/// The hosts and ports a module is allowed to connect to or listen on.
+///
+/// The network is disabled when there are no rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkPolicy {
+    /// The allowed hosts, with the allowed port if it's not any port.
+    /// The host `*` matches any host.
+    rules: Vec<(String, Option<u16>)>,
+}
+
+impl NetworkPolicy {
+    /// Adds a rule of the form `host:port`, where either can be `*` to
+    /// allow any. `*` alone allows everything.
+    pub(crate) fn allow(&mut self, rule: &str) -> Result<(), String> {
+        let (host, port) = match rule.rfind(':') {
+            Some(index) => (&rule[..index], &rule[index + 1..]),
+            None if rule == "*" => ("*", "*"),
+            None => return Err(format!("expected `host:port`, found `{}`", rule)),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(format!("the host is missing in `{}`", rule));
+        }
+        let port = match port {
+            "*" => None,
+            port => Some(
+                port.parse()
+                    .map_err(|_| format!("invalid port `{}` in `{}`", port, rule))?,
+            ),
+        };
+        self.rules.push((host.to_string(), port));
+        Ok(())
+    }
+
+    /// Whether the network is enabled at all.
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /// Whether the module may use `host:port`.
+    ///
+    /// This is checked before resolving `host`, so the module can't make
+    /// DNS queries for hosts it isn't allowed to use, and it can only
+    /// reach the addresses `host` resolves to: an IP address is only
+    /// allowed by a rule for the same address.
+    pub fn allows(&self, host: &str, port: u16) -> bool {
+        let ip = host.parse::<IpAddr>().ok();
+        self.rules.iter().any(|(allowed_host, allowed_port)| {
+            let host_matches = allowed_host == "*"
+                || allowed_host.eq_ignore_ascii_case(host)
+                || (ip.is_some() && allowed_host.parse::<IpAddr>().ok() == ip);
+            host_matches && allowed_port.map_or(true, |allowed| allowed == port)
+        })
+    }
+}
+
+/// A TCP socket opened by the module.
+#[derive(Debug)]
+pub enum Socket {
+    /// A connected stream
+    Stream(TcpStream),
+    /// A socket listening for connections
+    Listener(TcpListener),
+}
+
+impl Socket {
+    fn stream(&self) -> io::Result<&TcpStream> {
+        match self {
+            Self::Stream(stream) => Ok(stream),
+            Self::Listener(_) => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    /// Receives data, without removing it from the queue if `peek`.
+    pub fn recv(&self, buf: &mut [u8], peek: bool) -> io::Result<usize> {
+        let stream = self.stream()?;
+        if peek {
+            stream.peek(buf)
+        } else {
+            (&*stream).read(buf)
+        }
+    }
+
+    /// Gets another handle on the socket, so it can be used without
+    /// holding the lock of the state.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Self::Stream(stream) => Self::Stream(stream.try_clone()?),
+            Self::Listener(listener) => Self::Listener(listener.try_clone()?),
+        })
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.stream()?.shutdown(how)
+    }
+
+    /// Accepts a connection, if the socket is listening.
+    pub fn accept(&self) -> io::Result<(Self, SocketAddr)> {
+        match self {
+            Self::Listener(listener) => {
+                let (stream, addr) = listener.accept()?;
+                Ok((Self::Stream(stream), addr))
+            }
+            Self::Stream(_) => Err(io::ErrorKind::InvalidInput.into()),
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Self::Stream(stream) => stream.set_nonblocking(nonblocking),
+            Self::Listener(listener) => listener.set_nonblocking(nonblocking),
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Self::Stream(stream) => stream.local_addr(),
+            Self::Listener(listener) => listener.local_addr(),
+        }
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream()?.peer_addr()
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv(buf, false)
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stream(stream) => stream.write(buf),
+            Self::Listener(_) => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stream(stream) => stream.flush(),
+            Self::Listener(_) => Ok(()),
+        }
+    }
+}
+
+impl Seek for Socket {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not seek a socket",
+        ))
+    }
+}
+
+// Open connections can't outlive the process, so sockets can't be
+// serialized with the rest of the state.
+impl Serialize for Socket {
+    fn serialize<S: ser::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("sockets can not be serialized"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Socket {
+    fn deserialize<D: de::Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
+        Err(de::Error::custom("sockets can not be deserialized"))
+    }
+}
+
+#[typetag::serde]
+impl WasiFile for Socket {
+    fn last_accessed(&self) -> __wasi_timestamp_t {
+        0
+    }
+    fn last_modified(&self) -> __wasi_timestamp_t {
+        0
+    }
+    fn created_time(&self) -> __wasi_timestamp_t {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, _new_size: __wasi_filesize_t) -> Result<(), WasiFsError> {
+        Err(WasiFsError::InvalidInput)
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        match self {
+            Self::Stream(_) => match self.get_raw_fd() {
+                Some(host_fd) => host_file_bytes_available(host_fd),
+                // It can't be queried on this platform
+                None => Err(WasiFsError::IOError),
+            },
+            // Readiness means a pending connection, there are no bytes
+            Self::Listener(_) => Ok(0),
+        }
+    }
+
+    #[cfg(unix)]
+    fn get_raw_fd(&self) -> Option<i32> {
+        use std::os::unix::io::AsRawFd;
+        Some(match self {
+            Self::Stream(stream) => stream.as_raw_fd(),
+            Self::Listener(listener) => listener.as_raw_fd(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn network_policy() {
+        let mut policy = NetworkPolicy::default();
+        assert!(!policy.is_enabled());
+        assert!(!policy.allows("example.com", 80));
+
+        policy.allow("example.com:443").unwrap();
+        policy.allow("127.0.0.1:*").unwrap();
+        policy.allow("*:8080").unwrap();
+        policy.allow("[::1]:22").unwrap();
+        assert!(policy.is_enabled());
+        assert!(policy.allows("EXAMPLE.com", 443));
+        assert!(!policy.allows("example.com", 80));
+        assert!(policy.allows("127.0.0.1", 22));
+        assert!(policy.allows("0:0:0:0:0:0:0:1", 22));
+        assert!(!policy.allows("::1", 23));
+        // Even if it resolves to an allowed address
+        assert!(!policy.allows("localhost", 22));
+        assert!(policy.allows("anything", 8080));
+
+        assert!(policy.allow("example.com").is_err());
+        assert!(policy.allow(":80").is_err());
+        assert!(policy.allow("example.com:http").is_err());
+    }
+}
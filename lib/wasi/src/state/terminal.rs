@@ -0,0 +1,112 @@
+//! The terminals of the host the standard streams may be attached to.
+//!
+//! WASI programs tell terminals apart with `fd_fdstat_get`: a terminal is a
+//! character device which can't seek. Raw mode outlives the process, so the
+//! modes of the terminals put in raw mode are saved, to be restored with
+//! [`restore_terminals`] before exiting.
+use std::io;
+#[cfg(unix)]
+use std::sync::Mutex;
+
+#[cfg(unix)]
+lazy_static::lazy_static! {
+    /// The modes of the terminals in raw mode, from before they were
+    static ref ORIGINAL_MODES: Mutex<Vec<(i32, libc::termios)>> = Mutex::new(vec![]);
+}
+
+#[cfg(unix)]
+fn check(ret: libc::c_int) -> io::Result<()> {
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "terminals are only supported on unix")
+}
+
+/// Whether the host file descriptor `fd` is a terminal.
+#[cfg(unix)]
+pub(crate) fn is_terminal(fd: i32) -> bool {
+    unsafe { libc::isatty(fd) == 1 }
+}
+
+/// Gets the size of the terminal `fd`, as columns and rows.
+pub(crate) fn terminal_size(fd: i32) -> io::Result<(u16, u16)> {
+    #[cfg(unix)]
+    {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        check(unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) })?;
+        Ok((size.ws_col, size.ws_row))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = fd;
+        Err(unsupported())
+    }
+}
+
+/// Puts the terminal `fd` in raw mode, or back in the mode it had before.
+pub(crate) fn set_raw_mode(fd: i32, raw: bool) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut modes = ORIGINAL_MODES.lock().unwrap();
+        let saved = modes.iter().position(|(saved_fd, _)| *saved_fd == fd);
+        match (raw, saved) {
+            (true, None) => {
+                let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+                check(unsafe { libc::tcgetattr(fd, &mut termios) })?;
+                let original = termios;
+                unsafe { libc::cfmakeraw(&mut termios) };
+                check(unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) })?;
+                modes.push((fd, original));
+            }
+            (false, Some(index)) => {
+                let (_, original) = modes.remove(index);
+                check(unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) })?;
+            }
+            // Already in the requested mode
+            _ => {}
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (fd, raw);
+        Err(unsupported())
+    }
+}
+
+/// Restores the modes of the terminals WASI programs put in raw mode.
+///
+/// Call it before exiting, since the terminals stay in raw mode otherwise.
+pub fn restore_terminals() {
+    #[cfg(unix)]
+    {
+        let mut modes = ORIGINAL_MODES.lock().unwrap();
+        for (fd, original) in modes.drain(..) {
+            unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    #[test]
+    fn not_a_terminal() {
+        let file = File::open(std::env::current_exe().unwrap()).unwrap();
+        let fd = file.as_raw_fd();
+        assert!(!is_terminal(fd));
+        assert!(terminal_size(fd).is_err());
+        assert!(set_raw_mode(fd, true).is_err());
+        // Leaving raw mode does nothing when not in it
+        assert!(set_raw_mode(fd, false).is_ok());
+    }
+}
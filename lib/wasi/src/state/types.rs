@@ -10,7 +10,7 @@ use std::{
     fs,
     io::{self, Read, Seek, Write},
     path::PathBuf,
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 use thiserror::Error;
 use tracing::debug;
@@ -361,46 +361,67 @@ impl PollEventBuilder {
     }
 }
 
+/// Waits until the host file descriptors `fds` are ready for the
+/// `events`, or `timeout` elapses, and stores the events they are ready
+/// for in `seen_events`. Returns the number of file descriptors ready.
 #[cfg(unix)]
 pub(crate) fn poll(
-    selfs: &[&dyn WasiFile],
+    fds: &[i32],
     events: &[PollEventSet],
     seen_events: &mut [PollEventSet],
+    timeout: Option<Duration>,
 ) -> Result<u32, WasiFsError> {
-    if !(selfs.len() == events.len() && events.len() == seen_events.len()) {
+    if !(fds.len() == events.len() && events.len() == seen_events.len()) {
         return Err(WasiFsError::InvalidInput);
     }
-    let mut fds = selfs
+    let mut fds = fds
         .iter()
-        .enumerate()
-        .filter_map(|(i, s)| s.get_raw_fd().map(|rfd| (i, rfd)))
-        .map(|(i, host_fd)| libc::pollfd {
-            fd: host_fd,
-            events: poll_event_set_to_platform_poll_events(events[i]),
+        .zip(events)
+        .map(|(host_fd, events)| libc::pollfd {
+            fd: *host_fd,
+            events: poll_event_set_to_platform_poll_events(*events),
             revents: 0,
         })
         .collect::<Vec<_>>();
-    let result = unsafe { libc::poll(fds.as_mut_ptr(), selfs.len() as _, 1) };
+    let timeout_ms = match timeout {
+        // Rounded up, to not return before the timeout
+        Some(timeout) => ((timeout.as_nanos() + 999_999) / 1_000_000)
+            .min(libc::c_int::max_value() as u128) as libc::c_int,
+        None => -1,
+    };
+    let result = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, timeout_ms) };
 
     if result < 0 {
-        // TODO: check errno and return value
+        // A signal is handled like the timeout elapsing
+        if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+            return Ok(0);
+        }
         return Err(WasiFsError::IOError);
     }
     // convert result and write back values
-    for (i, fd) in fds.into_iter().enumerate() {
-        seen_events[i] = platform_poll_events_to_pollevent_set(fd.revents);
+    for (seen_events, fd) in seen_events.iter_mut().zip(fds) {
+        *seen_events = platform_poll_events_to_pollevent_set(fd.revents);
     }
-    // unwrap is safe because we check for negative values above
-    Ok(result.try_into().unwrap())
+    // the cast is safe because we check for negative values above
+    Ok(result as u32)
 }
 
+/// The host file descriptors can't be polled on other platforms, so this
+/// only waits for `timeout`.
 #[cfg(not(unix))]
 pub(crate) fn poll(
-    _selfs: &[&dyn WasiFile],
-    _events: &[PollEventSet],
-    _seen_events: &mut [PollEventSet],
-) -> Result<(), WasiFsError> {
-    unimplemented!("HostFile::poll in WasiFile is not implemented for non-Unix-like targets yet");
+    fds: &[i32],
+    events: &[PollEventSet],
+    seen_events: &mut [PollEventSet],
+    timeout: Option<Duration>,
+) -> Result<u32, WasiFsError> {
+    if !(fds.is_empty() && events.is_empty() && seen_events.is_empty()) {
+        return Err(WasiFsError::InvalidInput);
+    }
+    if let Some(timeout) = timeout {
+        std::thread::sleep(timeout);
+    }
+    Ok(0)
 }
 
 pub trait WasiPath {}
@@ -672,7 +693,7 @@ impl From<io::Error> for WasiFsError {
 }
 
 #[cfg(unix)]
-fn host_file_bytes_available(host_fd: i32) -> Result<usize, WasiFsError> {
+pub(crate) fn host_file_bytes_available(host_fd: i32) -> Result<usize, WasiFsError> {
     let mut bytes_found = 0 as libc::c_int;
     let result = unsafe { libc::ioctl(host_fd, libc::FIONREAD, &mut bytes_found) };
 
@@ -687,10 +708,29 @@ fn host_file_bytes_available(host_fd: i32) -> Result<usize, WasiFsError> {
 }
 
 #[cfg(not(unix))]
-fn host_file_bytes_available(_raw_fd: i32) -> Result<usize, WasiFsError> {
+pub(crate) fn host_file_bytes_available(_raw_fd: i32) -> Result<usize, WasiFsError> {
     unimplemented!("host_file_bytes_available not yet implemented for non-Unix-like targets.  This probably means the program tried to use wasi::poll_oneoff")
 }
 
+/// Gets the WASI file type of a host file descriptor, so that guests can
+/// tell whether their standard devices are terminals, pipes or files.
+#[cfg(unix)]
+pub(crate) fn host_file_type(host_fd: i32) -> __wasi_filetype_t {
+    let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+    if unsafe { libc::fstat(host_fd, stat.as_mut_ptr()) } != 0 {
+        return __WASI_FILETYPE_UNKNOWN;
+    }
+    match unsafe { stat.assume_init() }.st_mode & libc::S_IFMT {
+        libc::S_IFCHR => __WASI_FILETYPE_CHARACTER_DEVICE,
+        libc::S_IFBLK => __WASI_FILETYPE_BLOCK_DEVICE,
+        libc::S_IFREG => __WASI_FILETYPE_REGULAR_FILE,
+        libc::S_IFDIR => __WASI_FILETYPE_DIRECTORY,
+        libc::S_IFSOCK => __WASI_FILETYPE_SOCKET_STREAM,
+        // FIFO doesn't seem to fit any other type, so unknown
+        _ => __WASI_FILETYPE_UNKNOWN,
+    }
+}
+
 /// A wrapper type around Stdout that implements `WasiFile` and
 /// `Serialize` + `Deserialize`.
 #[derive(Debug, Serialize, Deserialize)]
@@ -1047,3 +1087,66 @@ trait WasiFdBacking: std::fmt::Debug {
     fn get_name(&self) -> &str;
 }
 */
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use std::fs::File;
+    use std::net::{TcpListener, TcpStream};
+    use std::os::unix::io::AsRawFd;
+    use std::time::Instant;
+
+    #[test]
+    fn host_file_types() {
+        let manifest = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")).unwrap();
+        assert_eq!(
+            host_file_type(manifest.as_raw_fd()),
+            __WASI_FILETYPE_REGULAR_FILE
+        );
+        let dir = File::open(env!("CARGO_MANIFEST_DIR")).unwrap();
+        assert_eq!(host_file_type(dir.as_raw_fd()), __WASI_FILETYPE_DIRECTORY);
+        let null = File::open("/dev/null").unwrap();
+        assert_eq!(
+            host_file_type(null.as_raw_fd()),
+            __WASI_FILETYPE_CHARACTER_DEVICE
+        );
+
+        // FIFOs have no WASI file type
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        assert_eq!(host_file_type(fds[0]), __WASI_FILETYPE_UNKNOWN);
+        assert_eq!(host_file_type(fds[1]), __WASI_FILETYPE_UNKNOWN);
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+
+        assert_eq!(host_file_type(-1), __WASI_FILETYPE_UNKNOWN);
+    }
+
+    #[test]
+    fn poll_files() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let fds = [listener.as_raw_fd()];
+        let read = PollEventBuilder::new().add(PollEvent::PollIn).build();
+
+        // Nothing to accept yet
+        let timeout = Duration::from_millis(20);
+        let start = Instant::now();
+        let mut seen_events = [0];
+        let ready = poll(&fds, &[read], &mut seen_events, Some(timeout)).unwrap();
+        assert_eq!(ready, 0);
+        assert_eq!(seen_events, [0]);
+        assert!(start.elapsed() >= timeout);
+
+        // Without any file descriptor, this only waits
+        let start = Instant::now();
+        assert_eq!(poll(&[], &[], &mut [], Some(timeout)).unwrap(), 0);
+        assert!(start.elapsed() >= timeout);
+
+        let _stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let ready = poll(&fds, &[read], &mut seen_events, None).unwrap();
+        assert_eq!(ready, 1);
+        assert_eq!(seen_events, [read]);
+    }
+}
@@ -8,27 +8,31 @@ pub mod types;
     target_os = "macos"
 ))]
 pub mod unix;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm32;
 #[cfg(any(target_os = "windows"))]
 pub mod windows;
 
 pub mod legacy;
+pub mod sockets;
+pub mod tty;
 
 use self::types::*;
 use crate::{
     ptr::{Array, WasmPtr},
     state::{
-        self, host_file_type_to_wasi_file_type, iterate_poll_events, poll, Fd, HostFile, Inode,
-        InodeVal, Kind, PollEvent, PollEventBuilder, WasiFile, WasiFsError, WasiState,
-        MAX_SYMLINKS,
+        self, host_file_bytes_available, iterate_poll_events, poll, same_file_system,
+        split_mount_path, Fd, Inode, InodeVal, Kind, OpenOptions, PollEvent, PollEventBuilder,
+        WasiFile, WasiFsError, WasiState, MAX_SYMLINKS,
     },
     WasiEnv, WasiError,
 };
 use std::borrow::Borrow;
-use std::cell::Cell;
 use std::convert::{Infallible, TryInto};
 use std::io::{self, Read, Seek, Write};
+use std::time::{Duration, Instant};
 use tracing::{debug, trace};
-use wasmer::{Memory, RuntimeError, Value};
+use wasmer::{Memory, RuntimeError, Value, WasmCell};
 
 #[cfg(any(
     target_os = "freebsd",
@@ -41,48 +45,92 @@ pub use unix::*;
 #[cfg(any(target_os = "windows"))]
 pub use windows::*;
 
+#[cfg(target_arch = "wasm32")]
+pub use wasm32::*;
+
+/// Writes the buffers of `iovs_arr_cell`, returning how many bytes were
+/// written. An error after some bytes were written, eg. `EAGAIN` for a
+/// non-blocking socket, is left for the next call to report.
 fn write_bytes_inner<T: Write>(
     mut write_loc: T,
     memory: &Memory,
-    iovs_arr_cell: &[Cell<__wasi_ciovec_t>],
+    iovs_arr_cell: &[WasmCell<__wasi_ciovec_t>],
 ) -> Result<u32, __wasi_errno_t> {
     let mut bytes_written = 0;
     for iov in iovs_arr_cell {
         let iov_inner = iov.get();
-        let bytes = iov_inner.buf.deref(memory, 0, iov_inner.buf_len)?;
-        write_loc
-            .write_all(&bytes.iter().map(|b_cell| b_cell.get()).collect::<Vec<u8>>())
-            .map_err(|_| __WASI_EIO)?;
-
-        // TODO: handle failure more accurately
-        bytes_written += iov_inner.buf_len;
+        let mut bytes = vec![0; iov_inner.buf_len as usize];
+        memory
+            .read(iov_inner.buf.offset() as u64, &mut bytes)
+            .map_err(|_| __WASI_EFAULT)?;
+        let mut offset = 0;
+        while offset < bytes.len() {
+            match write_loc.write(&bytes[offset..]) {
+                Ok(0) => return partial(bytes_written, io::ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    offset += written;
+                    bytes_written += written as u32;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return partial(bytes_written, e),
+            }
+        }
     }
     Ok(bytes_written)
 }
 
+/// The result of a transfer failing with `error` after `transferred`
+/// bytes: the bytes transferred if any, the error otherwise.
+fn partial(transferred: u32, error: io::Error) -> Result<u32, __wasi_errno_t> {
+    if transferred > 0 {
+        Ok(transferred)
+    } else {
+        Err(WasiFsError::from(error).into_wasi_err())
+    }
+}
+
 fn write_bytes<T: Write>(
     mut write_loc: T,
     memory: &Memory,
-    iovs_arr_cell: &[Cell<__wasi_ciovec_t>],
+    iovs_arr_cell: &[WasmCell<__wasi_ciovec_t>],
 ) -> Result<u32, __wasi_errno_t> {
     let result = write_bytes_inner(&mut write_loc, memory, iovs_arr_cell);
     write_loc.flush();
     result
 }
 
+/// Reads into the buffers of `iovs_arr_cell`, returning how many bytes
+/// were read. The reading stops at the first short read, and an error
+/// after some bytes were read is left for the next call to report.
 fn read_bytes<T: Read>(
     mut reader: T,
     memory: &Memory,
-    iovs_arr_cell: &[Cell<__wasi_iovec_t>],
+    iovs_arr_cell: &[WasmCell<__wasi_iovec_t>],
 ) -> Result<u32, __wasi_errno_t> {
     let mut bytes_read = 0;
 
+    // The bytes are read into a buffer first, as the memory can't be
+    // borrowed with the `js` backend
+    let mut raw_bytes = Vec::new();
     for iov in iovs_arr_cell {
         let iov_inner = iov.get();
-        let bytes = iov_inner.buf.deref(memory, 0, iov_inner.buf_len)?;
-        let mut raw_bytes: &mut [u8] =
-            unsafe { &mut *(bytes as *const [_] as *mut [_] as *mut [u8]) };
-        bytes_read += reader.read(raw_bytes).map_err(|_| __WASI_EIO)? as u32;
+        // Checked before reading, to not lose the bytes read
+        if iov_inner.buf.offset() as u64 + iov_inner.buf_len as u64 > memory.data_size() {
+            return Err(__WASI_EFAULT);
+        }
+        raw_bytes.clear();
+        raw_bytes.resize(iov_inner.buf_len as usize, 0);
+        let read = match reader.read(&mut raw_bytes) {
+            Ok(read) => read,
+            Err(e) => return partial(bytes_read, e),
+        };
+        memory
+            .write(iov_inner.buf.offset() as u64, &raw_bytes[..read])
+            .map_err(|_| __WASI_EFAULT)?;
+        bytes_read += read as u32;
+        if read < raw_bytes.len() {
+            break;
+        }
     }
     Ok(bytes_read)
 }
@@ -118,11 +166,7 @@ fn write_buffer_array(
 }
 
 fn get_current_time_in_nanos() -> Result<__wasi_timestamp_t, __wasi_errno_t> {
-    let now = std::time::SystemTime::now();
-    let duration = now
-        .duration_since(std::time::SystemTime::UNIX_EPOCH)
-        .map_err(|_| __WASI_EIO)?;
-    Ok(duration.as_nanos() as __wasi_timestamp_t)
+    platform_clock_time_get(__WASI_CLOCK_REALTIME, 1)
 }
 
 /// ### `args_get()`
@@ -204,10 +248,18 @@ pub fn clock_res_get(
     resolution: WasmPtr<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::clock_res_get");
-    let memory = env.memory();
+    let (memory, state) = env.get_memory_and_wasi_state(0);
 
     let out_addr = wasi_try!(resolution.deref(memory));
-    platform_clock_res_get(clock_id, out_addr)
+    if let Some(clock) = &state.clock {
+        if clock_id > __WASI_CLOCK_THREAD_CPUTIME_ID {
+            return __WASI_EINVAL;
+        }
+        out_addr.set(clock.resolution());
+        return __WASI_ESUCCESS;
+    }
+    out_addr.set(wasi_try!(platform_clock_res_get(clock_id)));
+    __WASI_ESUCCESS
 }
 
 /// ### `clock_time_get()`
@@ -230,10 +282,23 @@ pub fn clock_time_get(
         "wasi::clock_time_get clock_id: {}, precision: {}",
         clock_id, precision
     );
-    let memory = env.memory();
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let out_addr = wasi_try!(time.deref(memory));
-    let result = platform_clock_time_get(clock_id, precision, out_addr);
+    let result = match &mut state.clock {
+        Some(_) if clock_id > __WASI_CLOCK_THREAD_CPUTIME_ID => __WASI_EINVAL,
+        Some(clock) => {
+            out_addr.set(clock.tick());
+            __WASI_ESUCCESS
+        }
+        None => match platform_clock_time_get(clock_id, precision) {
+            Ok(now) => {
+                out_addr.set(now);
+                __WASI_ESUCCESS
+            }
+            Err(errno) => errno,
+        },
+    };
     debug!(
         "time: {} => {}",
         wasi_try!(time.deref(memory)).get(),
@@ -446,12 +511,21 @@ pub fn fd_fdstat_set_flags(
 ) -> __wasi_errno_t {
     debug!("wasi::fd_fdstat_set_flags");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
-    let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
+    let fd_entry = wasi_try!(state.fs.fd_map.get(&fd).ok_or(__WASI_EBADF));
 
     if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_FDSTAT_SET_FLAGS) {
         return __WASI_EACCES;
     }
 
+    // Sockets are the only host files which can be switched to
+    // non-blocking, so that a module can serve several connections
+    // with `poll_oneoff`. The flags are only changed once they are.
+    if let Ok(socket) = state.fs.get_socket_mut(fd) {
+        wasi_try!(socket
+            .set_nonblocking(flags & __WASI_FDFLAG_NONBLOCK != 0)
+            .map_err(|e| WasiFsError::from(e).into_wasi_err()));
+    }
+    let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
     fd_entry.flags = flags;
     __WASI_ESUCCESS
 }
@@ -657,7 +731,7 @@ pub fn fd_pread(
             if let Some(ref mut stdin) =
                 wasi_try!(state.fs.stdin_mut().map_err(WasiFsError::into_wasi_err))
             {
-                wasi_try!(read_bytes(stdin, memory, iov_cells))
+                wasi_try!(read_bytes(stdin, memory, &iov_cells))
             } else {
                 return __WASI_EBADF;
             }
@@ -684,7 +758,7 @@ pub fn fd_pread(
                             h.seek(std::io::SeekFrom::Start(offset as u64)).ok(),
                             __WASI_EIO
                         );
-                        wasi_try!(read_bytes(h, memory, iov_cells))
+                        wasi_try!(read_bytes(h, memory, &iov_cells))
                     } else {
                         return __WASI_EINVAL;
                     }
@@ -692,7 +766,7 @@ pub fn fd_pread(
                 Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
                 Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_pread"),
                 Kind::Buffer { buffer } => {
-                    wasi_try!(read_bytes(&buffer[(offset as usize)..], memory, iov_cells))
+                    wasi_try!(read_bytes(&buffer[(offset as usize)..], memory, &iov_cells))
                 }
             }
         }
@@ -755,13 +829,7 @@ pub fn fd_prestat_dir_name(
                 }
                 path_chars[i].set(0);
 
-                debug!(
-                    "=> result: \"{}\"",
-                    ::std::str::from_utf8(unsafe {
-                        &*(&path_chars[..] as *const [_] as *const [u8])
-                    })
-                    .unwrap()
-                );
+                debug!("=> result: \"{}\"", inode_val.name);
 
                 __WASI_ESUCCESS
             } else {
@@ -806,7 +874,7 @@ pub fn fd_pwrite(
             if let Some(ref mut stdout) =
                 wasi_try!(state.fs.stdout_mut().map_err(WasiFsError::into_wasi_err))
             {
-                wasi_try!(write_bytes(stdout, memory, iovs_arr_cell))
+                wasi_try!(write_bytes(stdout, memory, &iovs_arr_cell))
             } else {
                 return __WASI_EBADF;
             }
@@ -815,7 +883,7 @@ pub fn fd_pwrite(
             if let Some(ref mut stderr) =
                 wasi_try!(state.fs.stderr_mut().map_err(WasiFsError::into_wasi_err))
             {
-                wasi_try!(write_bytes(stderr, memory, iovs_arr_cell))
+                wasi_try!(write_bytes(stderr, memory, &iovs_arr_cell))
             } else {
                 return __WASI_EBADF;
             }
@@ -836,7 +904,7 @@ pub fn fd_pwrite(
                 Kind::File { handle, .. } => {
                     if let Some(handle) = handle {
                         handle.seek(std::io::SeekFrom::Start(offset as u64));
-                        wasi_try!(write_bytes(handle, memory, iovs_arr_cell))
+                        wasi_try!(write_bytes(handle, memory, &iovs_arr_cell))
                     } else {
                         return __WASI_EINVAL;
                     }
@@ -850,7 +918,7 @@ pub fn fd_pwrite(
                     wasi_try!(write_bytes(
                         &mut buffer[(offset as usize)..],
                         memory,
-                        iovs_arr_cell
+                        &iovs_arr_cell
                     ))
                 }
             }
@@ -892,7 +960,7 @@ pub fn fd_read(
             if let Some(ref mut stdin) =
                 wasi_try!(state.fs.stdin_mut().map_err(WasiFsError::into_wasi_err))
             {
-                wasi_try!(read_bytes(stdin, memory, iovs_arr_cell))
+                wasi_try!(read_bytes(stdin, memory, &iovs_arr_cell))
             } else {
                 return __WASI_EBADF;
             }
@@ -914,7 +982,7 @@ pub fn fd_read(
                 Kind::File { handle, .. } => {
                     if let Some(handle) = handle {
                         handle.seek(std::io::SeekFrom::Start(offset as u64));
-                        wasi_try!(read_bytes(handle, memory, iovs_arr_cell))
+                        wasi_try!(read_bytes(handle, memory, &iovs_arr_cell))
                     } else {
                         return __WASI_EINVAL;
                     }
@@ -925,7 +993,7 @@ pub fn fd_read(
                 }
                 Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_read"),
                 Kind::Buffer { buffer } => {
-                    wasi_try!(read_bytes(&buffer[offset..], memory, iovs_arr_cell))
+                    wasi_try!(read_bytes(&buffer[offset..], memory, &iovs_arr_cell))
                 }
             };
 
@@ -982,17 +1050,20 @@ pub fn fd_readdir(
             // we need to support multiple calls,
             // simple and obviously correct implementation for now:
             // maintain consistent order via lexacographic sorting
-            let fs_info = wasi_try!(wasi_try!(std::fs::read_dir(path).map_err(|_| __WASI_EIO))
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|_| __WASI_EIO));
-            let mut entry_vec = wasi_try!(fs_info
+            let (file_system, fs_path) = state.fs.file_system(path);
+            let fs_info = wasi_try!(file_system
+                .read_dir(&fs_path)
+                .map_err(WasiFsError::into_wasi_err));
+            let mut entry_vec = fs_info
                 .into_iter()
-                .map(|entry| Ok((
-                    entry.file_name().to_string_lossy().to_string(),
-                    host_file_type_to_wasi_file_type(entry.file_type().map_err(|_| __WASI_EIO)?),
-                    0, // TODO: inode
-                )))
-                .collect::<Result<Vec<(String, u8, u64)>, _>>());
+                .map(|entry| {
+                    (
+                        entry.name,
+                        entry.filetype,
+                        0, // TODO: inode
+                    )
+                })
+                .collect::<Vec<(String, u8, u64)>>();
             entry_vec.extend(
                 entries
                     .iter()
@@ -1068,24 +1139,16 @@ pub fn fd_readdir(
 }
 
 /// ### `fd_renumber()`
-/// Atomically copy file descriptor
+/// Atomically move a file descriptor, closing the one it replaces
 /// Inputs:
 /// - `__wasi_fd_t from`
-///     File descriptor to copy
+///     File descriptor to move
 /// - `__wasi_fd_t to`
-///     Location to copy file descriptor to
+///     Location to move file descriptor to
 pub fn fd_renumber(env: &WasiEnv, from: __wasi_fd_t, to: __wasi_fd_t) -> __wasi_errno_t {
     debug!("wasi::fd_renumber: from={}, to={}", from, to);
-    let (memory, mut state) = env.get_memory_and_wasi_state(0);
-    let fd_entry = wasi_try!(state.fs.fd_map.get(&from).ok_or(__WASI_EBADF));
-    let new_fd_entry = Fd {
-        // TODO: verify this is correct
-        rights: fd_entry.rights_inheriting,
-        ..*fd_entry
-    };
-
-    state.fs.fd_map.insert(to, new_fd_entry);
-    state.fs.fd_map.remove(&from);
+    let mut state = env.state();
+    wasi_try!(state.fs.renumber_fd(from, to));
     __WASI_ESUCCESS
 }
 
@@ -1263,7 +1326,7 @@ pub fn fd_write(
             if let Some(ref mut stdout) =
                 wasi_try!(state.fs.stdout_mut().map_err(WasiFsError::into_wasi_err))
             {
-                wasi_try!(write_bytes(stdout, memory, iovs_arr_cell))
+                wasi_try!(write_bytes(stdout, memory, &iovs_arr_cell))
             } else {
                 return __WASI_EBADF;
             }
@@ -1272,7 +1335,7 @@ pub fn fd_write(
             if let Some(ref mut stderr) =
                 wasi_try!(state.fs.stderr_mut().map_err(WasiFsError::into_wasi_err))
             {
-                wasi_try!(write_bytes(stderr, memory, iovs_arr_cell))
+                wasi_try!(write_bytes(stderr, memory, &iovs_arr_cell))
             } else {
                 return __WASI_EBADF;
             }
@@ -1292,7 +1355,7 @@ pub fn fd_write(
                 Kind::File { handle, .. } => {
                     if let Some(handle) = handle {
                         handle.seek(std::io::SeekFrom::Start(offset as u64));
-                        wasi_try!(write_bytes(handle, memory, iovs_arr_cell))
+                        wasi_try!(write_bytes(handle, memory, &iovs_arr_cell))
                     } else {
                         return __WASI_EINVAL;
                     }
@@ -1303,7 +1366,7 @@ pub fn fd_write(
                 }
                 Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_write"),
                 Kind::Buffer { buffer } => {
-                    wasi_try!(write_bytes(&mut buffer[offset..], memory, iovs_arr_cell))
+                    wasi_try!(write_bytes(&mut buffer[offset..], memory, &iovs_arr_cell))
                 }
             };
 
@@ -1350,10 +1413,10 @@ pub fn path_create_directory(
     if !has_rights(working_dir.rights, __WASI_RIGHT_PATH_CREATE_DIRECTORY) {
         return __WASI_EACCES;
     }
-    let path_string = unsafe { get_input_str!(memory, path, path_len) };
+    let path_string = get_input_str!(memory, path, path_len);
     debug!("=> fd: {}, path: {}", fd, &path_string);
 
-    let path = std::path::PathBuf::from(path_string);
+    let path = std::path::PathBuf::from(&path_string);
     let path_vec = wasi_try!(path
         .components()
         .map(|comp| {
@@ -1394,10 +1457,13 @@ pub fn path_create_directory(
                     let mut adjusted_path = path.clone();
                     // TODO: double check this doesn't risk breaking the sandbox
                     adjusted_path.push(comp);
-                    if adjusted_path.exists() && !adjusted_path.is_dir() {
-                        return __WASI_ENOTDIR;
-                    } else if !adjusted_path.exists() {
-                        wasi_try!(std::fs::create_dir(&adjusted_path).ok(), __WASI_EIO);
+                    let (file_system, fs_path) = state.fs.file_system(&adjusted_path);
+                    match file_system.metadata(&fs_path) {
+                        Ok(metadata) if !metadata.is_dir() => return __WASI_ENOTDIR,
+                        Ok(_) => {}
+                        Err(_) => {
+                            wasi_try!(file_system.create_dir(&fs_path).ok(), __WASI_EIO);
+                        }
                     }
                     let kind = Kind::Dir {
                         parent: Some(cur_dir_inode),
@@ -1453,13 +1519,13 @@ pub fn path_filestat_get(
     if !has_rights(root_dir.rights, __WASI_RIGHT_PATH_FILESTAT_GET) {
         return __WASI_EACCES;
     }
-    let path_string = unsafe { get_input_str!(memory, path, path_len) };
+    let path_string = get_input_str!(memory, path, path_len);
 
     debug!("=> base_fd: {}, path: {}", fd, &path_string);
 
     let file_inode = wasi_try!(state.fs.get_inode_at_path(
         fd,
-        path_string,
+        &path_string,
         flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     ));
     let stat = if state.fs.inodes[file_inode].is_preopened {
@@ -1518,12 +1584,12 @@ pub fn path_filestat_set_times(
         return __WASI_EINVAL;
     }
 
-    let path_string = unsafe { get_input_str!(memory, path, path_len) };
+    let path_string = get_input_str!(memory, path, path_len);
     debug!("=> base_fd: {}, path: {}", fd, &path_string);
 
     let file_inode = wasi_try!(state.fs.get_inode_at_path(
         fd,
-        path_string,
+        &path_string,
         flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     ));
     let stat = wasi_try!(state
@@ -1597,8 +1663,8 @@ pub fn path_link(
         debug!("  - will follow symlinks when opening path");
     }
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
-    let old_path_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
-    let new_path_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
+    let old_path_str = get_input_str!(memory, old_path, old_path_len);
+    let new_path_str = get_input_str!(memory, new_path, new_path_len);
     let source_fd = wasi_try!(state.fs.get_fd(old_fd));
     let target_fd = wasi_try!(state.fs.get_fd(new_fd));
     debug!(
@@ -1614,10 +1680,10 @@ pub fn path_link(
 
     let source_inode = wasi_try!(state.fs.get_inode_at_path(
         old_fd,
-        old_path_str,
+        &old_path_str,
         old_flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     ));
-    let target_path_arg = std::path::PathBuf::from(new_path_str);
+    let target_path_arg = std::path::PathBuf::from(&new_path_str);
     let (target_parent_inode, new_entry_name) =
         wasi_try!(state
             .fs
@@ -1696,20 +1762,21 @@ pub fn path_open(
     // - __WASI_O_TRUNC (truncate size to 0)
 
     let working_dir = wasi_try!(state.fs.get_fd(dirfd));
+    let working_dir_rights = working_dir.rights;
     let working_dir_rights_inheriting = working_dir.rights_inheriting;
 
     // ASSUMPTION: open rights apply recursively
     if !has_rights(working_dir.rights, __WASI_RIGHT_PATH_OPEN) {
         return __WASI_EACCES;
     }
-    let path_string = unsafe { get_input_str!(memory, path, path_len) };
+    let path_string = get_input_str!(memory, path, path_len);
 
     debug!("=> fd: {}, path: {}", dirfd, &path_string);
 
-    let path_arg = std::path::PathBuf::from(path_string);
+    let path_arg = std::path::PathBuf::from(&path_string);
     let maybe_inode = state.fs.get_inode_at_path(
         dirfd,
-        path_string,
+        &path_string,
         dirflags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     );
 
@@ -1721,11 +1788,7 @@ pub fn path_open(
     let inode = if let Ok(inode) = maybe_inode {
         // Happy path, we found the file we're trying to open
         match &mut state.fs.inodes[inode].kind {
-            Kind::File {
-                ref mut handle,
-                path,
-                fd,
-            } => {
+            Kind::File { handle, path, fd } => {
                 if let Some(special_fd) = fd {
                     // short circuit if we're dealing with a special file
                     assert!(handle.is_some());
@@ -1735,10 +1798,12 @@ pub fn path_open(
                 if o_flags & __WASI_O_DIRECTORY != 0 {
                     return __WASI_ENOTDIR;
                 }
-                if o_flags & __WASI_O_EXCL != 0 && path.exists() {
+                let path = path.clone();
+                let (file_system, fs_path) = state.fs.file_system(&path);
+                if o_flags & __WASI_O_EXCL != 0 && file_system.symlink_metadata(&fs_path).is_ok() {
                     return __WASI_EEXIST;
                 }
-                let mut open_options = std::fs::OpenOptions::new();
+                let mut open_options = OpenOptions::new();
                 let write_permission = adjusted_rights & __WASI_RIGHT_FD_WRITE != 0;
                 // append, truncate, and create all require the permission to write
                 let (append_permission, truncate_permission, create_permission) =
@@ -1768,13 +1833,12 @@ pub fn path_open(
                 if o_flags & __WASI_O_TRUNC != 0 {
                     open_flags |= Fd::TRUNCATE;
                 }
-                *handle = Some(Box::new(HostFile::new(
-                    wasi_try!(open_options.open(&path).map_err(|_| __WASI_EIO)),
-                    path.to_path_buf(),
-                    true,
-                    adjusted_rights & __WASI_RIGHT_FD_WRITE != 0,
-                    false,
-                )));
+                let new_handle = wasi_try!(file_system
+                    .open(&fs_path, open_options)
+                    .map_err(WasiFsError::into_wasi_err));
+                if let Kind::File { handle, .. } = &mut state.fs.inodes[inode].kind {
+                    *handle = Some(new_handle);
+                }
             }
             Kind::Buffer { .. } => unimplemented!("wasi::path_open for Buffer type files"),
             Kind::Dir { .. } | Kind::Root { .. } => {
@@ -1801,6 +1865,9 @@ pub fn path_open(
             if o_flags & __WASI_O_DIRECTORY != 0 {
                 return __WASI_ENOTDIR;
             }
+            if !has_rights(working_dir_rights, __WASI_RIGHT_PATH_CREATE_FILE) {
+                return __WASI_EACCES;
+            }
             debug!("Creating file");
             // strip end file name
 
@@ -1821,7 +1888,7 @@ pub fn path_open(
             // once we got the data we need from the parent, we lookup the host file
             // todo: extra check that opening with write access is okay
             let handle = {
-                let mut open_options = std::fs::OpenOptions::new();
+                let mut open_options = OpenOptions::new();
                 let open_options = open_options
                     .read(true)
                     .append(fs_flags & __WASI_FDFLAG_APPEND != 0)
@@ -1831,16 +1898,12 @@ pub fn path_open(
                     .create_new(true);
                 open_flags |= Fd::READ | Fd::WRITE | Fd::CREATE | Fd::TRUNCATE;
 
-                Some(Box::new(HostFile::new(
-                    wasi_try!(open_options.open(&new_file_host_path).map_err(|e| {
-                        debug!("Error opening file {}", e);
-                        __WASI_EIO
-                    })),
-                    new_file_host_path.clone(),
-                    true,
-                    true,
-                    true,
-                )) as Box<dyn WasiFile>)
+                let (file_system, fs_path) = state.fs.file_system(&new_file_host_path);
+                let handle = file_system.open(&fs_path, open_options).map_err(|e| {
+                    debug!("Error opening file {}", e);
+                    e.into_wasi_err()
+                });
+                Some(wasi_try!(handle))
             };
 
             let new_inode = {
@@ -1872,9 +1935,12 @@ pub fn path_open(
 
     // TODO: check and reduce these
     // TODO: ensure a mutable fd to root can never be opened
+    // The files opened through the new fd can't get more rights than the ones
+    // inherited from `dirfd`, or the restrictions of a preopened directory
+    // would be lost by opening a directory in it
     let out_fd = wasi_try!(state.fs.create_fd(
         adjusted_rights,
-        fs_rights_inheriting,
+        fs_rights_inheriting & working_dir_rights_inheriting,
         fs_flags,
         open_flags,
         inode
@@ -1918,8 +1984,8 @@ pub fn path_readlink(
     if !has_rights(base_dir.rights, __WASI_RIGHT_PATH_READLINK) {
         return __WASI_EACCES;
     }
-    let path_str = unsafe { get_input_str!(memory, path, path_len) };
-    let inode = wasi_try!(state.fs.get_inode_at_path(dir_fd, path_str, false));
+    let path_str = get_input_str!(memory, path, path_len);
+    let inode = wasi_try!(state.fs.get_inode_at_path(dir_fd, &path_str, false));
 
     if let Kind::Symlink { relative_path, .. } = &state.fs.inodes[inode].kind {
         let rel_path_str = relative_path.to_string_lossy();
@@ -1958,18 +2024,22 @@ pub fn path_remove_directory(
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
     let base_dir = wasi_try!(state.fs.fd_map.get(&fd), __WASI_EBADF);
-    let path_str = unsafe { get_input_str!(memory, path, path_len) };
+    if !has_rights(base_dir.rights, __WASI_RIGHT_PATH_REMOVE_DIRECTORY) {
+        return __WASI_EACCES;
+    }
+    let path_str = get_input_str!(memory, path, path_len);
 
-    let inode = wasi_try!(state.fs.get_inode_at_path(fd, path_str, false));
+    let inode = wasi_try!(state.fs.get_inode_at_path(fd, &path_str, false));
     let (parent_inode, childs_name) =
         wasi_try!(state
             .fs
-            .get_parent_inode_at_path(fd, std::path::Path::new(path_str), false));
+            .get_parent_inode_at_path(fd, std::path::Path::new(&path_str), false));
 
     let host_path_to_remove = match &state.fs.inodes[inode].kind {
         Kind::Dir { entries, path, .. } => {
+            let (file_system, fs_path) = state.fs.file_system(path);
             if !entries.is_empty()
-                || wasi_try!(std::fs::read_dir(path).ok(), __WASI_EIO).count() != 0
+                || !wasi_try!(file_system.read_dir(&fs_path).ok(), __WASI_EIO).is_empty()
             {
                 return __WASI_ENOTEMPTY;
             }
@@ -1993,7 +2063,8 @@ pub fn path_remove_directory(
         ),
     }
 
-    if std::fs::remove_dir(path_str).is_err() {
+    let (file_system, fs_path) = state.fs.file_system(&host_path_to_remove);
+    if file_system.remove_dir(&fs_path).is_err() {
         // reinsert to prevent FS from being in bad state
         if let Kind::Dir {
             ref mut entries, ..
@@ -2037,10 +2108,10 @@ pub fn path_rename(
         old_fd, new_fd
     );
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
-    let source_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
-    let source_path = std::path::Path::new(source_str);
-    let target_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
-    let target_path = std::path::Path::new(target_str);
+    let source_str = get_input_str!(memory, old_path, old_path_len);
+    let source_path = std::path::Path::new(&source_str);
+    let target_str = get_input_str!(memory, new_path, new_path_len);
+    let target_path = std::path::Path::new(&target_str);
     debug!("=> rename from {} to {}", source_str, target_str);
 
     {
@@ -2073,6 +2144,14 @@ pub fn path_rename(
             unreachable!("Fatal internal logic error: parent of inode is not a directory")
         }
     };
+    // A file system can only rename its own files, the files on another
+    // one, or on the host, being out of its reach
+    match &state.fs.inodes[source_parent_inode].kind {
+        Kind::Dir { path, .. } if !same_file_system(path, &host_adjusted_target_path) => {
+            return __WASI_EXDEV;
+        }
+        _ => {}
+    }
     let source_entry = match &mut state.fs.inodes[source_parent_inode].kind {
         Kind::Dir { entries, .. } => wasi_try!(entries.remove(&source_entry_name), __WASI_EINVAL),
         Kind::Root { .. } => return __WASI_ENOTCAPABLE,
@@ -2082,25 +2161,37 @@ pub fn path_rename(
     };
 
     match &mut state.fs.inodes[source_entry].kind {
-        Kind::File {
-            handle,
-            ref mut path,
-            ..
-        } => {
-            let result = if let Some(h) = handle {
-                h.rename_file(&host_adjusted_target_path)
-                    .map_err(|e| e.into_wasi_err())
-            } else {
-                let out =
-                    std::fs::rename(&path, &host_adjusted_target_path).map_err(|_| __WASI_EIO);
-                *path = host_adjusted_target_path;
-                out
+        Kind::File { handle, path, .. } => {
+            let mounted = split_mount_path(path).0.is_some();
+            let result = match handle {
+                Some(h) if !mounted => h
+                    .rename_file(&host_adjusted_target_path)
+                    .map_err(|e| e.into_wasi_err()),
+                // the file is closed or on a mounted file system, so it's
+                // renamed by its file system
+                _ => {
+                    let source_path = path.clone();
+                    let (file_system, fs_path) = state.fs.file_system(&source_path);
+                    let (_, fs_target_path) = state.fs.file_system(&host_adjusted_target_path);
+                    file_system
+                        .rename(&fs_path, &fs_target_path)
+                        .map_err(|e| e.into_wasi_err())
+                }
             };
-            // if the above operation failed we have to revert the previous change and then fail
-            if let Err(e) = result {
-                if let Kind::Dir { entries, .. } = &mut state.fs.inodes[source_parent_inode].kind {
-                    entries.insert(source_entry_name, source_entry);
-                    return e;
+            match result {
+                Ok(()) => {
+                    if let Kind::File { path, .. } = &mut state.fs.inodes[source_entry].kind {
+                        *path = host_adjusted_target_path;
+                    }
+                }
+                // if the above operation failed we have to revert the previous change and then fail
+                Err(e) => {
+                    if let Kind::Dir { entries, .. } =
+                        &mut state.fs.inodes[source_parent_inode].kind
+                    {
+                        entries.insert(source_entry_name, source_entry);
+                        return e;
+                    }
                 }
             }
         }
@@ -2144,19 +2235,19 @@ pub fn path_symlink(
 ) -> __wasi_errno_t {
     debug!("wasi::path_symlink");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
-    let old_path_str = unsafe { get_input_str!(memory, old_path, old_path_len) };
-    let new_path_str = unsafe { get_input_str!(memory, new_path, new_path_len) };
+    let old_path_str = get_input_str!(memory, old_path, old_path_len);
+    let new_path_str = get_input_str!(memory, new_path, new_path_len);
     let base_fd = wasi_try!(state.fs.get_fd(fd));
     if !has_rights(base_fd.rights, __WASI_RIGHT_PATH_SYMLINK) {
         return __WASI_EACCES;
     }
 
     // get the depth of the parent + 1 (UNDER INVESTIGATION HMMMMMMMM THINK FISH ^ THINK FISH)
-    let old_path_path = std::path::Path::new(old_path_str);
+    let old_path_path = std::path::Path::new(&old_path_str);
     let (source_inode, _) = wasi_try!(state.fs.get_parent_inode_at_path(fd, old_path_path, true));
     let depth = wasi_try!(state.fs.path_depth_from_fd(fd, source_inode)) - 1;
 
-    let new_path_path = std::path::Path::new(new_path_str);
+    let new_path_path = std::path::Path::new(&new_path_str);
     let (target_parent_inode, entry_name) =
         wasi_try!(state.fs.get_parent_inode_at_path(fd, new_path_path, true));
 
@@ -2173,7 +2264,7 @@ pub fn path_symlink(
         }
     }
 
-    let mut source_path = std::path::Path::new(old_path_str);
+    let mut source_path = std::path::Path::new(&old_path_str);
     let mut relative_path = std::path::PathBuf::new();
     for _ in 0..depth {
         relative_path.push("..");
@@ -2187,7 +2278,7 @@ pub fn path_symlink(
 
     let kind = Kind::Symlink {
         base_po_dir: fd,
-        path_to_symlink: std::path::PathBuf::from(new_path_str),
+        path_to_symlink: std::path::PathBuf::from(&new_path_str),
         relative_path,
     };
     let new_inode = state
@@ -2226,14 +2317,14 @@ pub fn path_unlink_file(
     if !has_rights(base_dir.rights, __WASI_RIGHT_PATH_UNLINK_FILE) {
         return __WASI_EACCES;
     }
-    let path_str = unsafe { get_input_str!(memory, path, path_len) };
+    let path_str = get_input_str!(memory, path, path_len);
     debug!("Requested file: {}", path_str);
 
-    let inode = wasi_try!(state.fs.get_inode_at_path(fd, path_str, false));
+    let inode = wasi_try!(state.fs.get_inode_at_path(fd, &path_str, false));
     let (parent_inode, childs_name) =
         wasi_try!(state
             .fs
-            .get_parent_inode_at_path(fd, std::path::Path::new(path_str), false));
+            .get_parent_inode_at_path(fd, std::path::Path::new(&path_str), false));
 
     let removed_inode = match &mut state.fs.inodes[parent_inode].kind {
         Kind::Dir {
@@ -2255,13 +2346,20 @@ pub fn path_unlink_file(
     if state.fs.inodes[removed_inode].stat.st_nlink == 0 {
         match &mut state.fs.inodes[removed_inode].kind {
             Kind::File { handle, path, .. } => {
-                if let Some(h) = handle {
-                    wasi_try!(h.unlink().map_err(WasiFsError::into_wasi_err));
-                } else {
-                    // File is closed
-                    // problem with the abstraction, we can't call unlink because there's no handle
-                    // TODO: replace this code
-                    wasi_try!(std::fs::remove_file(path).map_err(|_| __WASI_EIO));
+                let mounted = split_mount_path(path).0.is_some();
+                match handle {
+                    Some(h) if !mounted => {
+                        wasi_try!(h.unlink().map_err(WasiFsError::into_wasi_err));
+                    }
+                    // File is closed or on a mounted file system, so it's
+                    // removed by its file system
+                    _ => {
+                        let path = path.clone();
+                        let (file_system, fs_path) = state.fs.file_system(&path);
+                        wasi_try!(file_system
+                            .remove_file(&fs_path)
+                            .map_err(WasiFsError::into_wasi_err));
+                    }
                 }
             }
             Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
@@ -2316,184 +2414,277 @@ pub fn poll_oneoff(
 ) -> __wasi_errno_t {
     debug!("wasi::poll_oneoff");
     debug!("  => nsubscriptions = {}", nsubscriptions);
-    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let memory = env.memory();
 
+    // Nothing would ever happen
+    if nsubscriptions == 0 {
+        return __WASI_EINVAL;
+    }
     let subscription_array = wasi_try!(in_.deref(memory, 0, nsubscriptions));
     let event_array = wasi_try!(out_.deref(memory, 0, nsubscriptions));
-    let mut events_seen = 0;
     let out_ptr = wasi_try!(nevents.deref(memory));
 
-    let mut fds = vec![];
+    let start = Instant::now();
+    let mut fd_subs = vec![];
     let mut clock_subs = vec![];
-    let mut in_events = vec![];
-    let mut total_ns_slept = 0;
-
-    for sub in subscription_array.iter() {
-        let s: WasiSubscription = wasi_try!(sub.get().try_into());
-        let mut peb = PollEventBuilder::new();
-        let mut ns_to_sleep = 0;
-
-        let fd = match s.event_type {
-            EventType::Read(__wasi_subscription_fs_readwrite_t { fd }) => {
-                match fd {
-                    __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
-                    _ => {
-                        let fd_entry = wasi_try!(state.fs.get_fd(fd));
-                        if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_READ) {
-                            return __WASI_EACCES;
-                        }
-                    }
+    {
+        let state = env.state();
+        for sub in subscription_array.iter() {
+            let sub = sub.get();
+            let s: WasiSubscription = wasi_try!(sub.try_into());
+
+            let (fd, event) = match s.event_type {
+                EventType::Read(__wasi_subscription_fs_readwrite_t { fd }) => {
+                    (fd, PollEvent::PollIn)
                 }
-                in_events.push(peb.add(PollEvent::PollIn).build());
-                Some(fd)
-            }
-            EventType::Write(__wasi_subscription_fs_readwrite_t { fd }) => {
-                match fd {
-                    __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
-                    _ => {
-                        let fd_entry = wasi_try!(state.fs.get_fd(fd));
-
-                        if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE) {
-                            return __WASI_EACCES;
-                        }
-                    }
+                EventType::Write(__wasi_subscription_fs_readwrite_t { fd }) => {
+                    (fd, PollEvent::PollOut)
                 }
-                in_events.push(peb.add(PollEvent::PollOut).build());
-                Some(fd)
-            }
-            EventType::Clock(clock_info) => {
-                if clock_info.clock_id == __WASI_CLOCK_REALTIME {
-                    // this is a hack
-                    // TODO: do this properly
-                    ns_to_sleep = clock_info.timeout;
-                    clock_subs.push(clock_info);
-                    None
-                } else {
-                    unimplemented!("Polling not implemented for clocks yet");
+                EventType::Clock(clock_info) => {
+                    // Errors are reported in the event, right away
+                    let deadline =
+                        clock_timeout(&state, &clock_info).map(|timeout| start + timeout);
+                    clock_subs.push((sub, deadline));
+                    continue;
                 }
-            }
-        };
-
-        if let Some(fd) = fd {
-            let wasi_file_ref: &dyn WasiFile = match fd {
-                __WASI_STDERR_FILENO => wasi_try!(
-                    wasi_try!(state.fs.stderr().map_err(WasiFsError::into_wasi_err)).as_ref(),
-                    __WASI_EBADF
-                )
-                .as_ref(),
-                __WASI_STDIN_FILENO => wasi_try!(
-                    wasi_try!(state.fs.stdin().map_err(WasiFsError::into_wasi_err)).as_ref(),
-                    __WASI_EBADF
-                )
-                .as_ref(),
-                __WASI_STDOUT_FILENO => wasi_try!(
-                    wasi_try!(state.fs.stdout().map_err(WasiFsError::into_wasi_err)).as_ref(),
-                    __WASI_EBADF
-                )
-                .as_ref(),
-                _ => {
-                    let fd_entry = wasi_try!(state.fs.get_fd(fd));
-                    let inode = fd_entry.inode;
-                    if !has_rights(fd_entry.rights, __WASI_RIGHT_POLL_FD_READWRITE) {
-                        return __WASI_EACCES;
-                    }
+            };
+            // The files are checked before waiting for any of them
+            wasi_try!(poll_readiness(&state, fd, event.clone()));
+            fd_subs.push((sub, fd, event));
+        }
+    }
 
-                    match &state.fs.inodes[inode].kind {
-                        Kind::File { handle, .. } => {
-                            if let Some(h) = handle {
-                                h.as_ref()
-                            } else {
-                                return __WASI_EBADF;
-                            }
-                        }
-                        Kind::Dir { .. }
-                        | Kind::Root { .. }
-                        | Kind::Buffer { .. }
-                        | Kind::Symlink { .. } => {
-                            unimplemented!("polling read on non-files not yet supported")
-                        }
+    // Wait until a file is ready or a clock times out. The state isn't
+    // locked while waiting, so the files are looked up on each round.
+    let mut fd_events = vec![None; fd_subs.len()];
+    loop {
+        let mut host_subs = vec![];
+        let mut host_fds = vec![];
+        let mut in_events = vec![];
+        let mut pending_in_memory = false;
+        {
+            let state = env.state();
+            for (i, (_, fd, event)) in fd_subs.iter().enumerate() {
+                match wasi_try!(poll_readiness(&state, *fd, event.clone())) {
+                    Readiness::Host(host_fd) => {
+                        host_subs.push(i);
+                        host_fds.push(host_fd);
+                        in_events.push(PollEventBuilder::new().add(event.clone()).build());
                     }
+                    Readiness::Ready(nbytes) => fd_events[i] = Some((__WASI_ESUCCESS, 0, nbytes)),
+                    Readiness::NotReady => pending_in_memory = true,
                 }
-            };
-            fds.push(wasi_file_ref);
-        } else {
-            let remaining_ns = ns_to_sleep as i64 - total_ns_slept as i64;
-            if remaining_ns > 0 {
-                debug!("Sleeping for {} nanoseconds", remaining_ns);
-                let duration = std::time::Duration::from_nanos(remaining_ns as u64);
-                std::thread::sleep(duration);
-                total_ns_slept += remaining_ns;
             }
         }
-    }
-    let mut seen_events = vec![Default::default(); in_events.len()];
-    wasi_try!(poll(
-        fds.as_slice(),
-        in_events.as_slice(),
-        seen_events.as_mut_slice()
-    )
-    .map_err(|e| e.into_wasi_err()));
-
-    for (i, seen_event) in seen_events.into_iter().enumerate() {
-        let mut flags = 0;
-        let mut error = __WASI_EAGAIN;
-        let mut bytes_available = 0;
-        let event_iter = iterate_poll_events(seen_event);
-        for event in event_iter {
-            match event {
-                PollEvent::PollError => error = __WASI_EIO,
-                PollEvent::PollHangUp => flags = __WASI_EVENT_FD_READWRITE_HANGUP,
-                PollEvent::PollInvalid => error = __WASI_EINVAL,
-                PollEvent::PollIn => {
-                    bytes_available =
-                        wasi_try!(fds[i].bytes_available().map_err(|e| e.into_wasi_err()));
-                    error = __WASI_ESUCCESS;
-                }
-                PollEvent::PollOut => {
-                    bytes_available =
-                        wasi_try!(fds[i].bytes_available().map_err(|e| e.into_wasi_err()));
-                    error = __WASI_ESUCCESS;
+
+        let now = Instant::now();
+        let clock_timeout = clock_subs
+            .iter()
+            .map(|(_, deadline)| match deadline {
+                Ok(deadline) if *deadline > now => *deadline - now,
+                _ => Duration::from_secs(0),
+            })
+            .min();
+        let timeout = if fd_events.iter().any(Option::is_some) {
+            // Only check which other files are ready
+            Some(Duration::from_secs(0))
+        } else if pending_in_memory {
+            // The files in memory are checked again after a while
+            Some(clock_timeout.map_or(POLL_INTERVAL, |timeout| timeout.min(POLL_INTERVAL)))
+        } else {
+            clock_timeout
+        };
+
+        let mut seen_events = vec![Default::default(); host_fds.len()];
+        wasi_try!(poll(
+            host_fds.as_slice(),
+            in_events.as_slice(),
+            seen_events.as_mut_slice(),
+            timeout
+        )
+        .map_err(|e| e.into_wasi_err()));
+        for ((i, host_fd), seen_event) in host_subs.into_iter().zip(host_fds).zip(seen_events) {
+            if seen_event == 0 {
+                continue;
+            }
+            let mut flags = 0;
+            let mut error = __WASI_ESUCCESS;
+            for event in iterate_poll_events(seen_event) {
+                match event {
+                    PollEvent::PollError => error = __WASI_EIO,
+                    PollEvent::PollHangUp => flags = __WASI_EVENT_FD_READWRITE_HANGUP,
+                    PollEvent::PollInvalid => error = __WASI_EBADF,
+                    PollEvent::PollIn | PollEvent::PollOut => (),
                 }
             }
+            // Only the bytes to read are known
+            let nbytes = match fd_subs[i].2 {
+                PollEvent::PollIn => host_file_bytes_available(host_fd).unwrap_or(0),
+                _ => 0,
+            };
+            fd_events[i] = Some((error, flags, nbytes));
+        }
+
+        let now = Instant::now();
+        let clock_ready = clock_subs.iter().any(|(_, deadline)| match deadline {
+            Ok(deadline) => *deadline <= now,
+            Err(_) => true,
+        });
+        if clock_ready || fd_events.iter().any(Option::is_some) {
+            break;
         }
-        let event = __wasi_event_t {
-            userdata: subscription_array[i].get().userdata,
+    }
+
+    let mut events_seen = 0;
+    for ((sub, _, _), fd_event) in fd_subs.iter().zip(fd_events) {
+        let (error, flags, nbytes) = match fd_event {
+            Some(fd_event) => fd_event,
+            None => continue,
+        };
+        event_array[events_seen].set(__wasi_event_t {
+            userdata: sub.userdata,
             error,
-            type_: subscription_array[i].get().type_,
-            u: unsafe {
-                __wasi_event_u {
-                    fd_readwrite: __wasi_event_fd_readwrite_t {
-                        nbytes: bytes_available as u64,
-                        flags,
-                    },
-                }
+            type_: sub.type_,
+            u: __wasi_event_u {
+                fd_readwrite: __wasi_event_fd_readwrite_t {
+                    nbytes: nbytes as u64,
+                    flags,
+                },
             },
-        };
-        event_array[events_seen].set(event);
+        });
         events_seen += 1;
     }
-    for clock_info in clock_subs {
-        let event = __wasi_event_t {
-            // TOOD: review userdata value
-            userdata: 0,
-            error: __WASI_ESUCCESS,
+    let now = Instant::now();
+    for (sub, deadline) in clock_subs {
+        let error = match deadline {
+            Ok(deadline) if deadline > now => continue,
+            Ok(_) => __WASI_ESUCCESS,
+            Err(errno) => errno,
+        };
+        event_array[events_seen].set(__wasi_event_t {
+            userdata: sub.userdata,
+            error,
             type_: __WASI_EVENTTYPE_CLOCK,
-            u: unsafe {
-                __wasi_event_u {
-                    fd_readwrite: __wasi_event_fd_readwrite_t {
-                        nbytes: 0,
-                        flags: 0,
-                    },
-                }
+            u: __wasi_event_u {
+                fd_readwrite: __wasi_event_fd_readwrite_t {
+                    nbytes: 0,
+                    flags: 0,
+                },
             },
-        };
-        event_array[events_seen].set(event);
+        });
         events_seen += 1;
     }
     out_ptr.set(events_seen as u32);
     __WASI_ESUCCESS
 }
 
+/// How often `poll_oneoff` checks whether the files in memory, which
+/// can't be polled on the host, are ready.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Whether a file subscribed to with `poll_oneoff` is ready.
+enum Readiness {
+    /// The file is polled on the host, with its file descriptor
+    Host(i32),
+    /// The file is ready, with the number of bytes to read
+    Ready(usize),
+    /// The file is in memory and isn't ready yet
+    NotReady,
+}
+
+/// Finds whether the file `fd` is ready for `event`, without blocking.
+fn poll_readiness(
+    state: &WasiState,
+    fd: __wasi_fd_t,
+    event: PollEvent,
+) -> Result<Readiness, __wasi_errno_t> {
+    fn std_dev(
+        file: Result<&Option<Box<dyn WasiFile>>, WasiFsError>,
+    ) -> Result<&dyn WasiFile, __wasi_errno_t> {
+        file.map_err(WasiFsError::into_wasi_err)?
+            .as_deref()
+            .ok_or(__WASI_EBADF)
+    }
+
+    let read = matches!(event, PollEvent::PollIn);
+    let file: &dyn WasiFile = match fd {
+        __WASI_STDIN_FILENO => std_dev(state.fs.stdin())?,
+        __WASI_STDOUT_FILENO => std_dev(state.fs.stdout())?,
+        __WASI_STDERR_FILENO => std_dev(state.fs.stderr())?,
+        _ => {
+            let fd_entry = state.fs.get_fd(fd)?;
+            let right = if read {
+                __WASI_RIGHT_FD_READ
+            } else {
+                __WASI_RIGHT_FD_WRITE
+            };
+            if !has_rights(fd_entry.rights, right | __WASI_RIGHT_POLL_FD_READWRITE) {
+                return Err(__WASI_EACCES);
+            }
+            match &state.fs.inodes[fd_entry.inode].kind {
+                Kind::File {
+                    handle: Some(handle),
+                    ..
+                } => handle.as_ref(),
+                Kind::File { handle: None, .. } => return Err(__WASI_EBADF),
+                Kind::Dir { .. } | Kind::Root { .. } | Kind::Symlink { .. } => {
+                    return Err(__WASI_EISDIR)
+                }
+                // Buffers are read and written right away
+                Kind::Buffer { buffer } => {
+                    return Ok(Readiness::Ready(if read { buffer.len() } else { 0 }))
+                }
+            }
+        }
+    };
+    #[cfg(unix)]
+    {
+        if let Some(host_fd) = file.get_raw_fd() {
+            return Ok(Readiness::Host(host_fd));
+        }
+    }
+    // Only the pipes can be checked on other platforms, where the other
+    // files are reported ready
+    #[cfg(not(unix))]
+    {
+        let file = state::Upcastable::upcast_any_ref(file);
+        if file.downcast_ref::<state::Pipe>().is_none() {
+            return Ok(Readiness::Ready(0));
+        }
+    }
+    if !read {
+        // Writing to a file in memory never blocks
+        return Ok(Readiness::Ready(0));
+    }
+    Ok(match file.bytes_available() {
+        Ok(0) => Readiness::NotReady,
+        Ok(nbytes) => Readiness::Ready(nbytes),
+        // Without knowing, a read is let through
+        Err(_) => Readiness::Ready(0),
+    })
+}
+
+/// Gets how long a clock subscription of `poll_oneoff` waits.
+fn clock_timeout(
+    state: &WasiState,
+    clock_info: &__wasi_subscription_clock_t,
+) -> Result<Duration, __wasi_errno_t> {
+    if clock_info.clock_id != __WASI_CLOCK_REALTIME && clock_info.clock_id != __WASI_CLOCK_MONOTONIC
+    {
+        return Err(__WASI_EINVAL);
+    }
+    // A fixed clock doesn't follow the time of the host, so it's never
+    // waited for
+    if state.clock.is_some() {
+        return Ok(Duration::from_secs(0));
+    }
+    if clock_info.flags & __WASI_SUBSCRIPTION_CLOCK_ABSTIME == 0 {
+        return Ok(Duration::from_nanos(clock_info.timeout));
+    }
+    let now = platform_clock_time_get(clock_info.clock_id, 1)?;
+    Ok(Duration::from_nanos(clock_info.timeout.saturating_sub(now)))
+}
+
 pub fn proc_exit(env: &WasiEnv, code: __wasi_exitcode_t) {
     debug!("wasi::proc_exit, {}", code);
     RuntimeError::raise(Box::new(WasiError::Exit(code)));
@@ -2514,17 +2705,20 @@ pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
 ///     The number of bytes that will be written
 pub fn random_get(env: &WasiEnv, buf: WasmPtr<u8, Array>, buf_len: u32) -> __wasi_errno_t {
     debug!("wasi::random_get buf_len: {}", buf_len);
-    let memory = env.memory();
-
-    let buf = wasi_try!(buf.deref(memory, 0, buf_len));
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
 
-    let res = unsafe {
-        let u8_buffer = &mut *(buf as *const [_] as *mut [_] as *mut [u8]);
-        getrandom::getrandom(u8_buffer)
-    };
-    match res {
+    let mut u8_buffer = vec![0; buf_len as usize];
+    match &mut state.random {
+        Some(random) => random.fill(&mut u8_buffer),
+        None => {
+            if getrandom::getrandom(&mut u8_buffer).is_err() {
+                return __WASI_EIO;
+            }
+        }
+    }
+    match memory.write(buf.offset() as u64, &u8_buffer) {
         Ok(()) => __WASI_ESUCCESS,
-        Err(_) => __WASI_EIO,
+        Err(_) => __WASI_EFAULT,
     }
 }
 
@@ -2536,6 +2730,25 @@ pub fn sched_yield(env: &WasiEnv) -> __wasi_errno_t {
     __WASI_ESUCCESS
 }
 
+/// The maximum number of bytes received at once by `sock_recv`
+const MAX_RECV_SIZE: usize = 64 * 1024;
+
+/// ### `sock_recv()`
+/// Receive a message from a socket
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket to receive from
+/// - `__wasi_iovec_t *ri_data`
+///     Vectors where the data will be stored
+/// - `u32 ri_data_len`
+///     Length of `ri_data`
+/// - `__wasi_riflags_t ri_flags`
+///     Message flags
+/// Output:
+/// - `u32 *ro_datalen`
+///     Number of bytes stored in `ri_data`
+/// - `__wasi_roflags_t *ro_flags`
+///     Message flags
 pub fn sock_recv(
     env: &WasiEnv,
     sock: __wasi_fd_t,
@@ -2545,9 +2758,72 @@ pub fn sock_recv(
     ro_datalen: WasmPtr<u32>,
     ro_flags: WasmPtr<__wasi_roflags_t>,
 ) -> __wasi_errno_t {
-    debug!("wasi::sock_recv");
-    unimplemented!("wasi::sock_recv")
+    debug!("wasi::sock_recv: sock={}", sock);
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let iovs_arr_cell = wasi_try!(ri_data.deref(memory, 0, ri_data_len));
+    let ro_datalen_cell = wasi_try!(ro_datalen.deref(memory));
+    let ro_flags_cell = wasi_try!(ro_flags.deref(memory));
+
+    if !has_rights(
+        wasi_try!(state.fs.get_fd(sock)).rights,
+        __WASI_RIGHT_FD_READ,
+    ) {
+        return __WASI_EACCES;
+    }
+    let socket = wasi_try!(state.fs.get_socket_mut(sock));
+    // The lock isn't held while blocking
+    let socket = wasi_try!(socket
+        .try_clone()
+        .map_err(|e| WasiFsError::from(e).into_wasi_err()));
+    drop(state);
+
+    // Receive everything at once, so that peeking sees the same data as
+    // the next receive.
+    let capacity = iovs_arr_cell
+        .iter()
+        .map(|iov| iov.get().buf_len as usize)
+        .fold(0, usize::saturating_add)
+        .min(MAX_RECV_SIZE);
+    let mut buf = vec![0; capacity];
+    let peek = ri_flags & __WASI_SOCK_RECV_PEEK != 0;
+    let received = wasi_try!(socket
+        .recv(&mut buf, peek)
+        .map_err(|e| WasiFsError::from(e).into_wasi_err()));
+
+    let mut offset = 0;
+    for iov in iovs_arr_cell {
+        if offset == received {
+            break;
+        }
+        let iov_inner = iov.get();
+        let len = std::cmp::min(iov_inner.buf_len as usize, received - offset);
+        let bytes = wasi_try!(iov_inner.buf.deref(memory, 0, len as u32));
+        for (cell, byte) in bytes.iter().zip(&buf[offset..offset + len]) {
+            cell.set(*byte);
+        }
+        offset += len;
+    }
+
+    ro_datalen_cell.set(received as u32);
+    ro_flags_cell.set(0);
+
+    __WASI_ESUCCESS
 }
+
+/// ### `sock_send()`
+/// Send a message on a socket
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket to send on
+/// - `const __wasi_ciovec_t *si_data`
+///     Vectors of the data to send
+/// - `u32 si_data_len`
+///     Length of `si_data`
+/// - `__wasi_siflags_t si_flags`
+///     Message flags, none are defined
+/// Output:
+/// - `u32 *so_datalen`
+///     Number of bytes sent
 pub fn sock_send(
     env: &WasiEnv,
     sock: __wasi_fd_t,
@@ -2556,10 +2832,106 @@ pub fn sock_send(
     si_flags: __wasi_siflags_t,
     so_datalen: WasmPtr<u32>,
 ) -> __wasi_errno_t {
-    debug!("wasi::sock_send");
-    unimplemented!("wasi::sock_send")
+    debug!("wasi::sock_send: sock={}", sock);
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let iovs_arr_cell = wasi_try!(si_data.deref(memory, 0, si_data_len));
+    let so_datalen_cell = wasi_try!(so_datalen.deref(memory));
+
+    if !has_rights(
+        wasi_try!(state.fs.get_fd(sock)).rights,
+        __WASI_RIGHT_FD_WRITE,
+    ) {
+        return __WASI_EACCES;
+    }
+    let socket = wasi_try!(state.fs.get_socket_mut(sock));
+    // The lock isn't held while blocking
+    let socket = wasi_try!(socket
+        .try_clone()
+        .map_err(|e| WasiFsError::from(e).into_wasi_err()));
+    drop(state);
+    let bytes_written = wasi_try!(write_bytes(socket, memory, &iovs_arr_cell));
+
+    so_datalen_cell.set(bytes_written);
+
+    __WASI_ESUCCESS
 }
+
+/// ### `sock_shutdown()`
+/// Shut down the receiving and/or sending side of a socket
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket to shut down
+/// - `__wasi_sdflags_t how`
+///     Which sides to shut down
 pub fn sock_shutdown(env: &WasiEnv, sock: __wasi_fd_t, how: __wasi_sdflags_t) -> __wasi_errno_t {
-    debug!("wasi::sock_shutdown");
-    unimplemented!("wasi::sock_shutdown")
+    debug!("wasi::sock_shutdown: sock={}, how={}", sock, how);
+    let mut state = env.state();
+
+    let how = match how {
+        __WASI_SHUT_RD => std::net::Shutdown::Read,
+        __WASI_SHUT_WR => std::net::Shutdown::Write,
+        how if how == __WASI_SHUT_RD | __WASI_SHUT_WR => std::net::Shutdown::Both,
+        _ => return __WASI_EINVAL,
+    };
+    if !has_rights(
+        wasi_try!(state.fs.get_fd(sock)).rights,
+        __WASI_RIGHT_SOCK_SHUTDOWN,
+    ) {
+        return __WASI_EACCES;
+    }
+    let socket = wasi_try!(state.fs.get_socket_mut(sock));
+    wasi_try!(socket
+        .shutdown(how)
+        .map_err(|e| WasiFsError::from(e).into_wasi_err()));
+
+    __WASI_ESUCCESS
+}
+
+/// ### `sock_accept()`
+/// Accept a connection on a listening socket
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The listening socket
+/// - `__wasi_fdflags_t fd_flags`
+///     The flags of the new file descriptor
+/// Output:
+/// - `__wasi_fd_t *ro_fd`
+///     The file descriptor of the connection
+pub fn sock_accept(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    fd_flags: __wasi_fdflags_t,
+    ro_fd: WasmPtr<__wasi_fd_t>,
+) -> __wasi_errno_t {
+    debug!("wasi::sock_accept: sock={}", sock);
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let ro_fd_cell = wasi_try!(ro_fd.deref(memory));
+
+    if !has_rights(
+        wasi_try!(state.fs.get_fd(sock)).rights,
+        __WASI_RIGHT_FD_READ,
+    ) {
+        return __WASI_EACCES;
+    }
+    let socket = wasi_try!(state.fs.get_socket_mut(sock));
+    // The lock isn't held while blocking
+    let socket = wasi_try!(socket
+        .try_clone()
+        .map_err(|e| WasiFsError::from(e).into_wasi_err()));
+    drop(state);
+    let (connection, addr) = wasi_try!(socket
+        .accept()
+        .map_err(|e| WasiFsError::from(e).into_wasi_err()));
+    debug!("wasi::sock_accept: connection from {}", addr);
+    if fd_flags & __WASI_FDFLAG_NONBLOCK != 0 {
+        wasi_try!(connection
+            .set_nonblocking(true)
+            .map_err(|e| WasiFsError::from(e).into_wasi_err()));
+    }
+    let mut state = env.state();
+    let fd = wasi_try!(state.fs.create_socket_fd(connection, fd_flags));
+
+    ro_fd_cell.set(fd);
+
+    __WASI_ESUCCESS
 }
@@ -0,0 +1,181 @@
+//! Experimental functions opening TCP sockets, which WASI has no way to
+//! do yet. They are only available when the network is enabled with
+//! [`WasiStateBuilder::allow_network`](crate::WasiStateBuilder::allow_network).
+//!
+//! Addresses are passed as `host:port` strings.
+use super::types::*;
+use crate::ptr::{Array, WasmPtr};
+use crate::state::{Socket, WasiFsError};
+use crate::WasiEnv;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use tracing::debug;
+
+/// The namespace of the functions in this module.
+pub(crate) const SOCKETS_NAMESPACE: &str = "wasi_experimental_sockets";
+
+/// Reads the `host:port` string at `addr`, and resolves it if the network
+/// policy allows it.
+///
+/// The lock of the state isn't held while resolving, which may block.
+fn resolve(
+    env: &WasiEnv,
+    addr: WasmPtr<u8, Array>,
+    addr_len: u32,
+) -> Result<Vec<SocketAddr>, __wasi_errno_t> {
+    let addr = addr
+        .get_utf8_string(env.memory(), addr_len)
+        .ok_or(__WASI_EINVAL)?;
+    debug!("=> addr: {}", addr);
+    let network = env.state().network.clone();
+    if !network.is_enabled() {
+        return Err(__WASI_ENOTCAPABLE);
+    }
+    let (host, port) = match addr.rfind(':') {
+        Some(index) => (&addr[..index], &addr[index + 1..]),
+        None => return Err(__WASI_EINVAL),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = port.parse().map_err(|_| __WASI_EINVAL)?;
+    if !network.allows(host, port) {
+        debug!("=> denied by the network policy");
+        return Err(__WASI_ENOTCAPABLE);
+    }
+    let addrs = (host, port)
+        .to_socket_addrs()
+        .map_err(|_| __WASI_EADDRNOTAVAIL)?
+        .collect::<Vec<_>>();
+    Ok(addrs)
+}
+
+/// ### `sock_connect()`
+/// Open a TCP connection
+/// Inputs:
+/// - `const char *addr`
+///     The `host:port` to connect to
+/// - `u32 addr_len`
+///     The length of `addr`
+/// Output:
+/// - `__wasi_fd_t *ro_fd`
+///     The file descriptor of the connection
+pub fn sock_connect(
+    env: &WasiEnv,
+    addr: WasmPtr<u8, Array>,
+    addr_len: u32,
+    ro_fd: WasmPtr<__wasi_fd_t>,
+) -> __wasi_errno_t {
+    debug!("wasi::sock_connect");
+    let ro_fd_cell = wasi_try!(ro_fd.deref(env.memory()));
+
+    let addrs = wasi_try!(resolve(env, addr, addr_len));
+    let stream = TcpStream::connect(&addrs[..]).map_err(WasiFsError::from);
+    let stream = wasi_try!(stream.map_err(WasiFsError::into_wasi_err));
+    let mut state = env.state();
+    let fd = wasi_try!(state.fs.create_socket_fd(Socket::Stream(stream), 0));
+
+    ro_fd_cell.set(fd);
+
+    __WASI_ESUCCESS
+}
+
+/// ### `sock_listen()`
+/// Listen for TCP connections, accepted with `sock_accept`
+/// Inputs:
+/// - `const char *addr`
+///     The `host:port` to listen on
+/// - `u32 addr_len`
+///     The length of `addr`
+/// Output:
+/// - `__wasi_fd_t *ro_fd`
+///     The file descriptor of the listening socket
+pub fn sock_listen(
+    env: &WasiEnv,
+    addr: WasmPtr<u8, Array>,
+    addr_len: u32,
+    ro_fd: WasmPtr<__wasi_fd_t>,
+) -> __wasi_errno_t {
+    debug!("wasi::sock_listen");
+    let ro_fd_cell = wasi_try!(ro_fd.deref(env.memory()));
+
+    let addrs = wasi_try!(resolve(env, addr, addr_len));
+    let listener = TcpListener::bind(&addrs[..]).map_err(WasiFsError::from);
+    let listener = wasi_try!(listener.map_err(WasiFsError::into_wasi_err));
+    let mut state = env.state();
+    let fd = wasi_try!(state.fs.create_socket_fd(Socket::Listener(listener), 0));
+
+    ro_fd_cell.set(fd);
+
+    __WASI_ESUCCESS
+}
+
+/// ### `sock_addr_local()`
+/// Get the local address of a socket, as `ip:port`
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket
+/// - `char *buf`
+///     Where to write the address
+/// - `u32 buf_len`
+///     The length of `buf`
+/// Output:
+/// - `u32 *ro_len`
+///     The length of the address written to `buf`
+pub fn sock_addr_local(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    buf: WasmPtr<u8, Array>,
+    buf_len: u32,
+    ro_len: WasmPtr<u32>,
+) -> __wasi_errno_t {
+    debug!("wasi::sock_addr_local: sock={}", sock);
+    sock_addr(env, sock, buf, buf_len, ro_len, Socket::local_addr)
+}
+
+/// ### `sock_addr_peer()`
+/// Get the address of the peer of a connection, as `ip:port`
+/// Inputs:
+/// - `__wasi_fd_t sock`
+///     The socket
+/// - `char *buf`
+///     Where to write the address
+/// - `u32 buf_len`
+///     The length of `buf`
+/// Output:
+/// - `u32 *ro_len`
+///     The length of the address written to `buf`
+pub fn sock_addr_peer(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    buf: WasmPtr<u8, Array>,
+    buf_len: u32,
+    ro_len: WasmPtr<u32>,
+) -> __wasi_errno_t {
+    debug!("wasi::sock_addr_peer: sock={}", sock);
+    sock_addr(env, sock, buf, buf_len, ro_len, Socket::peer_addr)
+}
+
+fn sock_addr(
+    env: &WasiEnv,
+    sock: __wasi_fd_t,
+    buf: WasmPtr<u8, Array>,
+    buf_len: u32,
+    ro_len: WasmPtr<u32>,
+    get_addr: fn(&Socket) -> std::io::Result<SocketAddr>,
+) -> __wasi_errno_t {
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let ro_len_cell = wasi_try!(ro_len.deref(memory));
+
+    let socket = wasi_try!(state.fs.get_socket_mut(sock));
+    let addr = wasi_try!(get_addr(socket).map_err(|e| WasiFsError::from(e).into_wasi_err()));
+    let addr = addr.to_string();
+    if addr.len() > buf_len as usize {
+        return __WASI_EOVERFLOW;
+    }
+    let out = wasi_try!(buf.deref(memory, 0, addr.len() as u32));
+    for (cell, byte) in out.iter().zip(addr.bytes()) {
+        cell.set(byte);
+    }
+
+    ro_len_cell.set(addr.len() as u32);
+
+    __WASI_ESUCCESS
+}
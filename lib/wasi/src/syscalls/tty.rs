@@ -0,0 +1,88 @@
+//! Experimental functions for terminals, which WASI has no way to query or
+//! configure yet. They only work on the file descriptors backed by a
+//! terminal of the host, and fail with `ENOTTY` on the others.
+//!
+//! Raw mode stays on until it's turned off, or
+//! [`restore_terminals`](crate::restore_terminals) is called.
+use super::types::*;
+use crate::ptr::WasmPtr;
+#[cfg(unix)]
+use crate::state::{is_terminal, Kind};
+use crate::state::{set_raw_mode, terminal_size, WasiFsError, WasiState};
+use crate::WasiEnv;
+use tracing::debug;
+
+/// The namespace of the functions in this module.
+pub(crate) const TTY_NAMESPACE: &str = "wasi_experimental_tty";
+
+/// Gets the host file descriptor of the terminal backing `fd`.
+fn host_terminal(state: &mut WasiState, fd: __wasi_fd_t) -> Result<i32, __wasi_errno_t> {
+    #[cfg(unix)]
+    {
+        match &state.fs.get_inodeval_mut(fd)?.kind {
+            Kind::File {
+                handle: Some(handle),
+                ..
+            } => match handle.get_raw_fd() {
+                Some(host_fd) if is_terminal(host_fd) => Ok(host_fd),
+                _ => Err(__WASI_ENOTTY),
+            },
+            _ => Err(__WASI_ENOTTY),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (state, fd);
+        Err(__WASI_ENOTSUP)
+    }
+}
+
+/// ### `tty_get_size()`
+/// Get the size of a terminal
+/// Inputs:
+/// - `__wasi_fd_t fd`
+///     The terminal
+/// Output:
+/// - `u32 *ro_cols`
+///     The number of columns of the terminal
+/// - `u32 *ro_rows`
+///     The number of rows of the terminal
+pub fn tty_get_size(
+    env: &WasiEnv,
+    fd: __wasi_fd_t,
+    ro_cols: WasmPtr<u32>,
+    ro_rows: WasmPtr<u32>,
+) -> __wasi_errno_t {
+    debug!("wasi::tty_get_size: fd={}", fd);
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let ro_cols_cell = wasi_try!(ro_cols.deref(memory));
+    let ro_rows_cell = wasi_try!(ro_rows.deref(memory));
+
+    let host_fd = wasi_try!(host_terminal(&mut state, fd));
+    let size = terminal_size(host_fd).map_err(WasiFsError::from);
+    let (cols, rows) = wasi_try!(size.map_err(WasiFsError::into_wasi_err));
+
+    ro_cols_cell.set(cols as u32);
+    ro_rows_cell.set(rows as u32);
+
+    __WASI_ESUCCESS
+}
+
+/// ### `tty_set_raw()`
+/// Turn raw mode on or off: in raw mode, the input is available as it's
+/// typed, without being echoed or edited by the terminal
+/// Inputs:
+/// - `__wasi_fd_t fd`
+///     The terminal
+/// - `u32 raw`
+///     Whether to turn raw mode on
+pub fn tty_set_raw(env: &WasiEnv, fd: __wasi_fd_t, raw: u32) -> __wasi_errno_t {
+    debug!("wasi::tty_set_raw: fd={}, raw={}", fd, raw);
+    let mut state = env.state();
+
+    let host_fd = wasi_try!(host_terminal(&mut state, fd));
+    let result = set_raw_mode(host_fd, raw != 0).map_err(WasiFsError::from);
+    wasi_try!(result.map_err(WasiFsError::into_wasi_err));
+
+    __WASI_ESUCCESS
+}
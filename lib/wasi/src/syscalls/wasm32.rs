@@ -0,0 +1,26 @@
+use crate::syscalls::types::*;
+
+pub fn platform_clock_res_get(
+    clock_id: __wasi_clockid_t,
+) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+    match clock_id {
+        // `Date.now()` counts milliseconds
+        __WASI_CLOCK_MONOTONIC | __WASI_CLOCK_REALTIME => Ok(1_000_000),
+        _ => Err(__WASI_EINVAL),
+    }
+}
+
+pub fn platform_clock_time_get(
+    clock_id: __wasi_clockid_t,
+    precision: __wasi_timestamp_t,
+) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+    match clock_id {
+        // The host doesn't provide a monotonic clock without `web-sys`, so
+        // both clocks follow the time of the host.
+        __WASI_CLOCK_MONOTONIC | __WASI_CLOCK_REALTIME => {
+            let millis = js_sys::Date::now();
+            Ok((millis * 1_000_000.0) as __wasi_timestamp_t)
+        }
+        _ => Err(__WASI_EINVAL),
+    }
+}
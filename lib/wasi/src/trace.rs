@@ -0,0 +1,526 @@
+//! Tracing of the WASI syscalls made by a module, in the spirit of
+//! `strace`, and hooks to intercept them.
+use crate::syscalls::types::*;
+use crate::WasiEnv;
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use wasmer::{FromToNativeWasmType, Function, Store, Value, WasmPtr, WasmTypeList};
+
+/// Where the syscall traces are written to.
+#[derive(Clone)]
+pub(crate) struct SyscallTracer(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl SyscallTracer {
+    pub(crate) fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self(Arc::new(Mutex::new(writer)))
+    }
+
+    fn log(&self, line: fmt::Arguments) {
+        let mut writer = self.0.lock().unwrap();
+        // Failing to trace must not change the behavior of the module
+        let _ = writeln!(writer, "{}", line);
+        let _ = writer.flush();
+    }
+}
+
+impl fmt::Debug for SyscallTracer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SyscallTracer").finish()
+    }
+}
+
+/// A hook called around a WASI syscall, registered with
+/// [`WasiEnv::hook_syscall`].
+///
+/// The hooks get the raw arguments of the syscall, the pointers being
+/// offsets in the memory of the module. They are called without the lock
+/// of the [`WasiState`](crate::WasiState) being held, so they can use it
+/// with [`WasiEnv::state`].
+pub trait SyscallHook: Send + Sync + 'static {
+    /// Called before the syscall `name`. Returning an errno skips the
+    /// syscall, which returns it instead: this can deny the syscall, or
+    /// replace it with an implementation of the hook.
+    ///
+    /// `proc_exit` can't be skipped.
+    fn before(&self, env: &WasiEnv, name: &str, args: &[Value]) -> Option<__wasi_errno_t> {
+        let _ = (env, name, args);
+        None
+    }
+
+    /// Called after the syscall `name` returned `errno`, or after it was
+    /// skipped, and returns the errno given to the module.
+    fn after(
+        &self,
+        env: &WasiEnv,
+        name: &str,
+        args: &[Value],
+        errno: __wasi_errno_t,
+    ) -> __wasi_errno_t {
+        let _ = (env, name, args);
+        errno
+    }
+}
+
+// Lets the host keep a handle on a hook, eg. to get what it recorded.
+impl<H: SyscallHook + ?Sized> SyscallHook for Arc<H> {
+    fn before(&self, env: &WasiEnv, name: &str, args: &[Value]) -> Option<__wasi_errno_t> {
+        (**self).before(env, name, args)
+    }
+
+    fn after(
+        &self,
+        env: &WasiEnv,
+        name: &str,
+        args: &[Value],
+        errno: __wasi_errno_t,
+    ) -> __wasi_errno_t {
+        (**self).after(env, name, args, errno)
+    }
+}
+
+/// The names of the syscalls which can be hooked, in all the WASI
+/// versions and extensions.
+pub(crate) const SYSCALL_NAMES: &[&str] = &[
+    "args_get",
+    "args_sizes_get",
+    "clock_res_get",
+    "clock_time_get",
+    "environ_get",
+    "environ_sizes_get",
+    "fd_advise",
+    "fd_allocate",
+    "fd_close",
+    "fd_datasync",
+    "fd_fdstat_get",
+    "fd_fdstat_set_flags",
+    "fd_fdstat_set_rights",
+    "fd_filestat_get",
+    "fd_filestat_set_size",
+    "fd_filestat_set_times",
+    "fd_pread",
+    "fd_prestat_dir_name",
+    "fd_prestat_get",
+    "fd_pwrite",
+    "fd_read",
+    "fd_readdir",
+    "fd_renumber",
+    "fd_seek",
+    "fd_sync",
+    "fd_tell",
+    "fd_write",
+    "path_create_directory",
+    "path_filestat_get",
+    "path_filestat_set_times",
+    "path_link",
+    "path_open",
+    "path_readlink",
+    "path_remove_directory",
+    "path_rename",
+    "path_symlink",
+    "path_unlink_file",
+    "poll_oneoff",
+    "proc_exit",
+    "proc_raise",
+    "random_get",
+    "sched_yield",
+    "sock_accept",
+    "sock_addr_local",
+    "sock_addr_peer",
+    "sock_connect",
+    "sock_listen",
+    "sock_recv",
+    "sock_send",
+    "sock_shutdown",
+    "tty_get_size",
+    "tty_set_raw",
+];
+
+/// The hooks with the name of the syscall they were added for, `*` for
+/// all of them, in the order they were added.
+#[derive(Clone, Default)]
+pub(crate) struct SyscallHooks(Vec<(String, Arc<dyn SyscallHook>)>);
+
+impl SyscallHooks {
+    /// Adds `hook` for the syscall `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is neither `*` nor the name of a syscall.
+    pub(crate) fn add(&mut self, name: &str, hook: Arc<dyn SyscallHook>) {
+        assert!(
+            name == "*" || SYSCALL_NAMES.contains(&name),
+            "can't hook `{}`, which isn't a WASI syscall",
+            name
+        );
+        self.0.push((name.to_string(), hook));
+    }
+
+    /// Adds `hook` for all the syscalls, before the other hooks.
+    pub(crate) fn add_first(&mut self, hook: Arc<dyn SyscallHook>) {
+        self.0.insert(0, ("*".to_string(), hook));
+    }
+
+    fn get(&self, name: &str) -> Vec<Arc<dyn SyscallHook>> {
+        self.0
+            .iter()
+            .filter(|(hooked, _)| hooked == name || hooked == "*")
+            .map(|(_, hook)| hook.clone())
+            .collect()
+    }
+}
+
+impl fmt::Debug for SyscallHooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+/// Calls a syscall, `func`, with its `hooks` around it.
+fn call_with_hooks<R: SyscallResult>(
+    hooks: &[Arc<dyn SyscallHook>],
+    env: &WasiEnv,
+    name: &str,
+    args: &[Value],
+    func: impl FnOnce() -> R,
+) -> R {
+    let result = hooks
+        .iter()
+        .find_map(|hook| hook.before(env, name, args))
+        .and_then(R::from_errno)
+        .unwrap_or_else(func);
+    result.map_errno(|errno| {
+        hooks
+            .iter()
+            .fold(errno, |errno, hook| hook.after(env, name, args, errno))
+    })
+}
+
+/// An argument of a syscall, as given to the hooks.
+pub(crate) trait SyscallArg: Copy {
+    fn to_value(self) -> Value;
+}
+
+macro_rules! impl_syscall_arg {
+    ( $( $t:ty => $variant:ident ),* ) => {
+        $(
+            impl SyscallArg for $t {
+                fn to_value(self) -> Value {
+                    Value::$variant(self.to_native())
+                }
+            }
+        )*
+    };
+}
+
+impl_syscall_arg!(u8 => I32, u16 => I32, u32 => I32, i32 => I32, u64 => I64, i64 => I64);
+
+impl<T: Copy, Ty> SyscallArg for WasmPtr<T, Ty> {
+    fn to_value(self) -> Value {
+        Value::I32(self.to_native())
+    }
+}
+
+/// The result of a syscall, as displayed in the traces.
+pub(crate) trait SyscallResult: Sized {
+    /// Whether the syscall returns at all (`proc_exit` doesn't).
+    const RETURNS: bool;
+
+    fn trace(&self) -> String;
+
+    /// The result of the syscall returning `errno`, if it returns one.
+    fn from_errno(errno: __wasi_errno_t) -> Option<Self>;
+
+    fn map_errno(self, f: impl FnOnce(__wasi_errno_t) -> __wasi_errno_t) -> Self;
+}
+
+impl SyscallResult for __wasi_errno_t {
+    const RETURNS: bool = true;
+
+    fn trace(&self) -> String {
+        format!("{} ({})", self, errno_name(*self))
+    }
+
+    fn from_errno(errno: __wasi_errno_t) -> Option<Self> {
+        Some(errno)
+    }
+
+    fn map_errno(self, f: impl FnOnce(__wasi_errno_t) -> __wasi_errno_t) -> Self {
+        f(self)
+    }
+}
+
+impl SyscallResult for () {
+    const RETURNS: bool = false;
+
+    fn trace(&self) -> String {
+        "?".to_string()
+    }
+
+    fn from_errno(_errno: __wasi_errno_t) -> Option<Self> {
+        None
+    }
+
+    fn map_errno(self, _f: impl FnOnce(__wasi_errno_t) -> __wasi_errno_t) -> Self {}
+}
+
+/// A syscall implementation that can be turned into a [`Function`]
+/// tracing its calls and calling its hooks.
+pub(crate) trait Syscall<Args, Rets> {
+    fn into_function(self, store: &Store, env: WasiEnv) -> Function;
+}
+
+/// Creates the [`Function`] importing the syscall `func`.
+///
+/// `func` must be a function item, so that the name of the syscall
+/// can be recovered from its type, and that the wrapping closure
+/// captures nothing.
+pub(crate) fn syscall_function<Args, Rets>(
+    store: &Store,
+    env: WasiEnv,
+    func: impl Syscall<Args, Rets>,
+) -> Function {
+    func.into_function(store, env)
+}
+
+/// Returns the name of a syscall from the type of the function
+/// item implementing it, eg. `fd_write`.
+fn syscall_name<F>() -> &'static str {
+    let path = std::any::type_name::<F>();
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+macro_rules! impl_syscall {
+    ( $( $x:ident ),* ) => {
+        #[allow(non_snake_case, unused_parens)]
+        impl<Func, Rets, $( $x, )*> Syscall<( $( $x ),* ), Rets> for Func
+        where
+            Func: Fn(&WasiEnv, $( $x ),*) -> Rets + Send + 'static,
+            $( $x: FromToNativeWasmType + SyscallArg + fmt::Debug, )*
+            Rets: WasmTypeList + SyscallResult,
+        {
+            fn into_function(self, store: &Store, env: WasiEnv) -> Function {
+                let func = self;
+                // Host functions can't be closures capturing anything yet, so
+                // the name and the hooks are looked up from the types and the
+                // env on each call.
+                Function::new_native_with_env(store, env, move |env: &WasiEnv, $( $x: $x ),*| -> Rets {
+                    let name = syscall_name::<Func>();
+                    let call = || {
+                        let hooks = env.syscall_hooks.get(name);
+                        if hooks.is_empty() {
+                            return func(env, $( $x ),*);
+                        }
+                        let args: &[Value] = &[$( $x.to_value() ),*];
+                        call_with_hooks(&hooks, env, name, args, || func(env, $( $x ),*))
+                    };
+                    let tracer = match &env.syscall_tracer {
+                        Some(tracer) => tracer,
+                        None => return call(),
+                    };
+                    let args: Vec<String> = vec![$( format!("{:?}", $x) ),*];
+                    let args = args.join(", ");
+                    if !Rets::RETURNS {
+                        tracer.log(format_args!("{}({}) = ?", name, args));
+                    }
+                    let start = Instant::now();
+                    let result = call();
+                    let elapsed = start.elapsed();
+                    tracer.log(format_args!("{}({}) = {} <{:?}>", name, args, result.trace(), elapsed));
+                    result
+                })
+            }
+        }
+    };
+}
+
+impl_syscall!();
+impl_syscall!(A1);
+impl_syscall!(A1, A2);
+impl_syscall!(A1, A2, A3);
+impl_syscall!(A1, A2, A3, A4);
+impl_syscall!(A1, A2, A3, A4, A5);
+impl_syscall!(A1, A2, A3, A4, A5, A6);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9);
+impl_syscall!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10);
+
+/// Returns the symbolic name of an errno, eg. `EBADF`.
+fn errno_name(errno: __wasi_errno_t) -> &'static str {
+    match errno {
+        __WASI_ESUCCESS => "ESUCCESS",
+        __WASI_E2BIG => "E2BIG",
+        __WASI_EACCES => "EACCES",
+        __WASI_EADDRINUSE => "EADDRINUSE",
+        __WASI_EADDRNOTAVAIL => "EADDRNOTAVAIL",
+        __WASI_EAFNOSUPPORT => "EAFNOSUPPORT",
+        __WASI_EAGAIN => "EAGAIN",
+        __WASI_EALREADY => "EALREADY",
+        __WASI_EBADF => "EBADF",
+        __WASI_EBADMSG => "EBADMSG",
+        __WASI_EBUSY => "EBUSY",
+        __WASI_ECANCELED => "ECANCELED",
+        __WASI_ECHILD => "ECHILD",
+        __WASI_ECONNABORTED => "ECONNABORTED",
+        __WASI_ECONNREFUSED => "ECONNREFUSED",
+        __WASI_ECONNRESET => "ECONNRESET",
+        __WASI_EDEADLK => "EDEADLK",
+        __WASI_EDESTADDRREQ => "EDESTADDRREQ",
+        __WASI_EDOM => "EDOM",
+        __WASI_EDQUOT => "EDQUOT",
+        __WASI_EEXIST => "EEXIST",
+        __WASI_EFAULT => "EFAULT",
+        __WASI_EFBIG => "EFBIG",
+        __WASI_EHOSTUNREACH => "EHOSTUNREACH",
+        __WASI_EIDRM => "EIDRM",
+        __WASI_EILSEQ => "EILSEQ",
+        __WASI_EINPROGRESS => "EINPROGRESS",
+        __WASI_EINTR => "EINTR",
+        __WASI_EINVAL => "EINVAL",
+        __WASI_EIO => "EIO",
+        __WASI_EISCONN => "EISCONN",
+        __WASI_EISDIR => "EISDIR",
+        __WASI_ELOOP => "ELOOP",
+        __WASI_EMFILE => "EMFILE",
+        __WASI_EMLINK => "EMLINK",
+        __WASI_EMSGSIZE => "EMSGSIZE",
+        __WASI_EMULTIHOP => "EMULTIHOP",
+        __WASI_ENAMETOOLONG => "ENAMETOOLONG",
+        __WASI_ENETDOWN => "ENETDOWN",
+        __WASI_ENETRESET => "ENETRESET",
+        __WASI_ENETUNREACH => "ENETUNREACH",
+        __WASI_ENFILE => "ENFILE",
+        __WASI_ENOBUFS => "ENOBUFS",
+        __WASI_ENODEV => "ENODEV",
+        __WASI_ENOENT => "ENOENT",
+        __WASI_ENOEXEC => "ENOEXEC",
+        __WASI_ENOLCK => "ENOLCK",
+        __WASI_ENOLINK => "ENOLINK",
+        __WASI_ENOMEM => "ENOMEM",
+        __WASI_ENOMSG => "ENOMSG",
+        __WASI_ENOPROTOOPT => "ENOPROTOOPT",
+        __WASI_ENOSPC => "ENOSPC",
+        __WASI_ENOSYS => "ENOSYS",
+        __WASI_ENOTCONN => "ENOTCONN",
+        __WASI_ENOTDIR => "ENOTDIR",
+        __WASI_ENOTEMPTY => "ENOTEMPTY",
+        __WASI_ENOTRECOVERABLE => "ENOTRECOVERABLE",
+        __WASI_ENOTSOCK => "ENOTSOCK",
+        __WASI_ENOTSUP => "ENOTSUP",
+        __WASI_ENOTTY => "ENOTTY",
+        __WASI_ENXIO => "ENXIO",
+        __WASI_EOVERFLOW => "EOVERFLOW",
+        __WASI_EOWNERDEAD => "EOWNERDEAD",
+        __WASI_EPERM => "EPERM",
+        __WASI_EPIPE => "EPIPE",
+        __WASI_EPROTO => "EPROTO",
+        __WASI_EPROTONOSUPPORT => "EPROTONOSUPPORT",
+        __WASI_EPROTOTYPE => "EPROTOTYPE",
+        __WASI_ERANGE => "ERANGE",
+        __WASI_EROFS => "EROFS",
+        __WASI_ESPIPE => "ESPIPE",
+        __WASI_ESRCH => "ESRCH",
+        __WASI_ESTALE => "ESTALE",
+        __WASI_ETIMEDOUT => "ETIMEDOUT",
+        __WASI_ETXTBSY => "ETXTBSY",
+        __WASI_EXDEV => "EXDEV",
+        __WASI_ENOTCAPABLE => "ENOTCAPABLE",
+        _ => "unknown errno",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::WasiState;
+
+    /// Denies the syscalls whose first argument is `fd`, and counts the
+    /// calls it sees.
+    struct DenyFd {
+        fd: i32,
+        calls: Mutex<Vec<(String, __wasi_errno_t)>>,
+    }
+
+    impl SyscallHook for DenyFd {
+        fn before(&self, _env: &WasiEnv, _name: &str, args: &[Value]) -> Option<__wasi_errno_t> {
+            match args.first() {
+                Some(Value::I32(fd)) if *fd == self.fd => Some(__WASI_EACCES),
+                _ => None,
+            }
+        }
+
+        fn after(
+            &self,
+            _env: &WasiEnv,
+            name: &str,
+            _args: &[Value],
+            errno: __wasi_errno_t,
+        ) -> __wasi_errno_t {
+            self.calls.lock().unwrap().push((name.to_string(), errno));
+            errno
+        }
+    }
+
+    #[test]
+    fn syscall_names() {
+        let store = Store::default();
+        let env = WasiEnv::new(WasiState::new("test_prog").build().unwrap());
+        let import_objects = vec![
+            crate::generate_import_object_snapshot0(&store, env.clone()),
+            crate::generate_import_object_snapshot1(&store, env.clone()),
+            crate::generate_import_object_sockets(&store, env.clone()),
+            crate::generate_import_object_tty(&store, env),
+        ];
+        for ((_, name), _) in import_objects.into_iter().flatten() {
+            assert!(SYSCALL_NAMES.contains(&name.as_str()), "{}", name);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "can't hook `fd_clsoe`, which isn't a WASI syscall")]
+    fn hook_unknown_syscall() {
+        let hook = Arc::new(DenyFd {
+            fd: 3,
+            calls: Mutex::new(vec![]),
+        });
+        SyscallHooks::default().add("fd_clsoe", hook);
+    }
+
+    #[test]
+    fn hooks() {
+        let env = WasiEnv::new(WasiState::new("test_prog").build().unwrap());
+        let hook = Arc::new(DenyFd {
+            fd: 3,
+            calls: Mutex::new(vec![]),
+        });
+        let mut hooks = SyscallHooks::default();
+        hooks.add("fd_close", hook.clone());
+        let hooks = hooks.get("fd_close");
+
+        let mut called = false;
+        let result = call_with_hooks(&hooks, &env, "fd_close", &[Value::I32(3)], || {
+            called = true;
+            __WASI_ESUCCESS
+        });
+        assert_eq!(result, __WASI_EACCES);
+        assert!(!called);
+
+        let result = call_with_hooks(&hooks, &env, "fd_close", &[Value::I32(4)], || {
+            called = true;
+            __WASI_EBADF
+        });
+        assert_eq!(result, __WASI_EBADF);
+        assert!(called);
+        assert_eq!(
+            *hook.calls.lock().unwrap(),
+            [
+                ("fd_close".to_string(), __WASI_EACCES),
+                ("fd_close".to_string(), __WASI_EBADF)
+            ]
+        );
+    }
+}
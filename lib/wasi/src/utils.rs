@@ -1,5 +1,5 @@
 use std::collections::BTreeSet;
-use wasmer::Module;
+use wasmer::{ExternType, Instance, Module, RuntimeError};
 
 #[allow(dead_code)]
 /// Check if a provided module is compiled for some version of WASI.
@@ -132,6 +132,11 @@ pub fn get_wasi_versions(module: &Module, strict: bool) -> Option<BTreeSet<WasiV
             SNAPSHOT1_NAMESPACE => {
                 out.insert(WasiVersion::Snapshot1);
             }
+            // wasi-nn complements WASI rather than being a version of it
+            #[cfg(feature = "nn")]
+            crate::nn::NN_NAMESPACE => {}
+            // and so do the experimental socket and terminal functions
+            crate::syscalls::sockets::SOCKETS_NAMESPACE | crate::syscalls::tty::TTY_NAMESPACE => {}
             _ => {
                 non_wasi_seen = true;
             }
@@ -144,6 +149,50 @@ pub fn get_wasi_versions(module: &Module, strict: bool) -> Option<BTreeSet<WasiV
     }
 }
 
+/// How a WASI module is run. This is determined by its exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasiExecModel {
+    /// The module exports `_start`, which runs it once.
+    Command,
+
+    /// The module doesn't export `_start`: it's initialized by calling
+    /// `_initialize`, if it exports it, after which its other exports can
+    /// be called any number of times. See [`initialize_reactor`].
+    Reactor,
+}
+
+/// Detect how a WASI module is run, based on its exports.
+pub fn get_wasi_exec_model(module: &Module) -> WasiExecModel {
+    let has_start = module
+        .exports()
+        .any(|export| export.name() == "_start" && matches!(export.ty(), ExternType::Function(_)));
+    if has_start {
+        WasiExecModel::Command
+    } else {
+        WasiExecModel::Reactor
+    }
+}
+
+/// Initialize an instance of a reactor, by calling its `_initialize`
+/// export if it has one. This must be done once, before calling any of
+/// its other exports.
+///
+/// The instances of commands are initialized by `_start`, so nothing is
+/// done for them.
+pub fn initialize_reactor(instance: &Instance) -> Result<(), RuntimeError> {
+    if get_wasi_exec_model(instance.module()) == WasiExecModel::Command {
+        return Ok(());
+    }
+    let initialize = match instance.exports.get_function("_initialize") {
+        Ok(initialize) => initialize,
+        Err(_) => return Ok(()),
+    };
+    initialize
+        .native::<(), ()>()
+        .map_err(|e| RuntimeError::new(format!("invalid `_initialize` function: {}", e)))?
+        .call()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -178,4 +227,58 @@ mod test {
         assert!(!(WasiVersion::Snapshot1 < WasiVersion::Latest));
         assert!(!(WasiVersion::Latest > WasiVersion::Snapshot1));
     }
+
+    fn module(wat: &str) -> Module {
+        Module::new(&wasmer::Store::default(), wat).unwrap()
+    }
+
+    #[test]
+    fn wasi_exec_model() {
+        let command = module(r#"(module (func (export "_start")))"#);
+        assert_eq!(get_wasi_exec_model(&command), WasiExecModel::Command);
+        let reactor = module(r#"(module (func (export "_initialize")) (func (export "run")))"#);
+        assert_eq!(get_wasi_exec_model(&reactor), WasiExecModel::Reactor);
+        // Only a function is a start function
+        let global = module(r#"(module (global (export "_start") i32 (i32.const 0)))"#);
+        assert_eq!(get_wasi_exec_model(&global), WasiExecModel::Reactor);
+    }
+
+    /// A module counting the calls of `_initialize` in `initialized`.
+    const INITIALIZE: &str = r#"
+        (module
+          (global $initialized (export "initialized") (mut i32) (i32.const 0))
+          (func (export "_initialize")
+            global.get $initialized
+            i32.const 1
+            i32.add
+            global.set $initialized))
+    "#;
+
+    fn initialized(instance: &Instance) -> i32 {
+        let initialized = instance.exports.get_global("initialized").unwrap();
+        initialized.get().unwrap_i32()
+    }
+
+    #[test]
+    fn reactor_initialization() {
+        let instance = Instance::new(&module(INITIALIZE), &wasmer::imports! {}).unwrap();
+        assert_eq!(initialized(&instance), 0);
+        initialize_reactor(&instance).unwrap();
+        assert_eq!(initialized(&instance), 1);
+
+        // Commands are initialized by `_start`
+        let command = INITIALIZE.replace("(module", r#"(module (func (export "_start"))"#);
+        let instance = Instance::new(&module(&command), &wasmer::imports! {}).unwrap();
+        initialize_reactor(&instance).unwrap();
+        assert_eq!(initialized(&instance), 0);
+
+        let reactor = module(r#"(module (func (export "run")))"#);
+        let instance = Instance::new(&reactor, &wasmer::imports! {}).unwrap();
+        initialize_reactor(&instance).unwrap();
+
+        let invalid = module(r#"(module (func (export "_initialize") (param i32)))"#);
+        let instance = Instance::new(&invalid, &wasmer::imports! {}).unwrap();
+        let error = initialize_reactor(&instance).unwrap_err();
+        assert!(error.message().contains("invalid `_initialize` function"));
+    }
 }
@@ -0,0 +1,670 @@
+//! Loading of `.webc` packages: a container bundling the WebAssembly
+//! modules ("atoms") of an application, the commands running them, and
+//! the volumes of files they use.
+//!
+//! A package is laid out as follows, all integers being little-endian:
+//!
+//! - the magic `\0webc` and the version `001`
+//! - a checksum: 16 bytes naming the algorithm, a `u32` length, and 256
+//!   bytes padded with zeros. It's not verified.
+//! - a signature: a `u32` length, and 1024 bytes padded with zeros. It's
+//!   not verified.
+//! - the manifest, a `u64` length followed by CBOR
+//! - the atoms, a `u64` length followed by a volume whose root files are
+//!   the atoms
+//! - until the end, the volumes: a `u64` length followed by the name of
+//!   the volume, then a `u64` length followed by the volume
+//!
+//! A volume is a `u64` length followed by its header, then a `u64` length
+//! followed by the contents of its files. The header is made of levels,
+//! the first one being the root directory. A level is a `u64` length
+//! followed by the entries of a directory, each made of:
+//!
+//! - a `u64`, the length of the name of the entry, with the top bit set
+//!   for files
+//! - two `u64`, the start and end offsets of the level of the directory
+//!   in the header, or of the contents of the file
+//! - the name of the entry
+use crate::state::{
+    DirEntry, FileSystem, Metadata, OpenOptions, ReadOnlyFile, WasiFile, WasiFsError,
+    WasiStateBuilder, WasiStateCreationError,
+};
+use crate::syscalls::types::*;
+use serde::{de, ser, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// The magic bytes starting a package.
+const MAGIC: &[u8] = b"\0webc";
+
+/// The version of the container format supported.
+const VERSION: &[u8] = b"001";
+
+/// The size of an entry in the header of a volume, without its name.
+const ENTRY_SIZE: usize = 24;
+
+/// The flag marking the files in the header of a volume.
+const FILE_FLAG: u64 = 1 << 63;
+
+/// The volume holding the files of the package itself.
+const PACKAGE_VOLUME: &str = "atom";
+
+/// Error type returned when a package can't be loaded or run.
+#[derive(Error, Debug)]
+pub enum WebcError {
+    #[error("not a webc package")]
+    NotWebc,
+    #[error("unsupported webc version `{0}`")]
+    UnsupportedVersion(String),
+    #[error("the package is truncated")]
+    Truncated,
+    #[error("invalid manifest: {0}")]
+    InvalidManifest(String),
+    #[error("invalid volume `{0}`")]
+    InvalidVolume(String),
+    #[error("the package doesn't declare any command")]
+    NoCommands,
+    #[error("the package has no command `{0}`. Available commands: {1}")]
+    UnknownCommand(String, String),
+    #[error("the package declares several commands, select one of: {0}")]
+    AmbiguousCommand(String),
+    #[error("the command `{0}` doesn't run with WASI (runner `{1}`)")]
+    UnsupportedRunner(String, String),
+    #[error("the command `{0}` refers to the missing atom `{1}`")]
+    MissingAtom(String, String),
+    #[error("the file system entry `{0}` refers to the missing directory `{1}`")]
+    MissingDirectory(String, String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    entrypoint: Option<String>,
+    #[serde(default)]
+    package: PackageAnnotations,
+    #[serde(default)]
+    commands: BTreeMap<String, ManifestCommand>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct PackageAnnotations {
+    /// The directories of the package volume mounted in the guest, by
+    /// guest directory, like the `[fs]` table of `wapm.toml`
+    #[serde(default)]
+    fs: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ManifestCommand {
+    runner: String,
+    #[serde(default)]
+    annotations: CommandAnnotations,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct CommandAnnotations {
+    #[serde(default)]
+    wasi: Option<WasiAnnotations>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct WasiAnnotations {
+    atom: String,
+    #[serde(default)]
+    env: Option<Vec<String>>,
+    #[serde(default)]
+    main_args: Option<Vec<String>>,
+}
+
+/// A command of a package, ready to run with WASI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebcCommand {
+    pub name: String,
+    /// The name of the atom run by the command
+    pub atom: String,
+    /// The arguments passed before the ones of the user
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+}
+
+/// A loaded `.webc` package.
+#[derive(Debug, Clone)]
+pub struct WebcPackage {
+    manifest: Manifest,
+    atoms: Volume,
+    volumes: BTreeMap<String, Volume>,
+}
+
+impl WebcPackage {
+    /// Whether `bytes` are the start of a package.
+    pub fn is_webc(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, WebcError> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, WebcError> {
+        if !Self::is_webc(&bytes) {
+            return Err(WebcError::NotWebc);
+        }
+        let bytes = Arc::new(bytes);
+        let mut reader = Reader {
+            bytes: &bytes[..],
+            offset: MAGIC.len(),
+        };
+        let version = reader.take(VERSION.len())?;
+        if bytes[version.clone()] != *VERSION {
+            return Err(WebcError::UnsupportedVersion(
+                String::from_utf8_lossy(&bytes[version]).to_string(),
+            ));
+        }
+        // The checksum and the signature
+        reader.take(16 + 4 + 256)?;
+        reader.take(4 + 1024)?;
+
+        let manifest = reader.section()?;
+        let manifest = serde_cbor::from_slice(&bytes[manifest])
+            .map_err(|e| WebcError::InvalidManifest(e.to_string()))?;
+        let atoms = reader.section()?;
+        let atoms = Volume::parse(&bytes, atoms, "atoms")?;
+        let mut volumes = BTreeMap::new();
+        while reader.offset < bytes.len() {
+            let name = reader.section()?;
+            let name = String::from_utf8_lossy(&bytes[name]).to_string();
+            let volume = reader.section()?;
+            let volume = Volume::parse(&bytes, volume, &name)?;
+            volumes.insert(name, volume);
+        }
+        Ok(Self {
+            manifest,
+            atoms,
+            volumes,
+        })
+    }
+
+    /// Selects the command named `name`, or the entrypoint of the package
+    /// or its only command if there's no name.
+    pub fn command(&self, name: Option<&str>) -> Result<WebcCommand, WebcError> {
+        let commands = &self.manifest.commands;
+        let name = match name.or_else(|| self.manifest.entrypoint.as_deref()) {
+            Some(name) => name,
+            None if commands.len() == 1 => commands.keys().next().unwrap().as_str(),
+            None if commands.is_empty() => return Err(WebcError::NoCommands),
+            None => return Err(WebcError::AmbiguousCommand(self.command_names())),
+        };
+        let command = commands
+            .get(name)
+            .ok_or_else(|| WebcError::UnknownCommand(name.to_string(), self.command_names()))?;
+        let wasi = match &command.annotations.wasi {
+            Some(wasi) if command.runner.contains("wasi") => wasi,
+            _ => {
+                return Err(WebcError::UnsupportedRunner(
+                    name.to_string(),
+                    command.runner.clone(),
+                ))
+            }
+        };
+        if self.atom(&wasi.atom).is_none() {
+            return Err(WebcError::MissingAtom(name.to_string(), wasi.atom.clone()));
+        }
+        let env = wasi
+            .env
+            .iter()
+            .flatten()
+            .map(|var| match var.find('=') {
+                Some(index) => (var[..index].to_string(), var[index + 1..].to_string()),
+                None => (var.clone(), String::new()),
+            })
+            .collect();
+        Ok(WebcCommand {
+            name: name.to_string(),
+            atom: wasi.atom.clone(),
+            args: wasi.main_args.clone().unwrap_or_default(),
+            env,
+        })
+    }
+
+    /// Gets the WebAssembly module of the atom `name`.
+    pub fn atom(&self, name: &str) -> Option<&[u8]> {
+        match self.atoms.entries.get(&Path::new("/").join(name))? {
+            VolumeEntry::File(range) => Some(&self.atoms.bytes[range.clone()]),
+            VolumeEntry::Dir => None,
+        }
+    }
+
+    /// Mounts the volumes of the package. If the package declares an `fs`
+    /// table, its directories of the package volume are mounted where it
+    /// says. Otherwise each volume is mounted at `/` followed by its name.
+    pub fn mount_volumes(&self, builder: &mut WasiStateBuilder) -> Result<(), WebcError> {
+        let mount_error = |e: WasiStateCreationError| WebcError::InvalidManifest(e.to_string());
+        match (&self.manifest.package.fs, self.volumes.get(PACKAGE_VOLUME)) {
+            (Some(fs), Some(volume)) => {
+                for (guest_dir, dir) in fs {
+                    let root = Path::new("/").join(dir);
+                    if !matches!(volume.entries.get(&root), Some(VolumeEntry::Dir)) {
+                        return Err(WebcError::MissingDirectory(guest_dir.clone(), dir.clone()));
+                    }
+                    let file_system = VolumeFileSystem {
+                        volume: volume.clone(),
+                        root,
+                    };
+                    builder.mount(guest_dir, file_system).map_err(mount_error)?;
+                }
+            }
+            _ => {
+                for (name, volume) in &self.volumes {
+                    let file_system = VolumeFileSystem {
+                        volume: volume.clone(),
+                        root: PathBuf::from("/"),
+                    };
+                    builder
+                        .mount(&format!("/{}", name), file_system)
+                        .map_err(mount_error)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn command_names(&self) -> String {
+        self.manifest
+            .commands
+            .keys()
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Reads the sections of a package.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<Range<usize>, WebcError> {
+        let end = self.offset.checked_add(len).ok_or(WebcError::Truncated)?;
+        if end > self.bytes.len() {
+            return Err(WebcError::Truncated);
+        }
+        let range = self.offset..end;
+        self.offset = end;
+        Ok(range)
+    }
+
+    fn u64(&mut self) -> Result<u64, WebcError> {
+        let range = self.take(8)?;
+        Ok(u64::from_le_bytes(self.bytes[range].try_into().unwrap()))
+    }
+
+    /// Reads a section made of its length and its contents.
+    fn section(&mut self) -> Result<Range<usize>, WebcError> {
+        let len = self.u64()?;
+        self.take(len.try_into().map_err(|_| WebcError::Truncated)?)
+    }
+}
+
+/// The files of a volume, whose contents are in the package.
+#[derive(Debug, Clone)]
+struct Volume {
+    bytes: Arc<Vec<u8>>,
+    /// The entries of the volume by absolute path, with the range of the
+    /// contents of the files in `bytes`
+    entries: BTreeMap<PathBuf, VolumeEntry>,
+}
+
+#[derive(Debug, Clone)]
+enum VolumeEntry {
+    Dir,
+    File(Range<usize>),
+}
+
+impl Volume {
+    fn parse(bytes: &Arc<Vec<u8>>, range: Range<usize>, name: &str) -> Result<Self, WebcError> {
+        let invalid = || WebcError::InvalidVolume(name.to_string());
+        let mut reader = Reader {
+            bytes: &bytes[..range.end],
+            offset: range.start,
+        };
+        let header = reader.section().map_err(|_| invalid())?;
+        let data = reader.section().map_err(|_| invalid())?;
+
+        let mut entries = BTreeMap::new();
+        entries.insert(PathBuf::from("/"), VolumeEntry::Dir);
+        // Each entry takes its own space in the header, so there can't be
+        // more than this. Directories pointing to the same level, or to
+        // one of their parents, would go over it.
+        let max_entries = (header.end - header.start) / ENTRY_SIZE;
+        // The directories whose level is left to read, with the range of
+        // their entries
+        let mut root = Reader {
+            bytes: &bytes[..header.end],
+            offset: header.start,
+        };
+        let root_level = root.section().map_err(|_| invalid())?;
+        let mut levels = vec![(PathBuf::from("/"), root_level)];
+        while let Some((dir, level)) = levels.pop() {
+            let mut reader = Reader {
+                bytes: &bytes[..level.end],
+                offset: level.start,
+            };
+            while reader.offset < level.end {
+                let name_len = reader.u64().map_err(|_| invalid())?;
+                let start = reader.u64().map_err(|_| invalid())? as usize;
+                let end = reader.u64().map_err(|_| invalid())? as usize;
+                let entry_name = reader
+                    .take((name_len & !FILE_FLAG) as usize)
+                    .map_err(|_| invalid())?;
+                let entry_name = std::str::from_utf8(&bytes[entry_name]).map_err(|_| invalid())?;
+                if entry_name.is_empty() || entry_name.contains('/') || entry_name == ".." {
+                    return Err(invalid());
+                }
+                let path = dir.join(entry_name);
+                if entries.len() > max_entries {
+                    return Err(invalid());
+                }
+                if name_len & FILE_FLAG != 0 {
+                    if start > end || end > data.end - data.start {
+                        return Err(invalid());
+                    }
+                    let contents = data.start + start..data.start + end;
+                    entries.insert(path, VolumeEntry::File(contents));
+                } else {
+                    if start > end || end > header.end - header.start {
+                        return Err(invalid());
+                    }
+                    let level = header.start + start..header.start + end;
+                    entries.insert(path.clone(), VolumeEntry::Dir);
+                    levels.push((path, level));
+                }
+            }
+        }
+        Ok(Self {
+            bytes: bytes.clone(),
+            entries,
+        })
+    }
+}
+
+/// A directory of a volume of a package, mounted read-only.
+#[derive(Debug)]
+struct VolumeFileSystem {
+    volume: Volume,
+    /// The directory of the volume which is the root of the file system
+    root: PathBuf,
+}
+
+impl VolumeFileSystem {
+    fn entry(&self, path: &Path) -> Result<(PathBuf, &VolumeEntry), WasiFsError> {
+        let path = self.root.join(path.strip_prefix("/").unwrap_or(path));
+        match self.volume.entries.get(&path) {
+            Some(entry) => Ok((path, entry)),
+            None => Err(WasiFsError::EntityNotFound),
+        }
+    }
+}
+
+// The volumes are only in memory, so they can't be serialized with the
+// rest of the state.
+impl Serialize for VolumeFileSystem {
+    fn serialize<S: ser::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("webc volumes can not be serialized"))
+    }
+}
+
+impl<'de> Deserialize<'de> for VolumeFileSystem {
+    fn deserialize<D: de::Deserializer<'de>>(_deserializer: D) -> Result<Self, D::Error> {
+        Err(de::Error::custom("webc volumes can not be deserialized"))
+    }
+}
+
+#[typetag::serde]
+impl FileSystem for VolumeFileSystem {
+    fn metadata(&self, path: &Path) -> Result<Metadata, WasiFsError> {
+        let (filetype, len) = match self.entry(path)?.1 {
+            VolumeEntry::Dir => (__WASI_FILETYPE_DIRECTORY, 0),
+            VolumeEntry::File(range) => (__WASI_FILETYPE_REGULAR_FILE, range.len() as u64),
+        };
+        Ok(Metadata {
+            filetype,
+            len,
+            ..Metadata::default()
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, WasiFsError> {
+        let (path, entry) = self.entry(path)?;
+        if let VolumeEntry::File(_) = entry {
+            return Err(WasiFsError::BaseNotDirectory);
+        }
+        Ok(self
+            .volume
+            .entries
+            .iter()
+            .filter(|(entry_path, _)| entry_path.parent() == Some(&path))
+            .map(|(entry_path, entry)| DirEntry {
+                name: entry_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                filetype: match entry {
+                    VolumeEntry::Dir => __WASI_FILETYPE_DIRECTORY,
+                    VolumeEntry::File(_) => __WASI_FILETYPE_REGULAR_FILE,
+                },
+            })
+            .collect())
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+
+    fn remove_file(&self, _path: &Path) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+
+    fn open(&self, path: &Path, options: &OpenOptions) -> Result<Box<dyn WasiFile>, WasiFsError> {
+        let entry = match self.entry(path) {
+            Err(WasiFsError::EntityNotFound) if options.create || options.create_new => {
+                return Err(WasiFsError::PermissionDenied)
+            }
+            entry => entry?.1,
+        };
+        if options.write || options.append || options.truncate || options.create_new {
+            return Err(WasiFsError::PermissionDenied);
+        }
+        match entry {
+            VolumeEntry::File(range) => Ok(Box::new(ReadOnlyFile::new(
+                self.volume.bytes[range.clone()].to_vec(),
+                0,
+            ))),
+            VolumeEntry::Dir => Err(WasiFsError::NotAFile),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_cbor::Value;
+    use std::io::Read;
+
+    fn section(out: &mut Vec<u8>, contents: &[u8]) {
+        out.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+        out.extend_from_slice(contents);
+    }
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (Value::Text(key.to_string()), value))
+                .collect(),
+        )
+    }
+
+    fn text(text: &str) -> Value {
+        Value::Text(text.to_string())
+    }
+
+    /// Serializes a volume with the files `files`, in the directory `dir`
+    /// or its `subdir`.
+    fn volume(files: &[(&str, &[u8])], subdir: &str, subdir_files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = vec![];
+        let mut level = |files: &[(&str, &[u8])], dirs: &[(&str, u64, u64)]| {
+            let mut level = vec![];
+            for (name, contents) in files {
+                level.extend_from_slice(&(name.len() as u64 | FILE_FLAG).to_le_bytes());
+                level.extend_from_slice(&(data.len() as u64).to_le_bytes());
+                data.extend_from_slice(contents);
+                level.extend_from_slice(&(data.len() as u64).to_le_bytes());
+                level.extend_from_slice(name.as_bytes());
+            }
+            for (name, start, end) in dirs {
+                level.extend_from_slice(&(name.len() as u64).to_le_bytes());
+                level.extend_from_slice(&start.to_le_bytes());
+                level.extend_from_slice(&end.to_le_bytes());
+                level.extend_from_slice(name.as_bytes());
+            }
+            level
+        };
+        let subdir_level = level(subdir_files, &[]);
+        let root_len = files
+            .iter()
+            .map(|(name, _)| ENTRY_SIZE + name.len())
+            .sum::<usize>()
+            + ENTRY_SIZE
+            + subdir.len();
+        // The level of the subdirectory follows the root level
+        let start = (8 + root_len + 8) as u64;
+        let end = start + subdir_level.len() as u64;
+        let root_level = level(files, &[(subdir, start, end)]);
+
+        let mut header = vec![];
+        section(&mut header, &root_level);
+        section(&mut header, &subdir_level);
+        let mut volume = vec![];
+        section(&mut volume, &header);
+        section(&mut volume, &data);
+        volume
+    }
+
+    fn package() -> Vec<u8> {
+        let command = map(vec![
+            (
+                "runner",
+                text("https://webc.org/runner/wasi/command@unstable_"),
+            ),
+            (
+                "annotations",
+                map(vec![(
+                    "wasi",
+                    map(vec![
+                        ("atom", text("app")),
+                        ("env", Value::Array(vec![text("MODE=production")])),
+                        ("main_args", Value::Array(vec![text("--serve")])),
+                    ]),
+                )]),
+            ),
+        ]);
+        let manifest = map(vec![
+            ("entrypoint", text("serve")),
+            ("commands", map(vec![("serve", command)])),
+        ]);
+
+        let mut package = b"\0webc001".to_vec();
+        package.extend_from_slice(b"----------------");
+        package.extend_from_slice(&[0; 4 + 256 + 4 + 1024]);
+        section(&mut package, &serde_cbor::to_vec(&manifest).unwrap());
+        section(
+            &mut package,
+            &volume(&[("app", &b"\0asm"[..])], "unused", &[]),
+        );
+        section(&mut package, b"atom");
+        section(
+            &mut package,
+            &volume(
+                &[("README", &b"hello"[..])],
+                "public",
+                &[("index.html", &b"<html>"[..])],
+            ),
+        );
+        package
+    }
+
+    #[test]
+    fn parse_package() {
+        let package = WebcPackage::from_bytes(package()).unwrap();
+        assert_eq!(
+            package.command(None).unwrap(),
+            WebcCommand {
+                name: "serve".to_string(),
+                atom: "app".to_string(),
+                args: vec!["--serve".to_string()],
+                env: vec![("MODE".to_string(), "production".to_string())],
+            }
+        );
+        assert!(matches!(
+            package.command(Some("missing")),
+            Err(WebcError::UnknownCommand(..))
+        ));
+        assert_eq!(package.atom("app"), Some(&b"\0asm"[..]));
+
+        let file_system = VolumeFileSystem {
+            volume: package.volumes["atom"].clone(),
+            root: PathBuf::from("/"),
+        };
+        let mut names = file_system
+            .read_dir(Path::new("/"))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["README", "public"]);
+        let mut contents = String::new();
+        file_system
+            .open(
+                Path::new("/public/index.html"),
+                OpenOptions::new().read(true),
+            )
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "<html>");
+    }
+
+    #[test]
+    fn invalid_packages() {
+        assert!(matches!(
+            WebcPackage::from_bytes(b"\0asm".to_vec()),
+            Err(WebcError::NotWebc)
+        ));
+        assert!(matches!(
+            WebcPackage::from_bytes(b"\0webc002".to_vec()),
+            Err(WebcError::UnsupportedVersion(_))
+        ));
+        let mut truncated = package();
+        truncated.truncate(truncated.len() - 3);
+        assert!(WebcPackage::from_bytes(truncated).is_err());
+    }
+}
@@ -0,0 +1,702 @@
+//! The generation of the bindings of a parsed interface.
+
+use crate::wit::{Function, Interface, Type, TypeDef, TypeDefKind};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+
+/// The maximum number of flat parameters of a function, beyond which the
+/// canonical ABI passes them in memory, which isn't supported.
+const MAX_FLAT_PARAMS: usize = 16;
+
+/// The core WebAssembly type of a flattened value.
+#[derive(Clone, Copy)]
+enum Flat {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl Flat {
+    fn zero(self) -> TokenStream {
+        match self {
+            Flat::I32 => quote!(0i32),
+            Flat::I64 => quote!(0i64),
+            Flat::F32 => quote!(0f32),
+            Flat::F64 => quote!(0f64),
+        }
+    }
+}
+
+impl ToTokens for Flat {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(match self {
+            Flat::I32 => quote!(i32),
+            Flat::I64 => quote!(i64),
+            Flat::F32 => quote!(f32),
+            Flat::F64 => quote!(f64),
+        })
+    }
+}
+
+/// Converts a `.wit` name to a snake case Rust identifier.
+fn snake_case(name: &str) -> Ident {
+    let name = name.replace('-', "_").to_lowercase();
+    match syn::parse_str::<Ident>(&name) {
+        Ok(ident) => ident,
+        // Keywords get a trailing underscore
+        Err(_) => format_ident!("{}_", name),
+    }
+}
+
+/// Converts a `.wit` name to a camel case Rust identifier.
+fn camel_case(name: &str) -> Ident {
+    let name = name
+        .split(|c| c == '-' || c == '_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>();
+    Ident::new(&name, Span::call_site())
+}
+
+/// The type of the arguments or of the results of a `NativeFunc` or a
+/// host function with the flat types `flats`.
+fn flat_list(flats: &[Flat]) -> TokenStream {
+    match flats {
+        [flat] => quote!(#flat),
+        flats => quote!((#(#flats),*)),
+    }
+}
+
+pub struct Generator<'a> {
+    interface: &'a Interface,
+    /// The counter of the temporary variables.
+    temporaries: usize,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(interface: &'a Interface) -> Self {
+        Self {
+            interface,
+            temporaries: 0,
+        }
+    }
+
+    fn temporary(&mut self) -> Ident {
+        self.temporaries += 1;
+        format_ident!("tmp{}", self.temporaries)
+    }
+
+    fn type_def(&self, name: &str) -> &'a TypeDef {
+        self.interface.type_def(name)
+    }
+
+    /// The owned Rust type of `ty`.
+    fn rust_type(&self, ty: &Type) -> TokenStream {
+        match ty {
+            Type::U8 => quote!(u8),
+            Type::U16 => quote!(u16),
+            Type::U32 => quote!(u32),
+            Type::U64 => quote!(u64),
+            Type::S8 => quote!(i8),
+            Type::S16 => quote!(i16),
+            Type::S32 => quote!(i32),
+            Type::S64 => quote!(i64),
+            Type::F32 => quote!(f32),
+            Type::F64 => quote!(f64),
+            Type::Bool => quote!(bool),
+            Type::Char => quote!(char),
+            Type::String => quote!(String),
+            Type::List(ty) => {
+                let ty = self.rust_type(ty);
+                quote!(Vec<#ty>)
+            }
+            Type::Option(ty) => {
+                let ty = self.rust_type(ty);
+                quote!(Option<#ty>)
+            }
+            Type::Tuple(types) => {
+                let types = types.iter().map(|ty| self.rust_type(ty));
+                quote!((#(#types,)*))
+            }
+            Type::Named(name) => camel_case(name).into_token_stream(),
+        }
+    }
+
+    /// The Rust type of `ty` as a parameter of a method calling an export,
+    /// which borrows strings and lists.
+    fn param_type(&self, ty: &Type) -> TokenStream {
+        match ty {
+            Type::String => quote!(&str),
+            Type::List(ty) => {
+                let ty = self.rust_type(ty);
+                quote!(&[#ty])
+            }
+            ty => self.rust_type(ty),
+        }
+    }
+
+    fn flatten(&self, ty: &Type, flats: &mut Vec<Flat>) {
+        match ty {
+            Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::S8
+            | Type::S16
+            | Type::S32
+            | Type::Bool
+            | Type::Char => flats.push(Flat::I32),
+            Type::U64 | Type::S64 => flats.push(Flat::I64),
+            Type::F32 => flats.push(Flat::F32),
+            Type::F64 => flats.push(Flat::F64),
+            Type::String | Type::List(_) => flats.extend(&[Flat::I32, Flat::I32]),
+            Type::Option(ty) => {
+                flats.push(Flat::I32);
+                self.flatten(ty, flats);
+            }
+            Type::Tuple(types) => types.iter().for_each(|ty| self.flatten(ty, flats)),
+            Type::Named(name) => match &self.type_def(name).kind {
+                TypeDefKind::Record(fields) => {
+                    fields.iter().for_each(|(_, ty)| self.flatten(ty, flats))
+                }
+                TypeDefKind::Enum(_) => flats.push(Flat::I32),
+                TypeDefKind::Alias(ty) => self.flatten(ty, flats),
+            },
+        }
+    }
+
+    fn flat_types(&self, types: &[&Type]) -> Vec<Flat> {
+        let mut flats = Vec::new();
+        for ty in types {
+            self.flatten(ty, &mut flats);
+        }
+        flats
+    }
+
+    /// Whether passing `ty` involves the memory of the module.
+    fn uses_memory(&self, ty: &Type) -> bool {
+        match ty {
+            Type::String | Type::List(_) => true,
+            Type::Option(ty) => self.uses_memory(ty),
+            Type::Tuple(types) => types.iter().any(|ty| self.uses_memory(ty)),
+            Type::Named(name) => match &self.type_def(name).kind {
+                TypeDefKind::Record(fields) => fields.iter().any(|(_, ty)| self.uses_memory(ty)),
+                TypeDefKind::Enum(_) => false,
+                TypeDefKind::Alias(ty) => self.uses_memory(ty),
+            },
+            _ => false,
+        }
+    }
+
+    /// Generates the statements lowering `value`, a reference to a value of
+    /// type `ty`, to flat values, and pushes the variables holding them to
+    /// `flats`.
+    fn lower(
+        &mut self,
+        ty: &Type,
+        value: TokenStream,
+        stmts: &mut Vec<TokenStream>,
+        flats: &mut Vec<Ident>,
+    ) {
+        let rt = quote!(::wasmer_wit_bindgen::rt);
+        let v = self.temporary();
+        stmts.push(quote!(let #v = #value;));
+
+        match ty {
+            Type::U8 | Type::U16 | Type::U32 | Type::S8 | Type::S16 | Type::Bool | Type::Char => {
+                stmts.push(quote!(let #v = *#v as i32;));
+                flats.push(v);
+            }
+            Type::U64 => {
+                stmts.push(quote!(let #v = *#v as i64;));
+                flats.push(v);
+            }
+            Type::S32 | Type::S64 | Type::F32 | Type::F64 => {
+                stmts.push(quote!(let #v = *#v;));
+                flats.push(v);
+            }
+            Type::String | Type::List(_) => {
+                let (ptr, len) = (self.temporary(), self.temporary());
+                let lower = match ty {
+                    Type::String => quote!(lower_str),
+                    _ => quote!(lower_list),
+                };
+                stmts.push(quote!(let (#ptr, #len) = #rt::#lower(&lower, &#v[..])?;));
+                flats.extend(vec![ptr, len]);
+            }
+            Type::Option(ty) => {
+                let payload = self.temporary();
+                let mut some_stmts = Vec::new();
+                let mut some_flats = Vec::new();
+                self.lower(ty, quote!(#payload), &mut some_stmts, &mut some_flats);
+                let mut payload_types = Vec::new();
+                self.flatten(ty, &mut payload_types);
+                let zeros = payload_types.iter().map(|flat| flat.zero());
+
+                let discriminant = self.temporary();
+                let payload_flats = payload_types
+                    .iter()
+                    .map(|_| self.temporary())
+                    .collect::<Vec<_>>();
+                stmts.push(quote! {
+                    let (#discriminant, #(#payload_flats,)*) = match #v {
+                        Some(#payload) => {
+                            #(#some_stmts)*
+                            (1i32, #(#some_flats,)*)
+                        }
+                        None => (0i32, #(#zeros,)*),
+                    };
+                });
+                flats.push(discriminant);
+                flats.extend(payload_flats);
+            }
+            Type::Tuple(types) => {
+                for (index, ty) in types.iter().enumerate() {
+                    let index = syn::Index::from(index);
+                    self.lower(ty, quote!(&#v.#index), stmts, flats);
+                }
+            }
+            Type::Named(name) => match &self.type_def(name).kind {
+                TypeDefKind::Record(fields) => {
+                    for (field, ty) in fields {
+                        let field = snake_case(field);
+                        self.lower(ty, quote!(&#v.#field), stmts, flats);
+                    }
+                }
+                TypeDefKind::Enum(_) => {
+                    stmts.push(quote!(let #v = *#v as i32;));
+                    flats.push(v);
+                }
+                TypeDefKind::Alias(ty) => self.lower(ty, quote!(#v), stmts, flats),
+            },
+        }
+    }
+
+    /// Generates the expression lifting a value of type `ty` from the flat
+    /// values in `flats`.
+    fn lift(&self, ty: &Type, flats: &mut impl Iterator<Item = Ident>) -> TokenStream {
+        let rt = quote!(::wasmer_wit_bindgen::rt);
+        let next = |flats: &mut dyn Iterator<Item = Ident>| {
+            flats.next().expect("the flat values of the type")
+        };
+
+        match ty {
+            Type::U8 | Type::U16 | Type::U32 | Type::S8 | Type::S16 | Type::U64 => {
+                let v = next(flats);
+                let ty = self.rust_type(ty);
+                quote!(#v as #ty)
+            }
+            Type::S32 | Type::S64 | Type::F32 | Type::F64 => next(flats).into_token_stream(),
+            Type::Bool => {
+                let v = next(flats);
+                quote!(#rt::lift_bool(#v))
+            }
+            Type::Char => {
+                let v = next(flats);
+                quote!(#rt::lift_char(#v)?)
+            }
+            Type::String => {
+                let (ptr, len) = (next(flats), next(flats));
+                quote!(#rt::lift_string(&lift, #ptr, #len)?)
+            }
+            Type::List(_) => {
+                let (ptr, len) = (next(flats), next(flats));
+                quote!(#rt::lift_list(&lift, #ptr, #len)?)
+            }
+            Type::Option(ty) => {
+                let discriminant = next(flats);
+                let payload = self.lift(ty, flats);
+                quote! {
+                    match #discriminant {
+                        0 => None,
+                        1 => Some(#payload),
+                        discriminant => return Err(#rt::invalid_discriminant("option", discriminant)),
+                    }
+                }
+            }
+            Type::Tuple(types) => {
+                let items = types
+                    .iter()
+                    .map(|ty| self.lift(ty, flats))
+                    .collect::<Vec<_>>();
+                quote!((#(#items,)*))
+            }
+            Type::Named(name) => match &self.type_def(name).kind {
+                TypeDefKind::Record(fields) => {
+                    let ty = camel_case(name);
+                    let fields = fields
+                        .iter()
+                        .map(|(field, ty)| {
+                            let value = self.lift(ty, flats);
+                            let field = snake_case(field);
+                            quote!(#field: #value)
+                        })
+                        .collect::<Vec<_>>();
+                    quote!(#ty { #(#fields),* })
+                }
+                TypeDefKind::Enum(cases) => {
+                    let v = next(flats);
+                    let ty = camel_case(name);
+                    let discriminants = (0..cases.len()).map(Literal::usize_unsuffixed);
+                    let cases = cases.iter().map(|case| camel_case(case));
+                    quote! {
+                        match #v {
+                            #(#discriminants => #ty::#cases,)*
+                            discriminant => return Err(#rt::invalid_discriminant(#name, discriminant)),
+                        }
+                    }
+                }
+                TypeDefKind::Alias(ty) => self.lift(ty, flats),
+            },
+        }
+    }
+
+    /// Generates the Rust definitions of the types of the interface.
+    pub fn type_defs(&self) -> TokenStream {
+        let rt = quote!(::wasmer_wit_bindgen::rt);
+        let wasmer = quote!(::wasmer_wit_bindgen::wasmer);
+
+        let defs = self.interface.types.iter().map(|def| {
+            let name = camel_case(&def.name);
+            match &def.kind {
+                TypeDefKind::Record(fields) => {
+                    let names = fields.iter().map(|(field, _)| snake_case(field)).collect::<Vec<_>>();
+                    let types = fields.iter().map(|(_, ty)| self.rust_type(ty)).collect::<Vec<_>>();
+                    let layout = quote!(&[#((<#types as #rt::ComponentType>::SIZE, <#types as #rt::ComponentType>::ALIGN)),*]);
+                    quote! {
+                        #[derive(Debug, Clone, PartialEq)]
+                        pub struct #name {
+                            #(pub #names: #types,)*
+                        }
+
+                        impl #rt::ComponentType for #name {
+                            const SIZE: u32 = #rt::record_size(#layout);
+                            const ALIGN: u32 = #rt::record_align(#layout);
+
+                            fn load(cx: &#rt::Lift<'_>, offset: u32) -> Result<Self, #wasmer::RuntimeError> {
+                                let mut field_offset = offset;
+                                #(
+                                    field_offset = #rt::align_to(field_offset, <#types as #rt::ComponentType>::ALIGN);
+                                    let #names = <#types as #rt::ComponentType>::load(cx, field_offset)?;
+                                    field_offset += <#types as #rt::ComponentType>::SIZE;
+                                )*
+                                let _ = field_offset;
+                                Ok(Self { #(#names),* })
+                            }
+
+                            fn store(&self, cx: &#rt::Lower<'_>, offset: u32) -> Result<(), #wasmer::RuntimeError> {
+                                let mut field_offset = offset;
+                                #(
+                                    field_offset = #rt::align_to(field_offset, <#types as #rt::ComponentType>::ALIGN);
+                                    <#types as #rt::ComponentType>::store(&self.#names, cx, field_offset)?;
+                                    field_offset += <#types as #rt::ComponentType>::SIZE;
+                                )*
+                                let _ = field_offset;
+                                Ok(())
+                            }
+                        }
+                    }
+                }
+                TypeDefKind::Enum(cases) => {
+                    let wit_name = &def.name;
+                    let repr = match cases.len() {
+                        0..=0x100 => quote!(u8),
+                        0x101..=0x1_0000 => quote!(u16),
+                        _ => quote!(u32),
+                    };
+                    let discriminants = (0..cases.len()).map(Literal::usize_unsuffixed).collect::<Vec<_>>();
+                    let cases = cases.iter().map(|case| camel_case(case)).collect::<Vec<_>>();
+                    quote! {
+                        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+                        pub enum #name {
+                            #(#cases,)*
+                        }
+
+                        impl #rt::ComponentType for #name {
+                            const SIZE: u32 = <#repr as #rt::ComponentType>::SIZE;
+                            const ALIGN: u32 = <#repr as #rt::ComponentType>::ALIGN;
+
+                            fn load(cx: &#rt::Lift<'_>, offset: u32) -> Result<Self, #wasmer::RuntimeError> {
+                                match <#repr as #rt::ComponentType>::load(cx, offset)? {
+                                    #(#discriminants => Ok(Self::#cases),)*
+                                    discriminant => Err(#rt::invalid_discriminant(#wit_name, discriminant as i32)),
+                                }
+                            }
+
+                            fn store(&self, cx: &#rt::Lower<'_>, offset: u32) -> Result<(), #wasmer::RuntimeError> {
+                                <#repr as #rt::ComponentType>::store(&(*self as #repr), cx, offset)
+                            }
+                        }
+                    }
+                }
+                TypeDefKind::Alias(ty) => {
+                    let ty = self.rust_type(ty);
+                    quote!(pub type #name = #ty;)
+                }
+            }
+        });
+
+        quote!(#(#defs)*)
+    }
+
+    fn check_params(&self, function: &Function) -> Result<Vec<Flat>, String> {
+        let params = function.params.iter().map(|(_, ty)| ty).collect::<Vec<_>>();
+        let flats = self.flat_types(&params);
+        if flats.len() > MAX_FLAT_PARAMS {
+            return Err(format!(
+                "function `{}` has more than {} parameters once flattened, which isn't supported",
+                function.name, MAX_FLAT_PARAMS
+            ));
+        }
+        Ok(flats)
+    }
+
+    /// Generates the struct calling the functions exported by a module with
+    /// the interface.
+    pub fn exports(&mut self, name: &str) -> Result<TokenStream, String> {
+        let rt = quote!(::wasmer_wit_bindgen::rt);
+        let wasmer = quote!(::wasmer_wit_bindgen::wasmer);
+        let interface = self.interface;
+        let struct_name = camel_case(name);
+
+        let mut fields = Vec::new();
+        let mut inits = Vec::new();
+        let mut methods = Vec::new();
+        for function in &interface.functions {
+            let param_flats = self.check_params(function)?;
+            let result_flats = self.flat_types(&function.result.iter().collect::<Vec<_>>());
+            let field = format_ident!("func_{}", snake_case(&function.name));
+            let args = flat_list(&param_flats);
+            let rets = if result_flats.len() > 1 {
+                quote!(i32)
+            } else {
+                flat_list(&result_flats)
+            };
+            let wit_name = &function.name;
+            fields.push(quote!(#field: #wasmer::NativeFunc<#args, #rets>));
+            inits.push(quote!(#field: exports.get_native_function(#wit_name)?));
+
+            let method = snake_case(&function.name);
+            let param_names = function
+                .params
+                .iter()
+                .map(|(name, _)| snake_case(name))
+                .collect::<Vec<_>>();
+            let param_types = function.params.iter().map(|(_, ty)| self.param_type(ty));
+
+            let mut body = Vec::new();
+            if function.params.iter().any(|(_, ty)| self.uses_memory(ty)) {
+                body.push(
+                    quote!(let lower = #rt::Lower::new(&self.memory, self.realloc.as_ref());),
+                );
+            }
+            let mut flats = Vec::new();
+            for ((_, ty), name) in function.params.iter().zip(&param_names) {
+                self.lower(ty, quote!(&#name), &mut body, &mut flats);
+            }
+            let call = quote!(self.#field.call(#(#flats),*)?);
+
+            let result_type = match &function.result {
+                None => {
+                    body.push(quote!(#call;));
+                    body.push(quote!(Ok(())));
+                    quote!(())
+                }
+                Some(ty) => {
+                    if result_flats.len() > 1 || self.uses_memory(ty) {
+                        body.push(
+                            quote!(let lift = #rt::Lift::new(&self.memory, self.free.as_ref());),
+                        );
+                    }
+                    let result_type = self.rust_type(ty);
+                    let result = format_ident!("result");
+                    body.push(quote!(let #result = #call;));
+                    if result_flats.len() > 1 {
+                        body.push(quote! {
+                            Ok(<#result_type as #rt::ComponentType>::load(&lift, #result as u32)?)
+                        });
+                    } else {
+                        let value = self.lift(ty, &mut std::iter::once(result));
+                        body.push(quote!(Ok(#value)));
+                    }
+                    result_type
+                }
+            };
+
+            let doc = format!(
+                "Calls the `{}` function exported by the instance.",
+                wit_name
+            );
+            methods.push(quote! {
+                #[doc = #doc]
+                pub fn #method(&self, #(#param_names: #param_types),*) -> Result<#result_type, #wasmer::RuntimeError> {
+                    #(#body)*
+                }
+            });
+        }
+
+        let doc = format!(
+            "Calls the functions exported by an instance with the `{}` interface.",
+            name
+        );
+        Ok(quote! {
+            #[doc = #doc]
+            pub struct #struct_name {
+                memory: #wasmer::Memory,
+                realloc: Option<#rt::Realloc>,
+                free: Option<#rt::Free>,
+                #(#fields,)*
+            }
+
+            impl #struct_name {
+                /// Gets the exports of `instance` with the interface.
+                pub fn new(instance: &#wasmer::Instance) -> Result<Self, #wasmer::ExportError> {
+                    let exports = &instance.exports;
+                    Ok(Self {
+                        memory: #rt::memory(exports)?,
+                        realloc: exports.get_native_function(#rt::REALLOC).ok(),
+                        free: exports.get_native_function(#rt::FREE).ok(),
+                        #(#inits,)*
+                    })
+                }
+
+                #(#methods)*
+            }
+        })
+    }
+
+    /// Generates the trait of the functions imported by a module with the
+    /// interface, and the function adding an implementation of it to an
+    /// `ImportObject`.
+    pub fn imports(&mut self, name: &str) -> Result<TokenStream, String> {
+        let rt = quote!(::wasmer_wit_bindgen::rt);
+        let wasmer = quote!(::wasmer_wit_bindgen::wasmer);
+        let interface = self.interface;
+        let trait_name = camel_case(name);
+
+        let mut trait_methods = Vec::new();
+        let mut functions = Vec::new();
+        for function in &interface.functions {
+            let param_flats = self.check_params(function)?;
+            let result_flats = self.flat_types(&function.result.iter().collect::<Vec<_>>());
+            let wit_name = &function.name;
+            let method = snake_case(&function.name);
+            let param_names = function
+                .params
+                .iter()
+                .map(|(name, _)| snake_case(name))
+                .collect::<Vec<_>>();
+            let param_types = function
+                .params
+                .iter()
+                .map(|(_, ty)| self.rust_type(ty))
+                .collect::<Vec<_>>();
+            let result_type = function.result.as_ref().map(|ty| self.rust_type(ty));
+            let trait_result = result_type.as_ref().map(|ty| quote!(-> #ty));
+            let doc = format!("Implements the `{}` function.", wit_name);
+            trait_methods.push(quote! {
+                #[doc = #doc]
+                fn #method(&mut self, #(#param_names: #param_types),*) #trait_result;
+            });
+
+            let mut args = (0..param_flats.len())
+                .map(|index| format_ident!("arg{}", index))
+                .collect::<Vec<_>>();
+            let mut arg_types = param_flats.clone();
+            let retptr = format_ident!("retptr");
+            if result_flats.len() > 1 {
+                args.push(retptr.clone());
+                arg_types.push(Flat::I32);
+            }
+
+            let params_use_memory = function.params.iter().any(|(_, ty)| self.uses_memory(ty));
+            let mut body = vec![quote!(let mut data = env.data().borrow_mut()?;)];
+            if params_use_memory || result_flats.len() > 1 {
+                body.push(quote!(let memory = data.memory().ok_or_else(#rt::missing_memory)?;));
+            }
+            if params_use_memory {
+                body.push(quote!(let lift = #rt::Lift::new(memory, None);));
+            }
+            let mut arg_iter = args.iter().cloned();
+            let params = (0..function.params.len())
+                .map(|index| format_ident!("param{}", index))
+                .collect::<Vec<_>>();
+            for ((_, ty), param) in function.params.iter().zip(&params) {
+                let value = self.lift(ty, &mut arg_iter);
+                body.push(quote!(let #param = #value;));
+            }
+            body.push(quote! {
+                let result = <T as #trait_name>::#method(&mut *data, #(#params),*);
+                drop(data);
+            });
+
+            let rets = match &function.result {
+                None => {
+                    body.push(quote!(let () = result; Ok(())));
+                    quote!(())
+                }
+                Some(_) if result_flats.len() > 1 => {
+                    body.push(quote! {
+                        let lower = #rt::Lower::new(memory, env.realloc());
+                        <#result_type as #rt::ComponentType>::store(&result, &lower, #retptr as u32)?;
+                        Ok(())
+                    });
+                    quote!(())
+                }
+                Some(ty) => {
+                    let mut flats = Vec::new();
+                    self.lower(ty, quote!(&result), &mut body, &mut flats);
+                    body.push(quote!(Ok(#(#flats)*)));
+                    flat_list(&result_flats)
+                }
+            };
+
+            functions.push(quote! {
+                namespace.insert(
+                    #wit_name,
+                    #wasmer::Function::new_native_with_env(
+                        store,
+                        env.clone(),
+                        |env: &#rt::ImportEnv<T>, #(#args: #arg_types),*| -> Result<#rets, #wasmer::RuntimeError> {
+                            #(#body)*
+                        },
+                    ),
+                );
+            });
+        }
+
+        let trait_doc = format!(
+            "The functions imported by a module with the `{}` interface.",
+            name
+        );
+        let add_doc = format!(
+            "Adds the functions of `data` to `imports`, in the `{}` namespace, and returns the environment sharing it with them.",
+            name
+        );
+        Ok(quote! {
+            #[doc = #trait_doc]
+            pub trait #trait_name: Send + 'static {
+                #(#trait_methods)*
+            }
+
+            #[doc = #add_doc]
+            pub fn add_to_imports<T: #trait_name>(
+                store: &#wasmer::Store,
+                imports: &mut #wasmer::ImportObject,
+                data: T,
+            ) -> #wasmer::FunctionEnv<T> {
+                let data = #wasmer::FunctionEnv::new(data);
+                let env = #rt::ImportEnv::new(data.clone());
+                let mut namespace = #wasmer::Exports::new();
+                #(#functions)*
+                imports.register(#name, namespace);
+                data
+            }
+        })
+    }
+}
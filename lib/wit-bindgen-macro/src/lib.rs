@@ -0,0 +1,83 @@
+//! The procedural macros of `wasmer-wit-bindgen`, which generate Wasmer
+//! host bindings from `.wit` files.
+//!
+//! Use them through the `wasmer-wit-bindgen` crate, which provides the
+//! runtime support of the generated code.
+
+extern crate proc_macro;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::path::PathBuf;
+use syn::{parse_macro_input, LitStr};
+
+mod gen;
+mod wit;
+
+use crate::gen::Generator;
+use crate::wit::Interface;
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Import,
+    Export,
+}
+
+/// Generates the bindings to call the functions exported by a module with
+/// the interface of a `.wit` file.
+#[proc_macro]
+pub fn export(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    generate(&path, Direction::Export)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// Generates the bindings to provide the functions imported by a module
+/// with the interface of a `.wit` file.
+#[proc_macro]
+pub fn import(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    generate(&path, Direction::Import)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+fn generate(path: &LitStr, direction: Direction) -> syn::Result<TokenStream> {
+    let error = |message: String| syn::Error::new(path.span(), message);
+
+    let mut full_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default());
+    full_path.push(path.value());
+    let source = std::fs::read_to_string(&full_path)
+        .map_err(|e| error(format!("failed to read `{}`: {}", full_path.display(), e)))?;
+    let interface = Interface::parse(&source)
+        .map_err(|e| error(format!("failed to parse `{}`: {}", full_path.display(), e)))?;
+
+    let name = full_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| error("the name of the file isn't valid UTF-8".to_string()))?
+        .to_string();
+    let module = syn::parse_str::<syn::Ident>(&name.replace('-', "_"))
+        .map_err(|_| error(format!("`{}` isn't a valid module name", name)))?;
+
+    let mut generator = Generator::new(&interface);
+    let types = generator.type_defs();
+    let bindings = match direction {
+        Direction::Export => generator.exports(&name),
+        Direction::Import => generator.imports(&name),
+    }
+    .map_err(error)?;
+
+    // Rebuilds the bindings when the file changes
+    let full_path = full_path.to_string_lossy();
+    Ok(quote! {
+        #[allow(clippy::all, dead_code)]
+        pub mod #module {
+            const _: &str = include_str!(#full_path);
+
+            #types
+            #bindings
+        }
+    })
+}
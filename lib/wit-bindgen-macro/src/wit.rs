@@ -0,0 +1,525 @@
+//! A parser for the subset of the `.wit` format the bindings support:
+//! functions, records, enums and type aliases, over the primitive types,
+//! strings, lists, options and tuples.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// A parsed `.wit` interface.
+#[derive(Debug, Default)]
+pub struct Interface {
+    pub types: Vec<TypeDef>,
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug)]
+pub struct TypeDef {
+    pub name: String,
+    pub kind: TypeDefKind,
+}
+
+#[derive(Debug)]
+pub enum TypeDefKind {
+    Record(Vec<(String, Type)>),
+    Enum(Vec<String>),
+    Alias(Type),
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<(String, Type)>,
+    pub result: Option<Type>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    U8,
+    U16,
+    U32,
+    U64,
+    S8,
+    S16,
+    S32,
+    S64,
+    F32,
+    F64,
+    Bool,
+    Char,
+    String,
+    List(Box<Type>),
+    Option(Box<Type>),
+    Tuple(Vec<Type>),
+    Named(String),
+}
+
+/// An error while parsing a `.wit` file, with the line it occurred on, if
+/// it's a syntax error.
+#[derive(Debug)]
+pub struct Error {
+    line: Option<usize>,
+    message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Colon,
+    Comma,
+    Equals,
+    Arrow,
+    LeftParen,
+    RightParen,
+    LeftAngle,
+    RightAngle,
+    LeftBrace,
+    RightBrace,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "`{}`", ident),
+            Token::Colon => f.write_str("`:`"),
+            Token::Comma => f.write_str("`,`"),
+            Token::Equals => f.write_str("`=`"),
+            Token::Arrow => f.write_str("`->`"),
+            Token::LeftParen => f.write_str("`(`"),
+            Token::RightParen => f.write_str("`)`"),
+            Token::LeftAngle => f.write_str("`<`"),
+            Token::RightAngle => f.write_str("`>`"),
+            Token::LeftBrace => f.write_str("`{`"),
+            Token::RightBrace => f.write_str("`}`"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().map_or(false, |&c| c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => {
+                            return Err(Error {
+                                line: Some(line),
+                                message: "unterminated comment".to_string(),
+                            })
+                        }
+                    }
+                }
+                continue;
+            }
+            ':' => Token::Colon,
+            ',' => Token::Comma,
+            '=' => Token::Equals,
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                Token::Arrow
+            }
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '<' => Token::LeftAngle,
+            '>' => Token::RightAngle,
+            '{' => Token::LeftBrace,
+            '}' => Token::RightBrace,
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                Token::Ident(ident)
+            }
+            c => {
+                return Err(Error {
+                    line: Some(line),
+                    message: format!("unexpected character `{}`", c),
+                })
+            }
+        };
+        tokens.push((token, line));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, Error> {
+        Err(Error {
+            line: Some(self.line()),
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), Error> {
+        match self.peek() {
+            Some(next) if *next == token => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(next) => self.error(format!("expected {}, found {}", token, next)),
+            None => self.error(format!("expected {}, found the end of the file", token)),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some(Token::Ident(ident)) => {
+                let ident = ident.clone();
+                self.position += 1;
+                Ok(ident)
+            }
+            Some(next) => self.error(format!("expected a name, found {}", next)),
+            None => self.error("expected a name, found the end of the file"),
+        }
+    }
+
+    /// Parses the items of a comma-separated list, which may have a
+    /// trailing comma, up to the `end` token.
+    fn list<T>(
+        &mut self,
+        end: Token,
+        mut item: impl FnMut(&mut Self) -> Result<T, Error>,
+    ) -> Result<Vec<T>, Error> {
+        let mut items = Vec::new();
+        loop {
+            if self.eat(&end) {
+                return Ok(items);
+            }
+            items.push(item(self)?);
+            if !self.eat(&Token::Comma) {
+                self.expect(end)?;
+                return Ok(items);
+            }
+        }
+    }
+
+    fn interface(&mut self) -> Result<Interface, Error> {
+        let mut interface = Interface::default();
+        while self.peek().is_some() {
+            let line = self.line();
+            let name = self.ident()?;
+            let kind = match name.as_str() {
+                "record" => {
+                    let name = self.ident()?;
+                    self.expect(Token::LeftBrace)?;
+                    let fields = self.list(Token::RightBrace, Self::named_type)?;
+                    if fields.is_empty() {
+                        return self.error(format!("record `{}` has no fields", name));
+                    }
+                    Some((name, TypeDefKind::Record(fields)))
+                }
+                "enum" => {
+                    let name = self.ident()?;
+                    self.expect(Token::LeftBrace)?;
+                    let cases = self.list(Token::RightBrace, Self::ident)?;
+                    if cases.is_empty() {
+                        return self.error(format!("enum `{}` has no cases", name));
+                    }
+                    Some((name, TypeDefKind::Enum(cases)))
+                }
+                "type" => {
+                    let name = self.ident()?;
+                    self.expect(Token::Equals)?;
+                    Some((name, TypeDefKind::Alias(self.ty()?)))
+                }
+                "variant" | "union" | "flags" | "resource" | "handle" | "expected" | "use" => {
+                    return Err(Error {
+                        line: Some(line),
+                        message: format!("`{}` is not supported", name),
+                    })
+                }
+                _ => None,
+            };
+
+            match kind {
+                Some((name, kind)) => interface.types.push(TypeDef { name, kind }),
+                None => {
+                    self.expect(Token::Colon)?;
+                    match self.ident()?.as_str() {
+                        "function" => {}
+                        other => {
+                            return self.error(format!("expected `function`, found `{}`", other))
+                        }
+                    }
+                    self.expect(Token::LeftParen)?;
+                    let params = self.list(Token::RightParen, Self::named_type)?;
+                    let result = if self.eat(&Token::Arrow) {
+                        match self.ty()? {
+                            Type::Tuple(types) if types.is_empty() => None,
+                            ty => Some(ty),
+                        }
+                    } else {
+                        None
+                    };
+                    interface.functions.push(Function {
+                        name,
+                        params,
+                        result,
+                    });
+                }
+            }
+        }
+        Ok(interface)
+    }
+
+    fn named_type(&mut self) -> Result<(String, Type), Error> {
+        let name = self.ident()?;
+        self.expect(Token::Colon)?;
+        Ok((name, self.ty()?))
+    }
+
+    fn ty(&mut self) -> Result<Type, Error> {
+        if self.eat(&Token::LeftParen) {
+            return Ok(Type::Tuple(self.list(Token::RightParen, Self::ty)?));
+        }
+
+        let ty = match self.ident()?.as_str() {
+            "u8" => Type::U8,
+            "u16" => Type::U16,
+            "u32" => Type::U32,
+            "u64" => Type::U64,
+            "s8" => Type::S8,
+            "s16" => Type::S16,
+            "s32" => Type::S32,
+            "s64" => Type::S64,
+            "f32" => Type::F32,
+            "f64" => Type::F64,
+            "bool" => Type::Bool,
+            "char" => Type::Char,
+            "string" => Type::String,
+            "list" => Type::List(Box::new(self.type_argument()?)),
+            "option" => Type::Option(Box::new(self.type_argument()?)),
+            "tuple" => {
+                self.expect(Token::LeftAngle)?;
+                Type::Tuple(self.list(Token::RightAngle, Self::ty)?)
+            }
+            name => Type::Named(name.to_string()),
+        };
+        Ok(ty)
+    }
+
+    fn type_argument(&mut self) -> Result<Type, Error> {
+        self.expect(Token::LeftAngle)?;
+        let ty = self.ty()?;
+        self.expect(Token::RightAngle)?;
+        Ok(ty)
+    }
+}
+
+impl Interface {
+    /// Parses `source`, and checks that the types it uses are defined.
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let interface = parser.interface()?;
+        interface.check()?;
+        Ok(interface)
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        let error = |message: String| {
+            Err(Error {
+                line: None,
+                message,
+            })
+        };
+
+        let mut names = HashMap::new();
+        for (index, def) in self.types.iter().enumerate() {
+            if names.insert(def.name.as_str(), index).is_some() {
+                return error(format!("type `{}` is defined twice", def.name));
+            }
+        }
+        let mut functions = HashMap::new();
+        for function in &self.functions {
+            if functions.insert(function.name.as_str(), ()).is_some() {
+                return error(format!("function `{}` is defined twice", function.name));
+            }
+        }
+
+        // Types may only refer to the types defined before them, which
+        // rules out recursive types
+        fn check_type(
+            ty: &Type,
+            names: &HashMap<&str, usize>,
+            before: usize,
+        ) -> Result<(), String> {
+            match ty {
+                Type::List(ty) | Type::Option(ty) => check_type(ty, names, before),
+                Type::Tuple(types) => types
+                    .iter()
+                    .try_for_each(|ty| check_type(ty, names, before)),
+                Type::Named(name) => match names.get(name.as_str()) {
+                    Some(&index) if index < before => Ok(()),
+                    Some(_) => Err(format!("type `{}` is used before its definition", name)),
+                    None => Err(format!("type `{}` is not defined", name)),
+                },
+                _ => Ok(()),
+            }
+        }
+
+        for (index, def) in self.types.iter().enumerate() {
+            let result = match &def.kind {
+                TypeDefKind::Record(fields) => fields
+                    .iter()
+                    .try_for_each(|(_, ty)| check_type(ty, &names, index)),
+                TypeDefKind::Alias(ty) => check_type(ty, &names, index),
+                TypeDefKind::Enum(_) => Ok(()),
+            };
+            if let Err(message) = result {
+                return error(message);
+            }
+        }
+        for function in &self.functions {
+            let result = function
+                .params
+                .iter()
+                .map(|(_, ty)| ty)
+                .chain(function.result.as_ref())
+                .try_for_each(|ty| check_type(ty, &names, self.types.len()));
+            if let Err(message) = result {
+                return error(format!("in function `{}`: {}", function.name, message));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the definition of the type named `name`.
+    pub fn type_def(&self, name: &str) -> &TypeDef {
+        self.types
+            .iter()
+            .find(|def| def.name == name)
+            .expect("the interface to be checked")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_functions_and_types() {
+        let interface = Interface::parse(
+            r#"
+            // A point.
+            record point { x: s32, y: s32, }
+            enum color { red, green-ish }
+            type points = list<point>
+
+            /* Functions */
+            greet: function(name: string) -> string
+            draw: function(points: points, color: option<color>)
+            bounds: function(points: points) -> tuple<point, point>
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(interface.types.len(), 3);
+        match &interface.types[1].kind {
+            TypeDefKind::Enum(cases) => assert_eq!(cases, &["red", "green-ish"]),
+            other => panic!("unexpected type: {:?}", other),
+        }
+        assert_eq!(interface.functions.len(), 3);
+        assert_eq!(
+            interface.functions[1].params,
+            vec![
+                ("points".to_string(), Type::Named("points".to_string())),
+                (
+                    "color".to_string(),
+                    Type::Option(Box::new(Type::Named("color".to_string())))
+                ),
+            ]
+        );
+        assert_eq!(interface.functions[1].result, None);
+        assert_eq!(
+            interface.functions[2].result,
+            Some(Type::Tuple(vec![
+                Type::Named("point".to_string()),
+                Type::Named("point".to_string())
+            ]))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |source| Interface::parse(source).unwrap_err().to_string();
+
+        assert_eq!(
+            error("f: function(a: u32\ng: function()"),
+            "line 2: expected `)`, found `g`"
+        );
+        assert_eq!(
+            error("f: function(a: foo)"),
+            "in function `f`: type `foo` is not defined"
+        );
+        assert_eq!(
+            error("variant v { a, b }"),
+            "line 1: `variant` is not supported"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Wasmer host bindings generated from WebAssembly interface types.
+//!
+//! The [`export!`] and [`import!`] macros read a `.wit` file describing an
+//! interface, and generate the Rust types and functions to respectively
+//! call the functions a module exports with that interface, and provide the
+//! functions a module imports with it. Strings, lists, records and the
+//! other interface types are passed between the host and the module with
+//! the canonical ABI, so they don't have to be copied in and out of the
+//! memory of the module by hand.
+//!
+//! The module has to export its `memory`, and the `canonical_abi_realloc`
+//! and `canonical_abi_free` functions to allocate and free the buffers of
+//! strings and lists, like the guest bindings of `wit-bindgen` do.
+//!
+//! # Calling the exports of a module
+//!
+//! ```ignore
+//! // greeter.wit:
+//! //     greet: function(name: string) -> string
+//! wasmer_wit_bindgen::export!("greeter.wit");
+//!
+//! let instance = Instance::new(&module, &imports! {})?;
+//! let greeter = greeter::Greeter::new(&instance)?;
+//! assert_eq!(greeter.greet("world")?, "Hello, world!");
+//! ```
+//!
+//! # Providing the imports of a module
+//!
+//! ```ignore
+//! // logger.wit:
+//! //     log: function(message: string)
+//! wasmer_wit_bindgen::import!("logger.wit");
+//!
+//! struct Messages(Vec<String>);
+//!
+//! impl logger::Logger for Messages {
+//!     fn log(&mut self, message: String) {
+//!         self.0.push(message);
+//!     }
+//! }
+//!
+//! let mut import_object = ImportObject::new();
+//! let messages = logger::add_to_imports(&store, &mut import_object, Messages(vec![]));
+//! let instance = Instance::new(&module, &import_object)?;
+//! ```
+
+#![deny(missing_docs, broken_intra_doc_links)]
+
+pub mod rt;
+
+pub use wasmer;
+
+/// Generates the bindings to call the functions exported by a module with
+/// the interface of a `.wit` file.
+///
+/// The path of the file is relative to the directory of the manifest of
+/// the crate. The bindings are generated in a module named after the file,
+/// with a struct named after it too, which is created from an [`Instance`]
+/// and has a method for each function of the interface.
+///
+/// [`Instance`]: wasmer::Instance
+pub use wasmer_wit_bindgen_macro::export;
+
+/// Generates the bindings to provide the functions imported by a module
+/// with the interface of a `.wit` file.
+///
+/// The path of the file is relative to the directory of the manifest of
+/// the crate. The bindings are generated in a module named after the file,
+/// with a trait named after it too, which has a method for each function of
+/// the interface, and an `add_to_imports` function which adds the functions
+/// of an implementation of the trait to an [`ImportObject`], in the
+/// namespace named after the file.
+///
+/// [`ImportObject`]: wasmer::ImportObject
+pub use wasmer_wit_bindgen_macro::import;
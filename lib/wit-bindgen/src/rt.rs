@@ -0,0 +1,435 @@
+//! The canonical ABI: how the values of the interface types are lowered
+//! into the memory of a module, and lifted back out of it.
+//!
+//! This is the runtime support of the code generated by
+//! [`import!`](crate::import) and [`export!`](crate::export), which doesn't
+//! usually need to be used directly.
+
+use std::convert::TryFrom;
+use std::mem;
+use wasmer::{
+    Exports, FunctionEnv, HostEnvInitError, Instance, LazyInit, Memory, MemoryAccessError,
+    NativeFunc, RuntimeError, WasmerEnv,
+};
+
+/// The name of the allocation function exported by modules using the
+/// canonical ABI.
+pub const REALLOC: &str = "canonical_abi_realloc";
+
+/// The name of the deallocation function exported by modules using the
+/// canonical ABI.
+pub const FREE: &str = "canonical_abi_free";
+
+/// The signature of [`REALLOC`]: `(old_ptr, old_size, align, new_size) -> ptr`.
+pub type Realloc = NativeFunc<(i32, i32, i32, i32), i32>;
+
+/// The signature of [`FREE`]: `(ptr, size, align)`.
+pub type Free = NativeFunc<(i32, i32, i32), ()>;
+
+/// Rounds `offset` up to a multiple of `align`, which is a power of two.
+pub const fn align_to(offset: u32, align: u32) -> u32 {
+    (offset + align - 1) & !(align - 1)
+}
+
+/// The alignment of a record with fields of the `(size, align)` given.
+pub const fn record_align(fields: &[(u32, u32)]) -> u32 {
+    let mut align = 1;
+    let mut i = 0;
+    while i < fields.len() {
+        if fields[i].1 > align {
+            align = fields[i].1;
+        }
+        i += 1;
+    }
+    align
+}
+
+/// The size of a record with fields of the `(size, align)` given, laid out
+/// in order, each aligned, and padded to the alignment of the record.
+pub const fn record_size(fields: &[(u32, u32)]) -> u32 {
+    let mut size = 0;
+    let mut i = 0;
+    while i < fields.len() {
+        size = align_to(size, fields[i].1) + fields[i].0;
+        i += 1;
+    }
+    align_to(size, record_align(fields))
+}
+
+/// Checks that the `len` bytes at `offset` are in `memory`.
+fn check_bounds(memory: &Memory, offset: u32, len: u32) -> Result<(), RuntimeError> {
+    if offset as u64 + len as u64 > memory.data_size() {
+        return Err(MemoryAccessError::HeapOutOfBounds.into());
+    }
+    Ok(())
+}
+
+/// The length of a string or a list, which must fit in 32 bits.
+fn checked_len(len: usize) -> Result<u32, RuntimeError> {
+    u32::try_from(len).map_err(|_| RuntimeError::new("list too long"))
+}
+
+/// Lowers values into the memory of a module, allocating with its
+/// [`REALLOC`] function.
+pub struct Lower<'a> {
+    memory: &'a Memory,
+    realloc: Option<&'a Realloc>,
+}
+
+impl<'a> Lower<'a> {
+    /// Creates a new `Lower` for `memory`.
+    pub fn new(memory: &'a Memory, realloc: Option<&'a Realloc>) -> Self {
+        Self { memory, realloc }
+    }
+
+    /// Allocates `size` bytes aligned to `align`, and returns their offset.
+    pub fn alloc(&self, size: u32, align: u32) -> Result<u32, RuntimeError> {
+        // Nothing is read at the offset of an empty buffer
+        if size == 0 {
+            return Ok(align);
+        }
+        let realloc = self
+            .realloc
+            .ok_or_else(|| RuntimeError::new(format!("the module doesn't export `{}`", REALLOC)))?;
+        let ptr = realloc.call(0, 0, align as i32, size as i32)? as u32;
+        if ptr % align != 0 {
+            return Err(RuntimeError::new(format!(
+                "`{}` returned the unaligned pointer {:#x}",
+                REALLOC, ptr
+            )));
+        }
+        check_bounds(self.memory, ptr, size)?;
+        Ok(ptr)
+    }
+
+    /// Writes `bytes` at `offset`.
+    pub fn write(&self, offset: u32, bytes: &[u8]) -> Result<(), RuntimeError> {
+        Ok(self.memory.write(offset as u64, bytes)?)
+    }
+}
+
+/// Lifts values out of the memory of a module.
+///
+/// The buffers of strings and lists are deallocated with the [`FREE`]
+/// function when there is one, when they're owned by the caller.
+pub struct Lift<'a> {
+    memory: &'a Memory,
+    free: Option<&'a Free>,
+}
+
+impl<'a> Lift<'a> {
+    /// Creates a new `Lift` for `memory`, which frees the buffers it lifts
+    /// with `free`, if any.
+    pub fn new(memory: &'a Memory, free: Option<&'a Free>) -> Self {
+        Self { memory, free }
+    }
+
+    /// Reads `len` bytes at `offset`.
+    pub fn read(&self, offset: u32, len: u32) -> Result<Vec<u8>, RuntimeError> {
+        // The length comes from the module, so it's checked before allocating
+        check_bounds(self.memory, offset, len)?;
+        let mut bytes = vec![0; len as usize];
+        self.memory.read(offset as u64, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn free(&self, ptr: u32, size: u32, align: u32) -> Result<(), RuntimeError> {
+        match self.free {
+            Some(free) if size > 0 => free.call(ptr as i32, size as i32, align as i32),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A type of the interface, with a representation in memory.
+pub trait ComponentType: Sized {
+    /// The size of the type in memory.
+    const SIZE: u32;
+    /// The alignment of the type in memory.
+    const ALIGN: u32;
+
+    /// Loads a value from `offset`.
+    fn load(cx: &Lift<'_>, offset: u32) -> Result<Self, RuntimeError>;
+
+    /// Stores the value at `offset`.
+    fn store(&self, cx: &Lower<'_>, offset: u32) -> Result<(), RuntimeError>;
+}
+
+macro_rules! impl_component_type_for_numbers {
+    ($($ty:ty),*) => {
+        $(
+            impl ComponentType for $ty {
+                const SIZE: u32 = mem::size_of::<$ty>() as u32;
+                const ALIGN: u32 = mem::size_of::<$ty>() as u32;
+
+                fn load(cx: &Lift<'_>, offset: u32) -> Result<Self, RuntimeError> {
+                    let mut bytes = [0; mem::size_of::<$ty>()];
+                    cx.memory.read(offset as u64, &mut bytes)?;
+                    Ok(<$ty>::from_le_bytes(bytes))
+                }
+
+                fn store(&self, cx: &Lower<'_>, offset: u32) -> Result<(), RuntimeError> {
+                    cx.write(offset, &self.to_le_bytes())
+                }
+            }
+        )*
+    };
+}
+
+impl_component_type_for_numbers!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl ComponentType for bool {
+    const SIZE: u32 = 1;
+    const ALIGN: u32 = 1;
+
+    fn load(cx: &Lift<'_>, offset: u32) -> Result<Self, RuntimeError> {
+        Ok(u8::load(cx, offset)? != 0)
+    }
+
+    fn store(&self, cx: &Lower<'_>, offset: u32) -> Result<(), RuntimeError> {
+        (*self as u8).store(cx, offset)
+    }
+}
+
+impl ComponentType for char {
+    const SIZE: u32 = 4;
+    const ALIGN: u32 = 4;
+
+    fn load(cx: &Lift<'_>, offset: u32) -> Result<Self, RuntimeError> {
+        lift_char(u32::load(cx, offset)? as i32)
+    }
+
+    fn store(&self, cx: &Lower<'_>, offset: u32) -> Result<(), RuntimeError> {
+        (*self as u32).store(cx, offset)
+    }
+}
+
+impl ComponentType for String {
+    const SIZE: u32 = 8;
+    const ALIGN: u32 = 4;
+
+    fn load(cx: &Lift<'_>, offset: u32) -> Result<Self, RuntimeError> {
+        let ptr = u32::load(cx, offset)?;
+        let len = u32::load(cx, offset + 4)?;
+        lift_string(cx, ptr as i32, len as i32)
+    }
+
+    fn store(&self, cx: &Lower<'_>, offset: u32) -> Result<(), RuntimeError> {
+        let (ptr, len) = lower_str(cx, self)?;
+        (ptr as u32).store(cx, offset)?;
+        (len as u32).store(cx, offset + 4)
+    }
+}
+
+impl<T: ComponentType> ComponentType for Vec<T> {
+    const SIZE: u32 = 8;
+    const ALIGN: u32 = 4;
+
+    fn load(cx: &Lift<'_>, offset: u32) -> Result<Self, RuntimeError> {
+        let ptr = u32::load(cx, offset)?;
+        let len = u32::load(cx, offset + 4)?;
+        lift_list(cx, ptr as i32, len as i32)
+    }
+
+    fn store(&self, cx: &Lower<'_>, offset: u32) -> Result<(), RuntimeError> {
+        let (ptr, len) = lower_list(cx, self)?;
+        (ptr as u32).store(cx, offset)?;
+        (len as u32).store(cx, offset + 4)
+    }
+}
+
+impl<T: ComponentType> ComponentType for Option<T> {
+    const SIZE: u32 = record_size(&[(1, 1), (T::SIZE, T::ALIGN)]);
+    const ALIGN: u32 = record_align(&[(1, 1), (T::SIZE, T::ALIGN)]);
+
+    fn load(cx: &Lift<'_>, offset: u32) -> Result<Self, RuntimeError> {
+        match u8::load(cx, offset)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::load(cx, offset + align_to(1, T::ALIGN))?)),
+            discriminant => Err(invalid_discriminant("option", discriminant as i32)),
+        }
+    }
+
+    fn store(&self, cx: &Lower<'_>, offset: u32) -> Result<(), RuntimeError> {
+        match self {
+            None => 0u8.store(cx, offset),
+            Some(value) => {
+                1u8.store(cx, offset)?;
+                value.store(cx, offset + align_to(1, T::ALIGN))
+            }
+        }
+    }
+}
+
+macro_rules! impl_component_type_for_tuples {
+    ($(($($ty:ident),+)),*) => {
+        $(
+            #[allow(non_snake_case)]
+            impl<$($ty: ComponentType),+> ComponentType for ($($ty,)+) {
+                const SIZE: u32 = record_size(&[$(($ty::SIZE, $ty::ALIGN)),+]);
+                const ALIGN: u32 = record_align(&[$(($ty::SIZE, $ty::ALIGN)),+]);
+
+                fn load(cx: &Lift<'_>, offset: u32) -> Result<Self, RuntimeError> {
+                    let mut field_offset = offset;
+                    $(
+                        field_offset = align_to(field_offset, $ty::ALIGN);
+                        let $ty = $ty::load(cx, field_offset)?;
+                        field_offset += $ty::SIZE;
+                    )+
+                    let _ = field_offset;
+                    Ok(($($ty,)+))
+                }
+
+                fn store(&self, cx: &Lower<'_>, offset: u32) -> Result<(), RuntimeError> {
+                    let ($($ty,)+) = self;
+                    let mut field_offset = offset;
+                    $(
+                        field_offset = align_to(field_offset, <$ty as ComponentType>::ALIGN);
+                        $ty.store(cx, field_offset)?;
+                        field_offset += <$ty as ComponentType>::SIZE;
+                    )+
+                    let _ = field_offset;
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_component_type_for_tuples!((A), (A, B), (A, B, C), (A, B, C, D), (A, B, C, D, E));
+
+/// Lowers the string `s`, and returns its pointer and length.
+pub fn lower_str(cx: &Lower<'_>, s: &str) -> Result<(i32, i32), RuntimeError> {
+    let len = checked_len(s.len())?;
+    let ptr = cx.alloc(len, 1)?;
+    cx.write(ptr, s.as_bytes())?;
+    Ok((ptr as i32, len as i32))
+}
+
+/// Lowers the list `list`, and returns its pointer and length.
+pub fn lower_list<T: ComponentType>(
+    cx: &Lower<'_>,
+    list: &[T],
+) -> Result<(i32, i32), RuntimeError> {
+    let len = checked_len(list.len())?;
+    let size = len
+        .checked_mul(T::SIZE)
+        .ok_or_else(|| RuntimeError::new("list too long"))?;
+    let ptr = cx.alloc(size, T::ALIGN)?;
+    // The allocation is in bounds, so only the offset past the last item
+    // can wrap
+    let mut offset = ptr;
+    for item in list {
+        item.store(cx, offset)?;
+        offset = offset.wrapping_add(T::SIZE);
+    }
+    Ok((ptr as i32, len as i32))
+}
+
+/// Lifts the string of `len` bytes at `ptr`.
+pub fn lift_string(cx: &Lift<'_>, ptr: i32, len: i32) -> Result<String, RuntimeError> {
+    let bytes = cx.read(ptr as u32, len as u32)?;
+    cx.free(ptr as u32, len as u32, 1)?;
+    String::from_utf8(bytes).map_err(|_| RuntimeError::new("invalid utf-8 string"))
+}
+
+/// Lifts the list of `len` items at `ptr`.
+pub fn lift_list<T: ComponentType>(
+    cx: &Lift<'_>,
+    ptr: i32,
+    len: i32,
+) -> Result<Vec<T>, RuntimeError> {
+    let (ptr, len) = (ptr as u32, len as u32);
+    let size = len
+        .checked_mul(T::SIZE)
+        .ok_or_else(|| RuntimeError::new("list too long"))?;
+    // The length comes from the module, so it's checked before allocating
+    check_bounds(cx.memory, ptr, size)?;
+    let mut list = Vec::with_capacity(len as usize);
+    // Only the offset past the last item can wrap
+    let mut offset = ptr;
+    for _ in 0..len {
+        list.push(T::load(cx, offset)?);
+        offset = offset.wrapping_add(T::SIZE);
+    }
+    cx.free(ptr, size, T::ALIGN)?;
+    Ok(list)
+}
+
+/// Lifts a `bool` passed as an `i32`.
+pub fn lift_bool(value: i32) -> bool {
+    value != 0
+}
+
+/// Lifts a `char` passed as an `i32`.
+pub fn lift_char(value: i32) -> Result<char, RuntimeError> {
+    std::char::from_u32(value as u32)
+        .ok_or_else(|| RuntimeError::new(format!("invalid char {:#x}", value)))
+}
+
+/// The error of an invalid discriminant `value` of an enum or an option
+/// named `name`.
+pub fn invalid_discriminant(name: &str, value: i32) -> RuntimeError {
+    RuntimeError::new(format!("invalid discriminant {} for `{}`", value, name))
+}
+
+/// The environment of the host functions of imported interfaces: the data
+/// implementing the interface, and the memory and the allocation function
+/// of the instance calling them.
+pub struct ImportEnv<T> {
+    data: FunctionEnv<T>,
+    realloc: LazyInit<Realloc>,
+}
+
+impl<T: Send> ImportEnv<T> {
+    /// Creates a new environment sharing the data of `data`.
+    pub fn new(data: FunctionEnv<T>) -> Self {
+        Self {
+            data,
+            realloc: LazyInit::new(),
+        }
+    }
+
+    /// The data implementing the interface.
+    pub fn data(&self) -> &FunctionEnv<T> {
+        &self.data
+    }
+
+    /// The allocation function of the instance, if it exports one.
+    pub fn realloc(&self) -> Option<&Realloc> {
+        self.realloc.get_ref()
+    }
+}
+
+impl<T> Clone for ImportEnv<T> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            realloc: self.realloc.clone(),
+        }
+    }
+}
+
+impl<T: Send> WasmerEnv for ImportEnv<T> {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        self.data.init_with_instance(instance)?;
+        if let Ok(realloc) = instance
+            .exports
+            .get_with_generics_weak::<Realloc, _, _>(REALLOC)
+        {
+            self.realloc.initialize(realloc);
+        }
+        Ok(())
+    }
+}
+
+/// Gets the memory of `instance`, which modules using the canonical ABI
+/// export as `memory`.
+pub fn memory(exports: &Exports) -> Result<Memory, wasmer::ExportError> {
+    exports.get_memory("memory").map(Clone::clone)
+}
+
+/// The error of a host function called by an instance without a memory.
+pub fn missing_memory() -> RuntimeError {
+    RuntimeError::new("the module calling the host function doesn't export a `memory`")
+}
@@ -0,0 +1,224 @@
+#![cfg(feature = "sys")]
+
+use anyhow::Result;
+use wasmer::*;
+
+wasmer_wit_bindgen::export!("tests/wit/guest.wit");
+wasmer_wit_bindgen::import!("tests/wit/host.wit");
+
+/// The canonical ABI functions of the guests: a bump allocator, and a
+/// `free` counting the bytes freed.
+const CANONICAL_ABI: &str = r#"
+    (global $heap (mut i32) (i32.const 1024))
+    (global $freed (export "freed") (mut i32) (i32.const 0))
+    (func $realloc (export "canonical_abi_realloc")
+        (param $old_ptr i32) (param $old_size i32) (param $align i32) (param $new_size i32)
+        (result i32)
+        (local $ptr i32)
+        (local.set $ptr
+            (i32.and
+                (i32.add (global.get $heap) (i32.sub (local.get $align) (i32.const 1)))
+                (i32.sub (i32.const 0) (local.get $align))))
+        (global.set $heap (i32.add (local.get $ptr) (local.get $new_size)))
+        (local.get $ptr))
+    (func (export "canonical_abi_free") (param $ptr i32) (param $size i32) (param $align i32)
+        (global.set $freed (i32.add (global.get $freed) (local.get $size))))
+"#;
+
+fn guest_wat() -> String {
+    format!(
+        r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 0) "Hello, !")
+            {}
+            (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1)))
+            (func (export "greet") (param $ptr i32) (param $len i32) (result i32)
+                (local $out i32)
+                (local.set $out
+                    (call $realloc (i32.const 0) (i32.const 0) (i32.const 1)
+                        (i32.add (local.get $len) (i32.const 8))))
+                (memory.copy (local.get $out) (i32.const 0) (i32.const 7))
+                (memory.copy
+                    (i32.add (local.get $out) (i32.const 7)) (local.get $ptr) (local.get $len))
+                (memory.copy
+                    (i32.add (i32.add (local.get $out) (i32.const 7)) (local.get $len))
+                    (i32.const 7) (i32.const 1))
+                (i32.store (i32.const 16) (local.get $out))
+                (i32.store (i32.const 20) (i32.add (local.get $len) (i32.const 8)))
+                (i32.const 16))
+            (func (export "translate") (param $x i32) (param $y i32) (param $dx i32) (result i32)
+                (i32.store (i32.const 24) (i32.add (local.get $x) (local.get $dx)))
+                (i32.store (i32.const 28) (local.get $y))
+                (i32.const 24))
+            (func (export "sum") (param $ptr i32) (param $len i32) (result i64)
+                (local $sum i64)
+                (block $done
+                    (loop $next
+                        (br_if $done (i32.eqz (local.get $len)))
+                        (local.set $sum
+                            (i64.add (local.get $sum) (i64.extend_i32_u (i32.load (local.get $ptr)))))
+                        (local.set $ptr (i32.add (local.get $ptr) (i32.const 4)))
+                        (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+                        (br $next)))
+                (local.get $sum))
+            (func (export "first") (param $ptr i32) (param $len i32) (result i32)
+                (if (i32.eqz (local.get $len))
+                    (then (i32.store8 (i32.const 32) (i32.const 0)))
+                    (else
+                        (i32.store8 (i32.const 32) (i32.const 1))
+                        (i32.store (i32.const 36) (i32.load (local.get $ptr)))))
+                (i32.const 32)))"#,
+        CANONICAL_ABI
+    )
+}
+
+fn host_wat() -> String {
+    format!(
+        r#"(module
+            (import "host" "log" (func $log (param i32 i32 i32)))
+            (import "host" "name" (func $name (param i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "started")
+            {}
+            (func (export "run")
+                (call $log (i32.const 0) (i32.const 0) (i32.const 7))
+                (call $name (i32.const 16))
+                (call $log (i32.const 1) (i32.load (i32.const 16)) (i32.load (i32.const 20)))))"#,
+        CANONICAL_ABI
+    )
+}
+
+#[test]
+fn call_exports() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, guest_wat())?;
+    let instance = Instance::new(&module, &imports! {})?;
+    let guest = guest::Guest::new(&instance)?;
+
+    assert_eq!(guest.add(1, 2)?, 3);
+    assert_eq!(guest.greet("wasmer")?, "Hello, wasmer!");
+    assert_eq!(
+        guest.translate(guest::Point { x: 1, y: 2 }, 3)?,
+        guest::Point { x: 4, y: 2 }
+    );
+    assert_eq!(guest.sum(&[1, 2, u32::MAX])?, u32::MAX as u64 + 3);
+    assert_eq!(guest.sum(&[])?, 0);
+    assert_eq!(guest.first(&[7, 8])?, Some(7));
+    assert_eq!(guest.first(&[])?, None);
+
+    // The string returned by `greet` is freed once lifted
+    let freed = instance.exports.get_global("freed")?.get().unwrap_i32();
+    assert_eq!(freed, "Hello, wasmer!".len() as i32);
+
+    Ok(())
+}
+
+#[test]
+fn missing_export() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, r#"(module (memory (export "memory") 1))"#)?;
+    let instance = Instance::new(&module, &imports! {})?;
+
+    match guest::Guest::new(&instance) {
+        Err(ExportError::Missing(name)) => assert_eq!(name, "add"),
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    Ok(())
+}
+
+struct Recorder {
+    name: String,
+    messages: Vec<(host::Level, String)>,
+}
+
+impl host::Host for Recorder {
+    fn log(&mut self, level: host::Level, message: String) {
+        self.messages.push((level, message));
+    }
+
+    fn name(&mut self) -> String {
+        self.name.clone()
+    }
+}
+
+#[test]
+fn provide_imports() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(&store, host_wat())?;
+    let mut import_object = ImportObject::new();
+    let recorder = Recorder {
+        name: "wasmer".to_string(),
+        messages: vec![],
+    };
+    let env = host::add_to_imports(&store, &mut import_object, recorder);
+    let instance = Instance::new(&module, &import_object)?;
+
+    let run = instance.exports.get_native_function::<(), ()>("run")?;
+    run.call()?;
+
+    assert_eq!(
+        env.borrow_mut()?.messages,
+        vec![
+            (host::Level::Info, "started".to_string()),
+            (host::Level::Warning, "wasmer".to_string()),
+        ]
+    );
+    // Strings passed to the host are borrowed from the guest
+    let freed = instance.exports.get_global("freed")?.get().unwrap_i32();
+    assert_eq!(freed, 0);
+
+    Ok(())
+}
+
+#[test]
+fn invalid_enum_from_the_guest() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+            (import "host" "log" (func $log (param i32 i32 i32)))
+            (memory (export "memory") 1)
+            (func (export "run") (call $log (i32.const 2) (i32.const 0) (i32.const 0))))"#,
+    )?;
+    let mut import_object = ImportObject::new();
+    let recorder = Recorder {
+        name: String::new(),
+        messages: vec![],
+    };
+    host::add_to_imports(&store, &mut import_object, recorder);
+    let instance = Instance::new(&module, &import_object)?;
+
+    let run = instance.exports.get_native_function::<(), ()>("run")?;
+    let error = run.call().unwrap_err();
+    assert_eq!(error.message(), "invalid discriminant 2 for `level`");
+
+    Ok(())
+}
+
+#[test]
+fn string_out_of_bounds_from_the_guest() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        &store,
+        r#"(module
+            (import "host" "log" (func $log (param i32 i32 i32)))
+            (memory (export "memory") 1)
+            (func (export "run") (call $log (i32.const 0) (i32.const 16) (i32.const -1))))"#,
+    )?;
+    let mut import_object = ImportObject::new();
+    let recorder = Recorder {
+        name: String::new(),
+        messages: vec![],
+    };
+    host::add_to_imports(&store, &mut import_object, recorder);
+    let instance = Instance::new(&module, &import_object)?;
+
+    // The length is checked against the memory before allocating the string
+    let run = instance.exports.get_native_function::<(), ()>("run")?;
+    let error = run.call().unwrap_err();
+    assert_eq!(error.message(), "memory access out of bounds");
+
+    Ok(())
+}
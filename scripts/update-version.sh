@@ -4,8 +4,8 @@
 : "${FD:=fd}"
 
 # A script to update the version of all the crates at the same time
-PREVIOUS_VERSION='2.0.0-rc1'
-NEXT_VERSION='2.0.0-rc2'
+PREVIOUS_VERSION='2.0.0-rc2'
+NEXT_VERSION='2.0.0-rc3'
 
 # quick hack
 ${FD} Cargo.toml --exec sed -i '{}' -e "s/version = \"$PREVIOUS_VERSION\"/version = \"$NEXT_VERSION\"/"
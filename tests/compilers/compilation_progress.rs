@@ -0,0 +1,101 @@
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wasmer::*;
+
+#[compiler_test(compilation_progress)]
+fn report_compilation_progress(mut config: crate::Config) -> Result<()> {
+    let reports = Arc::new(Mutex::new(vec![]));
+    config.set_progress_callback({
+        let reports = reports.clone();
+        ProgressCallback::new(move |progress| reports.lock().unwrap().push(progress))
+    });
+    let store = config.store();
+    let wat = r#"(module
+        (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+        (func (export "nop"))
+        (func (export "answer") (result i32)
+            (i32.const 42))
+    )"#;
+    Module::new(&store, wat)?;
+
+    let mut reports = reports.lock().unwrap().clone();
+    assert_eq!(reports.len(), 3);
+    reports.sort_by_key(|progress| progress.functions_compiled);
+    let total_bytes = reports[0].total_bytes;
+    for (i, progress) in reports.iter().enumerate() {
+        assert_eq!(progress.functions_compiled, i + 1);
+        assert_eq!(progress.total_functions, 3);
+        assert_eq!(progress.total_bytes, total_bytes);
+        assert!(progress.bytes_compiled <= total_bytes);
+    }
+    assert_eq!(
+        reports.iter().map(|p| p.bytes_compiled).max(),
+        Some(total_bytes)
+    );
+
+    Ok(())
+}
+
+#[compiler_test(compilation_progress)]
+fn cancel_compilation(config: crate::Config) -> Result<()> {
+    // LLVM compiles to native objects for the Dylib engine, which can't
+    // be cancelled
+    if config.compiler == crate::Compiler::LLVM && config.engine == crate::Engine::Dylib {
+        return Ok(());
+    }
+
+    let store = config.store();
+    let wat = r#"(module
+        (func (export "nop"))
+    )"#;
+
+    let token = CancellationToken::with_timeout(Duration::from_secs(0));
+    match Module::new_cancellable(&store, wat, &token) {
+        Err(CompileError::Cancelled(message)) => {
+            assert_eq!(message, "the compilation took more than 0ns")
+        }
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("the compilation should time out"),
+    }
+
+    // The cancellation is sticky, even before the compilation starts
+    let token = CancellationToken::with_timeout(Duration::from_secs(3600));
+    token.cancel();
+    assert!(matches!(
+        Module::new_cancellable(&store, wat, &token),
+        Err(CompileError::Cancelled(_))
+    ));
+
+    // And it only cancels the compilations using the token
+    Module::new(&store, wat)?;
+    Module::new_cancellable(&store, wat, &CancellationToken::new())?;
+
+    Ok(())
+}
+
+#[compiler_test(compilation_progress)]
+fn compilation_metrics(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"(module
+        (import "host" "log" (func $log (param i32)))
+        (func (export "run") (param i32)
+            (call $log (local.get 0)))
+        (func (export "nop"))
+    )"#;
+    let module = Module::new(&store, wat)?;
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    let metrics = module.compilation_metrics().unwrap();
+    assert!(metrics.code_size > 0);
+    assert_eq!(metrics.function_compile_times.len(), 2);
+    assert!(metrics.total_time() >= metrics.compilation_time);
+
+    // The metrics aren't serialized
+    let module = unsafe { Module::deserialize(&store, module.serialize()?)? };
+    assert!(module.compilation_metrics().is_none());
+
+    Ok(())
+}
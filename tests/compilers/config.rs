@@ -1,5 +1,7 @@
 use std::sync::Arc;
-use wasmer::{CompilerConfig, Engine as WasmerEngine, Features, ModuleMiddleware, Store};
+use wasmer::{
+    CompilerConfig, Engine as WasmerEngine, Features, ModuleMiddleware, ProgressCallback, Store,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Compiler {
@@ -20,7 +22,9 @@ pub struct Config {
     pub engine: Engine,
     pub features: Option<Features>,
     pub middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+    pub progress_callback: Option<ProgressCallback>,
     pub canonicalize_nans: bool,
+    pub explicit_bounds_checks: bool,
 }
 
 impl Config {
@@ -30,7 +34,9 @@ impl Config {
             engine,
             features: None,
             canonicalize_nans: false,
+            explicit_bounds_checks: false,
             middlewares: vec![],
+            progress_callback: None,
         }
     }
 
@@ -38,6 +44,10 @@ impl Config {
         self.middlewares = middlewares;
     }
 
+    pub fn set_progress_callback(&mut self, progress_callback: ProgressCallback) {
+        self.progress_callback = Some(progress_callback);
+    }
+
     pub fn set_features(&mut self, features: Features) {
         self.features = Some(features);
     }
@@ -46,6 +56,10 @@ impl Config {
         self.canonicalize_nans = canonicalize_nans;
     }
 
+    pub fn set_explicit_bounds_checks(&mut self, explicit_bounds_checks: bool) {
+        self.explicit_bounds_checks = explicit_bounds_checks;
+    }
+
     pub fn store(&self) -> Store {
         let compiler_config = self.compiler_config(self.canonicalize_nans);
         let engine = self.engine(compiler_config);
@@ -71,7 +85,8 @@ impl Config {
             }
             #[cfg(feature = "universal")]
             Engine::Universal => {
-                let mut engine = wasmer_engine_universal::Universal::new(compiler_config);
+                let mut engine = wasmer_engine_universal::Universal::new(compiler_config)
+                    .explicit_bounds_checks(self.explicit_bounds_checks);
                 if let Some(ref features) = self.features {
                     engine = engine.features(features.clone())
                 }
@@ -107,6 +122,7 @@ impl Config {
                 compiler.canonicalize_nans(canonicalize_nans);
                 compiler.enable_verifier();
                 self.add_middlewares(&mut compiler);
+                self.add_progress_callback(&mut compiler);
                 Box::new(compiler)
             }
             #[cfg(feature = "llvm")]
@@ -115,6 +131,7 @@ impl Config {
                 compiler.canonicalize_nans(canonicalize_nans);
                 compiler.enable_verifier();
                 self.add_middlewares(&mut compiler);
+                self.add_progress_callback(&mut compiler);
                 Box::new(compiler)
             }
             #[cfg(feature = "singlepass")]
@@ -123,6 +140,7 @@ impl Config {
                 compiler.canonicalize_nans(canonicalize_nans);
                 compiler.enable_verifier();
                 self.add_middlewares(&mut compiler);
+                self.add_progress_callback(&mut compiler);
                 Box::new(compiler)
             }
             #[allow(dead_code)]
@@ -140,4 +158,10 @@ impl Config {
             config.push_middleware(middleware.clone());
         }
     }
+
+    fn add_progress_callback(&self, config: &mut dyn CompilerConfig) {
+        if let Some(progress_callback) = &self.progress_callback {
+            config.set_progress_callback(progress_callback.clone());
+        }
+    }
 }
@@ -83,6 +83,44 @@ fn dynamic_function(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[compiler_test(imports)]
+#[serial_test::serial(dynamic_function)]
+fn dynamic_function_raw(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = get_module(&store)?;
+    static HITS: AtomicUsize = AtomicUsize::new(0);
+    Instance::new(
+        &module,
+        &imports! {
+            "host" => {
+                "0" => Function::new_raw(&store, FunctionType::new(vec![], vec![]), |call| {
+                    assert_eq!(HITS.fetch_add(1, SeqCst), 0);
+                    call.ret(&[])
+                }),
+                "1" => Function::new_raw(&store, FunctionType::new(vec![ValType::I32], vec![ValType::I32]), |call| {
+                    assert_eq!(call.arg(0), Value::I32(0));
+                    assert_eq!(HITS.fetch_add(1, SeqCst), 1);
+                    call.ret(&[Value::I32(1)])
+                }),
+                "2" => Function::new_raw(&store, FunctionType::new(vec![ValType::I32, ValType::I64], vec![]), |call| {
+                    assert_eq!(call.arg(0), Value::I32(2));
+                    assert_eq!(call.arg(1), Value::I64(3));
+                    assert_eq!(HITS.fetch_add(1, SeqCst), 2);
+                    call.ret(&[])
+                }),
+                "3" => Function::new_raw(&store, FunctionType::new(vec![ValType::I32, ValType::I64, ValType::I32, ValType::F32, ValType::F64], vec![]), |call| {
+                    assert_eq!(call.arg(4), Value::F64(500.0));
+                    assert_eq!(call.arg(0), Value::I32(100));
+                    assert_eq!(HITS.fetch_add(1, SeqCst), 3);
+                    call.ret(&[])
+                }),
+            },
+        },
+    )?;
+    assert_eq!(HITS.swap(0, SeqCst), 4);
+    Ok(())
+}
+
 #[compiler_test(imports)]
 fn dynamic_function_with_env(config: crate::Config) -> Result<()> {
     let store = config.store();
@@ -138,6 +176,45 @@ fn dynamic_function_with_env(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[compiler_test(imports)]
+fn dynamic_function_with_many_params(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(
+        &store,
+        r#"
+        (import "host" "sum8" (func $sum8 (param i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "host" "sum10" (func $sum10 (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+        (func (export "run") (result i32 i32)
+            (call $sum8
+                (i32.const 1) (i32.const 2) (i32.const 3) (i32.const 4)
+                (i32.const 5) (i32.const 6) (i32.const 7) (i32.const 8))
+            (call $sum10
+                (i32.const 1) (i32.const 2) (i32.const 3) (i32.const 4) (i32.const 5)
+                (i32.const 6) (i32.const 7) (i32.const 8) (i32.const 9) (i32.const 10)))
+    "#,
+    )?;
+    let sum = |values: &[Value]| {
+        Ok(vec![Value::I32(
+            values.iter().map(|value| value.unwrap_i32()).sum(),
+        )])
+    };
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "host" => {
+                "sum8" => Function::new(&store, FunctionType::new(vec![ValType::I32; 8], vec![ValType::I32]), sum),
+                "sum10" => Function::new(&store, FunctionType::new(vec![ValType::I32; 10], vec![ValType::I32]), sum),
+            },
+        },
+    )?;
+    let run = instance.exports.get_function("run")?;
+    assert_eq!(
+        run.call(&[])?.into_vec(),
+        vec![Value::I32(36), Value::I32(55)]
+    );
+    Ok(())
+}
+
 #[compiler_test(imports)]
 #[serial_test::serial(static_function)]
 fn static_function(config: crate::Config) -> Result<()> {
@@ -5,6 +5,7 @@
 #[macro_use]
 extern crate compiler_test_derive;
 
+mod compilation_progress;
 mod config;
 mod imports;
 mod metering;
@@ -13,6 +14,7 @@ mod middlewares;
 mod native_functions;
 mod serialize;
 mod traps;
+mod validation_limits;
 mod wasi;
 mod wast;
 
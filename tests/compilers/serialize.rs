@@ -74,3 +74,323 @@ fn test_deserialize(config: crate::Config) -> Result<()> {
     assert_eq!(result.to_vec(), vec![Value::I64(1500)]);
     Ok(())
 }
+
+#[compiler_test(serialize)]
+fn test_headless_store(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1)))
+        )
+    "#;
+    let serialized_bytes = Module::new(&store, wat)?.serialize()?;
+
+    let headless_store = config.headless_store();
+    let wasm = wat2wasm(wat.as_bytes())?;
+    assert!(matches!(
+        Module::new(&headless_store, &wasm),
+        Err(CompileError::Headless(_))
+    ));
+    assert!(matches!(
+        Module::validate(&headless_store, &wasm),
+        Err(CompileError::Headless(_))
+    ));
+
+    let module = unsafe { Module::deserialize(&headless_store, &serialized_bytes)? };
+    let instance = Instance::new(&module, &imports! {})?;
+    let add: NativeFunc<(i32, i32), i32> = instance.exports.get_native_function("add")?;
+    assert_eq!(add.call(1, 2)?, 3);
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_serialize_to_writer_roundtrip(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module $name
+            (func (export "answer") (result i32)
+                i32.const 42
+            )
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let mut serialized_bytes = Vec::new();
+    module.serialize_to_writer(&mut serialized_bytes)?;
+    assert_eq!(serialized_bytes, module.serialize()?);
+
+    let headless_store = config.headless_store();
+    let mut reader = std::io::Cursor::new(serialized_bytes);
+    let deserialized_module =
+        unsafe { Module::deserialize_from_reader(&headless_store, &mut reader)? };
+    assert_eq!(deserialized_module.name(), Some("name"));
+
+    let instance = Instance::new(&deserialized_module, &imports! {})?;
+    let answer: NativeFunc<(), i32> = instance.exports.get_native_function("answer")?;
+    assert_eq!(answer.call()?, 42);
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_from_file(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module $name
+            (func (export "answer") (result i32)
+                i32.const 42
+            )
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let file = tempfile::NamedTempFile::new()?;
+    module.serialize_to_file(file.path())?;
+
+    let headless_store = config.headless_store();
+    let deserialized_module =
+        unsafe { Module::deserialize_from_file(&headless_store, file.path())? };
+    assert_eq!(deserialized_module.name(), Some("name"));
+    assert_eq!(
+        deserialized_module.serialize()?,
+        std::fs::read(file.path())?
+    );
+
+    let instance = Instance::new(&deserialized_module, &imports! {})?;
+    let answer: NativeFunc<(), i32> = instance.exports.get_native_function("answer")?;
+    assert_eq!(answer.call()?, 42);
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_serialize_to_the_file_deserialized_from(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let wat = r#"
+        (module
+            (func (export "answer") (result i32)
+                i32.const 42
+            )
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    let file = tempfile::NamedTempFile::new()?;
+    module.serialize_to_file(file.path())?;
+
+    let headless_store = config.headless_store();
+    let deserialized_module =
+        unsafe { Module::deserialize_from_file(&headless_store, file.path())? };
+    // The file the module was deserialized from is replaced rather than
+    // truncated under its mapping.
+    deserialized_module.serialize_to_file(file.path())?;
+
+    let instance = Instance::new(&deserialized_module, &imports! {})?;
+    let answer: NativeFunc<(), i32> = instance.exports.get_native_function("answer")?;
+    assert_eq!(answer.call()?, 42);
+    assert_eq!(
+        deserialized_module.serialize()?,
+        std::fs::read(file.path())?
+    );
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_truncated(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+
+    let store = config.store();
+    let module = Module::new(&store, "(module (func (export \"f\")))")?;
+    let serialized = module.serialize()?;
+
+    let headless_store = config.headless_store();
+    for len in &[22, 23, 32, serialized.len() / 2, serialized.len() - 1] {
+        let result = unsafe { Module::deserialize(&headless_store, &serialized[..*len]) };
+        assert!(result.is_err(), "truncated to {} bytes", len);
+    }
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_deserialize_other_format_version(config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+
+    let store = config.store();
+    let module = Module::new(&store, "(module (func (export \"f\")))")?;
+    let mut serialized = module.serialize()?;
+    // The format version follows the magic header
+    serialized[18] = serialized[18].wrapping_add(1);
+
+    let headless_store = config.headless_store();
+    let result = unsafe { Module::deserialize(&headless_store, &serialized) };
+    assert!(
+        matches!(result, Err(DeserializeError::Incompatible(_))),
+        "{:?}",
+        result.err()
+    );
+    Ok(())
+}
+
+#[compiler_test(serialize)]
+fn test_serialize_is_deterministic(config: crate::Config) -> Result<()> {
+    // The Dylib engine serializes a shared object produced by the
+    // system linker, which doesn't guarantee reproducible builds.
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+
+    let wat = r#"
+        (module $name
+            (import "host" "log" (func $log (param i32)))
+            (memory (export "memory") 1)
+            (table 4 funcref)
+            (elem func $a $b $c)
+            (elem func $c $b)
+            (data "passive")
+            (data "data")
+            (data (i32.const 16) "active")
+            (func $a (export "a") (param i32) local.get 0 call $log)
+            (func $b (export "b") (result i32) i32.const 1)
+            (func $c (export "c") (result i32)
+                i32.const 0
+                i32.const 0
+                i32.const 4
+                memory.init 0
+                i32.const 2)
+        )
+    "#;
+    let wasm = wat2wasm(wat.as_bytes())?;
+
+    // Each compilation uses its own engine, with its own hash seeds.
+    let serialized_bytes = Module::new(&config.store(), &wasm)?.serialize()?;
+    for _ in 0..4 {
+        let module = Module::new(&config.store(), &wasm)?;
+        assert!(module.serialize()? == serialized_bytes);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "compression")]
+#[compiler_test(serialize)]
+fn test_deserialize_compressed(config: crate::Config) -> Result<()> {
+    let wat = r#"
+        (module $name
+            (memory (export "memory") 1)
+            (data (i32.const 0) "hello")
+            (func (export "double") (param i32) (result i32)
+                local.get 0
+                i32.const 2
+                i32.mul)
+        )
+    "#;
+    let universal = || {
+        wasmer_engine_universal::Universal::new(config.compiler_config(config.canonicalize_nans))
+    };
+    let compressed_store = Store::new(&universal().compress_artifacts(true).engine());
+    let compressed_bytes = Module::new(&compressed_store, wat)?.serialize()?;
+    let uncompressed_store = Store::new(&universal().engine());
+    let uncompressed_bytes = Module::new(&uncompressed_store, wat)?.serialize()?;
+    assert!(compressed_bytes.len() < uncompressed_bytes.len());
+
+    // Compressed artifacts are recognized without enabling the compression
+    let headless_store = Store::new(&wasmer_engine_universal::Universal::headless().engine());
+    let deserialized_module = unsafe { Module::deserialize(&headless_store, &compressed_bytes)? };
+    assert_eq!(deserialized_module.name(), Some("name"));
+    let instance = Instance::new(&deserialized_module, &imports! {})?;
+    let double = instance.exports.get_native_function::<i32, i32>("double")?;
+    assert_eq!(double.call(21)?, 42);
+    Ok(())
+}
+
+#[cfg(feature = "universal")]
+#[compiler_test(serialize)]
+fn test_deserialize_stripped(config: crate::Config) -> Result<()> {
+    let wat = r#"
+        (module $name
+            (func $fail (export "fail")
+                i32.const 0
+                drop
+                unreachable)
+        )
+    "#;
+    let universal = || {
+        wasmer_engine_universal::Universal::new(config.compiler_config(config.canonicalize_nans))
+    };
+    let stripped_store = Store::new(&universal().strip_debug_info(true).engine());
+    let stripped_bytes = Module::new(&stripped_store, wat)?.serialize()?;
+    let store = Store::new(&universal().engine());
+    let bytes = Module::new(&store, wat)?.serialize()?;
+    assert!(stripped_bytes.len() < bytes.len());
+
+    let headless_store = Store::new(&wasmer_engine_universal::Universal::headless().engine());
+    let module = unsafe { Module::deserialize(&headless_store, &stripped_bytes)? };
+    assert_eq!(module.name(), Some("name"));
+    assert_eq!(module.function_name(0), None);
+
+    // The traps still tell why the code trapped, but their frames only
+    // point to the functions
+    let instance = Instance::new(&module, &imports! {})?;
+    let error = instance
+        .exports
+        .get_function("fail")?
+        .call(&[])
+        .unwrap_err();
+    let frame = &error.trace()[0];
+    assert_eq!(frame.func_index(), 0);
+    assert_eq!(frame.function_name(), None);
+    assert_eq!(frame.func_offset(), 0);
+    assert!(error.message().contains("unreachable"));
+    Ok(())
+}
+
+#[cfg(feature = "universal")]
+#[compiler_test(serialize)]
+fn test_serialize_stripped(config: crate::Config) -> Result<()> {
+    let wat = r#"
+        (module $name
+            (func $fail (export "fail")
+                i32.const 0
+                drop
+                unreachable)
+        )
+    "#;
+    let store = Store::new(
+        &wasmer_engine_universal::Universal::new(config.compiler_config(config.canonicalize_nans))
+            .engine(),
+    );
+    let module = Module::new(&store, wat)?;
+    let bytes = module.serialize()?;
+    let stripped_bytes = module.serialize_stripped()?;
+    assert!(stripped_bytes.len() < bytes.len());
+
+    // The module itself keeps its debug info
+    assert_eq!(module.serialize()?, bytes);
+    assert_eq!(module.function_name(0), Some("fail"));
+
+    let headless_store = Store::new(&wasmer_engine_universal::Universal::headless().engine());
+    let stripped_module = unsafe { Module::deserialize(&headless_store, &stripped_bytes)? };
+    assert_eq!(stripped_module.name(), Some("name"));
+    assert_eq!(stripped_module.function_name(0), None);
+
+    // The modules mapped from a file, which don't keep their code, can
+    // be stripped too
+    let file = tempfile::NamedTempFile::new()?;
+    module.serialize_to_file(file.path())?;
+    let mapped_module = unsafe { Module::deserialize_from_file(&headless_store, file.path())? };
+    let restripped_bytes = mapped_module.serialize_stripped()?;
+    let restripped_module = unsafe { Module::deserialize(&headless_store, &restripped_bytes)? };
+    assert_eq!(restripped_module.function_name(0), None);
+
+    let instance = Instance::new(&stripped_module, &imports! {})?;
+    let error = instance
+        .exports
+        .get_function("fail")?
+        .call(&[])
+        .unwrap_err();
+    assert_eq!(error.trace()[0].function_name(), None);
+    assert!(error.message().contains("unreachable"));
+    Ok(())
+}
@@ -1,5 +1,4 @@
 use anyhow::Result;
-use std::panic::{self, AssertUnwindSafe};
 use wasmer::*;
 
 #[compiler_test(traps)]
@@ -293,23 +292,27 @@ fn rust_panic_import(config: crate::Config) -> Result<()> {
         },
     )?;
     let func = instance.exports.get_function("foo")?.clone();
-    let err = panic::catch_unwind(AssertUnwindSafe(|| {
-        drop(func.call(&[]));
-    }))
-    .unwrap_err();
-    assert_eq!(err.downcast_ref::<&'static str>(), Some(&"this is a panic"));
+    let err = func.call(&[]).unwrap_err();
+    assert!(err.is_panic());
+    assert_eq!(err.message(), "host function panicked: this is a panic");
+    let payload = err.take_panic_payload().unwrap();
+    assert_eq!(
+        payload.downcast_ref::<&'static str>(),
+        Some(&"this is a panic")
+    );
+    assert!(err.take_panic_payload().is_none());
 
-    // TODO: Reenable this (disabled as it was not working with llvm/singlepass)
-    // It doesn't work either with cranelift and `--test-threads=1`.
-    // let func = instance.exports.get_function("bar")?.clone();
-    // let err = panic::catch_unwind(AssertUnwindSafe(|| {
-    //     drop(func.call(&[]));
-    // }))
-    // .unwrap_err();
-    // assert_eq!(
-    //     err.downcast_ref::<&'static str>(),
-    //     Some(&"this is another panic")
-    // );
+    let func = instance.exports.get_function("bar")?.clone();
+    let err = func.call(&[]).unwrap_err();
+    assert!(err.is_panic());
+    assert_eq!(
+        err.message(),
+        "host function panicked: this is another panic"
+    );
+
+    // The instance can still be used after a panic
+    let err = func.call(&[]).unwrap_err();
+    assert!(err.is_panic());
     Ok(())
 }
 
@@ -326,35 +329,45 @@ fn rust_panic_start_function(config: crate::Config) -> Result<()> {
     let module = Module::new(&store, &binary)?;
     let sig = FunctionType::new(vec![], vec![]);
     let func = Function::new(&store, &sig, |_| panic!("this is a panic"));
-    let err = panic::catch_unwind(AssertUnwindSafe(|| {
-        drop(Instance::new(
-            &module,
-            &imports! {
-                "" => {
-                    "" => func
-                }
-            },
-        ));
-    }))
-    .unwrap_err();
-    assert_eq!(err.downcast_ref::<&'static str>(), Some(&"this is a panic"));
+    let err = Instance::new(
+        &module,
+        &imports! {
+            "" => {
+                "" => func
+            }
+        },
+    )
+    .err()
+    .unwrap();
+    match err {
+        InstantiationError::Start(err) => {
+            assert!(err.is_panic());
+            assert_eq!(err.message(), "host function panicked: this is a panic");
+        }
+        _ => panic!("It should be a start error"),
+    }
 
     let func = Function::new_native(&store, || panic!("this is another panic"));
-    let err = panic::catch_unwind(AssertUnwindSafe(|| {
-        drop(Instance::new(
-            &module,
-            &imports! {
-                "" => {
-                    "" => func
-                }
-            },
-        ));
-    }))
-    .unwrap_err();
-    assert_eq!(
-        err.downcast_ref::<&'static str>(),
-        Some(&"this is another panic")
-    );
+    let err = Instance::new(
+        &module,
+        &imports! {
+            "" => {
+                "" => func
+            }
+        },
+    )
+    .err()
+    .unwrap();
+    match err {
+        InstantiationError::Start(err) => {
+            assert!(err.is_panic());
+            assert_eq!(
+                err.message(),
+                "host function panicked: this is another panic"
+            );
+        }
+        _ => panic!("It should be a start error"),
+    }
     Ok(())
 }
 
@@ -471,3 +484,76 @@ fn present_after_module_drop(config: crate::Config) -> Result<()> {
         // assert_eq!(t.trace()[0].func_index(), 0);
     }
 }
+
+#[compiler_test(traps)]
+fn explicit_bounds_checks(mut config: crate::Config) -> Result<()> {
+    if config.engine != crate::Engine::Universal {
+        return Ok(());
+    }
+    config.set_explicit_bounds_checks(true);
+    let store = config.store();
+    let wat = r#"
+        (module
+            (memory 1 1)
+            (func (export "load") (param i32) (result i32)
+                local.get 0
+                i32.load)
+            (func (export "div") (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.div_u)
+        )
+    "#;
+
+    let module = Module::new(&store, wat)?;
+    for style in module.artifact().memory_styles().values() {
+        assert_eq!(
+            *style,
+            vm::MemoryStyle::Dynamic {
+                offset_guard_size: 0
+            }
+        );
+    }
+
+    let instance = Instance::new(&module, &imports! {})?;
+    let load: NativeFunc<i32, i32> = instance.exports.get_native_function("load")?;
+    assert_eq!(load.call(0)?, 0);
+    let e = load.call(0x1_0000).unwrap_err();
+    assert!(e.message().contains("out of bounds memory access"));
+
+    let div: NativeFunc<(i32, i32), i32> = instance.exports.get_native_function("div")?;
+    assert_eq!(div.call(6, 3)?, 2);
+    let e = div.call(6, 0).unwrap_err();
+    assert!(e.message().contains("integer divide by zero"));
+
+    Ok(())
+}
+
+#[compiler_test(traps)]
+fn downcast_user_error(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let module = Module::new(
+        &store,
+        r#"(module (func $raise (import "" "raise")) (func (export "run") (call $raise)))"#,
+    )?;
+    let raise = Function::new(&store, &FunctionType::new(vec![], vec![]), |_| {
+        RuntimeError::raise(Box::new(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "user error",
+        )))
+    });
+    let instance = Instance::new(&module, &imports! { "" => { "raise" => raise } })?;
+    let run = instance.exports.get_function("run")?;
+
+    let e = run.call(&[]).unwrap_err();
+    let shared = e.clone();
+    assert_eq!(
+        e.downcast_ref::<std::io::Error>().unwrap().to_string(),
+        "user error"
+    );
+    assert!(e.downcast_ref::<std::fmt::Error>().is_none());
+    // `downcast` can't take the error out of a shared one
+    assert!(shared.downcast::<std::io::Error>().is_err());
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use anyhow::Result;
+use wasmer::*;
+
+fn store_with_limits(mut config: crate::Config, limits: ValidationLimits) -> Store {
+    let mut features = Features::default();
+    features.limits(limits);
+    config.set_features(features);
+    config.store()
+}
+
+fn assert_limit_exceeded(result: Result<Module, CompileError>, message: &str) {
+    match result {
+        Err(CompileError::LimitExceeded(e)) => assert_eq!(e, message),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("the module should exceed the limits"),
+    }
+}
+
+#[compiler_test(validation_limits)]
+fn limit_functions(config: crate::Config) -> Result<()> {
+    let store = store_with_limits(
+        config,
+        ValidationLimits {
+            max_functions: Some(2),
+            ..Default::default()
+        },
+    );
+    Module::new(&store, r#"(module (import "env" "f" (func)) (func))"#)?;
+    assert_limit_exceeded(
+        Module::new(
+            &store,
+            r#"(module (import "env" "f" (func)) (func) (func))"#,
+        ),
+        "the module has 3 functions, but the maximum is 2",
+    );
+    Ok(())
+}
+
+#[compiler_test(validation_limits)]
+fn limit_locals(config: crate::Config) -> Result<()> {
+    let store = store_with_limits(
+        config,
+        ValidationLimits {
+            max_locals_per_function: Some(3),
+            ..Default::default()
+        },
+    );
+    // The parameters don't count
+    Module::new(
+        &store,
+        r#"(module (func (param i32 i32) (local i32 i64 f32)))"#,
+    )?;
+    assert_limit_exceeded(
+        Module::new(
+            &store,
+            r#"(module (func) (func (local i32 i64) (local f32 f64)))"#,
+        ),
+        "a function declares 4 locals, but the maximum is 3",
+    );
+    Ok(())
+}
+
+#[compiler_test(validation_limits)]
+fn limit_tables(config: crate::Config) -> Result<()> {
+    let store = store_with_limits(
+        config,
+        ValidationLimits {
+            max_table_size: Some(10),
+            ..Default::default()
+        },
+    );
+    Module::new(&store, r#"(module (table 10 funcref))"#)?;
+    assert_limit_exceeded(
+        Module::new(&store, r#"(module (table 11 funcref))"#),
+        "a table has 11 elements, but the maximum is 10",
+    );
+    assert_limit_exceeded(
+        Module::new(&store, r#"(module (import "env" "t" (table 20 funcref)))"#),
+        "a table has 20 elements, but the maximum is 10",
+    );
+    Ok(())
+}
+
+#[compiler_test(validation_limits)]
+fn limit_module_size(config: crate::Config) -> Result<()> {
+    let store = store_with_limits(
+        config,
+        ValidationLimits {
+            max_module_size: Some(8),
+            ..Default::default()
+        },
+    );
+    Module::new(&store, "(module)")?;
+    assert_limit_exceeded(
+        Module::new(&store, "(module (func))"),
+        "the module is 24 bytes, but the maximum is 8 bytes",
+    );
+    Ok(())
+}
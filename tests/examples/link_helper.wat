@@ -0,0 +1,5 @@
+(module
+  (func $double (export "double") (param $x i32) (result i32)
+    local.get $x
+    i32.const 2
+    i32.mul))
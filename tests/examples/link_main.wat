@@ -0,0 +1,6 @@
+(module
+  (import "link_helper" "double" (func $double (param i32) (result i32)))
+  (func $quadruple (export "quadruple") (param $x i32) (result i32)
+    local.get $x
+    call $double
+    call $double))
@@ -0,0 +1,48 @@
+//! CLI tests for the create-obj subcommand.
+
+use anyhow::{bail, Context};
+use std::path::PathBuf;
+use std::process::Command;
+use wasmer_integration_tests_cli::*;
+
+fn create_obj_test_wasm_path() -> String {
+    format!("{}/{}", C_ASSET_PATH, "qjs.wasm")
+}
+
+#[test]
+fn create_obj_generates_object_and_header() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir().context("Making a temp dir")?;
+    let operating_dir: PathBuf = temp_dir.path().to_owned();
+
+    let wasm_path = operating_dir.join(create_obj_test_wasm_path());
+    #[cfg(not(windows))]
+    let object_path = operating_dir.join("wasm.o");
+    #[cfg(windows)]
+    let object_path = operating_dir.join("wasm.obj");
+
+    let output = Command::new(get_wasmer_path())
+        .current_dir(&operating_dir)
+        .arg("create-obj")
+        .arg(&wasm_path.canonicalize()?)
+        .arg(Compiler::Cranelift.to_flag())
+        .arg("-o")
+        .arg(&object_path)
+        .output()?;
+
+    if !output.status.success() {
+        bail!(
+            "wasmer create-obj failed with: stdout: {}\n\nstderr: {}",
+            std::str::from_utf8(&output.stdout)
+                .expect("stdout is not utf8! need to handle arbitrary bytes"),
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+
+    assert!(object_path.exists());
+    // The header is written next to the object file by default.
+    let header = std::fs::read_to_string(object_path.with_extension("h"))?;
+    assert!(header.contains("wasmer_staticlib_engine_new"));
+
+    Ok(())
+}
@@ -57,3 +57,58 @@ fn run_no_imports_wasm_works() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn run_invoke_parses_typed_arguments() -> anyhow::Result<()> {
+    let output = Command::new(WASMER_PATH)
+        .arg("run")
+        .arg(format!("{}/{}", ASSET_PATH, "add.wat"))
+        .arg("--invoke")
+        .arg("add")
+        .arg("-5")
+        .arg("0x10")
+        .output()?;
+
+    if !output.status.success() {
+        bail!(
+            "invoking failed with: stdout: {}\n\nstderr: {}",
+            std::str::from_utf8(&output.stdout)
+                .expect("stdout is not utf8! need to handle arbitrary bytes"),
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+
+    let stdout_output = std::str::from_utf8(&output.stdout).unwrap();
+    assert_eq!(stdout_output, "11:i64\n");
+
+    Ok(())
+}
+
+#[test]
+fn run_links_modules() -> anyhow::Result<()> {
+    let output = Command::new(WASMER_PATH)
+        .arg("run")
+        .arg(format!("{}/{}", ASSET_PATH, "link_main.wat"))
+        .arg("--link")
+        .arg(format!("{}/{}", ASSET_PATH, "link_helper.wat"))
+        .arg("--invoke")
+        .arg("quadruple")
+        .arg("5")
+        .output()?;
+
+    if !output.status.success() {
+        bail!(
+            "linking failed with: stdout: {}\n\nstderr: {}",
+            std::str::from_utf8(&output.stdout)
+                .expect("stdout is not utf8! need to handle arbitrary bytes"),
+            std::str::from_utf8(&output.stderr)
+                .expect("stderr is not utf8! need to handle arbitrary bytes")
+        );
+    }
+
+    let stdout_output = std::str::from_utf8(&output.stdout).unwrap();
+    assert_eq!(stdout_output, "20:i32\n");
+
+    Ok(())
+}
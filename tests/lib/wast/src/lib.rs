@@ -1,4 +1,40 @@
 //! Implementation of the WAST text format for wasmer.
+//!
+//! This crate runs the `.wast` scripts of the [WebAssembly spec
+//! testsuite] against any [`Store`](wasmer::Store), so compilers, engines
+//! and middlewares can be checked with it in their own tests.
+//!
+//! A [`Wast`] runs scripts with the modules it compiles in its store, and
+//! either fails on the first failing directive, or returns a [`WastReport`]
+//! with the outcome of each directive:
+//!
+//! ```
+//! use wasmer::Store;
+//! use wasmer_wast::{DirectiveOutcome, Wast};
+//!
+//! fn check_compiler(store: Store) -> anyhow::Result<()> {
+//!     let mut wast = Wast::new_with_spectest(store);
+//!     wast.fail_fast = false;
+//!
+//!     let report = wast.run_buffer_with_report(
+//!         "answer.wast",
+//!         br#"
+//!         (module (func (export "answer") (result i32) i32.const 42))
+//!         (assert_return (invoke "answer") (i32.const 42))
+//!         "#,
+//!     )?;
+//!     for directive in &report.directives {
+//!         if let DirectiveOutcome::Failed(message) = &directive.outcome {
+//!             eprintln!("{} at line {}: {}", directive.kind, directive.line, message);
+//!         }
+//!     }
+//!     assert!(report.is_success());
+//!     Ok(())
+//! }
+//! # let _ = check_compiler;
+//! ```
+//!
+//! [WebAssembly spec testsuite]: https://github.com/WebAssembly/testsuite
 
 #![deny(missing_docs, trivial_numeric_casts, unused_extern_crates)]
 #![warn(unused_import_braces)]
@@ -19,11 +55,13 @@
 )]
 
 mod error;
+mod report;
 mod spectest;
 mod wasi_wast;
 mod wast;
 
 pub use crate::error::{DirectiveError, DirectiveErrors};
+pub use crate::report::{DirectiveOutcome, DirectiveResult, WastReport};
 pub use crate::spectest::spectest_importobject;
 pub use crate::wasi_wast::WasiTest;
 pub use crate::wast::Wast;
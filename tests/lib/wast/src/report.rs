@@ -0,0 +1,140 @@
+use crate::error::{DirectiveError, DirectiveErrors};
+
+/// The outcome of running a directive of a wast script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectiveOutcome {
+    /// The directive ran, and its assertion held, if any.
+    Passed,
+    /// The directive failed, with the message describing why.
+    Failed(String),
+    /// The directive wasn't run, for the reason given.
+    Skipped(String),
+}
+
+/// A directive of a wast script, with the outcome of running it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectiveResult {
+    /// The kind of the directive, like `"module"` or `"assert_return"`.
+    pub kind: &'static str,
+    /// The line where the directive is defined, starting at 1.
+    pub line: usize,
+    /// The column where the directive is defined.
+    pub col: usize,
+    /// The outcome of running the directive.
+    pub outcome: DirectiveOutcome,
+}
+
+/// The structured results of running a wast script.
+///
+/// It holds the directives run in order, up to the first failure when
+/// [`Wast::fail_fast`] is set.
+///
+/// [`Wast::fail_fast`]: crate::Wast::fail_fast
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WastReport {
+    /// The name of the script.
+    pub filename: String,
+    /// The directives of the script which were run, in order.
+    pub directives: Vec<DirectiveResult>,
+}
+
+impl WastReport {
+    fn count(&self, predicate: impl Fn(&DirectiveOutcome) -> bool) -> usize {
+        self.directives
+            .iter()
+            .filter(|directive| predicate(&directive.outcome))
+            .count()
+    }
+
+    /// The number of directives which passed.
+    pub fn passed(&self) -> usize {
+        self.count(|outcome| *outcome == DirectiveOutcome::Passed)
+    }
+
+    /// The number of directives which failed.
+    pub fn failed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, DirectiveOutcome::Failed(_)))
+    }
+
+    /// The number of directives which were skipped.
+    pub fn skipped(&self) -> usize {
+        self.count(|outcome| matches!(outcome, DirectiveOutcome::Skipped(_)))
+    }
+
+    /// Whether no directive failed.
+    pub fn is_success(&self) -> bool {
+        self.failed() == 0
+    }
+
+    /// Returns the errors of the directives which failed, if any.
+    pub fn into_result(self) -> Result<(), DirectiveErrors> {
+        let errors = self
+            .directives
+            .into_iter()
+            .filter_map(|directive| match directive.outcome {
+                DirectiveOutcome::Failed(message) => Some(DirectiveError {
+                    line: directive.line,
+                    col: directive.col,
+                    message,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(DirectiveErrors {
+                filename: self.filename,
+                errors,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn directive(line: usize, outcome: DirectiveOutcome) -> DirectiveResult {
+        DirectiveResult {
+            kind: "assert_return",
+            line,
+            col: 1,
+            outcome,
+        }
+    }
+
+    #[test]
+    fn report_counts_and_errors() {
+        let report = WastReport {
+            filename: "test.wast".to_string(),
+            directives: vec![
+                directive(1, DirectiveOutcome::Passed),
+                directive(2, DirectiveOutcome::Skipped("quoted module".to_string())),
+                directive(3, DirectiveOutcome::Failed("expected 1, got 2".to_string())),
+                directive(4, DirectiveOutcome::Passed),
+            ],
+        };
+        assert_eq!(
+            (report.passed(), report.failed(), report.skipped()),
+            (2, 1, 1)
+        );
+        assert!(!report.is_success());
+
+        let errors = report.into_result().unwrap_err();
+        assert_eq!(errors.filename, "test.wast");
+        assert_eq!(errors.errors.len(), 1);
+        assert_eq!(errors.errors[0].line, 3);
+        assert_eq!(errors.errors[0].message, "expected 1, got 2");
+    }
+
+    #[test]
+    fn successful_report() {
+        let report = WastReport {
+            filename: "test.wast".to_string(),
+            directives: vec![directive(1, DirectiveOutcome::Passed)],
+        };
+        assert!(report.is_success());
+        assert!(report.into_result().is_ok());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::error::{DirectiveError, DirectiveErrors};
+use crate::report::{DirectiveOutcome, DirectiveResult, WastReport};
 use crate::spectest::spectest_importobject;
 use anyhow::{anyhow, bail, Result};
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -149,7 +149,7 @@ impl Wast {
         bail!("expected '{}', got '{}'", expected, actual)
     }
 
-    fn run_directive(&mut self, directive: wast::WastDirective) -> Result<()> {
+    fn run_directive(&mut self, directive: wast::WastDirective) -> Result<DirectiveOutcome> {
         use wast::WastDirective::*;
 
         match directive {
@@ -180,20 +180,26 @@ impl Wast {
                 exec,
                 message,
             } => {
-                if !self.disable_assert_trap_exhaustion {
-                    let result = self.perform_execute(exec);
-                    self.assert_trap(result, message)?;
+                if self.disable_assert_trap_exhaustion {
+                    return Ok(DirectiveOutcome::Skipped(
+                        "`assert_trap` is disabled".to_string(),
+                    ));
                 }
+                let result = self.perform_execute(exec);
+                self.assert_trap(result, message)?;
             }
             AssertExhaustion {
                 span: _,
                 call,
                 message,
             } => {
-                if !self.disable_assert_trap_exhaustion {
-                    let result = self.perform_invoke(call);
-                    self.assert_trap(result, message)?;
+                if self.disable_assert_trap_exhaustion {
+                    return Ok(DirectiveOutcome::Skipped(
+                        "`assert_exhaustion` is disabled".to_string(),
+                    ));
                 }
+                let result = self.perform_invoke(call);
+                self.assert_trap(result, message)?;
             }
             AssertInvalid {
                 span: _,
@@ -215,7 +221,9 @@ impl Wast {
                 }
             }
             QuoteModule { .. } => {
-                // Do nothing
+                return Ok(DirectiveOutcome::Skipped(
+                    "quoted modules are not supported".to_string(),
+                ));
             }
             AssertMalformed {
                 module,
@@ -226,7 +234,11 @@ impl Wast {
                     wast::QuoteModule::Module(m) => m,
                     // This is a `*.wat` parser test which we're not
                     // interested in.
-                    wast::QuoteModule::Quote(_) => return Ok(()),
+                    wast::QuoteModule::Quote(_) => {
+                        return Ok(DirectiveOutcome::Skipped(
+                            "quoted modules are not supported".to_string(),
+                        ))
+                    }
                 };
                 let bytes = module.encode()?;
                 if self.module(None, &bytes).is_ok() {
@@ -254,11 +266,15 @@ impl Wast {
             }
         }
 
-        Ok(())
+        Ok(DirectiveOutcome::Passed)
     }
 
-    /// Run a wast script from a byte buffer.
-    pub fn run_buffer(&mut self, filename: &str, wast: &[u8]) -> Result<()> {
+    /// Run a wast script from a byte buffer, and return the outcome of each
+    /// of its directives.
+    ///
+    /// Fails only when the script can't be parsed: the failures of its
+    /// directives are in the [`WastReport`].
+    pub fn run_buffer_with_report(&mut self, filename: &str, wast: &[u8]) -> Result<WastReport> {
         let wast = str::from_utf8(wast)?;
 
         let adjust_wast = |mut err: wast::Error| {
@@ -269,45 +285,69 @@ impl Wast {
 
         let buf = wast::parser::ParseBuffer::new(wast).map_err(adjust_wast)?;
         let ast = wast::parser::parse::<wast::Wast>(&buf).map_err(adjust_wast)?;
-        let mut errors = Vec::with_capacity(ast.directives.len());
+        let mut directives = Vec::with_capacity(ast.directives.len());
         for directive in ast.directives {
-            let sp = directive.span();
-            if let Err(e) = self.run_directive(directive) {
-                let message = format!("{}", e);
-                // If depends on an instance that doesn't exist
-                if message.contains("no previous instance found") {
-                    continue;
-                }
-                // We don't compute it, comes from instantiating an instance
-                // that we expected to fail.
-                if self.current.is_none() && self.current_is_allowed_failure {
-                    continue;
-                }
-                let (line, col) = sp.linecol_in(wast);
-                errors.push(DirectiveError {
-                    line: line + 1,
-                    col,
-                    message,
-                });
-                if self.fail_fast {
-                    break;
+            let kind = directive_kind(&directive);
+            let (line, col) = directive.span().linecol_in(wast);
+            let outcome = match self.run_directive(directive) {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    let message = format!("{}", e);
+                    if message.contains("no previous instance found") {
+                        // It depends on an instance that doesn't exist
+                        DirectiveOutcome::Skipped(message)
+                    } else if self.current.is_none() && self.current_is_allowed_failure {
+                        // It comes from instantiating an instance that we
+                        // expected to fail.
+                        DirectiveOutcome::Skipped(format!("allowed failure: {}", message))
+                    } else {
+                        DirectiveOutcome::Failed(message)
+                    }
                 }
+            };
+            let failed = matches!(outcome, DirectiveOutcome::Failed(_));
+            directives.push(DirectiveResult {
+                kind,
+                line: line + 1,
+                col,
+                outcome,
+            });
+            if failed && self.fail_fast {
+                break;
             }
         }
-        if !errors.is_empty() {
-            return Err(DirectiveErrors {
-                filename: filename.to_string(),
-                errors,
-            }
-            .into());
-        }
-        Ok(())
+        Ok(WastReport {
+            filename: filename.to_string(),
+            directives,
+        })
+    }
+
+    /// Run a wast script from a file, and return the outcome of each of its
+    /// directives.
+    pub fn run_file_with_report(&mut self, path: &Path) -> Result<WastReport> {
+        let bytes = std::fs::read(path)?;
+        self.run_buffer_with_report(&path.to_string_lossy(), &bytes)
+    }
+
+    /// Run a wast script from a byte buffer.
+    ///
+    /// Fails with [`DirectiveErrors`](crate::DirectiveErrors) when some of
+    /// its directives fail.
+    pub fn run_buffer(&mut self, filename: &str, wast: &[u8]) -> Result<()> {
+        Ok(self.run_buffer_with_report(filename, wast)?.into_result()?)
     }
 
     /// Run a wast script from a file.
+    ///
+    /// Fails with [`DirectiveErrors`](crate::DirectiveErrors) when some of
+    /// its directives fail.
     pub fn run_file(&mut self, path: &Path) -> Result<()> {
-        let bytes = std::fs::read(path)?;
-        self.run_buffer(path.to_str().unwrap(), &bytes)
+        Ok(self.run_file_with_report(path)?.into_result()?)
+    }
+
+    /// Returns the store the modules of the scripts are compiled with.
+    pub fn store(&self) -> &Store {
+        &self.store
     }
 }
 
@@ -490,6 +530,24 @@ impl Wast {
     }
 }
 
+/// The name of the kind of `directive`, as written in wast scripts.
+fn directive_kind(directive: &wast::WastDirective) -> &'static str {
+    use wast::WastDirective::*;
+
+    match directive {
+        Module(_) => "module",
+        QuoteModule { .. } => "module quote",
+        AssertMalformed { .. } => "assert_malformed",
+        AssertInvalid { .. } => "assert_invalid",
+        Register { .. } => "register",
+        Invoke(_) => "invoke",
+        AssertTrap { .. } => "assert_trap",
+        AssertReturn { .. } => "assert_return",
+        AssertExhaustion { .. } => "assert_exhaustion",
+        AssertUnlinkable { .. } => "assert_unlinkable",
+    }
+}
+
 fn extract_lane_as_i8(bytes: u128, lane: usize) -> i8 {
     (bytes >> (lane * 8)) as i8
 }